@@ -1,5 +1,5 @@
 use Result;
-use collector::Collector;
+use collector::{Collector, MatchInfo};
 use SegmentLocalId;
 use SegmentReader;
 use DocId;
@@ -59,6 +59,15 @@ impl<Left: Collector, Right: Collector> Collector for ChainedCollector<Left, Rig
     fn requires_scoring(&self) -> bool {
         self.left.requires_scoring() || self.right.requires_scoring()
     }
+
+    fn requires_match_info(&self) -> bool {
+        self.left.requires_match_info() || self.right.requires_match_info()
+    }
+
+    fn collect_with_match(&mut self, doc: DocId, score: Score, match_info: &MatchInfo) {
+        self.left.collect_with_match(doc, score, match_info);
+        self.right.collect_with_match(doc, score, match_info);
+    }
 }
 
 /// Creates a `ChainedCollector`
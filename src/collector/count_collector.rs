@@ -1,4 +1,4 @@
-use super::Collector;
+use super::{Collector, FruitCollector};
 use DocId;
 use Score;
 use Result;
@@ -7,7 +7,7 @@ use SegmentLocalId;
 
 /// `CountCollector` collector only counts how many
 /// documents match the query.
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct CountCollector {
     count: usize,
 }
@@ -34,6 +34,18 @@ impl Collector for CountCollector {
     }
 }
 
+impl FruitCollector for CountCollector {
+    type Fruit = usize;
+
+    fn harvest(self) -> usize {
+        self.count
+    }
+
+    fn merge_fruits(&self, counts: Vec<usize>) -> usize {
+        counts.into_iter().sum()
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
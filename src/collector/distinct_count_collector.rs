@@ -0,0 +1,357 @@
+use super::weighted_sample_collector::mix;
+use super::{Collector, FruitCollector};
+use fastfield::FastFieldReader;
+use schema::Field;
+use std::collections::BTreeMap;
+use DocId;
+use Result;
+use Score;
+use SegmentLocalId;
+use SegmentReader;
+
+/// Default precision of the sketches of the `DistinctCountCollector`.
+///
+/// Each sketch takes `2^precision` bytes, and the standard error
+/// of its estimates is about `1.04 / sqrt(2^precision)`, that is 3.25%.
+pub const DEFAULT_DISTINCT_COUNT_PRECISION: u8 = 10;
+
+const MIN_PRECISION: u8 = 4;
+const MAX_PRECISION: u8 = 16;
+
+/// HyperLogLog sketch, estimating the number of distinct values inserted.
+///
+/// The hash of a value selects one of the `2^precision` registers,
+/// which keeps the highest rank of the first set bit among
+/// the remaining bits of the hashes it selected.
+#[derive(Clone, Debug)]
+struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new(precision: u8) -> HyperLogLog {
+        HyperLogLog {
+            precision,
+            registers: vec![0u8; 1 << precision],
+        }
+    }
+
+    fn insert(&mut self, val: u64) {
+        let hash = mix(val);
+        let register = (hash >> (64 - self.precision)) as usize;
+        // the sentinel bit bounds the rank when the remaining bits are all 0.
+        let remaining_bits = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = remaining_bits.leading_zeros() as u8 + 1;
+        if self.registers[register] < rank {
+            self.registers[register] = rank;
+        }
+    }
+
+    fn merge(&mut self, other: &HyperLogLog) {
+        for (register, &other_register) in self.registers.iter_mut().zip(&other.registers) {
+            if *register < other_register {
+                *register = other_register;
+            }
+        }
+    }
+
+    fn estimate(&self) -> u64 {
+        let num_registers = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / num_registers),
+        };
+        let mut sum = 0f64;
+        let mut num_zeros = 0usize;
+        for &register in &self.registers {
+            sum += 1.0 / (1u64 << register) as f64;
+            if register == 0 {
+                num_zeros += 1;
+            }
+        }
+        let estimate = alpha * num_registers * num_registers / sum;
+        // linear counting is more accurate for small cardinalities.
+        if estimate <= 2.5 * num_registers && num_zeros > 0 {
+            (num_registers * (num_registers / num_zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// Approximate distinct counts per bucket, as returned
+/// by the `DistinctCountCollector`.
+///
+/// Buckets are identified by the lowest value of the bucket field they hold.
+#[derive(Clone, Debug)]
+pub struct DistinctCounts {
+    buckets: BTreeMap<u64, HyperLogLog>,
+}
+
+impl DistinctCounts {
+    /// Returns the approximate number of distinct values in `bucket`.
+    ///
+    /// Returns 0 if no document fell in the bucket.
+    pub fn get(&self, bucket: u64) -> u64 {
+        self.buckets
+            .get(&bucket)
+            .map(HyperLogLog::estimate)
+            .unwrap_or(0u64)
+    }
+
+    /// Returns the non-empty buckets together with their approximate
+    /// number of distinct values, sorted by bucket.
+    pub fn counts(&self) -> Vec<(u64, u64)> {
+        self.buckets
+            .iter()
+            .map(|(&bucket, sketch)| (bucket, sketch.estimate()))
+            .collect()
+    }
+
+    /// Merges the distinct counts of `other` into `self`.
+    ///
+    /// The counts must have been collected with the same precision.
+    /// A value appearing in both is only counted once.
+    pub fn merge(&mut self, other: &DistinctCounts) {
+        for (&bucket, sketch) in &other.buckets {
+            self.buckets
+                .entry(bucket)
+                .or_insert_with(|| HyperLogLog::new(sketch.precision))
+                .merge(sketch);
+        }
+    }
+}
+
+/// Collector counting the distinct values of a fast field
+/// per bucket of documents, e.g. the unique visitors per day.
+///
+/// The documents are bucketed on a `u64` fast field: either one bucket
+/// per value, like a terms aggregation, or one bucket per interval of values,
+/// like a histogram aggregation.
+/// The distinct values of the `u64` fast field `value_field` are
+/// then counted in each bucket, in one pass over the fast fields.
+///
+/// The counts are approximate: each bucket keeps a HyperLogLog sketch,
+/// whose size does not depend on the number of distinct values.
+/// See `DEFAULT_DISTINCT_COUNT_PRECISION`.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{SchemaBuilder, FAST};
+/// use tantivy::{Index, Result};
+/// use tantivy::collector::DistinctCountCollector;
+/// use tantivy::query::AllQuery;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let timestamp = schema_builder.add_u64_field("timestamp", FAST);
+///     let visitor = schema_builder.add_u64_field("visitor", FAST);
+///     let index = Index::create_in_ram(schema_builder.build());
+///     {
+///         let mut index_writer = index.writer_with_num_threads(1, 3_000_000)?;
+///         index_writer.add_document(doc!(timestamp => 10u64, visitor => 1u64));
+///         index_writer.add_document(doc!(timestamp => 20u64, visitor => 1u64));
+///         index_writer.add_document(doc!(timestamp => 86_410u64, visitor => 2u64));
+///         index_writer.commit()?;
+///     }
+///     index.load_searchers()?;
+///     let searcher = index.searcher();
+///     let collector = DistinctCountCollector::per_interval(timestamp, 86_400, visitor);
+///     let unique_visitors_per_day = searcher.search_fruit(&AllQuery, collector)?;
+///     assert_eq!(unique_visitors_per_day.counts(), vec![(0, 1), (86_400, 1)]);
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct DistinctCountCollector {
+    bucket_field: Field,
+    interval: u64,
+    value_field: Field,
+    precision: u8,
+    buckets: BTreeMap<u64, HyperLogLog>,
+    bucket_reader: Option<FastFieldReader<u64>>,
+    value_reader: Option<FastFieldReader<u64>>,
+}
+
+impl DistinctCountCollector {
+    /// Creates a collector counting the distinct values of `value_field`
+    /// for each value of `bucket_field`.
+    pub fn per_value(bucket_field: Field, value_field: Field) -> DistinctCountCollector {
+        DistinctCountCollector::per_interval(bucket_field, 1, value_field)
+    }
+
+    /// Creates a collector counting the distinct values of `value_field`
+    /// for each interval of `interval` values of `bucket_field`.
+    ///
+    /// The buckets are aligned on the multiples of `interval`.
+    ///
+    /// # Panics
+    /// The method panics if interval is 0
+    pub fn per_interval(
+        bucket_field: Field,
+        interval: u64,
+        value_field: Field,
+    ) -> DistinctCountCollector {
+        if interval == 0 {
+            panic!("Interval must be strictly greater than 0.");
+        }
+        DistinctCountCollector {
+            bucket_field,
+            interval,
+            value_field,
+            precision: DEFAULT_DISTINCT_COUNT_PRECISION,
+            buckets: BTreeMap::new(),
+            bucket_reader: None,
+            value_reader: None,
+        }
+    }
+
+    /// Sets the precision of the sketches, between 4 and 16.
+    ///
+    /// Each bucket takes `2^precision` bytes, and the standard error
+    /// of its count is about `1.04 / sqrt(2^precision)`.
+    ///
+    /// # Panics
+    /// The method panics if the precision is out of bounds
+    pub fn with_precision(mut self, precision: u8) -> DistinctCountCollector {
+        if precision < MIN_PRECISION || precision > MAX_PRECISION {
+            panic!(
+                "Precision must be between {} and {}.",
+                MIN_PRECISION, MAX_PRECISION
+            );
+        }
+        self.precision = precision;
+        self
+    }
+
+    /// Returns the distinct counts collected so far.
+    pub fn distinct_counts(&self) -> DistinctCounts {
+        DistinctCounts {
+            buckets: self.buckets.clone(),
+        }
+    }
+}
+
+impl Collector for DistinctCountCollector {
+    fn set_segment(&mut self, _: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        self.bucket_reader = Some(reader.fast_field_reader(self.bucket_field)?);
+        self.value_reader = Some(reader.fast_field_reader(self.value_field)?);
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _: Score) {
+        let bucket_val = self.bucket_reader
+            .as_ref()
+            .expect("collect() was called before set_segment. This should never happen.")
+            .get(doc);
+        let val = self.value_reader
+            .as_ref()
+            .expect("collect() was called before set_segment. This should never happen.")
+            .get(doc);
+        let bucket = bucket_val - bucket_val % self.interval;
+        let precision = self.precision;
+        self.buckets
+            .entry(bucket)
+            .or_insert_with(|| HyperLogLog::new(precision))
+            .insert(val);
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+impl FruitCollector for DistinctCountCollector {
+    type Fruit = DistinctCounts;
+
+    fn harvest(self) -> DistinctCounts {
+        DistinctCounts {
+            buckets: self.buckets,
+        }
+    }
+
+    fn merge_fruits(&self, fruits: Vec<DistinctCounts>) -> DistinctCounts {
+        let mut distinct_counts = DistinctCounts {
+            buckets: BTreeMap::new(),
+        };
+        for fruit in &fruits {
+            distinct_counts.merge(fruit);
+        }
+        distinct_counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{DistinctCountCollector, HyperLogLog};
+    use query::AllQuery;
+    use schema::{SchemaBuilder, FAST};
+    use Index;
+
+    #[test]
+    fn test_hyperloglog_estimate() {
+        let mut sketch = HyperLogLog::new(12);
+        assert_eq!(sketch.estimate(), 0);
+        for val in 0..20_000u64 {
+            // every value is inserted twice.
+            sketch.insert(val % 10_000);
+        }
+        let estimate = sketch.estimate() as f64;
+        assert!((estimate - 10_000f64).abs() < 500f64);
+        let mut other = HyperLogLog::new(12);
+        for val in 5_000..15_000u64 {
+            other.insert(val);
+        }
+        sketch.merge(&other);
+        let estimate = sketch.estimate() as f64;
+        assert!((estimate - 15_000f64).abs() < 750f64);
+    }
+
+    #[test]
+    fn test_distinct_count_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let day = schema_builder.add_u64_field("day", FAST);
+        let visitor = schema_builder.add_u64_field("visitor", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(day => 0u64, visitor => 1u64));
+            index_writer.add_document(doc!(day => 0u64, visitor => 2u64));
+            index_writer.add_document(doc!(day => 0u64, visitor => 2u64));
+            index_writer.add_document(doc!(day => 1u64, visitor => 3u64));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(day => 0u64, visitor => 3u64));
+            index_writer.add_document(doc!(day => 0u64, visitor => 1u64));
+            index_writer.add_document(doc!(day => 2u64, visitor => 1u64));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let mut collector = DistinctCountCollector::per_value(day, visitor);
+        searcher.search(&AllQuery, &mut collector).unwrap();
+        let distinct_counts = collector.distinct_counts();
+        assert_eq!(distinct_counts.counts(), vec![(0, 3), (1, 1), (2, 1)]);
+        assert_eq!(distinct_counts.get(0), 3);
+        assert_eq!(distinct_counts.get(3), 0);
+
+        let collector = DistinctCountCollector::per_interval(day, 2, visitor).with_precision(4);
+        let distinct_counts = searcher.search_fruit(&AllQuery, collector).unwrap();
+        assert_eq!(distinct_counts.counts(), vec![(0, 3), (2, 1)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_distinct_count_precision() {
+        let mut schema_builder = SchemaBuilder::default();
+        let field = schema_builder.add_u64_field("field", FAST);
+        DistinctCountCollector::per_value(field, field).with_precision(17);
+    }
+}
@@ -0,0 +1,305 @@
+use collector::{Collector, FacetCollector, FacetCounts};
+use common::BitSet;
+use docset::DocSet;
+use query::{create_weight, Query};
+use schema::{Facet, Field, IndexRecordOption, Term};
+use DocId;
+use Result;
+use Searcher;
+use SegmentLocalId;
+use SegmentReader;
+
+struct Dimension {
+    field: Field,
+    facet: Facet,
+    selected_facets: Vec<Facet>,
+}
+
+impl Dimension {
+    /// Returns the set of the documents associated with one of
+    /// the selected facets, or `None` if no facet is selected.
+    fn filter(&self, segment_reader: &SegmentReader) -> Option<BitSet> {
+        if self.selected_facets.is_empty() {
+            return None;
+        }
+        let inverted_index = segment_reader.inverted_index(self.field);
+        let mut docs = BitSet::with_max_value(segment_reader.max_doc());
+        for selected_facet in &self.selected_facets {
+            let term = Term::from_facet(self.field, selected_facet);
+            let postings_opt = inverted_index.read_postings(&term, IndexRecordOption::Basic);
+            if let Some(mut postings) = postings_opt {
+                while postings.advance() {
+                    docs.insert(postings.doc());
+                }
+            }
+        }
+        Some(docs)
+    }
+}
+
+/// Drill-sideways faceted search.
+///
+/// When the user of a faceted search UI selects facets in several
+/// dimensions (e.g. `/category/fiction` and `/lang/en`), the hits are
+/// restricted to the documents matching the query and all of the
+/// selected facets (drill-down), but the counts of each dimension are
+/// computed as if the selection within this very dimension was removed,
+/// so that the alternative values of the dimension remain visible.
+///
+/// Within a dimension, the selected facets are combined with an `OR`.
+///
+/// All of the counts are computed in a single pass over
+/// the documents matching the query.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{Facet, SchemaBuilder};
+/// use tantivy::{Index, Result};
+/// use tantivy::collector::{CountCollector, DrillSideways};
+/// use tantivy::query::AllQuery;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let facet = schema_builder.add_facet_field("facet");
+///     let index = Index::create_in_ram(schema_builder.build());
+///     {
+///         let mut index_writer = index.writer(3_000_000)?;
+///         index_writer.add_document(doc!(
+///             facet => Facet::from("/lang/en"),
+///             facet => Facet::from("/category/fiction")
+///         ));
+///         index_writer.add_document(doc!(
+///             facet => Facet::from("/lang/fr"),
+///             facet => Facet::from("/category/fiction")
+///         ));
+///         index_writer.add_document(doc!(
+///             facet => Facet::from("/lang/en"),
+///             facet => Facet::from("/category/biography")
+///         ));
+///         index_writer.commit()?;
+///     }
+///     index.load_searchers()?;
+///     let searcher = index.searcher();
+///
+///     let mut drill_sideways = DrillSideways::new(Box::new(AllQuery));
+///     drill_sideways.add_dimension(facet, "/lang", vec![Facet::from("/lang/en")]);
+///     let mut count_collector = CountCollector::default();
+///     let counts = drill_sideways.search(&searcher, &mut count_collector)?;
+///
+///     // the hits are restricted to the english documents...
+///     assert_eq!(count_collector.count(), 2);
+///     // ... but the counts of the `/lang` dimension ignore the selection.
+///     let lang_counts: Vec<(&Facet, u64)> = counts[0].get("/lang").collect();
+///     assert_eq!(lang_counts, vec![
+///         (&Facet::from("/lang/en"), 2),
+///         (&Facet::from("/lang/fr"), 1)
+///     ]);
+///     Ok(())
+/// }
+/// ```
+pub struct DrillSideways {
+    query: Box<Query>,
+    dimensions: Vec<Dimension>,
+}
+
+impl DrillSideways {
+    /// Creates a drill-sideways search for the given base query.
+    pub fn new(query: Box<Query>) -> DrillSideways {
+        DrillSideways {
+            query,
+            dimensions: Vec::new(),
+        }
+    }
+
+    /// Adds a dimension, identified by a facet of a facet field.
+    ///
+    /// The counts of the dimension are the counts of the direct
+    /// children of `facet`, as computed by the `FacetCollector`.
+    /// The hits are restricted to the documents associated
+    /// with one of the `selected_facets`.
+    /// If no facet is selected, the dimension does not restrict the hits.
+    pub fn add_dimension<T>(&mut self, field: Field, facet: T, selected_facets: Vec<Facet>)
+    where
+        Facet: From<T>,
+    {
+        self.dimensions.push(Dimension {
+            field,
+            facet: Facet::from(facet),
+            selected_facets,
+        });
+    }
+
+    /// Runs the search.
+    ///
+    /// The documents matching the query and the selected facets of all of
+    /// the dimensions are passed to `collector`.
+    ///
+    /// Returns the facet counts of each dimension, in the order
+    /// the dimensions were added.
+    pub fn search(
+        &self,
+        searcher: &Searcher,
+        collector: &mut Collector,
+    ) -> Result<Vec<FacetCounts>> {
+        let weight = create_weight(&*self.query, searcher, collector.requires_scoring())?;
+        let mut facet_collectors: Vec<FacetCollector> = self.dimensions
+            .iter()
+            .map(|dimension| {
+                let mut facet_collector = FacetCollector::for_field(dimension.field);
+                facet_collector.add_facet(dimension.facet.clone());
+                facet_collector
+            })
+            .collect();
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let segment_local_id = segment_ord as SegmentLocalId;
+            collector.set_segment(segment_local_id, segment_reader)?;
+            for facet_collector in &mut facet_collectors {
+                facet_collector.set_segment(segment_local_id, segment_reader)?;
+            }
+            let filters: Vec<Option<BitSet>> = self.dimensions
+                .iter()
+                .map(|dimension| dimension.filter(segment_reader))
+                .collect();
+            let mut scorer = weight.alive_scorer(segment_reader)?;
+            while scorer.advance() {
+                let doc = scorer.doc();
+                match missed_dimensions(&filters, doc) {
+                    MissedDimensions::None => {
+                        let score = scorer.score();
+                        collector.collect(doc, score);
+                        for facet_collector in &mut facet_collectors {
+                            facet_collector.collect(doc, score);
+                        }
+                    }
+                    MissedDimensions::One(dimension_ord) => {
+                        facet_collectors[dimension_ord].collect(doc, 0f32);
+                    }
+                    MissedDimensions::Many => {}
+                }
+            }
+        }
+        Ok(facet_collectors
+            .into_iter()
+            .map(FacetCollector::harvest)
+            .collect())
+    }
+}
+
+enum MissedDimensions {
+    None,
+    One(usize),
+    Many,
+}
+
+/// Returns the dimensions whose selected facets are not associated
+/// with the document.
+///
+/// A document missing a single dimension is a near miss: it does not
+/// belong to the hits, but is counted in the dimension it misses.
+fn missed_dimensions(filters: &[Option<BitSet>], doc: DocId) -> MissedDimensions {
+    let mut missed = MissedDimensions::None;
+    for (dimension_ord, filter) in filters.iter().enumerate() {
+        if let Some(ref filter) = *filter {
+            if !filter.contains(doc) {
+                if let MissedDimensions::One(_) = missed {
+                    return MissedDimensions::Many;
+                }
+                missed = MissedDimensions::One(dimension_ord);
+            }
+        }
+    }
+    missed
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::DrillSideways;
+    use collector::CountCollector;
+    use query::AllQuery;
+    use schema::*;
+    use Index;
+
+    #[test]
+    fn test_drill_sideways() {
+        let mut schema_builder = SchemaBuilder::default();
+        let facet = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for &(lang, category) in &[
+                ("/lang/en", "/category/fiction/fantasy"),
+                ("/lang/en", "/category/fiction/sci-fi"),
+                ("/lang/fr", "/category/fiction/fantasy"),
+                ("/lang/en", "/category/biography"),
+                ("/lang/de", "/category/biography"),
+            ] {
+                index_writer.add_document(doc!(
+                    facet => Facet::from(lang),
+                    facet => Facet::from(category)
+                ));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+
+        let mut drill_sideways = DrillSideways::new(box AllQuery);
+        drill_sideways.add_dimension(facet, "/lang", vec![Facet::from("/lang/en")]);
+        drill_sideways.add_dimension(
+            facet,
+            "/category",
+            vec![Facet::from("/category/fiction")],
+        );
+        let mut count_collector = CountCollector::default();
+        let counts = drill_sideways
+            .search(&searcher, &mut count_collector)
+            .unwrap();
+        assert_eq!(count_collector.count(), 2);
+        assert_eq!(counts.len(), 2);
+        {
+            // fiction documents, regardless of the language.
+            let lang_counts: Vec<(&Facet, u64)> = counts[0].get("/lang").collect();
+            assert_eq!(
+                lang_counts,
+                vec![(&Facet::from("/lang/en"), 2), (&Facet::from("/lang/fr"), 1)]
+            );
+        }
+        {
+            // english documents, regardless of the category.
+            let category_counts: Vec<(&Facet, u64)> = counts[1].get("/category").collect();
+            assert_eq!(
+                category_counts,
+                vec![
+                    (&Facet::from("/category/biography"), 1),
+                    (&Facet::from("/category/fiction"), 2),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_drill_sideways_no_selection() {
+        let mut schema_builder = SchemaBuilder::default();
+        let facet = schema_builder.add_facet_field("facet");
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(facet => Facet::from("/lang/en")));
+            index_writer.add_document(doc!(facet => Facet::from("/lang/fr")));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut drill_sideways = DrillSideways::new(box AllQuery);
+        drill_sideways.add_dimension(facet, "/lang", vec![]);
+        let mut count_collector = CountCollector::default();
+        let counts = drill_sideways
+            .search(&searcher, &mut count_collector)
+            .unwrap();
+        assert_eq!(count_collector.count(), 2);
+        assert_eq!(counts[0].get("/lang").count(), 2);
+    }
+}
@@ -1,5 +1,5 @@
 use std::mem;
-use collector::Collector;
+use collector::{Collector, FruitCollector};
 use fastfield::FacetReader;
 use schema::Field;
 use std::cell::UnsafeCell;
@@ -10,10 +10,9 @@ use std::collections::Bound;
 use termdict::TermDictionary;
 use termdict::TermStreamer;
 use termdict::TermStreamerBuilder;
-use std::collections::BTreeSet;
 use termdict::TermMerger;
 use docset::SkipResult;
-use std::{usize, u64};
+use std::u64;
 use std::iter::Peekable;
 
 use DocId;
@@ -71,7 +70,8 @@ fn facet_depth(facet_bytes: &[u8]) -> usize {
 /// facet you want to extract as argument.
 ///
 /// Facet counts will only be computed for the facet that are direct children
-/// of such a root facet.
+/// of such a root facet, or, using `.add_facet_with_depth(...)`, for its
+/// descendants down to a given depth.
 ///
 /// For instance, if your index represents books, your hierarchy of facets
 /// may contain `category`, `language`.
@@ -207,17 +207,27 @@ pub struct FacetCollector {
     current_segment_counts: Vec<u64>,
     // collapse facet_id -> facet_ord
     current_collapse_facet_ords: Vec<u64>,
-
-    facets: BTreeSet<Facet>,
+    // collapse facet_id -> collapse facet_id of the parent
+    // (0 if the parent is not counted)
+    current_collapse_parents: Vec<usize>,
+    // collapse facet_id -> last doc counted
+    current_segment_last_docs: Vec<DocId>,
+
+    // facet -> depth of the descendants to count
+    facets: BTreeMap<Facet, usize>,
+
+    // number of documents without any facet,
+    // if the missing documents are counted.
+    missing_count: Option<u64>,
 }
 
-fn skip<'a, I: Iterator<Item = &'a Facet>>(
+fn skip<'a, I: Iterator<Item = (&'a Facet, &'a usize)>>(
     target: &[u8],
     collapse_it: &mut Peekable<I>,
 ) -> SkipResult {
     loop {
         match collapse_it.peek() {
-            Some(facet_bytes) => match facet_bytes.encoded_bytes().cmp(target) {
+            Some(&(facet, _)) => match facet.encoded_bytes().cmp(target) {
                 Ordering::Less => {}
                 Ordering::Greater => {
                     return SkipResult::OverStep;
@@ -246,10 +256,13 @@ impl FacetCollector {
             segment_counters: Vec::new(),
             field,
             ff_reader: None,
-            facets: BTreeSet::new(),
+            facets: BTreeMap::new(),
+            missing_count: None,
 
             current_segment_collapse_mapping: Vec::new(),
             current_collapse_facet_ords: Vec::new(),
+            current_collapse_parents: Vec::new(),
+            current_segment_last_docs: Vec::new(),
             current_segment_counts: Vec::new(),
         }
     }
@@ -267,8 +280,27 @@ impl FacetCollector {
     where
         Facet: From<T>,
     {
+        self.add_facet_with_depth(facet_from, 1);
+    }
+
+    /// Adds a facet that we want to record counts, down to
+    /// a given depth.
+    ///
+    /// With a depth of 2, adding `Facet::from("/category")`
+    /// will record the counts of its children (e.g. `/category/fiction`)
+    /// as well as those of its grandchildren (e.g. `/category/fiction/fantasy`).
+    /// A depth of 1 is equivalent to `.add_facet(...)`.
+    ///
+    /// # Panics
+    /// If the depth is 0, or if the facet is the prefix of an
+    /// already added facet, or the other way around.
+    pub fn add_facet_with_depth<T>(&mut self, facet_from: T, depth: usize)
+    where
+        Facet: From<T>,
+    {
+        assert!(depth > 0, "The depth of the facet counts must be at least 1.");
         let facet = Facet::from(facet_from);
-        for old_facet in &self.facets {
+        for old_facet in self.facets.keys() {
             assert!(
                 !old_facet.is_prefix_of(&facet),
                 "Tried to add a facet which is a descendant of an already added facet."
@@ -278,15 +310,27 @@ impl FacetCollector {
                 "Tried to add a facet which is an ancestor of an already added facet."
             );
         }
-        self.facets.insert(facet);
+        self.facets.insert(facet, depth);
+    }
+
+    /// Enables the counting of the documents that are not
+    /// associated with any facet of the field.
+    ///
+    /// Without this, such documents are silently ignored.
+    /// Their number is returned by `FacetCounts::missing`.
+    pub fn count_missing(&mut self) {
+        self.missing_count = Some(0);
     }
 
     fn set_collapse_mapping(&mut self, facet_reader: &FacetReader) {
         self.current_segment_collapse_mapping.clear();
         self.current_collapse_facet_ords.clear();
+        self.current_collapse_parents.clear();
         self.current_segment_counts.clear();
+        self.current_segment_last_docs.clear();
         let mut collapse_facet_it = self.facets.iter().peekable();
         self.current_collapse_facet_ords.push(0);
+        self.current_collapse_parents.push(0);
         let mut facet_streamer = facet_reader.facet_dict().range().into_stream();
         if !facet_streamer.advance() {
             return;
@@ -299,20 +343,35 @@ impl FacetCollector {
                 SkipResult::Reached => {
                     // we reach a facet we decided to collapse.
                     let collapse_depth = facet_depth(facet_streamer.key());
-                    let mut collapsed_id = 0;
+                    let max_level = *collapse_facet_it
+                        .peek()
+                        .expect("A reached facet is always present")
+                        .1;
+                    // collapsed ids of the counted ancestors of the current facet,
+                    // from the children of the collapsed facet downwards.
+                    //
+                    // Facets are streamed in depth-first order, so that all of
+                    // the ancestors of a facet have been streamed before it.
+                    let mut ancestor_ids: Vec<usize> = Vec::with_capacity(max_level);
                     self.current_segment_collapse_mapping.push(0);
                     while facet_streamer.advance() {
                         let depth = facet_depth(facet_streamer.key());
                         if depth <= collapse_depth {
                             continue 'outer;
                         }
-                        if depth == collapse_depth + 1 {
-                            collapsed_id = self.current_collapse_facet_ords.len();
+                        let level = depth - collapse_depth;
+                        if level <= max_level {
+                            ancestor_ids.truncate(level - 1);
+                            let parent_id = ancestor_ids.last().cloned().unwrap_or(0);
+                            let collapsed_id = self.current_collapse_facet_ords.len();
                             self.current_collapse_facet_ords
                                 .push(facet_streamer.term_ord());
+                            self.current_collapse_parents.push(parent_id);
+                            ancestor_ids.push(collapsed_id);
                             self.current_segment_collapse_mapping.push(collapsed_id);
                         } else {
-                            self.current_segment_collapse_mapping.push(collapsed_id);
+                            self.current_segment_collapse_mapping
+                                .push(ancestor_ids[max_level - 1]);
                         }
                     }
                     break;
@@ -385,7 +444,10 @@ impl FacetCollector {
                 facet_counts.insert(Facet::from_encoded(bytes), count);
             }
         }
-        FacetCounts { facet_counts }
+        FacetCounts {
+            facet_counts,
+            missing_count: self.missing_count,
+        }
     }
 }
 
@@ -396,6 +458,8 @@ impl Collector for FacetCollector {
         self.set_collapse_mapping(&facet_reader);
         self.current_segment_counts
             .resize(self.current_collapse_facet_ords.len(), 0);
+        self.current_segment_last_docs
+            .resize(self.current_collapse_facet_ords.len(), DocId::max_value());
         self.ff_reader = Some(UnsafeCell::new(facet_reader));
         Ok(())
     }
@@ -408,16 +472,20 @@ impl Collector for FacetCollector {
                 .get()
         };
         facet_reader.facet_ords(doc, &mut self.facet_ords);
-        let mut previous_collapsed_ord: usize = usize::MAX;
+        if self.facet_ords.is_empty() {
+            if let Some(ref mut missing_count) = self.missing_count {
+                *missing_count += 1;
+            }
+        }
         for &facet_ord in &self.facet_ords {
-            let collapsed_ord = self.current_segment_collapse_mapping[facet_ord as usize];
-            self.current_segment_counts[collapsed_ord] += if collapsed_ord == previous_collapsed_ord
-            {
-                0
-            } else {
-                1
-            };
-            previous_collapsed_ord = collapsed_ord;
+            // counts the facet and its counted ancestors,
+            // once per document.
+            let mut collapsed_ord = self.current_segment_collapse_mapping[facet_ord as usize];
+            while collapsed_ord != 0 && self.current_segment_last_docs[collapsed_ord] != doc {
+                self.current_segment_last_docs[collapsed_ord] = doc;
+                self.current_segment_counts[collapsed_ord] += 1;
+                collapsed_ord = self.current_collapse_parents[collapsed_ord];
+            }
         }
     }
 
@@ -426,13 +494,48 @@ impl Collector for FacetCollector {
     }
 }
 
+impl FruitCollector for FacetCollector {
+    type Fruit = FacetCounts;
+
+    fn harvest(self) -> FacetCounts {
+        FacetCollector::harvest(self)
+    }
+
+    /// Sums the counts of each facet.
+    fn merge_fruits(&self, fruits: Vec<FacetCounts>) -> FacetCounts {
+        let mut facet_counts = BTreeMap::new();
+        let mut missing_count = self.missing_count.map(|_| 0u64);
+        for fruit in fruits {
+            for (facet, count) in fruit.facet_counts {
+                *facet_counts.entry(facet).or_insert(0u64) += count;
+            }
+            if let (Some(total), Some(count)) = (missing_count.as_mut(), fruit.missing_count) {
+                *total += count;
+            }
+        }
+        FacetCounts {
+            facet_counts,
+            missing_count,
+        }
+    }
+}
+
 /// Intermediary result of the `FacetCollector` that stores
 /// the facet counts for all the segments.
 pub struct FacetCounts {
     facet_counts: BTreeMap<Facet, u64>,
+    missing_count: Option<u64>,
 }
 
 impl FacetCounts {
+    /// Returns the number of documents that are not associated with
+    /// any facet of the field, or `None` if they were not counted.
+    ///
+    /// See `FacetCollector::count_missing`.
+    pub fn missing(&self) -> Option<u64> {
+        self.missing_count
+    }
+
     #[allow(needless_lifetimes)] //< compiler fails if we remove the lifetime
     pub fn get<'a, T>(&'a self, facet_from: T) -> impl Iterator<Item = (&'a Facet, u64)>
     where
@@ -485,7 +588,7 @@ impl FacetCounts {
 mod tests {
     use test::Bencher;
     use core::Index;
-    use schema::{Document, Facet, SchemaBuilder};
+    use schema::{Document, Facet, SchemaBuilder, TEXT};
     use query::AllQuery;
     use super::{FacetCollector, FacetCounts};
     use std::iter;
@@ -604,6 +707,99 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_facet_collector_missing() {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+
+        let mut index_writer = index.writer(3_000_000).unwrap();
+        index_writer.add_document(doc!(facet_field => Facet::from("/lang/en")));
+        index_writer.add_document(doc!(text_field => "no facet"));
+        index_writer.add_document(doc!(facet_field => Facet::from("/lang/fr")));
+        index_writer.add_document(doc!(text_field => "no facet either"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+
+        {
+            let mut facet_collector = FacetCollector::for_field(facet_field);
+            facet_collector.add_facet("/lang");
+            searcher.search(&AllQuery, &mut facet_collector).unwrap();
+            assert_eq!(facet_collector.harvest().missing(), None);
+        }
+        {
+            let mut facet_collector = FacetCollector::for_field(facet_field);
+            facet_collector.add_facet("/lang");
+            facet_collector.count_missing();
+            searcher.search(&AllQuery, &mut facet_collector).unwrap();
+            let counts = facet_collector.harvest();
+            assert_eq!(counts.missing(), Some(2));
+            assert_eq!(counts.get("/lang").count(), 2);
+        }
+    }
+
+    #[test]
+    fn test_facet_collector_depth() {
+        let mut schema_builder = SchemaBuilder::new();
+        let facet_field = schema_builder.add_facet_field("facet");
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+
+        let mut index_writer = index.writer(3_000_000).unwrap();
+        index_writer.add_document(doc!(
+            facet_field => Facet::from("/category/fiction/fantasy/epic"),
+            facet_field => Facet::from("/lang/en")
+        ));
+        index_writer.add_document(doc!(
+            facet_field => Facet::from("/category/fiction/fantasy"),
+            facet_field => Facet::from("/category/fiction/horror")
+        ));
+        index_writer.add_document(doc!(facet_field => Facet::from("/category/fiction/sci-fi")));
+        index_writer.add_document(doc!(facet_field => Facet::from("/category/biography")));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+
+        let facet_counts = |depth: usize| -> Vec<(Facet, u64)> {
+            let mut facet_collector = FacetCollector::for_field(facet_field);
+            facet_collector.add_facet_with_depth("/category", depth);
+            searcher.search(&AllQuery, &mut facet_collector).unwrap();
+            facet_collector
+                .harvest()
+                .get("/category")
+                .map(|(facet, count)| (facet.clone(), count))
+                .collect()
+        };
+        assert_eq!(
+            facet_counts(1),
+            vec![
+                (Facet::from("/category/biography"), 1),
+                (Facet::from("/category/fiction"), 3),
+            ]
+        );
+        assert_eq!(
+            facet_counts(2),
+            vec![
+                (Facet::from("/category/biography"), 1),
+                (Facet::from("/category/fiction"), 3),
+                (Facet::from("/category/fiction/fantasy"), 2),
+                (Facet::from("/category/fiction/horror"), 1),
+                (Facet::from("/category/fiction/sci-fi"), 1),
+            ]
+        );
+        assert_eq!(facet_counts(3).len(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "The depth of the facet counts must be at least 1.")]
+    fn test_facet_collector_zero_depth() {
+        let mut facet_collector = FacetCollector::for_field(Field(0));
+        facet_collector.add_facet_with_depth("/category", 0);
+    }
+
     #[bench]
     fn bench_facet_collector(b: &mut Bencher) {
         let mut schema_builder = SchemaBuilder::new();
@@ -0,0 +1,128 @@
+use super::Collector;
+use DocId;
+use Result;
+use Score;
+use SegmentLocalId;
+use SegmentReader;
+
+/// The global collector passes all of the alive documents
+/// of the index to its wrapped collector, regardless of the query.
+///
+/// Combined with other collectors in a `MultiCollector` or a chained
+/// collector, it makes it possible to compute, in one search, both the
+/// hits of the query and unfiltered aggregates, e.g. the facet counts
+/// over the whole index.
+///
+/// The wrapped collector receives all of the documents of a segment
+/// when the segment is set, and each document is given a score of `1`.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{Facet, SchemaBuilder, TEXT};
+/// use tantivy::{Index, Result};
+/// use tantivy::collector::{chain, CountCollector, FacetCollector, GlobalCollector};
+/// use tantivy::query::QueryParser;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let title = schema_builder.add_text_field("title", TEXT);
+///     let facet = schema_builder.add_facet_field("facet");
+///     let index = Index::create_in_ram(schema_builder.build());
+///     {
+///         let mut index_writer = index.writer(3_000_000)?;
+///         index_writer.add_document(doc!(title => "Dune", facet => Facet::from("/lang/en")));
+///         index_writer.add_document(doc!(title => "Candide", facet => Facet::from("/lang/fr")));
+///         index_writer.commit()?;
+///     }
+///     index.load_searchers()?;
+///     let searcher = index.searcher();
+///     let query = QueryParser::for_index(&index, vec![title]).parse_query("dune")?;
+///
+///     let mut count_collector = CountCollector::default();
+///     let mut facet_collector = FacetCollector::for_field(facet);
+///     facet_collector.add_facet("/lang");
+///     {
+///         let mut global_collector = GlobalCollector::new(&mut facet_collector);
+///         let mut collectors = chain().push(&mut count_collector).push(&mut global_collector);
+///         searcher.search(&*query, &mut collectors)?;
+///     }
+///     assert_eq!(count_collector.count(), 1);
+///     assert_eq!(facet_collector.harvest().get("/lang").count(), 2);
+///     Ok(())
+/// }
+/// ```
+pub struct GlobalCollector<'a> {
+    collector: &'a mut Collector,
+}
+
+impl<'a> GlobalCollector<'a> {
+    /// Wraps a collector so that it collects all of the alive documents.
+    pub fn new(collector: &'a mut Collector) -> GlobalCollector<'a> {
+        GlobalCollector { collector }
+    }
+}
+
+impl<'a> Collector for GlobalCollector<'a> {
+    fn set_segment(
+        &mut self,
+        segment_local_id: SegmentLocalId,
+        segment: &SegmentReader,
+    ) -> Result<()> {
+        self.collector.set_segment(segment_local_id, segment)?;
+        for doc in 0..segment.max_doc() {
+            if !segment.is_deleted(doc) {
+                self.collector.collect(doc, 1f32);
+            }
+        }
+        Ok(())
+    }
+
+    fn collect(&mut self, _: DocId, _: Score) {}
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::GlobalCollector;
+    use collector::{chain, CountCollector};
+    use query::TermQuery;
+    use schema::*;
+    use Index;
+
+    #[test]
+    fn test_global_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a"));
+            index_writer.add_document(doc!(text => "b"));
+            index_writer.add_document(doc!(text => "c"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text => "a"));
+            index_writer.delete_term(Term::from_field_text(text, "c"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(Term::from_field_text(text, "a"), IndexRecordOption::Basic);
+        let mut hits_collector = CountCollector::default();
+        let mut all_docs_collector = CountCollector::default();
+        {
+            let mut global_collector = GlobalCollector::new(&mut all_docs_collector);
+            let mut collectors = chain()
+                .push(&mut hits_collector)
+                .push(&mut global_collector);
+            searcher.search(&query, &mut collectors).unwrap();
+        }
+        assert_eq!(hits_collector.count(), 2);
+        assert_eq!(all_docs_collector.count(), 3);
+    }
+}
@@ -15,10 +15,31 @@ mod multi_collector;
 pub use self::multi_collector::MultiCollector;
 
 mod top_collector;
-pub use self::top_collector::TopCollector;
+pub use self::top_collector::{compare_score_docs, TopCollector, TopDocs, TotalHits,
+                              TotalHitsMode};
+
+mod top_fields_collector;
+pub use self::top_fields_collector::{ProjectedHit, TopFieldsCollector};
 
 mod facet_collector;
-pub use self::facet_collector::FacetCollector;
+pub use self::facet_collector::{FacetCollector, FacetCounts};
+
+mod drill_sideways;
+pub use self::drill_sideways::DrillSideways;
+
+mod global_collector;
+pub use self::global_collector::GlobalCollector;
+
+mod significant_terms_collector;
+pub use self::significant_terms_collector::{SignificanceHeuristic, SignificantTerm,
+                                            SignificantTermsCollector};
+
+mod weighted_sample_collector;
+pub use self::weighted_sample_collector::WeightedSampleCollector;
+
+mod distinct_count_collector;
+pub use self::distinct_count_collector::{DistinctCountCollector, DistinctCounts,
+                                         DEFAULT_DISTINCT_COUNT_PRECISION};
 
 mod chained_collector;
 pub use self::chained_collector::chain;
@@ -52,6 +73,11 @@ pub use self::chained_collector::chain;
 /// - `.collect(last_doc_of_last_segment)`
 ///
 /// Segments are not guaranteed to be visited in any specific order.
+///
+/// `set_segment` hands over the complete `SegmentReader`, so that collectors
+/// can open the fast fields, facet readers or stored fields they need.
+/// Cloning a `SegmentReader` is cheap, so a collector may keep
+/// the reader of the current segment to access it on demand in `collect`.
 pub trait Collector {
     /// `set_segment` is called before beginning to enumerate
     /// on this segment.
@@ -65,6 +91,55 @@ pub trait Collector {
 
     /// Returns true iff the collector requires to compute scores for documents.
     fn requires_scoring(&self) -> bool;
+
+    /// Returns true iff the collector requires the term frequency and
+    /// positions of the matched documents.
+    ///
+    /// If so, the documents are pushed through `.collect_with_match(...)`,
+    /// and the queries decode term frequencies and positions as if scoring
+    /// was enabled.
+    fn requires_match_info(&self) -> bool {
+        false
+    }
+
+    /// Pushes a scored document to the collector, together with
+    /// the information exposed by the scorer about the match.
+    ///
+    /// This is only called if `.requires_match_info()` returns true.
+    /// Defaults to `.collect(doc, score)`.
+    fn collect_with_match(&mut self, doc: DocId, score: Score, _match_info: &MatchInfo) {
+        self.collect(doc, score);
+    }
+}
+
+/// Collector producing a typed result, its fruit, once the search is over.
+///
+/// Rather than interrogating the state of the collector after the search,
+/// `Searcher::search_fruit` consumes the collector and returns its fruit.
+/// The fruits of collectors run separately, for instance on disjoint sets
+/// of segments or on distinct shards, can be merged with `.merge_fruits(...)`.
+pub trait FruitCollector: Collector {
+    /// Typed result of the collector.
+    type Fruit;
+
+    /// Consumes the collector and returns its fruit.
+    fn harvest(self) -> Self::Fruit;
+
+    /// Merges the fruits of collectors configured like `self`.
+    fn merge_fruits(&self, fruits: Vec<Self::Fruit>) -> Self::Fruit;
+}
+
+/// Information about the match of a document, as exposed by its `Scorer`.
+///
+/// See `Scorer::term_freq` and `Scorer::positions`.
+#[derive(Clone, Copy, Debug)]
+pub struct MatchInfo<'a> {
+    /// Term frequency of the document, if the scorer matches a single term
+    /// and this information is available.
+    pub term_freq: Option<u32>,
+    /// Positions matched within the document. This is empty if the
+    /// scorer does not expose them.
+    pub positions: &'a [u32],
 }
 
 impl<'a, C: Collector> Collector for &'a mut C {
@@ -83,6 +158,14 @@ impl<'a, C: Collector> Collector for &'a mut C {
     fn requires_scoring(&self) -> bool {
         C::requires_scoring(self)
     }
+
+    fn requires_match_info(&self) -> bool {
+        C::requires_match_info(self)
+    }
+
+    fn collect_with_match(&mut self, doc: DocId, score: Score, match_info: &MatchInfo) {
+        C::collect_with_match(self, doc, score, match_info)
+    }
 }
 
 #[cfg(test)]
@@ -96,6 +179,9 @@ pub mod tests {
     use SegmentLocalId;
     use fastfield::FastFieldReader;
     use schema::Field;
+    use schema::*;
+    use query::TermQuery;
+    use Index;
 
     /// Stores all of the doc ids.
     /// This collector is only used for tests.
@@ -179,6 +265,60 @@ pub mod tests {
         }
     }
 
+    /// Keeps the segment reader, and fetches the stored documents
+    /// on demand.
+    struct StoredDocCollector {
+        segment_reader: Option<SegmentReader>,
+        docs: Vec<Document>,
+    }
+
+    impl Collector for StoredDocCollector {
+        fn set_segment(&mut self, _: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+            self.segment_reader = Some(reader.clone());
+            Ok(())
+        }
+
+        fn collect(&mut self, doc: DocId, _score: Score) {
+            let segment_reader = self.segment_reader.as_ref().unwrap();
+            self.docs.push(segment_reader.doc(doc).unwrap());
+        }
+
+        fn requires_scoring(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_collector_keeps_segment_reader() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b"));
+            index_writer.add_document(doc!(text_field => "c"));
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        let mut collector = StoredDocCollector {
+            segment_reader: None,
+            docs: Vec::new(),
+        };
+        searcher.search(&query, &mut collector).unwrap();
+        let texts: Vec<&str> = collector
+            .docs
+            .iter()
+            .map(|doc| doc.get_first(text_field).unwrap().text())
+            .collect();
+        assert_eq!(texts, vec!["a b", "a"]);
+    }
+
     #[bench]
     fn build_collector(b: &mut Bencher) {
         b.iter(|| {
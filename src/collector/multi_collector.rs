@@ -1,4 +1,4 @@
-use super::Collector;
+use super::{Collector, MatchInfo};
 use DocId;
 use Score;
 use Result;
@@ -42,6 +42,18 @@ impl<'a> Collector for MultiCollector<'a> {
             .iter()
             .any(|collector| collector.requires_scoring())
     }
+
+    fn requires_match_info(&self) -> bool {
+        self.collectors
+            .iter()
+            .any(|collector| collector.requires_match_info())
+    }
+
+    fn collect_with_match(&mut self, doc: DocId, score: Score, match_info: &MatchInfo) {
+        for collector in &mut self.collectors {
+            collector.collect_with_match(doc, score, match_info);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,343 @@
+use super::Collector;
+use common::BitSet;
+use core::InvertedIndexReader;
+use docset::DocSet;
+use schema::{Field, IndexRecordOption, Term};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+use termdict::{TermDictionary, TermStreamer};
+use DocId;
+use Result;
+use Score;
+use SegmentLocalId;
+use SegmentReader;
+
+/// Scoring of the significance of a term.
+///
+/// Both heuristics only retain the terms that are more frequent
+/// in the matching documents than in the whole index.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SignificanceHeuristic {
+    /// `(p_subset - p_superset) * p_subset / p_superset`, where `p_subset`
+    /// and `p_superset` are the ratios of the matching documents and of all
+    /// of the documents containing the term.
+    JLH,
+    /// Chi-square statistic of the contingency table of the documents
+    /// matching the query and the documents containing the term.
+    ChiSquare,
+}
+
+impl SignificanceHeuristic {
+    fn score(
+        &self,
+        subset_doc_count: u64,
+        subset_size: u64,
+        superset_doc_count: u64,
+        superset_size: u64,
+    ) -> f64 {
+        if subset_size == 0 || superset_size == 0 || superset_doc_count == 0 {
+            return 0f64;
+        }
+        let subset_ratio = subset_doc_count as f64 / subset_size as f64;
+        let superset_ratio = superset_doc_count as f64 / superset_size as f64;
+        if subset_ratio <= superset_ratio {
+            return 0f64;
+        }
+        match *self {
+            SignificanceHeuristic::JLH => {
+                (subset_ratio - superset_ratio) * subset_ratio / superset_ratio
+            }
+            SignificanceHeuristic::ChiSquare => {
+                // the matching documents are part of the superset.
+                let n11 = subset_doc_count as f64;
+                let n01 = (subset_size - subset_doc_count) as f64;
+                let n10 = superset_doc_count.saturating_sub(subset_doc_count) as f64;
+                let n00 = superset_size.saturating_sub(subset_size) as f64 - n10;
+                let n = n11 + n01 + n10 + n00;
+                let denominator = (n11 + n01) * (n11 + n10) * (n10 + n00) * (n01 + n00);
+                if denominator <= 0f64 {
+                    return 0f64;
+                }
+                let det = n11 * n00 - n10 * n01;
+                n * det * det / denominator
+            }
+        }
+    }
+}
+
+/// Term over-represented in the documents matching a query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignificantTerm {
+    /// The term.
+    pub term: Term,
+    /// Significance score of the term.
+    pub score: f64,
+    /// Number of matching documents containing the term.
+    pub subset_doc_count: u64,
+    /// Number of documents of the index containing the term.
+    pub superset_doc_count: u64,
+}
+
+/// Collector surfacing the terms of a field that are over-represented
+/// in the matching documents, compared to the whole index.
+///
+/// The background frequencies are those of the segments
+/// visited by the search. As for `Searcher::doc_freq`, they
+/// include the deleted documents.
+///
+/// Computing the frequencies of the terms among the matching documents
+/// requires going through the postings of all the terms of the field,
+/// for each segment with at least one match.
+/// This is affordable on fields with a moderate number of terms,
+/// e.g. the level, host or error code of a log entry.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{SchemaBuilder, STRING};
+/// use tantivy::{Index, Result, Term};
+/// use tantivy::collector::{SignificanceHeuristic, SignificantTermsCollector};
+/// use tantivy::query::TermQuery;
+/// use tantivy::schema::IndexRecordOption;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let level = schema_builder.add_text_field("level", STRING);
+///     let host = schema_builder.add_text_field("host", STRING);
+///     let index = Index::create_in_ram(schema_builder.build());
+///     {
+///         let mut index_writer = index.writer(3_000_000)?;
+///         for i in 0..100 {
+///             let host_name = format!("host{}", i % 4);
+///             index_writer.add_document(doc!(level => "info", host => host_name));
+///         }
+///         for _ in 0..10 {
+///             index_writer.add_document(doc!(level => "error", host => "host2"));
+///         }
+///         index_writer.commit()?;
+///     }
+///     index.load_searchers()?;
+///     let searcher = index.searcher();
+///
+///     let query = TermQuery::new(Term::from_field_text(level, "error"), IndexRecordOption::Basic);
+///     let heuristic = SignificanceHeuristic::JLH;
+///     let mut collector = SignificantTermsCollector::for_field(host, 1, heuristic);
+///     searcher.search(&query, &mut collector)?;
+///     let significant_terms = collector.harvest();
+///     assert_eq!(significant_terms[0].term, Term::from_field_text(host, "host2"));
+///     Ok(())
+/// }
+/// ```
+pub struct SignificantTermsCollector {
+    field: Field,
+    limit: usize,
+    heuristic: SignificanceHeuristic,
+    min_doc_count: u64,
+    subset_size: u64,
+    superset_size: u64,
+    subset_doc_counts: HashMap<Vec<u8>, u64>,
+    inverted_indexes: Vec<Arc<InvertedIndexReader>>,
+    current_docs: Option<BitSet>,
+}
+
+impl SignificantTermsCollector {
+    /// Creates a collector returning the `limit` most significant
+    /// terms of `field`, scored with the given heuristic.
+    pub fn for_field(
+        field: Field,
+        limit: usize,
+        heuristic: SignificanceHeuristic,
+    ) -> SignificantTermsCollector {
+        SignificantTermsCollector {
+            field,
+            limit,
+            heuristic,
+            min_doc_count: 3,
+            subset_size: 0,
+            superset_size: 0,
+            subset_doc_counts: HashMap::new(),
+            inverted_indexes: Vec::new(),
+            current_docs: None,
+        }
+    }
+
+    /// Sets the minimum number of matching documents a term
+    /// should appear in to be returned. Defaults to 3.
+    ///
+    /// Terms appearing in very few documents tend to
+    /// get high scores while being anecdotal.
+    pub fn set_min_doc_count(&mut self, min_doc_count: u64) {
+        self.min_doc_count = min_doc_count;
+    }
+
+    /// Counts the matching documents of the current
+    /// segment associated with each term.
+    fn finalize_segment(&mut self) {
+        let docs = match self.current_docs.take() {
+            Some(docs) => docs,
+            None => return,
+        };
+        if docs.len() == 0 {
+            return;
+        }
+        let inverted_index = self.inverted_indexes
+            .last()
+            .expect("An inverted index is set with the docs. This should never happen.");
+        let mut term_stream = inverted_index.terms().stream();
+        while term_stream.advance() {
+            let mut postings = inverted_index
+                .read_postings_from_terminfo(term_stream.value(), IndexRecordOption::Basic);
+            let mut doc_count = 0u64;
+            while postings.advance() {
+                if docs.contains(postings.doc()) {
+                    doc_count += 1;
+                }
+            }
+            if doc_count > 0 {
+                *self.subset_doc_counts
+                    .entry(term_stream.key().to_owned())
+                    .or_insert(0u64) += doc_count;
+            }
+        }
+    }
+
+    /// Returns the most significant terms, by decreasing score.
+    ///
+    /// Terms with a null score are omitted.
+    pub fn harvest(mut self) -> Vec<SignificantTerm> {
+        self.finalize_segment();
+        let mut significant_terms: Vec<SignificantTerm> = Vec::new();
+        for (term_bytes, &subset_doc_count) in &self.subset_doc_counts {
+            if subset_doc_count < self.min_doc_count {
+                continue;
+            }
+            let term = Term::from_field_bytes(self.field, term_bytes);
+            let superset_doc_count: u64 = self.inverted_indexes
+                .iter()
+                .map(|inverted_index| u64::from(inverted_index.doc_freq(&term)))
+                .sum();
+            let score = self.heuristic.score(
+                subset_doc_count,
+                self.subset_size,
+                superset_doc_count,
+                self.superset_size,
+            );
+            if score > 0f64 {
+                significant_terms.push(SignificantTerm {
+                    term,
+                    score,
+                    subset_doc_count,
+                    superset_doc_count,
+                });
+            }
+        }
+        significant_terms.sort_by(|left, right| {
+            right
+                .score
+                .partial_cmp(&left.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left.term.cmp(&right.term))
+        });
+        significant_terms.truncate(self.limit);
+        significant_terms
+    }
+}
+
+impl Collector for SignificantTermsCollector {
+    fn set_segment(&mut self, _: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        self.finalize_segment();
+        self.superset_size += u64::from(reader.max_doc());
+        self.inverted_indexes.push(reader.inverted_index(self.field));
+        self.current_docs = Some(BitSet::with_max_value(reader.max_doc()));
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _: Score) {
+        self.current_docs
+            .as_mut()
+            .expect("collect() was called before set_segment. This should never happen.")
+            .insert(doc);
+        self.subset_size += 1;
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{SignificanceHeuristic, SignificantTermsCollector};
+    use query::TermQuery;
+    use schema::*;
+    use Index;
+
+    #[test]
+    fn test_significance_heuristics() {
+        for &heuristic in &[SignificanceHeuristic::JLH, SignificanceHeuristic::ChiSquare] {
+            // as frequent in the subset as in the superset.
+            assert_eq!(heuristic.score(10, 100, 100, 1_000), 0f64);
+            // under-represented.
+            assert_eq!(heuristic.score(1, 100, 100, 1_000), 0f64);
+            let score = heuristic.score(50, 100, 100, 1_000);
+            assert!(score > 0f64);
+            assert!(heuristic.score(90, 100, 100, 1_000) > score);
+        }
+        let jlh = SignificanceHeuristic::JLH.score(50, 100, 100, 1_000);
+        assert!((jlh - 2f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_significant_terms_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let level = schema_builder.add_text_field("level", STRING);
+        let host = schema_builder.add_text_field("host", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..100 {
+                let host_name = format!("host{}", i % 4);
+                index_writer.add_document(doc!(level => "info", host => host_name));
+            }
+            index_writer.commit().unwrap();
+            for _ in 0..11 {
+                index_writer.add_document(doc!(level => "error", host => "host1"));
+            }
+            // host0 is less frequent among the errors than overall.
+            for _ in 0..3 {
+                index_writer.add_document(doc!(level => "error", host => "host0"));
+            }
+            // host4 is significant, but anecdotal.
+            index_writer.add_document(doc!(level => "error", host => "host4"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(level, "error"),
+            IndexRecordOption::Basic,
+        );
+        for &heuristic in &[SignificanceHeuristic::JLH, SignificanceHeuristic::ChiSquare] {
+            let mut collector = SignificantTermsCollector::for_field(host, 10, heuristic);
+            searcher.search(&query, &mut collector).unwrap();
+            let significant_terms = collector.harvest();
+            assert_eq!(significant_terms.len(), 1);
+            assert_eq!(significant_terms[0].term, Term::from_field_text(host, "host1"));
+            assert_eq!(significant_terms[0].subset_doc_count, 11);
+            assert_eq!(significant_terms[0].superset_doc_count, 36);
+        }
+        {
+            let mut collector =
+                SignificantTermsCollector::for_field(host, 10, SignificanceHeuristic::JLH);
+            collector.set_min_doc_count(1);
+            searcher.search(&query, &mut collector).unwrap();
+            let significant_terms = collector.harvest();
+            assert_eq!(significant_terms.len(), 2);
+            assert_eq!(significant_terms[0].term, Term::from_field_text(host, "host1"));
+            assert_eq!(significant_terms[1].term, Term::from_field_text(host, "host4"));
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::Collector;
+use super::{Collector, FruitCollector};
 use SegmentReader;
 use SegmentLocalId;
 use DocAddress;
@@ -8,7 +8,27 @@ use std::cmp::Ordering;
 use DocId;
 use Score;
 
+/// Order of the documents returned by the `TopCollector`.
+///
+/// Documents are sorted by decreasing score. Documents with the same
+/// score are sorted by increasing `DocAddress`, that is by segment
+/// ordinal first, and then by doc id.
+/// The order of the results of a query is therefore deterministic,
+/// as long as the searcher is the same, which makes it possible to
+/// paginate them.
+///
+/// `NaN` scores are considered equal to any other score.
+pub fn compare_score_docs(left: &(Score, DocAddress), right: &(Score, DocAddress)) -> Ordering {
+    right
+        .0
+        .partial_cmp(&left.0)
+        .unwrap_or(Ordering::Equal)
+        .then(left.1.cmp(&right.1))
+}
+
 // Rust heap is a max-heap and we need a min heap.
+// The `GlobalScoredDoc` with the greatest ordering is hence
+// the worst document.
 #[derive(Clone, Copy)]
 struct GlobalScoredDoc {
     score: Score,
@@ -24,10 +44,10 @@ impl PartialOrd for GlobalScoredDoc {
 impl Ord for GlobalScoredDoc {
     #[inline]
     fn cmp(&self, other: &GlobalScoredDoc) -> Ordering {
-        other
-            .score
-            .partial_cmp(&self.score)
-            .unwrap_or_else(|| other.doc_address.cmp(&self.doc_address))
+        compare_score_docs(
+            &(self.score, self.doc_address),
+            &(other.score, other.doc_address),
+        )
     }
 }
 
@@ -39,15 +59,67 @@ impl PartialEq for GlobalScoredDoc {
 
 impl Eq for GlobalScoredDoc {}
 
+/// Defines how the `TopCollector` counts the documents matching the query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TotalHitsMode {
+    /// Counts all of the matching documents.
+    Exact,
+    /// Counts the matching documents up to the given threshold.
+    UpTo(usize),
+    /// Does not count the matching documents.
+    Disabled,
+}
+
+impl Default for TotalHitsMode {
+    fn default() -> TotalHitsMode {
+        TotalHitsMode::Exact
+    }
+}
+
+/// Number of documents matching the query, as counted
+/// by the `TopCollector` with its `TotalHitsMode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TotalHits {
+    /// Exact number of matching documents.
+    Exact(usize),
+    /// The counting stopped at the threshold of `TotalHitsMode::UpTo`:
+    /// more than this number of documents match.
+    AtLeast(usize),
+    /// The matching documents were not counted.
+    Unknown,
+}
+
+/// Result of a top-k search, as returned by `TopCollector::top_docs`.
+#[derive(Clone, Debug)]
+pub struct TopDocs {
+    /// K best documents, sorted by decreasing score.
+    pub score_docs: Vec<(Score, DocAddress)>,
+    /// Number of documents matching the query.
+    pub total_hits: TotalHits,
+}
+
 /// The Top Collector keeps track of the K documents
 /// with the best scores.
 ///
 /// The implementation is based on a `BinaryHeap`.
 /// The theorical complexity is `O(n log K)`.
+///
+/// Documents with equal scores are ordered by segment ordinal and doc id,
+/// see `compare_score_docs`.
+///
+/// By default, it also counts the total number of matching documents.
+/// See `TotalHitsMode`.
+///
+/// Results can be paginated by skipping the best documents
+/// of the previous pages with `.and_offset(...)`.
+#[derive(Clone)]
 pub struct TopCollector {
     limit: usize,
+    offset: usize,
     heap: BinaryHeap<GlobalScoredDoc>,
     segment_id: u32,
+    total_hits_mode: TotalHitsMode,
+    num_hits: usize,
 }
 
 impl TopCollector {
@@ -61,12 +133,72 @@ impl TopCollector {
         }
         TopCollector {
             limit: limit,
+            offset: 0,
             heap: BinaryHeap::with_capacity(limit),
             segment_id: 0,
+            total_hits_mode: TotalHitsMode::default(),
+            num_hits: 0,
+        }
+    }
+
+    /// Creates a top collector counting the total number of hits
+    /// according to `total_hits_mode`.
+    ///
+    /// # Panics
+    /// The method panics if limit is 0
+    pub fn with_limit_and_total_hits(
+        limit: usize,
+        total_hits_mode: TotalHitsMode,
+    ) -> TopCollector {
+        let mut top_collector = TopCollector::with_limit(limit);
+        top_collector.total_hits_mode = total_hits_mode;
+        top_collector
+    }
+
+    /// Skips the `offset` best documents, and returns the `limit`
+    /// following ones.
+    ///
+    /// For instance, `TopCollector::with_limit(20).and_offset(40)`
+    /// returns the third page of 20 results.
+    /// The collector keeps track of the `limit + offset` best documents.
+    ///
+    /// The offset is applied by `.score_docs()`, `.top_docs()`, and
+    /// when merging fruits. The fruit returned by `.harvest()` holds the
+    /// `limit + offset` best documents, so that the fruits of collectors
+    /// configured alike, e.g. one per segment, are merged before the offset is skipped.
+    pub fn and_offset(mut self, offset: usize) -> TopCollector {
+        self.offset = offset;
+        self.heap.reserve(offset);
+        self
+    }
+
+    /// Returns the number of documents that went through the collector,
+    /// as counted with the `TotalHitsMode` of the collector.
+    pub fn total_hits(&self) -> TotalHits {
+        match self.total_hits_mode {
+            TotalHitsMode::Exact => TotalHits::Exact(self.num_hits),
+            TotalHitsMode::UpTo(threshold) => {
+                if self.num_hits > threshold {
+                    TotalHits::AtLeast(threshold)
+                } else {
+                    TotalHits::Exact(self.num_hits)
+                }
+            }
+            TotalHitsMode::Disabled => TotalHits::Unknown,
+        }
+    }
+
+    /// Returns the K best documents, sorted in decreasing order,
+    /// together with the total number of hits.
+    pub fn top_docs(&self) -> TopDocs {
+        TopDocs {
+            score_docs: self.score_docs(),
+            total_hits: self.total_hits(),
         }
     }
 
-    /// Returns K best documents sorted in decreasing order.
+    /// Returns K best documents sorted in decreasing order,
+    /// after skipping the offset.
     ///
     /// Calling this method triggers the sort.
     /// The result of the sort is not cached.
@@ -77,11 +209,17 @@ impl TopCollector {
             .collect()
     }
 
-    /// Returns K best ScoredDocument sorted in decreasing order.
+    /// Returns K best ScoredDocument sorted in decreasing order,
+    /// after skipping the offset.
     ///
     /// Calling this method triggers the sort.
     /// The result of the sort is not cached.
     pub fn score_docs(&self) -> Vec<(Score, DocAddress)> {
+        self.sorted_score_docs().into_iter().skip(self.offset).collect()
+    }
+
+    /// Returns the `limit + offset` best documents, sorted in decreasing order.
+    fn sorted_score_docs(&self) -> Vec<(Score, DocAddress)> {
         let mut scored_docs: Vec<GlobalScoredDoc> = self.heap.iter().cloned().collect();
         scored_docs.sort();
         scored_docs
@@ -90,11 +228,11 @@ impl TopCollector {
             .collect()
     }
 
-    /// Return true iff at least K documents have gone through
-    /// the collector.
+    /// Return true iff at least K documents, plus the offset,
+    /// have gone through the collector.
     #[inline]
     pub fn at_capacity(&self) -> bool {
-        self.heap.len() >= self.limit
+        self.heap.len() >= self.limit + self.offset
     }
 }
 
@@ -105,17 +243,31 @@ impl Collector for TopCollector {
     }
 
     fn collect(&mut self, doc: DocId, score: Score) {
+        match self.total_hits_mode {
+            TotalHitsMode::Exact => self.num_hits += 1,
+            TotalHitsMode::UpTo(threshold) => {
+                // counting one hit past the threshold tells
+                // exactly `threshold` hits from more hits.
+                if self.num_hits <= threshold {
+                    self.num_hits += 1;
+                }
+            }
+            TotalHitsMode::Disabled => {}
+        }
         if self.at_capacity() {
             // It's ok to unwrap as long as a limit of 0 is forbidden.
             let limit_doc: GlobalScoredDoc = *self.heap
                 .peek()
                 .expect("Top collector with size 0 is forbidden");
-            if limit_doc.score < score {
+            let wrapped_doc = GlobalScoredDoc {
+                score: score,
+                doc_address: DocAddress(self.segment_id, doc),
+            };
+            if wrapped_doc < limit_doc {
                 let mut mut_head = self.heap
                     .peek_mut()
                     .expect("Top collector with size 0 is forbidden");
-                mut_head.score = score;
-                mut_head.doc_address = DocAddress(self.segment_id, doc);
+                *mut_head = wrapped_doc;
             }
         } else {
             let wrapped_doc = GlobalScoredDoc {
@@ -131,6 +283,69 @@ impl Collector for TopCollector {
     }
 }
 
+impl TotalHits {
+    fn merge(self, other: TotalHits) -> TotalHits {
+        match (self, other) {
+            (TotalHits::Unknown, _) | (_, TotalHits::Unknown) => TotalHits::Unknown,
+            (TotalHits::Exact(left), TotalHits::Exact(right)) => TotalHits::Exact(left + right),
+            (TotalHits::AtLeast(left), TotalHits::Exact(right))
+            | (TotalHits::Exact(left), TotalHits::AtLeast(right))
+            | (TotalHits::AtLeast(left), TotalHits::AtLeast(right)) => {
+                TotalHits::AtLeast(left + right)
+            }
+        }
+    }
+}
+
+impl FruitCollector for TopCollector {
+    type Fruit = TopDocs;
+
+    /// Returns the `limit + offset` best documents: the offset
+    /// is only skipped once the fruits are merged.
+    fn harvest(self) -> TopDocs {
+        TopDocs {
+            score_docs: self.sorted_score_docs(),
+            total_hits: self.total_hits(),
+        }
+    }
+
+    /// Keeps the K best documents of all of the fruits, after skipping the offset,
+    /// and sums their total hits.
+    fn merge_fruits(&self, fruits: Vec<TopDocs>) -> TopDocs {
+        let mut total_hits = match self.total_hits_mode {
+            TotalHitsMode::Disabled => TotalHits::Unknown,
+            _ => TotalHits::Exact(0),
+        };
+        let mut scored_docs: Vec<GlobalScoredDoc> = Vec::new();
+        for fruit in fruits {
+            total_hits = total_hits.merge(fruit.total_hits);
+            scored_docs.extend(
+                fruit
+                    .score_docs
+                    .into_iter()
+                    .map(|(score, doc_address)| GlobalScoredDoc { score, doc_address }),
+            );
+        }
+        scored_docs.sort();
+        if let TotalHitsMode::UpTo(threshold) = self.total_hits_mode {
+            total_hits = match total_hits {
+                TotalHits::Exact(count) if count > threshold => TotalHits::AtLeast(threshold),
+                TotalHits::AtLeast(count) if count >= threshold => TotalHits::AtLeast(threshold),
+                total_hits => total_hits,
+            };
+        }
+        TopDocs {
+            score_docs: scored_docs
+                .into_iter()
+                .skip(self.offset)
+                .take(self.limit)
+                .map(|GlobalScoredDoc { score, doc_address }| (score, doc_address))
+                .collect(),
+            total_hits,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -181,6 +396,110 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_top_collector_total_hits() {
+        let collect = |total_hits_mode: TotalHitsMode| {
+            let mut top_collector = TopCollector::with_limit_and_total_hits(2, total_hits_mode);
+            for doc in 0..5 {
+                top_collector.collect(doc, doc as Score);
+            }
+            top_collector.top_docs()
+        };
+        let top_docs = collect(TotalHitsMode::Exact);
+        assert_eq!(top_docs.total_hits, TotalHits::Exact(5));
+        assert_eq!(top_docs.score_docs.len(), 2);
+        assert_eq!(collect(TotalHitsMode::UpTo(3)).total_hits, TotalHits::AtLeast(3));
+        assert_eq!(collect(TotalHitsMode::UpTo(4)).total_hits, TotalHits::AtLeast(4));
+        assert_eq!(collect(TotalHitsMode::UpTo(5)).total_hits, TotalHits::Exact(5));
+        assert_eq!(collect(TotalHitsMode::UpTo(10)).total_hits, TotalHits::Exact(5));
+        let top_docs = collect(TotalHitsMode::Disabled);
+        assert_eq!(top_docs.total_hits, TotalHits::Unknown);
+        assert_eq!(top_docs.score_docs[0].1.doc(), 4);
+    }
+
+    #[test]
+    fn test_top_collector_equal_scores() {
+        let collect = |docs: &[(u32, DocId)]| {
+            let mut top_collector = TopCollector::with_limit(3);
+            for &(segment_id, doc) in docs {
+                top_collector.segment_id = segment_id;
+                top_collector.collect(doc, 1.0);
+            }
+            top_collector.docs()
+        };
+        let expected = vec![DocAddress(0, 2), DocAddress(0, 4), DocAddress(1, 1)];
+        assert_eq!(collect(&[(0, 2), (0, 4), (1, 1), (1, 3)]), expected);
+        // the order in which the documents are collected does not matter.
+        assert_eq!(collect(&[(1, 3), (1, 1), (0, 4), (0, 2)]), expected);
+        assert_eq!(
+            compare_score_docs(&(1.0, DocAddress(0, 7)), &(1.0, DocAddress(1, 0))),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_score_docs(&(0.5, DocAddress(0, 7)), &(1.0, DocAddress(1, 0))),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_top_collector_merge_fruits() {
+        let mut left = TopCollector::with_limit(2);
+        left.collect(1, 0.8);
+        left.collect(3, 0.2);
+        left.collect(5, 0.5);
+        let mut right = TopCollector::with_limit(2);
+        right.segment_id = 1;
+        right.collect(2, 0.6);
+        let top_collector = TopCollector::with_limit(2);
+        let top_docs = top_collector.merge_fruits(vec![left.harvest(), right.harvest()]);
+        assert_eq!(top_docs.total_hits, TotalHits::Exact(4));
+        assert_eq!(
+            top_docs.score_docs,
+            vec![(0.8, DocAddress(0, 1)), (0.6, DocAddress(1, 2))]
+        );
+    }
+
+    #[test]
+    fn test_top_collector_offset() {
+        let mut top_collector = TopCollector::with_limit(2).and_offset(3);
+        for doc in 0..10 {
+            top_collector.collect(doc, doc as Score);
+        }
+        let docs: Vec<DocId> = top_collector
+            .docs()
+            .into_iter()
+            .map(|doc_address| doc_address.doc())
+            .collect();
+        assert_eq!(docs, vec![6, 5]);
+        assert_eq!(top_collector.top_docs().total_hits, TotalHits::Exact(10));
+
+        let mut last_page = TopCollector::with_limit(2).and_offset(3);
+        for doc in 0..4 {
+            last_page.collect(doc, doc as Score);
+        }
+        assert_eq!(last_page.score_docs(), vec![(0.0, DocAddress(0, 0))]);
+    }
+
+    #[test]
+    fn test_top_collector_merge_fruits_with_offset() {
+        let top_collector = TopCollector::with_limit(2).and_offset(1);
+        let mut left = top_collector.clone();
+        left.collect(1, 0.8);
+        left.collect(3, 0.2);
+        left.collect(5, 0.5);
+        let mut right = top_collector.clone();
+        right.segment_id = 1;
+        right.collect(2, 0.6);
+        // the offset is not skipped within each fruit.
+        let left_top_docs = left.harvest();
+        assert_eq!(left_top_docs.score_docs.len(), 3);
+        let top_docs = top_collector.merge_fruits(vec![left_top_docs, right.harvest()]);
+        assert_eq!(
+            top_docs.score_docs,
+            vec![(0.6, DocAddress(1, 2)), (0.5, DocAddress(0, 5))]
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_top_0() {
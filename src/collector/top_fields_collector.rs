@@ -0,0 +1,192 @@
+use super::{compare_score_docs, Collector, FruitCollector, TopCollector};
+use schema::{Field, NamedFieldDocument};
+use std::collections::HashMap;
+use DocAddress;
+use DocId;
+use Result;
+use Score;
+use SegmentLocalId;
+use SegmentReader;
+
+/// Hit returned by the `TopFieldsCollector`.
+///
+/// It serializes as `{"score": ..., "doc": {"field_name": [values]}}`.
+#[derive(Serialize)]
+pub struct ProjectedHit {
+    /// Score of the document.
+    pub score: Score,
+    /// Address of the document, only meaningful for the searcher
+    /// the hit was collected with.
+    #[serde(skip_serializing)]
+    pub doc_address: DocAddress,
+    /// Projection of the document on the requested stored fields.
+    pub doc: NamedFieldDocument,
+}
+
+/// Collector keeping the K documents with the best scores, and
+/// fetching the given stored fields for each of them.
+///
+/// This saves fetching the documents by address once the search is over.
+/// The fields that are not stored are ignored.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{SchemaBuilder, TEXT, STORED};
+/// use tantivy::{Index, Result};
+/// use tantivy::collector::TopFieldsCollector;
+/// use tantivy::query::QueryParser;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let title = schema_builder.add_text_field("title", TEXT | STORED);
+///     let body = schema_builder.add_text_field("body", TEXT | STORED);
+///     let index = Index::create_in_ram(schema_builder.build());
+///     {
+///         let mut index_writer = index.writer_with_num_threads(1, 3_000_000)?;
+///         index_writer.add_document(doc!(title => "The Diary of Muadib", body => "..."));
+///         index_writer.commit()?;
+///     }
+///     index.load_searchers()?;
+///     let searcher = index.searcher();
+///     let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+///     let collector = TopFieldsCollector::with_limit(10, vec![title]);
+///     let hits = searcher.search_fruit(&*query, collector)??;
+///     assert_eq!(hits.len(), 1);
+///     assert!(hits[0].doc.0.get("body").is_none());
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone)]
+pub struct TopFieldsCollector {
+    top_collector: TopCollector,
+    limit: usize,
+    fields: Vec<Field>,
+    segment_readers: HashMap<SegmentLocalId, SegmentReader>,
+}
+
+impl TopFieldsCollector {
+    /// Creates a collector returning the `limit` best documents,
+    /// projected on `fields`.
+    ///
+    /// # Panics
+    /// The method panics if limit is 0
+    pub fn with_limit(limit: usize, fields: Vec<Field>) -> TopFieldsCollector {
+        TopFieldsCollector {
+            top_collector: TopCollector::with_limit(limit),
+            limit,
+            fields,
+            segment_readers: HashMap::new(),
+        }
+    }
+
+    fn project(&self, score: Score, doc_address: DocAddress) -> Result<ProjectedHit> {
+        let segment_reader = &self.segment_readers[&doc_address.segment_ord()];
+        let doc = segment_reader.doc_fields(doc_address.doc(), &self.fields)?;
+        Ok(ProjectedHit {
+            score,
+            doc_address,
+            doc: segment_reader.schema().to_named_doc(&doc),
+        })
+    }
+}
+
+impl Collector for TopFieldsCollector {
+    fn set_segment(&mut self, segment_id: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        self.segment_readers.insert(segment_id, reader.clone());
+        self.top_collector.set_segment(segment_id, reader)
+    }
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.top_collector.collect(doc, score);
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+}
+
+impl FruitCollector for TopFieldsCollector {
+    type Fruit = Result<Vec<ProjectedHit>>;
+
+    /// Fetches the stored fields of the best documents,
+    /// sorted by decreasing score.
+    fn harvest(self) -> Result<Vec<ProjectedHit>> {
+        self.top_collector
+            .score_docs()
+            .into_iter()
+            .map(|(score, doc_address)| self.project(score, doc_address))
+            .collect()
+    }
+
+    fn merge_fruits(&self, fruits: Vec<Result<Vec<ProjectedHit>>>) -> Result<Vec<ProjectedHit>> {
+        let mut hits = Vec::new();
+        for fruit in fruits {
+            hits.extend(fruit?);
+        }
+        hits.sort_by(|left, right| {
+            compare_score_docs(
+                &(left.score, left.doc_address),
+                &(right.score, right.doc_address),
+            )
+        });
+        hits.truncate(self.limit);
+        Ok(hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::TopFieldsCollector;
+    use collector::FruitCollector;
+    use query::TermQuery;
+    use schema::*;
+    use serde_json;
+    use DocAddress;
+    use Index;
+
+    #[test]
+    fn test_top_fields_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let body = schema_builder.add_text_field("body", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "a", body => "first"));
+            index_writer.add_document(doc!(title => "b", body => "second"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(title => "b", body => "third"));
+            index_writer.add_document(doc!(title => "a", body => "fourth"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(title, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        // both hits have the same score, and are ordered by `DocAddress`.
+        let collector = TopFieldsCollector::with_limit(1, vec![body]);
+        let hits = searcher.search_fruit(&query, collector).unwrap().unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].doc_address, DocAddress(0, 0));
+        let hit_json = serde_json::to_string(&hits[0].doc).unwrap();
+        assert_eq!(hit_json, r#"{"body":["first"]}"#);
+        let collector = TopFieldsCollector::with_limit(3, vec![title, body]);
+        let hits = searcher.search_fruit(&query, collector).unwrap().unwrap();
+        let doc_addresses: Vec<DocAddress> = hits.iter().map(|hit| hit.doc_address).collect();
+        assert_eq!(doc_addresses, vec![DocAddress(0, 0), DocAddress(1, 1)]);
+        assert_eq!(hits[0].score, hits[1].score);
+        assert_eq!(hits[0].doc.0.len(), 2);
+        let hit_json = serde_json::to_string(&hits[1].doc).unwrap();
+        assert_eq!(hit_json, r#"{"body":["fourth"],"title":["a"]}"#);
+        let merged = TopFieldsCollector::with_limit(1, vec![])
+            .merge_fruits(vec![Ok(hits)])
+            .unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].doc_address, DocAddress(0, 0));
+    }
+}
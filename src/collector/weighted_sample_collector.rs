@@ -0,0 +1,213 @@
+use super::Collector;
+use fastfield::FastFieldReader;
+use schema::Field;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use DocAddress;
+use DocId;
+use Result;
+use Score;
+use SegmentLocalId;
+use SegmentReader;
+
+// Sampled document, ordered by increasing key so that the
+// `BinaryHeap` keeps the document with the lowest key at its top.
+struct SampledDoc {
+    key: f64,
+    doc_address: DocAddress,
+}
+
+impl PartialOrd for SampledDoc {
+    fn partial_cmp(&self, other: &SampledDoc) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SampledDoc {
+    fn cmp(&self, other: &SampledDoc) -> Ordering {
+        other
+            .key
+            .partial_cmp(&self.key)
+            .unwrap_or_else(|| other.doc_address.cmp(&self.doc_address))
+    }
+}
+
+impl PartialEq for SampledDoc {
+    fn eq(&self, other: &SampledDoc) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SampledDoc {}
+
+/// `SplitMix64` finalizer, used to derive a pseudo-random
+/// number from the seed and the address of a document.
+///
+/// It is also used to hash the values counted by the `DistinctCountCollector`.
+pub(crate) fn mix(mut val: u64) -> u64 {
+    val = val.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    val = (val ^ (val >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    val = (val ^ (val >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    val ^ (val >> 31)
+}
+
+/// Collector sampling the matching documents, with a probability
+/// proportional to the weight stored in a `u64` fast field.
+///
+/// The collector keeps `sample_size` documents, sampled without
+/// replacement among the documents passed to the collector.
+/// Documents with a weight of `0` are never sampled.
+///
+/// The sampling is deterministic for a given seed and a given searcher,
+/// which makes it possible to reproduce a sample, e.g. to build a
+/// training dataset. Use a different seed to draw a different sample.
+///
+/// The implementation is the A-Res reservoir algorithm
+/// of Efraimidis and Spirakis: each document is given the random
+/// key `u^(1/weight)` and the documents with the highest keys are kept.
+pub struct WeightedSampleCollector {
+    weight_field: Field,
+    sample_size: usize,
+    seed: u64,
+    heap: BinaryHeap<SampledDoc>,
+    segment_id: SegmentLocalId,
+    ff_reader: Option<FastFieldReader<u64>>,
+}
+
+impl WeightedSampleCollector {
+    /// Creates a collector sampling `sample_size` documents, weighted by
+    /// the `u64` fast field `weight_field`.
+    ///
+    /// # Panics
+    /// The method panics if sample_size is 0
+    pub fn new(weight_field: Field, sample_size: usize, seed: u64) -> WeightedSampleCollector {
+        if sample_size < 1 {
+            panic!("Sample size must be strictly greater than 0.");
+        }
+        WeightedSampleCollector {
+            weight_field,
+            sample_size,
+            seed,
+            heap: BinaryHeap::with_capacity(sample_size),
+            segment_id: 0,
+            ff_reader: None,
+        }
+    }
+
+    /// Returns the sampled documents.
+    ///
+    /// The documents are sorted by decreasing key: any prefix of the
+    /// result is itself a weighted sample of the documents.
+    pub fn docs(&self) -> Vec<DocAddress> {
+        let mut sampled_docs: Vec<&SampledDoc> = self.heap.iter().collect();
+        sampled_docs.sort();
+        sampled_docs
+            .into_iter()
+            .map(|sampled_doc| sampled_doc.doc_address)
+            .collect()
+    }
+
+    /// Returns a uniformly distributed number in `]0, 1[`.
+    fn random(&self, doc: DocId) -> f64 {
+        let doc_key = (u64::from(self.segment_id) << 32) | u64::from(doc);
+        let bits = mix(self.seed ^ mix(doc_key)) >> 11;
+        (bits as f64 + 0.5) / (1u64 << 53) as f64
+    }
+}
+
+impl Collector for WeightedSampleCollector {
+    fn set_segment(&mut self, segment_id: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        self.segment_id = segment_id;
+        self.ff_reader = Some(reader.fast_field_reader(self.weight_field)?);
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _: Score) {
+        let weight = self.ff_reader
+            .as_ref()
+            .expect("collect() was called before set_segment. This should never happen.")
+            .get(doc);
+        if weight == 0 {
+            return;
+        }
+        // `ln(u^(1/w)) = ln(u) / w` preserves the order of the keys.
+        let key = self.random(doc).ln() / weight as f64;
+        let sampled_doc = SampledDoc {
+            key,
+            doc_address: DocAddress(self.segment_id, doc),
+        };
+        if self.heap.len() < self.sample_size {
+            self.heap.push(sampled_doc);
+        } else if let Some(mut head) = self.heap.peek_mut() {
+            if head.key < key {
+                *head = sampled_doc;
+            }
+        }
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::WeightedSampleCollector;
+    use query::AllQuery;
+    use schema::*;
+    use Index;
+
+    #[test]
+    fn test_weighted_sample_collector() {
+        let mut schema_builder = SchemaBuilder::default();
+        let weight = schema_builder.add_u64_field("weight", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0u64..100u64 {
+                // only the first 10 documents can be sampled.
+                index_writer.add_document(doc!(weight => if i < 10 { 1u64 } else { 0u64 }));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let sample = |sample_size: usize, seed: u64| {
+            let mut collector = WeightedSampleCollector::new(weight, sample_size, seed);
+            searcher.search(&AllQuery, &mut collector).unwrap();
+            collector.docs()
+        };
+        let docs = sample(5, 1);
+        assert_eq!(docs.len(), 5);
+        assert!(docs.iter().all(|doc_address| doc_address.doc() < 10));
+        assert_eq!(sample(5, 1), docs);
+        assert_eq!(&sample(3, 1)[..], &docs[..3]);
+        assert_eq!(sample(20, 1).len(), 10);
+    }
+
+    #[test]
+    fn test_weighted_sample_collector_distribution() {
+        let mut schema_builder = SchemaBuilder::default();
+        let weight = schema_builder.add_u64_field("weight", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(weight => 1u64));
+            index_writer.add_document(doc!(weight => 9u64));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut heavy_count = 0;
+        for seed in 0..1_000 {
+            let mut collector = WeightedSampleCollector::new(weight, 1, seed);
+            searcher.search(&AllQuery, &mut collector).unwrap();
+            if collector.docs()[0].doc() == 1 {
+                heavy_count += 1;
+            }
+        }
+        // the heavy document is expected to be sampled 90% of the time.
+        assert!(heavy_count > 850 && heavy_count < 950);
+    }
+}
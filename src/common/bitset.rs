@@ -190,6 +190,9 @@ impl BitSet {
     /// Reminder: the tiny set with the bucket `bucket`, represents the
     /// elements from `bucket * 64` to `(bucket+1) * 64`.
     pub(crate) fn first_non_empty_bucket(&self, bucket: u32) -> Option<u32> {
+        if bucket >= self.num_buckets() {
+            return None;
+        }
         self.tinysets[bucket as usize..]
             .iter()
             .cloned()
@@ -201,6 +204,11 @@ impl BitSet {
         self.max_value
     }
 
+    /// Returns the number of `TinySet` buckets of the `BitSet`.
+    pub(crate) fn num_buckets(&self) -> u32 {
+        self.tinysets.len() as u32
+    }
+
     /// Returns the tiny bitset representing the
     /// the set restricted to the number range from
     /// `bucket * 64` to `(bucket + 1) * 64`.
@@ -175,6 +175,15 @@ impl CompositeFile {
             })
             .map(|&(from, to)| self.data.slice(from, to))
     }
+
+    /// Returns the number of bytes used by each field.
+    pub fn field_num_bytes(&self) -> HashMap<Field, usize> {
+        let mut field_num_bytes = HashMap::new();
+        for (file_addr, &(from, to)) in &self.offsets_index {
+            *field_num_bytes.entry(file_addr.field).or_insert(0) += to - from;
+        }
+        field_num_bytes
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,226 @@
+use common::BinarySerializable;
+use crc::crc32::{self, Hasher32};
+use directory::ReadOnlySource;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::{self, Seek, SeekFrom, Write};
+
+/// Magic number identifying a tantivy footer.
+const FOOTER_MAGIC_NUMBER: u32 = 0x7a4e_7479;
+
+/// Version of the format of the segment files.
+///
+/// It should be incremented every time the format of one
+/// of the segment component changes.
+///
+/// The version is also recorded in the `SegmentMeta`. Segments
+/// written before footers were introduced record no version,
+/// which is read as 0: their files have no footer.
+pub const FOOTER_VERSION: u32 = 1;
+
+/// Length of the footer, in bytes.
+///
+/// The footer is made of `[crc32: u32][version: u32][magic number: u32]`.
+pub const FOOTER_LEN: usize = 12;
+
+fn corrupted(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Error wrapped in an `io::Error` when a file was written
+/// with an unsupported file format version.
+#[derive(Debug, Clone, Copy)]
+pub struct UnsupportedVersion {
+    /// Version of the format of the file.
+    pub version: u32,
+    /// Version supported by this version of tantivy.
+    pub supported: u32,
+}
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unsupported file format version {}. This version of tantivy supports version {}.",
+            self.version, self.supported
+        )
+    }
+}
+
+impl StdError for UnsupportedVersion {
+    fn description(&self) -> &str {
+        "unsupported file format version"
+    }
+}
+
+/// Footer appended to all of the segment files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Footer {
+    /// CRC32 (IEEE) checksum of the file content, footer excluded.
+    pub crc: u32,
+    /// Version of the format of the file.
+    pub version: u32,
+}
+
+impl Footer {
+    fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.crc.serialize(writer)?;
+        self.version.serialize(writer)?;
+        FOOTER_MAGIC_NUMBER.serialize(writer)
+    }
+
+    /// Reads the footer of a file, and checks its magic number and version.
+    ///
+    /// Returns the footer, and the content of the file without its footer.
+    /// The checksum is not verified. See `Footer::verify`.
+    pub fn extract(source: &ReadOnlySource) -> io::Result<(Footer, ReadOnlySource)> {
+        let data_len = source
+            .len()
+            .checked_sub(FOOTER_LEN)
+            .ok_or_else(|| corrupted("File is too short to contain a footer.".to_string()))?;
+        let mut footer_bytes: &[u8] = &source.as_slice()[data_len..];
+        let crc = u32::deserialize(&mut footer_bytes)?;
+        let version = u32::deserialize(&mut footer_bytes)?;
+        let magic_number = u32::deserialize(&mut footer_bytes)?;
+        if magic_number != FOOTER_MAGIC_NUMBER {
+            return Err(corrupted(format!(
+                "Footer magic number is invalid ({:x}). The file is either truncated or corrupted.",
+                magic_number
+            )));
+        }
+        if version != FOOTER_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                UnsupportedVersion {
+                    version,
+                    supported: FOOTER_VERSION,
+                },
+            ));
+        }
+        Ok((Footer { crc, version }, source.slice_to(data_len)))
+    }
+
+    /// Checks that the checksum of the footer matches the given file content.
+    pub fn verify(&self, data: &[u8]) -> io::Result<()> {
+        let mut digest = crc32::Digest::new(crc32::IEEE);
+        digest.write(data);
+        let crc = digest.sum32();
+        if crc != self.crc {
+            return Err(corrupted(format!(
+                "Checksum mismatch: expected {:x}, computed {:x}.",
+                self.crc, crc
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Writer appending a `Footer` to the data written through it.
+///
+/// Tantivy's serializers may flush in the middle of a file,
+/// so the footer is written on every flush, and overwritten
+/// when more data gets written afterwards.
+///
+/// Seeking is not supported.
+pub struct FooterProxy<W: Write + Seek> {
+    writer: W,
+    digest: crc32::Digest,
+    data_len: u64,
+    footer_written: bool,
+}
+
+impl<W: Write + Seek> FooterProxy<W> {
+    /// Wraps a writer.
+    pub fn new(writer: W) -> FooterProxy<W> {
+        FooterProxy {
+            writer,
+            digest: crc32::Digest::new(crc32::IEEE),
+            data_len: 0u64,
+            footer_written: false,
+        }
+    }
+}
+
+impl<W: Write + Seek> Write for FooterProxy<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.footer_written {
+            self.writer.seek(SeekFrom::Start(self.data_len))?;
+            self.footer_written = false;
+        }
+        let written = self.writer.write(buf)?;
+        self.digest.write(&buf[..written]);
+        self.data_len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.footer_written {
+            let footer = Footer {
+                crc: self.digest.sum32(),
+                version: FOOTER_VERSION,
+            };
+            footer.serialize(&mut self.writer)?;
+            self.footer_written = true;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W: Write + Seek> Seek for FooterProxy<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.data_len),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Seeking is not supported on segment files.",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Cursor;
+
+    fn write_with_footer(chunks: &[&[u8]]) -> ReadOnlySource {
+        let mut footer_proxy = FooterProxy::new(Cursor::new(Vec::new()));
+        for chunk in chunks {
+            footer_proxy.write_all(chunk).unwrap();
+            footer_proxy.flush().unwrap();
+        }
+        ReadOnlySource::from(footer_proxy.writer.into_inner())
+    }
+
+    #[test]
+    fn test_footer() {
+        let source = write_with_footer(&[b"hello", b" happy", b" tax payer"]);
+        assert_eq!(source.len(), "hello happy tax payer".len() + FOOTER_LEN);
+        let (footer, data) = Footer::extract(&source).unwrap();
+        assert_eq!(data.as_slice(), b"hello happy tax payer");
+        assert_eq!(footer.version, FOOTER_VERSION);
+        assert!(footer.verify(data.as_slice()).is_ok());
+        assert!(footer.verify(b"hello happy tax payeR").is_err());
+    }
+
+    #[test]
+    fn test_footer_unsupported_version() {
+        let mut data = write_with_footer(&[b"hello"]).as_slice().to_owned();
+        data[5 + 4] ^= 1u8;
+        let err = Footer::extract(&ReadOnlySource::from(data)).unwrap_err();
+        let unsupported_version = err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<UnsupportedVersion>())
+            .unwrap();
+        assert_eq!(unsupported_version.version, FOOTER_VERSION ^ 1u32);
+        assert_eq!(unsupported_version.supported, FOOTER_VERSION);
+    }
+
+    #[test]
+    fn test_footer_truncated() {
+        let source = write_with_footer(&[b"hello"]);
+        assert!(Footer::extract(&source.slice_to(source.len() - 1)).is_err());
+        assert!(Footer::extract(&source.slice_to(4)).is_err());
+    }
+}
@@ -3,10 +3,12 @@ mod timer;
 mod vint;
 mod counting_writer;
 mod composite_file;
+mod footer;
 pub mod bitpacker;
 mod bitset;
 
 pub(crate) use self::composite_file::{CompositeFile, CompositeWrite};
+pub(crate) use self::footer::{Footer, FooterProxy, UnsupportedVersion, FOOTER_VERSION};
 pub use self::serialize::{BinarySerializable, FixedSize};
 pub use self::timer::Timing;
 pub use self::timer::TimerTree;
@@ -56,6 +58,16 @@ pub(crate) fn is_power_of_2(n: usize) -> bool {
     (n > 0) && (n & (n - 1) == 0)
 }
 
+/// 64-bit FNV-1a hash of `bytes`.
+///
+/// Unlike the hashers of the standard library, the hash is stable
+/// across processes and versions, so that it can be persisted.
+pub(crate) fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
 /// Create a default io error given a string.
 pub(crate) fn make_io_err(msg: String) -> io::Error {
     io::Error::new(io::ErrorKind::Other, msg)
@@ -105,16 +117,73 @@ pub fn u64_to_i64(val: u64) -> i64 {
     (val ^ HIGHEST_BIT) as i64
 }
 
+/// Maps a `f64` to `u64`
+///
+/// The mapping is monotonic, so that the order of the `u64`
+/// (and of their big endian bytes, as used in terms) is
+/// the order of the `f64`.
+/// It is a total order : `-0.0` is lower than `0.0`,
+/// and `NaN`s with a positive sign are greater than all of the numbers.
+///
+/// Positive values simply get their sign bit flipped, while
+/// all of the bits of negative values are flipped.
+///
+/// # See also
+/// The [reverse mapping is `u64_to_f64`](./fn.u64_to_f64.html).
+#[inline(always)]
+pub fn f64_to_u64(val: f64) -> u64 {
+    let bits = val.to_bits();
+    if bits & HIGHEST_BIT == 0 {
+        bits ^ HIGHEST_BIT
+    } else {
+        !bits
+    }
+}
+
+/// Reverse the mapping given by [`f64_to_u64`](./fn.f64_to_u64.html).
+#[inline(always)]
+pub fn u64_to_f64(val: u64) -> f64 {
+    if val & HIGHEST_BIT != 0 {
+        f64::from_bits(val ^ HIGHEST_BIT)
+    } else {
+        f64::from_bits(!val)
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod test {
 
-    use super::{compute_num_bits, i64_to_u64, u64_to_i64};
+    use super::{compute_num_bits, f64_to_u64, i64_to_u64, u64_to_f64, u64_to_i64};
     pub use super::serialize::test::fixed_size_test;
 
     fn test_i64_converter_helper(val: i64) {
         assert_eq!(u64_to_i64(i64_to_u64(val)), val);
     }
 
+    #[test]
+    fn test_f64_converter() {
+        let vals = [
+            ::std::f64::NEG_INFINITY,
+            ::std::f64::MIN,
+            -1.5f64,
+            -::std::f64::MIN_POSITIVE,
+            -0.0f64,
+            0.0f64,
+            ::std::f64::MIN_POSITIVE,
+            1.0f64,
+            1.5f64,
+            ::std::f64::MAX,
+            ::std::f64::INFINITY,
+        ];
+        for val in &vals {
+            assert_eq!(u64_to_f64(f64_to_u64(*val)).to_bits(), val.to_bits());
+        }
+        for pair in vals.windows(2) {
+            assert!(f64_to_u64(pair[0]) < f64_to_u64(pair[1]));
+        }
+        assert!(f64_to_u64(::std::f64::NAN) > f64_to_u64(::std::f64::INFINITY));
+    }
+
     #[test]
     fn test_i64_converter() {
         assert_eq!(i64_to_u64(i64::min_value()), u64::min_value());
@@ -0,0 +1,146 @@
+use Result;
+use core::Searcher;
+use docset::DocSet;
+use query::{create_weight, Query, Scorer, Weight};
+use schema::{Document, Field};
+use std::io::Write;
+use DocAddress;
+use SegmentLocalId;
+
+/// Iterator over the stored documents matching a query,
+/// as returned by `Searcher::export`.
+///
+/// Documents are fetched one at a time, in the order of their
+/// `DocAddress`, so that exporting a large result set does
+/// not require to hold it in memory.
+pub struct DocExport<'a> {
+    searcher: &'a Searcher,
+    weight: Box<Weight>,
+    fields: Option<Vec<Field>>,
+    segment_ord: usize,
+    scorer: Option<Box<Scorer>>,
+}
+
+impl<'a> DocExport<'a> {
+    pub(crate) fn new(
+        searcher: &'a Searcher,
+        query: &Query,
+        fields: Option<&[Field]>,
+    ) -> Result<DocExport<'a>> {
+        Ok(DocExport {
+            searcher,
+            weight: create_weight(query, searcher, false)?,
+            fields: fields.map(|fields| fields.to_vec()),
+            segment_ord: 0,
+            scorer: None,
+        })
+    }
+
+    fn next_doc(&mut self) -> Result<Option<(DocAddress, Document)>> {
+        let searcher = self.searcher;
+        let segment_readers = searcher.segment_readers();
+        while self.segment_ord < segment_readers.len() {
+            let segment_reader = &segment_readers[self.segment_ord];
+            if self.scorer.is_none() {
+                self.scorer = Some(self.weight.alive_scorer(segment_reader)?);
+            }
+            if let Some(ref mut scorer) = self.scorer {
+                while scorer.advance() {
+                    let doc_id = scorer.doc();
+                    let doc = match self.fields {
+                        Some(ref fields) => segment_reader.doc_fields(doc_id, fields)?,
+                        None => segment_reader.doc(doc_id)?,
+                    };
+                    let doc_address = DocAddress(self.segment_ord as SegmentLocalId, doc_id);
+                    return Ok(Some((doc_address, doc)));
+                }
+            }
+            self.scorer = None;
+            self.segment_ord += 1;
+        }
+        Ok(None)
+    }
+
+    /// Writes the documents as JSON lines, that is one JSON
+    /// object per line, keyed by field names.
+    ///
+    /// Returns the number of documents written.
+    pub fn write_json_lines<W: Write>(self, writer: &mut W) -> Result<u64> {
+        let searcher = self.searcher;
+        let mut num_docs = 0u64;
+        for doc_res in self {
+            let (doc_address, doc) = doc_res?;
+            let schema = searcher.segment_reader(doc_address.segment_ord()).schema();
+            writeln!(writer, "{}", schema.to_json(&doc))?;
+            num_docs += 1;
+        }
+        writer.flush()?;
+        Ok(num_docs)
+    }
+}
+
+impl<'a> Iterator for DocExport<'a> {
+    type Item = Result<(DocAddress, Document)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_doc() {
+            Ok(Some(doc)) => Some(Ok(doc)),
+            Ok(None) => None,
+            Err(e) => {
+                // stops the iteration after an error.
+                self.segment_ord = self.searcher.segment_readers().len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::Index;
+    use query::{AllQuery, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, Term, STORED, STRING, TEXT};
+
+    #[test]
+    fn test_export_json_lines() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let body = schema_builder.add_text_field("body", STRING | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(title => "a b", body => "x"));
+        index_writer.add_document(doc!(title => "b c", body => "y"));
+        index_writer.add_document(doc!(title => "c d", body => "z"));
+        index_writer.commit().unwrap();
+        index_writer.delete_term(Term::from_field_text(body, "z"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        {
+            let mut buffer = Vec::new();
+            let num_docs = searcher
+                .export(&AllQuery, Some(&[title]))
+                .unwrap()
+                .write_json_lines(&mut buffer)
+                .unwrap();
+            assert_eq!(num_docs, 2);
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                "{\"title\":[\"a b\"]}\n{\"title\":[\"b c\"]}\n"
+            );
+        }
+        {
+            let term_query = TermQuery::new(
+                Term::from_field_text(title, "c"),
+                IndexRecordOption::Basic,
+            );
+            let docs: Vec<_> = searcher
+                .export(&term_query, None)
+                .unwrap()
+                .map(|doc_res| doc_res.unwrap().1)
+                .collect();
+            assert_eq!(docs, vec![doc!(title => "b c", body => "y")]);
+        }
+    }
+}
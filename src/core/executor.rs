@@ -0,0 +1,140 @@
+use Result;
+use futures::Future;
+use futures::future::join_all;
+use futures_cpupool::{Builder, CpuPool};
+use std::cell::Cell;
+use std::fmt;
+use std::sync::Arc;
+
+thread_local! {
+    // true in the threads of the pools of the executors.
+    static IN_EXECUTOR_POOL: Cell<bool> = Cell::new(false);
+}
+
+/// Executor running the per-segment tasks of searches
+/// and the warming of new segment readers.
+///
+/// The executor of an index is shared by all of its searches, and
+/// can be configured with `Index::set_search_threads`.
+/// A multithreaded executor is backed by a bounded thread pool,
+/// so that the number of threads used for searching does not
+/// grow with the number of concurrent searches.
+///
+/// A thread of the pool never waits for other tasks of a pool:
+/// `.map(...)` called from within a task, for instance by a warmer
+/// running a search, runs sequentially in the calling thread.
+pub enum Executor {
+    /// Runs the tasks sequentially, in the calling thread.
+    SingleThread,
+    /// Runs the tasks in a bounded thread pool.
+    ThreadPool(CpuPool),
+}
+
+impl Executor {
+    /// Creates an executor running the tasks in the calling thread.
+    pub fn single_thread() -> Executor {
+        Executor::SingleThread
+    }
+
+    /// Creates an executor backed by a pool of `num_threads` threads.
+    pub fn multi_thread(num_threads: usize) -> Executor {
+        let pool = Builder::new()
+            .pool_size(num_threads)
+            .name_prefix("search-")
+            .after_start(|| IN_EXECUTOR_POOL.with(|in_pool| in_pool.set(true)))
+            .create();
+        Executor::ThreadPool(pool)
+    }
+
+    /// Applies `f` to each of the `args`, and returns the results
+    /// in the order of the `args`.
+    ///
+    /// If one of the tasks fails, the first error is returned.
+    pub fn map<A, R, F>(&self, f: F, args: Vec<A>) -> Result<Vec<R>>
+    where
+        A: Send + 'static,
+        R: Send + 'static,
+        F: Fn(A) -> Result<R> + Send + Sync + 'static,
+    {
+        let in_executor_pool = IN_EXECUTOR_POOL.with(|in_pool| in_pool.get());
+        match *self {
+            Executor::SingleThread => args.into_iter().map(f).collect(),
+            // blocking a thread of the pool on tasks queued behind it may deadlock.
+            Executor::ThreadPool(_) if in_executor_pool => args.into_iter().map(f).collect(),
+            Executor::ThreadPool(ref pool) => {
+                let f = Arc::new(f);
+                let futures: Vec<_> = args.into_iter()
+                    .map(|arg| {
+                        let f = Arc::clone(&f);
+                        pool.spawn_fn(move || f(arg))
+                    })
+                    .collect();
+                join_all(futures).wait()
+            }
+        }
+    }
+}
+
+impl Default for Executor {
+    fn default() -> Executor {
+        Executor::single_thread()
+    }
+}
+
+impl fmt::Debug for Executor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Executor::SingleThread => write!(f, "Executor(SingleThread)"),
+            Executor::ThreadPool(_) => write!(f, "Executor(ThreadPool)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Executor;
+    use error::ErrorKind;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_executor_map() {
+        for executor in &[Executor::single_thread(), Executor::multi_thread(3)] {
+            let results = executor.map(|i: u32| Ok(i * 2), (0..10).collect()).unwrap();
+            assert_eq!(results, (0..10).map(|i| i * 2).collect::<Vec<u32>>());
+        }
+    }
+
+    #[test]
+    fn test_executor_map_error() {
+        for executor in &[Executor::single_thread(), Executor::multi_thread(3)] {
+            let result = executor.map(
+                |i: u32| {
+                    if i == 5 {
+                        bail!(ErrorKind::InvalidArgument(format!("{}", i)));
+                    }
+                    Ok(i)
+                },
+                (0..10).collect(),
+            );
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_executor_map_within_task() {
+        // every thread of the pool runs a task mapping over the same pool.
+        let executor = Arc::new(Executor::multi_thread(2));
+        let inner_executor = Arc::clone(&executor);
+        let results = executor
+            .map(
+                move |i: u32| {
+                    let doubled = inner_executor.map(|j: u32| Ok(j * 2), (0..i).collect())?;
+                    Ok(doubled.into_iter().sum::<u32>())
+                },
+                (0..4).collect(),
+            )
+            .unwrap();
+        assert_eq!(results, vec![0, 0, 2, 6]);
+    }
+}
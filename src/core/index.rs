@@ -1,45 +1,168 @@
 use Result;
-use error::{ErrorKind, ResultExt};
+use error::{Error, ErrorKind};
 use serde_json;
-use schema::Schema;
-use std::sync::Arc;
+use schema::{BM25Params, Field, NamedFieldDocument, Schema};
+use Score;
+use query::{AllQuery, BM25Similarity, Similarity};
+use std::sync::{Arc, Mutex, RwLock};
+use byteorder::{BigEndian, ByteOrder};
+use uuid::Uuid;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::borrow::BorrowMut;
 use std::fmt;
 use core::SegmentId;
-use directory::{Directory, MmapDirectory, RAMDirectory};
+use directory::{Directory, DurabilityPolicy, MmapDirectory, RAMDirectory, ReadOnlyDirectory,
+                WatchHandle};
 use indexer::index_writer::open_index_writer;
-use core::searcher::Searcher;
+use core::searcher::{Searcher, SearcherToken};
+use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use num_cpus;
 use super::segment::Segment;
-use core::SegmentReader;
+use core::{Executor, MemoryAccountant, SegmentReader};
+use core::reader_lease::{self, GcLock, ReaderLease};
+use core::segment_reader::bloom_filter_component;
+use core::terms_by_doc_freq::{doc_freq_index_component, write_doc_freq_index};
+use datastruct::BloomFilterBuilder;
+use termdict::{TermDictionary, TermStreamer};
 use super::pool::Pool;
-use core::SegmentMeta;
+use core::{SegmentComponent, SegmentInfo, SegmentMeta, SpaceUsage};
 use super::pool::LeasedItem;
-use std::path::Path;
-use core::IndexMeta;
-use indexer::DirectoryLock;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::io::{self, Write};
+use std::fs::{self, File};
+use common::BinarySerializable;
+use core::{IndexMeta, IndexSettings, LostSegment, RepairReport};
+use schema::FieldType;
+use query::QueryParser;
+use indexer::{Deduplicator, DirectoryLock};
 use IndexWriter;
-use directory::ManagedDirectory;
-use core::META_FILEPATH;
+use indexer::DocIdMapping;
+use directory::{atomic_write_tmp_path, sync_local_directory, FileProtection, ManagedDirectory};
+use directory::error::OpenWriteError;
+use core::{commit_meta_filepath, META_FILEPATH};
 use super::segment::create_segment;
-use indexer::segment_updater::save_new_metas;
+use indexer::segment_updater::{save_metas, save_new_metas};
 use tokenizer::TokenizerManager;
 
-const NUM_SEARCHERS: usize = 12;
+/// Default number of searchers in the searcher pool.
+pub const DEFAULT_NUM_SEARCHERS: usize = 12;
+
+/// Callback warming a new `SegmentReader` before it gets published
+/// to the searchers.
+///
+/// Warmers typically preload fast fields or prime caches,
+/// in order to avoid latency spikes on the first queries following
+/// a commit or a merge.
+pub type Warmer = Box<Fn(&SegmentReader) -> Result<()> + Send + Sync + 'static>;
+
+/// Callback serializing a custom component of a new segment.
+///
+/// See `Index::add_custom_component`.
+pub type CustomComponentWriter =
+    Box<Fn(&SegmentReader, &mut Write) -> Result<()> + Send + Sync + 'static>;
+
+/// Callback notified of the doc ids remapping of every merge.
+///
+/// See `Index::add_merge_listener`.
+pub type MergeListener = Box<Fn(&DocIdMapping) + Send + Sync + 'static>;
+
+/// False positive rate of the bloom filters of the key fields.
+const BLOOM_FILTER_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Magic number identifying an index serialized with `Index::to_bytes`.
+const INDEX_BYTES_MAGIC_NUMBER: u32 = 0x7a4e_7462;
+
+/// Appends a file to a serialized index, as `[path][len: u64][data]`.
+fn write_file_entry(path: &Path, data: &[u8], buffer: &mut Vec<u8>) -> io::Result<()> {
+    path.to_string_lossy().into_owned().serialize(buffer)?;
+    (data.len() as u64).serialize(buffer)?;
+    buffer.extend_from_slice(data);
+    Ok(())
+}
+
+fn parse_metas(meta_data: &[u8]) -> Result<IndexMeta> {
+    let meta_string = String::from_utf8_lossy(meta_data);
+    serde_json::from_str(&meta_string)
+        .map_err(|e| ErrorKind::CorruptedFile(META_FILEPATH.clone(), e.to_string()).into())
+}
 
 fn load_metas(directory: &Directory) -> Result<IndexMeta> {
     let meta_data = directory.atomic_read(&META_FILEPATH)?;
-    let meta_string = String::from_utf8_lossy(&meta_data);
-    serde_json::from_str(&meta_string).chain_err(|| ErrorKind::CorruptedFile(META_FILEPATH.clone()))
+    parse_metas(&meta_data)
+}
+
+/// Reads the meta file of a previous commit.
+fn load_commit_metas(directory: &Directory, generation: u64) -> Result<IndexMeta> {
+    let commit_meta_path = commit_meta_filepath(generation);
+    let meta_data = directory.atomic_read(&commit_meta_path)?;
+    serde_json::from_slice(&meta_data)
+        .map_err(|e| ErrorKind::CorruptedFile(commit_meta_path, e.to_string()).into())
+}
+
+/// Checks the fields of the search preset of the settings, if any,
+/// and returns its field boosts.
+fn preset_field_boosts(schema: &Schema, settings: &IndexSettings) -> Result<HashMap<Field, Score>> {
+    let mut field_boosts = HashMap::new();
+    if let Some(ref search_preset) = settings.search_preset {
+        let get_field = |field_name: &str| -> Result<Field> {
+            schema
+                .get_field(field_name)
+                .ok_or_else(|| ErrorKind::SchemaError(field_name.to_string()).into())
+        };
+        for field_name in &search_preset.default_fields {
+            get_field(field_name)?;
+        }
+        for (field_name, &boost) in &search_preset.field_boosts {
+            field_boosts.insert(get_field(field_name)?, boost);
+        }
+    }
+    Ok(field_boosts)
+}
+
+/// Returns the sorted list of the segment files used by a commit.
+fn committed_files(directory: &Directory, metas: &IndexMeta) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = metas
+        .segments
+        .iter()
+        .flat_map(|segment_meta| segment_meta.list_files())
+        .filter(|path| directory.exists(path))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Returns a random seed for the ids of the `SearcherToken`s,
+/// so that tokens pinned by different processes do not collide.
+fn random_pin_id_seed() -> u64 {
+    let uuid = Uuid::new_v4();
+    BigEndian::read_u64(&uuid.as_bytes()[..8])
 }
 
 /// Search Index
 pub struct Index {
     directory: ManagedDirectory,
     schema: Schema,
+    settings: IndexSettings,
     searcher_pool: Arc<Pool<Searcher>>,
+    num_searchers: Arc<AtomicUsize>,
+    warmers: Arc<RwLock<Vec<Warmer>>>,
+    segment_warmers: Arc<RwLock<Vec<Warmer>>>,
+    custom_component_writers: Arc<RwLock<Vec<(String, CustomComponentWriter)>>>,
+    merge_listeners: Arc<RwLock<Vec<MergeListener>>>,
+    // `SegmentReader` is not `Sync`: a `Mutex` keeps the `Index` `Send` and `Sync`.
+    pinned_searchers: Arc<Mutex<HashMap<SearcherToken, Vec<SegmentReader>>>>,
+    pin_id_seed: u64,
+    next_pin_id: Arc<AtomicUsize>,
+    searcher_generation: Arc<AtomicUsize>,
+    search_executor: Arc<RwLock<Arc<Executor>>>,
+    memory_accountant: Arc<RwLock<MemoryAccountant>>,
+    field_boosts: Arc<RwLock<Arc<HashMap<Field, Score>>>>,
+    bm25_params: Arc<RwLock<Arc<HashMap<Field, BM25Params>>>>,
+    similarities: Arc<RwLock<Arc<HashMap<Field, Arc<Similarity>>>>>,
     tokenizers: TokenizerManager,
+    read_only: bool,
 }
 
 impl Index {
@@ -48,13 +171,24 @@ impl Index {
     /// The index will be allocated in anonymous memory.
     /// This should only be used for unit tests.
     pub fn create_in_ram(schema: Schema) -> Index {
+        Index::create_in_ram_with_settings(schema, IndexSettings::default())
+    }
+
+    /// Creates a new index using the `RAMDirectory`, with the given
+    /// `IndexSettings`.
+    ///
+    /// # Panics
+    /// If the settings are invalid, e.g. if they use a doc store
+    /// compressor that is not supported by this build of tantivy.
+    pub fn create_in_ram_with_settings(schema: Schema, settings: IndexSettings) -> Index {
         let ram_directory = RAMDirectory::create();
         // unwrap is ok here
         let directory = ManagedDirectory::new(ram_directory).expect(
             "Creating a managed directory from a brand new RAM directory \
              should never fail.",
         );
-        Index::from_directory(directory, schema).expect("Creating a RAMDirectory should never fail")
+        Index::from_directory_with_settings(directory, schema, settings)
+            .expect("Creating a RAMDirectory should never fail")
     }
 
     /// Creates a new index in a given filepath.
@@ -62,9 +196,40 @@ impl Index {
     ///
     /// If a previous index was in this directory, then its meta file will be destroyed.
     pub fn create<P: AsRef<Path>>(directory_path: P, schema: Schema) -> Result<Index> {
-        let mmap_directory = MmapDirectory::open(directory_path)?;
+        Index::create_with_settings(directory_path, schema, IndexSettings::default())
+    }
+
+    /// Creates a new index in a given filepath, with the given `IndexSettings`.
+    /// The index will use the `MMapDirectory`.
+    ///
+    /// If a previous index was in this directory, then its meta file will be destroyed.
+    pub fn create_with_settings<P: AsRef<Path>>(
+        directory_path: P,
+        schema: Schema,
+        settings: IndexSettings,
+    ) -> Result<Index> {
+        Index::create_with_durability(
+            directory_path,
+            schema,
+            settings,
+            DurabilityPolicy::default(),
+        )
+    }
+
+    /// Creates a new index in a given filepath, with the given `IndexSettings`
+    /// and `DurabilityPolicy`.
+    /// The index will use the `MMapDirectory`.
+    ///
+    /// If a previous index was in this directory, then its meta file will be destroyed.
+    pub fn create_with_durability<P: AsRef<Path>>(
+        directory_path: P,
+        schema: Schema,
+        settings: IndexSettings,
+        durability: DurabilityPolicy,
+    ) -> Result<Index> {
+        let mmap_directory = MmapDirectory::open(directory_path)?.with_durability(durability);
         let directory = ManagedDirectory::new(mmap_directory)?;
-        Index::from_directory(directory, schema)
+        Index::from_directory_with_settings(directory, schema, settings)
     }
 
     /// Accessor for the tokenizer manager.
@@ -89,31 +254,313 @@ impl Index {
     /// Creates a new index given a directory and an `IndexMeta`.
     fn create_from_metas(directory: ManagedDirectory, metas: &IndexMeta) -> Result<Index> {
         let schema = metas.schema.clone();
+        let settings = metas.index_settings.clone();
+        let field_boosts = preset_field_boosts(&schema, &settings)?;
         let index = Index {
             directory,
             schema,
+            settings,
             searcher_pool: Arc::new(Pool::new()),
+            num_searchers: Arc::new(AtomicUsize::new(DEFAULT_NUM_SEARCHERS)),
+            warmers: Arc::new(RwLock::new(Vec::new())),
+            segment_warmers: Arc::new(RwLock::new(Vec::new())),
+            custom_component_writers: Arc::new(RwLock::new(Vec::new())),
+            merge_listeners: Arc::new(RwLock::new(Vec::new())),
+            pinned_searchers: Arc::default(),
+            pin_id_seed: random_pin_id_seed(),
+            next_pin_id: Arc::default(),
+            searcher_generation: Arc::default(),
+            search_executor: Arc::default(),
+            memory_accountant: Arc::default(),
+            field_boosts: Arc::new(RwLock::new(Arc::new(field_boosts))),
+            bm25_params: Arc::default(),
+            similarities: Arc::default(),
             tokenizers: TokenizerManager::default(),
+            read_only: false,
         };
         index.load_searchers()?;
         Ok(index)
     }
 
     /// Create a new index from a directory.
-    pub fn from_directory(mut directory: ManagedDirectory, schema: Schema) -> Result<Index> {
-        save_new_metas(schema.clone(), 0, directory.borrow_mut())?;
-        let metas = IndexMeta::with_schema(schema);
+    pub fn from_directory(directory: ManagedDirectory, schema: Schema) -> Result<Index> {
+        Index::from_directory_with_settings(directory, schema, IndexSettings::default())
+    }
+
+    /// Create a new index from a directory, with the given `IndexSettings`.
+    ///
+    /// # Errors
+    /// If the settings use a doc store compressor that is not supported
+    /// by this build of tantivy, returns `Error::InvalidArgument`.
+    pub fn from_directory_with_settings(
+        mut directory: ManagedDirectory,
+        schema: Schema,
+        settings: IndexSettings,
+    ) -> Result<Index> {
+        if !settings.docstore_compression.is_supported() {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The doc store compressor {:?} requires the `zstd-compression` feature.",
+                settings.docstore_compression
+            )));
+        }
+        preset_field_boosts(&schema, &settings)?;
+        if let Some(ref dedup_settings) = settings.dedup {
+            Deduplicator::new(&schema, dedup_settings)?;
+        }
+        save_new_metas(schema.clone(), settings.clone(), 0, directory.borrow_mut())?;
+        let metas = IndexMeta::with_schema_and_settings(schema, settings);
         Index::create_from_metas(directory, &metas)
     }
 
     /// Opens a new directory from an index path.
     pub fn open<P: AsRef<Path>>(directory_path: P) -> Result<Index> {
-        let mmap_directory = MmapDirectory::open(directory_path)?;
+        Index::open_with_durability(directory_path, DurabilityPolicy::default())
+    }
+
+    /// Opens a new directory from an index path, with the given `DurabilityPolicy`.
+    pub fn open_with_durability<P: AsRef<Path>>(
+        directory_path: P,
+        durability: DurabilityPolicy,
+    ) -> Result<Index> {
+        let mmap_directory = MmapDirectory::open(directory_path)?.with_durability(durability);
         let directory = ManagedDirectory::new(mmap_directory)?;
         let metas = load_metas(&directory)?;
         Index::create_from_metas(directory, &metas)
     }
 
+    /// Opens an index in read-only mode.
+    ///
+    /// A read-only index never writes anything in its directory,
+    /// and does not require nor take the lockfile. This makes it
+    /// possible to open an index stored on a read-only filesystem,
+    /// or from many concurrent reader processes.
+    ///
+    /// Reader processes should hold a `ReaderLease` (see
+    /// `.acquire_reader_lease(...)`) to prevent the writer process
+    /// from deleting the files of the segments they are searching.
+    ///
+    /// Calling `.writer(...)` on a read-only index returns an error.
+    pub fn open_read_only<P: AsRef<Path>>(directory_path: P) -> Result<Index> {
+        let mmap_directory = MmapDirectory::open(directory_path)?;
+        let directory = ManagedDirectory::new(ReadOnlyDirectory::new(mmap_directory))?;
+        let metas = load_metas(&directory)?;
+        let mut index = Index::create_from_metas(directory, &metas)?;
+        index.read_only = true;
+        Ok(index)
+    }
+
+    /// Returns true iff the index was opened with `Index::open_read_only`.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Sets a size budget, in bytes, on the files of the index.
+    /// `None` removes the budget.
+    ///
+    /// Once the budget is reached, flushes fail with `ErrorKind::DiskFull`,
+    /// and merges that could exceed the budget are not started.
+    /// The index is left in its last committed state: after freeing
+    /// some space or raising the budget, the `IndexWriter` can be
+    /// rolled back and the documents added again.
+    ///
+    /// The budget is not persisted, and has to be set again every
+    /// time the index is opened.
+    pub fn set_size_budget(&self, budget: Option<u64>) -> Result<()> {
+        self.directory.set_size_budget(budget)?;
+        Ok(())
+    }
+
+    /// Returns the number of bytes that can still be written before
+    /// exceeding the size budget, or `None` if there is no budget.
+    pub fn remaining_size_budget(&self) -> Option<u64> {
+        self.directory.remaining_size_budget()
+    }
+
+    /// Reindexes the documents of this index into a new index,
+    /// created in `directory_path` with `new_schema`, typically
+    /// after a change of analyzers or field options.
+    ///
+    /// The new index has the same `IndexSettings` as this index.
+    ///
+    /// The stored fields of the documents visible by `.searcher()` are
+    /// streamed, and passed by name to `transform`, which may modify the
+    /// document or return `None` to drop it. Fields that do not exist in
+    /// `new_schema` are ignored, and fields that are not stored
+    /// cannot be reindexed.
+    /// The documents are committed in the new index once all of them
+    /// are added, and its searchers are loaded.
+    ///
+    /// # Errors
+    /// Returns an error if the settings of this index are not valid
+    /// for `new_schema`, e.g. if its search preset refers to a field
+    /// that does not exist anymore.
+    pub fn reindex<P, F>(
+        &self,
+        directory_path: P,
+        new_schema: Schema,
+        heap_size_in_bytes: usize,
+        mut transform: F,
+    ) -> Result<Index>
+    where
+        P: AsRef<Path>,
+        F: FnMut(NamedFieldDocument) -> Option<NamedFieldDocument>,
+    {
+        let target = Index::create_with_settings(
+            directory_path,
+            new_schema.clone(),
+            self.settings.clone(),
+        )?;
+        {
+            let mut index_writer = target.writer(heap_size_in_bytes)?;
+            let searcher = self.searcher();
+            for doc_res in searcher.export(&AllQuery, None)? {
+                let (_, doc) = doc_res?;
+                if let Some(named_doc) = transform(self.schema.to_named_doc(&doc)) {
+                    let NamedFieldDocument(field_values) = named_doc;
+                    let named_doc = NamedFieldDocument(
+                        field_values
+                            .into_iter()
+                            .filter(|&(ref name, _)| new_schema.get_field(name).is_some())
+                            .collect(),
+                    );
+                    index_writer.add_document(new_schema.convert_named_doc(named_doc)?);
+                }
+            }
+            index_writer.commit()?;
+            index_writer.wait_merging_threads()?;
+        }
+        target.load_searchers()?;
+        Ok(target)
+    }
+
+    /// Creates a backup of the last commit of the index in the
+    /// directory `target_path`, which is created if necessary.
+    ///
+    /// Segment files are hard linked whenever possible, so that
+    /// backups are nearly free in terms of IO and disk space.
+    /// The backup can safely be taken while an `IndexWriter`
+    /// is indexing, committing or merging in this process.
+    ///
+    /// The backup is crash-consistent: all of the segment files,
+    /// and the backup directory, are synced to disk before its
+    /// `meta.json` is atomically written and synced, last.
+    ///
+    /// # Errors
+    /// If `target_path` already contains an index, returns
+    /// `Error::FileAlreadyExists`.
+    pub fn backup_to<P: AsRef<Path>>(&self, target_path: P) -> Result<()> {
+        let target_path = target_path.as_ref();
+        let target_meta_path = target_path.join(&*META_FILEPATH);
+        if target_meta_path.exists() {
+            bail!(ErrorKind::FileAlreadyExists(target_meta_path));
+        }
+        fs::create_dir_all(target_path)?;
+        loop {
+            let metas_data = self.directory.atomic_read(&META_FILEPATH)?;
+            let metas = parse_metas(&metas_data)?;
+            let paths = committed_files(&self.directory, &metas);
+            let _file_protections: Vec<FileProtection> = paths
+                .iter()
+                .map(|path| self.directory.protect_file_from_delete(path))
+                .collect();
+            // A commit may have happened, and its garbage collection
+            // may have removed some of the files before they got protected.
+            if self.directory.atomic_read(&META_FILEPATH)? != metas_data {
+                continue;
+            }
+            self.directory.copy_files_to(&paths, target_path)?;
+            // the meta file is written to a temporary file, and renamed
+            // once synced, so that a crash never leaves a partial meta file.
+            let tmp_meta_path = atomic_write_tmp_path(&target_meta_path);
+            {
+                let mut meta_file = File::create(&tmp_meta_path)?;
+                meta_file.write_all(&metas_data)?;
+                meta_file.sync_all()?;
+            }
+            fs::rename(&tmp_meta_path, &target_meta_path)?;
+            sync_local_directory(target_path)?;
+            return Ok(());
+        }
+    }
+
+    /// Serializes the generation of the searchers into a single buffer.
+    ///
+    /// The index can then be opened back, in RAM, using `Index::from_bytes`.
+    /// This is handy to embed small indexes in a binary, send them over
+    /// the network or store them in a database.
+    ///
+    /// Only the segments visible through `.searcher()` are included:
+    /// the documents committed since the last call to `.load_searchers()` are not.
+    /// The generation is pinned while it is serialized, so that its files
+    /// cannot be garbage collected in the meantime.
+    /// The opstamp and the payload serialized are the ones of the last commit.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let token = self.pin_searcher();
+        let serialized = self.pinned_searcher_to_bytes(token);
+        self.unpin_searcher(token);
+        serialized
+    }
+
+    fn pinned_searcher_to_bytes(&self, token: SearcherToken) -> Result<Vec<u8>> {
+        let searcher = self.pinned_searcher(token)
+            .expect("The searcher to serialize should be pinned");
+        let mut metas = self.load_metas()?;
+        metas.segments = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment_reader| segment_reader.segment_meta().clone())
+            .collect();
+        metas.generation = self.searcher_generation();
+        // the meta files of the previous commits are not serialized.
+        metas.previous_generations.clear();
+        let metas_data = serde_json::to_vec_pretty(&metas)?;
+        let paths = committed_files(&self.directory, &metas);
+        let mut buffer = Vec::new();
+        INDEX_BYTES_MAGIC_NUMBER.serialize(&mut buffer)?;
+        (paths.len() as u32 + 1).serialize(&mut buffer)?;
+        write_file_entry(&META_FILEPATH, &metas_data, &mut buffer)?;
+        for path in &paths {
+            let source = self.directory.open_read(path)?;
+            write_file_entry(path, source.as_slice(), &mut buffer)?;
+        }
+        Ok(buffer)
+    }
+
+    /// Opens an index, in RAM, from a buffer created with `Index::to_bytes`.
+    pub fn from_bytes(data: &[u8]) -> Result<Index> {
+        let mut cursor = data;
+        if u32::deserialize(&mut cursor)? != INDEX_BYTES_MAGIC_NUMBER {
+            bail!(ErrorKind::InvalidArgument(
+                "The buffer does not contain a serialized index.".to_string()
+            ));
+        }
+        let num_files = u32::deserialize(&mut cursor)?;
+        // files are written through the managed directory, so that
+        // they get registered and garbage collected as usual.
+        let mut directory = ManagedDirectory::new(RAMDirectory::create())?;
+        for _ in 0..num_files {
+            let path = PathBuf::from(String::deserialize(&mut cursor)?);
+            let len = u64::deserialize(&mut cursor)? as usize;
+            if cursor.len() < len {
+                bail!(ErrorKind::InvalidArgument(format!(
+                    "The serialized index is truncated within {:?}.",
+                    path
+                )));
+            }
+            let (file_data, rest) = cursor.split_at(len);
+            if path == *META_FILEPATH {
+                directory.atomic_write(&path, file_data)?;
+            } else {
+                let mut wrt = directory.open_write(&path)?;
+                wrt.write_all(file_data)?;
+                wrt.flush()?;
+            }
+            cursor = rest;
+        }
+        let metas = load_metas(&directory)?;
+        Index::create_from_metas(directory, &metas)
+    }
+
     /// Reads the index meta file from the directory.
     pub fn load_metas(&self) -> Result<IndexMeta> {
         load_metas(self.directory())
@@ -131,7 +578,8 @@ impl Index {
     /// should work at the same time.
     ///
     /// # Errors
-    /// If the lockfile already exists, returns `Error::FileAlreadyExists`.
+    /// If the lockfile already exists, returns `Error::LockBusy`.
+    /// If the index was opened in read-only mode, returns `Error::InvalidArgument`.
     /// # Panics
     /// If the heap size per thread is too small, panics.
     pub fn writer_with_num_threads(
@@ -139,15 +587,27 @@ impl Index {
         num_threads: usize,
         heap_size_in_bytes: usize,
     ) -> Result<IndexWriter> {
-        let directory_lock = DirectoryLock::lock(self.directory().box_clone())?;
+        if self.read_only {
+            bail!(ErrorKind::InvalidArgument(
+                "Cannot create a writer on an index opened in read-only mode.".to_string()
+            ));
+        }
+        let directory_lock = self.lock_directory()?;
         open_index_writer(self, num_threads, heap_size_in_bytes, directory_lock)
     }
 
+    fn lock_directory(&self) -> Result<DirectoryLock> {
+        DirectoryLock::lock(self.directory().box_clone()).map_err(|e| match e {
+            OpenWriteError::FileAlreadyExists(path) => ErrorKind::LockBusy(path).into(),
+            e => Error::from(e),
+        })
+    }
+
     /// Creates a multithreaded writer
     /// It just calls `writer_with_num_threads` with the number of cores as `num_threads`
     ///
     /// # Errors
-    /// If the lockfile already exists, returns `Error::FileAlreadyExists`.
+    /// If the lockfile already exists, returns `Error::LockBusy`.
     /// # Panics
     /// If the heap size per thread is too small, panics.
     pub fn writer(&self, heap_size_in_bytes: usize) -> Result<IndexWriter> {
@@ -161,6 +621,11 @@ impl Index {
         self.schema.clone()
     }
 
+    /// Accessor to the index settings.
+    pub fn settings(&self) -> &IndexSettings {
+        &self.settings
+    }
+
     /// Returns the list of segments that are searchable
     pub fn searchable_segments(&self) -> Result<Vec<Segment>> {
         Ok(self.searchable_segment_metas()?
@@ -196,6 +661,115 @@ impl Index {
         Ok(self.load_metas()?.segments)
     }
 
+    /// Verifies the checksums of all of the files of the
+    /// searchable segments.
+    ///
+    /// This requires reading the entire index, and is therefore
+    /// expensive. Regular reads only check the footer of the files.
+    ///
+    /// Returns a `CorruptedFile` error naming the first file
+    /// that is truncated or corrupted, or an `IncompatibleVersion`
+    /// error if a file was written in an unsupported format.
+    pub fn validate(&self) -> Result<()> {
+        for segment in self.searchable_segments()? {
+            segment.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Detects the searchable segments with missing, truncated or
+    /// corrupted files, e.g. after a crash in the middle of a copy,
+    /// and drops them from the last commit, so that the rest
+    /// of the index remains searchable.
+    ///
+    /// As for `.validate()`, this requires reading the entire index.
+    /// The files of the dropped segments are left to the garbage
+    /// collection of the next `IndexWriter`.
+    ///
+    /// Returns the segments that were dropped, and reloads the searchers.
+    ///
+    /// # Errors
+    /// As the commit is rewritten, returns `Error::LockBusy` if an
+    /// `IndexWriter` is alive, and `Error::InvalidArgument` if the index
+    /// was opened in read-only mode.
+    /// Other errors, such as an `IncompatibleVersion` error,
+    /// are returned as is, leaving the index untouched.
+    pub fn repair(&self) -> Result<RepairReport> {
+        if self.read_only {
+            bail!(ErrorKind::InvalidArgument(
+                "Cannot repair an index opened in read-only mode.".to_string()
+            ));
+        }
+        let _directory_lock = self.lock_directory()?;
+        let metas = self.load_metas()?;
+        let mut kept_segment_metas = Vec::with_capacity(metas.segments.len());
+        let mut report = RepairReport::default();
+        for segment_meta in metas.segments {
+            let error = match self.segment(segment_meta.clone()).validate() {
+                Ok(()) => {
+                    kept_segment_metas.push(segment_meta);
+                    continue;
+                }
+                Err(error) => error,
+            };
+            let lost_path = match *error.kind() {
+                ErrorKind::PathDoesNotExist(ref path) | ErrorKind::CorruptedFile(ref path, _) => {
+                    Some(path.clone())
+                }
+                _ => None,
+            };
+            if let Some(path) = lost_path {
+                warn!("Dropping segment {:?}: {}", segment_meta.id(), error);
+                report.lost_segments.push(LostSegment {
+                    segment_id: segment_meta.id(),
+                    num_docs: segment_meta.num_docs(),
+                    path,
+                    detail: error.to_string(),
+                });
+            } else {
+                return Err(error);
+            }
+        }
+        if !report.is_healthy() {
+            let mut directory = self.directory().box_clone();
+            save_metas(
+                kept_segment_metas,
+                metas.schema,
+                metas.index_settings,
+                metas.opstamp,
+                metas.payload,
+                &mut *directory,
+            )?;
+            self.load_searchers()?;
+        }
+        Ok(report)
+    }
+
+    /// Returns the id, number of documents, creation opstamp
+    /// and file sizes of each of the searchable segments.
+    ///
+    /// This is useful to monitor the effectiveness of merges
+    /// and the space used by the index.
+    pub fn segments_info(&self) -> Result<Vec<SegmentInfo>> {
+        self.searchable_segments()?
+            .iter()
+            .map(Segment::info)
+            .collect()
+    }
+
+    /// Returns the number of bytes used by each field and each component,
+    /// summed over all of the searchable segments.
+    ///
+    /// This helps identifying the fields inflating the index, and tuning
+    /// their options.
+    pub fn space_usage(&self) -> Result<SpaceUsage> {
+        let mut space_usage = SpaceUsage::default();
+        for segment in self.searchable_segments()? {
+            space_usage += SegmentReader::open(&segment)?.space_usage();
+        }
+        Ok(space_usage)
+    }
+
     /// Returns the list of segment ids that are searchable.
     pub fn searchable_segment_ids(&self) -> Result<Vec<SegmentId>> {
         Ok(self.searchable_segment_metas()?
@@ -211,23 +785,486 @@ impl Index {
     /// This needs to be called when a new segment has been
     /// published or after a merge.
     pub fn load_searchers(&self) -> Result<()> {
-        let searchable_segments = self.searchable_segments()?;
-        let segment_readers: Vec<SegmentReader> = searchable_segments
-            .iter()
-            .map(SegmentReader::open)
+        let metas = self.load_metas()?;
+        let segment_readers: Vec<SegmentReader> = metas
+            .segments
+            .into_iter()
+            .map(|segment_meta| SegmentReader::open(&self.segment(segment_meta)))
             .collect::<Result<_>>()?;
-        let searchers = (0..NUM_SEARCHERS)
-            .map(|_| Searcher::from(segment_readers.clone()))
+        let warmers = Arc::clone(&self.warmers);
+        self.search_executor().map(
+            move |segment_reader: SegmentReader| {
+                let warmers = warmers.read().expect("Warmers lock poisoned");
+                for warmer in warmers.iter() {
+                    warmer(&segment_reader)?;
+                }
+                Ok(())
+            },
+            segment_readers.clone(),
+        )?;
+        let searchers = (0..self.num_searchers())
+            .map(|_| self.create_searcher(segment_readers.clone()))
             .collect();
         self.searcher_pool.publish_new_generation(searchers);
+        self.searcher_generation
+            .store(metas.generation as usize, Ordering::SeqCst);
         Ok(())
     }
 
+    /// Returns the generation of the `meta.json` the searchers
+    /// were loaded from.
+    ///
+    /// See `IndexMeta::generation`.
+    pub fn searcher_generation(&self) -> u64 {
+        self.searcher_generation.load(Ordering::SeqCst) as u64
+    }
+
+    /// Sets the number of searchers in the searcher pool,
+    /// and reloads the searchers.
+    ///
+    /// This is the maximum number of queries that can be
+    /// searched concurrently. Defaults to `DEFAULT_NUM_SEARCHERS`.
+    pub fn set_num_searchers(&self, num_searchers: usize) -> Result<()> {
+        if num_searchers == 0 {
+            bail!(ErrorKind::InvalidArgument(
+                "The searcher pool requires at least one searcher.".to_string()
+            ));
+        }
+        self.num_searchers.store(num_searchers, Ordering::SeqCst);
+        self.load_searchers()
+    }
+
+    /// Returns the number of searchers in the searcher pool.
+    pub fn num_searchers(&self) -> usize {
+        self.num_searchers.load(Ordering::SeqCst)
+    }
+
+    /// Sets the number of threads of the executor shared by
+    /// all of the searches of this index.
+    ///
+    /// With a single thread, the tasks run in the calling thread.
+    /// The executor searches the segments in `Searcher::search_fruit_in_parallel`,
+    /// and runs the warmers. It is also available to `Searcher::map_segments`.
+    /// Defaults to a single thread.
+    ///
+    /// The searchers created before the call keep the former executor.
+    pub fn set_search_threads(&self, num_threads: usize) -> Result<()> {
+        let executor = match num_threads {
+            0 => {
+                bail!(ErrorKind::InvalidArgument(
+                    "The search executor requires at least one thread.".to_string()
+                ));
+            }
+            1 => Executor::single_thread(),
+            _ => Executor::multi_thread(num_threads),
+        };
+        *self.search_executor
+            .write()
+            .expect("Search executor lock poisoned") = Arc::new(executor);
+        Ok(())
+    }
+
+    /// Returns the executor shared by all of the searches of this index.
+    pub fn search_executor(&self) -> Arc<Executor> {
+        Arc::clone(&*self.search_executor
+            .read()
+            .expect("Search executor lock poisoned"))
+    }
+
+    /// Sets the accountant of the memory used by this index.
+    ///
+    /// Sharing an accountant between several indexes, or with a
+    /// `CachingDirectory`, makes it possible to account and cap
+    /// their memory usage as a whole.
+    /// The memory reserved before the call remains
+    /// accounted by the former accountant.
+    pub fn set_memory_accountant(&self, memory_accountant: MemoryAccountant) {
+        *self.memory_accountant
+            .write()
+            .expect("Memory accountant lock poisoned") = memory_accountant;
+    }
+
+    /// Returns the accountant of the memory used by this index.
+    ///
+    /// The indexing threads account for the part of their arena
+    /// in use, and flush their segment as soon as the limiter of the
+    /// accountant refuses more memory.
+    pub fn memory_accountant(&self) -> MemoryAccountant {
+        self.memory_accountant
+            .read()
+            .expect("Memory accountant lock poisoned")
+            .clone()
+    }
+
+    /// Returns a query parser configured with the search preset
+    /// of the index, if any.
+    ///
+    /// Without a search preset, the default fields are
+    /// the indexed text fields of the schema.
+    pub fn query_parser(&self) -> QueryParser {
+        let (default_fields, conjunction_by_default) = match self.settings.search_preset {
+            Some(ref search_preset) => {
+                let default_fields = search_preset
+                    .default_fields
+                    .iter()
+                    .filter_map(|field_name| self.schema.get_field(field_name))
+                    .collect();
+                (default_fields, search_preset.conjunction_by_default)
+            }
+            None => {
+                let default_fields = self.schema
+                    .fields()
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, field_entry)| match *field_entry.field_type() {
+                        FieldType::Str(_) => field_entry.is_indexed(),
+                        _ => false,
+                    })
+                    .map(|(field_id, _)| Field(field_id as u32))
+                    .collect();
+                (default_fields, false)
+            }
+        };
+        let mut query_parser = QueryParser::for_index(self, default_fields);
+        if conjunction_by_default {
+            query_parser.set_conjunction_by_default();
+        }
+        query_parser
+    }
+
+    /// Sets the boost applied to the scores of the matches on `field`,
+    /// and reloads the searchers.
+    ///
+    /// The boosts of the search preset of the index, if any,
+    /// are applied when the index is opened.
+    ///
+    /// This makes it possible to tune the relevance globally, for
+    /// instance to favor matches in a title over matches in a body,
+    /// without rebuilding the queries.
+    pub fn set_field_boost(&self, field: Field, boost: Score) -> Result<()> {
+        {
+            let mut field_boosts = self.field_boosts
+                .write()
+                .expect("Field boosts lock poisoned");
+            let mut new_field_boosts: HashMap<Field, Score> = (**field_boosts).clone();
+            new_field_boosts.insert(field, boost);
+            *field_boosts = Arc::new(new_field_boosts);
+        }
+        self.load_searchers()
+    }
+
+    /// Returns the boost applied to the scores of the matches on `field`.
+    pub fn field_boost(&self, field: Field) -> Score {
+        self.field_boosts
+            .read()
+            .expect("Field boosts lock poisoned")
+            .get(&field)
+            .cloned()
+            .unwrap_or(1f32)
+    }
+
+    /// Sets the parameters of the BM25 similarity used to score
+    /// the matches on `field`, and reloads the searchers.
+    ///
+    /// These parameters take precedence over the ones of the schema.
+    /// See `TextFieldIndexing::set_bm25_params`.
+    pub fn set_bm25_params(&self, field: Field, bm25_params: BM25Params) -> Result<()> {
+        {
+            let mut all_bm25_params = self.bm25_params
+                .write()
+                .expect("BM25 params lock poisoned");
+            let mut new_bm25_params: HashMap<Field, BM25Params> = (**all_bm25_params).clone();
+            new_bm25_params.insert(field, bm25_params);
+            *all_bm25_params = Arc::new(new_bm25_params);
+        }
+        self.load_searchers()
+    }
+
+    /// Returns the parameters of the BM25 similarity used to score
+    /// the matches on `field`.
+    pub fn bm25_params(&self, field: Field) -> BM25Params {
+        self.bm25_params
+            .read()
+            .expect("BM25 params lock poisoned")
+            .get(&field)
+            .cloned()
+            .or_else(|| self.schema.get_field_entry(field).field_type().bm25_params())
+            .unwrap_or_default()
+    }
+
+    /// Sets the similarity used to score the term queries
+    /// on `field`, and reloads the searchers.
+    ///
+    /// This makes it possible to use another scoring model than
+    /// BM25, without rebuilding the queries.
+    pub fn set_similarity(&self, field: Field, similarity: Arc<Similarity>) -> Result<()> {
+        {
+            let mut similarities = self.similarities
+                .write()
+                .expect("Similarities lock poisoned");
+            let mut new_similarities: HashMap<Field, Arc<Similarity>> =
+                (**similarities).clone();
+            new_similarities.insert(field, similarity);
+            *similarities = Arc::new(new_similarities);
+        }
+        self.load_searchers()
+    }
+
+    /// Returns the similarity used to score the term queries on `field`.
+    ///
+    /// Defaults to `BM25Similarity`.
+    pub fn similarity(&self, field: Field) -> Arc<Similarity> {
+        self.similarities
+            .read()
+            .expect("Similarities lock poisoned")
+            .get(&field)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(BM25Similarity))
+    }
+
+    fn create_searcher(&self, segment_readers: Vec<SegmentReader>) -> Searcher {
+        let field_boosts = Arc::clone(&*self.field_boosts
+            .read()
+            .expect("Field boosts lock poisoned"));
+        let bm25_params = Arc::clone(&*self.bm25_params
+            .read()
+            .expect("BM25 params lock poisoned"));
+        let similarities = Arc::clone(&*self.similarities
+            .read()
+            .expect("Similarities lock poisoned"));
+        Searcher::from(segment_readers)
+            .with_field_boosts(field_boosts)
+            .with_bm25_params(bm25_params)
+            .with_similarities(similarities)
+            .with_executor(self.search_executor())
+    }
+
+    /// Registers a warmer, and reloads the searchers.
+    ///
+    /// Warmers are called on every `SegmentReader` of a new generation of
+    /// searchers, before it becomes visible through `.searcher()`.
+    /// If a warmer fails, the new generation is not published
+    /// and `load_searchers` returns the error.
+    pub fn add_warmer(&self, warmer: Warmer) -> Result<()> {
+        self.warmers
+            .write()
+            .expect("Warmers lock poisoned")
+            .push(warmer);
+        self.load_searchers()
+    }
+
+    /// Registers a segment warmer.
+    ///
+    /// Segment warmers are called once on every segment created
+    /// by this index, after it is flushed or after a merge, and before
+    /// it gets published. Unlike the warmers registered with `add_warmer`,
+    /// they are not called again on the following reloads of the searchers,
+    /// which makes them the right place to build per-segment derived
+    /// data (filters, global ordinals, ...), keyed by `SegmentReader::segment_id`.
+    ///
+    /// If a segment warmer fails after a flush, the indexing fails.
+    /// If it fails after a merge, the merge is cancelled.
+    pub fn add_segment_warmer(&self, warmer: Warmer) {
+        self.segment_warmers
+            .write()
+            .expect("Segment warmers lock poisoned")
+            .push(warmer);
+    }
+
+    /// Registers the writer of a custom segment component.
+    ///
+    /// The writer is called on every segment created by this index,
+    /// after it is flushed or after a merge, and before the segment warmers.
+    /// The data it writes is stored in the file `<segment_uuid>.<name>`,
+    /// which is recorded in the `SegmentMeta`, and can be read back with
+    /// `SegmentReader::custom_component`.
+    ///
+    /// This makes it possible to store extension data structures
+    /// (bloom filters, vector graphs, ...) alongside the standard
+    /// components of the segments, and to have them rebuilt on merges.
+    ///
+    /// The name must be made of ASCII alphanumeric characters or `_`,
+    /// and may not be the extension of a standard component.
+    pub fn add_custom_component(&self, name: &str, writer: CustomComponentWriter) -> Result<()> {
+        let is_valid_name = !name.is_empty()
+            && name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && SegmentComponent::iterator().all(|component| component.extension() != name);
+        if !is_valid_name {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "Invalid custom component name {:?}",
+                name
+            )));
+        }
+        let mut custom_component_writers = self.custom_component_writers
+            .write()
+            .expect("Custom component writers lock poisoned");
+        if custom_component_writers
+            .iter()
+            .any(|&(ref registered_name, _)| registered_name == name)
+        {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "Custom component {:?} is already registered",
+                name
+            )));
+        }
+        custom_component_writers.push((name.to_string(), writer));
+        Ok(())
+    }
+
+    /// Adds a bloom filter over the terms of a key field to every
+    /// segment created from now on.
+    ///
+    /// The bloom filter is consulted before the term dictionary when
+    /// looking up a key with `Searcher::doc_address_for_key`, and when
+    /// applying the deletes by term of `IndexWriter::delete_term`
+    /// (and therefore of updates), which makes these operations
+    /// much cheaper on indexes with many segments.
+    ///
+    /// The bloom filter is stored as a custom component of the segments.
+    /// See `Index::add_custom_component`.
+    pub fn add_key_bloom_filter(&self, field: Field) -> Result<()> {
+        if !self.schema.get_field_entry(field).is_indexed() {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The key field {:?} is not indexed",
+                field
+            )));
+        }
+        self.add_custom_component(
+            &bloom_filter_component(field),
+            box move |segment_reader: &SegmentReader, mut write: &mut Write| -> Result<()> {
+                let inverted_index = segment_reader.inverted_index(field);
+                let terms = inverted_index.terms();
+                let mut bloom_filter = BloomFilterBuilder::with_capacity(
+                    terms.num_terms(),
+                    BLOOM_FILTER_FALSE_POSITIVE_RATE,
+                );
+                let mut term_stream = terms.stream();
+                while term_stream.advance() {
+                    bloom_filter.insert(term_stream.key());
+                }
+                bloom_filter.serialize(&mut write)?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Registers a doc frequency index for `field`.
+    ///
+    /// The terms of the field are then also stored by decreasing doc
+    /// frequency in the segments written from now on, so that the most
+    /// frequent terms can be streamed without scanning the whole term
+    /// dictionary. See `SegmentReader::terms_by_doc_freq` and
+    /// `Searcher::top_terms`.
+    ///
+    /// The index has to be registered every time the index is opened.
+    /// The segments written before the registration are indexed
+    /// when they are merged.
+    ///
+    /// # Errors
+    /// Returns an error if the field is not indexed.
+    pub fn add_doc_freq_index(&self, field: Field) -> Result<()> {
+        if !self.schema.get_field_entry(field).is_indexed() {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The field {:?} is not indexed",
+                field
+            )));
+        }
+        self.add_custom_component(
+            &doc_freq_index_component(field),
+            box move |segment_reader: &SegmentReader, mut write: &mut Write| -> Result<()> {
+                let inverted_index = segment_reader.inverted_index(field);
+                write_doc_freq_index(inverted_index.terms(), &mut write)?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Computes the field bounds of a newly created segment, writes
+    /// its custom components, and records both in its meta.
+    ///
+    /// The segment is only opened once for both.
+    pub(crate) fn finalize_new_segment(&self, segment_meta: &mut SegmentMeta) -> Result<()> {
+        let mut segment = self.segment(segment_meta.clone());
+        let segment_reader = SegmentReader::open(&segment)?;
+        segment_meta.set_field_bounds(segment_reader.compute_field_bounds());
+        let custom_component_writers = self.custom_component_writers
+            .read()
+            .expect("Custom component writers lock poisoned");
+        for &(ref name, ref custom_component_writer) in custom_component_writers.iter() {
+            let mut write = segment.open_custom_write(name)?;
+            custom_component_writer(&segment_reader, &mut write)?;
+            write.flush()?;
+            segment_meta.add_custom_component(name.clone());
+        }
+        Ok(())
+    }
+
+    /// Registers a merge listener.
+    ///
+    /// Merge listeners are called after each successful merge, once the
+    /// merged segment has replaced the segments it was merged from in
+    /// the index meta, with the mapping from the old doc ids to the new ones.
+    /// This makes it possible to remap external data keyed by `DocAddress`
+    /// or by doc id (caches, feature stores, ...) rather than rebuilding it.
+    ///
+    /// Listeners are called from the merging thread.
+    pub fn add_merge_listener(&self, merge_listener: MergeListener) {
+        self.merge_listeners
+            .write()
+            .expect("Merge listeners lock poisoned")
+            .push(merge_listener);
+    }
+
+    /// Notifies the merge listeners of a merge.
+    pub(crate) fn notify_merge_listeners(&self, doc_id_mapping: &DocIdMapping) {
+        let merge_listeners = self.merge_listeners
+            .read()
+            .expect("Merge listeners lock poisoned");
+        for merge_listener in merge_listeners.iter() {
+            merge_listener(doc_id_mapping);
+        }
+    }
+
+    /// Calls the segment warmers on a newly created segment.
+    pub(crate) fn warm_new_segment(&self, segment: &Segment) -> Result<()> {
+        let segment_warmers = self.segment_warmers
+            .read()
+            .expect("Segment warmers lock poisoned");
+        if segment_warmers.is_empty() {
+            return Ok(());
+        }
+        let segment_reader = SegmentReader::open(segment)?;
+        for segment_warmer in segment_warmers.iter() {
+            segment_warmer(&segment_reader)?;
+        }
+        Ok(())
+    }
+
+    /// Watches the `meta.json` file, and reloads the searchers
+    /// every time a new commit is detected.
+    ///
+    /// Commits may come from this process or from another one,
+    /// for instance when segments are replicated by copying files.
+    /// The searchers are reloaded as long as the returned
+    /// `WatchHandle` is alive.
+    pub fn reload_searchers_on_commit(&self) -> WatchHandle {
+        let index = self.clone();
+        self.directory.watch(
+            &META_FILEPATH,
+            Box::new(move || {
+                if let Err(e) = index.load_searchers() {
+                    error!("Failed to reload searchers after a commit: {:?}", e);
+                }
+            }),
+        )
+    }
+
     /// Returns a searcher
     ///
     /// This method should be called every single time a search
     /// query is performed.
-    /// The searchers are taken from a pool of `NUM_SEARCHERS` searchers.
+    /// The searchers are taken from a pool of `.num_searchers()` searchers.
     /// If no searcher is available
     /// this may block.
     ///
@@ -236,6 +1273,222 @@ impl Index {
     pub fn searcher(&self) -> LeasedItem<Searcher> {
         self.searcher_pool.acquire()
     }
+
+    /// Pins the current generation of searchers, and returns a token
+    /// to reacquire it later with `.pinned_searcher(token)`.
+    ///
+    /// The files of a pinned generation are not garbage collected,
+    /// even after subsequent commits and merges,
+    /// so that paginated sessions see a consistent view of the index.
+    /// The generation remains pinned until `.unpin_searcher(token)`
+    /// is called.
+    pub fn pin_searcher(&self) -> SearcherToken {
+        let segment_readers = self.searcher().segment_readers().to_vec();
+        let pin_id = self.next_pin_id.fetch_add(1, Ordering::SeqCst) as u64;
+        let token = SearcherToken::from(self.pin_id_seed.wrapping_add(pin_id));
+        self.pinned_searchers
+            .lock()
+            .expect("Pinned searchers lock poisoned")
+            .insert(token, segment_readers);
+        token
+    }
+
+    /// Returns a searcher on the generation pinned by `token`,
+    /// or `None` if the token is unknown or was unpinned.
+    pub fn pinned_searcher(&self, token: SearcherToken) -> Option<Searcher> {
+        self.pinned_searchers
+            .lock()
+            .expect("Pinned searchers lock poisoned")
+            .get(&token)
+            .map(|segment_readers| self.create_searcher(segment_readers.clone()))
+    }
+
+    /// Releases the generation pinned by `token`.
+    ///
+    /// Its files will be removed by the next garbage collection
+    /// if they are not used anymore.
+    /// Returns false if the token was not pinned.
+    pub fn unpin_searcher(&self, token: SearcherToken) -> bool {
+        self.pinned_searchers
+            .lock()
+            .expect("Pinned searchers lock poisoned")
+            .remove(&token)
+            .is_some()
+    }
+
+    /// Returns the meta information of the commits the index
+    /// can be rolled back to, from the oldest to the last one.
+    ///
+    /// Commits are identified by their `generation`.
+    /// Besides the last commit, the number of commits kept
+    /// is defined by `IndexSettings::num_kept_commits`.
+    pub fn list_commits(&self) -> Result<Vec<IndexMeta>> {
+        let metas = self.load_metas()?;
+        let mut commits = metas
+            .previous_generations
+            .iter()
+            .map(|&generation| load_commit_metas(&self.directory, generation))
+            .collect::<Result<Vec<IndexMeta>>>()?;
+        commits.push(metas);
+        Ok(commits)
+    }
+
+    /// Returns the meta information of the commit of the given generation.
+    ///
+    /// Returns an `InvalidArgument` error if the commit is not kept.
+    pub fn commit_metas(&self, generation: u64) -> Result<IndexMeta> {
+        let metas = self.load_metas()?;
+        if metas.generation == generation {
+            return Ok(metas);
+        }
+        if !metas.previous_generations.contains(&generation) {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The commit of generation {} is not kept",
+                generation
+            )));
+        }
+        load_commit_metas(&self.directory, generation)
+    }
+
+    /// Returns a searcher on the documents of the commit of the given generation.
+    ///
+    /// This makes it possible to inspect a previous commit before
+    /// rolling back to it with `IndexWriter::rollback_to`.
+    /// The warmers are not run on the segments of the commit.
+    ///
+    /// Returns an `InvalidArgument` error if the commit is not kept.
+    pub fn open_commit(&self, generation: u64) -> Result<Searcher> {
+        let segment_readers: Vec<SegmentReader> = self.commit_metas(generation)?
+            .segments
+            .into_iter()
+            .map(|segment_meta| SegmentReader::open(&self.segment(segment_meta)))
+            .collect::<Result<_>>()?;
+        Ok(self.create_searcher(segment_readers))
+    }
+
+    /// Returns the files of the previous commits that are kept.
+    ///
+    /// The meta file of a kept commit that cannot be read is still
+    /// returned, but the files of its segments are not.
+    pub(crate) fn kept_commit_files(&self) -> Result<HashSet<PathBuf>> {
+        let mut files = HashSet::new();
+        for generation in self.load_metas()?.previous_generations {
+            files.insert(commit_meta_filepath(generation));
+            let commit_metas = match load_commit_metas(&self.directory, generation) {
+                Ok(commit_metas) => commit_metas,
+                Err(e) => {
+                    warn!("Failed to read the kept commit {}: {:?}", generation, e);
+                    continue;
+                }
+            };
+            files.extend(
+                commit_metas
+                    .segments
+                    .iter()
+                    .flat_map(|segment_meta| segment_meta.list_files()),
+            );
+        }
+        Ok(files)
+    }
+
+    /// Acquires a lease on the files of the current searchers.
+    ///
+    /// In a deployment where one process writes the index
+    /// and other processes read it, the writer does not garbage collect
+    /// the files leased by the readers.
+    /// The lease expires after `ttl` unless it is renewed with
+    /// `.renew_reader_lease(...)`, which should be called
+    /// periodically, and each time the searchers are reloaded.
+    ///
+    /// Returns an error if the index is not stored on the local filesystem.
+    pub fn acquire_reader_lease(&self, ttl: Duration) -> Result<ReaderLease> {
+        let index_path = self.local_index_path()?;
+        let (generation, files) = self.searcher_files();
+        let mut lease = ReaderLease::create(&index_path, ttl, generation, files)?;
+        self.check_reader_lease(&mut lease)?;
+        Ok(lease)
+    }
+
+    /// Updates a lease with the files of the current searchers,
+    /// and extends it by its time to live.
+    pub fn renew_reader_lease(&self, lease: &mut ReaderLease) -> Result<()> {
+        let (generation, files) = self.searcher_files();
+        lease.update(generation, files)?;
+        self.check_reader_lease(lease)
+    }
+
+    /// The writer may have garbage collected the files of the
+    /// searchers before the lease was written.
+    /// In that case, the searchers are reloaded and the lease updated.
+    ///
+    /// The files are checked under the `GcLock`, so that a garbage
+    /// collection deleting them afterwards would read the lease.
+    fn check_reader_lease(&self, lease: &mut ReaderLease) -> Result<()> {
+        let index_path = self.local_index_path()?;
+        loop {
+            {
+                let _gc_lock = GcLock::acquire(&index_path)?;
+                if lease.files().iter().all(|path| self.directory.exists(path)) {
+                    return Ok(());
+                }
+            }
+            self.load_searchers()?;
+            let (generation, files) = self.searcher_files();
+            lease.update(generation, files)?;
+        }
+    }
+
+    fn searcher_files(&self) -> (u64, Vec<PathBuf>) {
+        let generation = self.searcher_generation();
+        let mut files: Vec<PathBuf> = self.searcher()
+            .segment_readers()
+            .iter()
+            .flat_map(|segment_reader| segment_reader.segment_meta().list_files())
+            .collect();
+        files.sort();
+        (generation, files)
+    }
+
+    fn local_index_path(&self) -> Result<PathBuf> {
+        self.directory.local_path(Path::new("")).ok_or_else(|| {
+            ErrorKind::InvalidArgument(
+                "Reader leases require an index on the local filesystem.".to_string(),
+            ).into()
+        })
+    }
+
+    /// Acquires the lock preventing the readers in other processes from
+    /// checking their leases while the garbage collection runs.
+    ///
+    /// Returns `None` if the index is not stored on the local filesystem,
+    /// as it then cannot be leased.
+    pub(crate) fn lock_garbage_collection(&self) -> Result<Option<GcLock>> {
+        match self.directory.local_path(Path::new("")) {
+            Some(index_path) => GcLock::acquire(&index_path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the files leased by the readers in other processes.
+    ///
+    /// See `.lock_garbage_collection()`.
+    pub(crate) fn leased_files(&self) -> Result<HashSet<PathBuf>> {
+        match self.directory.local_path(Path::new("")) {
+            Some(index_path) => reader_lease::leased_files(&index_path),
+            None => Ok(HashSet::new()),
+        }
+    }
+
+    /// Returns the files used by the pinned searchers.
+    pub(crate) fn pinned_files(&self) -> HashSet<PathBuf> {
+        self.pinned_searchers
+            .lock()
+            .expect("Pinned searchers lock poisoned")
+            .values()
+            .flat_map(|segment_readers| segment_readers.iter())
+            .flat_map(|segment_reader| segment_reader.segment_meta().list_files())
+            .collect()
+    }
 }
 
 impl fmt::Debug for Index {
@@ -249,8 +1502,23 @@ impl Clone for Index {
         Index {
             directory: self.directory.clone(),
             schema: self.schema.clone(),
+            settings: self.settings.clone(),
             searcher_pool: Arc::clone(&self.searcher_pool),
+            num_searchers: Arc::clone(&self.num_searchers),
+            warmers: Arc::clone(&self.warmers),
+            segment_warmers: Arc::clone(&self.segment_warmers),
+            custom_component_writers: Arc::clone(&self.custom_component_writers),
+            merge_listeners: Arc::clone(&self.merge_listeners),
+            pinned_searchers: Arc::clone(&self.pinned_searchers),
+            pin_id_seed: self.pin_id_seed,
+            next_pin_id: Arc::clone(&self.next_pin_id),
+            searcher_generation: Arc::clone(&self.searcher_generation),
+            search_executor: Arc::clone(&self.search_executor),
+            memory_accountant: Arc::clone(&self.memory_accountant),
+            field_boosts: Arc::clone(&self.field_boosts),
+            bm25_params: Arc::clone(&self.bm25_params),
             tokenizers: self.tokenizers.clone(),
+            read_only: self.read_only,
         }
     }
 }
@@ -1,7 +1,104 @@
 use schema::Schema;
 use core::SegmentMeta;
+use std::collections::BTreeMap;
 use std::fmt;
 use serde_json;
+use store::{Compressor, DEFAULT_BLOCK_SIZE};
+
+fn default_docstore_blocksize() -> usize {
+    DEFAULT_BLOCK_SIZE
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+/// Search preset of an `Index`.
+///
+/// The preset is persisted with the settings of the index, so that
+/// every consumer of the index, in any process, parses the queries and
+/// scores the matches consistently without out-of-band configuration.
+/// See `Index::query_parser`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchPreset {
+    /// Names of the fields searched when a query does not target a field.
+    pub default_fields: Vec<String>,
+    /// If true, the terms of a query are combined with `AND` rather than `OR`.
+    #[serde(default)]
+    pub conjunction_by_default: bool,
+    /// Boosts applied to the scores of the matches on a field,
+    /// by field name. See `Index::set_field_boost`.
+    #[serde(default)]
+    pub field_boosts: BTreeMap<String, f32>,
+}
+
+/// Behavior of `IndexWriter::add_document` when a document
+/// with the same content hash already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DedupMode {
+    /// The new document is not added.
+    Skip,
+    /// The existing documents are deleted, and the new document is added.
+    Replace,
+}
+
+/// Deduplication of the documents at index time.
+///
+/// The `IndexWriter` computes a hash of the values of `fields`,
+/// and indexes it in `hash_field`, replacing any value given
+/// in the document. Two documents are duplicates if they have the
+/// same hash, that is, in practice, the same values for `fields`,
+/// in the same order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DedupSettings {
+    /// Name of the field holding the hash.
+    /// It must be an indexed `u64` field.
+    pub hash_field: String,
+    /// Names of the fields whose values are hashed.
+    pub fields: Vec<String>,
+    /// What to do with the duplicates.
+    pub mode: DedupMode,
+}
+
+/// Settings of an `Index`.
+///
+/// These settings are defined when the index is created,
+/// and persisted in the `meta.json` file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexSettings {
+    /// Compressor used for the blocks of the doc store.
+    #[serde(default)]
+    pub docstore_compression: Compressor,
+    /// Size (in bytes) above which a block of the doc store
+    /// gets compressed and written.
+    #[serde(default = "default_docstore_blocksize")]
+    pub docstore_blocksize: usize,
+    /// Default search fields, operator and field boosts of the index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_preset: Option<SearchPreset>,
+    /// Number of previous commits kept besides the last one.
+    ///
+    /// The files of these commits are not garbage collected,
+    /// so that the index can be rolled back to any of them.
+    /// See `Index::list_commits` and `IndexWriter::rollback_to`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub num_kept_commits: usize,
+    /// Deduplication of the documents added by the `IndexWriter`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<DedupSettings>,
+}
+
+impl Default for IndexSettings {
+    fn default() -> IndexSettings {
+        IndexSettings {
+            docstore_compression: Compressor::default(),
+            docstore_blocksize: DEFAULT_BLOCK_SIZE,
+            search_preset: None,
+            num_kept_commits: 0,
+            dedup: None,
+        }
+    }
+}
 
 /// Meta information about the `Index`.
 ///
@@ -10,23 +107,47 @@ use serde_json;
 /// * the searchable segments,
 /// * the index `docstamp`
 /// * the schema
+/// * the index settings
 ///
 #[derive(Clone, Serialize, Deserialize)]
 pub struct IndexMeta {
     pub segments: Vec<SegmentMeta>,
     pub schema: Schema,
+    #[serde(default)]
+    pub index_settings: IndexSettings,
     pub opstamp: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub payload: Option<String>,
+    /// Number of times the `meta.json` was saved.
+    ///
+    /// Readers in other processes use it to detect that
+    /// a new commit was published.
+    #[serde(default)]
+    pub generation: u64,
+    /// Generations of the previous commits that are kept,
+    /// from the oldest to the most recent.
+    ///
+    /// The meta information of each of these commits
+    /// is saved in a `meta.{generation}.json` file.
+    /// See `IndexSettings::num_kept_commits`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_generations: Vec<u64>,
 }
 
 impl IndexMeta {
     pub fn with_schema(schema: Schema) -> IndexMeta {
+        IndexMeta::with_schema_and_settings(schema, IndexSettings::default())
+    }
+
+    pub fn with_schema_and_settings(schema: Schema, index_settings: IndexSettings) -> IndexMeta {
         IndexMeta {
             segments: vec![],
             schema,
+            index_settings,
             opstamp: 0u64,
             payload: None,
+            generation: 0u64,
+            previous_generations: Vec::new(),
         }
     }
 }
@@ -41,7 +162,7 @@ impl fmt::Debug for IndexMeta {
 mod tests {
 
     use serde_json;
-    use super::IndexMeta;
+    use super::{IndexMeta, IndexSettings};
     use schema::{SchemaBuilder, TEXT};
 
     #[test]
@@ -54,10 +175,13 @@ mod tests {
         let index_metas = IndexMeta {
             segments: Vec::new(),
             schema: schema,
+            index_settings: IndexSettings::default(),
             opstamp: 0u64,
             payload: None,
+            generation: 0u64,
+            previous_generations: Vec::new(),
         };
         let json = serde_json::ser::to_string(&index_metas).expect("serialization failed");
-        assert_eq!(json, r#"{"segments":[],"schema":[{"name":"text","type":"text","options":{"indexing":{"record":"position","tokenizer":"default"},"stored":false}}],"opstamp":0}"#);
+        assert_eq!(json, r#"{"segments":[],"schema":[{"name":"text","type":"text","options":{"indexing":{"record":"position","tokenizer":"default"},"stored":false}}],"index_settings":{"docstore_compression":"lz4","docstore_blocksize":16384},"opstamp":0,"generation":0}"#);
     }
 }
@@ -27,6 +27,7 @@ pub struct InvertedIndexReader {
     positions_source: ReadOnlySource,
     delete_bitset: DeleteBitSet,
     record_option: IndexRecordOption,
+    per_term_record_option: bool,
 }
 
 impl InvertedIndexReader {
@@ -36,6 +37,7 @@ impl InvertedIndexReader {
         positions_source: ReadOnlySource,
         delete_bitset: DeleteBitSet,
         record_option: IndexRecordOption,
+        per_term_record_option: bool,
     ) -> InvertedIndexReader {
         InvertedIndexReader {
             termdict: TermDictionaryImpl::from_source(termdict_source),
@@ -43,9 +45,35 @@ impl InvertedIndexReader {
             positions_source,
             delete_bitset,
             record_option,
+            per_term_record_option,
         }
     }
 
+    /// Returns the information recorded for the term.
+    ///
+    /// It is the `IndexRecordOption` of the field, unless the field
+    /// has a `freqs_min_doc_freq` and the term is too rare
+    /// to have its term frequencies recorded.
+    pub fn term_record_option(&self, term_info: &TermInfo) -> IndexRecordOption {
+        if self.per_term_record_option {
+            let offset = term_info.postings_offset as usize;
+            let code = self.postings_source.as_slice()[offset];
+            IndexRecordOption::from_code(code)
+                .expect("Index corrupted. Invalid record option in the postings.")
+        } else {
+            self.record_option
+        }
+    }
+
+    /// Returns the postings data of the term, after its record option if any.
+    fn postings_data(&self, term_info: &TermInfo) -> ReadOnlySource {
+        let mut offset = term_info.postings_offset as usize;
+        if self.per_term_record_option {
+            offset += 1;
+        }
+        self.postings_source.slice_from(offset)
+    }
+
     /// Returns the term info associated with the term.
     pub fn get_term_info(&self, term: &Term) -> Option<TermInfo> {
         self.termdict.get(term.value_bytes())
@@ -71,11 +99,9 @@ impl InvertedIndexReader {
         term_info: &TermInfo,
         block_postings: &mut BlockSegmentPostings,
     ) {
-        let offset = term_info.postings_offset as usize;
-        let end_source = self.postings_source.len();
-        let postings_slice = self.postings_source.slice(offset, end_source);
-        let postings_reader = SourceRead::from(postings_slice);
-        block_postings.reset(term_info.doc_freq as usize, postings_reader);
+        let postings_reader = SourceRead::from(self.postings_data(term_info));
+        let term_has_freqs = self.term_record_option(term_info).is_termfreq_enabled();
+        block_postings.reset(term_info.doc_freq as usize, postings_reader, term_has_freqs);
     }
 
     /// Returns a block postings given a `term_info`.
@@ -87,9 +113,8 @@ impl InvertedIndexReader {
         term_info: &TermInfo,
         requested_option: IndexRecordOption,
     ) -> BlockSegmentPostings {
-        let offset = term_info.postings_offset as usize;
-        let postings_data = self.postings_source.slice_from(offset);
-        let freq_reading_option = match (self.record_option, requested_option) {
+        let postings_data = self.postings_data(term_info);
+        let freq_reading_option = match (self.term_record_option(term_info), requested_option) {
             (IndexRecordOption::Basic, _) => FreqReadingOption::NoFreq,
             (_, IndexRecordOption::Basic) => FreqReadingOption::SkipFreq,
             (_, _) => FreqReadingOption::ReadFreq,
@@ -98,6 +123,7 @@ impl InvertedIndexReader {
             term_info.doc_freq as usize,
             SourceRead::from(postings_data),
             freq_reading_option,
+            requested_option.is_termfreq_enabled(),
         )
     }
 
@@ -112,8 +138,9 @@ impl InvertedIndexReader {
     ) -> SegmentPostings {
         let block_postings = self.read_block_postings_from_terminfo(term_info, option);
         let delete_bitset = self.delete_bitset.clone();
+        let has_offsets = self.term_record_option(term_info).has_offsets();
         let position_stream = {
-            if option.has_positions() {
+            if option.has_positions() && self.term_record_option(term_info).has_positions() {
                 let position_offset = term_info.positions_offset;
                 let positions_source = self.positions_source.slice_from(position_offset as usize);
                 let mut stream = CompressedIntStream::wrap(positions_source);
@@ -123,7 +150,12 @@ impl InvertedIndexReader {
                 None
             }
         };
-        SegmentPostings::from_block_postings(block_postings, delete_bitset, position_stream)
+        SegmentPostings::from_block_postings_with_offsets(
+            block_postings,
+            delete_bitset,
+            position_stream,
+            has_offsets,
+        )
     }
 
     /// Returns the segment postings associated with the term, and with the given option,
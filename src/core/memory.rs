@@ -0,0 +1,234 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+const NUM_MEMORY_COMPONENTS: usize = 3;
+
+/// Component whose memory usage is accounted by a `MemoryAccountant`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum MemoryComponent {
+    /// Memory arenas in which the indexing threads build their segments.
+    IndexingArena,
+    /// Files held in memory by a `CachingDirectory`, and blocks
+    /// of files held by an `ObjectStoreDirectory`.
+    SourceCache,
+    /// Delete bitsets computed while applying deletes to segments.
+    DeleteBitSet,
+}
+
+impl MemoryComponent {
+    fn ord(&self) -> usize {
+        match *self {
+            MemoryComponent::IndexingArena => 0,
+            MemoryComponent::SourceCache => 1,
+            MemoryComponent::DeleteBitSet => 2,
+        }
+    }
+}
+
+/// Snapshot of the memory usage of each component, in bytes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryUsage {
+    num_bytes: [usize; NUM_MEMORY_COMPONENTS],
+}
+
+impl MemoryUsage {
+    /// Returns the number of bytes used by `component`.
+    pub fn component(&self, component: MemoryComponent) -> usize {
+        self.num_bytes[component.ord()]
+    }
+
+    /// Returns the number of bytes used by all of the components.
+    pub fn total(&self) -> usize {
+        self.num_bytes.iter().sum()
+    }
+}
+
+/// Decides whether memory may be allocated.
+///
+/// Components asking for memory they can do without are refused
+/// gracefully: the indexing threads flush their segment earlier,
+/// and the caches evict entries or skip caching.
+pub trait MemoryLimiter: Send + Sync + 'static {
+    /// Returns true if `num_bytes` more bytes may be allocated
+    /// by `component`, given the current memory usage.
+    fn allows(&self, usage: &MemoryUsage, component: MemoryComponent, num_bytes: usize) -> bool;
+}
+
+/// Limiter capping the total memory usage of the components.
+pub struct MemoryCap {
+    cap_in_bytes: usize,
+}
+
+impl MemoryCap {
+    /// Creates a limiter refusing any allocation
+    /// beyond `cap_in_bytes` in total.
+    pub fn new(cap_in_bytes: usize) -> MemoryCap {
+        MemoryCap { cap_in_bytes }
+    }
+}
+
+impl MemoryLimiter for MemoryCap {
+    fn allows(&self, usage: &MemoryUsage, _: MemoryComponent, num_bytes: usize) -> bool {
+        usage.total() + num_bytes <= self.cap_in_bytes
+    }
+}
+
+#[derive(Default)]
+struct InnerMemoryAccountant {
+    num_bytes: [AtomicUsize; NUM_MEMORY_COMPONENTS],
+    // also serializes the reservations that may be refused,
+    // so that concurrent reservations cannot exceed the limit.
+    limiter: Mutex<Option<Box<MemoryLimiter>>>,
+}
+
+/// Accounts the memory used by tantivy, per component.
+///
+/// A single accountant may be shared by several indexes and
+/// caching directories, so that their memory usage is accounted,
+/// and capped, as a whole.
+///
+/// Memory is accounted via `MemoryReservation`s, which release
+/// their bytes when they are dropped.
+/// By default, no limiter is set and all reservations are accepted.
+#[derive(Clone, Default)]
+pub struct MemoryAccountant(Arc<InnerMemoryAccountant>);
+
+impl MemoryAccountant {
+    /// Sets the limiter deciding whether memory may be allocated.
+    ///
+    /// The memory already reserved is not affected.
+    pub fn set_limiter(&self, limiter: Box<MemoryLimiter>) {
+        *self.0
+            .limiter
+            .lock()
+            .expect("Memory limiter lock poisoned") = Some(limiter);
+    }
+
+    /// Returns the current memory usage.
+    pub fn usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+        for (num_bytes, counter) in usage.num_bytes.iter_mut().zip(self.0.num_bytes.iter()) {
+            *num_bytes = counter.load(Ordering::SeqCst);
+        }
+        usage
+    }
+
+    /// Reserves `num_bytes` for `component`, regardless of the limiter.
+    ///
+    /// This is used to account for memory that cannot be done without.
+    pub fn reserve(&self, component: MemoryComponent, num_bytes: usize) -> MemoryReservation {
+        self.0.num_bytes[component.ord()].fetch_add(num_bytes, Ordering::SeqCst);
+        MemoryReservation {
+            accountant: self.clone(),
+            component,
+            num_bytes,
+        }
+    }
+
+    /// Reserves `num_bytes` for `component`, or returns `None`
+    /// if the limiter refuses the allocation.
+    pub fn try_reserve(
+        &self,
+        component: MemoryComponent,
+        num_bytes: usize,
+    ) -> Option<MemoryReservation> {
+        if self.acquire(component, num_bytes) {
+            Some(MemoryReservation {
+                accountant: self.clone(),
+                component,
+                num_bytes,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn acquire(&self, component: MemoryComponent, num_bytes: usize) -> bool {
+        let limiter = self.0.limiter.lock().expect("Memory limiter lock poisoned");
+        if let Some(ref limiter) = *limiter {
+            if !limiter.allows(&self.usage(), component, num_bytes) {
+                return false;
+            }
+        }
+        self.0.num_bytes[component.ord()].fetch_add(num_bytes, Ordering::SeqCst);
+        true
+    }
+
+    fn release(&self, component: MemoryComponent, num_bytes: usize) {
+        self.0.num_bytes[component.ord()].fetch_sub(num_bytes, Ordering::SeqCst);
+    }
+}
+
+impl fmt::Debug for MemoryAccountant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MemoryAccountant({:?})", self.usage())
+    }
+}
+
+/// Memory reserved for a component.
+///
+/// The memory is released when the reservation is dropped.
+pub struct MemoryReservation {
+    accountant: MemoryAccountant,
+    component: MemoryComponent,
+    num_bytes: usize,
+}
+
+impl MemoryReservation {
+    /// Returns the number of bytes reserved.
+    pub fn num_bytes(&self) -> usize {
+        self.num_bytes
+    }
+
+    /// Reserves `num_bytes` more bytes, or returns false
+    /// if the limiter refuses the allocation.
+    pub fn try_grow(&mut self, num_bytes: usize) -> bool {
+        if self.accountant.acquire(self.component, num_bytes) {
+            self.num_bytes += num_bytes;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.accountant.release(self.component, self.num_bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{MemoryAccountant, MemoryCap, MemoryComponent};
+
+    #[test]
+    fn test_memory_accountant() {
+        let accountant = MemoryAccountant::default();
+        let reservation = accountant.try_reserve(MemoryComponent::SourceCache, 100);
+        assert!(reservation.is_some());
+        accountant.set_limiter(box MemoryCap::new(150));
+        let mut arena = accountant
+            .try_reserve(MemoryComponent::IndexingArena, 50)
+            .unwrap();
+        assert!(!arena.try_grow(1));
+        assert!(accountant
+            .try_reserve(MemoryComponent::SourceCache, 1)
+            .is_none());
+        {
+            let _bitset = accountant.reserve(MemoryComponent::DeleteBitSet, 10);
+            let usage = accountant.usage();
+            assert_eq!(usage.component(MemoryComponent::IndexingArena), 50);
+            assert_eq!(usage.component(MemoryComponent::SourceCache), 100);
+            assert_eq!(usage.component(MemoryComponent::DeleteBitSet), 10);
+            assert_eq!(usage.total(), 160);
+        }
+        drop(reservation);
+        assert!(arena.try_grow(100));
+        assert_eq!(arena.num_bytes(), 150);
+        drop(arena);
+        assert_eq!(accountant.usage().total(), 0);
+    }
+}
@@ -7,18 +7,39 @@ mod segment;
 mod index_meta;
 mod pool;
 mod segment_meta;
+mod segment_info;
 mod inverted_index_reader;
+mod multi_searcher;
+mod doc_export;
+mod scored_docs;
+mod executor;
+mod space_usage;
+mod memory;
+mod terms_by_doc_freq;
+mod reader_lease;
+mod repair_report;
 
 pub use self::inverted_index_reader::InvertedIndexReader;
-pub use self::searcher::Searcher;
+pub use self::searcher::{SearchOutcome, Searcher, SearcherToken};
+pub use self::multi_searcher::MultiSearcher;
+pub use self::doc_export::DocExport;
+pub use self::scored_docs::ScoredDocs;
+pub use self::executor::Executor;
+pub use self::space_usage::{FieldSpaceUsage, SpaceUsage};
+pub use self::memory::{MemoryAccountant, MemoryCap, MemoryComponent, MemoryLimiter,
+                        MemoryReservation, MemoryUsage};
 pub use self::segment_component::SegmentComponent;
 pub use self::segment_id::SegmentId;
 pub use self::segment_reader::SegmentReader;
+pub use self::terms_by_doc_freq::TermsByDocFreq;
+pub use self::reader_lease::ReaderLease;
 pub use self::segment::Segment;
 pub use self::segment::SerializableSegment;
-pub use self::index::Index;
-pub use self::segment_meta::SegmentMeta;
-pub use self::index_meta::IndexMeta;
+pub use self::index::{CustomComponentWriter, Index, MergeListener, Warmer};
+pub use self::segment_meta::{FieldBounds, SegmentMeta};
+pub use self::segment_info::SegmentInfo;
+pub use self::repair_report::{LostSegment, RepairReport};
+pub use self::index_meta::{DedupMode, DedupSettings, IndexMeta, IndexSettings, SearchPreset};
 
 use std::path::PathBuf;
 
@@ -40,3 +61,9 @@ lazy_static! {
     /// If the process is killed and this file remains, it is safe to remove it manually.
     pub static ref LOCKFILE_FILEPATH: PathBuf = PathBuf::from(".tantivy-indexer.lock");
 }
+
+/// Returns the path of the copy of the meta file of
+/// a previous commit, kept to allow rolling back to it.
+pub(crate) fn commit_meta_filepath(generation: u64) -> PathBuf {
+    PathBuf::from(format!("meta.{}.json", generation))
+}
@@ -0,0 +1,136 @@
+use Result;
+use collector::Collector;
+use common::TimerTree;
+use core::{Index, Searcher};
+use error::ErrorKind;
+use query::Query;
+use schema::Document;
+use DocAddress;
+use DocId;
+
+/// Searches several indexes sharing the same schema,
+/// as if they were a single index.
+///
+/// The segments of all of the indexes are gathered in a single `Searcher`.
+/// Segment ordinals are assigned consecutively, index after index,
+/// so that `DocAddress`es are unique across indexes, and collectors
+/// produce a single merged result. Term statistics used for scoring
+/// are computed over all of the indexes.
+///
+/// This makes it possible for instance to split the documents
+/// in time-partitioned indexes, and drop old partitions cheaply.
+pub struct MultiSearcher {
+    searcher: Searcher,
+    segment_index_ords: Vec<usize>,
+}
+
+impl MultiSearcher {
+    /// Creates a `MultiSearcher` over the current searchers of `indexes`.
+    ///
+    /// Returns an `InvalidArgument` error if the indexes do not
+    /// all have the same schema.
+    pub fn new(indexes: &[Index]) -> Result<MultiSearcher> {
+        let mut segment_readers = Vec::new();
+        let mut segment_index_ords = Vec::new();
+        if let Some(first_index) = indexes.first() {
+            let schema = first_index.schema();
+            for (index_ord, index) in indexes.iter().enumerate() {
+                if index.schema() != schema {
+                    bail!(ErrorKind::InvalidArgument(format!(
+                        "The schema of index #{} differs from the schema of index #0.",
+                        index_ord
+                    )));
+                }
+                let searcher = index.searcher();
+                for segment_reader in searcher.segment_readers() {
+                    segment_readers.push(segment_reader.clone());
+                    segment_index_ords.push(index_ord);
+                }
+            }
+        }
+        Ok(MultiSearcher {
+            searcher: Searcher::from(segment_readers),
+            segment_index_ords,
+        })
+    }
+
+    /// Returns the `Searcher` spanning the segments of all of the indexes.
+    pub fn searcher(&self) -> &Searcher {
+        &self.searcher
+    }
+
+    /// Runs a query on all of the indexes.
+    pub fn search<C: Collector>(&self, query: &Query, collector: &mut C) -> Result<TimerTree> {
+        self.searcher.search(query, collector)
+    }
+
+    /// Fetches a document given a `DocAddress` returned by a collector.
+    pub fn doc(&self, doc_address: &DocAddress) -> Result<Document> {
+        self.searcher.doc(doc_address)
+    }
+
+    /// Returns the overall number of documents in all of the indexes.
+    pub fn num_docs(&self) -> DocId {
+        self.searcher.num_docs()
+    }
+
+    /// Returns the ordinal, within the indexes given to `MultiSearcher::new`,
+    /// of the index a document belongs to.
+    pub fn index_ord(&self, doc_address: &DocAddress) -> usize {
+        self.segment_index_ords[doc_address.segment_ord() as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use collector::TopCollector;
+    use query::TermQuery;
+    use schema::{IndexRecordOption, SchemaBuilder, Term, STRING, TEXT};
+
+    #[test]
+    fn test_multi_searcher() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let indexes: Vec<Index> = (0..2)
+            .map(|_| Index::create_in_ram(schema.clone()))
+            .collect();
+        for (index, texts) in indexes.iter().zip(&[&["a b", "b"][..], &["a", "c"][..]]) {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for text in texts.iter() {
+                index_writer.add_document(doc!(text_field => *text));
+            }
+            index_writer.commit().unwrap();
+            index.load_searchers().unwrap();
+        }
+        let multi_searcher = MultiSearcher::new(&indexes).unwrap();
+        assert_eq!(multi_searcher.num_docs(), 4);
+        assert_eq!(multi_searcher.searcher().segment_readers().len(), 2);
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        let mut collector = TopCollector::with_limit(10);
+        multi_searcher.search(&term_query, &mut collector).unwrap();
+        let mut index_ords: Vec<usize> = collector
+            .docs()
+            .iter()
+            .map(|doc_address| multi_searcher.index_ord(doc_address))
+            .collect();
+        index_ords.sort();
+        assert_eq!(index_ords, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_multi_searcher_incompatible_schemas() {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut other_schema_builder = SchemaBuilder::default();
+        other_schema_builder.add_text_field("text", STRING);
+        let other_index = Index::create_in_ram(other_schema_builder.build());
+        assert!(MultiSearcher::new(&[index, other_index]).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use Result;
+use serde_json;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+const LEASE_FILE_PREFIX: &str = ".tantivy-reader-";
+const LEASE_FILE_SUFFIX: &str = ".lease";
+const GC_LOCK_FILE_NAME: &str = ".tantivy-gc.lock";
+/// Age after which a garbage collection lock is considered
+/// left behind by a crashed process.
+const GC_LOCK_TIMEOUT_SECS: u64 = 30;
+const GC_LOCK_RETRY_DELAY_MS: u64 = 5;
+
+/// Content of a lease file.
+#[derive(Serialize, Deserialize)]
+struct LeaseRecord {
+    pid: u32,
+    generation: u64,
+    expires_at: u64,
+    files: Vec<PathBuf>,
+}
+
+fn now_in_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0u64)
+}
+
+fn is_lease_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .map(|file_name| {
+            file_name.starts_with(LEASE_FILE_PREFIX) && file_name.ends_with(LEASE_FILE_SUFFIX)
+        })
+        .unwrap_or(false)
+}
+
+/// Lease advertising the files used by a reader process,
+/// so that a writer in another process does not garbage collect them.
+///
+/// A lease is a small file stored in the index directory.
+/// It expires after its time to live, unless it is renewed,
+/// so that a crashed reader does not prevent the garbage
+/// collection of the files forever. The lease file is
+/// removed when the `ReaderLease` is dropped.
+///
+/// See `Index::acquire_reader_lease`.
+pub struct ReaderLease {
+    path: PathBuf,
+    ttl: Duration,
+    generation: u64,
+    files: Vec<PathBuf>,
+}
+
+impl ReaderLease {
+    pub(crate) fn create(
+        index_path: &Path,
+        ttl: Duration,
+        generation: u64,
+        files: Vec<PathBuf>,
+    ) -> Result<ReaderLease> {
+        let file_name = format!(
+            "{}{}{}",
+            LEASE_FILE_PREFIX,
+            Uuid::new_v4().simple(),
+            LEASE_FILE_SUFFIX
+        );
+        let mut lease = ReaderLease {
+            path: index_path.join(file_name),
+            ttl,
+            generation,
+            files,
+        };
+        lease.write()?;
+        Ok(lease)
+    }
+
+    /// Returns the generation of the `meta.json` the leased files belong to.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the leased files.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files[..]
+    }
+
+    pub(crate) fn update(&mut self, generation: u64, files: Vec<PathBuf>) -> Result<()> {
+        self.generation = generation;
+        self.files = files;
+        self.write()
+    }
+
+    fn write(&mut self) -> Result<()> {
+        let record = LeaseRecord {
+            pid: process::id(),
+            generation: self.generation,
+            expires_at: now_in_secs() + self.ttl.as_secs(),
+            files: self.files.clone(),
+        };
+        let data = serde_json::to_vec(&record)?;
+        // the lease is written to a temporary file and renamed,
+        // so that the writer never reads a half-written lease.
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, &data)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl Drop for ReaderLease {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            error!("Failed to remove the reader lease {:?}. {:?}", self.path, e);
+        }
+    }
+}
+
+/// Short-lived lock coordinating the garbage collection of the writer
+/// with the readers checking their leases.
+///
+/// The writer holds it from the moment it reads the leases until
+/// the unused files are deleted. A reader holds it while checking
+/// that the files of its lease, written beforehand, still exist.
+/// Either the garbage collection reads the lease, or the reader sees
+/// that the files were deleted, and reloads its searchers.
+///
+/// The lock is a file created in the index directory, removed on drop.
+pub(crate) struct GcLock {
+    path: PathBuf,
+}
+
+impl GcLock {
+    /// Acquires the lock of the index stored in `index_path`,
+    /// waiting for its current holder to release it.
+    pub(crate) fn acquire(index_path: &Path) -> Result<GcLock> {
+        let path = index_path.join(GC_LOCK_FILE_NAME);
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(GcLock { path }),
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    if is_stale_lock(&path) {
+                        warn!("Removing the stale garbage collection lock {:?}", path);
+                        if let Err(e) = fs::remove_file(&path) {
+                            if e.kind() != io::ErrorKind::NotFound {
+                                return Err(e.into());
+                            }
+                        }
+                    } else {
+                        thread::sleep(Duration::from_millis(GC_LOCK_RETRY_DELAY_MS));
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for GcLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            error!("Failed to remove the garbage collection lock {:?}. {:?}", self.path, e);
+        }
+    }
+}
+
+fn is_stale_lock(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|age| age.as_secs() >= GC_LOCK_TIMEOUT_SECS)
+        .unwrap_or(false)
+}
+
+/// Returns the files leased by the readers of the index
+/// stored in `index_path`.
+///
+/// Expired leases are removed. Corrupted leases are logged and ignored.
+/// The caller should hold the `GcLock` until the files that are
+/// not leased are deleted.
+pub(crate) fn leased_files(index_path: &Path) -> Result<HashSet<PathBuf>> {
+    let now = now_in_secs();
+    let mut files = HashSet::new();
+    for entry in fs::read_dir(index_path)? {
+        let path = entry?.path();
+        if !is_lease_file(&path) {
+            continue;
+        }
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            // the lease was released in the meantime.
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        // leases are written atomically: a lease that cannot be parsed
+        // was not written by a reader, and does not protect any file.
+        let record: LeaseRecord = match serde_json::from_slice(&data) {
+            Ok(record) => record,
+            Err(e) => {
+                warn!("Ignoring the corrupted lease {:?}. {:?}", path, e);
+                continue;
+            }
+        };
+        if record.expires_at < now {
+            info!("Removing the expired lease of the reader process {}", record.pid);
+            if let Err(e) = fs::remove_file(&path) {
+                error!("Failed to remove the expired lease {:?}. {:?}", path, e);
+            }
+            continue;
+        }
+        files.extend(record.files);
+    }
+    Ok(files)
+}
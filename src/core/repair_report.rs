@@ -0,0 +1,38 @@
+use core::SegmentId;
+use std::path::PathBuf;
+
+/// A segment dropped by `Index::repair`, as one of its
+/// files was missing, truncated or corrupted.
+#[derive(Clone, Debug)]
+pub struct LostSegment {
+    /// Id of the segment.
+    pub segment_id: SegmentId,
+    /// Number of alive documents of the segment.
+    pub num_docs: u32,
+    /// Path of the first unusable file of the segment.
+    pub path: PathBuf,
+    /// Description of the problem.
+    pub detail: String,
+}
+
+/// Outcome of `Index::repair`.
+#[derive(Clone, Debug, Default)]
+pub struct RepairReport {
+    /// Segments dropped from the index.
+    pub lost_segments: Vec<LostSegment>,
+}
+
+impl RepairReport {
+    /// Returns true iff no segment had to be dropped.
+    pub fn is_healthy(&self) -> bool {
+        self.lost_segments.is_empty()
+    }
+
+    /// Returns the number of documents lost.
+    pub fn num_lost_docs(&self) -> u64 {
+        self.lost_segments
+            .iter()
+            .map(|lost_segment| u64::from(lost_segment.num_docs))
+            .sum()
+    }
+}
@@ -0,0 +1,119 @@
+use Result;
+use core::Searcher;
+use docset::DocSet;
+use query::{create_weight, Query, Scorer, Weight};
+use DocAddress;
+use Score;
+use SegmentLocalId;
+
+/// Iterator over the scored documents matching a query,
+/// as returned by `Searcher::docs_for_query`.
+///
+/// Documents are scored lazily, one at a time, in the order
+/// of their `DocAddress`. Consumers post-processing every hit
+/// (exporters, joins, ...) can therefore iterate over the hits
+/// without implementing a `Collector`.
+pub struct ScoredDocs<'a> {
+    searcher: &'a Searcher,
+    weight: Box<Weight>,
+    segment_ord: usize,
+    scorer: Option<Box<Scorer>>,
+}
+
+impl<'a> ScoredDocs<'a> {
+    pub(crate) fn new(searcher: &'a Searcher, query: &Query) -> Result<ScoredDocs<'a>> {
+        Ok(ScoredDocs {
+            searcher,
+            weight: create_weight(query, searcher, true)?,
+            segment_ord: 0,
+            scorer: None,
+        })
+    }
+
+    fn next_doc(&mut self) -> Result<Option<(Score, DocAddress)>> {
+        let segment_readers = self.searcher.segment_readers();
+        while self.segment_ord < segment_readers.len() {
+            if self.scorer.is_none() {
+                let segment_reader = &segment_readers[self.segment_ord];
+                self.scorer = Some(self.weight.alive_scorer(segment_reader)?);
+            }
+            if let Some(ref mut scorer) = self.scorer {
+                if scorer.advance() {
+                    let doc_address = DocAddress(self.segment_ord as SegmentLocalId, scorer.doc());
+                    return Ok(Some((scorer.score(), doc_address)));
+                }
+            }
+            self.scorer = None;
+            self.segment_ord += 1;
+        }
+        Ok(None)
+    }
+}
+
+impl<'a> Iterator for ScoredDocs<'a> {
+    type Item = Result<(Score, DocAddress)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_doc() {
+            Ok(Some(scored_doc)) => Some(Ok(scored_doc)),
+            Ok(None) => None,
+            Err(e) => {
+                // stops the iteration after an error.
+                self.segment_ord = self.searcher.segment_readers().len();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::Index;
+    use collector::TopCollector;
+    use query::{Query, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, Term, TEXT};
+    use DocAddress;
+
+    #[test]
+    fn test_docs_for_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(title => "a b"));
+        index_writer.add_document(doc!(title => "b b c"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(title => "c"));
+        index_writer.add_document(doc!(title => "b"));
+        index_writer.commit().unwrap();
+        index_writer.delete_term(Term::from_field_text(title, "a"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(title, "b"),
+            IndexRecordOption::WithFreqs,
+        );
+        let scored_docs: Vec<_> = searcher
+            .docs_for_query(&query)
+            .unwrap()
+            .map(|scored_doc_res| scored_doc_res.unwrap())
+            .collect();
+        // `a b` is deleted.
+        assert_eq!(scored_docs.len(), 2);
+        let doc_addresses: Vec<DocAddress> = scored_docs
+            .iter()
+            .map(|&(_, doc_address)| doc_address)
+            .collect();
+        let mut sorted_doc_addresses = doc_addresses.clone();
+        sorted_doc_addresses.sort();
+        assert_eq!(doc_addresses, sorted_doc_addresses);
+        // the scores are the ones computed by the collectors.
+        let mut top_collector = TopCollector::with_limit(10);
+        query.search(&*searcher, &mut top_collector).unwrap();
+        let mut top_docs = top_collector.score_docs();
+        top_docs.sort_by_key(|&(_, doc_address)| doc_address);
+        assert_eq!(scored_docs, top_docs);
+    }
+}
@@ -1,35 +1,231 @@
 use Result;
 use core::SegmentReader;
 use schema::Document;
-use collector::Collector;
+use collector::{compare_score_docs, Collector, FruitCollector};
 use common::TimerTree;
-use query::Query;
+use query::{create_weight, ParallelQuery, Query};
 use DocId;
 use DocAddress;
-use schema::{Field, Term};
+use SegmentLocalId;
+use schema::{BM25Params, Field, Term};
 use termdict::{TermDictionary, TermMerger};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::cmp::Reverse;
+use error::ErrorKind;
+use query::Rescorer;
+use query::{BM25Similarity, Similarity};
+use Score;
 use std::fmt;
+use store::{BlockCache, DEFAULT_NUM_CACHED_BLOCKS};
+use directory::CacheCounters;
+use std::time::{Duration, Instant};
 use core::InvertedIndexReader;
+use core::DocExport;
+use core::ScoredDocs;
+use core::Executor;
+
+/// Outcome of a search with a time budget.
+///
+/// See `Searcher::search_with_budget`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchOutcome {
+    /// All of the matching documents were collected.
+    Complete,
+    /// The budget was exhausted, and the collector only
+    /// holds the documents collected before the deadline.
+    TimedOut,
+}
+
+impl SearchOutcome {
+    /// Returns true iff the search was interrupted by its deadline.
+    pub fn is_timed_out(&self) -> bool {
+        *self == SearchOutcome::TimedOut
+    }
+}
+
+/// Token identifying a searcher pinned with `Index::pin_searcher`.
+///
+/// The token is serializable, so that it can be handed over to a client,
+/// for instance to paginate over a consistent view of the index.
+///
+/// Tokens are local to the process, and to the `Index` (and its clones)
+/// that pinned them: the pinned segment readers live in memory.
+/// Their ids start at a random seed, so that a token pinned by
+/// another process is, in practice, never mistaken for a local one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SearcherToken(u64);
+
+impl SearcherToken {
+    /// Returns the id of the token.
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for SearcherToken {
+    fn from(id: u64) -> SearcherToken {
+        SearcherToken(id)
+    }
+}
 
 /// Holds a list of `SegmentReader`s ready for search.
 ///
 /// It guarantees that the `Segment` will not be removed before
 /// the destruction of the `Searcher`.
 ///
+/// The searcher also keeps a small LRU cache of the decompressed
+/// blocks of the doc stores, so that fetching several documents
+/// of the same block only decompresses it once.
+///
 pub struct Searcher {
     segment_readers: Vec<SegmentReader>,
+    field_boosts: Arc<HashMap<Field, Score>>,
+    bm25_params: Arc<HashMap<Field, BM25Params>>,
+    similarities: Arc<HashMap<Field, Arc<Similarity>>>,
+    doc_store_cache: Mutex<BlockCache>,
+    executor: Arc<Executor>,
 }
 
 impl Searcher {
+    /// Sets the per-field boosts of the searcher.
+    pub(crate) fn with_field_boosts(
+        mut self,
+        field_boosts: Arc<HashMap<Field, Score>>,
+    ) -> Searcher {
+        self.field_boosts = field_boosts;
+        self
+    }
+
+    /// Sets the executor searching the segments in parallel.
+    pub(crate) fn with_executor(mut self, executor: Arc<Executor>) -> Searcher {
+        self.executor = executor;
+        self
+    }
+
+    /// Returns the executor searching the segments in `.search_fruit_in_parallel(...)`.
+    ///
+    /// This is the executor of the index when the searcher
+    /// was created, see `Index::set_search_threads`.
+    pub fn executor(&self) -> &Executor {
+        &*self.executor
+    }
+
+    /// Returns the boost applied to the scores of the matches on `field`.
+    ///
+    /// Boosts are configured with `Index::set_field_boost`, and default to `1`.
+    /// Weights multiply the score of the matches of their field by this boost.
+    ///
+    /// The term, phrase, fuzzy, regex, range and join queries apply the
+    /// boost of their field, the latter four to their constant score.
+    /// The boolean and constant score queries leave the scores of their sub queries as is.
+    pub fn field_boost(&self, field: Field) -> Score {
+        self.field_boosts.get(&field).cloned().unwrap_or(1f32)
+    }
+
+    /// Sets the BM25 parameters overriding the ones of the schema.
+    pub(crate) fn with_bm25_params(
+        mut self,
+        bm25_params: Arc<HashMap<Field, BM25Params>>,
+    ) -> Searcher {
+        self.bm25_params = bm25_params;
+        self
+    }
+
+    /// Returns the parameters of the BM25 similarity used to score
+    /// the matches on `field`.
+    ///
+    /// The parameters set with `Index::set_bm25_params` take precedence
+    /// over the ones of the schema, and default to `BM25Params::default()`.
+    pub fn bm25_params(&self, field: Field) -> BM25Params {
+        self.bm25_params
+            .get(&field)
+            .cloned()
+            .or_else(|| {
+                self.segment_readers.first().and_then(|segment_reader| {
+                    segment_reader
+                        .schema()
+                        .get_field_entry(field)
+                        .field_type()
+                        .bm25_params()
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sets the similarities of the fields.
+    pub(crate) fn with_similarities(
+        mut self,
+        similarities: Arc<HashMap<Field, Arc<Similarity>>>,
+    ) -> Searcher {
+        self.similarities = similarities;
+        self
+    }
+
+    /// Returns the similarity used to score the matches on `field`.
+    ///
+    /// Similarities are configured with `Index::set_similarity`,
+    /// and default to `BM25Similarity`.
+    pub fn similarity(&self, field: Field) -> Arc<Similarity> {
+        self.similarities
+            .get(&field)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(BM25Similarity))
+    }
+
+    /// Returns the average number of tokens of `field` per document.
+    ///
+    /// As for `doc_freq`, deleted documents are included.
+    pub fn average_fieldnorm(&self, field: Field) -> Score {
+        let total_num_tokens: u64 = self.segment_readers
+            .iter()
+            .map(|segment_reader| segment_reader.total_num_tokens(field))
+            .sum();
+        let max_doc: u64 = self.segment_readers
+            .iter()
+            .map(|segment_reader| u64::from(segment_reader.max_doc()))
+            .sum();
+        if max_doc == 0 {
+            0f32
+        } else {
+            total_num_tokens as Score / max_doc as Score
+        }
+    }
+
     /// Fetches a document from tantivy's store given a `DocAddress`.
     ///
     /// The searcher uses the segment ordinal to route the
     /// the request to the right `Segment`.
     pub fn doc(&self, doc_address: &DocAddress) -> Result<Document> {
+        self.doc_with_cache(doc_address, None)
+    }
+
+    /// Fetches the values of some of the fields of a document
+    /// from tantivy's store, given a `DocAddress`.
+    ///
+    /// This is much cheaper than `.doc(...)` when documents
+    /// contain large fields that are not needed.
+    pub fn doc_fields(&self, doc_address: &DocAddress, fields: &[Field]) -> Result<Document> {
+        self.doc_with_cache(doc_address, Some(fields))
+    }
+
+    fn doc_with_cache(
+        &self,
+        doc_address: &DocAddress,
+        fields: Option<&[Field]>,
+    ) -> Result<Document> {
         let DocAddress(segment_local_id, doc_id) = *doc_address;
         let segment_reader = &self.segment_readers[segment_local_id as usize];
-        segment_reader.doc(doc_id)
+        segment_reader.doc_with_cache(doc_id, fields, segment_local_id, &self.doc_store_cache)
+    }
+
+    /// Returns the hit and miss counters of the cache of
+    /// decompressed doc store blocks.
+    pub fn doc_store_cache_counters(&self) -> CacheCounters {
+        self.doc_store_cache
+            .lock()
+            .expect("Block cache lock poisoned")
+            .counters()
     }
 
     /// Returns the overall number of documents in the index.
@@ -49,21 +245,267 @@ impl Searcher {
             .sum::<u32>()
     }
 
+    /// Returns the `k` terms of `field` with the highest doc frequency,
+    /// sorted by decreasing doc frequency.
+    ///
+    /// The doc frequencies are summed over the segments, and,
+    /// as for `doc_freq`, include the deleted documents.
+    /// The terms of each segment are read by decreasing doc frequency
+    /// until no unread term can make it to the top `k`, so that only
+    /// a fraction of the term dictionaries is typically read.
+    /// Among the terms with the same doc frequency, which of them make
+    /// it to the top `k` is unspecified.
+    ///
+    /// # Errors
+    /// Returns an error if a segment has no doc frequency index
+    /// for the field. See `Index::add_doc_freq_index`.
+    pub fn top_terms(&self, field: Field, k: usize) -> Result<Vec<(Term, u32)>> {
+        let mut segment_terms = Vec::with_capacity(self.segment_readers.len());
+        for segment_reader in &self.segment_readers {
+            match segment_reader.terms_by_doc_freq(field) {
+                Some(terms) => segment_terms.push(terms.peekable()),
+                None => bail!(ErrorKind::InvalidArgument(format!(
+                    "Segment {:?} has no doc frequency index for the field {:?}",
+                    segment_reader.segment_id(),
+                    field
+                ))),
+            }
+        }
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        let mut visited_terms: HashSet<Term> = HashSet::new();
+        let mut top_terms: BinaryHeap<Reverse<(u32, Term)>> = BinaryHeap::with_capacity(k + 1);
+        loop {
+            // An unvisited term cannot be more frequent
+            // than the sum of the next doc frequencies.
+            let threshold: u32 = segment_terms
+                .iter_mut()
+                .filter_map(|terms| terms.peek().map(|&(_, doc_freq)| doc_freq))
+                .sum();
+            if threshold == 0 {
+                break;
+            }
+            if top_terms.len() == k {
+                if let Some(&Reverse((lowest_doc_freq, _))) = top_terms.peek() {
+                    if lowest_doc_freq >= threshold {
+                        break;
+                    }
+                }
+            }
+            for terms in &mut segment_terms {
+                if let Some((term, _)) = terms.next() {
+                    if visited_terms.insert(term.clone()) {
+                        top_terms.push(Reverse((self.doc_freq(&term), term)));
+                        if top_terms.len() > k {
+                            top_terms.pop();
+                        }
+                    }
+                }
+            }
+        }
+        let mut top_terms: Vec<(Term, u32)> = top_terms
+            .into_iter()
+            .map(|Reverse((doc_freq, term))| (term, doc_freq))
+            .collect();
+        top_terms.sort_by(|left, right| right.1.cmp(&left.1).then(left.0.cmp(&right.0)));
+        Ok(top_terms)
+    }
+
     /// Return the list of segment readers
     pub fn segment_readers(&self) -> &[SegmentReader] {
         &self.segment_readers
     }
 
+    /// Resolves a unique key to the `DocAddress` of the alive document
+    /// holding it, without running a query.
+    ///
+    /// See `SegmentReader::doc_id_for_key`. `DocAddress`es are only valid
+    /// for this searcher, while keys are stable across merges.
+    /// If several segments contain the key, the document of the most
+    /// recently created segment is returned.
+    pub fn doc_address_for_key(&self, key: &Term) -> Option<DocAddress> {
+        self.segment_readers
+            .iter()
+            .enumerate()
+            .filter_map(|(segment_ord, segment_reader)| {
+                segment_reader.doc_id_for_key(key).map(|doc_id| {
+                    let create_opstamp = segment_reader.segment_meta().create_opstamp();
+                    (create_opstamp, DocAddress(segment_ord as SegmentLocalId, doc_id))
+                })
+            })
+            .max_by_key(|&(create_opstamp, _)| create_opstamp)
+            .map(|(_, doc_address)| doc_address)
+    }
+
+    /// Runs `f` on every segment reader, using the given executor,
+    /// and returns the results in the order of the segments.
+    ///
+    /// This makes it possible to search the segments in parallel
+    /// with the executor shared by all searches, `Index::search_executor()`.
+    pub fn map_segments<R, F>(&self, executor: &Executor, f: F) -> Result<Vec<R>>
+    where
+        R: Send + 'static,
+        F: Fn(SegmentLocalId, SegmentReader) -> Result<R> + Send + Sync + 'static,
+    {
+        let args = self.segment_readers
+            .iter()
+            .cloned()
+            .enumerate()
+            .map(|(segment_ord, segment_reader)| (segment_ord as SegmentLocalId, segment_reader))
+            .collect();
+        executor.map(
+            move |(segment_ord, segment_reader)| f(segment_ord, segment_reader),
+            args,
+        )
+    }
+
     /// Returns the segment_reader associated with the given segment_ordinal
     pub fn segment_reader(&self, segment_ord: u32) -> &SegmentReader {
         &self.segment_readers[segment_ord as usize]
     }
 
+    /// Reranks the top candidates of a search with a `Rescorer`.
+    ///
+    /// The candidates are typically the `score_docs()` of a `TopCollector`.
+    /// They are returned with their combined score, sorted by decreasing score.
+    pub fn rescore(
+        &self,
+        candidates: &[(Score, DocAddress)],
+        rescorer: &Rescorer,
+    ) -> Result<Vec<(Score, DocAddress)>> {
+        let weight = rescorer.weight(self)?;
+        let mut sorted_candidates: Vec<(DocAddress, Score)> = candidates
+            .iter()
+            .map(|&(score, doc_address)| (doc_address, score))
+            .collect();
+        sorted_candidates.sort_by_key(|&(doc_address, _)| doc_address);
+        let mut rescored = Vec::with_capacity(candidates.len());
+        for (segment_ord, segment_reader) in self.segment_readers.iter().enumerate() {
+            let segment_ord = segment_ord as SegmentLocalId;
+            let segment_candidates: Vec<(DocAddress, Score)> = sorted_candidates
+                .iter()
+                .cloned()
+                .filter(|&(doc_address, _)| doc_address.segment_ord() == segment_ord)
+                .collect();
+            if segment_candidates.is_empty() {
+                continue;
+            }
+            let docs: Vec<DocId> = segment_candidates
+                .iter()
+                .map(|&(doc_address, _)| doc_address.doc())
+                .collect();
+            let rescores = rescorer.rescore_segment(
+                weight.as_ref().map(|weight| &**weight),
+                segment_reader,
+                &docs,
+            )?;
+            for (&(doc_address, score), rescore) in segment_candidates.iter().zip(rescores) {
+                rescored.push((rescorer.combine(score, rescore), doc_address));
+            }
+        }
+        rescored.sort_by(compare_score_docs);
+        Ok(rescored)
+    }
+
+    /// Runs a query, and returns the fruit of the collector.
+    ///
+    /// Unlike `.search(...)`, the collector is consumed, and
+    /// its typed result is returned directly.
+    pub fn search_fruit<C: FruitCollector>(
+        &self,
+        query: &Query,
+        mut collector: C,
+    ) -> Result<C::Fruit> {
+        self.search(query, &mut collector)?;
+        Ok(collector.harvest())
+    }
+
+    /// Runs a query, searching the segments in parallel with the
+    /// executor of the searcher, and returns the fruit of the collector.
+    ///
+    /// Each segment is collected by a clone of `collector`, and their
+    /// fruits are merged with `.merge_fruits(...)`.
+    /// The weight of the query is shared by the threads, see `ParallelQuery`.
+    pub fn search_fruit_in_parallel<Q, C>(&self, query: &Q, collector: C) -> Result<C::Fruit>
+    where
+        Q: ParallelQuery + ?Sized,
+        C: FruitCollector + Clone + Send + 'static,
+        C::Fruit: Send + 'static,
+    {
+        let scoring_enabled = collector.requires_scoring() || collector.requires_match_info();
+        let weight = Arc::new(query.parallel_weight(self, scoring_enabled)?);
+        let segment_collector = Arc::new(Mutex::new(collector.clone()));
+        let fruits = self.map_segments(&self.executor, move |segment_ord, segment_reader| {
+            let mut segment_collector = segment_collector
+                .lock()
+                .expect("Collector lock poisoned")
+                .clone();
+            segment_collector.set_segment(segment_ord, &segment_reader)?;
+            let mut scorer = weight.alive_scorer(&segment_reader)?;
+            scorer.collect(&mut segment_collector);
+            Ok(segment_collector.harvest())
+        })?;
+        Ok(collector.merge_fruits(fruits))
+    }
+
     /// Runs a query on the segment readers wrapped by the searcher
+    ///
+    /// The segments are searched sequentially, in the calling thread.
     pub fn search<C: Collector>(&self, query: &Query, collector: &mut C) -> Result<TimerTree> {
         query.search(self, collector)
     }
 
+    /// Runs a query on the segment readers wrapped by the searcher,
+    /// stopping as soon as `budget` is exhausted.
+    ///
+    /// The deadline is checked before each segment and periodically
+    /// while collecting documents. On timeout, the collector holds the
+    /// partial results collected so far, and `SearchOutcome::TimedOut`
+    /// is returned.
+    pub fn search_with_budget<C: Collector>(
+        &self,
+        query: &Query,
+        collector: &mut C,
+        budget: Duration,
+    ) -> Result<SearchOutcome> {
+        let deadline = Instant::now() + budget;
+        let scoring_enabled = collector.requires_scoring() || collector.requires_match_info();
+        let weight = create_weight(query, self, scoring_enabled)?;
+        for (segment_ord, segment_reader) in self.segment_readers.iter().enumerate() {
+            if Instant::now() >= deadline {
+                return Ok(SearchOutcome::TimedOut);
+            }
+            collector.set_segment(segment_ord as SegmentLocalId, segment_reader)?;
+            let mut scorer = weight.alive_scorer(segment_reader)?;
+            if !scorer.collect_until(collector, deadline) {
+                return Ok(SearchOutcome::TimedOut);
+            }
+        }
+        Ok(SearchOutcome::Complete)
+    }
+
+    /// Returns an iterator over the stored documents matching a query,
+    /// for reindexing or ETL jobs.
+    ///
+    /// Only the given stored fields are fetched. `None` fetches
+    /// all of the stored fields.
+    /// Unlike collectors, the export does not score documents nor
+    /// hold the result set in memory. See `DocExport::write_json_lines`
+    /// to dump the documents as JSON lines.
+    pub fn export(&self, query: &Query, fields: Option<&[Field]>) -> Result<DocExport> {
+        DocExport::new(self, query, fields)
+    }
+
+    /// Returns an iterator over the `(score, doc_address)` of the
+    /// documents matching a query, in the order of their `DocAddress`.
+    ///
+    /// The documents are scored lazily, without a `Collector`,
+    /// and deleted documents are skipped.
+    pub fn docs_for_query(&self, query: &Query) -> Result<ScoredDocs> {
+        ScoredDocs::new(self, query)
+    }
+
     /// Return the field searcher associated to a `Field`.
     pub fn field(&self, field: Field) -> FieldSearcher {
         let inv_index_readers = self.segment_readers
@@ -96,7 +538,14 @@ impl FieldSearcher {
 
 impl From<Vec<SegmentReader>> for Searcher {
     fn from(segment_readers: Vec<SegmentReader>) -> Searcher {
-        Searcher { segment_readers }
+        Searcher {
+            segment_readers,
+            field_boosts: Arc::default(),
+            bm25_params: Arc::default(),
+            similarities: Arc::default(),
+            doc_store_cache: Mutex::new(BlockCache::with_capacity(DEFAULT_NUM_CACHED_BLOCKS)),
+            executor: Arc::default(),
+        }
     }
 }
 
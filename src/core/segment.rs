@@ -9,8 +9,10 @@ use super::SegmentComponent;
 use core::Index;
 use std::result;
 use directory::Directory;
-use core::SegmentMeta;
-use directory::error::{OpenReadError, OpenWriteError};
+use core::{SegmentInfo, SegmentMeta};
+use directory::error::{IOError, OpenReadError, OpenWriteError};
+use common::{Footer, FooterProxy};
+use std::io::BufWriter;
 
 /// A segment is a piece of the index.
 #[derive(Clone)]
@@ -76,24 +78,138 @@ impl Segment {
         self.index.directory().protect_file_from_delete(&path)
     }
 
+    fn read_file(&self, path: PathBuf) -> result::Result<ReadOnlySource, OpenReadError> {
+        let source = self.index.directory().open_read(&path)?;
+        if !self.meta.has_footer() {
+            return Ok(source);
+        }
+        let (_footer, data) =
+            Footer::extract(&source).map_err(|e| IOError::with_path(path, e))?;
+        Ok(data)
+    }
+
+    fn write_file(&mut self, path: PathBuf) -> result::Result<WritePtr, OpenWriteError> {
+        let write = self.index.directory_mut().open_write(&path)?;
+        if !self.meta.has_footer() {
+            return Ok(write);
+        }
+        Ok(BufWriter::new(Box::new(FooterProxy::new(write))))
+    }
+
     /// Open one of the component file for a *regular* read.
+    ///
+    /// The footer of the file is checked and stripped from the returned
+    /// source. Its checksum is not verified, as it would require
+    /// reading the entire file. See `Segment::validate`.
+    ///
+    /// Files of segments written before footers were introduced
+    /// are returned as is.
     pub fn open_read(
         &self,
         component: SegmentComponent,
     ) -> result::Result<ReadOnlySource, OpenReadError> {
         let path = self.relative_path(component);
-        let source = self.index.directory().open_read(&path)?;
-        Ok(source)
+        self.read_file(path)
     }
 
     /// Open one of the component file for *regular* write.
+    ///
+    /// A footer containing the checksum of the file is appended
+    /// to the file on flush, unless the segment was written
+    /// before footers were introduced.
     pub fn open_write(
         &mut self,
         component: SegmentComponent,
     ) -> result::Result<WritePtr, OpenWriteError> {
         let path = self.relative_path(component);
-        let write = self.index.directory_mut().open_write(&path)?;
-        Ok(write)
+        self.write_file(path)
+    }
+
+    /// Open a custom component file for a *regular* read.
+    ///
+    /// See `Index::add_custom_component`.
+    pub fn open_custom_read(&self, name: &str) -> result::Result<ReadOnlySource, OpenReadError> {
+        let path = self.meta.custom_component_path(name);
+        self.read_file(path)
+    }
+
+    /// Open a custom component file for *regular* write.
+    ///
+    /// As for the other components, a footer containing the
+    /// checksum of the file is appended to the file on flush.
+    pub fn open_custom_write(&mut self, name: &str) -> result::Result<WritePtr, OpenWriteError> {
+        let path = self.meta.custom_component_path(name);
+        self.write_file(path)
+    }
+
+    /// Returns the meta information of the segment, along
+    /// with the size on disk of each of its files.
+    pub fn info(&self) -> Result<SegmentInfo> {
+        let mut component_sizes = Vec::new();
+        for &component in SegmentComponent::iterator() {
+            if component == SegmentComponent::DELETE && !self.meta.has_deletes() {
+                continue;
+            }
+            let path = self.relative_path(component);
+            match self.index.directory().open_read(&path) {
+                Ok(source) => component_sizes.push((component, source.len() as u64)),
+                Err(OpenReadError::FileDoesNotExist(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(SegmentInfo {
+            segment_id: self.id(),
+            max_doc: self.meta.max_doc(),
+            num_deleted_docs: self.meta.num_deleted_docs(),
+            create_opstamp: self.meta.create_opstamp(),
+            component_sizes,
+        })
+    }
+
+    /// Verifies the checksum of all of the files of the segment.
+    ///
+    /// Returns a `CorruptedFile` error naming the first file
+    /// that is truncated or corrupted, or an `IncompatibleVersion`
+    /// error if a file was written in an unsupported format.
+    ///
+    /// The files of segments written before footers were introduced
+    /// have no checksum: only their presence is checked.
+    pub fn validate(&self) -> Result<()> {
+        for &component in SegmentComponent::iterator() {
+            let optional = match component {
+                SegmentComponent::DELETE => {
+                    if !self.meta.has_deletes() {
+                        continue;
+                    }
+                    false
+                }
+                SegmentComponent::POSITIONS => true,
+                _ => false,
+            };
+            let path = self.relative_path(component);
+            let source = match self.index.directory().open_read(&path) {
+                Ok(source) => source,
+                Err(OpenReadError::FileDoesNotExist(_)) if optional => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if !self.meta.has_footer() {
+                continue;
+            }
+            Footer::extract(&source)
+                .and_then(|(footer, data)| footer.verify(data.as_slice()))
+                .map_err(|e| IOError::with_path(path, e))?;
+        }
+        for name in self.meta.custom_components() {
+            let path = self.meta.custom_component_path(name);
+            let source = self.index.directory().open_read(&path)?;
+            if !self.meta.has_footer() {
+                continue;
+            }
+            Footer::extract(&source)
+                .and_then(|(footer, data)| footer.verify(data.as_slice()))
+                .map_err(|e| IOError::with_path(path, e))?;
+        }
+        Ok(())
     }
 }
 
@@ -109,11 +225,15 @@ pub trait SerializableSegment {
 #[cfg(test)]
 mod tests {
 
-    use core::SegmentComponent;
-    use directory::Directory;
+    use common::Footer;
+    use core::{SegmentComponent, META_FILEPATH};
+    use directory::{Directory, MmapDirectory};
+    use serde_json;
     use std::collections::HashSet;
-    use schema::SchemaBuilder;
-    use Index;
+    use schema::{SchemaBuilder, Term, FAST, INT_INDEXED, STORED, TEXT};
+    use store::Compressor;
+    use tempdir::TempDir;
+    use {DocAddress, Index};
 
     #[test]
     fn test_segment_protect_component() {
@@ -136,4 +256,90 @@ mod tests {
         assert!(!directory.exists(&*path));
     }
 
+    #[test]
+    fn test_segment_without_footer() {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let count_field = schema_builder.add_u64_field("count", FAST | INT_INDEXED);
+        let schema = schema_builder.build();
+        let segment_metas = {
+            let index = Index::create(tempdir.path(), schema).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "hello happy", count_field => 3u64));
+            index_writer.add_document(doc!(text_field => "hello", count_field => 7u64));
+            index_writer.commit().unwrap();
+            index.searchable_segment_metas().unwrap()
+        };
+        assert_eq!(segment_metas.len(), 1);
+        let segment_meta = &segment_metas[0];
+        assert!(segment_meta.has_footer());
+
+        // rewrite the segment files and the `meta.json` in the layout
+        // they had before footers were introduced.
+        let mut directory = MmapDirectory::open(tempdir.path()).unwrap();
+        for component in SegmentComponent::iterator() {
+            let path = segment_meta.relative_path(*component);
+            if !directory.exists(&path) {
+                continue;
+            }
+            let mut data = {
+                let source = directory.open_read(&path).unwrap();
+                let (_footer, data) = Footer::extract(&source).unwrap();
+                data.as_slice().to_vec()
+            };
+            if *component == SegmentComponent::STORE {
+                // the footer of the doc store did not end with the compressor id.
+                assert_eq!(data.pop(), Some(Compressor::Lz4.id()));
+            }
+            directory.atomic_write(&path, &data).unwrap();
+        }
+        let legacy_metas = format!(
+            r#"{{
+                "segments": [{{"segment_id": {}, "max_doc": 2, "deletes": null}}],
+                "schema": [
+                    {{
+                        "name": "text",
+                        "type": "text",
+                        "options": {{
+                            "indexing": {{"record": "position", "tokenizer": "default"}},
+                            "stored": true
+                        }}
+                    }},
+                    {{
+                        "name": "count",
+                        "type": "u64",
+                        "options": {{"indexed": true, "fast": "single", "stored": false}}
+                    }}
+                ],
+                "opstamp": 2
+            }}"#,
+            serde_json::to_string(&segment_meta.id()).unwrap()
+        );
+        directory
+            .atomic_write(&META_FILEPATH, legacy_metas.as_bytes())
+            .unwrap();
+
+        let index = Index::open(tempdir.path()).unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        assert_eq!(segment_reader.segment_meta().footer_version(), 0);
+        assert_eq!(searcher.num_docs(), 2);
+        assert_eq!(
+            searcher.doc_freq(&Term::from_field_text(text_field, "hello")),
+            2
+        );
+        assert_eq!(
+            searcher.doc_freq(&Term::from_field_text(text_field, "happy")),
+            1
+        );
+        assert_eq!(searcher.doc_freq(&Term::from_field_u64(count_field, 7u64)), 1);
+        let count_reader = segment_reader.fast_field_reader::<u64>(count_field).unwrap();
+        assert_eq!(count_reader.get(0), 3u64);
+        assert_eq!(count_reader.get(1), 7u64);
+        let doc = searcher.doc(&DocAddress(0, 1)).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), "hello");
+    }
+
 }
@@ -2,7 +2,7 @@
 /// Each component is stored in its own file,
 /// using the pattern `segment_uuid`.`component_extension`,
 /// except the delete component that takes an `segment_uuid`.`delete_opstamp`.`component_extension`
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum SegmentComponent {
     /// Postings (or inverted list). Sorted lists of document ids, associated to terms
     POSTINGS,
@@ -16,7 +16,7 @@ pub enum SegmentComponent {
     /// Dictionary associating `Term`s to `TermInfo`s which is
     /// simply an address into the `postings` file and the `positions` file.
     TERMS,
-    /// Row-oriented, LZ4-compressed storage of the documents.
+    /// Row-oriented, compressed storage of the documents.
     /// Accessing a document from the store is relatively slow, as it
     /// requires to decompress the entire block it belongs to.
     STORE,
@@ -38,4 +38,17 @@ impl SegmentComponent {
         ];
         SEGMENT_COMPONENTS.into_iter()
     }
+
+    /// Returns the extension of the files of this component.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            SegmentComponent::POSITIONS => "pos",
+            SegmentComponent::POSTINGS => "idx",
+            SegmentComponent::TERMS => "term",
+            SegmentComponent::STORE => "store",
+            SegmentComponent::FASTFIELDS => "fast",
+            SegmentComponent::FIELDNORMS => "fieldnorm",
+            SegmentComponent::DELETE => "del",
+        }
+    }
 }
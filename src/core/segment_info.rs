@@ -0,0 +1,45 @@
+use core::{SegmentComponent, SegmentId};
+
+/// Information about a segment and the size of its files,
+/// as returned by `Index::segments_info`.
+#[derive(Clone, Debug)]
+pub struct SegmentInfo {
+    /// Id of the segment.
+    pub segment_id: SegmentId,
+    /// Highest doc id of the segment + 1.
+    pub max_doc: u32,
+    /// Number of deleted documents.
+    pub num_deleted_docs: u32,
+    /// Opstamp of the last document of the segment when it was created.
+    /// See `SegmentMeta::create_opstamp`.
+    pub create_opstamp: Option<u64>,
+    /// Size on disk, in bytes, of each of the component files of the segment.
+    ///
+    /// Components without a file (e.g. the deletes of a segment without
+    /// deleted documents) are omitted.
+    pub component_sizes: Vec<(SegmentComponent, u64)>,
+}
+
+impl SegmentInfo {
+    /// Returns the number of alive documents.
+    pub fn num_docs(&self) -> u32 {
+        self.max_doc - self.num_deleted_docs
+    }
+
+    /// Returns the size on disk of a component, or `None`
+    /// if the segment has no file for this component.
+    pub fn component_size(&self, component: SegmentComponent) -> Option<u64> {
+        self.component_sizes
+            .iter()
+            .find(|&&(segment_component, _)| segment_component == component)
+            .map(|&(_, num_bytes)| num_bytes)
+    }
+
+    /// Returns the total size on disk of the segment, in bytes.
+    pub fn num_bytes(&self) -> u64 {
+        self.component_sizes
+            .iter()
+            .map(|&(_, num_bytes)| num_bytes)
+            .sum()
+    }
+}
@@ -1,4 +1,8 @@
+use byteorder::{BigEndian, ByteOrder};
+use common::FOOTER_VERSION;
 use core::SegmentId;
+use schema::Field;
+use std::collections::Bound;
 use super::SegmentComponent;
 use std::path::PathBuf;
 use std::collections::HashSet;
@@ -9,6 +13,111 @@ struct DeleteMeta {
     opstamp: u64,
 }
 
+/// Bounds of the values of a field within a segment.
+///
+/// The bounds are computed when the segment is written, and include
+/// the deleted documents. They make it possible to skip the segments
+/// that cannot match a term or a range query without opening them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldBounds {
+    /// The field.
+    pub field: Field,
+    /// Lowest and highest terms of the field, as stored in its
+    /// term dictionary, or `None` if the field has no term.
+    /// `None` for fields that are not indexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub term_bounds: Option<(Vec<u8>, Vec<u8>)>,
+    /// Lowest and highest values of the field, for single-valued
+    /// integer, f64 or date fast fields. `i64` values, and the microseconds
+    /// of dates, are mapped to `u64` as in `common::i64_to_u64`,
+    /// `f64` values as in `common::f64_to_u64`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_bounds: Option<(u64, u64)>,
+}
+
+impl FieldBounds {
+    /// Returns false if the field has no term with the given value bytes.
+    pub fn may_contain_term(&self, value_bytes: &[u8]) -> bool {
+        match self.term_bounds {
+            Some((ref first_term, ref last_term)) => {
+                &first_term[..] <= value_bytes && value_bytes <= &last_term[..]
+            }
+            None => false,
+        }
+    }
+
+    /// Returns false if the field has no term within the given bounds.
+    pub fn may_overlap_terms(
+        &self,
+        left_bound: &Bound<Vec<u8>>,
+        right_bound: &Bound<Vec<u8>>,
+    ) -> bool {
+        let (first_term, last_term) = match self.term_bounds {
+            Some((ref first_term, ref last_term)) => (first_term, last_term),
+            None => return false,
+        };
+        let above_left = match *left_bound {
+            Bound::Included(ref left) => last_term >= left,
+            Bound::Excluded(ref left) => last_term > left,
+            Bound::Unbounded => true,
+        };
+        let below_right = match *right_bound {
+            Bound::Included(ref right) => first_term <= right,
+            Bound::Excluded(ref right) => first_term < right,
+            Bound::Unbounded => true,
+        };
+        above_left && below_right
+    }
+
+    /// Returns false if the field has no value within the given bounds.
+    ///
+    /// The bounds are the value bytes of integer, f64 or date terms,
+    /// that is the big endian encoding of the values mapped to `u64`.
+    /// If the field has no value bounds, or if the bounds do not encode
+    /// a value, true is returned.
+    pub fn may_overlap_values(
+        &self,
+        left_bound: &Bound<Vec<u8>>,
+        right_bound: &Bound<Vec<u8>>,
+    ) -> bool {
+        let (min_value, max_value) = match self.value_bounds {
+            Some(value_bounds) => value_bounds,
+            None => return true,
+        };
+        let (left_bound, right_bound) = match (value_bound(left_bound), value_bound(right_bound)) {
+            (Some(left_bound), Some(right_bound)) => (left_bound, right_bound),
+            _ => return true,
+        };
+        let above_left = match left_bound {
+            Bound::Included(left) => max_value >= left,
+            Bound::Excluded(left) => max_value > left,
+            Bound::Unbounded => true,
+        };
+        let below_right = match right_bound {
+            Bound::Included(right) => min_value <= right,
+            Bound::Excluded(right) => min_value < right,
+            Bound::Unbounded => true,
+        };
+        above_left && below_right
+    }
+}
+
+/// Decodes the value of a bound over the value bytes of an integer term.
+fn value_bound(bound: &Bound<Vec<u8>>) -> Option<Bound<u64>> {
+    let decode = |value_bytes: &Vec<u8>| {
+        if value_bytes.len() == 8 {
+            Some(BigEndian::read_u64(value_bytes))
+        } else {
+            None
+        }
+    };
+    match *bound {
+        Bound::Included(ref value_bytes) => decode(value_bytes).map(Bound::Included),
+        Bound::Excluded(ref value_bytes) => decode(value_bytes).map(Bound::Excluded),
+        Bound::Unbounded => Some(Bound::Unbounded),
+    }
+}
+
 /// `SegmentMeta` contains simple meta information about a segment.
 ///
 /// For instance the number of docs it contains,
@@ -18,6 +127,15 @@ pub struct SegmentMeta {
     segment_id: SegmentId,
     max_doc: u32,
     deletes: Option<DeleteMeta>,
+    create_opstamp: Option<u64>,
+    #[serde(default)]
+    custom_components: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    field_bounds: Vec<FieldBounds>,
+    // segments written before footers were introduced have no
+    // `footer_version` in their meta, and are read as version 0.
+    #[serde(default)]
+    footer_version: u32,
 }
 
 impl SegmentMeta {
@@ -28,6 +146,10 @@ impl SegmentMeta {
             segment_id,
             max_doc: 0,
             deletes: None,
+            create_opstamp: None,
+            custom_components: Vec::new(),
+            field_bounds: Vec::new(),
+            footer_version: FOOTER_VERSION,
         }
     }
 
@@ -36,6 +158,20 @@ impl SegmentMeta {
         self.segment_id
     }
 
+    /// Returns the version of the footer appended to the files of the segment.
+    ///
+    /// Segments written by older versions of tantivy have no footer,
+    /// and return 0. Their files, including the delete files written
+    /// afterwards, are read and written without footer.
+    pub fn footer_version(&self) -> u32 {
+        self.footer_version
+    }
+
+    /// Returns true if the files of the segment end with a footer.
+    pub fn has_footer(&self) -> bool {
+        self.footer_version > 0
+    }
+
     /// Returns the number of deleted documents.
     pub fn num_deleted_docs(&self) -> u32 {
         self.deletes
@@ -53,9 +189,35 @@ impl SegmentMeta {
     pub fn list_files(&self) -> HashSet<PathBuf> {
         SegmentComponent::iterator()
             .map(|component| self.relative_path(*component))
+            .chain(
+                self.custom_components
+                    .iter()
+                    .map(|name| self.custom_component_path(name)),
+            )
             .collect::<HashSet<PathBuf>>()
     }
 
+    /// Returns the names of the custom components of the segment.
+    ///
+    /// See `Index::add_custom_component`.
+    pub fn custom_components(&self) -> &[String] {
+        &self.custom_components
+    }
+
+    /// Returns the bounds of the values of `field`, if they were computed.
+    ///
+    /// Segments written by older versions of tantivy have no bounds.
+    pub fn field_bounds(&self, field: Field) -> Option<&FieldBounds> {
+        self.field_bounds
+            .iter()
+            .find(|field_bounds| field_bounds.field == field)
+    }
+
+    /// Returns the relative path of a custom component of our segment.
+    pub fn custom_component_path(&self, name: &str) -> PathBuf {
+        PathBuf::from(format!("{}.{}", self.id().uuid_string(), name))
+    }
+
     /// Returns the relative path of a component of our segment.
     ///
     /// It just joins the segment id with the extension
@@ -63,13 +225,12 @@ impl SegmentMeta {
     pub fn relative_path(&self, component: SegmentComponent) -> PathBuf {
         let mut path = self.id().uuid_string();
         path.push_str(&*match component {
-            SegmentComponent::POSITIONS => ".pos".to_string(),
-            SegmentComponent::POSTINGS => ".idx".to_string(),
-            SegmentComponent::TERMS => ".term".to_string(),
-            SegmentComponent::STORE => ".store".to_string(),
-            SegmentComponent::FASTFIELDS => ".fast".to_string(),
-            SegmentComponent::FIELDNORMS => ".fieldnorm".to_string(),
-            SegmentComponent::DELETE => format!(".{}.del", self.delete_opstamp().unwrap_or(0)),
+            SegmentComponent::DELETE => format!(
+                ".{}.{}",
+                self.delete_opstamp().unwrap_or(0),
+                component.extension()
+            ),
+            _ => format!(".{}", component.extension()),
         });
         PathBuf::from(path)
     }
@@ -94,6 +255,16 @@ impl SegmentMeta {
         self.deletes.as_ref().map(|delete_meta| delete_meta.opstamp)
     }
 
+    /// Returns the opstamp of the last document added to the segment
+    /// when it was created.
+    ///
+    /// For a segment resulting from a merge, this is the highest
+    /// creation opstamp of the merged segments.
+    /// Segments created by older versions of tantivy return `None`.
+    pub fn create_opstamp(&self) -> Option<u64> {
+        self.create_opstamp
+    }
+
     /// Returns true iff the segment meta contains
     /// delete information.
     pub fn has_deletes(&self) -> bool {
@@ -105,6 +276,21 @@ impl SegmentMeta {
         self.max_doc = max_doc;
     }
 
+    #[doc(hidden)]
+    pub fn set_create_opstamp(&mut self, opstamp: u64) {
+        self.create_opstamp = Some(opstamp);
+    }
+
+    #[doc(hidden)]
+    pub fn set_field_bounds(&mut self, field_bounds: Vec<FieldBounds>) {
+        self.field_bounds = field_bounds;
+    }
+
+    #[doc(hidden)]
+    pub fn add_custom_component(&mut self, name: String) {
+        self.custom_components.push(name);
+    }
+
     #[doc(hidden)]
     pub fn set_delete_meta(&mut self, num_deleted_docs: u32, opstamp: u64) {
         self.deletes = Some(DeleteMeta {
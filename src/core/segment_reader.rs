@@ -2,15 +2,17 @@ use Result;
 use core::Segment;
 use core::SegmentId;
 use core::SegmentComponent;
-use std::sync::RwLock;
+use std::sync::{Mutex, RwLock};
 use common::HasLen;
 use core::SegmentMeta;
 use fastfield::{self, FastFieldNotAvailableError};
-use fastfield::DeleteBitSet;
-use store::StoreReader;
+use fastfield::{AliveBitSet, DeleteBitSet};
+use store::{BlockCache, StoreReader};
 use directory::ReadOnlySource;
+use directory::error::IOError;
 use schema::Document;
 use DocId;
+use SegmentLocalId;
 use std::sync::Arc;
 use std::collections::HashMap;
 use common::CompositeFile;
@@ -24,8 +26,23 @@ use fastfield::FacetReader;
 use fastfield::FastFieldReader;
 use schema::Schema;
 use termdict::TermDictionary;
+use schema::{IndexRecordOption, Term};
+use docset::DocSet;
+use core::{FieldSpaceUsage, SpaceUsage};
 use fastfield::{FastValue, MultiValueIntFastFieldReader};
 use schema::Cardinality;
+use schema::DateTime;
+use datastruct::BloomFilter;
+use core::terms_by_doc_freq::doc_freq_index_component;
+use core::TermsByDocFreq;
+use core::FieldBounds;
+use common::{f64_to_u64, i64_to_u64};
+
+/// Returns the name of the custom component holding
+/// the bloom filter of a key field.
+pub(crate) fn bloom_filter_component(field: Field) -> String {
+    format!("bloom_{}", field.0)
+}
 
 /// Entry point to access all of the datastructures of the `Segment`
 ///
@@ -43,6 +60,7 @@ use schema::Cardinality;
 #[derive(Clone)]
 pub struct SegmentReader {
     inv_idx_reader_cache: Arc<RwLock<HashMap<Field, Arc<InvertedIndexReader>>>>,
+    num_tokens_cache: Arc<RwLock<HashMap<Field, u64>>>,
 
     segment_id: SegmentId,
     segment_meta: SegmentMeta,
@@ -55,6 +73,8 @@ pub struct SegmentReader {
 
     store_reader: StoreReader,
     delete_bitset: DeleteBitSet,
+    custom_components: HashMap<String, ReadOnlySource>,
+    bloom_filters: HashMap<Field, BloomFilter>,
     schema: Schema,
 }
 
@@ -153,8 +173,7 @@ impl SegmentReader {
     /// Accessor to the segment's `Field norms`'s reader.
     ///
     /// Field norms are the length (in tokens) of the fields.
-    /// It is used in the computation of the [TfIdf]
-    /// (https://fulmicoton.gitbooks.io/tantivy-doc/content/tfidf.html).
+    /// It is used in the computation of the BM25 score.
     ///
     /// They are simply stored as a fast field, serialized in
     /// the `.fieldnorm` file of the segment.
@@ -164,6 +183,33 @@ impl SegmentReader {
             .map(FastFieldReader::open)
     }
 
+    /// Returns the sum of the field norms of `field`, that is
+    /// the overall number of tokens of the field in the segment.
+    ///
+    /// Deleted documents are included.
+    /// The sum is computed on the first call, and then cached.
+    pub fn total_num_tokens(&self, field: Field) -> u64 {
+        if let Some(&num_tokens) = self.num_tokens_cache
+            .read()
+            .expect("Lock poisoned. This should never happen")
+            .get(&field)
+        {
+            return num_tokens;
+        }
+        let num_tokens: u64 = self.get_fieldnorms_reader(field)
+            .map(|fieldnorms_reader| {
+                (0..self.max_doc())
+                    .map(|doc| fieldnorms_reader.get(doc))
+                    .sum()
+            })
+            .unwrap_or(0u64);
+        self.num_tokens_cache
+            .write()
+            .expect("Lock poisoned. This should never happen")
+            .insert(field, num_tokens);
+        num_tokens
+    }
+
     /// Accessor to the segment's `StoreReader`.
     pub fn get_store_reader(&self) -> &StoreReader {
         &self.store_reader
@@ -175,7 +221,11 @@ impl SegmentReader {
         let termdict_composite = CompositeFile::open(&termdict_source)?;
 
         let store_source = segment.open_read(SegmentComponent::STORE)?;
-        let store_reader = StoreReader::from_source(store_source);
+        let store_reader = if segment.meta().has_footer() {
+            StoreReader::from_source(store_source)?
+        } else {
+            StoreReader::from_legacy_source(store_source)?
+        };
 
         let postings_source = segment.open_read(SegmentComponent::POSTINGS)?;
         let postings_composite = CompositeFile::open(&postings_source)?;
@@ -201,9 +251,27 @@ impl SegmentReader {
             DeleteBitSet::empty()
         };
 
+        let mut custom_components = HashMap::new();
+        for name in segment.meta().custom_components() {
+            let custom_component_data = segment.open_custom_read(name)?;
+            custom_components.insert(name.clone(), custom_component_data);
+        }
+
         let schema = segment.schema();
+        let mut bloom_filters = HashMap::new();
+        for field_id in 0..schema.fields().len() {
+            let field = Field(field_id as u32);
+            let name = bloom_filter_component(field);
+            if let Some(source) = custom_components.get(&name) {
+                let bloom_filter = BloomFilter::open(source).map_err(|e| {
+                    IOError::with_path(segment.meta().custom_component_path(&name), e)
+                })?;
+                bloom_filters.insert(field, bloom_filter);
+            }
+        }
         Ok(SegmentReader {
             inv_idx_reader_cache: Arc::new(RwLock::new(HashMap::new())),
+            num_tokens_cache: Arc::new(RwLock::new(HashMap::new())),
             segment_meta: segment.meta().clone(),
             termdict_composite,
             postings_composite,
@@ -213,10 +281,20 @@ impl SegmentReader {
             store_reader,
             delete_bitset,
             positions_composite,
+            custom_components,
+            bloom_filters,
             schema,
         })
     }
 
+    /// Returns the data of a custom component of the segment,
+    /// or `None` if the segment does not have such a component.
+    ///
+    /// See `Index::add_custom_component`.
+    pub fn custom_component(&self, name: &str) -> Option<ReadOnlySource> {
+        self.custom_components.get(name).cloned()
+    }
+
     /// Returns a field reader associated to the field given in argument.
     ///
     /// The field reader is in charge of iterating through the
@@ -231,11 +309,11 @@ impl SegmentReader {
             return Arc::clone(inv_idx_reader);
         }
 
-        let record_option = self.schema
-            .get_field_entry(field)
-            .field_type()
+        let field_type = self.schema.get_field_entry(field).field_type();
+        let record_option = field_type
             .get_index_record_option()
             .expect("Field does not seem indexed.");
+        let per_term_record_option = field_type.freqs_min_doc_freq().is_some();
 
         let termdict_source: ReadOnlySource = self.termdict_composite
             .open_read(field)
@@ -255,6 +333,7 @@ impl SegmentReader {
             positions_source,
             self.delete_bitset.clone(),
             record_option,
+            per_term_record_option,
         ));
 
         // by releasing the lock in between, we may end up opening the inverting index
@@ -275,6 +354,64 @@ impl SegmentReader {
         self.store_reader.get(doc_id)
     }
 
+    /// Returns the stored values of the given fields for a document.
+    ///
+    /// Only the requested fields are decoded. If none of them
+    /// is stored, the doc store is not accessed at all.
+    pub fn doc_fields(&self, doc_id: DocId, fields: &[Field]) -> Result<Document> {
+        let stored_fields = self.stored_fields(fields);
+        if stored_fields.is_empty() {
+            return Ok(Document::default());
+        }
+        self.store_reader.get_fields(doc_id, &stored_fields)
+    }
+
+    /// Same as `doc`, or `doc_fields` if `fields` is defined,
+    /// but the decompressed blocks of the doc store are
+    /// looked up in `block_cache`.
+    pub(crate) fn doc_with_cache(
+        &self,
+        doc_id: DocId,
+        fields: Option<&[Field]>,
+        segment_ord: SegmentLocalId,
+        block_cache: &Mutex<BlockCache>,
+    ) -> Result<Document> {
+        match fields {
+            Some(fields) => {
+                let stored_fields = self.stored_fields(fields);
+                if stored_fields.is_empty() {
+                    return Ok(Document::default());
+                }
+                self.store_reader.get_with_cache(
+                    doc_id,
+                    Some(&stored_fields),
+                    segment_ord,
+                    block_cache,
+                )
+            }
+            None => self.store_reader
+                .get_with_cache(doc_id, None, segment_ord, block_cache),
+        }
+    }
+
+    fn stored_fields(&self, fields: &[Field]) -> Vec<Field> {
+        fields
+            .iter()
+            .cloned()
+            .filter(|&field| self.schema.get_field_entry(field).is_stored())
+            .collect()
+    }
+
+    /// Returns the schema of the index the segment belongs to.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Returns the meta information of the segment.
+    pub fn segment_meta(&self) -> &SegmentMeta {
+        &self.segment_meta
+    }
+
     /// Returns the segment id
     pub fn segment_id(&self) -> SegmentId {
         self.segment_id
@@ -286,6 +423,141 @@ impl SegmentReader {
         &self.delete_bitset
     }
 
+    /// Returns the alive document whose unique key is `key`, if any.
+    ///
+    /// The key is looked up directly in the term dictionary, in `O(log n)`,
+    /// so the key field must be indexed, typically as a `STRING` field.
+    /// If several alive documents share the same key, the last
+    /// one added is returned.
+    ///
+    /// If the key field has a bloom filter, it is consulted before
+    /// the term dictionary. See `Index::add_key_bloom_filter`.
+    pub fn doc_id_for_key(&self, key: &Term) -> Option<DocId> {
+        if !self.may_contain_key(key) {
+            return None;
+        }
+        let mut postings = self.inverted_index(key.field())
+            .read_postings(key, IndexRecordOption::Basic)?;
+        let mut doc_id = None;
+        while postings.advance() {
+            doc_id = Some(postings.doc());
+        }
+        doc_id
+    }
+
+    /// Returns `false` if no document of the segment has the given key.
+    ///
+    /// The answer comes from the bloom filter of the key field,
+    /// and may be a false positive. Without bloom filter,
+    /// `true` is always returned.
+    pub fn may_contain_key(&self, key: &Term) -> bool {
+        self.bloom_filters
+            .get(&key.field())
+            .map(|bloom_filter| bloom_filter.may_contain(key.value_bytes()))
+            .unwrap_or(true)
+    }
+
+    /// Computes the bounds of the indexed fields and of
+    /// the single-valued integer fast fields of the segment.
+    pub(crate) fn compute_field_bounds(&self) -> Vec<FieldBounds> {
+        let mut all_field_bounds = Vec::new();
+        for (field_id, field_entry) in self.schema.fields().iter().enumerate() {
+            let field = Field(field_id as u32);
+            let term_bounds = if field_entry.is_indexed() {
+                let inverted_index = self.inverted_index(field);
+                let terms = inverted_index.terms();
+                let num_terms = terms.num_terms() as u64;
+                let mut first_term = Vec::new();
+                let mut last_term = Vec::new();
+                if num_terms > 0 && terms.ord_to_term(0, &mut first_term)
+                    && terms.ord_to_term(num_terms - 1, &mut last_term)
+                {
+                    Some((first_term, last_term))
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+            let value_bounds = match *field_entry.field_type() {
+                FieldType::U64(_) => self.fast_field_reader::<u64>(field)
+                    .ok()
+                    .map(|ff_reader| (ff_reader.min_value(), ff_reader.max_value())),
+                FieldType::I64(_) => self.fast_field_reader::<i64>(field).ok().map(|ff_reader| {
+                    (
+                        i64_to_u64(ff_reader.min_value()),
+                        i64_to_u64(ff_reader.max_value()),
+                    )
+                }),
+                FieldType::Date(_) => self.fast_field_reader::<DateTime>(field)
+                    .ok()
+                    .map(|ff_reader| {
+                        (
+                            i64_to_u64(ff_reader.min_value().timestamp_micros()),
+                            i64_to_u64(ff_reader.max_value().timestamp_micros()),
+                        )
+                    }),
+                FieldType::F64(_) => self.fast_field_reader::<f64>(field).ok().map(|ff_reader| {
+                    (
+                        f64_to_u64(ff_reader.min_value()),
+                        f64_to_u64(ff_reader.max_value()),
+                    )
+                }),
+                _ => None,
+            };
+            if field_entry.is_indexed() || value_bounds.is_some() {
+                all_field_bounds.push(FieldBounds {
+                    field,
+                    term_bounds,
+                    value_bounds,
+                });
+            }
+        }
+        all_field_bounds
+    }
+
+    /// Returns the terms of `field`, by decreasing doc frequency.
+    ///
+    /// The order is materialized when the segment is written, so that
+    /// the most frequent terms are accessed without scanning the whole
+    /// term dictionary. Returns `None` if the field has no doc frequency
+    /// index (see `Index::add_doc_freq_index`).
+    pub fn terms_by_doc_freq(&self, field: Field) -> Option<TermsByDocFreq> {
+        self.custom_components
+            .get(&doc_freq_index_component(field))
+            .map(|data| TermsByDocFreq::new(field, self.inverted_index(field), data.clone()))
+    }
+
+    /// Returns the number of bytes used by each field and by the doc store
+    /// of this segment.
+    pub fn space_usage(&self) -> SpaceUsage {
+        let mut fields: HashMap<Field, FieldSpaceUsage> = HashMap::new();
+        {
+            let mut add_component =
+                |composite: &CompositeFile, set: fn(&mut FieldSpaceUsage, u64)| {
+                    for (field, num_bytes) in composite.field_num_bytes() {
+                        let field_usage = fields.entry(field).or_insert_with(Default::default);
+                        set(field_usage, num_bytes as u64);
+                    }
+                };
+            add_component(&self.termdict_composite, |usage, n| usage.termdict = n);
+            add_component(&self.postings_composite, |usage, n| usage.postings = n);
+            add_component(&self.positions_composite, |usage, n| usage.positions = n);
+            add_component(&self.fast_fields_composite, |usage, n| usage.fast_fields = n);
+            add_component(&self.fieldnorms_composite, |usage, n| usage.fieldnorms = n);
+        }
+        SpaceUsage {
+            fields,
+            store: self.store_reader.num_bytes() as u64,
+            deletes: self.delete_bitset.num_bytes() as u64,
+        }
+    }
+
+    /// Returns the set of the documents that are not deleted.
+    pub fn alive_bitset(&self) -> AliveBitSet {
+        AliveBitSet::new(self.delete_bitset.clone(), self.max_doc())
+    }
+
     /// Returns true iff the `doc` is marked
     /// as deleted.
     pub fn is_deleted(&self, doc: DocId) -> bool {
@@ -0,0 +1,77 @@
+use schema::Field;
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+/// Number of bytes used by a field in each of the
+/// per-field components of the index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldSpaceUsage {
+    /// Bytes used by the term dictionary.
+    pub termdict: u64,
+    /// Bytes used by the postings (doc ids and term frequencies).
+    pub postings: u64,
+    /// Bytes used by the term positions.
+    pub positions: u64,
+    /// Bytes used by the fast fields.
+    pub fast_fields: u64,
+    /// Bytes used by the field norms.
+    pub fieldnorms: u64,
+}
+
+impl FieldSpaceUsage {
+    /// Returns the total number of bytes used by the field.
+    pub fn num_bytes(&self) -> u64 {
+        self.termdict + self.postings + self.positions + self.fast_fields + self.fieldnorms
+    }
+}
+
+impl AddAssign for FieldSpaceUsage {
+    fn add_assign(&mut self, other: FieldSpaceUsage) {
+        self.termdict += other.termdict;
+        self.postings += other.postings;
+        self.positions += other.positions;
+        self.fast_fields += other.fast_fields;
+        self.fieldnorms += other.fieldnorms;
+    }
+}
+
+/// Breakdown of the space used by a segment, or by
+/// all of the segments of an index.
+///
+/// See `SegmentReader::space_usage` and `Index::space_usage`.
+#[derive(Clone, Debug, Default)]
+pub struct SpaceUsage {
+    /// Bytes used by each field. Fields that do not use any
+    /// byte are omitted.
+    pub fields: HashMap<Field, FieldSpaceUsage>,
+    /// Bytes used by the doc store.
+    pub store: u64,
+    /// Bytes used by the delete bitsets.
+    pub deletes: u64,
+}
+
+impl SpaceUsage {
+    /// Returns the space usage of the given field.
+    pub fn field(&self, field: Field) -> FieldSpaceUsage {
+        self.fields.get(&field).cloned().unwrap_or_default()
+    }
+
+    /// Returns the total number of bytes used.
+    pub fn num_bytes(&self) -> u64 {
+        let fields_num_bytes: u64 = self.fields
+            .values()
+            .map(FieldSpaceUsage::num_bytes)
+            .sum();
+        fields_num_bytes + self.store + self.deletes
+    }
+}
+
+impl AddAssign for SpaceUsage {
+    fn add_assign(&mut self, other: SpaceUsage) {
+        for (field, field_usage) in other.fields {
+            *self.fields.entry(field).or_insert_with(FieldSpaceUsage::default) += field_usage;
+        }
+        self.store += other.store;
+        self.deletes += other.deletes;
+    }
+}
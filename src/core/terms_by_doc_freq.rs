@@ -0,0 +1,95 @@
+use byteorder::ByteOrder;
+use common::{BinarySerializable, Endianness};
+use core::InvertedIndexReader;
+use directory::ReadOnlySource;
+use schema::{Field, Term};
+use std::io::{self, Write};
+use std::sync::Arc;
+use termdict::{TermDictionary, TermDictionaryImpl, TermOrdinal, TermStreamer};
+
+// Each entry is serialized as `[term_ord: u64][doc_freq: u32]`.
+const ENTRY_NUM_BYTES: usize = 8 + 4;
+
+/// Returns the name of the custom component holding
+/// the terms of a field sorted by decreasing doc frequency.
+pub(crate) fn doc_freq_index_component(field: Field) -> String {
+    format!("docfreq_{}", field.0)
+}
+
+/// Writes the term ordinals of a term dictionary,
+/// sorted by decreasing doc frequency.
+///
+/// Terms with the same doc frequency are sorted by increasing term ordinal.
+pub(crate) fn write_doc_freq_index<W: Write>(
+    terms: &TermDictionaryImpl,
+    write: &mut W,
+) -> io::Result<()> {
+    let mut entries: Vec<(u32, TermOrdinal)> = Vec::with_capacity(terms.num_terms());
+    let mut term_stream = terms.stream();
+    while term_stream.advance() {
+        entries.push((term_stream.value().doc_freq, term_stream.term_ord()));
+    }
+    entries.sort_by(|left, right| right.0.cmp(&left.0).then(left.1.cmp(&right.1)));
+    for &(doc_freq, term_ord) in &entries {
+        term_ord.serialize(write)?;
+        doc_freq.serialize(write)?;
+    }
+    Ok(())
+}
+
+/// Iterator over the terms of a field of a segment,
+/// by decreasing doc frequency.
+///
+/// Terms with the same doc frequency are returned in their natural order.
+/// As for `InvertedIndexReader::doc_freq`, the doc frequencies
+/// include the deleted documents.
+///
+/// See `SegmentReader::terms_by_doc_freq`.
+pub struct TermsByDocFreq {
+    field: Field,
+    inverted_index: Arc<InvertedIndexReader>,
+    data: ReadOnlySource,
+    cursor: usize,
+    term_bytes: Vec<u8>,
+}
+
+impl TermsByDocFreq {
+    pub(crate) fn new(
+        field: Field,
+        inverted_index: Arc<InvertedIndexReader>,
+        data: ReadOnlySource,
+    ) -> TermsByDocFreq {
+        TermsByDocFreq {
+            field,
+            inverted_index,
+            data,
+            cursor: 0,
+            term_bytes: Vec::new(),
+        }
+    }
+
+    /// Returns the number of terms of the field.
+    pub fn num_terms(&self) -> usize {
+        self.data.len() / ENTRY_NUM_BYTES
+    }
+}
+
+impl Iterator for TermsByDocFreq {
+    type Item = (Term, u32);
+
+    fn next(&mut self) -> Option<(Term, u32)> {
+        if self.cursor + ENTRY_NUM_BYTES > self.data.len() {
+            return None;
+        }
+        let (term_ord, doc_freq) = {
+            let entry = &self.data.as_slice()[self.cursor..self.cursor + ENTRY_NUM_BYTES];
+            (Endianness::read_u64(&entry[..8]), Endianness::read_u32(&entry[8..]))
+        };
+        self.cursor += ENTRY_NUM_BYTES;
+        let found = self.inverted_index
+            .terms()
+            .ord_to_term(term_ord, &mut self.term_bytes);
+        assert!(found, "Term ordinal out of the term dictionary. This is a bug.");
+        Some((Term::from_field_bytes(self.field, &self.term_bytes), doc_freq))
+    }
+}
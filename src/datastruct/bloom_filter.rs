@@ -0,0 +1,137 @@
+use byteorder::ByteOrder;
+use common::{fnv1a_hash, BinarySerializable, Endianness, HasLen};
+use directory::ReadOnlySource;
+use std::f64;
+use std::io::{self, Write};
+
+/// Iterates over the `num_hashes` bit positions of a key
+/// in a filter of `num_bits` bits.
+///
+/// The positions are derived from the two halves of a 64-bit
+/// FNV-1a hash of the key, as described by Kirsch and Mitzenmacher.
+fn bit_positions(key: &[u8], num_hashes: u32, num_bits: u64) -> impl Iterator<Item = u64> {
+    let hash = fnv1a_hash(key);
+    let h1 = hash & 0xffff_ffff;
+    let h2 = (hash >> 32) | 1u64;
+    (0..u64::from(num_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+}
+
+/// Builds a `BloomFilter`.
+///
+/// Serialized as `[num_hashes: u32][bits]`.
+pub struct BloomFilterBuilder {
+    bits: Vec<u8>,
+    num_hashes: u32,
+}
+
+impl BloomFilterBuilder {
+    /// Creates a builder sized for `num_keys` keys with the
+    /// given false positive rate.
+    pub fn with_capacity(num_keys: usize, false_positive_rate: f64) -> BloomFilterBuilder {
+        let num_keys = num_keys.max(1) as f64;
+        let ln2 = f64::consts::LN_2;
+        let num_bits = (-num_keys * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(8f64);
+        let num_hashes = (num_bits / num_keys * ln2).round().max(1f64) as u32;
+        BloomFilterBuilder {
+            bits: vec![0u8; (num_bits as usize + 7) / 8],
+            num_hashes,
+        }
+    }
+
+    /// Adds a key to the filter.
+    pub fn insert(&mut self, key: &[u8]) {
+        let num_bits = self.bits.len() as u64 * 8;
+        for bit in bit_positions(key, self.num_hashes, num_bits) {
+            self.bits[(bit / 8) as usize] |= 1u8 << (bit % 8);
+        }
+    }
+
+    /// Writes the filter.
+    pub fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.num_hashes.serialize(writer)?;
+        writer.write_all(&self.bits)
+    }
+}
+
+/// Probabilistic set of keys.
+///
+/// `may_contain` never returns `false` for a key that was inserted,
+/// but may return `true` for a key that was not.
+#[derive(Clone)]
+pub struct BloomFilter {
+    num_hashes: u32,
+    bits: ReadOnlySource,
+}
+
+impl BloomFilter {
+    /// Opens a filter serialized by a `BloomFilterBuilder`.
+    ///
+    /// Returns an `InvalidData` error if the source is too short
+    /// to hold the header of the filter.
+    pub fn open(source: &ReadOnlySource) -> io::Result<BloomFilter> {
+        if source.len() < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Bloom filter is truncated ({} bytes).", source.len()),
+            ));
+        }
+        let num_hashes = Endianness::read_u32(&source.as_slice()[..4]);
+        Ok(BloomFilter {
+            num_hashes,
+            bits: source.slice_from(4),
+        })
+    }
+
+    /// Returns `false` if the key was definitely not inserted in the filter.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        let bits = self.bits.as_slice();
+        let num_bits = bits.len() as u64 * 8;
+        if num_bits == 0 {
+            return true;
+        }
+        bit_positions(key, self.num_hashes, num_bits)
+            .all(|bit| bits[(bit / 8) as usize] & (1u8 << (bit % 8)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{BloomFilter, BloomFilterBuilder};
+    use directory::ReadOnlySource;
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut builder = BloomFilterBuilder::with_capacity(1_000, 0.01);
+        for i in 0..1_000 {
+            builder.insert(format!("key{}", i).as_bytes());
+        }
+        let mut buffer = Vec::new();
+        builder.serialize(&mut buffer).unwrap();
+        let bloom_filter = BloomFilter::open(&ReadOnlySource::from(buffer)).unwrap();
+        for i in 0..1_000 {
+            assert!(bloom_filter.may_contain(format!("key{}", i).as_bytes()));
+        }
+        let num_false_positives = (1_000..11_000)
+            .filter(|i| bloom_filter.may_contain(format!("key{}", i).as_bytes()))
+            .count();
+        assert!(num_false_positives < 300);
+    }
+
+    #[test]
+    fn test_bloom_filter_empty() {
+        let builder = BloomFilterBuilder::with_capacity(0, 0.01);
+        let mut buffer = Vec::new();
+        builder.serialize(&mut buffer).unwrap();
+        let bloom_filter = BloomFilter::open(&ReadOnlySource::from(buffer)).unwrap();
+        assert!(!bloom_filter.may_contain(b"key"));
+    }
+
+    #[test]
+    fn test_bloom_filter_truncated() {
+        let source = ReadOnlySource::from(vec![1u8, 0u8]);
+        assert!(BloomFilter::open(&source).is_err());
+    }
+}
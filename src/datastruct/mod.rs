@@ -1,4 +1,6 @@
 mod skip;
 pub mod stacker;
+mod bloom_filter;
 
 pub use self::skip::{SkipList, SkipListBuilder};
+pub use self::bloom_filter::{BloomFilter, BloomFilterBuilder};
@@ -62,6 +62,11 @@ impl Heap {
         self.inner().num_free_bytes()
     }
 
+    /// Return amount of allocated space, in bytes.
+    pub fn num_used_bytes(&self) -> u32 {
+        self.inner().used
+    }
+
     /// Allocate a given amount of space and returns an address
     /// in the Heap.
     pub fn allocate_space(&self, num_bytes: usize) -> u32 {
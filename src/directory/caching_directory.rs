@@ -0,0 +1,417 @@
+use common::make_io_err;
+use core::{MemoryAccountant, MemoryComponent, MemoryReservation, SegmentComponent};
+use directory::error::{DeleteError, IOError, OpenReadError, OpenWriteError};
+use directory::mmap_directory::CacheCounters;
+use directory::{Directory, ReadOnlySource, WritePtr};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::result;
+use std::sync::{Arc, RwLock};
+
+struct CacheEntry {
+    source: ReadOnlySource,
+    last_access: u64,
+    _reservation: MemoryReservation,
+}
+
+/// LRU cache of `ReadOnlySource`, bounded by the sum
+/// of the length of the cached sources.
+struct SourceCache {
+    counters: CacheCounters,
+    memory_accountant: MemoryAccountant,
+    budget_in_bytes: usize,
+    num_bytes: usize,
+    clock: u64,
+    // incremented every time a file is invalidated, so that
+    // a read racing with a write does not cache stale data.
+    generation: u64,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+impl SourceCache {
+    fn new(budget_in_bytes: usize, memory_accountant: MemoryAccountant) -> SourceCache {
+        SourceCache {
+            counters: CacheCounters::default(),
+            memory_accountant,
+            budget_in_bytes,
+            num_bytes: 0,
+            clock: 0,
+            generation: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, path: &Path) -> Option<ReadOnlySource> {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.last_access = clock;
+            self.counters.hit += 1;
+            return Some(entry.source.clone());
+        }
+        self.counters.miss += 1;
+        None
+    }
+
+    fn insert(&mut self, path: &Path, source: ReadOnlySource) {
+        let source_len = source.len();
+        if source_len > self.budget_in_bytes {
+            return;
+        }
+        self.discard(path);
+        while self.num_bytes + source_len > self.budget_in_bytes {
+            self.evict_least_recently_used();
+        }
+        // the memory limiter may require evicting more entries,
+        // or to give up caching the source.
+        let reservation = loop {
+            if let Some(reservation) = self.memory_accountant
+                .try_reserve(MemoryComponent::SourceCache, source_len)
+            {
+                break reservation;
+            }
+            if self.entries.is_empty() {
+                return;
+            }
+            self.evict_least_recently_used();
+        };
+        self.num_bytes += source_len;
+        self.entries.insert(
+            path.to_owned(),
+            CacheEntry {
+                source,
+                last_access: self.clock,
+                _reservation: reservation,
+            },
+        );
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let lru_path: Option<PathBuf> = self.entries
+            .iter()
+            .min_by_key(|&(_, entry)| entry.last_access)
+            .map(|(path, _)| path.clone());
+        if let Some(path) = lru_path {
+            self.discard(&path);
+        }
+    }
+
+    fn invalidate(&mut self, path: &Path) {
+        self.generation += 1;
+        self.discard(path);
+    }
+
+    fn discard(&mut self, path: &Path) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.num_bytes -= entry.source.len();
+        }
+    }
+}
+
+/// Components cached by default: the ones accessed by every search.
+const DEFAULT_CACHED_COMPONENTS: [SegmentComponent; 3] = [
+    SegmentComponent::TERMS,
+    SegmentComponent::FASTFIELDS,
+    SegmentComponent::FIELDNORMS,
+];
+
+/// Directory wrapper keeping the most recently read files in memory.
+///
+/// The cache follows a LRU policy, and its memory usage is bounded
+/// by a budget expressed in bytes.
+/// Files larger than the budget are never cached.
+///
+/// This is especially useful when the underlying directory is
+/// remote or on a slow disk: the term dictionaries, fast fields
+/// and field norms are then served from memory.
+///
+/// Only the files of these components are cached by default, so that
+/// reading a large doc store or positions file does not evict them.
+/// See `.with_cached_components(...)`.
+///
+/// Files written via `atomic_write` are not cached, as their
+/// content is expected to change.
+///
+/// The cached files are accounted as `MemoryComponent::SourceCache`
+/// by the memory accountant of the directory. When its limiter refuses
+/// memory, the least recently used files are evicted.
+pub struct CachingDirectory {
+    directory: Box<Directory>,
+    cached_components: Vec<SegmentComponent>,
+    cache: Arc<RwLock<SourceCache>>,
+}
+
+impl CachingDirectory {
+    /// Wraps a directory, caching up to `budget_in_bytes` bytes.
+    pub fn new<Dir: Directory>(directory: Dir, budget_in_bytes: usize) -> CachingDirectory {
+        CachingDirectory::with_memory_accountant(
+            directory,
+            budget_in_bytes,
+            MemoryAccountant::default(),
+        )
+    }
+
+    /// Wraps a directory, caching up to `budget_in_bytes` bytes,
+    /// and accounting the cached files in `memory_accountant`.
+    ///
+    /// Passing the accountant of an index (see `Index::memory_accountant`)
+    /// makes the cache and the index share the same memory limit.
+    pub fn with_memory_accountant<Dir: Directory>(
+        directory: Dir,
+        budget_in_bytes: usize,
+        memory_accountant: MemoryAccountant,
+    ) -> CachingDirectory {
+        CachingDirectory {
+            directory: box directory,
+            cached_components: DEFAULT_CACHED_COMPONENTS.to_vec(),
+            cache: Arc::new(RwLock::new(SourceCache::new(
+                budget_in_bytes,
+                memory_accountant,
+            ))),
+        }
+    }
+
+    /// Sets the segment components whose files are cached.
+    ///
+    /// Files that do not belong to any of these components,
+    /// as identified by their extension, are read from the
+    /// wrapped directory every time.
+    pub fn with_cached_components(
+        mut self,
+        cached_components: &[SegmentComponent],
+    ) -> CachingDirectory {
+        self.cached_components = cached_components.to_vec();
+        self
+    }
+
+    fn is_cached(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| {
+                self.cached_components
+                    .iter()
+                    .any(|component| component.extension() == extension)
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns the number of cache hits and misses so far.
+    pub fn counters(&self) -> CacheCounters {
+        self.cache
+            .read()
+            .expect("Caching directory lock poisoned.")
+            .counters
+            .clone()
+    }
+
+    /// Returns the number of bytes currently held in the cache.
+    pub fn cached_bytes(&self) -> usize {
+        self.cache
+            .read()
+            .expect("Caching directory lock poisoned.")
+            .num_bytes
+    }
+
+    fn discard_from_cache(&self, path: &Path) -> io::Result<()> {
+        self.cache
+            .write()
+            .map_err(|_| {
+                make_io_err(format!("Caching directory lock poisoned discarding {:?}", path))
+            })?
+            .invalidate(path);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CachingDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CachingDirectory({:?})", self.directory)
+    }
+}
+
+impl Clone for CachingDirectory {
+    fn clone(&self) -> CachingDirectory {
+        CachingDirectory {
+            directory: self.directory.box_clone(),
+            cached_components: self.cached_components.clone(),
+            cache: Arc::clone(&self.cache),
+        }
+    }
+}
+
+impl Directory for CachingDirectory {
+    fn open_read(&self, path: &Path) -> result::Result<ReadOnlySource, OpenReadError> {
+        if !self.is_cached(path) {
+            return self.directory.open_read(path);
+        }
+        let lock_err = || {
+            let msg = format!("Caching directory lock poisoned reading {:?}", path);
+            IOError::with_path(path.to_owned(), make_io_err(msg))
+        };
+        // The lock is not held while reading the underlying directory,
+        // so that a slow read does not block the other readers.
+        let generation = {
+            let mut cache = self.cache.write().map_err(|_| lock_err())?;
+            if let Some(source) = cache.get(path) {
+                return Ok(source);
+            }
+            cache.generation
+        };
+        let source = self.directory.open_read(path)?;
+        let mut cache = self.cache.write().map_err(|_| lock_err())?;
+        if cache.generation == generation {
+            cache.insert(path, source.clone());
+        }
+        Ok(source)
+    }
+
+    fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
+        self.discard_from_cache(path)
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        self.directory.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.directory.exists(path)
+    }
+
+    fn open_write(&mut self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
+        self.discard_from_cache(path)
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        self.directory.open_write(path)
+    }
+
+    fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
+        self.directory.atomic_read(path)
+    }
+
+    fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.discard_from_cache(path)?;
+        self.directory.atomic_write(path, data)
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.directory.sync_directory()
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        self.directory.local_path(path)
+    }
+
+    fn box_clone(&self) -> Box<Directory> {
+        box self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    // There are more tests in directory/mod.rs
+    // The following tests are specific to the CachingDirectory
+
+    use super::*;
+    use core::MemoryCap;
+    use directory::RAMDirectory;
+    use std::io::Write;
+
+    fn write_file(directory: &mut Directory, path: &Path, len: usize) {
+        let mut w = directory.open_write(path).unwrap();
+        w.write_all(&vec![1u8; len]).unwrap();
+        w.flush().unwrap();
+    }
+
+    #[test]
+    fn test_caching_directory_lru() {
+        let mut directory = CachingDirectory::new(RAMDirectory::create(), 10);
+        let (path_a, path_b, path_c) = (
+            Path::new("a.term"),
+            Path::new("b.fast"),
+            Path::new("c.term"),
+        );
+        write_file(&mut directory, path_a, 4);
+        write_file(&mut directory, path_b, 4);
+        write_file(&mut directory, path_c, 4);
+        directory.open_read(path_a).unwrap();
+        directory.open_read(path_b).unwrap();
+        directory.open_read(path_a).unwrap();
+        assert_eq!(directory.counters().hit, 1);
+        assert_eq!(directory.counters().miss, 2);
+        assert_eq!(directory.cached_bytes(), 8);
+        // b is the least recently used file and gets evicted.
+        directory.open_read(path_c).unwrap();
+        assert_eq!(directory.cached_bytes(), 8);
+        directory.open_read(path_a).unwrap();
+        directory.open_read(path_b).unwrap();
+        assert_eq!(directory.counters().hit, 2);
+        assert_eq!(directory.counters().miss, 4);
+    }
+
+    #[test]
+    fn test_caching_directory_memory_limit() {
+        let memory_accountant = MemoryAccountant::default();
+        let mut directory = CachingDirectory::with_memory_accountant(
+            RAMDirectory::create(),
+            10,
+            memory_accountant.clone(),
+        );
+        let (path_a, path_b, path_c) = (
+            Path::new("a.term"),
+            Path::new("b.fast"),
+            Path::new("c.term"),
+        );
+        write_file(&mut directory, path_a, 4);
+        write_file(&mut directory, path_b, 4);
+        write_file(&mut directory, path_c, 4);
+        directory.open_read(path_a).unwrap();
+        directory.open_read(path_b).unwrap();
+        let cached_bytes = |memory_accountant: &MemoryAccountant| {
+            memory_accountant
+                .usage()
+                .component(MemoryComponent::SourceCache)
+        };
+        assert_eq!(cached_bytes(&memory_accountant), 8);
+        // both a and b are evicted to stay under the cap.
+        memory_accountant.set_limiter(box MemoryCap::new(6));
+        directory.open_read(path_c).unwrap();
+        assert_eq!(directory.cached_bytes(), 4);
+        assert_eq!(cached_bytes(&memory_accountant), 4);
+        directory.open_read(path_c).unwrap();
+        assert_eq!(directory.counters().hit, 1);
+        // nothing can be cached when the cap is exhausted.
+        let _reservation = memory_accountant.reserve(MemoryComponent::IndexingArena, 10);
+        directory.open_read(path_a).unwrap();
+        assert_eq!(directory.cached_bytes(), 0);
+        assert_eq!(memory_accountant.usage().total(), 10);
+    }
+
+    #[test]
+    fn test_caching_directory_too_large() {
+        let mut directory = CachingDirectory::new(RAMDirectory::create(), 10);
+        let path = Path::new("large.term");
+        write_file(&mut directory, path, 11);
+        directory.open_read(path).unwrap();
+        assert_eq!(directory.cached_bytes(), 0);
+        directory.delete(path).unwrap();
+        assert!(directory.open_read(path).is_err());
+    }
+
+    #[test]
+    fn test_caching_directory_components() {
+        let mut directory = CachingDirectory::new(RAMDirectory::create(), 10);
+        let (store_path, terms_path) = (Path::new("a.store"), Path::new("a.term"));
+        write_file(&mut directory, store_path, 4);
+        write_file(&mut directory, terms_path, 4);
+        // doc stores are not cached by default.
+        directory.open_read(store_path).unwrap();
+        directory.open_read(terms_path).unwrap();
+        assert_eq!(directory.cached_bytes(), 4);
+        assert_eq!(directory.counters().miss, 1);
+        let directory = directory.with_cached_components(&[SegmentComponent::STORE]);
+        directory.open_read(store_path).unwrap();
+        assert_eq!(directory.cached_bytes(), 8);
+        directory.delete(terms_path).unwrap();
+        assert_eq!(directory.cached_bytes(), 4);
+    }
+}
@@ -1,8 +1,10 @@
 use std::marker::Send;
 use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use directory::error::{DeleteError, OpenReadError, OpenWriteError};
 use directory::{ReadOnlySource, WritePtr};
+use directory::watch::{self, WatchCallback, WatchHandle, DEFAULT_WATCH_INTERVAL_MS};
+use std::time::Duration;
 use std::result;
 use std::io;
 use std::marker::Sync;
@@ -10,12 +12,14 @@ use std::marker::Sync;
 /// Write-once read many (WORM) abstraction for where
 /// tantivy's data should be stored.
 ///
-/// There are currently two implementations of `Directory`
+/// There are currently three implementations of `Directory`
 ///
 /// - The [`MMapDirectory`](struct.MmapDirectory.html), this
 /// should be your default choice.
 /// - The [`RAMDirectory`](struct.RAMDirectory.html), which
 /// should be used mostly for tests.
+/// - The [`ObjectStoreDirectory`](struct.ObjectStoreDirectory.html), which
+/// serves an index from a remote blob store.
 ///
 pub trait Directory: fmt::Debug + Send + Sync + 'static {
     /// Opens a virtual file for read.
@@ -74,6 +78,41 @@ pub trait Directory: fmt::Debug + Send + Sync + 'static {
     /// The file may or may not previously exist.
     fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()>;
 
+    /// Makes all of the files written so far, as well as the
+    /// directory entries themselves, durable.
+    ///
+    /// This is useful after a bulk load done with a relaxed
+    /// `DurabilityPolicy`. Directories that do not persist
+    /// anything locally simply return `Ok(())`.
+    fn sync_directory(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns the path of the file backing `path` on the local
+    /// filesystem, if the file is stored as is in a local file.
+    ///
+    /// This makes it possible to hard link files, for instance
+    /// to create backups.
+    fn local_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Watches a file written via `atomic_write`, typically `meta.json`,
+    /// and calls `callback` every time its content changes,
+    /// including when it is replaced by another process.
+    ///
+    /// The watch lasts as long as the returned `WatchHandle` is alive.
+    ///
+    /// The default implementation polls the file through the directory.
+    fn watch(&self, path: &Path, callback: WatchCallback) -> WatchHandle {
+        watch::poll_file(
+            self.box_clone(),
+            path.to_owned(),
+            Duration::from_millis(DEFAULT_WATCH_INTERVAL_MS),
+            callback,
+        )
+    }
+
     /// Clones the directory and boxes the clone
     fn box_clone(&self) -> Box<Directory>;
 }
@@ -0,0 +1,145 @@
+use directory::error::SizeBudgetExceeded;
+use directory::WritePtr;
+use std::collections::HashMap;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Keeps track of the size of the files of a directory,
+/// and enforces its size budget.
+#[derive(Debug, Default)]
+pub struct DiskUsage {
+    budget: Option<u64>,
+    file_sizes: HashMap<PathBuf, u64>,
+    num_bytes: u64,
+}
+
+impl DiskUsage {
+    /// Sets the size budget, in bytes. `None` removes the budget.
+    pub fn set_budget(&mut self, budget: Option<u64>) {
+        self.budget = budget;
+    }
+
+    /// Returns the size budget, in bytes.
+    pub fn budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    /// Returns the size of a file, if it is tracked.
+    pub fn file_size(&self, path: &Path) -> Option<u64> {
+        self.file_sizes.get(path).cloned()
+    }
+
+    /// Returns the sum of the size of the files tracked.
+    pub fn num_bytes(&self) -> u64 {
+        self.num_bytes
+    }
+
+    /// Returns the number of bytes that can still be written
+    /// before exceeding the budget, or `None` if there is no budget.
+    pub fn remaining(&self) -> Option<u64> {
+        self.budget
+            .map(|budget| budget.saturating_sub(self.num_bytes))
+    }
+
+    /// Records the size of a file.
+    ///
+    /// Growing a file beyond the budget fails, unless
+    /// `force` is true.
+    pub fn set_file_size(&mut self, path: &Path, len: u64, force: bool) -> io::Result<()> {
+        let previous_len = self.file_sizes.get(path).cloned().unwrap_or(0u64);
+        let num_bytes = self.num_bytes - previous_len + len;
+        if let Some(budget) = self.budget {
+            if !force && len > previous_len && num_bytes > budget {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    SizeBudgetExceeded { budget },
+                ));
+            }
+        }
+        self.file_sizes.insert(path.to_owned(), len);
+        self.num_bytes = num_bytes;
+        Ok(())
+    }
+
+    /// Stops tracking a file that was deleted.
+    pub fn remove_file(&mut self, path: &Path) {
+        if let Some(len) = self.file_sizes.remove(path) {
+            self.num_bytes -= len;
+        }
+    }
+}
+
+/// Writer recording the size of the file in a `DiskUsage`,
+/// and failing if it would exceed the size budget.
+pub struct BudgetedWriter {
+    path: PathBuf,
+    underlying: WritePtr,
+    disk_usage: Arc<Mutex<DiskUsage>>,
+    position: u64,
+    len: u64,
+}
+
+impl BudgetedWriter {
+    pub fn new(path: PathBuf, underlying: WritePtr, disk_usage: Arc<Mutex<DiskUsage>>) -> Self {
+        BudgetedWriter {
+            path,
+            underlying,
+            disk_usage,
+            position: 0u64,
+            len: 0u64,
+        }
+    }
+}
+
+impl Write for BudgetedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let end = self.position + buf.len() as u64;
+        if end > self.len {
+            self.disk_usage
+                .lock()
+                .map_err(|_| io::Error::new(io::ErrorKind::Other, "Disk usage lock poisoned"))?
+                .set_file_size(&self.path, end, false)?;
+            self.len = end;
+        }
+        let written = self.underlying.write(buf)?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.underlying.flush()
+    }
+}
+
+impl Seek for BudgetedWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.underlying.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_disk_usage() {
+        let mut disk_usage = DiskUsage::default();
+        let (path_a, path_b) = (Path::new("a"), Path::new("b"));
+        disk_usage.set_file_size(path_a, 10, false).unwrap();
+        assert_eq!(disk_usage.remaining(), None);
+        disk_usage.set_budget(Some(15));
+        assert_eq!(disk_usage.remaining(), Some(5));
+        assert!(disk_usage.set_file_size(path_b, 6, false).is_err());
+        disk_usage.set_file_size(path_b, 5, false).unwrap();
+        assert_eq!(disk_usage.remaining(), Some(0));
+        // shrinking a file, or forcing a write, is always allowed.
+        disk_usage.set_file_size(path_a, 8, false).unwrap();
+        disk_usage.set_file_size(path_b, 20, true).unwrap();
+        assert_eq!(disk_usage.num_bytes(), 28);
+        disk_usage.remove_file(path_b);
+        assert_eq!(disk_usage.num_bytes(), 8);
+    }
+}
@@ -0,0 +1,455 @@
+use common::BinarySerializable;
+use directory::error::{DeleteError, IOError, OpenReadError, OpenWriteError};
+use directory::{Directory, ReadOnlySource, WritePtr};
+use ring::aead::{self, OpeningKey, SealingKey, AES_256_GCM};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::cmp;
+use std::fmt;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::result;
+use std::sync::Arc;
+
+/// Size of the plaintext blocks that get encrypted independently.
+const PLAINTEXT_BLOCK_SIZE: usize = 1 << 16;
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// AES-256-GCM keys, and the source of randomness used for the nonces.
+struct Cipher {
+    sealing_key: SealingKey,
+    opening_key: OpeningKey,
+    rng: SystemRandom,
+}
+
+impl Cipher {
+    fn new(key: &[u8; 32]) -> Cipher {
+        Cipher {
+            sealing_key: SealingKey::new(&AES_256_GCM, key)
+                .expect("A 256 bits key is always valid for AES-256-GCM."),
+            opening_key: OpeningKey::new(&AES_256_GCM, key)
+                .expect("A 256 bits key is always valid for AES-256-GCM."),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// The additional authenticated data binds each block to
+    /// its file and its position within the file, so that blocks
+    /// cannot be swapped or reordered.
+    ///
+    /// It also records whether the block is the last one of the file,
+    /// so that dropping trailing records is detected.
+    fn associated_data(path: &Path, block_ord: u64, is_last: bool) -> Vec<u8> {
+        let mut ad = path.to_string_lossy().into_owned().into_bytes();
+        block_ord
+            .serialize(&mut ad)
+            .expect("Writing to a Vec should never fail.");
+        ad.push(is_last as u8);
+        ad
+    }
+
+    /// Encrypts `data` by blocks, starting at block `first_block_ord`.
+    ///
+    /// If `is_end` is true, the last block is flagged as the last
+    /// block of the file. An empty `data` then still yields one
+    /// (empty) record, so that an empty file is never mistaken
+    /// for a truncated one.
+    fn seal_blocks<W: Write>(
+        &self,
+        path: &Path,
+        first_block_ord: u64,
+        data: &[u8],
+        is_end: bool,
+        output: &mut W,
+    ) -> io::Result<()> {
+        if data.is_empty() {
+            if is_end {
+                self.seal_block(path, first_block_ord, &[], true, output)?;
+            }
+            return Ok(());
+        }
+        let num_blocks = (data.len() + PLAINTEXT_BLOCK_SIZE - 1) / PLAINTEXT_BLOCK_SIZE;
+        for (i, block) in data.chunks(PLAINTEXT_BLOCK_SIZE).enumerate() {
+            let is_last = is_end && i + 1 == num_blocks;
+            self.seal_block(path, first_block_ord + i as u64, block, is_last, output)?;
+        }
+        Ok(())
+    }
+
+    /// Encrypts a block and appends the resulting record to `output`.
+    ///
+    /// A record is laid out as
+    /// `[ciphertext_len: u32][nonce][ciphertext + tag]`
+    fn seal_block<W: Write>(
+        &self,
+        path: &Path,
+        block_ord: u64,
+        plaintext: &[u8],
+        is_last: bool,
+        output: &mut W,
+    ) -> io::Result<()> {
+        let nonce_len = AES_256_GCM.nonce_len();
+        let tag_len = AES_256_GCM.tag_len();
+        let mut nonce = vec![0u8; nonce_len];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| invalid_data(format!("Failed to generate a nonce for {:?}", path)))?;
+        let mut in_out = Vec::with_capacity(plaintext.len() + tag_len);
+        in_out.extend_from_slice(plaintext);
+        in_out.resize(plaintext.len() + tag_len, 0u8);
+        let ad = Cipher::associated_data(path, block_ord, is_last);
+        let ciphertext_len =
+            aead::seal_in_place(&self.sealing_key, &nonce, &ad, &mut in_out, tag_len)
+                .map_err(|_| invalid_data(format!("Failed to encrypt {:?}", path)))?;
+        (ciphertext_len as u32).serialize(output)?;
+        output.write_all(&nonce)?;
+        output.write_all(&in_out[..ciphertext_len])
+    }
+
+    /// Decrypts all of the records of an encrypted file.
+    fn open_file(&self, path: &Path, data: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce_len = AES_256_GCM.nonce_len();
+        let mut plaintext = Vec::with_capacity(data.len());
+        let mut cursor = data;
+        let mut block_ord = 0u64;
+        if cursor.is_empty() {
+            return Err(invalid_data(format!("Encrypted file {:?} is truncated", path)));
+        }
+        while !cursor.is_empty() {
+            let ciphertext_len = u32::deserialize(&mut cursor)? as usize;
+            if cursor.len() < nonce_len + ciphertext_len {
+                return Err(invalid_data(format!("Encrypted file {:?} is truncated", path)));
+            }
+            let (nonce, rest) = cursor.split_at(nonce_len);
+            let (ciphertext, rest) = rest.split_at(ciphertext_len);
+            let mut in_out = ciphertext.to_owned();
+            // if the file was truncated at a record boundary, the record
+            // read last was not sealed as the last one, and fails to open.
+            let ad = Cipher::associated_data(path, block_ord, rest.is_empty());
+            let block = aead::open_in_place(&self.opening_key, nonce, &ad, 0, &mut in_out)
+                .map_err(|_| {
+                    invalid_data(format!(
+                        "Failed to decrypt {:?}. The key is wrong or the file is corrupted",
+                        path
+                    ))
+                })?;
+            plaintext.extend_from_slice(block);
+            cursor = rest;
+            block_ord += 1;
+        }
+        Ok(plaintext)
+    }
+}
+
+/// Length of the record of a full block.
+fn full_record_len() -> u64 {
+    (4 + AES_256_GCM.nonce_len() + PLAINTEXT_BLOCK_SIZE + AES_256_GCM.tag_len()) as u64
+}
+
+/// Writer associated with the `EncryptedDirectory`.
+///
+/// Data is encrypted by blocks of `PLAINTEXT_BLOCK_SIZE` bytes.
+/// On flush, the pending (possibly partial) block is encrypted and
+/// written, so that flushed data is always readable.
+///
+/// The plaintext of the last two full blocks and of the pending block
+/// is kept in memory, and re-encrypted on flush.
+/// Seeking is only supported within this tail.
+struct EncryptedWriter {
+    path: PathBuf,
+    cipher: Arc<Cipher>,
+    underlying: WritePtr,
+    tail: Vec<u8>,
+    tail_block_ord: u64,
+    cursor: usize,
+    dirty: bool,
+}
+
+impl EncryptedWriter {
+    fn new(path: PathBuf, cipher: Arc<Cipher>, underlying: WritePtr) -> EncryptedWriter {
+        EncryptedWriter {
+            path,
+            cipher,
+            underlying,
+            tail: Vec::with_capacity(PLAINTEXT_BLOCK_SIZE),
+            tail_block_ord: 0u64,
+            cursor: 0,
+            // even an empty file gets its (empty) last record on flush.
+            dirty: true,
+        }
+    }
+
+    fn tail_start(&self) -> u64 {
+        self.tail_block_ord * PLAINTEXT_BLOCK_SIZE as u64
+    }
+
+    /// Encrypts the blocks of the tail, overwriting
+    /// the records that may have been written on a previous flush.
+    ///
+    /// The last block is flagged as the end of the file
+    /// only if the whole tail is written.
+    fn write_tail(&mut self, num_bytes: usize) -> io::Result<()> {
+        self.underlying
+            .seek(SeekFrom::Start(self.tail_block_ord * full_record_len()))?;
+        let is_end = num_bytes == self.tail.len();
+        self.cipher.seal_blocks(
+            &self.path,
+            self.tail_block_ord,
+            &self.tail[..num_bytes],
+            is_end,
+            &mut self.underlying,
+        )
+    }
+
+    /// Seals the oldest blocks of the tail for good,
+    /// so that it never holds more than two full blocks
+    /// and the pending block.
+    fn shrink_tail(&mut self) -> io::Result<()> {
+        while self.tail.len() > 2 * PLAINTEXT_BLOCK_SIZE && self.cursor >= PLAINTEXT_BLOCK_SIZE {
+            self.write_tail(PLAINTEXT_BLOCK_SIZE)?;
+            self.tail.drain(..PLAINTEXT_BLOCK_SIZE);
+            self.tail_block_ord += 1;
+            self.cursor -= PLAINTEXT_BLOCK_SIZE;
+        }
+        Ok(())
+    }
+}
+
+impl Write for EncryptedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let overlap = cmp::min(buf.len(), self.tail.len() - self.cursor);
+        self.tail[self.cursor..self.cursor + overlap].copy_from_slice(&buf[..overlap]);
+        self.tail.extend_from_slice(&buf[overlap..]);
+        self.cursor += buf.len();
+        self.dirty = true;
+        self.shrink_tail()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.dirty {
+            let num_bytes = self.tail.len();
+            self.write_tail(num_bytes)?;
+            self.dirty = false;
+        }
+        self.underlying.flush()
+    }
+}
+
+impl Seek for EncryptedWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let tail_start = self.tail_start() as i64;
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => tail_start + self.cursor as i64 + offset,
+            SeekFrom::End(offset) => tail_start + self.tail.len() as i64 + offset,
+        };
+        if target < tail_start || target > tail_start + self.tail.len() as i64 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "The EncryptedDirectory only supports seeking within the last written blocks",
+            ));
+        }
+        self.cursor = (target - tail_start) as usize;
+        Ok(target as u64)
+    }
+}
+
+/// Directory wrapper encrypting the content of all files
+/// with AES-256-GCM.
+///
+/// Files are split in blocks that are encrypted independently,
+/// each with a random nonce. Each block is authenticated together
+/// with its file path, its position and whether it is the last
+/// block of the file, so that tampering with the files, including
+/// truncating them, is detected on read.
+///
+/// Only the content of the files is encrypted. File names
+/// (segment ids, `meta.json`, ...) are left in clear.
+///
+/// Files are entirely decrypted in anonymous memory on `open_read`.
+/// Writers returned by this directory only support seeking within
+/// the last couple of blocks written.
+pub struct EncryptedDirectory {
+    directory: Box<Directory>,
+    cipher: Arc<Cipher>,
+}
+
+impl EncryptedDirectory {
+    /// Wraps a directory, encrypting its files with the given 256 bits key.
+    pub fn new<Dir: Directory>(directory: Dir, key: &[u8; 32]) -> EncryptedDirectory {
+        EncryptedDirectory {
+            directory: box directory,
+            cipher: Arc::new(Cipher::new(key)),
+        }
+    }
+
+    fn encrypt(&self, path: &Path, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encrypted = Vec::new();
+        self.cipher
+            .seal_blocks(path, 0u64, data, true, &mut encrypted)?;
+        Ok(encrypted)
+    }
+}
+
+impl fmt::Debug for EncryptedDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EncryptedDirectory({:?})", self.directory)
+    }
+}
+
+impl Clone for EncryptedDirectory {
+    fn clone(&self) -> EncryptedDirectory {
+        EncryptedDirectory {
+            directory: self.directory.box_clone(),
+            cipher: Arc::clone(&self.cipher),
+        }
+    }
+}
+
+impl Directory for EncryptedDirectory {
+    fn open_read(&self, path: &Path) -> result::Result<ReadOnlySource, OpenReadError> {
+        let source = self.directory.open_read(path)?;
+        let plaintext = self.cipher
+            .open_file(path, source.as_slice())
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        Ok(ReadOnlySource::from(plaintext))
+    }
+
+    fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
+        self.directory.delete(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.directory.exists(path)
+    }
+
+    fn open_write(&mut self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
+        let underlying = self.directory.open_write(path)?;
+        let writer = EncryptedWriter::new(path.to_owned(), Arc::clone(&self.cipher), underlying);
+        Ok(BufWriter::new(Box::new(writer)))
+    }
+
+    fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
+        let data = self.directory.atomic_read(path)?;
+        let plaintext = self.cipher
+            .open_file(path, &data)
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        Ok(plaintext)
+    }
+
+    fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let encrypted = self.encrypt(path, data)?;
+        self.directory.atomic_write(path, &encrypted)
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.directory.sync_directory()
+    }
+
+    fn box_clone(&self) -> Box<Directory> {
+        box self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use directory::RAMDirectory;
+
+    const KEY: [u8; 32] = [7u8; 32];
+
+    #[test]
+    fn test_encrypted_directory() {
+        let ram_directory = RAMDirectory::create();
+        let mut directory = EncryptedDirectory::new(ram_directory.clone(), &KEY);
+        let path = Path::new("test");
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        {
+            let mut w = directory.open_write(path).unwrap();
+            w.write_all(&data[..1_000]).unwrap();
+            w.flush().unwrap();
+            w.write_all(&data[1_000..]).unwrap();
+            w.flush().unwrap();
+        }
+        assert_eq!(directory.open_read(path).unwrap().as_slice(), &data[..]);
+        // the underlying directory only sees ciphertext
+        let raw = ram_directory.open_read(path).unwrap();
+        assert!(!raw.as_slice()
+            .windows(100)
+            .any(|window| window == &data[..100]));
+    }
+
+    #[test]
+    fn test_encrypted_directory_seek() {
+        let mut directory = EncryptedDirectory::new(RAMDirectory::create(), &KEY);
+        let path = Path::new("test");
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        {
+            let mut w = directory.open_write(path).unwrap();
+            w.write_all(&data[..70_000]).unwrap();
+            w.write_all(b"footer").unwrap();
+            w.flush().unwrap();
+            w.seek(SeekFrom::Start(70_000)).unwrap();
+            w.write_all(&data[70_000..]).unwrap();
+            w.flush().unwrap();
+            assert!(w.seek(SeekFrom::Start(0)).is_err());
+        }
+        assert_eq!(directory.open_read(path).unwrap().as_slice(), &data[..]);
+    }
+
+    #[test]
+    fn test_encrypted_directory_atomic() {
+        let mut directory = EncryptedDirectory::new(RAMDirectory::create(), &KEY);
+        let path = Path::new("meta.json");
+        directory.atomic_write(path, b"hello").unwrap();
+        assert_eq!(&directory.atomic_read(path).unwrap()[..], b"hello");
+        directory.atomic_write(path, b"").unwrap();
+        assert!(directory.atomic_read(path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_encrypted_directory_truncated() {
+        let mut ram_directory = RAMDirectory::create();
+        let mut directory = EncryptedDirectory::new(ram_directory.clone(), &KEY);
+        let path = Path::new("test");
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        directory.atomic_write(path, &data).unwrap();
+        let raw = ram_directory.atomic_read(path).unwrap();
+        // dropping the last record
+        let num_full_records = data.len() / PLAINTEXT_BLOCK_SIZE;
+        let truncated_len = num_full_records * full_record_len() as usize;
+        ram_directory
+            .atomic_write(path, &raw[..truncated_len])
+            .unwrap();
+        assert!(directory.atomic_read(path).is_err());
+        // dropping all of the records
+        ram_directory.atomic_write(path, &[]).unwrap();
+        assert!(directory.atomic_read(path).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_directory_empty_file() {
+        let mut directory = EncryptedDirectory::new(RAMDirectory::create(), &KEY);
+        let path = Path::new("empty");
+        {
+            let mut w = directory.open_write(path).unwrap();
+            w.flush().unwrap();
+        }
+        assert!(directory.open_read(path).unwrap().as_slice().is_empty());
+    }
+
+    #[test]
+    fn test_encrypted_directory_wrong_key() {
+        let ram_directory = RAMDirectory::create();
+        let path = Path::new("meta.json");
+        EncryptedDirectory::new(ram_directory.clone(), &KEY)
+            .atomic_write(path, b"secret")
+            .unwrap();
+        let other_directory = EncryptedDirectory::new(ram_directory, &[3u8; 32]);
+        assert!(other_directory.atomic_read(path).is_err());
+    }
+}
@@ -36,6 +36,60 @@ impl IOError {
             err,
         }
     }
+
+    /// Returns the underlying `io::Error`.
+    pub fn io_error(&self) -> &io::Error {
+        &self.err
+    }
+
+    /// Returns the path of the offending file, if known.
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// Returns true iff the error was caused by the disk being full,
+    /// or by the size budget of the directory being exceeded.
+    pub fn is_disk_full(&self) -> bool {
+        is_disk_full(&self.err)
+    }
+}
+
+/// Error wrapped in an `io::Error` when a write would exceed the
+/// size budget of a directory.
+#[derive(Debug)]
+pub struct SizeBudgetExceeded {
+    /// The size budget of the directory, in bytes.
+    pub budget: u64,
+}
+
+impl fmt::Display for SizeBudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the size budget of {} bytes was exceeded", self.budget)
+    }
+}
+
+impl StdError for SizeBudgetExceeded {
+    fn description(&self) -> &str {
+        "size budget exceeded"
+    }
+}
+
+/// `ENOSPC` is 28 on both Linux and macOS.
+#[cfg(unix)]
+const ENOSPC: i32 = 28;
+
+/// Returns true iff the io error was caused by the disk being full,
+/// or by the size budget of the directory being exceeded.
+pub(crate) fn is_disk_full(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    {
+        if err.raw_os_error() == Some(ENOSPC) {
+            return true;
+        }
+    }
+    err.get_ref()
+        .map(|inner| inner.is::<SizeBudgetExceeded>())
+        .unwrap_or(false)
 }
 
 impl From<io::Error> for IOError {
@@ -1,18 +1,21 @@
 use std::path::{Path, PathBuf};
 use serde_json;
 use directory::error::{DeleteError, IOError, OpenReadError, OpenWriteError};
-use directory::{ReadOnlySource, WritePtr};
+use directory::{sync_local_directory, ReadOnlySource, WritePtr};
+use directory::disk_usage::{BudgetedWriter, DiskUsage};
 use std::result;
 use std::io;
+use std::fs::{self, File, OpenOptions};
+use common::make_io_err;
 use Directory;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 use std::collections::HashSet;
 use std::sync::RwLockWriteGuard;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use core::MANAGED_FILEPATH;
 use std::collections::HashMap;
 use std::fmt;
-use error::{ErrorKind, Result, ResultExt};
+use error::{ErrorKind, Result};
 
 /// Wrapper of directories that keeps track of files created by Tantivy.
 ///
@@ -27,6 +30,7 @@ use error::{ErrorKind, Result, ResultExt};
 pub struct ManagedDirectory {
     directory: Box<Directory>,
     meta_informations: Arc<RwLock<MetaInformation>>,
+    disk_usage: Arc<Mutex<DiskUsage>>,
 }
 
 #[derive(Debug, Default)]
@@ -84,18 +88,22 @@ impl ManagedDirectory {
             Ok(data) => {
                 let managed_files_json = String::from_utf8_lossy(&data);
                 let managed_files: HashSet<PathBuf> = serde_json::from_str(&managed_files_json)
-                    .chain_err(|| ErrorKind::CorruptedFile(MANAGED_FILEPATH.clone()))?;
+                    .map_err(|e| {
+                        ErrorKind::CorruptedFile(MANAGED_FILEPATH.clone(), e.to_string())
+                    })?;
                 Ok(ManagedDirectory {
                     directory: box directory,
                     meta_informations: Arc::new(RwLock::new(MetaInformation {
                         managed_paths: managed_files,
                         protected_files: HashMap::default(),
                     })),
+                    disk_usage: Arc::default(),
                 })
             }
             Err(OpenReadError::FileDoesNotExist(_)) => Ok(ManagedDirectory {
                 directory: box directory,
                 meta_informations: Arc::default(),
+                disk_usage: Arc::default(),
             }),
             Err(OpenReadError::IOError(e)) => Err(From::from(e)),
         }
@@ -207,6 +215,94 @@ impl ManagedDirectory {
         }
     }
 
+    /// Copies files to a directory of the local filesystem.
+    ///
+    /// Files are hard linked whenever possible, and copied
+    /// otherwise (e.g. when the backup is on a different device,
+    /// or when the files are not stored as is on the local filesystem).
+    ///
+    /// The copied and linked files, as well as the `target` directory
+    /// itself, are synced to disk.
+    /// The caller is responsible for protecting the files from
+    /// deletion while they are being copied.
+    pub fn copy_files_to(&self, files: &[PathBuf], target: &Path) -> io::Result<()> {
+        for file in files {
+            let target_path = target.join(file);
+            let linked = match self.directory.local_path(file) {
+                Some(local_path) => fs::hard_link(&local_path, &target_path).is_ok(),
+                None => false,
+            };
+            if linked {
+                // the source file may not have been synced,
+                // depending on the `DurabilityPolicy`.
+                OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(&target_path)?
+                    .sync_all()?;
+            } else {
+                let source = self.directory
+                    .open_read(file)
+                    .map_err(|e| make_io_err(format!("Failed to read {:?}: {:?}", file, e)))?;
+                let mut target_file = File::create(&target_path)?;
+                target_file.write_all(source.as_slice())?;
+                target_file.sync_all()?;
+            }
+        }
+        sync_local_directory(target)
+    }
+
+    /// Sets a size budget, in bytes, on the files managed by
+    /// this directory. `None` removes the budget.
+    ///
+    /// Writes that would exceed the budget fail with an error
+    /// that is reported as `ErrorKind::DiskFull`.
+    /// Files written via `atomic_write` (e.g. `meta.json`) are
+    /// exempted, so that the index meta state is never left
+    /// half-written.
+    pub fn set_size_budget(&self, budget: Option<u64>) -> io::Result<()> {
+        let managed_paths: Vec<PathBuf> = self.meta_informations
+            .read()
+            .expect("Managed directory rlock poisoned in set_size_budget.")
+            .managed_paths
+            .iter()
+            .cloned()
+            .collect();
+        let mut disk_usage = self.lock_disk_usage()?;
+        // files that existed before the directory was opened are measured once.
+        for path in managed_paths {
+            if disk_usage.file_size(&path).is_none() {
+                if let Ok(source) = self.directory.open_read(&path) {
+                    disk_usage.set_file_size(&path, source.len() as u64, true)?;
+                }
+            }
+        }
+        disk_usage.set_budget(budget);
+        Ok(())
+    }
+
+    /// Returns the number of bytes that can still be written before
+    /// exceeding the size budget, or `None` if there is no budget.
+    pub fn remaining_size_budget(&self) -> Option<u64> {
+        self.lock_disk_usage()
+            .ok()
+            .and_then(|disk_usage| disk_usage.remaining())
+    }
+
+    /// Returns the size of a file written through this directory,
+    /// if it is known.
+    pub fn file_size(&self, path: &Path) -> Option<u64> {
+        self.lock_disk_usage()
+            .ok()
+            .and_then(|disk_usage| disk_usage.file_size(path))
+    }
+
+    fn lock_disk_usage(&self) -> io::Result<MutexGuard<DiskUsage>> {
+        self.disk_usage
+            .lock()
+            .map_err(|_| make_io_err("Disk usage lock poisoned".to_string()))
+    }
+
     /// Registers a file as managed
     ///
     /// This method must be called before the file is
@@ -234,12 +330,19 @@ impl Directory for ManagedDirectory {
     fn open_write(&mut self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
         self.register_file_as_managed(path)
             .map_err(|e| IOError::with_path(path.to_owned(), e))?;
-        self.directory.open_write(path)
+        let underlying = self.directory.open_write(path)?;
+        self.lock_disk_usage()
+            .and_then(|mut disk_usage| disk_usage.set_file_size(path, 0u64, true))
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        let writer = BudgetedWriter::new(path.to_owned(), underlying, Arc::clone(&self.disk_usage));
+        Ok(BufWriter::new(Box::new(writer)))
     }
 
     fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
         self.register_file_as_managed(path)?;
-        self.directory.atomic_write(path, data)
+        self.directory.atomic_write(path, data)?;
+        self.lock_disk_usage()?
+            .set_file_size(path, data.len() as u64, true)
     }
 
     fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
@@ -257,13 +360,25 @@ impl Directory for ManagedDirectory {
                 }
             }
         }
-        self.directory.delete(path)
+        self.directory.delete(path)?;
+        self.lock_disk_usage()
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?
+            .remove_file(path);
+        Ok(())
     }
 
     fn exists(&self, path: &Path) -> bool {
         self.directory.exists(path)
     }
 
+    fn sync_directory(&self) -> io::Result<()> {
+        self.directory.sync_directory()
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        self.directory.local_path(path)
+    }
+
     fn box_clone(&self) -> Box<Directory> {
         box self.clone()
     }
@@ -274,6 +389,7 @@ impl Clone for ManagedDirectory {
         ManagedDirectory {
             directory: self.directory.box_clone(),
             meta_informations: Arc::clone(&self.meta_informations),
+            disk_usage: Arc::clone(&self.disk_usage),
         }
     }
 }
@@ -282,7 +398,7 @@ impl Clone for ManagedDirectory {
 mod tests {
 
     use super::*;
-    use directory::MmapDirectory;
+    use directory::{MmapDirectory, RAMDirectory};
     use std::path::Path;
     use std::io::Write;
     use tempdir::TempDir;
@@ -292,6 +408,18 @@ mod tests {
         static ref TEST_PATH2: &'static Path = Path::new("some_path_for_test2");
     }
 
+    #[test]
+    fn test_managed_directory_corrupted_managed_file() {
+        let mut ram_directory = RAMDirectory::create();
+        ram_directory
+            .atomic_write(&MANAGED_FILEPATH, b"not json")
+            .unwrap();
+        match *ManagedDirectory::new(ram_directory).unwrap_err().kind() {
+            ErrorKind::CorruptedFile(ref path, _) => assert_eq!(path, &*MANAGED_FILEPATH),
+            _ => panic!("Expected a CorruptedFile error"),
+        }
+    }
+
     #[test]
     fn test_managed_directory() {
         let tempdir = TempDir::new("index").unwrap();
@@ -0,0 +1,263 @@
+use directory::error::{DeleteError, OpenReadError, OpenWriteError};
+use directory::{Directory, ReadOnlySource, WritePtr};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::result;
+use std::sync::{Arc, Mutex};
+
+/// IO counters for a given kind of file.
+#[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileKindStats {
+    /// Number of calls to `open_read` and `atomic_read`.
+    pub num_reads: u64,
+    /// Number of bytes opened for read.
+    ///
+    /// Files are typically mmapped, so this is an upper bound
+    /// of the number of bytes actually read from disk.
+    pub bytes_read: u64,
+    /// Number of calls to `open_write` and `atomic_write`.
+    pub num_writes: u64,
+    /// Number of bytes written.
+    pub bytes_written: u64,
+    /// Number of files deleted.
+    pub num_deletes: u64,
+}
+
+/// IO statistics collected by a `MetricsDirectory`.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct DirectoryStats {
+    /// Counters, by kind of file.
+    ///
+    /// The kind of a file is its extension (e.g. `idx`, `term`, `json`).
+    pub file_kinds: HashMap<String, FileKindStats>,
+    /// Number of writers currently open.
+    pub open_writers: usize,
+}
+
+impl DirectoryStats {
+    /// Returns the sum of the counters of all of the kinds of files.
+    pub fn total(&self) -> FileKindStats {
+        self.file_kinds
+            .values()
+            .fold(FileKindStats::default(), |mut total, stats| {
+                total.num_reads += stats.num_reads;
+                total.bytes_read += stats.bytes_read;
+                total.num_writes += stats.num_writes;
+                total.bytes_written += stats.bytes_written;
+                total.num_deletes += stats.num_deletes;
+                total
+            })
+    }
+}
+
+fn file_kind(path: &Path) -> String {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("other")
+        .to_string()
+}
+
+#[derive(Default)]
+struct Metrics {
+    stats: Mutex<DirectoryStats>,
+}
+
+impl Metrics {
+    fn record<F: FnOnce(&mut FileKindStats)>(&self, path: &Path, update: F) {
+        self.record_kind(file_kind(path), update);
+    }
+
+    fn record_kind<F: FnOnce(&mut FileKindStats)>(&self, kind: String, update: F) {
+        if let Ok(mut stats) = self.stats.lock() {
+            update(stats.file_kinds.entry(kind).or_insert_with(Default::default));
+        }
+    }
+
+    fn update_open_writers<F: FnOnce(&mut usize)>(&self, update: F) {
+        if let Ok(mut stats) = self.stats.lock() {
+            update(&mut stats.open_writers);
+        }
+    }
+}
+
+/// Writer associated with the `MetricsDirectory`.
+struct MetricsWriter {
+    underlying: WritePtr,
+    kind: String,
+    metrics: Arc<Metrics>,
+}
+
+impl Write for MetricsWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.underlying.write(buf)?;
+        self.metrics
+            .record_kind(self.kind.clone(), |stats| stats.bytes_written += written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.underlying.flush()
+    }
+}
+
+impl Seek for MetricsWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.underlying.seek(pos)
+    }
+}
+
+impl Drop for MetricsWriter {
+    fn drop(&mut self) {
+        self.metrics.update_open_writers(|open_writers| *open_writers -= 1);
+    }
+}
+
+/// Directory wrapper collecting IO statistics about
+/// the underlying directory.
+///
+/// Counters are kept per kind of file, so that capacity planning
+/// can be done without resorting to `strace`.
+/// See `MetricsDirectory::stats`.
+pub struct MetricsDirectory {
+    directory: Box<Directory>,
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsDirectory {
+    /// Wraps a directory, collecting statistics about its usage.
+    pub fn new<Dir: Directory>(directory: Dir) -> MetricsDirectory {
+        MetricsDirectory {
+            directory: box directory,
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Returns a snapshot of the statistics collected so far.
+    pub fn stats(&self) -> DirectoryStats {
+        self.metrics
+            .stats
+            .lock()
+            .expect("Metrics directory lock poisoned.")
+            .clone()
+    }
+}
+
+impl fmt::Debug for MetricsDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MetricsDirectory({:?})", self.directory)
+    }
+}
+
+impl Clone for MetricsDirectory {
+    fn clone(&self) -> MetricsDirectory {
+        MetricsDirectory {
+            directory: self.directory.box_clone(),
+            metrics: Arc::clone(&self.metrics),
+        }
+    }
+}
+
+impl Directory for MetricsDirectory {
+    fn open_read(&self, path: &Path) -> result::Result<ReadOnlySource, OpenReadError> {
+        let source = self.directory.open_read(path)?;
+        self.metrics.record(path, |stats| {
+            stats.num_reads += 1;
+            stats.bytes_read += source.len() as u64;
+        });
+        Ok(source)
+    }
+
+    fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
+        self.directory.delete(path)?;
+        self.metrics.record(path, |stats| stats.num_deletes += 1);
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.directory.exists(path)
+    }
+
+    fn open_write(&mut self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
+        let underlying = self.directory.open_write(path)?;
+        self.metrics.record(path, |stats| stats.num_writes += 1);
+        self.metrics.update_open_writers(|open_writers| *open_writers += 1);
+        let writer = MetricsWriter {
+            underlying,
+            kind: file_kind(path),
+            metrics: Arc::clone(&self.metrics),
+        };
+        Ok(BufWriter::new(Box::new(writer)))
+    }
+
+    fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
+        let data = self.directory.atomic_read(path)?;
+        self.metrics.record(path, |stats| {
+            stats.num_reads += 1;
+            stats.bytes_read += data.len() as u64;
+        });
+        Ok(data)
+    }
+
+    fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.directory.atomic_write(path, data)?;
+        self.metrics.record(path, |stats| {
+            stats.num_writes += 1;
+            stats.bytes_written += data.len() as u64;
+        });
+        Ok(())
+    }
+
+    fn sync_directory(&self) -> io::Result<()> {
+        self.directory.sync_directory()
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        self.directory.local_path(path)
+    }
+
+    fn box_clone(&self) -> Box<Directory> {
+        box self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    // There are more tests in directory/mod.rs
+    // The following tests are specific to the MetricsDirectory
+
+    use super::*;
+    use directory::RAMDirectory;
+
+    #[test]
+    fn test_metrics_directory() {
+        let mut directory = MetricsDirectory::new(RAMDirectory::create());
+        let path = Path::new("segment.idx");
+        {
+            let mut w = directory.open_write(path).unwrap();
+            assert_eq!(directory.stats().open_writers, 1);
+            w.write_all(&[1u8; 100]).unwrap();
+            w.flush().unwrap();
+        }
+        assert_eq!(directory.stats().open_writers, 0);
+        directory.open_read(path).unwrap();
+        directory.open_read(path).unwrap();
+        directory.atomic_write(Path::new("meta.json"), b"{}").unwrap();
+        directory.delete(path).unwrap();
+        let stats = directory.stats();
+        assert_eq!(
+            stats.file_kinds["idx"],
+            FileKindStats {
+                num_reads: 2,
+                bytes_read: 200,
+                num_writes: 1,
+                bytes_written: 100,
+                num_deletes: 1,
+            }
+        );
+        assert_eq!(stats.file_kinds["json"].bytes_written, 2);
+        assert_eq!(stats.total().bytes_written, 102);
+    }
+}
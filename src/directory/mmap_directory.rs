@@ -1,5 +1,6 @@
 use atomicwrites;
 use common::make_io_err;
+use core::SegmentComponent;
 use directory::Directory;
 use directory::error::{DeleteError, IOError, OpenDirectoryError, OpenReadError, OpenWriteError};
 use directory::ReadOnlySource;
@@ -19,6 +20,57 @@ use std::result;
 use std::sync::Arc;
 use std::sync::RwLock;
 use tempdir::TempDir;
+use uuid::Uuid;
+
+/// Access pattern hint given to the kernel, via `madvise`,
+/// for the files of the `MmapDirectory`.
+///
+/// Hints only have an effect on unix, when tantivy is compiled
+/// with the `madvise` feature. They are silently ignored otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessHint {
+    /// No specific access pattern.
+    Normal,
+    /// The file will be read sequentially, e.g. during a merge.
+    /// The kernel reads ahead aggressively, and frees pages
+    /// soon after they were read.
+    Sequential,
+    /// The file will be accessed randomly, e.g. term dictionaries
+    /// and fast fields. Readahead is disabled.
+    Random,
+    /// The file will be needed soon, and should be preloaded
+    /// in the page cache.
+    WillNeed,
+}
+
+#[cfg(all(unix, feature = "madvise"))]
+fn advise(mmap: &MmapReadOnly, hint: AccessHint) -> io::Result<()> {
+    use libc;
+    let advice = match hint {
+        AccessHint::Normal => libc::MADV_NORMAL,
+        AccessHint::Sequential => libc::MADV_SEQUENTIAL,
+        AccessHint::Random => libc::MADV_RANDOM,
+        AccessHint::WillNeed => libc::MADV_WILLNEED,
+    };
+    // safety: the slice is only used to get the address and the length
+    // of the mapping, which stays alive while `mmap` is borrowed.
+    // `madvise` does not read nor modify the mapped data.
+    // The mmap covers the entire file, and is therefore page-aligned.
+    let ret = unsafe {
+        let data = mmap.as_slice();
+        libc::madvise(data.as_ptr() as *mut libc::c_void, data.len(), advice)
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(all(unix, feature = "madvise")))]
+fn advise(_mmap: &MmapReadOnly, _hint: AccessHint) -> io::Result<()> {
+    Ok(())
+}
 
 /// Returns None iff the file exists, can be read, but is empty (and hence
 /// cannot be mmapped).
@@ -45,12 +97,83 @@ fn open_mmap(full_path: &Path) -> result::Result<Option<MmapReadOnly>, OpenReadE
         .map_err(|e| From::from(IOError::with_path(full_path.to_owned(), e)))
 }
 
+/// Defines when the `MmapDirectory` calls `fsync`.
+///
+/// Relaxing the durability policy can greatly improve indexing
+/// throughput, typically for bulk loads that can simply be
+/// restarted from scratch if the machine crashes.
+/// `Directory::sync_directory` can then be called once
+/// the bulk load is over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DurabilityPolicy {
+    /// Every file is synced on flush, and the directory is
+    /// synced whenever a file is created or deleted.
+    ///
+    /// This is the default.
+    SyncAll,
+    /// Only the files written via `atomic_write`
+    /// (e.g. `meta.json`) are synced.
+    ///
+    /// After a crash, the last commit may point to segment
+    /// files that were not entirely persisted.
+    SyncMeta,
+    /// `fsync` is never called, and durability is left
+    /// entirely to the operating system.
+    NoSync,
+}
+
+impl Default for DurabilityPolicy {
+    fn default() -> DurabilityPolicy {
+        DurabilityPolicy::SyncAll
+    }
+}
+
+/// Syncs a directory of the local filesystem, so that
+/// the files created or renamed within it are persisted.
+pub(crate) fn sync_local_directory(path: &Path) -> io::Result<()> {
+    let mut open_opts = OpenOptions::new();
+
+    // Linux needs read to be set, otherwise returns EINVAL
+    // write must not be set, or it fails with EISDIR
+    open_opts.read(true);
+
+    // On Windows, opening a directory requires FILE_FLAG_BACKUP_SEMANTICS
+    // and calling sync_all() only works if write access is requested.
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        use winapi::winbase;
+
+        open_opts
+            .write(true)
+            .custom_flags(winbase::FILE_FLAG_BACKUP_SEMANTICS);
+    }
+
+    let fd = open_opts.open(path)?;
+    fd.sync_all()?;
+    Ok(())
+}
+
+/// Returns a unique temporary path, next to `path`,
+/// for the atomic writes that do not go through `atomicwrites`.
+pub(crate) fn atomic_write_tmp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()))
+}
+
+/// Hit and miss counters of a cache.
+///
+/// They are shared by the mmap cache of the `MmapDirectory`,
+/// the `CachingDirectory` and the doc store block cache.
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct CacheCounters {
-    // Number of time the cache prevents to call `mmap`
+    /// Number of lookups served from the cache,
+    /// without reading the underlying data.
     pub hit: usize,
-    // Number of time tantivy had to call `mmap`
-    // as no entry was in the cache.
+    /// Number of lookups for which no entry was in the cache,
+    /// and the data had to be read (or mmapped) again.
     pub miss: usize,
 }
 
@@ -58,6 +181,11 @@ pub struct CacheCounters {
 pub struct CacheInfo {
     pub counters: CacheCounters,
     pub mmapped: Vec<PathBuf>,
+    /// Sum of the length of the files currently mmapped.
+    ///
+    /// This is an upper bound of the memory resident
+    /// in the page cache for this directory.
+    pub mmapped_bytes: usize,
 }
 
 struct MmapCache {
@@ -82,13 +210,19 @@ impl MmapCache {
 
     fn get_info(&mut self) -> CacheInfo {
         let paths: Vec<PathBuf> = self.cache.keys().cloned().collect();
+        let mmapped_bytes = self.cache.values().map(|mmap| mmap.len()).sum();
         CacheInfo {
             counters: self.counters.clone(),
             mmapped: paths,
+            mmapped_bytes,
         }
     }
 
-    fn get_mmap(&mut self, full_path: &Path) -> Result<Option<MmapReadOnly>, OpenReadError> {
+    fn get_mmap(
+        &mut self,
+        full_path: &Path,
+        hint: Option<AccessHint>,
+    ) -> Result<Option<MmapReadOnly>, OpenReadError> {
         Ok(match self.cache.entry(full_path.to_owned()) {
             HashMapEntry::Occupied(occupied_entry) => {
                 let mmap = occupied_entry.get();
@@ -98,6 +232,10 @@ impl MmapCache {
             HashMapEntry::Vacant(vacant_entry) => {
                 self.counters.miss += 1;
                 if let Some(mmap) = open_mmap(full_path)? {
+                    if let Some(hint) = hint {
+                        advise(&mmap, hint)
+                            .map_err(|e| IOError::with_path(full_path.to_owned(), e))?;
+                    }
                     vacant_entry.insert(mmap.clone());
                     Some(mmap)
                 } else {
@@ -116,6 +254,8 @@ impl MmapCache {
 pub struct MmapDirectory {
     root_path: PathBuf,
     mmap_cache: Arc<RwLock<MmapCache>>,
+    durability: DurabilityPolicy,
+    access_hints: HashMap<String, AccessHint>,
     _temp_directory: Arc<Option<TempDir>>,
 }
 
@@ -136,6 +276,8 @@ impl MmapDirectory {
         let directory = MmapDirectory {
             root_path: tempdir_path,
             mmap_cache: Arc::new(RwLock::new(MmapCache::default())),
+            durability: DurabilityPolicy::default(),
+            access_hints: HashMap::new(),
             _temp_directory: Arc::new(Some(tempdir)),
         };
         Ok(directory)
@@ -159,11 +301,64 @@ impl MmapDirectory {
             Ok(MmapDirectory {
                 root_path: PathBuf::from(directory_path),
                 mmap_cache: Arc::new(RwLock::new(MmapCache::default())),
+                durability: DurabilityPolicy::default(),
+                access_hints: HashMap::new(),
                 _temp_directory: Arc::new(None),
             })
         }
     }
 
+    /// Sets the `DurabilityPolicy` of the directory.
+    pub fn with_durability(mut self, durability: DurabilityPolicy) -> MmapDirectory {
+        self.durability = durability;
+        self
+    }
+
+    /// Returns the `DurabilityPolicy` of the directory.
+    pub fn durability(&self) -> DurabilityPolicy {
+        self.durability
+    }
+
+    /// Sets the `AccessHint` given to the kernel when mapping
+    /// the files of a given segment component.
+    pub fn with_access_hint(
+        mut self,
+        component: SegmentComponent,
+        hint: AccessHint,
+    ) -> MmapDirectory {
+        self.access_hints
+            .insert(component.extension().to_string(), hint);
+        self
+    }
+
+    /// Changes the `AccessHint` of a file that is already mapped,
+    /// for instance to switch to `Sequential` before a merge, or
+    /// to preload a hot file with `WillNeed`.
+    ///
+    /// The file is mapped if it was not already.
+    pub fn advise(&self, path: &Path, hint: AccessHint) -> result::Result<(), OpenReadError> {
+        let full_path = self.resolve_path(path);
+        let mut mmap_cache = self.mmap_cache.write().map_err(|_| {
+            let msg = format!(
+                "Failed to acquired write lock \
+                 on mmap cache while advising {:?}",
+                path
+            );
+            IOError::with_path(path.to_owned(), make_io_err(msg))
+        })?;
+        if let Some(mmap) = mmap_cache.get_mmap(&full_path, None)? {
+            advise(&mmap, hint).map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        }
+        Ok(())
+    }
+
+    fn access_hint(&self, path: &Path) -> Option<AccessHint> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| self.access_hints.get(extension))
+            .cloned()
+    }
+
     /// Joins a relative_path to the directory `root_path`
     /// to create a proper complete `filepath`.
     fn resolve_path(&self, relative_path: &Path) -> PathBuf {
@@ -173,28 +368,8 @@ impl MmapDirectory {
     /// Sync the root directory.
     /// In certain FS, this is required to persistently create
     /// a file.
-    fn sync_directory(&self) -> Result<(), io::Error> {
-        let mut open_opts = OpenOptions::new();
-
-        // Linux needs read to be set, otherwise returns EINVAL
-        // write must not be set, or it fails with EISDIR
-        open_opts.read(true);
-
-        // On Windows, opening a directory requires FILE_FLAG_BACKUP_SEMANTICS
-        // and calling sync_all() only works if write access is requested.
-        #[cfg(windows)]
-        {
-            use std::os::windows::fs::OpenOptionsExt;
-            use winapi::winbase;
-
-            open_opts
-                .write(true)
-                .custom_flags(winbase::FILE_FLAG_BACKUP_SEMANTICS);
-        }
-
-        let fd = open_opts.open(&self.root_path)?;
-        fd.sync_all()?;
-        Ok(())
+    fn sync_root_directory(&self) -> Result<(), io::Error> {
+        sync_local_directory(&self.root_path)
     }
 
     /// Returns some statistical information
@@ -211,29 +386,39 @@ impl MmapDirectory {
 }
 
 /// This Write wraps a File, but has the specificity of
-/// call `sync_all` on flush.
-struct SafeFileWriter(File);
+/// call `sync_all` on flush, unless the `DurabilityPolicy`
+/// says otherwise.
+struct SafeFileWriter {
+    file: File,
+    sync_on_flush: bool,
+}
 
 impl SafeFileWriter {
-    fn new(file: File) -> SafeFileWriter {
-        SafeFileWriter(file)
+    fn new(file: File, sync_on_flush: bool) -> SafeFileWriter {
+        SafeFileWriter {
+            file,
+            sync_on_flush,
+        }
     }
 }
 
 impl Write for SafeFileWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf)
+        self.file.write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()?;
-        self.0.sync_all()
+        self.file.flush()?;
+        if self.sync_on_flush {
+            self.file.sync_all()?;
+        }
+        Ok(())
     }
 }
 
 impl Seek for SafeFileWriter {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.0.seek(pos)
+        self.file.seek(pos)
     }
 }
 
@@ -252,7 +437,7 @@ impl Directory for MmapDirectory {
         })?;
 
         Ok(mmap_cache
-            .get_mmap(&full_path)?
+            .get_mmap(&full_path, self.access_hint(path))?
             .map(ReadOnlySource::Mmap)
             .unwrap_or_else(|| ReadOnlySource::Anonymous(SharedVecSlice::empty())))
     }
@@ -278,12 +463,16 @@ impl Directory for MmapDirectory {
         file.flush()
             .map_err(|e| IOError::with_path(path.to_owned(), e))?;
 
+        let sync_all = self.durability == DurabilityPolicy::SyncAll;
+
         // Apparetntly, on some filesystem syncing the parent
         // directory is required.
-        self.sync_directory()
-            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        if sync_all {
+            self.sync_root_directory()
+                .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        }
 
-        let writer = SafeFileWriter::new(file);
+        let writer = SafeFileWriter::new(file, sync_all);
         Ok(BufWriter::new(Box::new(writer)))
     }
 
@@ -307,8 +496,9 @@ impl Directory for MmapDirectory {
         // when the last reference is gone.
         mmap_cache.cache.remove(&full_path);
         match fs::remove_file(&full_path) {
-            Ok(_) => self.sync_directory()
+            Ok(_) if self.durability == DurabilityPolicy::SyncAll => self.sync_root_directory()
                 .map_err(|e| IOError::with_path(path.to_owned(), e).into()),
+            Ok(_) => Ok(()),
             Err(e) => {
                 if e.kind() == io::ErrorKind::NotFound {
                     Err(DeleteError::FileDoesNotExist(path.to_owned()))
@@ -346,11 +536,42 @@ impl Directory for MmapDirectory {
     fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
         debug!("Atomic Write {:?}", path);
         let full_path = self.resolve_path(path);
+        if self.durability == DurabilityPolicy::NoSync {
+            // write to a temporary file and rename it, without any fsync.
+            // The temporary file name is unique, so that it cannot collide
+            // with a regular file, nor with a concurrent atomic write.
+            let tmp_path = atomic_write_tmp_path(&full_path);
+            let written = File::create(&tmp_path)
+                .and_then(|mut file| file.write_all(data))
+                .and_then(|_| fs::rename(&tmp_path, &full_path));
+            if written.is_err() {
+                let _ = fs::remove_file(&tmp_path);
+            }
+            return written;
+        }
         let meta_file = atomicwrites::AtomicFile::new(full_path, atomicwrites::AllowOverwrite);
         meta_file.write(|f| f.write_all(data))?;
         Ok(())
     }
 
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.resolve_path(path))
+    }
+
+    /// Syncs all of the files of the directory, regardless
+    /// of the `DurabilityPolicy`.
+    fn sync_directory(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.root_path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                // a read-only handle is enough to sync the file,
+                // which may not be writable.
+                File::open(entry.path())?.sync_all()?;
+            }
+        }
+        self.sync_root_directory()
+    }
+
     fn box_clone(&self) -> Box<Directory> {
         Box::new(self.clone())
     }
@@ -380,6 +601,45 @@ mod tests {
         assert_eq!(readonlymap.len(), 0);
     }
 
+    #[test]
+    fn test_nosync_atomic_write() {
+        let mut mmap_directory = MmapDirectory::create_from_tempdir()
+            .unwrap()
+            .with_durability(DurabilityPolicy::NoSync);
+        // a regular file named as the former temporary file is left untouched.
+        let tmp_path = PathBuf::from("meta.tmp");
+        mmap_directory.atomic_write(&tmp_path, b"regular").unwrap();
+        let path = PathBuf::from("meta.json");
+        mmap_directory.atomic_write(&path, b"meta").unwrap();
+        assert_eq!(&mmap_directory.atomic_read(&path).unwrap()[..], b"meta");
+        assert_eq!(&mmap_directory.atomic_read(&tmp_path).unwrap()[..], b"regular");
+        assert_ne!(
+            atomic_write_tmp_path(Path::new("meta.json")),
+            atomic_write_tmp_path(Path::new("meta.json"))
+        );
+    }
+
+    #[test]
+    fn test_access_hints() {
+        let mut mmap_directory = MmapDirectory::create_from_tempdir()
+            .unwrap()
+            .with_access_hint(SegmentComponent::TERMS, AccessHint::Random)
+            .with_access_hint(SegmentComponent::STORE, AccessHint::WillNeed);
+        let path = PathBuf::from("segment.term");
+        {
+            let mut w = mmap_directory.open_write(&path).unwrap();
+            w.write_all(&[1u8; 10_000]).unwrap();
+            w.flush().unwrap();
+        }
+        assert_eq!(mmap_directory.access_hint(&path), Some(AccessHint::Random));
+        assert_eq!(mmap_directory.access_hint(Path::new("segment.fast")), None);
+        assert_eq!(mmap_directory.open_read(&path).unwrap().len(), 10_000);
+        assert!(mmap_directory.advise(&path, AccessHint::Sequential).is_ok());
+        assert!(mmap_directory
+            .advise(Path::new("missing.store"), AccessHint::WillNeed)
+            .is_err());
+    }
+
     #[test]
     fn test_cache() {
         let content = "abc".as_bytes();
@@ -402,6 +662,7 @@ mod tests {
             for (i, path) in paths.iter().enumerate() {
                 let _r = mmap_directory.open_read(path).unwrap();
                 assert_eq!(mmap_directory.get_cache_info().mmapped.len(), i + 1);
+                assert_eq!(mmap_directory.get_cache_info().mmapped_bytes, 3 * (i + 1));
             }
             for path in paths.iter() {
                 let _r = mmap_directory.open_read(path).unwrap();
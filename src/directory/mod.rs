@@ -9,16 +9,33 @@ mod directory;
 mod read_only_source;
 mod shared_vec_slice;
 mod managed_directory;
+mod object_store_directory;
+mod caching_directory;
+mod read_only_directory;
+mod metrics_directory;
+mod disk_usage;
+mod watch;
+#[cfg(feature = "encryption")]
+mod encrypted_directory;
 
 /// Errors specific to the directory module.
 pub mod error;
 
 use std::io::{BufWriter, Seek, Write};
 
-pub use self::read_only_source::ReadOnlySource;
+pub use self::read_only_source::{LazySource, ReadOnlySource};
 pub use self::directory::Directory;
 pub use self::ram_directory::RAMDirectory;
-pub use self::mmap_directory::MmapDirectory;
+pub use self::mmap_directory::{AccessHint, DurabilityPolicy, MmapDirectory};
+pub use self::caching_directory::CachingDirectory;
+pub use self::read_only_directory::ReadOnlyDirectory;
+pub use self::metrics_directory::{DirectoryStats, FileKindStats, MetricsDirectory};
+pub use self::watch::{WatchCallback, WatchHandle};
+#[cfg(feature = "encryption")]
+pub use self::encrypted_directory::EncryptedDirectory;
+pub use self::mmap_directory::CacheCounters;
+pub(crate) use self::mmap_directory::{atomic_write_tmp_path, sync_local_directory};
+pub use self::object_store_directory::{FileObjectStore, ObjectStore, ObjectStoreDirectory};
 
 pub(crate) use self::read_only_source::SourceRead;
 pub(crate) use self::managed_directory::{FileProtection, ManagedDirectory};
@@ -39,6 +56,7 @@ mod tests {
     use super::*;
     use std::path::Path;
     use std::io::{Seek, SeekFrom, Write};
+    use tempdir::TempDir;
 
     lazy_static! {
         static ref TEST_PATH: &'static Path = Path::new("some_path_for_test");
@@ -56,6 +74,37 @@ mod tests {
         test_directory(&mut mmap_directory);
     }
 
+    #[test]
+    fn test_mmap_directory_no_sync() {
+        let mut mmap_directory = MmapDirectory::create_from_tempdir()
+            .unwrap()
+            .with_durability(DurabilityPolicy::NoSync);
+        test_directory(&mut mmap_directory);
+        mmap_directory.atomic_write(*TEST_PATH, b"meta").unwrap();
+        assert_eq!(&mmap_directory.atomic_read(*TEST_PATH).unwrap()[..], b"meta");
+        assert!(mmap_directory.sync_directory().is_ok());
+    }
+
+    #[test]
+    fn test_object_store_directory() {
+        let tempdir = TempDir::new("objectstore").unwrap();
+        let mut directory =
+            ObjectStoreDirectory::with_block_size(FileObjectStore::new(tempdir.path()), 2);
+        test_directory(&mut directory);
+    }
+
+    #[test]
+    fn test_metrics_directory() {
+        let mut directory = MetricsDirectory::new(RAMDirectory::create());
+        test_directory(&mut directory);
+    }
+
+    #[test]
+    fn test_caching_directory() {
+        let mut directory = CachingDirectory::new(RAMDirectory::create(), 1_000);
+        test_directory(&mut directory);
+    }
+
     #[test]
     #[should_panic]
     fn ram_directory_panics_if_flush_forgotten() {
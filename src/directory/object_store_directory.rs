@@ -0,0 +1,648 @@
+use common::make_io_err;
+use core::{MemoryAccountant, MemoryComponent, MemoryReservation};
+use directory::error::{DeleteError, IOError, OpenReadError, OpenWriteError};
+use directory::read_only_source::{LazySource, RangeReader};
+use directory::{Directory, ReadOnlySource, WritePtr};
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::result;
+use std::sync::{Arc, RwLock};
+
+/// Default size of the blocks fetched from the object store.
+const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+
+/// Default budget of the cache of blocks.
+const DEFAULT_CACHE_BUDGET_IN_BYTES: usize = 256 << 20;
+
+/// Minimal interface of a remote blob store (S3, GCS, ...).
+///
+/// Objects are addressed by a relative path, and are expected
+/// to be immutable once written, with the exception of the files
+/// written via `Directory::atomic_write` (e.g. `meta.json`)
+/// which are simply overwritten by a new `put`.
+///
+/// Reads are always explicit byte ranges, so that implementations
+/// can map them to HTTP range requests.
+pub trait ObjectStore: fmt::Debug + Send + Sync + 'static {
+    /// Returns the length of the object, in bytes.
+    ///
+    /// Should return an error of kind `io::ErrorKind::NotFound`
+    /// if the object does not exist.
+    fn len(&self, path: &Path) -> io::Result<u64>;
+
+    /// Returns the bytes of the object within `[from, to)`.
+    ///
+    /// Should return an error of kind `io::ErrorKind::NotFound`
+    /// if the object does not exist.
+    fn read_range(&self, path: &Path, from: u64, to: u64) -> io::Result<Vec<u8>>;
+
+    /// Uploads an object, replacing any previous object with the same path.
+    fn put(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Removes an object.
+    ///
+    /// Should return an error of kind `io::ErrorKind::NotFound`
+    /// if the object does not exist.
+    fn delete(&self, path: &Path) -> io::Result<()>;
+
+    /// Returns true iff the object exists.
+    fn exists(&self, path: &Path) -> bool;
+}
+
+/// Reference implementation of an `ObjectStore`, storing each
+/// object as a file under a root directory.
+///
+/// It is mostly useful for testing, and as an example for
+/// people implementing their own remote `ObjectStore`.
+#[derive(Debug, Clone)]
+pub struct FileObjectStore {
+    root_path: PathBuf,
+}
+
+impl FileObjectStore {
+    /// Creates an object store storing its objects in `root_path`.
+    pub fn new<P: AsRef<Path>>(root_path: P) -> FileObjectStore {
+        FileObjectStore {
+            root_path: root_path.as_ref().to_owned(),
+        }
+    }
+}
+
+impl ObjectStore for FileObjectStore {
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        Ok(fs::metadata(self.root_path.join(path))?.len())
+    }
+
+    fn read_range(&self, path: &Path, from: u64, to: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(self.root_path.join(path))?;
+        file.seek(SeekFrom::Start(from))?;
+        let mut buffer = Vec::with_capacity((to - from) as usize);
+        file.take(to - from).read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    fn put(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        let mut file = File::create(self.root_path.join(path))?;
+        file.write_all(data)?;
+        file.sync_all()
+    }
+
+    fn delete(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(self.root_path.join(path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.root_path.join(path).exists()
+    }
+}
+
+fn to_open_read_error(path: &Path, err: io::Error) -> OpenReadError {
+    if err.kind() == io::ErrorKind::NotFound {
+        OpenReadError::FileDoesNotExist(path.to_owned())
+    } else {
+        OpenReadError::IOError(IOError::with_path(path.to_owned(), err))
+    }
+}
+
+struct CachedBlock {
+    block: Arc<Vec<u8>>,
+    last_access: u64,
+    _reservation: MemoryReservation,
+}
+
+/// LRU cache of the blocks that were fetched from the object store,
+/// bounded by the sum of the length of the cached blocks.
+///
+/// Since the segment files are immutable, a cached block
+/// never needs to be invalidated, except when its file
+/// gets deleted.
+struct BlockCache {
+    memory_accountant: MemoryAccountant,
+    budget_in_bytes: usize,
+    num_bytes: usize,
+    clock: u64,
+    blocks: HashMap<(PathBuf, usize), CachedBlock>,
+}
+
+impl BlockCache {
+    fn new(budget_in_bytes: usize, memory_accountant: MemoryAccountant) -> BlockCache {
+        BlockCache {
+            memory_accountant,
+            budget_in_bytes,
+            num_bytes: 0,
+            clock: 0,
+            blocks: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, path: &Path, block_id: usize) -> Option<Arc<Vec<u8>>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.blocks
+            .get_mut(&(path.to_owned(), block_id))
+            .map(|cached_block| {
+                cached_block.last_access = clock;
+                Arc::clone(&cached_block.block)
+            })
+    }
+
+    fn insert(&mut self, path: &Path, block_id: usize, block: Arc<Vec<u8>>) {
+        let block_len = block.len();
+        if block_len > self.budget_in_bytes {
+            return;
+        }
+        let key = (path.to_owned(), block_id);
+        self.discard(&key);
+        while self.num_bytes + block_len > self.budget_in_bytes {
+            self.evict_least_recently_used();
+        }
+        // the memory limiter may require evicting more blocks,
+        // or to give up caching the block.
+        let reservation = loop {
+            if let Some(reservation) = self.memory_accountant
+                .try_reserve(MemoryComponent::SourceCache, block_len)
+            {
+                break reservation;
+            }
+            if self.blocks.is_empty() {
+                return;
+            }
+            self.evict_least_recently_used();
+        };
+        self.num_bytes += block_len;
+        self.blocks.insert(
+            key,
+            CachedBlock {
+                block,
+                last_access: self.clock,
+                _reservation: reservation,
+            },
+        );
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let lru_key: Option<(PathBuf, usize)> = self.blocks
+            .iter()
+            .min_by_key(|&(_, cached_block)| cached_block.last_access)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = lru_key {
+            self.discard(&key);
+        }
+    }
+
+    fn discard(&mut self, key: &(PathBuf, usize)) {
+        if let Some(cached_block) = self.blocks.remove(key) {
+            self.num_bytes -= cached_block.block.len();
+        }
+    }
+
+    fn discard_file(&mut self, path: &Path) {
+        let keys: Vec<(PathBuf, usize)> = self.blocks
+            .keys()
+            .filter(|&&(ref block_path, _)| block_path == path)
+            .cloned()
+            .collect();
+        for key in &keys {
+            self.discard(key);
+        }
+    }
+}
+
+/// Writer associated with the `ObjectStoreDirectory`.
+///
+/// Object stores do not support appends, so the
+/// file is buffered in memory and uploaded as a whole on `flush`.
+struct ObjectWriter {
+    path: PathBuf,
+    store: Arc<ObjectStore>,
+    file_lens: Arc<RwLock<HashMap<PathBuf, usize>>>,
+    data: Cursor<Vec<u8>>,
+}
+
+impl Seek for ObjectWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.data.seek(pos)
+    }
+}
+
+impl Write for ObjectWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.store.put(&self.path, self.data.get_ref())?;
+        let path = &self.path;
+        self.file_lens
+            .write()
+            .map_err(|_| make_io_err(format!("File lengths lock poisoned writing {:?}", path)))?
+            .remove(path);
+        Ok(())
+    }
+}
+
+/// Directory serving an index from an `ObjectStore`.
+///
+/// Files are read by block of `block_size` bytes, using ranged reads.
+/// Fetched blocks are kept in a local LRU cache, relying on the fact
+/// that tantivy never modifies a file after it has been written.
+/// The lengths of the files are cached for the same reason.
+///
+/// `open_read` does not download anything: the returned source
+/// only fetches the blocks of the ranges that are actually accessed.
+/// A file must therefore not be deleted while a searcher uses it,
+/// as accessing a range that was not fetched yet would then panic.
+///
+/// Files written via `atomic_write` are the exception to this rule,
+/// and are therefore never cached.
+///
+/// The cached blocks are accounted as `MemoryComponent::SourceCache`
+/// by the memory accountant of the directory. When its limiter refuses
+/// memory, the least recently used blocks are evicted.
+#[derive(Clone)]
+pub struct ObjectStoreDirectory {
+    store: Arc<ObjectStore>,
+    block_size: usize,
+    block_cache: Arc<RwLock<BlockCache>>,
+    file_lens: Arc<RwLock<HashMap<PathBuf, usize>>>,
+}
+
+impl fmt::Debug for ObjectStoreDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ObjectStoreDirectory({:?})", self.store)
+    }
+}
+
+impl ObjectStoreDirectory {
+    /// Creates a directory over the given object store.
+    pub fn new<S: ObjectStore>(store: S) -> ObjectStoreDirectory {
+        ObjectStoreDirectory::with_block_size(store, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Creates a directory over the given object store, fetching
+    /// data by blocks of `block_size` bytes.
+    ///
+    /// # Panics
+    /// If `block_size` is 0.
+    pub fn with_block_size<S: ObjectStore>(store: S, block_size: usize) -> ObjectStoreDirectory {
+        ObjectStoreDirectory::with_memory_accountant(
+            store,
+            block_size,
+            DEFAULT_CACHE_BUDGET_IN_BYTES,
+            MemoryAccountant::default(),
+        )
+    }
+
+    /// Creates a directory over the given object store, fetching
+    /// data by blocks of `block_size` bytes, caching up to
+    /// `budget_in_bytes` bytes of blocks, and accounting them
+    /// in `memory_accountant`.
+    ///
+    /// Passing the accountant of an index (see `Index::memory_accountant`)
+    /// makes the cache and the index share the same memory limit.
+    ///
+    /// # Panics
+    /// If `block_size` is 0.
+    pub fn with_memory_accountant<S: ObjectStore>(
+        store: S,
+        block_size: usize,
+        budget_in_bytes: usize,
+        memory_accountant: MemoryAccountant,
+    ) -> ObjectStoreDirectory {
+        assert!(block_size > 0, "The block size must be strictly positive.");
+        ObjectStoreDirectory {
+            store: Arc::new(store),
+            block_size,
+            block_cache: Arc::new(RwLock::new(BlockCache::new(
+                budget_in_bytes,
+                memory_accountant,
+            ))),
+            file_lens: Arc::default(),
+        }
+    }
+
+    /// Returns the number of bytes currently held in the cache of blocks.
+    pub fn cached_bytes(&self) -> usize {
+        self.block_cache
+            .read()
+            .expect("Block cache lock poisoned")
+            .num_bytes
+    }
+
+    /// Returns the length of a file, asking the object store
+    /// only the first time.
+    fn file_len(&self, path: &Path) -> io::Result<usize> {
+        let poisoned_err = || make_io_err(format!("File lengths lock poisoned reading {:?}", path));
+        if let Some(&file_len) = self.file_lens
+            .read()
+            .map_err(|_| poisoned_err())?
+            .get(path)
+        {
+            return Ok(file_len);
+        }
+        let file_len = self.store.len(path)? as usize;
+        self.file_lens
+            .write()
+            .map_err(|_| poisoned_err())?
+            .insert(path.to_owned(), file_len);
+        Ok(file_len)
+    }
+
+    fn read_block(
+        &self,
+        path: &Path,
+        block_id: usize,
+        file_len: usize,
+    ) -> io::Result<Arc<Vec<u8>>> {
+        let poisoned_err = || make_io_err(format!("Block cache lock poisoned reading {:?}", path));
+        if let Some(block) = self.block_cache
+            .write()
+            .map_err(|_| poisoned_err())?
+            .get(path, block_id)
+        {
+            return Ok(block);
+        }
+        let start = block_id * self.block_size;
+        let stop = cmp::min(start + self.block_size, file_len);
+        let block = Arc::new(self.store.read_range(path, start as u64, stop as u64)?);
+        self.block_cache
+            .write()
+            .map_err(|_| poisoned_err())?
+            .insert(path, block_id, Arc::clone(&block));
+        Ok(block)
+    }
+
+    /// Reads the bytes `[from, to)` of a file.
+    ///
+    /// Only the blocks overlapping the range are fetched from
+    /// the object store.
+    pub fn read_range(
+        &self,
+        path: &Path,
+        from: usize,
+        to: usize,
+    ) -> result::Result<ReadOnlySource, OpenReadError> {
+        let file_len = self.file_len(path)
+            .map_err(|e| to_open_read_error(path, e))?;
+        let data = self.read_blocks(path, file_len, from, cmp::min(to, file_len))
+            .map_err(|e| to_open_read_error(path, e))?;
+        Ok(ReadOnlySource::from(data))
+    }
+
+    /// Reads the bytes `[from, to)` of a file of length `file_len`.
+    fn read_blocks(
+        &self,
+        path: &Path,
+        file_len: usize,
+        from: usize,
+        to: usize,
+    ) -> io::Result<Vec<u8>> {
+        if from >= to {
+            return Ok(Vec::new());
+        }
+        let mut data = Vec::with_capacity(to - from);
+        let first_block = from / self.block_size;
+        let last_block = (to - 1) / self.block_size;
+        for block_id in first_block..last_block + 1 {
+            let block = self.read_block(path, block_id, file_len)?;
+            let block_start = block_id * self.block_size;
+            let start_in_block = from.saturating_sub(block_start);
+            let stop_in_block = cmp::min(to - block_start, block.len());
+            data.extend_from_slice(&block[start_in_block..stop_in_block]);
+        }
+        Ok(data)
+    }
+
+    fn discard_from_cache(&self, path: &Path) -> io::Result<()> {
+        self.file_lens
+            .write()
+            .map_err(|_| make_io_err(format!("File lengths lock poisoned discarding {:?}", path)))?
+            .remove(path);
+        self.block_cache
+            .write()
+            .map_err(|_| {
+                make_io_err(format!("Block cache lock poisoned discarding {:?}", path))
+            })?
+            .discard_file(path);
+        Ok(())
+    }
+}
+
+/// Reads the ranges of a file accessed by a `LazySource`.
+struct FileRangeReader {
+    directory: ObjectStoreDirectory,
+    path: PathBuf,
+    file_len: usize,
+}
+
+impl RangeReader for FileRangeReader {
+    fn read_range(&self, from: usize, to: usize) -> io::Result<Vec<u8>> {
+        self.directory
+            .read_blocks(&self.path, self.file_len, from, to)
+    }
+}
+
+impl Directory for ObjectStoreDirectory {
+    fn open_read(&self, path: &Path) -> result::Result<ReadOnlySource, OpenReadError> {
+        let file_len = self.file_len(path)
+            .map_err(|e| to_open_read_error(path, e))?;
+        let range_reader = FileRangeReader {
+            directory: self.clone(),
+            path: path.to_owned(),
+            file_len,
+        };
+        Ok(ReadOnlySource::Lazy(LazySource::new(
+            Arc::new(range_reader),
+            0,
+            file_len,
+        )))
+    }
+
+    fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
+        self.discard_from_cache(path)
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        self.store.delete(path).map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                DeleteError::FileDoesNotExist(path.to_owned())
+            } else {
+                IOError::with_path(path.to_owned(), e).into()
+            }
+        })
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.store.exists(path)
+    }
+
+    fn open_write(&mut self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
+        if self.store.exists(path) {
+            return Err(OpenWriteError::FileAlreadyExists(path.to_owned()));
+        }
+        // force the creation of the file to mimic the MMap directory.
+        self.store
+            .put(path, &[])
+            .map_err(|e| IOError::with_path(path.to_owned(), e))?;
+        let writer = ObjectWriter {
+            path: path.to_owned(),
+            store: Arc::clone(&self.store),
+            file_lens: Arc::clone(&self.file_lens),
+            data: Cursor::new(Vec::new()),
+        };
+        Ok(BufWriter::new(Box::new(writer)))
+    }
+
+    fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
+        let file_len = self.store
+            .len(path)
+            .map_err(|e| to_open_read_error(path, e))?;
+        self.store
+            .read_range(path, 0, file_len)
+            .map_err(|e| to_open_read_error(path, e))
+    }
+
+    fn atomic_write(&mut self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.store.put(path, data)
+    }
+
+    fn box_clone(&self) -> Box<Directory> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    // There are more tests in directory/mod.rs
+    // The following tests are specific to the ObjectStoreDirectory
+
+    use super::*;
+    use common::HasLen;
+    use core::MemoryCap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempdir::TempDir;
+
+    /// Counts the requests sent to the object store.
+    #[derive(Debug, Clone)]
+    struct CountingObjectStore {
+        store: FileObjectStore,
+        num_len_requests: Arc<AtomicUsize>,
+        num_read_requests: Arc<AtomicUsize>,
+    }
+
+    impl ObjectStore for CountingObjectStore {
+        fn len(&self, path: &Path) -> io::Result<u64> {
+            self.num_len_requests.fetch_add(1, Ordering::SeqCst);
+            self.store.len(path)
+        }
+
+        fn read_range(&self, path: &Path, from: u64, to: u64) -> io::Result<Vec<u8>> {
+            self.num_read_requests.fetch_add(1, Ordering::SeqCst);
+            self.store.read_range(path, from, to)
+        }
+
+        fn put(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+            self.store.put(path, data)
+        }
+
+        fn delete(&self, path: &Path) -> io::Result<()> {
+            self.store.delete(path)
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.store.exists(path)
+        }
+    }
+
+    #[test]
+    fn test_read_range() {
+        let tempdir = TempDir::new("objectstore").unwrap();
+        let mut directory =
+            ObjectStoreDirectory::with_block_size(FileObjectStore::new(tempdir.path()), 3);
+        let path = Path::new("test");
+        {
+            let mut w = directory.open_write(path).unwrap();
+            w.write_all(&[0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+            w.flush().unwrap();
+        }
+        assert_eq!(directory.read_range(path, 2, 7).unwrap().as_slice(), &[2u8, 3, 4, 5, 6]);
+        assert_eq!(directory.read_range(path, 8, 20).unwrap().as_slice(), &[8u8, 9]);
+        assert!(directory.read_range(path, 12, 20).unwrap().as_slice().is_empty());
+        assert_eq!(directory.open_read(path).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn test_lazy_open_read() {
+        let tempdir = TempDir::new("objectstore").unwrap();
+        let store = CountingObjectStore {
+            store: FileObjectStore::new(tempdir.path()),
+            num_len_requests: Arc::default(),
+            num_read_requests: Arc::default(),
+        };
+        let num_len_requests = Arc::clone(&store.num_len_requests);
+        let num_read_requests = Arc::clone(&store.num_read_requests);
+        let mut directory = ObjectStoreDirectory::with_block_size(store, 3);
+        let path = Path::new("test");
+        {
+            let mut w = directory.open_write(path).unwrap();
+            w.write_all(&[0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+            w.flush().unwrap();
+        }
+        let source = directory.open_read(path).unwrap();
+        assert_eq!(source.len(), 10);
+        assert_eq!(num_read_requests.load(Ordering::SeqCst), 0);
+        // only the block containing the slice is fetched.
+        let slice = source.slice(4, 6);
+        assert_eq!(slice.len(), 2);
+        assert_eq!(num_read_requests.load(Ordering::SeqCst), 0);
+        assert_eq!(slice.as_slice(), &[4u8, 5]);
+        assert_eq!(num_read_requests.load(Ordering::SeqCst), 1);
+        // the block is then served from the cache.
+        assert_eq!(source.slice(3, 5).as_slice(), &[3u8, 4]);
+        assert_eq!(num_read_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(directory.cached_bytes(), 3);
+        // the length of the file is only requested once.
+        directory.open_read(path).unwrap();
+        directory.read_range(path, 0, 2).unwrap();
+        assert_eq!(num_len_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(source.as_slice(), &[0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        directory.delete(path).unwrap();
+        assert_eq!(directory.cached_bytes(), 0);
+        assert!(directory.open_read(path).is_err());
+    }
+
+    #[test]
+    fn test_block_cache_budget() {
+        let tempdir = TempDir::new("objectstore").unwrap();
+        let memory_accountant = MemoryAccountant::default();
+        let mut directory = ObjectStoreDirectory::with_memory_accountant(
+            FileObjectStore::new(tempdir.path()),
+            3,
+            6,
+            memory_accountant.clone(),
+        );
+        let path = Path::new("test");
+        {
+            let mut w = directory.open_write(path).unwrap();
+            w.write_all(&[0u8, 1, 2, 3, 4, 5, 6, 7, 8, 9]).unwrap();
+            w.flush().unwrap();
+        }
+        let cached_bytes = |memory_accountant: &MemoryAccountant| {
+            memory_accountant
+                .usage()
+                .component(MemoryComponent::SourceCache)
+        };
+        // only the two most recently used blocks are kept.
+        assert_eq!(directory.read_range(path, 0, 10).unwrap().len(), 10);
+        assert_eq!(directory.cached_bytes(), 4);
+        assert_eq!(cached_bytes(&memory_accountant), 4);
+        // blocks are evicted to stay under the cap.
+        memory_accountant.set_limiter(box MemoryCap::new(3));
+        directory.read_range(path, 0, 3).unwrap();
+        assert_eq!(directory.cached_bytes(), 3);
+        assert_eq!(cached_bytes(&memory_accountant), 3);
+    }
+}
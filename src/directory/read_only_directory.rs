@@ -0,0 +1,104 @@
+use directory::error::{DeleteError, IOError, OpenReadError, OpenWriteError};
+use directory::{Directory, ReadOnlySource, WritePtr};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::result;
+
+fn read_only_error(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("Cannot write {:?}: the directory is read-only", path),
+    )
+}
+
+/// Directory wrapper rejecting all of the write operations.
+///
+/// It is used to open an index in read-only mode, for instance
+/// on search replicas living on a read-only filesystem.
+/// Such an index never creates any file, not even the lockfile.
+pub struct ReadOnlyDirectory {
+    directory: Box<Directory>,
+}
+
+impl ReadOnlyDirectory {
+    /// Wraps a directory, making it read-only.
+    pub fn new<Dir: Directory>(directory: Dir) -> ReadOnlyDirectory {
+        ReadOnlyDirectory {
+            directory: box directory,
+        }
+    }
+}
+
+impl fmt::Debug for ReadOnlyDirectory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ReadOnlyDirectory({:?})", self.directory)
+    }
+}
+
+impl Clone for ReadOnlyDirectory {
+    fn clone(&self) -> ReadOnlyDirectory {
+        ReadOnlyDirectory {
+            directory: self.directory.box_clone(),
+        }
+    }
+}
+
+impl Directory for ReadOnlyDirectory {
+    fn open_read(&self, path: &Path) -> result::Result<ReadOnlySource, OpenReadError> {
+        self.directory.open_read(path)
+    }
+
+    fn delete(&self, path: &Path) -> result::Result<(), DeleteError> {
+        Err(IOError::with_path(path.to_owned(), read_only_error(path)).into())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.directory.exists(path)
+    }
+
+    fn open_write(&mut self, path: &Path) -> result::Result<WritePtr, OpenWriteError> {
+        Err(IOError::with_path(path.to_owned(), read_only_error(path)).into())
+    }
+
+    fn atomic_read(&self, path: &Path) -> result::Result<Vec<u8>, OpenReadError> {
+        self.directory.atomic_read(path)
+    }
+
+    fn atomic_write(&mut self, path: &Path, _data: &[u8]) -> io::Result<()> {
+        Err(read_only_error(path))
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        self.directory.local_path(path)
+    }
+
+    fn box_clone(&self) -> Box<Directory> {
+        box self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use directory::RAMDirectory;
+    use std::io::Write;
+
+    #[test]
+    fn test_read_only_directory() {
+        let mut ram_directory = RAMDirectory::create();
+        let path = Path::new("test");
+        {
+            let mut w = ram_directory.open_write(path).unwrap();
+            w.write_all(b"hello").unwrap();
+            w.flush().unwrap();
+        }
+        let mut directory = ReadOnlyDirectory::new(ram_directory);
+        assert_eq!(directory.open_read(path).unwrap().as_slice(), b"hello");
+        assert!(directory.open_write(Path::new("other")).is_err());
+        assert!(directory.atomic_write(Path::new("meta.json"), b"").is_err());
+        assert!(directory.delete(path).is_err());
+        assert!(directory.exists(path));
+    }
+}
@@ -4,6 +4,7 @@ use super::shared_vec_slice::SharedVecSlice;
 use common::HasLen;
 use std::slice;
 use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
 use stable_deref_trait::{CloneStableDeref, StableDeref};
 
 /// Read object that represents files in tantivy.
@@ -17,6 +18,89 @@ pub enum ReadOnlySource {
     Mmap(MmapReadOnly),
     /// Wrapping a `Vec<u8>`
     Anonymous(SharedVecSlice),
+    /// Range of a file fetched on first access
+    Lazy(LazySource),
+}
+
+/// Reads byte ranges of a file on demand.
+pub(crate) trait RangeReader: Send + Sync {
+    /// Returns the bytes `[from, to)` of the file.
+    fn read_range(&self, from: usize, to: usize) -> io::Result<Vec<u8>>;
+}
+
+/// Range of a file that is only read the first time
+/// its data is accessed.
+///
+/// Slicing a `LazySource` that has not been read yet does not
+/// read anything either, so that only the parts of a remote file
+/// which are actually used get downloaded.
+///
+/// # Panics
+/// Since `ReadOnlySource::as_slice` cannot fail, accessing the data
+/// panics if the underlying read fails.
+#[derive(Clone)]
+pub struct LazySource {
+    reader: Arc<RangeReader>,
+    from: usize,
+    to: usize,
+    // shared by the clones, so that they all point to the same data.
+    data: Arc<Mutex<Option<Arc<Vec<u8>>>>>,
+}
+
+impl LazySource {
+    pub(crate) fn new(reader: Arc<RangeReader>, from: usize, to: usize) -> LazySource {
+        LazySource {
+            reader,
+            from,
+            to,
+            data: Arc::default(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.to - self.from
+    }
+
+    /// Returns the data, reading it if necessary.
+    pub(crate) fn fetch(&self) -> SharedVecSlice {
+        let mut data = self.data.lock().expect("Lazy source lock poisoned");
+        if data.is_none() {
+            let bytes = self.reader
+                .read_range(self.from, self.to)
+                .unwrap_or_else(|e| {
+                    panic!("Failed to read the bytes [{}, {}): {:?}", self.from, self.to, e)
+                });
+            *data = Some(Arc::new(bytes));
+        }
+        let bytes = data.as_ref().expect("The data was just read");
+        SharedVecSlice::new(Arc::clone(bytes))
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        let shared_vec = self.fetch();
+        let bytes = shared_vec.as_slice();
+        // the data is never replaced once read, and `self`
+        // holds a reference to it.
+        unsafe { slice::from_raw_parts(bytes.as_ptr(), bytes.len()) }
+    }
+
+    fn slice(&self, from_offset: usize, to_offset: usize) -> ReadOnlySource {
+        let fetched_opt = self.data
+            .lock()
+            .expect("Lazy source lock poisoned")
+            .clone();
+        if let Some(data) = fetched_opt {
+            // no need to read the data again.
+            return ReadOnlySource::Anonymous(
+                SharedVecSlice::new(data).slice(from_offset, to_offset),
+            );
+        }
+        ReadOnlySource::Lazy(LazySource::new(
+            Arc::clone(&self.reader),
+            self.from + from_offset,
+            self.from + to_offset,
+        ))
+    }
 }
 
 unsafe impl StableDeref for ReadOnlySource {}
@@ -41,6 +125,7 @@ impl ReadOnlySource {
         match *self {
             ReadOnlySource::Mmap(ref mmap_read_only) => unsafe { mmap_read_only.as_slice() },
             ReadOnlySource::Anonymous(ref shared_vec) => shared_vec.as_slice(),
+            ReadOnlySource::Lazy(ref lazy_source) => lazy_source.as_slice(),
         }
     }
 
@@ -71,6 +156,7 @@ impl ReadOnlySource {
             ReadOnlySource::Anonymous(ref shared_vec) => {
                 ReadOnlySource::Anonymous(shared_vec.slice(from_offset, to_offset))
             }
+            ReadOnlySource::Lazy(ref lazy_source) => lazy_source.slice(from_offset, to_offset),
         }
     }
 
@@ -94,13 +180,20 @@ impl ReadOnlySource {
 
 impl HasLen for ReadOnlySource {
     fn len(&self) -> usize {
-        self.as_slice().len()
+        match *self {
+            // the length is known without reading the data.
+            ReadOnlySource::Lazy(ref lazy_source) => lazy_source.len(),
+            _ => self.as_slice().len(),
+        }
     }
 }
 
 impl Clone for ReadOnlySource {
     fn clone(&self) -> Self {
-        self.slice(0, self.len())
+        match *self {
+            ReadOnlySource::Lazy(ref lazy_source) => ReadOnlySource::Lazy(lazy_source.clone()),
+            _ => self.slice(0, self.len()),
+        }
     }
 }
 
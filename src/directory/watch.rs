@@ -0,0 +1,111 @@
+use crc::crc32;
+use directory::Directory;
+use directory::error::OpenReadError;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Default interval between two checks of a watched file, in milliseconds.
+pub const DEFAULT_WATCH_INTERVAL_MS: u64 = 500;
+
+/// Callback called when a watched file changes.
+pub type WatchCallback = Box<Fn() + Send + Sync + 'static>;
+
+/// Handle keeping a watch alive.
+///
+/// The watch stops when the handle is dropped.
+pub struct WatchHandle {
+    stopped: Arc<AtomicBool>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Returns a checksum of the content of a file written with `atomic_write`,
+/// or `None` if the file does not exist or cannot be read.
+fn file_checksum(directory: &Directory, path: &Path) -> Option<u32> {
+    match directory.atomic_read(path) {
+        Ok(data) => Some(crc32::checksum_ieee(&data)),
+        Err(OpenReadError::FileDoesNotExist(_)) => None,
+        Err(e) => {
+            warn!("Failed to read watched file {:?}: {:?}", path, e);
+            None
+        }
+    }
+}
+
+/// Watches a file written with `atomic_write` by polling its content
+/// every `interval`, and calls `callback` every time it changes.
+///
+/// The file is read through the directory, so that this works for
+/// any `Directory` implementation, including when the file is
+/// replaced by another process.
+pub fn poll_file(
+    directory: Box<Directory>,
+    path: PathBuf,
+    interval: Duration,
+    callback: WatchCallback,
+) -> WatchHandle {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stopped_clone = Arc::clone(&stopped);
+    let mut checksum = file_checksum(&*directory, &path);
+    thread::Builder::new()
+        .name(format!("watch thread for {:?}", path))
+        .spawn(move || loop {
+            thread::sleep(interval);
+            if stopped_clone.load(Ordering::SeqCst) {
+                break;
+            }
+            let new_checksum = file_checksum(&*directory, &path);
+            if new_checksum.is_some() && new_checksum != checksum {
+                checksum = new_checksum;
+                callback();
+            }
+        })
+        .expect("Failed to spawn the watch thread");
+    WatchHandle { stopped }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use directory::RAMDirectory;
+    use std::sync::Mutex;
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_poll_file() {
+        let mut directory = RAMDirectory::create();
+        let path = Path::new("meta.json");
+        directory.atomic_write(path, b"v1").unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let handle = poll_file(
+            directory.box_clone(),
+            path.to_owned(),
+            Duration::from_millis(10),
+            Box::new(move || {
+                sender.lock().unwrap().send(()).unwrap();
+            }),
+        );
+        directory.atomic_write(path, b"v2").unwrap();
+        assert!(receiver.recv_timeout(TIMEOUT).is_ok());
+        directory.atomic_write(path, b"v3").unwrap();
+        assert!(receiver.recv_timeout(TIMEOUT).is_ok());
+        // once the handle is dropped, the watch thread stops and drops
+        // the callback. No other call was made in the meantime.
+        drop(handle);
+        assert_eq!(
+            receiver.recv_timeout(TIMEOUT),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+}
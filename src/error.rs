@@ -2,9 +2,10 @@
 
 use std::io;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::PoisonError;
 use directory::error::{IOError, OpenDirectoryError, OpenReadError, OpenWriteError};
+use common::UnsupportedVersion;
 use query;
 use schema;
 use fastfield::FastFieldNotAvailableError;
@@ -29,10 +30,28 @@ error_chain!(
         }
         /// The data within is corrupted.
         ///
-        /// For instance, it contains invalid JSON.
-        CorruptedFile(buf: PathBuf) {
+        /// For instance, it contains invalid JSON, is truncated,
+        /// or its checksum does not match its content.
+        CorruptedFile(path: PathBuf, detail: String) {
             description("file contains corrupted data")
-            display("file contains corrupted data: '{:?}'", buf)
+            display("file contains corrupted data: '{:?}': {}", path, detail)
+        }
+        /// The file was written with a file format version that is not
+        /// supported by this version of tantivy.
+        IncompatibleVersion(path: Option<PathBuf>, version: u32, supported: u32) {
+            description("file format version is not supported")
+            display("file '{:?}' has format version {}, \
+                     but this version of tantivy only supports version {}",
+                    path, version, supported)
+        }
+        /// The index is already locked by another `IndexWriter`,
+        /// in this process or in another one.
+        ///
+        /// If no other writer is alive, the lock file was left behind
+        /// by a crashed process and can be deleted manually.
+        LockBusy(path: PathBuf) {
+            description("the index is locked by another writer")
+            display("the index is locked by another writer: '{:?}'", path)
         }
         /// A thread holding the locked panicked and poisoned the lock.
         Poisoned {
@@ -53,11 +72,30 @@ error_chain!(
             description("a schema field is missing")
             display("a schema field is missing: '{}'", field)
         }
+        /// The disk is full, or the size budget of the directory was exceeded.
+        ///
+        /// The index is left in its last committed state.
+        DiskFull(path: Option<PathBuf>) {
+            description("the disk is full")
+            display("the disk is full or the size budget was exceeded writing: '{:?}'", path)
+        }
+        /// A query exceeded one of its complexity limits,
+        /// e.g. the number of terms a regular expression expands to.
+        QueryTooComplex(reason: String) {
+            description("the query is too complex")
+            display("the query is too complex: '{}'", reason)
+        }
         /// Tried to access a fastfield reader for a field not configured accordingly.
         FastFieldError(err: FastFieldNotAvailableError) {
             description("fast field not available")
             display("fast field not available: '{:?}'", err)
         }
+        /// The operation with the given opstamp was discarded before being
+        /// committed, by a rollback or by dropping the `IndexWriter`.
+        OperationDiscarded(opstamp: u64) {
+            description("the operation was discarded before being committed")
+            display("the operation was discarded before being committed: '{}'", opstamp)
+        }
     }
 );
 
@@ -67,15 +105,53 @@ impl From<FastFieldNotAvailableError> for Error {
     }
 }
 
+impl Error {
+    /// Returns the path of the file that caused the error, if known.
+    pub fn path(&self) -> Option<&Path> {
+        match *self.kind() {
+            ErrorKind::PathDoesNotExist(ref path)
+            | ErrorKind::FileAlreadyExists(ref path)
+            | ErrorKind::CorruptedFile(ref path, _)
+            | ErrorKind::LockBusy(ref path) => Some(path),
+            ErrorKind::IncompatibleVersion(ref path, _, _) | ErrorKind::DiskFull(ref path) => {
+                path.as_ref().map(|path| path.as_path())
+            }
+            ErrorKind::IOError(ref io_error) => io_error.path().map(|path| path.as_path()),
+            _ => None,
+        }
+    }
+}
+
 impl From<IOError> for Error {
     fn from(io_error: IOError) -> Error {
+        if io_error.is_disk_full() {
+            return ErrorKind::DiskFull(io_error.path().cloned()).into();
+        }
+        let unsupported_version = io_error
+            .io_error()
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<UnsupportedVersion>())
+            .cloned();
+        if let Some(unsupported_version) = unsupported_version {
+            return ErrorKind::IncompatibleVersion(
+                io_error.path().cloned(),
+                unsupported_version.version,
+                unsupported_version.supported,
+            ).into();
+        }
+        if io_error.io_error().kind() == io::ErrorKind::InvalidData {
+            if let Some(path) = io_error.path().cloned() {
+                let detail = io_error.io_error().to_string();
+                return ErrorKind::CorruptedFile(path, detail).into();
+            }
+        }
         ErrorKind::IOError(io_error).into()
     }
 }
 
 impl From<io::Error> for Error {
     fn from(io_error: io::Error) -> Error {
-        ErrorKind::IOError(io_error.into()).into()
+        Error::from(IOError::from(io_error))
     }
 }
 
@@ -97,7 +173,7 @@ impl From<OpenReadError> for Error {
             OpenReadError::FileDoesNotExist(filepath) => {
                 ErrorKind::PathDoesNotExist(filepath).into()
             }
-            OpenReadError::IOError(io_error) => ErrorKind::IOError(io_error).into(),
+            OpenReadError::IOError(io_error) => io_error.into(),
         }
     }
 }
@@ -111,9 +187,11 @@ impl From<schema::DocParsingError> for Error {
 impl From<OpenWriteError> for Error {
     fn from(error: OpenWriteError) -> Error {
         match error {
-            OpenWriteError::FileAlreadyExists(filepath) => ErrorKind::FileAlreadyExists(filepath),
-            OpenWriteError::IOError(io_error) => ErrorKind::IOError(io_error),
-        }.into()
+            OpenWriteError::FileAlreadyExists(filepath) => {
+                ErrorKind::FileAlreadyExists(filepath).into()
+            }
+            OpenWriteError::IOError(io_error) => io_error.into(),
+        }
     }
 }
 
@@ -78,12 +78,60 @@ impl DeleteBitSet {
     }
 }
 
+impl DeleteBitSet {
+    /// Returns the number of bytes used by the bitset.
+    pub fn num_bytes(&self) -> usize {
+        self.data.len()
+    }
+}
+
 impl HasLen for DeleteBitSet {
     fn len(&self) -> usize {
         self.len
     }
 }
 
+/// Set of the alive (that is, non-deleted) `DocId`s of a segment.
+///
+/// It is a cheap view over the `DeleteBitSet` of the segment.
+#[derive(Clone)]
+pub struct AliveBitSet {
+    delete_bitset: DeleteBitSet,
+    max_doc: DocId,
+}
+
+impl AliveBitSet {
+    /// Creates the set of alive documents of a segment
+    /// given its deleted documents and its `max_doc`.
+    pub fn new(delete_bitset: DeleteBitSet, max_doc: DocId) -> AliveBitSet {
+        AliveBitSet {
+            delete_bitset,
+            max_doc,
+        }
+    }
+
+    /// Returns true iff the document is alive.
+    #[inline]
+    pub fn is_alive(&self, doc: DocId) -> bool {
+        doc < self.max_doc && !self.delete_bitset.is_deleted(doc)
+    }
+
+    /// Returns true iff some of the documents of the segment are deleted.
+    pub fn has_deletes(&self) -> bool {
+        self.delete_bitset.has_deletes()
+    }
+
+    /// Returns the number of alive documents.
+    pub fn num_alive_docs(&self) -> u32 {
+        self.max_doc - self.delete_bitset.len() as u32
+    }
+
+    /// Iterates over the alive documents, in increasing order.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = DocId> + 'a {
+        (0..self.max_doc).filter(move |&doc| !self.delete_bitset.is_deleted(doc))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -106,6 +154,15 @@ mod tests {
                 assert_eq!(bitset.contains(doc), delete_bitset.is_deleted(doc as DocId));
             }
             assert_eq!(delete_bitset.len(), bitset.len());
+            let alive_bitset = AliveBitSet::new(delete_bitset, n as DocId);
+            let alive_docs: Vec<DocId> = alive_bitset.iter().collect();
+            assert_eq!(alive_docs.len(), n - bitset.len());
+            assert_eq!(alive_bitset.num_alive_docs() as usize, n - bitset.len());
+            for doc in alive_docs {
+                assert!(alive_bitset.is_alive(doc));
+                assert!(!bitset.contains(doc as usize));
+            }
+            assert!(!alive_bitset.is_alive(n as DocId));
         }
     }
 
@@ -13,8 +13,8 @@ the `DocSet` : for instance for scoring, grouping, filtering, or faceting.
 
 
 Fields have to be declared as `FAST` in the  schema.
-Currently only 64-bits integers (signed or unsigned) are
-supported.
+Currently only 64-bits integers (signed or unsigned),
+64-bits floats and dates are supported.
 
 They are stored in a bit-packed fashion so that their
 memory usage is directly linear with the amplitude of the
@@ -26,14 +26,14 @@ Read access performance is comparable to that of an array lookup.
 use common;
 use schema::Cardinality;
 use schema::FieldType;
-use schema::Value;
-pub use self::delete::DeleteBitSet;
+use schema::{DateTime, Value};
+pub use self::delete::{AliveBitSet, DeleteBitSet};
 pub use self::delete::write_delete_bitset;
 pub use self::error::{FastFieldNotAvailableError, Result};
 pub use self::facet_reader::FacetReader;
 pub use self::multivalued::MultiValueIntFastFieldReader;
 pub use self::reader::FastFieldReader;
-pub use self::serializer::FastFieldSerializer;
+pub use self::serializer::{FastFieldSerializer, FastSingleFieldSerializer};
 pub use self::writer::{FastFieldsWriter, IntFastFieldWriter};
 
 mod reader;
@@ -44,7 +44,7 @@ mod delete;
 mod facet_reader;
 mod multivalued;
 
-/// Trait for types that are allowed for fast fields: (u64 or i64).
+/// Trait for types that are allowed for fast fields: (u64, i64, f64 or `DateTime`).
 pub trait FastValue: Default + Clone + Copy {
     /// Converts a value from u64
     ///
@@ -115,11 +115,57 @@ impl FastValue for i64 {
     }
 }
 
+impl FastValue for f64 {
+    fn from_u64(val: u64) -> Self {
+        common::u64_to_f64(val)
+    }
+
+    fn to_u64(&self) -> u64 {
+        common::f64_to_u64(*self)
+    }
+
+    fn fast_field_cardinality(field_type: &FieldType) -> Option<Cardinality> {
+        match *field_type {
+            FieldType::F64(ref integer_options) =>
+                integer_options.get_fastfield_cardinality(),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> u64 {
+        self.to_bits()
+    }
+}
+
+impl FastValue for DateTime {
+    fn from_u64(val: u64) -> Self {
+        DateTime::from_timestamp_micros(i64::from_u64(val))
+    }
+
+    fn to_u64(&self) -> u64 {
+        self.timestamp_micros().to_u64()
+    }
+
+    fn fast_field_cardinality(field_type: &FieldType) -> Option<Cardinality> {
+        match *field_type {
+            FieldType::Date(ref integer_options) =>
+                integer_options.get_fastfield_cardinality(),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> u64 {
+        self.timestamp_micros().as_u64()
+    }
+}
+
 fn value_to_u64(value: &Value) -> u64 {
     match *value {
         Value::U64(ref val) => *val,
         Value::I64(ref val) => common::i64_to_u64(*val),
-        _ => panic!("Expected a u64/i64 field, got {:?} ", value),
+        Value::Date(ref date) => common::i64_to_u64(date.timestamp_micros()),
+        Value::F64(val) => common::f64_to_u64(val),
+        _ => panic!("Expected a u64/i64/f64/date field, got {:?} ", value),
     }
 }
 
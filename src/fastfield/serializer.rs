@@ -105,6 +105,16 @@ impl<'a, W: Write> FastSingleFieldSerializer<'a, W> {
         Ok(())
     }
 
+    /// Pushes a block of values to the currently open u64 fast field.
+    ///
+    /// The values are bitpacked one by one, as with `add_val`.
+    pub fn add_vals(&mut self, vals: &[u64]) -> io::Result<()> {
+        for &val in vals {
+            self.add_val(val)?;
+        }
+        Ok(())
+    }
+
     pub fn close_field(mut self) -> io::Result<()> {
         self.bit_packer.close(&mut self.write)
     }
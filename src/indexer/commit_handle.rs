@@ -0,0 +1,144 @@
+use std::mem;
+use std::sync::{Arc, Mutex};
+use futures::{Async, Future, Poll};
+use futures::task::{self, Task};
+use error::{Error, ErrorKind};
+
+struct CommitState {
+    committed_opstamp: u64,
+    closed: bool,
+    waiting_tasks: Vec<Task>,
+}
+
+impl CommitState {
+    fn notify_all(&mut self) {
+        for task in mem::replace(&mut self.waiting_tasks, vec![]) {
+            task.notify();
+        }
+    }
+}
+
+/// Tracks the commits of an `IndexWriter`,
+/// and resolves the `CommitHandle`s it gave away.
+///
+/// Dropping the `CommitWatch`, which happens when the `IndexWriter`
+/// is dropped or rolled back, fails all of the pending handles.
+pub(crate) struct CommitWatch(Arc<Mutex<CommitState>>);
+
+impl CommitWatch {
+    pub fn new(committed_opstamp: u64) -> CommitWatch {
+        CommitWatch(Arc::new(Mutex::new(CommitState {
+            committed_opstamp,
+            closed: false,
+            waiting_tasks: vec![],
+        })))
+    }
+
+    /// Records that all of the operations before `opstamp` are
+    /// durably committed.
+    pub fn notify_commit(&self, opstamp: u64) {
+        let mut state = self.0.lock().expect("Commit state lock poisoned");
+        state.committed_opstamp = opstamp;
+        state.notify_all();
+    }
+
+    pub fn handle(&self, opstamp: u64) -> CommitHandle {
+        CommitHandle {
+            opstamp,
+            state: self.0.clone(),
+        }
+    }
+}
+
+impl Drop for CommitWatch {
+    fn drop(&mut self) {
+        let mut state = self.0.lock().expect("Commit state lock poisoned");
+        state.closed = true;
+        state.notify_all();
+    }
+}
+
+/// Handle on an operation of the `IndexWriter`, resolved
+/// once the operation has been durably committed.
+///
+/// `CommitHandle` is a future. It resolves into the opstamp
+/// of the commit that included the operation, after this commit
+/// has been persisted, so that ingestion services can
+/// acknowledge a write only once it will survive a crash.
+/// Calling `.wait()` blocks the current thread until then.
+///
+/// If the operation is cancelled by a `rollback`, or if the
+/// `IndexWriter` is dropped before committing it, the future
+/// fails with an `OperationDiscarded` error.
+///
+/// The handle does not trigger any commit by itself :
+/// some other thread, or the caller at a later point,
+/// still needs to call `IndexWriter::commit()`.
+pub struct CommitHandle {
+    opstamp: u64,
+    state: Arc<Mutex<CommitState>>,
+}
+
+impl CommitHandle {
+    /// Returns the opstamp of the operation.
+    pub fn opstamp(&self) -> u64 {
+        self.opstamp
+    }
+
+    /// Returns true iff the operation has been committed.
+    pub fn is_committed(&self) -> bool {
+        let state = self.state.lock().expect("Commit state lock poisoned");
+        self.opstamp < state.committed_opstamp
+    }
+}
+
+impl Future for CommitHandle {
+    type Item = u64;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<u64, Error> {
+        let mut state = self.state.lock().expect("Commit state lock poisoned");
+        if self.opstamp < state.committed_opstamp {
+            return Ok(Async::Ready(state.committed_opstamp));
+        }
+        if state.closed {
+            bail!(ErrorKind::OperationDiscarded(self.opstamp));
+        }
+        state.waiting_tasks.push(task::current());
+        Ok(Async::NotReady)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::thread;
+    use futures::Future;
+    use error::{Error, ErrorKind};
+    use super::CommitWatch;
+
+    #[test]
+    fn test_commit_handle_resolved_on_commit() {
+        let watch = CommitWatch::new(2);
+        assert!(!watch.handle(2).is_committed());
+        assert!(watch.handle(1).is_committed());
+        let handle = watch.handle(3);
+        let committer = thread::spawn(move || {
+            watch.notify_commit(5);
+            watch
+        });
+        assert_eq!(handle.wait().unwrap(), 5);
+        let _watch = committer.join().unwrap();
+    }
+
+    #[test]
+    fn test_commit_handle_discarded() {
+        let watch = CommitWatch::new(0);
+        let handle = watch.handle(0);
+        thread::spawn(move || drop(watch));
+        match handle.wait() {
+            Err(Error(ErrorKind::OperationDiscarded(0), _)) => {}
+            _ => panic!("Expected OperationDiscarded error"),
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use Result;
+use common::{fnv1a_hash, BinarySerializable};
+use core::{DedupMode, DedupSettings, Index, SegmentReader};
+use error::ErrorKind;
+use schema::{Document, Field, FieldType, Schema, Term};
+use std::collections::HashSet;
+
+/// What the `IndexWriter` should do with a document.
+pub(crate) enum DedupAction {
+    /// Adds the document.
+    Add,
+    /// Drops the document, as it is a duplicate.
+    Skip,
+    /// Deletes the documents with the given hash term,
+    /// and then adds the document.
+    Replace(Term),
+}
+
+/// Computes the content hash of the documents added by the
+/// `IndexWriter`, and detects the duplicates, as defined by the
+/// `DedupSettings` of the index.
+pub(crate) struct Deduplicator {
+    hash_field: Field,
+    fields: Vec<Field>,
+    mode: DedupMode,
+    // readers of the committed segments, only opened in `Skip` mode.
+    committed_segment_readers: Vec<SegmentReader>,
+    // hashes of the documents added since the committed segments were opened.
+    uncommitted_hashes: HashSet<u64>,
+}
+
+impl Deduplicator {
+    /// Resolves the fields of the dedup settings against the schema.
+    ///
+    /// Returns an error if a field does not exist, or if the hash
+    /// field is not an indexed `u64` field.
+    pub fn new(schema: &Schema, dedup_settings: &DedupSettings) -> Result<Deduplicator> {
+        let get_field = |field_name: &str| -> Result<Field> {
+            schema
+                .get_field(field_name)
+                .ok_or_else(|| ErrorKind::SchemaError(field_name.to_string()).into())
+        };
+        let hash_field = get_field(&dedup_settings.hash_field)?;
+        match *schema.get_field_entry(hash_field).field_type() {
+            FieldType::U64(ref int_options) if int_options.is_indexed() => {}
+            _ => bail!(ErrorKind::InvalidArgument(format!(
+                "The dedup hash field {:?} must be an indexed u64 field",
+                dedup_settings.hash_field
+            ))),
+        }
+        let fields = dedup_settings
+            .fields
+            .iter()
+            .map(|field_name| get_field(field_name))
+            .collect::<Result<Vec<Field>>>()?;
+        if fields.is_empty() || fields.contains(&hash_field) {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The dedup fields {:?} must be non empty, and exclude the hash field",
+                dedup_settings.fields
+            )));
+        }
+        Ok(Deduplicator {
+            hash_field,
+            fields,
+            mode: dedup_settings.mode,
+            committed_segment_readers: Vec::new(),
+            uncommitted_hashes: HashSet::new(),
+        })
+    }
+
+    /// Opens the deduplicator of the index, if its settings define one.
+    pub fn open(index: &Index) -> Result<Option<Deduplicator>> {
+        if let Some(ref dedup_settings) = index.settings().dedup {
+            let mut deduplicator = Deduplicator::new(&index.schema(), dedup_settings)?;
+            deduplicator.open_committed_segments(index)?;
+            Ok(Some(deduplicator))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Opens the readers of the committed segments, in `Skip` mode.
+    fn open_committed_segments(&mut self, index: &Index) -> Result<()> {
+        if self.mode == DedupMode::Skip {
+            self.committed_segment_readers = index
+                .searchable_segments()?
+                .iter()
+                .map(SegmentReader::open)
+                .collect::<Result<Vec<SegmentReader>>>()?;
+        }
+        Ok(())
+    }
+
+    /// Refreshes the committed segments, after a commit.
+    ///
+    /// The commit is durable at this point, so a failure to reopen
+    /// the segments is only logged. The previous segments are then kept,
+    /// together with the hashes of the documents added since they were
+    /// opened, so that the duplicates are still detected.
+    pub fn on_commit(&mut self, index: &Index) {
+        if let Err(e) = self.open_committed_segments(index) {
+            error!(
+                "Failed to reopen the committed segments for deduplication: {:?}",
+                e
+            );
+            return;
+        }
+        self.uncommitted_hashes.clear();
+    }
+
+    /// Returns the hash of the values of the dedup fields of the document.
+    pub fn content_hash(&self, doc: &Document) -> u64 {
+        let mut buffer = Vec::new();
+        for &field in &self.fields {
+            for value in doc.get_all(field) {
+                // writing to a `Vec` cannot fail.
+                field.serialize(&mut buffer).expect("Failed to serialize field");
+                value.serialize(&mut buffer).expect("Failed to serialize value");
+            }
+        }
+        fnv1a_hash(&buffer)
+    }
+
+    /// Returns true iff the duplicates are skipped.
+    pub fn is_skipping(&self) -> bool {
+        self.mode == DedupMode::Skip
+    }
+
+    fn hash_term(&self, hash: u64) -> Term {
+        Term::from_field_u64(self.hash_field, hash)
+    }
+
+    /// Returns true iff a document with the same hash has been committed,
+    /// or added since the last commit.
+    ///
+    /// The documents added and then deleted since the last commit
+    /// are still considered.
+    pub fn is_duplicate(&self, hash: u64) -> bool {
+        if self.uncommitted_hashes.contains(&hash) {
+            return true;
+        }
+        let hash_term = self.hash_term(hash);
+        self.committed_segment_readers
+            .iter()
+            .any(|segment_reader| segment_reader.doc_id_for_key(&hash_term).is_some())
+    }
+
+    /// Sets the hash field of the document, and returns
+    /// what should be done with it.
+    pub fn prepare(&mut self, doc: &mut Document) -> DedupAction {
+        let hash = self.content_hash(doc);
+        let hash_field = self.hash_field;
+        doc.filter_fields(|field| field != hash_field);
+        doc.add_u64(hash_field, hash);
+        match self.mode {
+            DedupMode::Skip => {
+                if self.is_duplicate(hash) {
+                    DedupAction::Skip
+                } else {
+                    self.uncommitted_hashes.insert(hash);
+                    DedupAction::Add
+                }
+            }
+            DedupMode::Replace => DedupAction::Replace(self.hash_term(hash)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Deduplicator;
+    use core::{DedupMode, DedupSettings};
+    use schema::{Document, SchemaBuilder, INT_INDEXED, STRING, TEXT};
+
+    fn dedup_settings(hash_field: &str, fields: &[&str]) -> DedupSettings {
+        DedupSettings {
+            hash_field: hash_field.to_string(),
+            fields: fields.iter().map(|field| field.to_string()).collect(),
+            mode: DedupMode::Skip,
+        }
+    }
+
+    #[test]
+    fn test_dedup_settings_validation() {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_text_field("title", TEXT);
+        schema_builder.add_text_field("url", STRING);
+        schema_builder.add_u64_field("hash", INT_INDEXED);
+        let schema = schema_builder.build();
+        assert!(Deduplicator::new(&schema, &dedup_settings("hash", &["title", "url"])).is_ok());
+        assert!(Deduplicator::new(&schema, &dedup_settings("hash", &["body"])).is_err());
+        assert!(Deduplicator::new(&schema, &dedup_settings("hash", &[])).is_err());
+        assert!(Deduplicator::new(&schema, &dedup_settings("hash", &["hash"])).is_err());
+        assert!(Deduplicator::new(&schema, &dedup_settings("url", &["title"])).is_err());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let hash_field = schema_builder.add_u64_field("hash", INT_INDEXED);
+        let schema = schema_builder.build();
+        let mut deduplicator =
+            Deduplicator::new(&schema, &dedup_settings("hash", &["title"])).unwrap();
+        let hash = deduplicator.content_hash(&doc!(title_field => "a", body_field => "b"));
+        assert_eq!(
+            hash,
+            deduplicator.content_hash(&doc!(title_field => "a", body_field => "c"))
+        );
+        assert!(hash != deduplicator.content_hash(&doc!(title_field => "b")));
+        let mut doc = doc!(title_field => "a", hash_field => 3u64);
+        deduplicator.prepare(&mut doc);
+        assert_eq!(doc.get_all(hash_field).len(), 1);
+        assert_eq!(doc.get_first(hash_field).unwrap().u64_value(), hash);
+        assert!(deduplicator.is_duplicate(hash));
+        let empty_doc = Document::default();
+        assert!(!deduplicator.is_duplicate(deduplicator.content_hash(&empty_doc)));
+    }
+}
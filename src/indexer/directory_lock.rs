@@ -1,20 +1,31 @@
 use Directory;
-use directory::error::OpenWriteError;
+use directory::error::{IOError, OpenWriteError};
 use core::LOCKFILE_FILEPATH;
+use std::io::Write;
+use std::process;
 
 /// The directory lock is a mechanism used to
 /// prevent the creation of two [`IndexWriter`](struct.IndexWriter.html)
 ///
 /// Only one lock can exist at a time for a given directory.
 /// The lock is release automatically on `Drop`.
+///
+/// The lockfile contains the id of the process holding the lock,
+/// to help identify a lockfile left behind by a crashed process.
+/// Reader processes do not take the lock.
 pub struct DirectoryLock {
     directory: Box<Directory>,
 }
 
 impl DirectoryLock {
     pub fn lock(mut directory: Box<Directory>) -> Result<DirectoryLock, OpenWriteError> {
-        directory.open_write(&*LOCKFILE_FILEPATH)?;
-        Ok(DirectoryLock { directory })
+        let mut lockfile = directory.open_write(&*LOCKFILE_FILEPATH)?;
+        // from now on, the lockfile is removed if an error occurs.
+        let directory_lock = DirectoryLock { directory };
+        write!(lockfile, "{}", process::id())
+            .and_then(|_| lockfile.flush())
+            .map_err(|e| IOError::with_path(LOCKFILE_FILEPATH.clone(), e))?;
+        Ok(directory_lock)
     }
 }
 
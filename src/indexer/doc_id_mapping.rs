@@ -0,0 +1,71 @@
+use core::{SegmentId, SegmentReader};
+use std::collections::HashMap;
+use DocId;
+
+/// Mapping from the doc ids of the segments of a merge
+/// to the doc ids of the segment resulting from the merge.
+///
+/// It makes it possible to remap external data keyed by doc ids,
+/// rather than rebuilding it.
+/// See `Index::add_merge_listener`.
+#[derive(Clone)]
+pub struct DocIdMapping {
+    merged_segment_id: SegmentId,
+    new_doc_ids: HashMap<SegmentId, Vec<Option<DocId>>>,
+}
+
+impl DocIdMapping {
+    /// Computes the mapping of a merge.
+    ///
+    /// The alive documents of the readers are given consecutive
+    /// doc ids in the merged segment, in the order of the readers.
+    pub(crate) fn for_merge(
+        merged_segment_id: SegmentId,
+        readers: &[SegmentReader],
+    ) -> DocIdMapping {
+        let mut new_doc_ids = HashMap::new();
+        let mut next_doc_id: DocId = 0;
+        for reader in readers {
+            let segment_new_doc_ids: Vec<Option<DocId>> = (0..reader.max_doc())
+                .map(|doc| {
+                    if reader.is_deleted(doc) {
+                        None
+                    } else {
+                        next_doc_id += 1;
+                        Some(next_doc_id - 1)
+                    }
+                })
+                .collect();
+            new_doc_ids.insert(reader.segment_id(), segment_new_doc_ids);
+        }
+        DocIdMapping {
+            merged_segment_id,
+            new_doc_ids,
+        }
+    }
+
+    /// Returns the id of the segment resulting from the merge.
+    pub fn merged_segment_id(&self) -> SegmentId {
+        self.merged_segment_id
+    }
+
+    /// Returns the ids of the merged segments, in no particular order.
+    ///
+    /// Merged segments without any alive document are included:
+    /// `new_doc_id` returns `None` for all of their documents.
+    pub fn segment_ids(&self) -> Vec<SegmentId> {
+        self.new_doc_ids.keys().cloned().collect()
+    }
+
+    /// Returns the doc id, in the merged segment, of the document `doc`
+    /// of the segment `segment_id`.
+    ///
+    /// Returns `None` if the document was deleted, or if the
+    /// segment is not part of the merge.
+    pub fn new_doc_id(&self, segment_id: SegmentId, doc: DocId) -> Option<DocId> {
+        self.new_doc_ids
+            .get(&segment_id)
+            .and_then(|segment_new_doc_ids| segment_new_doc_ids.get(doc as usize).cloned())
+            .and_then(|new_doc_id| new_doc_id)
+    }
+}
@@ -1,6 +1,7 @@
 use bit_set::BitSet;
 use chan;
 use core::Index;
+use core::MemoryComponent;
 use core::Segment;
 use core::SegmentComponent;
 use core::SegmentId;
@@ -16,22 +17,30 @@ use futures::Canceled;
 use datastruct::stacker::hashmap::split_memory;
 use futures::Future;
 use indexer::doc_opstamp_mapping::DocToOpstampMapping;
-use indexer::MergePolicy;
-use indexer::operation::DeleteOperation;
+use indexer::{MergePolicy, MergeScheduler};
+use indexer::operation::{DeleteOperation, DeleteTarget};
 use indexer::SegmentEntry;
 use indexer::SegmentWriter;
 use docset::DocSet;
 use schema::IndexRecordOption;
 use schema::Document;
+use schema::{FieldType, StoredValueOverflow};
 use schema::Term;
+use query::{create_weight, Query};
+use core::Searcher;
 use std::mem;
+use std::sync::Arc;
 use std::mem::swap;
 use std::thread::JoinHandle;
 use indexer::DirectoryLock;
 use super::operation::AddOperation;
 use super::segment_updater::SegmentUpdater;
 use super::PreparedCommit;
+use super::{CommitHandle, CommitWatch};
+use super::{DedupAction, Deduplicator};
+use super::LanguageRouter;
 use std::thread;
+use time::PreciseTime;
 
 // Size of the margin for the heap. A segment is closed when the remaining memory
 // in the heap goes below MARGIN_IN_BYTES.
@@ -79,6 +88,10 @@ pub struct IndexWriter {
 
     stamper: Stamper,
     committed_opstamp: u64,
+    commit_watch: CommitWatch,
+
+    deduplicator: Option<Deduplicator>,
+    language_router: Option<LanguageRouter>,
 }
 
 // IndexWriter cannot be sent to another thread.
@@ -96,7 +109,7 @@ impl !Sync for IndexWriter {}
 /// `num_threads` specifies the number of indexing workers that
 /// should work at the same time.
 /// # Errors
-/// If the lockfile already exists, returns `Error::FileAlreadyExists`.
+/// If the lockfile already exists, returns `Error::LockBusy`.
 /// # Panics
 /// If the heap size per thread is too small, panics.
 pub fn open_index_writer(
@@ -123,6 +136,8 @@ pub fn open_index_writer(
     let segment_updater =
         SegmentUpdater::new(index.clone(), stamper.clone(), &delete_queue.cursor())?;
 
+    let deduplicator = Deduplicator::open(index)?;
+
     let mut index_writer = IndexWriter {
         _directory_lock: Some(directory_lock),
 
@@ -140,8 +155,12 @@ pub fn open_index_writer(
         delete_queue,
 
         committed_opstamp: current_opstamp,
+        commit_watch: CommitWatch::new(current_opstamp),
         stamper,
 
+        deduplicator,
+        language_router: None,
+
         generation: 0,
 
         worker_id: 0,
@@ -171,13 +190,35 @@ pub fn compute_deleted_bitset(
                 // Limit doc helps identify the first document
                 // that may be affected by the delete operation.
                 let limit_doc = doc_opstamps.compute_doc_limit(delete_op.opstamp);
-                let inverted_index = segment_reader.inverted_index(delete_op.term.field());
-                if let Some(mut docset) =
-                    inverted_index.read_postings(&delete_op.term, IndexRecordOption::Basic)
-                {
-                    while docset.advance() {
-                        let deleted_doc = docset.doc();
-                        if deleted_doc < limit_doc {
+                match delete_op.target {
+                    DeleteTarget::Term(ref term) => {
+                        // the bloom filter of the field, if any, spares
+                        // the lookup in the term dictionary.
+                        if segment_reader.may_contain_key(term) {
+                            let inverted_index = segment_reader.inverted_index(term.field());
+                            if let Some(mut docset) =
+                                inverted_index.read_postings(term, IndexRecordOption::Basic)
+                            {
+                                while docset.advance() {
+                                    let deleted_doc = docset.doc();
+                                    if deleted_doc < limit_doc {
+                                        delete_bitset.insert(deleted_doc as usize);
+                                        might_have_changed = true;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    DeleteTarget::Query(ref query) => {
+                        // the query is evaluated against the segment alone.
+                        let searcher = Searcher::from(vec![segment_reader.clone()]);
+                        let weight = create_weight(&**query, &searcher, false)?;
+                        let mut scorer = weight.scorer(segment_reader)?;
+                        while scorer.advance() {
+                            let deleted_doc = scorer.doc();
+                            if deleted_doc >= limit_doc {
+                                break;
+                            }
                             delete_bitset.insert(deleted_doc as usize);
                             might_have_changed = true;
                         }
@@ -211,6 +252,10 @@ pub fn advance_deletes(
         let segment_reader = SegmentReader::open(&segment)?;
         let max_doc = segment_reader.max_doc();
 
+        let _delete_bitset_reservation = segment.index().memory_accountant().reserve(
+            MemoryComponent::DeleteBitSet,
+            (max_doc as usize + 7) / 8,
+        );
         let mut delete_bitset: BitSet = match segment_entry.delete_bitset() {
             Some(previous_delete_bitset) => (*previous_delete_bitset).clone(),
             None => BitSet::with_capacity(max_doc as usize),
@@ -254,11 +299,14 @@ fn index_documents(
     segment_updater: &mut SegmentUpdater,
     mut delete_cursor: DeleteCursor,
 ) -> Result<bool> {
+    let start = PreciseTime::now();
     heap.clear();
     let schema = segment.schema();
     let segment_id = segment.id();
     let mut segment_writer =
         SegmentWriter::for_segment(heap, table_size, segment.clone(), &schema)?;
+    let memory_accountant = segment.index().memory_accountant();
+    let mut arena_reservation = memory_accountant.reserve(MemoryComponent::IndexingArena, 0);
     for doc in document_iterator {
         segment_writer.add_document(doc, &schema)?;
         // There is two possible conditions to close the segment.
@@ -283,6 +331,22 @@ fn index_documents(
             );
             break;
         }
+        // The segment is also closed when the memory limiter
+        // refuses to account for more of the arena.
+        //
+        // The arena is reserved by blocks of `MARGIN_IN_BYTES`
+        // to limit the contention on the accountant.
+        let num_used_bytes = heap.num_used_bytes() as usize;
+        if num_used_bytes > arena_reservation.num_bytes() {
+            let num_missing_bytes = num_used_bytes - arena_reservation.num_bytes();
+            if !arena_reservation.try_grow(num_missing_bytes + MARGIN_IN_BYTES as usize) {
+                info!(
+                    "Memory limit reached, flushing segment with maxdoc={}.",
+                    segment_writer.max_doc()
+                );
+                break;
+            }
+        }
     }
 
     if !segment_updater.is_alive() {
@@ -295,16 +359,27 @@ fn index_documents(
     // the worker thread.
     assert!(num_docs > 0);
 
+    // the serialized segment is expected to be smaller
+    // than its in-memory representation.
+    if let Some(remaining) = segment.index().remaining_size_budget() {
+        if u64::from(heap.num_used_bytes()) > remaining {
+            bail!(ErrorKind::DiskFull(None));
+        }
+    }
+
     let doc_opstamps: Vec<u64> = segment_writer.finalize()?;
 
     let mut segment_meta = SegmentMeta::new(segment_id);
     segment_meta.set_max_doc(num_docs);
 
     let last_docstamp: u64 = *(doc_opstamps.last().unwrap());
+    segment_meta.set_create_opstamp(last_docstamp);
 
     let doc_to_opstamps = DocToOpstampMapping::from(doc_opstamps);
     let segment_reader = SegmentReader::open(segment)?;
     let mut deleted_bitset = BitSet::with_capacity(num_docs as usize);
+    let _deleted_bitset_reservation =
+        memory_accountant.reserve(MemoryComponent::DeleteBitSet, (num_docs as usize + 7) / 8);
     let may_have_deletes = compute_deleted_bitset(
         &mut deleted_bitset,
         &segment_reader,
@@ -313,6 +388,23 @@ fn index_documents(
         last_docstamp,
     )?;
 
+    lifecycle_event!(
+        "segment_flushed",
+        segment = segment_id.short_uuid_string(),
+        num_docs = num_docs,
+        num_bytes = segment
+            .index()
+            .segment(segment_meta.clone())
+            .info()
+            .map(|segment_info| segment_info.num_bytes())
+            .unwrap_or(0),
+        duration_ms = start.to(PreciseTime::now()).num_milliseconds()
+    );
+
+    let index = segment.index();
+    index.finalize_new_segment(&mut segment_meta)?;
+    index.warm_new_segment(&index.segment(segment_meta.clone()))?;
+
     let segment_entry = SegmentEntry::new(segment_meta, delete_cursor, {
         if may_have_deletes {
             Some(deleted_bitset)
@@ -434,6 +526,27 @@ impl IndexWriter {
         self.segment_updater.set_merge_policy(merge_policy);
     }
 
+    /// Set the merge scheduler.
+    ///
+    /// The merges that the former merge scheduler has not
+    /// run yet are cancelled if it drops them.
+    pub fn set_merge_scheduler(&self, merge_scheduler: Box<MergeScheduler>) {
+        self.segment_updater.set_merge_scheduler(merge_scheduler);
+    }
+
+    /// Set the language router, detecting the language of the
+    /// documents added from now on, and selecting the tokenizer
+    /// of their text fields.
+    ///
+    /// Returns an `InvalidArgument` error if its fields are not
+    /// text fields, or if one of its tokenizers is not registered
+    /// in the `TokenizerManager` of the index.
+    pub fn set_language_router(&mut self, language_router: LanguageRouter) -> Result<()> {
+        language_router.validate(&self.index.schema(), self.index.tokenizers())?;
+        self.language_router = Some(language_router);
+        Ok(())
+    }
+
     fn start_workers(&mut self) -> Result<()> {
         for _ in 0..self.num_threads {
             self.add_indexing_worker()?;
@@ -495,12 +608,13 @@ impl IndexWriter {
             .take()
             .expect("The IndexWriter does not have any lock. This is a bug, please report.");
 
-        let new_index_writer: IndexWriter = open_index_writer(
+        let mut new_index_writer: IndexWriter = open_index_writer(
             &self.index,
             self.num_threads,
             self.heap_size_in_bytes_per_thread,
             directory_lock,
         )?;
+        new_index_writer.language_router = self.language_router.take();
 
         // the current `self` is dropped right away because of this call.
         //
@@ -518,6 +632,27 @@ impl IndexWriter {
         Ok(())
     }
 
+    /// Rollback to a previous commit.
+    ///
+    /// The previous commits that are kept are defined by
+    /// `IndexSettings::num_kept_commits`, and listed by
+    /// `Index::list_commits()`.
+    ///
+    /// The segments of the commit of the given generation are
+    /// published as a new commit, so that a rollback can itself be
+    /// undone by rolling back to the commit it replaced.
+    /// As with `.rollback()`, the updates that happened after
+    /// the last commit are cancelled.
+    ///
+    /// Returns an `InvalidArgument` error if the commit is not kept.
+    pub fn rollback_to(&mut self, generation: u64) -> Result<()> {
+        let commit_metas = self.index.commit_metas(generation)?;
+        info!("Rolling back to generation {}", generation);
+        let opstamp = self.stamper.stamp();
+        self.segment_updater.restore_commit(commit_metas, opstamp)?;
+        self.rollback()
+    }
+
     /// Prepares a commit.
     ///
     /// Calling `prepare_commit()` will cut the indexing
@@ -600,6 +735,19 @@ impl IndexWriter {
         &self.segment_updater
     }
 
+    /// Records a successful commit, resolving the
+    /// `CommitHandle`s of the operations it included.
+    ///
+    /// With deduplication, the committed segments are then reopened.
+    /// This cannot fail the commit, which is already durable.
+    pub(crate) fn on_commit(&mut self, opstamp: u64) {
+        self.committed_opstamp = opstamp;
+        self.commit_watch.notify_commit(opstamp);
+        if let Some(ref mut deduplicator) = self.deduplicator {
+            deduplicator.on_commit(&self.index);
+        }
+    }
+
     /// Delete all documents containing a given term.
     ///
     /// Delete operation only affects documents that
@@ -610,11 +758,50 @@ impl IndexWriter {
     /// only after calling `commit()`.
     pub fn delete_term(&mut self, term: Term) -> u64 {
         let opstamp = self.stamper.stamp();
-        let delete_operation = DeleteOperation { opstamp, term };
+        let delete_operation = DeleteOperation {
+            opstamp,
+            target: DeleteTarget::Term(term),
+        };
+        self.delete_queue.push(delete_operation);
+        opstamp
+    }
+
+    /// Delete all documents matching a given query.
+    ///
+    /// The query is evaluated against each segment when the deletes
+    /// are applied, that is at commit or merge time, rather than when
+    /// this method is called.
+    /// As for `delete_term`, only the documents added before
+    /// the delete operation are affected, and the deletion is only
+    /// visible after calling `commit()`.
+    ///
+    /// Failing to evaluate the query makes the commit fail.
+    pub fn delete_query(&mut self, query: Box<Query + Send + Sync>) -> u64 {
+        let opstamp = self.stamper.stamp();
+        let delete_operation = DeleteOperation {
+            opstamp,
+            target: DeleteTarget::Query(Arc::from(query)),
+        };
         self.delete_queue.push(delete_operation);
         opstamp
     }
 
+    /// Returns true iff the index deduplicates documents
+    /// in `DedupMode::Skip` mode, and the document would be skipped
+    /// by `.add_document(...)`.
+    ///
+    /// The documents added and then deleted since the last
+    /// commit are still considered.
+    pub fn is_duplicate(&self, document: &Document) -> bool {
+        self.deduplicator
+            .as_ref()
+            .map(|deduplicator| {
+                deduplicator.is_skipping()
+                    && deduplicator.is_duplicate(deduplicator.content_hash(document))
+            })
+            .unwrap_or(false)
+    }
+
     /// Returns the opstamp of the last successful commit.
     ///
     /// This is, for instance, the opstamp the index will
@@ -626,6 +813,15 @@ impl IndexWriter {
         self.committed_opstamp
     }
 
+    /// Returns a `CommitHandle` resolved once the operation
+    /// of the given opstamp has been durably committed.
+    ///
+    /// This is typically used with the opstamp returned by
+    /// `.delete_term(...)` or `.delete_query(...)`.
+    pub fn commit_handle(&self, opstamp: u64) -> CommitHandle {
+        self.commit_watch.handle(opstamp)
+    }
+
     /// Adds a document.
     ///
     /// If the indexing pipeline is full, this call may block.
@@ -636,11 +832,119 @@ impl IndexWriter {
     ///
     /// Currently it represents the number of documents that
     /// have been added since the creation of the index.
-    pub fn add_document(&mut self, document: Document) -> u64 {
+    /// It is available through the returned `CommitHandle`,
+    /// which can also be waited on until the document
+    /// is durably committed.
+    ///
+    /// If the index defines `IndexSettings::dedup`, the content hash
+    /// of the document is computed and indexed, and duplicates are either
+    /// skipped, or replace the existing documents. A skipped document
+    /// still gets an opstamp, and its `CommitHandle` is resolved by
+    /// the next commit. See also `.is_duplicate(...)`.
+    ///
+    /// If a `LanguageRouter` is set, the language of the document
+    /// is detected first. See `.set_language_router(...)`.
+    ///
+    /// A document with a stored value exceeding a `StoredValueLimit`
+    /// in `StoredValueOverflow::Reject` mode is not indexed, but still gets
+    /// an opstamp. Use `.try_add_document(...)` to be told about it.
+    pub fn add_document(&mut self, document: Document) -> CommitHandle {
+        self.push_document(document, true)
+    }
+
+    /// Adds a document, unless it has a stored value exceeding
+    /// a `StoredValueLimit` in `StoredValueOverflow::Reject` mode.
+    ///
+    /// # Errors
+    /// Returns `ErrorKind::InvalidArgument` if the document is rejected.
+    /// It then gets no opstamp. See `.add_document(...)`.
+    pub fn try_add_document(&mut self, document: Document) -> Result<CommitHandle> {
+        self.check_stored_value_limits(&document)?;
+        Ok(self.push_document(document, true))
+    }
+
+    /// Replaces the documents containing the given term,
+    /// typically a primary key, by a new document.
+    ///
+    /// The old versions are deleted, and the new document is added
+    /// right after, with a greater opstamp. The new document is therefore
+    /// not affected by the deletion, and no commit can happen
+    /// in between: the update is visible, as a whole, after the next commit.
+    ///
+    /// Returns the opstamp of the added document. Opstamps are monotonically
+    /// increasing, and the update is visible once `.commit_opstamp()`
+    /// is greater than it. See also `.commit_handle(...)`.
+    ///
+    /// Unlike `.add_document(...)`, the new document is never skipped
+    /// as a duplicate, since it may well be a duplicate of the deleted one.
+    pub fn update_document(&mut self, term: Term, document: Document) -> u64 {
+        self.delete_term(term);
+        self.push_document(document, false).opstamp()
+    }
+
+    /// Replaces the documents containing the given term by a new document,
+    /// unless it has a stored value exceeding a `StoredValueLimit`
+    /// in `StoredValueOverflow::Reject` mode.
+    ///
+    /// # Errors
+    /// Returns `ErrorKind::InvalidArgument` if the document is rejected.
+    /// The old versions are then kept. See `.update_document(...)`.
+    pub fn try_update_document(&mut self, term: Term, document: Document) -> Result<u64> {
+        self.check_stored_value_limits(&document)?;
+        Ok(self.update_document(term, document))
+    }
+
+    /// Returns an error if a stored value of the document exceeds
+    /// a `StoredValueLimit` in `StoredValueOverflow::Reject` mode.
+    fn check_stored_value_limits(&self, document: &Document) -> Result<()> {
+        let schema = self.index.schema();
+        for field_value in document.field_values() {
+            let field_entry = schema.get_field_entry(field_value.field());
+            let limit_opt = match *field_entry.field_type() {
+                FieldType::Str(ref text_options) => {
+                    text_options.exceeded_stored_value_limit(field_value.value())
+                }
+                _ => None,
+            };
+            if let Some(limit) = limit_opt {
+                if limit.overflow == StoredValueOverflow::Reject {
+                    bail!(ErrorKind::InvalidArgument(format!(
+                        "The value of field {:?} is too long to be stored (> {} bytes)",
+                        field_entry.name(),
+                        limit.max_len
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_document(&mut self, mut document: Document, skip_duplicates: bool) -> CommitHandle {
+        let tokenizer_route = self.language_router
+            .as_ref()
+            .and_then(|language_router| language_router.prepare(&mut document));
+        match self.deduplicator
+            .as_mut()
+            .map(|deduplicator| deduplicator.prepare(&mut document))
+        {
+            Some(DedupAction::Skip) if skip_duplicates => {
+                debug!("Skipping a duplicate document");
+                let opstamp = self.stamper.stamp();
+                return self.commit_handle(opstamp);
+            }
+            Some(DedupAction::Replace(hash_term)) => {
+                self.delete_term(hash_term);
+            }
+            Some(DedupAction::Skip) | Some(DedupAction::Add) | None => {}
+        }
         let opstamp = self.stamper.stamp();
-        let add_operation = AddOperation { opstamp, document };
+        let add_operation = AddOperation {
+            opstamp,
+            document,
+            tokenizer_route,
+        };
         self.document_sender.send(add_operation);
-        opstamp
+        self.commit_handle(opstamp)
     }
 }
 
@@ -660,8 +964,8 @@ mod tests {
         let index = Index::create_in_ram(schema_builder.build());
         let _index_writer = index.writer(40_000_000).unwrap();
         match index.writer(40_000_000) {
-            Err(Error(ErrorKind::FileAlreadyExists(_), _)) => {}
-            _ => panic!("Expected FileAlreadyExists error"),
+            Err(Error(ErrorKind::LockBusy(_), _)) => {}
+            _ => panic!("Expected LockBusy error"),
         }
     }
 
@@ -673,7 +977,7 @@ mod tests {
         assert_eq!(
             format!("{:?}", index_writer.get_merge_policy()),
             "LogMergePolicy { min_merge_size: 8, min_layer_size: 10000, \
-             level_log_size: 0.75 }"
+             level_log_size: 0.75, max_merge_size: None }"
         );
         let merge_policy = box NoMergePolicy::default();
         index_writer.set_merge_policy(merge_policy);
@@ -683,6 +987,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_update_document() {
+        use collector::CountCollector;
+        use query::TermQuery;
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_field = schema_builder.add_u64_field("id", schema::INT_INDEXED);
+        let text_field = schema_builder.add_text_field("text", schema::TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        // the doc freq includes the deleted documents, hence the search.
+        let num_docs_containing = |s: &str| {
+            let searcher = index.searcher();
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, s),
+                schema::IndexRecordOption::Basic,
+            );
+            let mut count_collector = CountCollector::default();
+            searcher.search(&term_query, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(id_field => 1u64, text_field => "a"));
+        index_writer.add_document(doc!(id_field => 2u64, text_field => "a"));
+        index_writer.commit().unwrap();
+
+        let id_term = |id: u64| Term::from_field_u64(id_field, id);
+        let first_opstamp =
+            index_writer.update_document(id_term(1), doc!(id_field => 1u64, text_field => "b"));
+        let second_opstamp =
+            index_writer.update_document(id_term(1), doc!(id_field => 1u64, text_field => "c"));
+        assert!(second_opstamp > first_opstamp);
+        // updating a document that does not exist just adds it.
+        let third_opstamp =
+            index_writer.update_document(id_term(3), doc!(id_field => 3u64, text_field => "b"));
+        assert!(third_opstamp > second_opstamp);
+        assert!(!index_writer.commit_handle(third_opstamp).is_committed());
+        let commit_opstamp = index_writer.commit().unwrap();
+        assert!(commit_opstamp > third_opstamp);
+        assert!(index_writer.commit_handle(third_opstamp).is_committed());
+
+        index.load_searchers().unwrap();
+        assert_eq!(num_docs_containing("a"), 1);
+        assert_eq!(num_docs_containing("b"), 1);
+        assert_eq!(num_docs_containing("c"), 1);
+        assert_eq!(index.searcher().num_docs(), 3);
+    }
+
     #[test]
     fn test_lockfile_released_on_drop() {
         let schema_builder = schema::SchemaBuilder::default();
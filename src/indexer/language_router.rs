@@ -0,0 +1,205 @@
+use Result;
+use error::ErrorKind;
+use schema::{Document, Field, FieldType, Schema, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokenizer::{LanguageDetector, TokenizerManager};
+
+/// Tokenizer selected by the `LanguageRouter` for
+/// the routed fields of a document.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TokenizerRoute {
+    pub(crate) fields: Vec<Field>,
+    pub(crate) tokenizer_name: String,
+}
+
+/// Detects the language of the documents at indexing time,
+/// stores it in a field, and selects the tokenizer of their text fields.
+///
+/// The language is detected on the values of the routed `fields`,
+/// and its code is added to `language_field`, typically a
+/// `STRING | STORED` field. If the document already has a value
+/// for `language_field`, this value is used instead.
+///
+/// The routed fields are then tokenized with the tokenizer
+/// registered for the language, falling back to the tokenizer
+/// of the field for the languages without route.
+/// By default, the languages of the `StopWordLanguageDetector` are
+/// routed to the stemming tokenizers of the `TokenizerManager`:
+/// `en` to `en_stem`, `fr` to `fr_stem`, ..., and `ja` to `ja`.
+///
+/// Queries are still analyzed with the tokenizer of the field,
+/// so a multilingual index is typically queried with a filter
+/// on `language_field`, and a query parser for each language.
+///
+/// See `IndexWriter::set_language_router`.
+pub struct LanguageRouter {
+    detector: Box<LanguageDetector>,
+    language_field: Field,
+    fields: Vec<Field>,
+    routes: HashMap<String, Arc<TokenizerRoute>>,
+}
+
+impl LanguageRouter {
+    /// Creates a `LanguageRouter`, with the default routes.
+    pub fn new(
+        detector: Box<LanguageDetector>,
+        language_field: Field,
+        fields: Vec<Field>,
+    ) -> LanguageRouter {
+        let mut language_router = LanguageRouter {
+            detector,
+            language_field,
+            fields,
+            routes: HashMap::new(),
+        };
+        for language in &["en", "fr", "de", "it", "pt", "es"] {
+            language_router = language_router.route(language, &format!("{}_stem", language));
+        }
+        language_router.route("ja", "ja")
+    }
+
+    /// Routes the documents of the given language
+    /// to the tokenizer `tokenizer_name`.
+    pub fn route(mut self, language: &str, tokenizer_name: &str) -> LanguageRouter {
+        let tokenizer_route = TokenizerRoute {
+            fields: self.fields.clone(),
+            tokenizer_name: tokenizer_name.to_string(),
+        };
+        self.routes
+            .insert(language.to_string(), Arc::new(tokenizer_route));
+        self
+    }
+
+    /// Checks that the fields are text fields, the routed fields
+    /// are indexed, and that the tokenizers of the routes are registered.
+    pub(crate) fn validate(&self, schema: &Schema, tokenizers: &TokenizerManager) -> Result<()> {
+        let is_text_field = |field: Field, indexed: bool| match *schema
+            .get_field_entry(field)
+            .field_type()
+        {
+            FieldType::Str(ref text_options) => {
+                !indexed || text_options.get_indexing_options().is_some()
+            }
+            _ => false,
+        };
+        if !is_text_field(self.language_field, false) {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The language field {:?} must be a text field",
+                self.language_field
+            )));
+        }
+        for &field in &self.fields {
+            if !is_text_field(field, true) {
+                bail!(ErrorKind::InvalidArgument(format!(
+                    "The routed field {:?} must be an indexed text field",
+                    field
+                )));
+            }
+        }
+        for tokenizer_route in self.routes.values() {
+            if tokenizers.get(&tokenizer_route.tokenizer_name).is_none() {
+                bail!(ErrorKind::InvalidArgument(format!(
+                    "The tokenizer {:?} is not registered",
+                    tokenizer_route.tokenizer_name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the language of the document, if it could be detected,
+    /// and returns the route of its text fields.
+    pub(crate) fn prepare(&self, doc: &mut Document) -> Option<Arc<TokenizerRoute>> {
+        let given_language = match doc.get_first(self.language_field) {
+            Some(&Value::Str(ref language)) => Some(language.clone()),
+            _ => None,
+        };
+        let language = match given_language {
+            Some(language) => language,
+            None => {
+                let detected_language = {
+                    let texts: Vec<&str> = self.fields
+                        .iter()
+                        .flat_map(|&field| doc.get_all(field))
+                        .filter_map(|value| match *value {
+                            Value::Str(ref text) => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    self.detector.detect(&texts.join("\n"))
+                };
+                let language = detected_language?;
+                doc.add_text(self.language_field, &language);
+                language
+            }
+        };
+        self.routes.get(&language).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::LanguageRouter;
+    use schema::{Document, SchemaBuilder, INT_INDEXED, STORED, STRING, TEXT};
+    use tokenizer::{StopWordLanguageDetector, TokenizerManager};
+
+    #[test]
+    fn test_language_router_prepare() {
+        let mut schema_builder = SchemaBuilder::default();
+        let lang_field = schema_builder.add_text_field("lang", STRING | STORED);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+        let language_router =
+            LanguageRouter::new(box StopWordLanguageDetector, lang_field, vec![body_field]);
+        assert!(
+            language_router
+                .validate(&schema, &TokenizerManager::default())
+                .is_ok()
+        );
+
+        let mut doc = doc!(body_field => "Le chat dort dans le jardin, et il n'a pas faim.");
+        let tokenizer_route = language_router.prepare(&mut doc).unwrap();
+        assert_eq!(tokenizer_route.tokenizer_name, "fr_stem");
+        assert_eq!(tokenizer_route.fields, vec![body_field]);
+        assert_eq!(doc.get_first(lang_field).unwrap().text(), "fr");
+
+        // the language given in the document is kept.
+        let mut doc = doc!(lang_field => "de", body_field => "The cat is not hungry.");
+        let tokenizer_route = language_router.prepare(&mut doc).unwrap();
+        assert_eq!(tokenizer_route.tokenizer_name, "de_stem");
+        assert_eq!(doc.get_all(lang_field).len(), 1);
+
+        let mut doc = doc!(body_field => "tantivy");
+        assert!(language_router.prepare(&mut doc).is_none());
+        assert!(doc.get_first(lang_field).is_none());
+
+        let mut doc = Document::default();
+        assert!(language_router.prepare(&mut doc).is_none());
+    }
+
+    #[test]
+    fn test_language_router_validate() {
+        let mut schema_builder = SchemaBuilder::default();
+        let lang_field = schema_builder.add_text_field("lang", STRING | STORED);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let stored_field = schema_builder.add_text_field("stored", STORED);
+        let int_field = schema_builder.add_u64_field("int", INT_INDEXED);
+        let schema = schema_builder.build();
+        let tokenizers = TokenizerManager::default();
+        let validate = |language_router: LanguageRouter| {
+            language_router.validate(&schema, &tokenizers).is_ok()
+        };
+        let router = |language_field, fields| {
+            LanguageRouter::new(box StopWordLanguageDetector, language_field, fields)
+        };
+        assert!(validate(router(stored_field, vec![body_field])));
+        assert!(!validate(router(int_field, vec![body_field])));
+        assert!(!validate(router(lang_field, vec![stored_field])));
+        assert!(!validate(router(lang_field, vec![int_field])));
+        assert!(!validate(
+            router(lang_field, vec![body_field]).route("nl", "nl_stem")
+        ));
+    }
+}
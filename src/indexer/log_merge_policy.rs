@@ -9,11 +9,23 @@ const DEFAULT_MIN_MERGE_SIZE: usize = 8;
 
 /// `LogMergePolicy` tries tries to merge segments that have a similar number of
 /// documents.
+///
+/// Segments are grouped in levels of similar sizes, all of the segments
+/// under `min_layer_size` documents belonging to the same level.
+/// A level is merged once it holds at least `min_merge_size` segments.
+/// The merges can be capped to `max_merge_size` segments, so that a level
+/// holding many small segments is merged in several merges, rather
+/// than in one large merge.
+///
+/// The minimum segment size is therefore set with `set_min_layer_size`,
+/// and the merge factor, the number of segments merged at once,
+/// with `set_min_merge_size` and `set_max_merge_size`.
 #[derive(Debug, Clone)]
 pub struct LogMergePolicy {
     min_merge_size: usize,
     min_layer_size: u32,
     level_log_size: f64,
+    max_merge_size: Option<usize>,
 }
 
 impl LogMergePolicy {
@@ -26,6 +38,20 @@ impl LogMergePolicy {
         self.min_merge_size = min_merge_size;
     }
 
+    /// Set the maximum number of segments that may be merged together.
+    ///
+    /// Once a level holds at least `min_merge_size` segments, its segments
+    /// are merged in several merges of at most `max_merge_size` segments.
+    /// The last of these merges may hold fewer segments, but at least 2.
+    /// There is no maximum by default.
+    ///
+    /// # Panics
+    /// The method panics if `max_merge_size` is lower than 2.
+    pub fn set_max_merge_size(&mut self, max_merge_size: usize) {
+        assert!(max_merge_size >= 2, "At least 2 segments are required to merge.");
+        self.max_merge_size = Some(max_merge_size);
+    }
+
     /// Set the minimum segment size under which all segment belong
     /// to the same level.
     pub fn set_min_layer_size(&mut self, min_layer_size: u32) {
@@ -74,9 +100,13 @@ impl MergePolicy for LogMergePolicy {
             levels.last_mut().unwrap().push(ind);
         }
 
+        let max_merge_size = self.max_merge_size.unwrap_or_else(usize::max_value);
         levels
             .iter()
             .filter(|level| level.len() >= self.min_merge_size)
+            .flat_map(|level| level.chunks(max_merge_size))
+            // a single segment left after splitting the level is not merged.
+            .filter(|ind_vec| ind_vec.len() >= 2)
             .map(|ind_vec| MergeCandidate(ind_vec.iter().map(|&ind| segments[ind].id()).collect()))
             .collect()
     }
@@ -92,6 +122,7 @@ impl Default for LogMergePolicy {
             min_merge_size: DEFAULT_MIN_MERGE_SIZE,
             min_layer_size: DEFAULT_MIN_LAYER_SIZE,
             level_log_size: DEFAULT_LEVEL_LOG_SIZE,
+            max_merge_size: None,
         }
     }
 }
@@ -181,4 +212,43 @@ mod tests {
         let result_list = test_merge_policy().compute_merge_candidates(&test_input);
         assert_eq!(result_list.len(), 1);
     }
+
+    #[test]
+    fn test_log_merge_policy_max_merge_size() {
+        let test_input: Vec<SegmentMeta> = (0..8).map(|_| seg_meta(1)).collect();
+        let mut merge_policy = test_merge_policy();
+        assert_eq!(merge_policy.compute_merge_candidates(&test_input).len(), 1);
+        merge_policy.set_max_merge_size(3);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        let merge_sizes: Vec<usize> = result_list
+            .iter()
+            .map(|merge_candidate| merge_candidate.0.len())
+            .collect();
+        assert_eq!(merge_sizes, vec![3, 3, 2]);
+        // a single segment left after splitting the level is not merged.
+        let test_input: Vec<SegmentMeta> = (0..7).map(|_| seg_meta(1)).collect();
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        assert_eq!(result_list.len(), 2);
+        for merge_candidate in result_list {
+            assert_eq!(merge_candidate.0.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_log_merge_policy_max_below_min_merge_size() {
+        let test_input: Vec<SegmentMeta> = (0..8).map(|_| seg_meta(1)).collect();
+        let mut merge_policy = LogMergePolicy::default();
+        merge_policy.set_min_layer_size(2);
+        assert_eq!(merge_policy.compute_merge_candidates(&test_input).len(), 1);
+        merge_policy.set_max_merge_size(3);
+        let result_list = merge_policy.compute_merge_candidates(&test_input);
+        let merge_sizes: Vec<usize> = result_list
+            .iter()
+            .map(|merge_candidate| merge_candidate.0.len())
+            .collect();
+        assert_eq!(merge_sizes, vec![3, 3, 2]);
+        // the level holds fewer than `min_merge_size = 8` segments.
+        let test_input: Vec<SegmentMeta> = (0..3).map(|_| seg_meta(1)).collect();
+        assert!(merge_policy.compute_merge_candidates(&test_input).is_empty());
+    }
 }
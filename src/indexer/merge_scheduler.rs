@@ -0,0 +1,350 @@
+use core::Segment;
+use core::SegmentId;
+use core::SegmentMeta;
+use futures::sync::oneshot::Sender;
+use indexer::segment_updater::SegmentUpdater;
+use std::fmt::{self, Debug};
+use std::marker;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// A merge selected by the `MergePolicy`, waiting to be executed.
+///
+/// The segments to merge are reserved until the merge is either
+/// run, or dropped, in which case the merge is cancelled.
+pub struct PendingMerge {
+    segment_updater: SegmentUpdater,
+    segment_ids: Vec<SegmentId>,
+    num_docs: u64,
+    target_opstamp: u64,
+    // `None` once the merge has been run.
+    merged_segment: Option<Segment>,
+    merged_segment_sender: Option<Sender<SegmentMeta>>,
+}
+
+impl PendingMerge {
+    pub(crate) fn new(
+        segment_updater: SegmentUpdater,
+        segment_ids: Vec<SegmentId>,
+        num_docs: u64,
+        target_opstamp: u64,
+        merged_segment: Segment,
+        merged_segment_sender: Sender<SegmentMeta>,
+    ) -> PendingMerge {
+        segment_updater.merge_tracker().start_merge();
+        PendingMerge {
+            segment_updater,
+            segment_ids,
+            num_docs,
+            target_opstamp,
+            merged_segment: Some(merged_segment),
+            merged_segment_sender: Some(merged_segment_sender),
+        }
+    }
+
+    /// Returns the ids of the segments to merge.
+    pub fn segment_ids(&self) -> &[SegmentId] {
+        &self.segment_ids
+    }
+
+    /// Returns the number of alive documents in the segments to merge,
+    /// at the time the merge was scheduled.
+    ///
+    /// This is a good proxy for the cost of the merge.
+    pub fn num_docs(&self) -> u64 {
+        self.num_docs
+    }
+
+    /// Executes the merge, on the calling thread.
+    ///
+    /// This call blocks until the merged segment has been published.
+    pub fn run(mut self) {
+        let merged_segment = self.merged_segment
+            .take()
+            .expect("A pending merge cannot be run twice");
+        let merged_segment_sender = self.merged_segment_sender
+            .take()
+            .expect("A pending merge cannot be run twice");
+        self.segment_updater.run_merge(
+            &self.segment_ids,
+            merged_segment,
+            self.target_opstamp,
+            merged_segment_sender,
+        );
+    }
+}
+
+impl Debug for PendingMerge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PendingMerge({:?}, num_docs={})",
+            self.segment_ids, self.num_docs
+        )
+    }
+}
+
+impl Drop for PendingMerge {
+    fn drop(&mut self) {
+        if let Some(merged_segment) = self.merged_segment.take() {
+            info!("Merge of {:?} was dropped without being run", self.segment_ids);
+            self.segment_updater
+                .cancel_merge(&self.segment_ids, merged_segment.id());
+        }
+        self.segment_updater
+            .merge_tracker()
+            .end_merge(thread::panicking());
+    }
+}
+
+/// The `MergeScheduler` defines when, and on which thread,
+/// the merges selected by the `MergePolicy` are executed.
+///
+/// Implementing this trait makes it possible to integrate merging
+/// into an existing task system : `.schedule(...)` may simply
+/// enqueue the `PendingMerge`, and the task system calls
+/// `PendingMerge::run()` when it sees fit.
+pub trait MergeScheduler: marker::Send + marker::Sync + Debug {
+    /// Schedules a merge.
+    ///
+    /// This call happens on the segment updater thread, and will block
+    /// other segment updates, so all implementations should return rapidly.
+    /// In particular, the merge must not be run on the calling thread,
+    /// as publishing the merged segment requires the segment updater thread.
+    ///
+    /// Dropping the `PendingMerge` cancels the merge.
+    fn schedule(&self, merge: PendingMerge);
+}
+
+/// Runs each merge on its own thread, as soon as it is scheduled.
+#[derive(Debug, Default)]
+pub struct ConcurrentMergeScheduler;
+
+impl MergeScheduler for ConcurrentMergeScheduler {
+    fn schedule(&self, merge: PendingMerge) {
+        thread::spawn(move || merge.run());
+    }
+}
+
+struct MergeQueue {
+    pending_merges: Vec<PendingMerge>,
+    closed: bool,
+}
+
+/// Runs the merges one at a time, on a dedicated thread.
+///
+/// When several merges are pending, the one with the
+/// fewest documents is run first, so that small merges
+/// are not stuck behind a large one.
+pub struct SerialMergeScheduler {
+    queue: Arc<(Mutex<MergeQueue>, Condvar)>,
+}
+
+impl SerialMergeScheduler {
+    /// Creates a `SerialMergeScheduler`, and starts its merging thread.
+    pub fn new() -> SerialMergeScheduler {
+        let queue = Arc::new((
+            Mutex::new(MergeQueue {
+                pending_merges: vec![],
+                closed: false,
+            }),
+            Condvar::new(),
+        ));
+        let queue_clone = queue.clone();
+        thread::Builder::new()
+            .name("merge_scheduler".to_string())
+            .spawn(move || {
+                while let Some(merge) = SerialMergeScheduler::next_merge(&queue_clone) {
+                    // a failed merge is reported by `wait_merging_threads`,
+                    // and should not prevent the next merges from running.
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| merge.run()));
+                }
+            })
+            .expect("Failed to spawn the merging thread");
+        SerialMergeScheduler { queue }
+    }
+
+    /// Blocks until a merge is pending, and returns the smallest one,
+    /// or returns `None` once the scheduler is dropped.
+    fn next_merge(queue: &(Mutex<MergeQueue>, Condvar)) -> Option<PendingMerge> {
+        let (ref merge_queue, ref merge_available) = *queue;
+        let mut merge_queue = merge_queue.lock().expect("Merge queue lock poisoned");
+        loop {
+            if merge_queue.closed {
+                return None;
+            }
+            let smallest_merge_ord = merge_queue
+                .pending_merges
+                .iter()
+                .enumerate()
+                .min_by_key(|&(_, merge)| merge.num_docs())
+                .map(|(ord, _)| ord);
+            if let Some(ord) = smallest_merge_ord {
+                return Some(merge_queue.pending_merges.swap_remove(ord));
+            }
+            merge_queue = merge_available
+                .wait(merge_queue)
+                .expect("Merge queue lock poisoned");
+        }
+    }
+}
+
+impl Default for SerialMergeScheduler {
+    fn default() -> SerialMergeScheduler {
+        SerialMergeScheduler::new()
+    }
+}
+
+impl Debug for SerialMergeScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let merge_queue = self.queue.0.lock().expect("Merge queue lock poisoned");
+        write!(
+            f,
+            "SerialMergeScheduler {{ num_pending_merges: {} }}",
+            merge_queue.pending_merges.len()
+        )
+    }
+}
+
+impl MergeScheduler for SerialMergeScheduler {
+    fn schedule(&self, merge: PendingMerge) {
+        let (ref merge_queue, ref merge_available) = *self.queue;
+        merge_queue
+            .lock()
+            .expect("Merge queue lock poisoned")
+            .pending_merges
+            .push(merge);
+        merge_available.notify_one();
+    }
+}
+
+impl Drop for SerialMergeScheduler {
+    fn drop(&mut self) {
+        let (ref merge_queue, ref merge_available) = *self.queue;
+        let pending_merges = {
+            let mut merge_queue = merge_queue.lock().expect("Merge queue lock poisoned");
+            merge_queue.closed = true;
+            mem::replace(&mut merge_queue.pending_merges, vec![])
+        };
+        // cancels the merges that were not run,
+        // outside of the lock.
+        drop(pending_merges);
+        merge_available.notify_all();
+    }
+}
+
+/// Keeps track of the merges that were scheduled
+/// and have not been run or dropped yet.
+#[derive(Default)]
+pub(crate) struct MergeTracker {
+    // (number of pending merges, whether a merge failed)
+    state: Mutex<(usize, bool)>,
+    all_merges_ended: Condvar,
+}
+
+impl MergeTracker {
+    fn start_merge(&self) {
+        self.state.lock().expect("Merge tracker lock poisoned").0 += 1;
+    }
+
+    fn end_merge(&self, failed: bool) {
+        let mut state = self.state.lock().expect("Merge tracker lock poisoned");
+        state.0 -= 1;
+        state.1 |= failed;
+        if state.0 == 0 {
+            self.all_merges_ended.notify_all();
+        }
+    }
+
+    /// Blocks until all of the pending merges are run or dropped.
+    ///
+    /// Returns true if one of the merges failed since the last call.
+    pub fn wait(&self) -> bool {
+        let mut state = self.state.lock().expect("Merge tracker lock poisoned");
+        while state.0 > 0 {
+            state = self.all_merges_ended
+                .wait(state)
+                .expect("Merge tracker lock poisoned");
+        }
+        mem::replace(&mut state.1, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use futures::Future;
+    use indexer::NoMergePolicy;
+    use indexer::merge_policy::tests::MergeWheneverPossible;
+    use schema::{SchemaBuilder, TEXT};
+    use Index;
+
+    /// `MergeScheduler` leaving the execution of the merges to the test.
+    #[derive(Debug, Clone, Default)]
+    struct ExternalMergeScheduler(Arc<Mutex<Vec<PendingMerge>>>);
+
+    impl MergeScheduler for ExternalMergeScheduler {
+        fn schedule(&self, merge: PendingMerge) {
+            self.0.lock().unwrap().push(merge);
+        }
+    }
+
+    #[test]
+    fn test_external_merge_scheduler() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box NoMergePolicy);
+        let merge_scheduler = ExternalMergeScheduler::default();
+        index_writer.set_merge_scheduler(box merge_scheduler.clone());
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.add_document(doc!(text_field => "b"));
+            index_writer.commit().unwrap();
+        }
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        assert_eq!(segment_ids.len(), 2);
+
+        // dropping a pending merge cancels it.
+        let cancelled_merge = index_writer.merge(&segment_ids);
+        let pending_merge = merge_scheduler.0.lock().unwrap().pop().unwrap();
+        assert_eq!(pending_merge.num_docs(), 4);
+        assert_eq!(pending_merge.segment_ids().len(), 2);
+        drop(pending_merge);
+        assert!(cancelled_merge.wait().is_err());
+
+        let merge = index_writer.merge(&segment_ids);
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().segment_readers().len(), 2);
+        let pending_merge = merge_scheduler.0.lock().unwrap().pop().unwrap();
+        pending_merge.run();
+        let merged_segment_meta = merge.wait().unwrap();
+        assert_eq!(merged_segment_meta.num_docs(), 4);
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().segment_readers().len(), 1);
+        index_writer.wait_merging_threads().unwrap();
+    }
+
+    #[test]
+    fn test_serial_merge_scheduler() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.set_merge_policy(box MergeWheneverPossible);
+        index_writer.set_merge_scheduler(box SerialMergeScheduler::new());
+        for _ in 0..5 {
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+        }
+        index_writer.wait_merging_threads().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        assert_eq!(searcher.num_docs(), 5);
+    }
+}
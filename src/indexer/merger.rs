@@ -1,6 +1,8 @@
 use error::{ErrorKind, Result};
 use core::SegmentReader;
 use core::Segment;
+use core::SegmentId;
+use indexer::DocIdMapping;
 use DocId;
 use core::SerializableSegment;
 use indexer::SegmentSerializer;
@@ -9,15 +11,19 @@ use itertools::Itertools;
 use postings::Postings;
 use docset::DocSet;
 use fastfield::DeleteBitSet;
-use schema::{Field, Schema};
+use schema::{Field, FieldType, Schema};
 use termdict::TermMerger;
-use fastfield::FastFieldSerializer;
+use fastfield::{FastFieldSerializer, FastSingleFieldSerializer};
 use fastfield::FastFieldReader;
 use store::StoreWriter;
 use std::cmp::{max, min};
+use std::io::Write;
 use termdict::TermDictionary;
 use termdict::TermStreamer;
 
+/// Number of fast field values decoded at once during a merge.
+const FAST_FIELD_MERGE_BLOCK_SIZE: usize = 1_024;
+
 pub struct IndexMerger {
     schema: Schema,
     readers: Vec<SegmentReader>,
@@ -46,6 +52,39 @@ fn compute_min_max_val(
     }
 }
 
+/// Appends the values of the alive documents of a segment to
+/// the fast field being serialized.
+///
+/// The values are decoded by blocks, rather than one document
+/// at a time, and the deleted documents are filtered out of each block.
+/// The remaining values are then bitpacked one by one.
+fn write_alive_fast_field_values<W: Write>(
+    u64_reader: &FastFieldReader<u64>,
+    max_doc: DocId,
+    delete_bitset: &DeleteBitSet,
+    fast_single_field_serializer: &mut FastSingleFieldSerializer<W>,
+) -> Result<()> {
+    let mut block = [0u64; FAST_FIELD_MERGE_BLOCK_SIZE];
+    let has_deletes = delete_bitset.has_deletes();
+    let mut block_start: DocId = 0;
+    while block_start < max_doc {
+        let block_len = min(FAST_FIELD_MERGE_BLOCK_SIZE, (max_doc - block_start) as usize);
+        let vals = &mut block[..block_len];
+        u64_reader.get_range(block_start, vals);
+        if has_deletes {
+            for (doc_id, &val) in (block_start..).zip(vals.iter()) {
+                if !delete_bitset.is_deleted(doc_id) {
+                    fast_single_field_serializer.add_val(val)?;
+                }
+            }
+        } else {
+            fast_single_field_serializer.add_vals(vals)?;
+        }
+        block_start += block_len as DocId;
+    }
+    Ok(())
+}
+
 fn extract_fieldnorm_reader(
     segment_reader: &SegmentReader,
     field: Field,
@@ -82,6 +121,30 @@ impl DeltaComputer {
         }
         &self.buffer[..positions.len()]
     }
+
+    /// Interleaves the position deltas with the offsets, as expected
+    /// by the `FieldSerializer` for the fields recording offsets.
+    fn compute_delta_with_offsets(&mut self, positions: &[u32], offsets: &[(u32, u32)]) -> &[u32] {
+        debug_assert_eq!(positions.len(), offsets.len());
+        let len = positions.len() * 3;
+        if len > self.buffer.len() {
+            self.buffer.resize(len, 0u32);
+        }
+        let mut last_pos = 0u32;
+        let mut last_offset_from = 0u32;
+        for ((&cur_pos, &(offset_from, offset_to)), dest) in positions
+            .iter()
+            .zip(offsets.iter())
+            .zip(self.buffer.chunks_mut(3))
+        {
+            dest[0] = cur_pos - last_pos;
+            dest[1] = offset_from.wrapping_sub(last_offset_from);
+            dest[2] = offset_to - offset_from;
+            last_pos = cur_pos;
+            last_offset_from = offset_from;
+        }
+        &self.buffer[..len]
+    }
 }
 
 impl IndexMerger {
@@ -102,6 +165,12 @@ impl IndexMerger {
         })
     }
 
+    /// Returns the mapping from the doc ids of the merged
+    /// segments to the doc ids of the merged segment.
+    pub fn doc_id_mapping(&self, merged_segment_id: SegmentId) -> DocIdMapping {
+        DocIdMapping::for_merge(merged_segment_id, &self.readers)
+    }
+
     fn write_fieldnorms(&self, fast_field_serializer: &mut FastFieldSerializer) -> Result<()> {
         let fieldnorm_fastfields: Vec<Field> = self.schema
             .fields()
@@ -182,12 +251,12 @@ impl IndexMerger {
             let mut fast_single_field_serializer =
                 fast_field_serializer.new_u64_fast_field(field, min_val, max_val)?;
             for (max_doc, u64_reader, delete_bitset) in u64_readers {
-                for doc_id in 0..max_doc {
-                    if !delete_bitset.is_deleted(doc_id) {
-                        let val = u64_reader.get(doc_id);
-                        fast_single_field_serializer.add_val(val)?;
-                    }
-                }
+                write_alive_fast_field_values(
+                    &u64_reader,
+                    max_doc,
+                    delete_bitset,
+                    &mut fast_single_field_serializer,
+                )?;
             }
 
             fast_single_field_serializer.close_field()?;
@@ -258,10 +327,33 @@ impl IndexMerger {
                          indexed. Have you modified the schema?",
                 );
 
+            let doc_freq_pruning = match *field_entry.field_type() {
+                FieldType::Str(ref text_options) => text_options
+                    .get_indexing_options()
+                    .and_then(|text_indexing| text_indexing.doc_freq_pruning()),
+                _ => None,
+            };
+            let num_docs: u64 = self.readers
+                .iter()
+                .map(|reader| u64::from(reader.max_doc()))
+                .sum();
+
             while merged_terms.advance() {
                 let term_bytes: &[u8] = merged_terms.key();
 
+                if let Some(doc_freq_pruning) = doc_freq_pruning {
+                    let doc_freq: u64 = merged_terms
+                        .current_kvs()
+                        .iter()
+                        .map(|heap_item| u64::from(heap_item.streamer.value().doc_freq))
+                        .sum();
+                    if doc_freq_pruning.prunes(doc_freq, num_docs) {
+                        continue;
+                    }
+                }
+
                 // Let's compute the list of non-empty posting lists
+                let mut term_record_option = segment_postings_option;
                 let segment_postings: Vec<_> = merged_terms
                     .current_kvs()
                     .iter()
@@ -273,6 +365,12 @@ impl IndexMerger {
                         let mut segment_postings = inverted_index
                             .read_postings_from_terminfo(term_info, segment_postings_option);
                         if segment_postings.advance() {
+                            // the merged term only records the information
+                            // available in all of the segments.
+                            term_record_option = min(
+                                term_record_option,
+                                inverted_index.term_record_option(term_info),
+                            );
                             Some((segment_ord, segment_postings))
                         } else {
                             None
@@ -291,6 +389,7 @@ impl IndexMerger {
                     // We know that there is at least one document containing
                     // the term, so we add it.
                     field_serializer.new_term(term_bytes)?;
+                    field_serializer.set_term_record_option(term_record_option);
 
                     // We can now serialize this postings, by pushing each document to the
                     // postings serializer.
@@ -306,7 +405,12 @@ impl IndexMerger {
                                 // there is at least one document.
                                 let positions: &[u32] = segment_postings.positions();
                                 let term_freq = segment_postings.term_freq();
-                                let delta_positions = delta_computer.compute_delta(positions);
+                                let delta_positions = if term_record_option.has_offsets() {
+                                    let offsets = segment_postings.offsets();
+                                    delta_computer.compute_delta_with_offsets(positions, offsets)
+                                } else {
+                                    delta_computer.compute_delta(positions)
+                                };
                                 field_serializer.write_doc(
                                     remapped_doc_id,
                                     term_freq,
@@ -513,6 +617,85 @@ mod tests {
         collector.vals()
     }
 
+    #[test]
+    fn test_merge_fast_fields_by_blocks() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let id_options = schema::IntOptions::default()
+            .set_indexed()
+            .set_fast(Cardinality::SingleValue);
+        let id_field = schema_builder.add_u64_field("id", id_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for segment in 0u64..3u64 {
+            for i in segment * 1_500..(segment + 1) * 1_500 {
+                index_writer.add_document(doc!(id_field => i));
+            }
+            index_writer.commit().expect("committed");
+        }
+        for i in (0u64..4_500u64).filter(|i| i % 7 == 0) {
+            index_writer.delete_term(Term::from_field_u64(id_field, i));
+        }
+        index_writer.commit().expect("committed");
+        let segment_ids = index
+            .searchable_segment_ids()
+            .expect("Searchable segments failed.");
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let segment_reader = searcher.segment_reader(0);
+        let fast_field_reader = segment_reader.fast_field_reader::<u64>(id_field).unwrap();
+        let mut vals: Vec<u64> = (0..segment_reader.max_doc())
+            .map(|doc| fast_field_reader.get(doc))
+            .collect();
+        vals.sort();
+        let expected_vals: Vec<u64> = (0u64..4_500u64).filter(|i| i % 7 != 0).collect();
+        assert_eq!(vals, expected_vals);
+    }
+
+    #[test]
+    fn test_merge_doc_freq_pruning() {
+        let mut schema_builder = schema::SchemaBuilder::default();
+        let text_options = schema::TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+                .set_doc_freq_pruning(50, 4),
+        );
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "the a"));
+        index_writer.add_document(doc!(text_field => "the b"));
+        index_writer.commit().expect("committed");
+        index_writer.add_document(doc!(text_field => "the c"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit().expect("committed");
+        let doc_freq = |text: &str| {
+            index.load_searchers().unwrap();
+            index
+                .searcher()
+                .doc_freq(&Term::from_field_text(text_field, text))
+        };
+        // segments are only pruned when merged.
+        assert_eq!(doc_freq("the"), 3);
+        let segment_ids = index
+            .searchable_segment_ids()
+            .expect("Searchable segments failed.");
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+        // `the` appears in 75% of the documents.
+        assert_eq!(doc_freq("the"), 0);
+        assert_eq!(doc_freq("a"), 1);
+        assert_eq!(doc_freq("c"), 2);
+    }
+
     #[test]
     fn test_index_merger_with_deletes() {
         let mut schema_builder = schema::SchemaBuilder::default();
@@ -2,6 +2,7 @@ pub mod index_writer;
 pub mod segment_serializer;
 pub mod merger;
 mod merge_policy;
+mod merge_scheduler;
 mod log_merge_policy;
 mod segment_register;
 mod segment_writer;
@@ -11,19 +12,33 @@ pub mod segment_updater;
 mod directory_lock;
 mod segment_entry;
 mod doc_opstamp_mapping;
+mod doc_id_mapping;
 pub mod operation;
 mod stamper;
 mod prepared_commit;
+mod commit_handle;
+mod dedup;
+mod language_router;
 
 pub use self::prepared_commit::PreparedCommit;
+pub use self::commit_handle::CommitHandle;
+pub(crate) use self::commit_handle::CommitWatch;
+pub(crate) use self::dedup::{DedupAction, Deduplicator};
+pub use self::doc_id_mapping::DocIdMapping;
 pub use self::segment_entry::{SegmentEntry, SegmentState};
 pub use self::segment_serializer::SegmentSerializer;
 pub use self::segment_writer::SegmentWriter;
 pub use self::index_writer::IndexWriter;
+pub use self::language_router::{LanguageRouter, TokenizerRoute};
 pub use self::log_merge_policy::LogMergePolicy;
 pub use self::merge_policy::{MergeCandidate, MergePolicy, NoMergePolicy};
+pub use self::merge_scheduler::{ConcurrentMergeScheduler, MergeScheduler, PendingMerge,
+                                SerialMergeScheduler};
 pub use self::segment_manager::SegmentManager;
 pub(crate) use self::directory_lock::DirectoryLock;
 
 /// Alias for the default merge policy, which is the `LogMergePolicy`.
 pub type DefaultMergePolicy = LogMergePolicy;
+
+/// Alias for the default merge scheduler, which is the `ConcurrentMergeScheduler`.
+pub type DefaultMergeScheduler = ConcurrentMergeScheduler;
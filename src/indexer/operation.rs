@@ -1,11 +1,23 @@
+use indexer::TokenizerRoute;
+use query::Query;
 use schema::Document;
 use schema::Term;
+use std::sync::Arc;
+
+/// Documents removed by a delete operation.
+#[derive(Clone, Debug)]
+pub enum DeleteTarget {
+    /// The documents containing the term.
+    Term(Term),
+    /// The documents matching the query.
+    Query(Arc<Query + Send + Sync>),
+}
 
 /// Timestamped Delete operation.
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Debug)]
 pub struct DeleteOperation {
     pub opstamp: u64,
-    pub term: Term,
+    pub target: DeleteTarget,
 }
 
 /// Timestamped Add operation.
@@ -13,4 +25,6 @@ pub struct DeleteOperation {
 pub struct AddOperation {
     pub opstamp: u64,
     pub document: Document,
+    /// Tokenizer selected by the `LanguageRouter`, if any.
+    pub tokenizer_route: Option<Arc<TokenizerRoute>>,
 }
@@ -34,6 +34,8 @@ impl<'a> PreparedCommit<'a> {
         self.index_writer
             .segment_updater()
             .commit(self.opstamp, self.payload)?;
+        self.index_writer.on_commit(self.opstamp);
+        lifecycle_event!("commit", opstamp = self.opstamp);
         Ok(self.opstamp)
     }
 }
@@ -18,7 +18,13 @@ pub struct SegmentSerializer {
 impl SegmentSerializer {
     /// Creates a new `SegmentSerializer`.
     pub fn for_segment(segment: &mut Segment) -> Result<SegmentSerializer> {
+        let index_settings = segment.index().settings().clone();
         let store_write = segment.open_write(SegmentComponent::STORE)?;
+        let store_writer = StoreWriter::with_compressor(
+            store_write,
+            index_settings.docstore_compression,
+            index_settings.docstore_blocksize,
+        );
 
         let fast_field_write = segment.open_write(SegmentComponent::FASTFIELDS)?;
         let fast_field_serializer = FastFieldSerializer::from_write(fast_field_write)?;
@@ -29,7 +35,7 @@ impl SegmentSerializer {
         let postings_serializer = InvertedIndexSerializer::open(segment)?;
         Ok(SegmentSerializer {
             postings_serializer,
-            store_writer: StoreWriter::new(store_write),
+            store_writer,
             fast_field_serializer,
             fieldnorms_serializer,
         })
@@ -1,6 +1,6 @@
 use core::Index;
-use core::IndexMeta;
-use core::META_FILEPATH;
+use core::{IndexMeta, IndexSettings};
+use core::{commit_meta_filepath, META_FILEPATH};
 use core::Segment;
 use core::SegmentId;
 use core::SegmentMeta;
@@ -12,28 +12,27 @@ use futures_cpupool::CpuPool;
 use futures::Future;
 use futures::Canceled;
 use futures::oneshot;
+use futures::sync::oneshot::Sender;
 use directory::FileProtection;
 use indexer::{DefaultMergePolicy, MergePolicy};
+use indexer::{DefaultMergeScheduler, MergeScheduler, PendingMerge};
+use indexer::merge_scheduler::MergeTracker;
 use indexer::index_writer::advance_deletes;
 use indexer::MergeCandidate;
 use indexer::merger::IndexMerger;
-use indexer::SegmentEntry;
+use indexer::{DocIdMapping, SegmentEntry};
 use indexer::SegmentSerializer;
 use futures_cpupool::CpuFuture;
 use serde_json;
 use indexer::delete_queue::DeleteCursor;
 use schema::Schema;
 use std::borrow::BorrowMut;
-use std::collections::HashMap;
 use std::io::Write;
-use std::mem;
-use std::ops::DerefMut;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::atomic::Ordering;
 use std::sync::RwLock;
-use std::thread;
-use std::thread::JoinHandle;
+use time::PreciseTime;
 use super::segment_manager::{get_mergeable_segments, SegmentManager};
 
 /// Save the index meta file.
@@ -45,8 +44,13 @@ use super::segment_manager::{get_mergeable_segments, SegmentManager};
 /// and flushed.
 ///
 /// This method is not part of tantivy's public API
-pub fn save_new_metas(schema: Schema, opstamp: u64, directory: &mut Directory) -> Result<()> {
-    save_metas(vec![], schema, opstamp, None, directory)
+pub fn save_new_metas(
+    schema: Schema,
+    index_settings: IndexSettings,
+    opstamp: u64,
+    directory: &mut Directory,
+) -> Result<()> {
+    save_metas(vec![], schema, index_settings, opstamp, None, directory)
 }
 
 /// Save the index meta file.
@@ -57,19 +61,48 @@ pub fn save_new_metas(schema: Schema, opstamp: u64, directory: &mut Directory) -
 /// - it success, and `meta.json` is written
 /// and flushed.
 ///
+/// If the settings keep previous commits, the former `meta.json`
+/// is first copied to `meta.{generation}.json`, unless it has the
+/// same opstamp: merges rewrite the `meta.json` of the last commit
+/// without creating a new commit.
+///
 /// This method is not part of tantivy's public API
 pub fn save_metas(
     segment_metas: Vec<SegmentMeta>,
     schema: Schema,
+    index_settings: IndexSettings,
     opstamp: u64,
     payload: Option<String>,
     directory: &mut Directory,
 ) -> Result<()> {
+    // the generation of a new index is 1.
+    let mut previous_generation = 0u64;
+    let mut previous_generations = Vec::new();
+    if let Ok(meta_data) = directory.atomic_read(&META_FILEPATH) {
+        if let Ok(previous_metas) = serde_json::from_slice::<IndexMeta>(&meta_data) {
+            previous_generation = previous_metas.generation;
+            previous_generations = previous_metas.previous_generations;
+            if index_settings.num_kept_commits > 0 && previous_metas.opstamp != opstamp {
+                let commit_meta_path = commit_meta_filepath(previous_generation);
+                directory.atomic_write(&commit_meta_path, &meta_data)?;
+                previous_generations.push(previous_generation);
+            }
+        }
+    }
+    // the files of the commits falling out of the history
+    // are left to the garbage collection.
+    let num_discarded_generations = previous_generations
+        .len()
+        .saturating_sub(index_settings.num_kept_commits);
+    previous_generations.drain(..num_discarded_generations);
     let metas = IndexMeta {
         segments: segment_metas,
         schema,
+        index_settings,
         opstamp,
         payload,
+        generation: previous_generation + 1,
+        previous_generations,
     };
     let mut buffer = serde_json::to_vec_pretty(&metas)?;
     write!(&mut buffer, "\n")?;
@@ -91,9 +124,18 @@ fn perform_merge(
     segment_updater: &SegmentUpdater,
     mut merged_segment: Segment,
     target_opstamp: u64,
-) -> Result<SegmentEntry> {
+) -> Result<(SegmentEntry, DocIdMapping)> {
     // first we need to apply deletes to our segment.
     info!("Start merge: {:?}", segment_ids);
+    let start = PreciseTime::now();
+    lifecycle_event!(
+        "merge_started",
+        segments = segment_ids
+            .iter()
+            .map(|segment_id| segment_id.short_uuid_string())
+            .collect::<Vec<_>>(),
+        target_opstamp = target_opstamp
+    );
 
     let index = &segment_updater.0.index;
     let schema = index.schema();
@@ -134,17 +176,36 @@ fn perform_merge(
     // ... we just serialize this index merger in our new segment
     // to merge the two segments.
 
-    let segment_serializer = SegmentSerializer::for_segment(&mut merged_segment)
-        .expect("Creating index serializer failed");
+    let doc_id_mapping = merger.doc_id_mapping(merged_segment.id());
+    let segment_serializer = SegmentSerializer::for_segment(&mut merged_segment)?;
 
-    let num_docs = merger
-        .write(segment_serializer)
-        .expect("Serializing merged index failed");
+    let num_docs = merger.write(segment_serializer)?;
     let mut segment_meta = SegmentMeta::new(merged_segment.id());
     segment_meta.set_max_doc(num_docs);
+    if let Some(create_opstamp) = segment_entries
+        .iter()
+        .filter_map(|segment_entry| segment_entry.meta().create_opstamp())
+        .max()
+    {
+        segment_meta.set_create_opstamp(create_opstamp);
+    }
+    lifecycle_event!(
+        "merge_finished",
+        segment = segment_meta.id().short_uuid_string(),
+        num_docs = num_docs,
+        num_bytes = index
+            .segment(segment_meta.clone())
+            .info()
+            .map(|segment_info| segment_info.num_bytes())
+            .unwrap_or(0),
+        duration_ms = start.to(PreciseTime::now()).num_milliseconds()
+    );
+
+    index.finalize_new_segment(&mut segment_meta)?;
+    index.warm_new_segment(&index.segment(segment_meta.clone()))?;
 
     let after_merge_segment_entry = SegmentEntry::new(segment_meta.clone(), delete_cursor, None);
-    Ok(after_merge_segment_entry)
+    Ok((after_merge_segment_entry, doc_id_mapping))
 }
 
 struct InnerSegmentUpdater {
@@ -152,8 +213,8 @@ struct InnerSegmentUpdater {
     index: Index,
     segment_manager: SegmentManager,
     merge_policy: RwLock<Box<MergePolicy>>,
-    merging_thread_id: AtomicUsize,
-    merging_threads: RwLock<HashMap<usize, JoinHandle<Result<()>>>>,
+    merge_scheduler: RwLock<Box<MergeScheduler>>,
+    merge_tracker: MergeTracker,
     generation: AtomicUsize,
     killed: AtomicBool,
     stamper: Stamper,
@@ -172,8 +233,8 @@ impl SegmentUpdater {
             index,
             segment_manager,
             merge_policy: RwLock::new(box DefaultMergePolicy::default()),
-            merging_thread_id: AtomicUsize::default(),
-            merging_threads: RwLock::new(HashMap::new()),
+            merge_scheduler: RwLock::new(box DefaultMergeScheduler::default()),
+            merge_tracker: MergeTracker::default(),
             generation: AtomicUsize::default(),
             killed: AtomicBool::new(false),
             stamper,
@@ -195,8 +256,12 @@ impl SegmentUpdater {
         *self.0.merge_policy.write().unwrap() = merge_policy;
     }
 
-    fn get_merging_thread_id(&self) -> usize {
-        self.0.merging_thread_id.fetch_add(1, Ordering::SeqCst)
+    pub fn set_merge_scheduler(&self, merge_scheduler: Box<MergeScheduler>) {
+        *self.0.merge_scheduler.write().unwrap() = merge_scheduler;
+    }
+
+    pub(crate) fn merge_tracker(&self) -> &MergeTracker {
+        &self.0.merge_tracker
     }
 
     fn run_async<T: 'static + Send, F: 'static + Send + FnOnce(SegmentUpdater) -> T>(
@@ -248,6 +313,7 @@ impl SegmentUpdater {
             save_metas(
                 self.0.segment_manager.committed_segment_metas(),
                 index.schema(),
+                index.settings().clone(),
                 opstamp,
                 commit_message,
                 directory.box_clone().borrow_mut(),
@@ -255,6 +321,27 @@ impl SegmentUpdater {
         }
     }
 
+    /// Publishes the segments of a previous commit as a new commit,
+    /// and kills the segment updater, as the segments it manages
+    /// are not the committed ones anymore.
+    pub fn restore_commit(&self, commit_metas: IndexMeta, opstamp: u64) -> Result<()> {
+        self.run_async(move |mut segment_updater| {
+            if !segment_updater.is_alive() {
+                return Ok(());
+            }
+            segment_updater.kill();
+            let index = &segment_updater.0.index;
+            save_metas(
+                commit_metas.segments,
+                index.schema(),
+                index.settings().clone(),
+                opstamp,
+                commit_metas.payload,
+                index.directory().box_clone().borrow_mut(),
+            )
+        }).wait()?
+    }
+
     pub fn garbage_collect_files(&self) -> Result<()> {
         self.run_async(move |segment_updater| {
             segment_updater.garbage_collect_files_exec();
@@ -264,9 +351,38 @@ impl SegmentUpdater {
     fn garbage_collect_files_exec(&self) {
         info!("Running garbage collection");
         let mut index = self.0.index.clone();
-        index
-            .directory_mut()
-            .garbage_collect(|| self.0.segment_manager.list_files());
+        let pinned_files = index.pinned_files();
+        // held until the files are deleted: the readers cannot
+        // check their leases in the meantime.
+        let _gc_lock = match index.lock_garbage_collection() {
+            Ok(gc_lock) => gc_lock,
+            Err(e) => {
+                error!("Failed to lock the reader leases, skipping garbage collection. {:?}", e);
+                return;
+            }
+        };
+        let leased_files = match index.leased_files() {
+            Ok(leased_files) => leased_files,
+            Err(e) => {
+                // deleting files a reader may be using is not an option.
+                error!("Failed to read the reader leases, skipping garbage collection. {:?}", e);
+                return;
+            }
+        };
+        let kept_commit_files = match index.kept_commit_files() {
+            Ok(kept_commit_files) => kept_commit_files,
+            Err(e) => {
+                error!("Failed to read the kept commits, skipping garbage collection. {:?}", e);
+                return;
+            }
+        };
+        index.directory_mut().garbage_collect(|| {
+            let mut living_files = self.0.segment_manager.list_files();
+            living_files.extend(pinned_files);
+            living_files.extend(leased_files);
+            living_files.extend(kept_commit_files);
+            living_files
+        });
     }
 
     pub fn commit(&self, opstamp: u64, payload: Option<String>) -> Result<()> {
@@ -288,67 +404,77 @@ impl SegmentUpdater {
         segment_ids: &[SegmentId],
     ) -> impl Future<Item = SegmentMeta, Error = Canceled> {
         self.0.segment_manager.start_merge(segment_ids);
-        let segment_updater_clone = self.clone();
-
-        let segment_ids_vec = segment_ids.to_vec();
 
-        let merging_thread_id = self.get_merging_thread_id();
         let (merging_future_send, merging_future_recv) = oneshot();
 
         if segment_ids.is_empty() {
             return merging_future_recv;
         }
 
+        let num_docs = segment_ids
+            .iter()
+            .filter_map(|segment_id| self.0.segment_manager.segment_entry(segment_id))
+            .map(|segment_entry| u64::from(segment_entry.meta().num_docs()))
+            .sum();
         let target_opstamp = self.0.stamper.stamp();
-        let merging_join_handle = thread::spawn(move || {
-            // first we need to apply deletes to our segment.
-            let merged_segment = segment_updater_clone.new_segment();
-            let merged_segment_id = merged_segment.id();
-            let merge_result = perform_merge(
-                &segment_ids_vec,
-                &segment_updater_clone,
-                merged_segment,
-                target_opstamp,
-            );
+        let merged_segment = self.new_segment();
+        let pending_merge = PendingMerge::new(
+            self.clone(),
+            segment_ids.to_vec(),
+            num_docs,
+            target_opstamp,
+            merged_segment,
+            merging_future_send,
+        );
+        self.0
+            .merge_scheduler
+            .read()
+            .unwrap()
+            .schedule(pending_merge);
+        merging_future_recv
+    }
 
-            match merge_result {
-                Ok(after_merge_segment_entry) => {
-                    let merged_segment_meta = after_merge_segment_entry.meta().clone();
-                    segment_updater_clone
-                        .end_merge(segment_ids_vec, after_merge_segment_entry)
-                        .expect("Segment updater thread is corrupted.");
-
-                    // the future may fail if the listener of the oneshot future
-                    // has been destroyed.
-                    //
-                    // This is not a problem here, so we just ignore any
-                    // possible error.
-                    let _merging_future_res = merging_future_send.send(merged_segment_meta);
+    /// Executes a merge, and publishes the merged segment.
+    ///
+    /// This is called by `PendingMerge::run`, on a thread
+    /// chosen by the `MergeScheduler`.
+    pub(crate) fn run_merge(
+        &self,
+        segment_ids: &[SegmentId],
+        merged_segment: Segment,
+        target_opstamp: u64,
+        merging_future_send: Sender<SegmentMeta>,
+    ) {
+        // first we need to apply deletes to our segment.
+        let merged_segment_id = merged_segment.id();
+        let merge_result = perform_merge(segment_ids, self, merged_segment, target_opstamp);
+
+        match merge_result {
+            Ok((after_merge_segment_entry, doc_id_mapping)) => {
+                let merged_segment_meta = after_merge_segment_entry.meta().clone();
+                let merged = self.end_merge(segment_ids.to_vec(), after_merge_segment_entry)
+                    .expect("Segment updater thread is corrupted.");
+                if merged {
+                    self.0.index.notify_merge_listeners(&doc_id_mapping);
                 }
-                Err(e) => {
-                    error!("Merge of {:?} was cancelled: {:?}", segment_ids_vec, e);
-                    // ... cancel merge
-                    if cfg!(test) {
-                        panic!("Merge failed.");
-                    }
-                    segment_updater_clone.cancel_merge(&segment_ids_vec, merged_segment_id);
-                    // merging_future_send will be dropped, sending an error to the future.
+
+                // the future may fail if the listener of the oneshot future
+                // has been destroyed.
+                //
+                // This is not a problem here, so we just ignore any
+                // possible error.
+                let _merging_future_res = merging_future_send.send(merged_segment_meta);
+            }
+            Err(e) => {
+                error!("Merge of {:?} was cancelled: {:?}", segment_ids, e);
+                // ... cancel merge
+                if cfg!(test) {
+                    panic!("Merge failed.");
                 }
+                self.cancel_merge(segment_ids, merged_segment_id);
+                // merging_future_send will be dropped, sending an error to the future.
             }
-            segment_updater_clone
-                .0
-                .merging_threads
-                .write()
-                .unwrap()
-                .remove(&merging_thread_id);
-            Ok(())
-        });
-        self.0
-            .merging_threads
-            .write()
-            .unwrap()
-            .insert(merging_thread_id, merging_join_handle);
-        merging_future_recv
+        }
     }
 
     fn consider_merge_options(&self) {
@@ -360,12 +486,36 @@ impl SegmentUpdater {
         let mut merge_candidates = merge_policy.compute_merge_candidates(&uncommitted_segments);
         let committed_merge_candidates = merge_policy.compute_merge_candidates(&committed_segments);
         merge_candidates.extend_from_slice(&committed_merge_candidates[..]);
-        for MergeCandidate(segment_metas) in merge_candidates {
-            self.start_merge(&segment_metas);
+        let mut remaining_size_budget = self.0.index.remaining_size_budget();
+        for MergeCandidate(segment_ids) in merge_candidates {
+            if let Some(remaining) = remaining_size_budget {
+                // the merged segment is at most as large as the segments it replaces.
+                let projected_size = self.projected_merge_size(&segment_ids);
+                if projected_size > remaining {
+                    warn!(
+                        "Not merging {:?}: the merged segment may exceed the size budget.",
+                        segment_ids
+                    );
+                    continue;
+                }
+                remaining_size_budget = Some(remaining - projected_size);
+            }
+            self.start_merge(&segment_ids);
         }
     }
 
-    fn cancel_merge(
+    /// Returns the sum of the size of the files of the given segments.
+    fn projected_merge_size(&self, segment_ids: &[SegmentId]) -> u64 {
+        let directory = self.0.index.directory();
+        segment_ids
+            .iter()
+            .filter_map(|segment_id| self.0.segment_manager.segment_entry(segment_id))
+            .flat_map(|segment_entry| segment_entry.meta().list_files())
+            .filter_map(|path| directory.file_size(&path))
+            .sum()
+    }
+
+    pub(crate) fn cancel_merge(
         &self,
         before_merge_segment_ids: &[SegmentId],
         after_merge_segment_entry: SegmentId,
@@ -379,7 +529,7 @@ impl SegmentUpdater {
         &self,
         before_merge_segment_ids: Vec<SegmentId>,
         mut after_merge_segment_entry: SegmentEntry,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         self.run_async(move |segment_updater| {
             info!("End merge {:?}", after_merge_segment_entry.meta());
             let mut delete_cursor = after_merge_segment_entry.delete_cursor().clone();
@@ -415,7 +565,7 @@ impl SegmentUpdater {
                                 &before_merge_segment_ids,
                                 after_merge_segment_entry.segment_id(),
                             );
-                            return;
+                            return false;
                         }
                     }
                 }
@@ -429,6 +579,7 @@ impl SegmentUpdater {
             let previous_metas = segment_updater.0.index.load_metas().unwrap();
             segment_updater.save_metas(previous_metas.opstamp, previous_metas.payload);
             segment_updater.garbage_collect_files_exec();
+            true
         }).wait()
     }
 
@@ -452,17 +603,9 @@ impl SegmentUpdater {
         loop {
             num_segments = self.0.segment_manager.num_segments();
 
-            let mut new_merging_threads = HashMap::new();
-            {
-                let mut merging_threads = self.0.merging_threads.write().unwrap();
-                mem::swap(&mut new_merging_threads, merging_threads.deref_mut());
-            }
-            debug!("wait merging thread {}", new_merging_threads.len());
-            for (_, merging_thread_handle) in new_merging_threads {
-                merging_thread_handle
-                    .join()
-                    .map(|_| ())
-                    .map_err(|_| ErrorKind::ErrorInThread("Merging thread failed.".into()))?;
+            debug!("wait merging threads");
+            if self.0.merge_tracker.wait() {
+                bail!(ErrorKind::ErrorInThread("Merging thread failed.".into()));
             }
             // Our merging thread may have queued their completed
             self.run_async(move |_| {}).wait()?;
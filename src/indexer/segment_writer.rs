@@ -16,9 +16,11 @@ use indexer::index_writer::MARGIN_IN_BYTES;
 use super::operation::AddOperation;
 use postings::MultiFieldPostingsWriter;
 use tokenizer::BoxedTokenizer;
+use tokenizer::TokenizerManager;
 use tokenizer::FacetTokenizer;
 use tokenizer::{TokenStream, Tokenizer};
 use schema::Value;
+use schema::{Document, FieldValue, StoredValueLimit, StoredValueOverflow, TextOptions};
 
 /// A `SegmentWriter` is in charge of creating segment index from a
 /// documents.
@@ -34,6 +36,13 @@ pub struct SegmentWriter<'a> {
     fieldnorms_writer: FastFieldsWriter,
     doc_opstamps: Vec<u64>,
     tokenizers: Vec<Option<Box<BoxedTokenizer>>>,
+    tokenizer_manager: TokenizerManager,
+    // tokenizers selected by the `LanguageRouter`, by name.
+    routed_tokenizers: HashMap<String, Option<Box<BoxedTokenizer>>>,
+    // u64 fields filled with the opstamp of the documents.
+    opstamp_fields: Vec<Field>,
+    // options of the text fields, capping the length of their stored values.
+    text_options: Vec<Option<TextOptions>>,
 }
 
 fn create_fieldnorms_writer(schema: &Schema) -> FastFieldsWriter {
@@ -79,6 +88,25 @@ impl<'a> SegmentWriter<'a> {
                 _ => None,
             })
             .collect();
+        let tokenizer_manager = segment.index().tokenizers().clone();
+        let opstamp_fields = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|&(_, field_entry)| match *field_entry.field_type() {
+                FieldType::U64(ref int_options) => int_options.is_opstamp(),
+                _ => false,
+            })
+            .map(|(field_id, _)| Field(field_id as u32))
+            .collect();
+        let text_options = schema
+            .fields()
+            .iter()
+            .map(|field_entry| match *field_entry.field_type() {
+                FieldType::Str(ref text_options) => Some(text_options.clone()),
+                _ => None,
+            })
+            .collect();
         Ok(SegmentWriter {
             heap,
             max_doc: 0,
@@ -88,6 +116,10 @@ impl<'a> SegmentWriter<'a> {
             fast_field_writers: FastFieldsWriter::from_schema(schema),
             doc_opstamps: Vec::with_capacity(1_000),
             tokenizers,
+            tokenizer_manager,
+            routed_tokenizers: HashMap::new(),
+            opstamp_fields,
+            text_options,
         })
     }
 
@@ -126,11 +158,31 @@ impl<'a> SegmentWriter<'a> {
     /// Indexes a new document
     ///
     /// As a user, you should rather use `IndexWriter`'s add_document.
+    ///
+    /// Documents with a stored value exceeding a `StoredValueLimit`
+    /// in `StoredValueOverflow::Reject` mode are skipped.
     pub fn add_document(&mut self, add_operation: AddOperation, schema: &Schema) -> io::Result<()> {
+        if let Some(field) = self.rejecting_field(&add_operation.document) {
+            warn!(
+                "Rejecting document {}: its value of field {:?} is too long to be stored",
+                add_operation.opstamp,
+                schema.get_field_name(field)
+            );
+            return Ok(());
+        }
         let doc_id = self.max_doc;
         let mut doc = add_operation.document;
+        let tokenizer_route = add_operation.tokenizer_route;
         self.doc_opstamps.push(add_operation.opstamp);
 
+        if !self.opstamp_fields.is_empty() {
+            let opstamp_fields = &self.opstamp_fields;
+            doc.filter_fields(|field| !opstamp_fields.contains(&field));
+            for &field in opstamp_fields {
+                doc.add_u64(field, add_operation.opstamp);
+            }
+        }
+
         self.fast_field_writers.add_document(&doc);
 
         for (field, field_values) in doc.get_sorted_field_values() {
@@ -169,9 +221,26 @@ impl<'a> SegmentWriter<'a> {
                     }
                 }
                 FieldType::Str(_) => {
-                    let num_tokens = if let Some(ref mut tokenizer) =
-                        self.tokenizers[field.0 as usize]
-                    {
+                    let routed_tokenizer_name = tokenizer_route.as_ref().and_then(|route| {
+                        if route.fields.contains(&field) {
+                            Some(route.tokenizer_name.as_str())
+                        } else {
+                            None
+                        }
+                    });
+                    let field_tokenizer = self.tokenizers[field.0 as usize].as_ref();
+                    let tokenizer_opt = match routed_tokenizer_name {
+                        Some(tokenizer_name) => {
+                            let tokenizer_manager = &self.tokenizer_manager;
+                            self.routed_tokenizers
+                                .entry(tokenizer_name.to_string())
+                                .or_insert_with(|| tokenizer_manager.get(tokenizer_name))
+                                .as_ref()
+                                .or(field_tokenizer)
+                        }
+                        None => field_tokenizer,
+                    };
+                    let num_tokens = if let Some(tokenizer) = tokenizer_opt {
                         let texts: Vec<&str> = field_values
                             .iter()
                             .flat_map(|field_value| match *field_value.value() {
@@ -217,16 +286,85 @@ impl<'a> SegmentWriter<'a> {
                         }
                     }
                 }
+                FieldType::Date(ref int_option) => {
+                    if int_option.is_indexed() {
+                        for field_value in field_values {
+                            let term = Term::from_field_date(
+                                field_value.field(),
+                                &field_value.value().date_value(),
+                            );
+                            self.multifield_postings.subscribe(doc_id, &term);
+                        }
+                    }
+                }
+                FieldType::F64(ref int_option) => {
+                    if int_option.is_indexed() {
+                        for field_value in field_values {
+                            let term = Term::from_field_f64(
+                                field_value.field(),
+                                field_value.value().f64_value(),
+                            );
+                            self.multifield_postings.subscribe(doc_id, &term);
+                        }
+                    }
+                }
             }
         }
         self.fieldnorms_writer.fill_val_up_to(doc_id);
         doc.filter_fields(|field| schema.get_field_entry(field).is_stored());
+        self.limit_stored_values(&mut doc);
         let doc_writer = self.segment_serializer.get_store_writer();
         doc_writer.store(&doc)?;
         self.max_doc += 1;
         Ok(())
     }
 
+    /// Returns the stored values of the document exceeding
+    /// the limit of their field, together with the limit.
+    fn exceeded_limits<'b>(
+        &'b self,
+        doc: &'b Document,
+    ) -> impl Iterator<Item = (&'b FieldValue, StoredValueLimit)> + 'b {
+        doc.field_values().iter().filter_map(move |field_value| {
+            self.exceeded_limit(field_value)
+                .map(|limit| (field_value, limit))
+        })
+    }
+
+    /// Returns the limit exceeded by the stored value, if any.
+    fn exceeded_limit(&self, field_value: &FieldValue) -> Option<StoredValueLimit> {
+        self.text_options
+            .get(field_value.field().0 as usize)
+            .and_then(|text_options_opt| text_options_opt.as_ref())
+            .and_then(|text_options| text_options.exceeded_stored_value_limit(field_value.value()))
+    }
+
+    /// Returns the field whose value should make the document rejected, if any.
+    fn rejecting_field(&self, doc: &Document) -> Option<Field> {
+        self.exceeded_limits(doc)
+            .find(|&(_, limit)| limit.overflow == StoredValueOverflow::Reject)
+            .map(|(field_value, _)| field_value.field())
+    }
+
+    /// Truncates or hashes the stored values exceeding their limit.
+    fn limit_stored_values(&self, doc: &mut Document) {
+        if self.exceeded_limits(doc).next().is_none() {
+            return;
+        }
+        let field_values: Vec<FieldValue> = doc.field_values()
+            .iter()
+            .map(|field_value| {
+                match (self.exceeded_limit(field_value), field_value.value()) {
+                    (Some(limit), &Value::Str(ref text)) => {
+                        FieldValue::new(field_value.field(), Value::Str(limit.limited_value(text)))
+                    }
+                    _ => field_value.clone(),
+                }
+            })
+            .collect();
+        *doc = Document::from(field_values);
+    }
+
     /// Max doc is
     /// - the number of documents in the segment assuming there is no deletes
     /// - the maximum document id (including deleted documents) + 1
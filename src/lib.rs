@@ -134,8 +134,10 @@ extern crate bit_set;
 extern crate byteorder;
 extern crate chan;
 extern crate combine;
+extern crate crc;
 extern crate crossbeam;
 extern crate fst;
+extern crate fst_regex;
 extern crate futures;
 extern crate futures_cpupool;
 extern crate itertools;
@@ -159,13 +161,19 @@ extern crate matches;
 #[cfg(test)]
 extern crate env_logger;
 
-#[cfg(feature = "simdcompression")]
+#[cfg(any(feature = "simdcompression", feature = "madvise"))]
 extern crate libc;
 
+#[cfg(feature = "encryption")]
+extern crate ring;
+
+#[cfg(feature = "zstd-compression")]
+extern crate zstd;
+
 #[cfg(windows)]
 extern crate winapi;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "test-support"))]
 extern crate rand;
 #[cfg(test)]
 extern crate test;
@@ -204,15 +212,24 @@ pub mod collector;
 pub mod postings;
 pub mod schema;
 pub mod fastfield;
+pub mod snippet;
+
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 mod docset;
 pub use self::docset::{DocSet, SkipResult};
 
 pub use directory::Directory;
-pub use core::{Index, Searcher, Segment, SegmentId, SegmentMeta};
-pub use indexer::IndexWriter;
-pub use schema::{Document, Term};
-pub use core::{InvertedIndexReader, SegmentReader};
+pub use core::{DedupMode, DedupSettings, Index, IndexSettings, MultiSearcher, SearchOutcome,
+               SearchPreset, Searcher, Segment, SegmentId, SegmentInfo, SegmentMeta};
+pub use indexer::{CommitHandle, DocIdMapping, IndexWriter, LanguageRouter};
+pub use schema::{DateTime, Document, Term};
+pub use core::{CustomComponentWriter, DocExport, Executor, InvertedIndexReader, MergeListener,
+               ScoredDocs, SearcherToken, SegmentReader, TermsByDocFreq, Warmer};
+pub use core::{FieldBounds, FieldSpaceUsage, LostSegment, ReaderLease, RepairReport, SpaceUsage};
+pub use core::{MemoryAccountant, MemoryCap, MemoryComponent, MemoryLimiter, MemoryReservation,
+               MemoryUsage};
 pub use self::common::TimerTree;
 
 pub use postings::Postings;
@@ -238,6 +255,15 @@ pub mod merge_policy {
     pub use indexer::DefaultMergePolicy;
 }
 
+/// Defines when and where tantivy's merges are executed
+pub mod merge_scheduler {
+    pub use indexer::MergeScheduler;
+    pub use indexer::PendingMerge;
+    pub use indexer::ConcurrentMergeScheduler;
+    pub use indexer::SerialMergeScheduler;
+    pub use indexer::DefaultMergeScheduler;
+}
+
 /// A `u32` identifying a document within a segment.
 /// Documents have their `DocId` assigned incrementally,
 /// as they are added in the segment.
@@ -285,13 +311,24 @@ mod tests {
 
     use collector::tests::TestCollector;
     use Index;
+    use IndexSettings;
+    use SearchOutcome;
+    use DocId;
+    use store::Compressor;
     use core::SegmentReader;
+    use SegmentComponent;
+    use directory::Directory;
+    use error::ErrorKind;
     use query::BooleanQuery;
     use schema::*;
     use docset::DocSet;
     use IndexWriter;
     use Postings;
     use rand::{Rng, SeedableRng, XorShiftRng};
+    use serde_json;
+    use std::thread;
+    use std::time::Duration;
+    use tempdir::TempDir;
     use rand::distributions::{IndependentSample, Range};
 
     fn generate_array_with_seed(n: usize, ratio: f32, seed_val: u32) -> Vec<u32> {
@@ -889,4 +926,2045 @@ mod tests {
             assert_eq!(fast_field_reader.get(0), 4i64)
         }
     }
+
+    #[cfg(not(feature = "zstd-compression"))]
+    #[test]
+    fn test_index_settings_unsupported_compression() {
+        let settings = IndexSettings {
+            docstore_compression: Compressor::Zstd,
+            ..IndexSettings::default()
+        };
+        let tempdir = TempDir::new("index").unwrap();
+        let result = Index::create_with_settings(
+            tempdir.path(),
+            SchemaBuilder::default().build(),
+            settings,
+        );
+        match result.map(|_| ()).unwrap_err().kind() {
+            &ErrorKind::InvalidArgument(_) => {}
+            _ => panic!("Expected an invalid argument error."),
+        }
+        assert!(!tempdir.path().join("meta.json").exists());
+    }
+
+    #[test]
+    fn test_index_settings_docstore_compression() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let settings = IndexSettings {
+            docstore_compression: Compressor::None,
+            docstore_blocksize: 100,
+            ..IndexSettings::default()
+        };
+        let index = Index::create_in_ram_with_settings(schema_builder.build(), settings);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..50 {
+                index_writer.add_document(doc!(text_field => format!("doc {}", i)));
+            }
+            index_writer.commit().unwrap();
+        }
+        let metas = index.load_metas().unwrap();
+        assert_eq!(metas.index_settings.docstore_compression, Compressor::None);
+        assert_eq!(metas.index_settings.docstore_blocksize, 100);
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader: &SegmentReader = searcher.segment_reader(0);
+        assert_eq!(
+            segment_reader.get_store_reader().compressor(),
+            Compressor::None
+        );
+        let doc = segment_reader.doc(42).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), "doc 42");
+    }
+
+    #[test]
+    fn test_index_validate() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let mut index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b c"));
+            index_writer.add_document(doc!(text_field => "b c d"));
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "a"));
+            index_writer.commit().unwrap();
+        }
+        assert!(index.validate().is_ok());
+        let segment = index.searchable_segments().unwrap().pop().unwrap();
+        let path = segment.relative_path(SegmentComponent::STORE);
+        let mut data: Vec<u8> = index.directory().open_read(&path).unwrap().as_slice().to_owned();
+        data[0] ^= 1u8;
+        index.directory_mut().delete(&path).unwrap();
+        index.directory_mut().atomic_write(&path, &data).unwrap();
+        let error = index.validate().unwrap_err();
+        assert_eq!(error.path(), Some(path.as_path()));
+        match *error.kind() {
+            ErrorKind::CorruptedFile(ref corrupted_path, _) => assert_eq!(corrupted_path, &path),
+            _ => panic!("Expected a CorruptedFile error"),
+        }
+        // truncated files are detected when opening the segment.
+        index.directory_mut().delete(&path).unwrap();
+        index.directory_mut().atomic_write(&path, &data[..data.len() - 1]).unwrap();
+        assert!(index.load_searchers().is_err());
+    }
+
+    #[test]
+    fn test_index_repair() {
+        use indexer::NoMergePolicy;
+        use SegmentId;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let mut index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.set_merge_policy(box NoMergePolicy);
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+            index_writer.wait_merging_threads().unwrap();
+        }
+        assert!(index.repair().unwrap().is_healthy());
+        let segments = index.searchable_segments().unwrap();
+        assert_eq!(segments.len(), 3);
+        // truncates the store of a segment, and removes the postings of another one.
+        let store_path = segments[0].relative_path(SegmentComponent::STORE);
+        let data: Vec<u8> = index
+            .directory()
+            .open_read(&store_path)
+            .unwrap()
+            .as_slice()
+            .to_owned();
+        index.directory_mut().delete(&store_path).unwrap();
+        index
+            .directory_mut()
+            .atomic_write(&store_path, &data[..data.len() / 2])
+            .unwrap();
+        let postings_path = segments[1].relative_path(SegmentComponent::POSTINGS);
+        index.directory_mut().delete(&postings_path).unwrap();
+        {
+            let _index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            match *index.repair().unwrap_err().kind() {
+                ErrorKind::LockBusy(_) => {}
+                _ => panic!("Expected a LockBusy error"),
+            }
+        }
+        let report = index.repair().unwrap();
+        assert_eq!(report.lost_segments.len(), 2);
+        let lost_segment_ids: Vec<SegmentId> = report
+            .lost_segments
+            .iter()
+            .map(|lost_segment| lost_segment.segment_id)
+            .collect();
+        assert!(lost_segment_ids.contains(&segments[0].id()));
+        assert!(lost_segment_ids.contains(&segments[1].id()));
+        assert_eq!(
+            report.num_lost_docs(),
+            u64::from(segments[0].meta().num_docs() + segments[1].meta().num_docs())
+        );
+        assert_eq!(index.searchable_segment_ids().unwrap(), vec![segments[2].id()]);
+        assert!(index.validate().is_ok());
+        assert_eq!(
+            u64::from(index.searcher().num_docs()),
+            4 - report.num_lost_docs()
+        );
+        assert!(index.repair().unwrap().is_healthy());
+    }
+
+    #[test]
+    fn test_index_to_bytes() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b c"));
+            index_writer.add_document(doc!(text_field => "b c d"));
+            index_writer.commit().unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "a"));
+            index_writer.commit().unwrap();
+            index.load_searchers().unwrap();
+            // not visible to the searchers, hence not serialized.
+            index_writer.add_document(doc!(text_field => "e f"));
+            index_writer.commit().unwrap();
+        }
+        let data = index.to_bytes().unwrap();
+        assert!(index.pinned_files().is_empty());
+        let loaded_index = Index::from_bytes(&data).unwrap();
+        assert!(loaded_index.validate().is_ok());
+        let searcher = loaded_index.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+        let segment_reader: &SegmentReader = searcher.segment_reader(0);
+        let doc = segment_reader.doc(1).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), "b c d");
+        assert!(Index::from_bytes(&data[..data.len() - 1]).is_err());
+        assert!(Index::from_bytes(b"not an index").is_err());
+        // the loaded files are managed, and get garbage collected.
+        let delete_path = segment_reader
+            .segment_meta()
+            .relative_path(SegmentComponent::DELETE);
+        assert!(loaded_index.directory().exists(&delete_path));
+        {
+            let mut index_writer = loaded_index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.delete_term(Term::from_field_text(text_field, "b"));
+            index_writer.commit().unwrap();
+            index_writer.garbage_collect_files().unwrap();
+        }
+        assert!(!loaded_index.directory().exists(&delete_path));
+    }
+
+    #[test]
+    fn test_reload_searchers_on_commit() {
+        use std::sync::Mutex;
+        use std::sync::mpsc;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        index
+            .add_warmer(Box::new(move |segment_reader: &SegmentReader| {
+                let _ = sender.lock().unwrap().send(segment_reader.num_docs());
+                Ok(())
+            }))
+            .unwrap();
+        let _watch_handle = index.reload_searchers_on_commit();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b c"));
+            index_writer.commit().unwrap();
+        }
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)), Ok(1));
+        assert_eq!(index.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_index_open_read_only() {
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        {
+            let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b c"));
+            index_writer.commit().unwrap();
+        }
+        let index = Index::open_read_only(tempdir.path()).unwrap();
+        assert!(index.is_read_only());
+        assert_eq!(index.searcher().num_docs(), 1);
+        assert!(index.writer_with_num_threads(1, 40_000_000).is_err());
+        assert!(!tempdir.path().join(".tantivy-indexer.lock").exists());
+    }
+
+    #[test]
+    fn test_searcher_pool_warmers() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert!(index.set_num_searchers(0).is_err());
+        index.set_num_searchers(2).unwrap();
+        assert_eq!(index.num_searchers(), 2);
+        let num_warmed = Arc::new(AtomicUsize::new(0));
+        {
+            let num_warmed = Arc::clone(&num_warmed);
+            index
+                .add_warmer(Box::new(move |segment_reader: &SegmentReader| {
+                    assert_eq!(segment_reader.num_docs(), 1);
+                    num_warmed.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }))
+                .unwrap();
+        }
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a b c"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(num_warmed.load(Ordering::SeqCst), 1);
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 1);
+    }
+
+    #[test]
+    fn test_segments_info() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit().unwrap();
+        {
+            let segments_info = index.segments_info().unwrap();
+            assert_eq!(segments_info.len(), 1);
+            let segment_info = &segments_info[0];
+            assert_eq!(segment_info.max_doc, 2);
+            assert_eq!(segment_info.num_deleted_docs, 0);
+            assert_eq!(segment_info.create_opstamp, Some(1));
+            assert!(segment_info.component_size(SegmentComponent::STORE).unwrap() > 0);
+            assert!(segment_info.component_size(SegmentComponent::DELETE).is_none());
+            assert!(segment_info.num_bytes() > 0);
+        }
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().unwrap();
+        let segments_info = index.segments_info().unwrap();
+        assert_eq!(segments_info.len(), 1);
+        assert_eq!(segments_info[0].num_deleted_docs, 1);
+        assert_eq!(segments_info[0].num_docs(), 1);
+        assert!(segments_info[0].component_size(SegmentComponent::DELETE).is_some());
+    }
+
+    #[test]
+    fn test_pinned_searcher() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let token = index.pin_searcher();
+        let pinned_files = index.pinned_files();
+        assert!(!pinned_files.is_empty());
+        // token ids are seeded randomly, and are not shared across indexes.
+        let other_index = Index::create_in_ram(index.schema());
+        let other_token = other_index.pin_searcher();
+        assert_ne!(other_token, token);
+        assert!(other_index.pinned_searcher(token).is_none());
+        index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 1);
+        assert_eq!(index.pinned_searcher(token).unwrap().num_docs(), 2);
+        for path in &pinned_files {
+            assert!(index.directory().exists(path));
+        }
+        assert!(index.unpin_searcher(token));
+        assert!(!index.unpin_searcher(token));
+        assert!(index.pinned_searcher(token).is_none());
+        index_writer.garbage_collect_files().unwrap();
+        assert!(pinned_files
+            .iter()
+            .any(|path| !index.directory().exists(path)));
+    }
+
+    #[test]
+    fn test_search_executor() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert!(index.set_search_threads(0).is_err());
+        index.set_search_threads(2).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit().unwrap();
+        let executor = index.search_executor();
+        // the warmers run in the pool, and use the pool themselves.
+        index
+            .add_warmer(Box::new(move |segment_reader: &SegmentReader| {
+                executor.map(
+                    move |segment_reader: SegmentReader| {
+                        segment_reader.inverted_index(text_field);
+                        Ok(())
+                    },
+                    vec![segment_reader.clone()],
+                )?;
+                Ok(())
+            }))
+            .unwrap();
+        let searcher = index.searcher();
+        let term = Term::from_field_text(text_field, "a");
+        let doc_freqs = searcher
+            .map_segments(&index.search_executor(), move |_, segment_reader| {
+                Ok(segment_reader.inverted_index(term.field()).doc_freq(&term))
+            })
+            .unwrap();
+        assert_eq!(doc_freqs.len(), 2);
+        assert_eq!(doc_freqs.iter().sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn test_search_fruit_in_parallel() {
+        use collector::{CountCollector, TopCollector};
+        use query::{AllQuery, TermQuery};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.set_search_threads(3).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for i in 0..4 {
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.add_document(doc!(text_field => "a a b"));
+            if i % 2 == 0 {
+                index_writer.add_document(doc!(text_field => "b"));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 4);
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let count = searcher
+            .search_fruit_in_parallel(&query, CountCollector::default())
+            .unwrap();
+        assert_eq!(count, 8);
+        let count_all = searcher
+            .search_fruit_in_parallel(&AllQuery, CountCollector::default())
+            .unwrap();
+        assert_eq!(count_all, 10);
+        let mut top_collector = TopCollector::with_limit(5);
+        searcher.search(&query, &mut top_collector).unwrap();
+        let top_docs = searcher
+            .search_fruit_in_parallel(&query, TopCollector::with_limit(5))
+            .unwrap();
+        assert_eq!(top_docs.score_docs, top_collector.score_docs());
+    }
+
+    #[test]
+    fn test_search_fruit_pagination() {
+        use collector::TopCollector;
+        use query::TermQuery;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        index.set_search_threads(2).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for num_tokens in 1..10 {
+            let text = vec!["a"; num_tokens].join(" ");
+            index_writer.add_document(doc!(text_field => text));
+            if num_tokens % 3 == 0 {
+                index_writer.commit().unwrap();
+            }
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 3);
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let all_docs = searcher
+            .search_fruit(&query, TopCollector::with_limit(9))
+            .unwrap()
+            .score_docs;
+        assert_eq!(all_docs.len(), 9);
+        let mut pages = Vec::new();
+        for page in 0..4 {
+            let collector = TopCollector::with_limit(3).and_offset(page * 3);
+            let score_docs = searcher.search_fruit(&query, collector).unwrap().score_docs;
+            assert_eq!(score_docs.len(), if page < 3 { 3 } else { 0 });
+            pages.extend(score_docs);
+        }
+        assert_eq!(pages, all_docs);
+    }
+
+    #[test]
+    fn test_space_usage() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let id_field = schema_builder.add_u64_field("id", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for i in 0..10u64 {
+            index_writer.add_document(doc!(text_field => "hello happy tax payer", id_field => i));
+        }
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field => "hello", id_field => 10u64));
+        index_writer.commit().unwrap();
+        let space_usage = index.space_usage().unwrap();
+        let text_usage = space_usage.field(text_field);
+        assert!(text_usage.termdict > 0);
+        assert!(text_usage.postings > 0);
+        assert!(text_usage.positions > 0);
+        assert!(text_usage.fieldnorms > 0);
+        assert_eq!(text_usage.fast_fields, 0);
+        let id_usage = space_usage.field(id_field);
+        assert!(id_usage.fast_fields > 0);
+        assert_eq!(id_usage.positions, 0);
+        assert!(space_usage.store > 0);
+        assert_eq!(space_usage.deletes, 0);
+        assert_eq!(
+            space_usage.num_bytes(),
+            text_usage.num_bytes() + id_usage.num_bytes() + space_usage.store
+        );
+    }
+
+    #[test]
+    fn test_collect_with_match_info() {
+        use collector::{Collector, MatchInfo};
+        use query::{Query, TermQuery};
+        use {Result, Score, SegmentLocalId};
+
+        #[derive(Default)]
+        struct MatchInfoCollector {
+            matches: Vec<(DocId, Option<u32>, Vec<u32>)>,
+        }
+
+        impl Collector for MatchInfoCollector {
+            fn set_segment(&mut self, _: SegmentLocalId, _: &SegmentReader) -> Result<()> {
+                Ok(())
+            }
+
+            fn collect(&mut self, _doc: DocId, _score: Score) {
+                panic!("collect_with_match should be called instead.");
+            }
+
+            fn requires_scoring(&self) -> bool {
+                false
+            }
+
+            fn requires_match_info(&self) -> bool {
+                true
+            }
+
+            fn collect_with_match(&mut self, doc: DocId, _score: Score, match_info: &MatchInfo) {
+                self.matches
+                    .push((doc, match_info.term_freq, match_info.positions.to_vec()));
+            }
+        }
+
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a b a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c c a"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqsAndPositions,
+        );
+        let mut collector = MatchInfoCollector::default();
+        term_query.search(&*searcher, &mut collector).unwrap();
+        assert_eq!(
+            collector.matches,
+            vec![(0, Some(2), vec![0, 2]), (2, Some(1), vec![2])]
+        );
+        // the term frequencies are not available without them.
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::Basic,
+        );
+        let mut collector = MatchInfoCollector::default();
+        term_query.search(&*searcher, &mut collector).unwrap();
+        assert_eq!(collector.matches, vec![(0, None, vec![]), (2, None, vec![])]);
+    }
+
+    #[test]
+    fn test_field_boost() {
+        use collector::TopCollector;
+        use query::{FuzzyTermQuery, Query, RangeQuery, TermQuery};
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(title_field => "a"));
+        index_writer.add_document(doc!(body_field => "a"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let score = |field: Field| {
+            let searcher = index.searcher();
+            let term_query = TermQuery::new(
+                Term::from_field_text(field, "a"),
+                IndexRecordOption::WithFreqs,
+            );
+            let mut top_collector = TopCollector::with_limit(1);
+            searcher.search(&term_query, &mut top_collector).unwrap();
+            top_collector.score_docs()[0].0
+        };
+        let title_score = score(title_field);
+        assert_eq!(title_score, score(body_field));
+        assert_eq!(index.field_boost(title_field), 1f32);
+        index.set_field_boost(title_field, 2f32).unwrap();
+        assert_eq!(index.field_boost(title_field), 2f32);
+        assert_eq!(index.searcher().field_boost(title_field), 2f32);
+        assert_eq!(score(title_field), title_score * 2f32);
+        assert_eq!(score(body_field), title_score);
+
+        // the constant score queries are boosted as well.
+        let constant_score = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(1);
+            index.searcher().search(query, &mut top_collector).unwrap();
+            top_collector.score_docs()[0].0
+        };
+        let fuzzy_query = FuzzyTermQuery::new(Term::from_field_text(title_field, "b"), 1, false);
+        assert_eq!(constant_score(&fuzzy_query), 2f32);
+        let range_query = RangeQuery::new_str(title_field, "a".."b");
+        assert_eq!(constant_score(&range_query), 2f32);
+        let range_query = RangeQuery::new_str(body_field, "a".."b");
+        assert_eq!(constant_score(&range_query), 1f32);
+    }
+
+    #[test]
+    fn test_bm25_params() {
+        use collector::TopCollector;
+        use query::TermQuery;
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let no_length_norm = BM25Params::new(2f32, 0f32).unwrap();
+        let body_options = TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_index_option(IndexRecordOption::WithFreqs)
+                .set_bm25_params(no_length_norm),
+        );
+        let body_field = schema_builder.add_text_field("body", body_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(title_field => "a", body_field => "a"));
+        index_writer.add_document(doc!(title_field => "a b c d", body_field => "a b c d"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.bm25_params(title_field), BM25Params::default());
+        assert_eq!(index.bm25_params(body_field), no_length_norm);
+        assert_eq!(index.searcher().bm25_params(body_field), no_length_norm);
+        assert_eq!(index.searcher().average_fieldnorm(title_field), 2.5f32);
+        let score_docs = |field: Field| {
+            let searcher = index.searcher();
+            let term_query = TermQuery::new(
+                Term::from_field_text(field, "a"),
+                IndexRecordOption::WithFreqs,
+            );
+            let mut top_collector = TopCollector::with_limit(2);
+            searcher.search(&term_query, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        // the shorter field scores higher.
+        let title_score_docs = score_docs(title_field);
+        assert_eq!(title_score_docs[0].1, DocAddress(0, 0));
+        assert!(title_score_docs[0].0 > title_score_docs[1].0);
+        // the length of the field is ignored with `b = 0`.
+        let body_score_docs = score_docs(body_field);
+        assert_eq!(body_score_docs[0].0, body_score_docs[1].0);
+
+        index.set_bm25_params(title_field, no_length_norm).unwrap();
+        assert_eq!(index.bm25_params(title_field), no_length_norm);
+        let title_score_docs = score_docs(title_field);
+        assert_eq!(title_score_docs[0].0, title_score_docs[1].0);
+        assert!(BM25Params::new(::std::f32::NAN, 0.75).is_err());
+        assert!(BM25Params::new(1.2, ::std::f32::INFINITY).is_err());
+        assert!(BM25Params::new(-0.5, 0.75).is_err());
+        assert!(BM25Params::new(1.2, -0.1).is_err());
+        assert!(BM25Params::new(1.2, 1.5).is_err());
+        assert!(BM25Params::new(0f32, 1f32).is_ok());
+        assert!(serde_json::from_str::<BM25Params>(r#"{"k1":-1.0,"b":0.75}"#).is_err());
+        assert!(serde_json::from_str::<BM25Params>(r#"{"k1":1.2,"b":2.0}"#).is_err());
+        // `1e39` overflows a `f32`.
+        assert!(serde_json::from_str::<BM25Params>(r#"{"k1":1e39,"b":0.75}"#).is_err());
+        let bm25_params: BM25Params = serde_json::from_str(r#"{"k1":2.0,"b":0.0}"#).unwrap();
+        assert_eq!(bm25_params, no_length_norm);
+    }
+
+    #[test]
+    fn test_similarity() {
+        use collector::TopCollector;
+        use query::{ConstantSimilarity, Similarity, TermQuery, TermSimilarity};
+        use std::sync::Arc;
+        use {Score, Searcher};
+
+        // scores the matches with their term frequency.
+        struct TermFreqSimilarity;
+
+        impl TermSimilarity for TermFreqSimilarity {
+            fn score(&self, _fieldnorm_opt: Option<u64>, term_freq: u32) -> Score {
+                term_freq as Score
+            }
+        }
+
+        impl Similarity for TermFreqSimilarity {
+            fn term_similarity(&self, _searcher: &Searcher, _term: &Term) -> Arc<TermSimilarity> {
+                Arc::new(TermFreqSimilarity)
+            }
+        }
+
+        let mut schema_builder = SchemaBuilder::default();
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(body_field => "a"));
+        index_writer.add_document(doc!(body_field => "a b a c a"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let scores = || {
+            let searcher = index.searcher();
+            let term_query = TermQuery::new(
+                Term::from_field_text(body_field, "a"),
+                IndexRecordOption::WithFreqs,
+            );
+            let mut top_collector = TopCollector::with_limit(2);
+            searcher.search(&term_query, &mut top_collector).unwrap();
+            let mut scores: Vec<Score> = top_collector
+                .score_docs()
+                .into_iter()
+                .map(|(score, _)| score)
+                .collect();
+            scores.sort_by(|left, right| left.partial_cmp(right).unwrap());
+            scores
+        };
+        index
+            .set_similarity(body_field, Arc::new(TermFreqSimilarity))
+            .unwrap();
+        assert_eq!(scores(), vec![1f32, 3f32]);
+        // the field boost is applied on top of the similarity.
+        index.set_field_boost(body_field, 2f32).unwrap();
+        assert_eq!(scores(), vec![2f32, 6f32]);
+        index
+            .set_similarity(body_field, Arc::new(ConstantSimilarity(0.5f32)))
+            .unwrap();
+        assert_eq!(scores(), vec![1f32, 1f32]);
+    }
+
+    #[test]
+    fn test_freqs_min_doc_freq() {
+        use futures::Future;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_indexing = TextFieldIndexing::default()
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions)
+            .set_freqs_min_doc_freq(2);
+        let text_options = TextOptions::default().set_indexing_options(text_indexing);
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for _ in 0..2 {
+            index_writer.add_document(doc!(text_field => "a a b b c"));
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+        }
+        let check_postings = |reader: &SegmentReader,
+                              text: &str,
+                              expected_option: IndexRecordOption,
+                              expected_term_freq: u32,
+                              expected_positions: &[u32]| {
+            let term = Term::from_field_text(text_field, text);
+            let inverted_index = reader.inverted_index(text_field);
+            let term_info = inverted_index.get_term_info(&term).unwrap();
+            assert_eq!(inverted_index.term_record_option(&term_info), expected_option);
+            let mut postings = inverted_index
+                .read_postings(&term, IndexRecordOption::WithFreqsAndPositions)
+                .unwrap();
+            assert!(postings.advance());
+            assert_eq!(postings.doc(), 0);
+            assert_eq!(postings.term_freq(), expected_term_freq);
+            assert_eq!(postings.positions(), expected_positions);
+        };
+        {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let reader = searcher.segment_reader(0);
+            check_postings(
+                reader,
+                "a",
+                IndexRecordOption::WithFreqsAndPositions,
+                2,
+                &[0, 1],
+            );
+            check_postings(reader, "b", IndexRecordOption::Basic, 1, &[]);
+            check_postings(reader, "c", IndexRecordOption::Basic, 1, &[]);
+        }
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        let reader = searcher.segment_reader(0);
+        check_postings(
+            reader,
+            "a",
+            IndexRecordOption::WithFreqsAndPositions,
+            2,
+            &[0, 1],
+        );
+        // `b` and `c` were too rare in each of the merged segments.
+        check_postings(reader, "b", IndexRecordOption::Basic, 1, &[]);
+        check_postings(reader, "c", IndexRecordOption::Basic, 1, &[]);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(text_field, "b")), 2);
+    }
+
+    #[test]
+    fn test_stored_value_limit() {
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let limited_options = |overflow: StoredValueOverflow| {
+            (STORED | TEXT).set_stored_value_limit(StoredValueLimit {
+                max_len: 10,
+                overflow,
+            })
+        };
+        let title =
+            schema_builder.add_text_field("title", limited_options(StoredValueOverflow::Truncate));
+        let body =
+            schema_builder.add_text_field("body", limited_options(StoredValueOverflow::StoreHash));
+        let url =
+            schema_builder.add_text_field("url", limited_options(StoredValueOverflow::Reject));
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(
+                title => "a title that is too long",
+                body => "a body that is too long",
+                url => "short"
+            ));
+            index_writer.add_document(doc!(title => "short", url => "a url that is too long"));
+            let rejected_doc = || doc!(url => "another url that is too long");
+            assert!(index_writer.try_add_document(rejected_doc()).is_err());
+            let url_term = Term::from_field_text(url, "short");
+            assert!(index_writer.try_update_document(url_term, rejected_doc()).is_err());
+            // unlike the documents rejected by `add_document`, they get no opstamp.
+            let commit_handle = index_writer.try_add_document(doc!(title => "kept")).unwrap();
+            assert_eq!(commit_handle.opstamp(), 2);
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        // the documents with a long url were rejected.
+        assert_eq!(searcher.num_docs(), 2);
+        // the values are indexed in full.
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(title, "long")), 1);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(body, "long")), 1);
+        let doc = searcher.doc(&DocAddress(0, 0)).unwrap();
+        assert_eq!(doc.get_first(title).unwrap().text(), "a title th");
+        let body_value = doc.get_first(body).unwrap().text();
+        assert_eq!(body_value.len(), 16);
+        assert!(body_value.chars().all(|c| c.is_digit(16)));
+        assert_eq!(doc.get_first(url).unwrap().text(), "short");
+    }
+
+    #[test]
+    fn test_offsets() {
+        use futures::Future;
+        use Searcher;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_indexing = TextFieldIndexing::default()
+            .set_tokenizer("default")
+            .set_index_option(IndexRecordOption::WithFreqsAndPositionsAndOffsets);
+        let text_options = TextOptions::default().set_indexing_options(text_indexing);
+        let text_field = schema_builder.add_text_field("text", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "Hello happy world, hello"));
+        index_writer.commit().unwrap();
+        // offsets of multivalued fields are relative to the concatenation of the values.
+        index_writer.add_document(doc!(text_field => "hello", text_field => "foo hello"));
+        index_writer.commit().unwrap();
+        let check_postings = |searcher: &Searcher| {
+            let term = Term::from_field_text(text_field, "hello");
+            let mut found = vec![];
+            for reader in searcher.segment_readers() {
+                let inverted_index = reader.inverted_index(text_field);
+                let mut postings = inverted_index
+                    .read_postings(&term, IndexRecordOption::WithFreqsAndPositionsAndOffsets)
+                    .unwrap();
+                while postings.advance() {
+                    found.push((postings.positions().to_vec(), postings.offsets().to_vec()));
+                }
+            }
+            found.sort();
+            assert_eq!(
+                found,
+                vec![
+                    (vec![0, 3], vec![(0, 5), (9, 14)]),
+                    (vec![0, 3], vec![(0, 5), (19, 24)]),
+                ]
+            );
+        };
+        index.load_searchers().unwrap();
+        check_postings(&*index.searcher());
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.segment_readers().len(), 1);
+        check_postings(&*searcher);
+    }
+
+    #[test]
+    fn test_rescore() {
+        use collector::TopCollector;
+        use query::{Query, Rescorer, TermQuery};
+        use {DocAddress, Result, Score};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let rank_field = schema_builder.add_u64_field("rank", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a", rank_field => 1u64));
+        index_writer.add_document(doc!(text_field => "a b", rank_field => 3u64));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field => "a", rank_field => 2u64));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |text: &str| -> Box<Query> {
+            box TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            )
+        };
+        let mut top_collector = TopCollector::with_limit(3);
+        searcher
+            .search(&*term_query("a"), &mut top_collector)
+            .unwrap();
+        let candidates = top_collector.score_docs();
+        assert_eq!(candidates.len(), 3);
+
+        let rank = move |segment_reader: &SegmentReader, doc: DocId| -> Result<Score> {
+            let rank_reader = segment_reader.fast_field_reader::<u64>(rank_field)?;
+            Ok(rank_reader.get(doc) as Score)
+        };
+        let mut rescorer = Rescorer::with_function(Box::new(rank));
+        rescorer.set_weights(0f32, 1f32);
+        let rescored = searcher.rescore(&candidates, &rescorer).unwrap();
+        let ranks: Vec<Score> = rescored.iter().map(|&(score, _)| score).collect();
+        assert_eq!(ranks, vec![3f32, 2f32, 1f32]);
+
+        let rescorer = Rescorer::with_query(term_query("b"));
+        let rescored = searcher.rescore(&candidates, &rescorer).unwrap();
+        assert_eq!(rescored.len(), 3);
+        let b_doc = rescored[0].1;
+        assert_eq!(b_doc, DocAddress(0, 1));
+        assert!(rescored[0].0 > rescored[1].0);
+        let base_score = |doc_address: DocAddress| {
+            candidates
+                .iter()
+                .find(|&&(_, candidate)| candidate == doc_address)
+                .unwrap()
+                .0
+        };
+        assert_eq!(rescored[1].0, base_score(rescored[1].1));
+
+        // the scorer of "b" oversteps the first candidate onto the second one.
+        let mut rescorer = Rescorer::with_query(term_query("b"));
+        rescorer.set_weights(0f32, 1f32);
+        let rescored = searcher.rescore(&candidates, &rescorer).unwrap();
+        assert_eq!(rescored[0].1, DocAddress(0, 1));
+        assert!(rescored[0].0 > 0f32);
+        assert_eq!(rescored[1].0, 0f32);
+        assert_eq!(rescored[2].0, 0f32);
+
+        // all of the candidates match the second pass query.
+        let mut rescorer = Rescorer::with_query(term_query("a"));
+        rescorer.set_weights(0f32, 1f32);
+        let rescored = searcher.rescore(&candidates, &rescorer).unwrap();
+        assert_eq!(rescored.len(), 3);
+        for &(score, doc_address) in &rescored {
+            assert!(score > 0f32);
+            assert_eq!(score, base_score(doc_address));
+        }
+    }
+
+    #[test]
+    fn test_search_fruit() {
+        use collector::{CountCollector, FruitCollector, TopCollector, TotalHits};
+        use query::TermQuery;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "a b"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let count = searcher
+            .search_fruit(&query, CountCollector::default())
+            .unwrap();
+        assert_eq!(count, 2);
+        let top_docs = searcher
+            .search_fruit(&query, TopCollector::with_limit(1))
+            .unwrap();
+        assert_eq!(top_docs.total_hits, TotalHits::Exact(2));
+        assert_eq!(top_docs.score_docs.len(), 1);
+        let count_collector = CountCollector::default();
+        assert_eq!(count_collector.merge_fruits(vec![count, 3]), 5);
+    }
+
+    #[test]
+    fn test_segment_warmers() {
+        use futures::Future;
+        use std::sync::{Arc, Mutex};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let warmed_max_docs = Arc::new(Mutex::new(Vec::new()));
+        {
+            let warmed_max_docs = Arc::clone(&warmed_max_docs);
+            index.add_segment_warmer(Box::new(move |segment_reader: &SegmentReader| {
+                warmed_max_docs.lock().unwrap().push(segment_reader.max_doc());
+                Ok(())
+            }));
+        }
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit().unwrap();
+        assert_eq!(*warmed_max_docs.lock().unwrap(), vec![1, 2]);
+        index.load_searchers().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(warmed_max_docs.lock().unwrap().len(), 2);
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+        assert_eq!(*warmed_max_docs.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_custom_component() {
+        use futures::Future;
+        use std::io::Write;
+        use {CustomComponentWriter, Result};
+        let noop_writer = || -> CustomComponentWriter {
+            Box::new(|_: &SegmentReader, _: &mut Write| -> Result<()> { Ok(()) })
+        };
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert!(index.add_custom_component("idx", noop_writer()).is_err());
+        assert!(index.add_custom_component("a.b", noop_writer()).is_err());
+        index
+            .add_custom_component(
+                "num_docs",
+                Box::new(|segment_reader: &SegmentReader, write: &mut Write| -> Result<()> {
+                    write.write_all(format!("{}", segment_reader.num_docs()).as_bytes())?;
+                    Ok(())
+                }),
+            )
+            .unwrap();
+        assert!(index.add_custom_component("num_docs", noop_writer()).is_err());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit().unwrap();
+        let read_custom_components = || -> Vec<Vec<u8>> {
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            let mut custom_components: Vec<Vec<u8>> = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| {
+                    assert!(segment_reader.custom_component("other").is_none());
+                    segment_reader
+                        .custom_component("num_docs")
+                        .unwrap()
+                        .as_slice()
+                        .to_owned()
+                })
+                .collect();
+            custom_components.sort();
+            custom_components
+        };
+        assert_eq!(read_custom_components(), vec![b"1".to_vec(), b"2".to_vec()]);
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+        assert_eq!(read_custom_components(), vec![b"3".to_vec()]);
+    }
+
+    #[test]
+    fn test_key_bloom_filter() {
+        let mut schema_builder = SchemaBuilder::default();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let score_field = schema_builder.add_u64_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert!(index.add_key_bloom_filter(score_field).is_err());
+        index.add_key_bloom_filter(id_field).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for i in 0..10 {
+            index_writer.add_document(doc!(id_field => format!("id{}", i), text_field => "a"));
+        }
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(id_field => "id10", text_field => "b"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        {
+            let searcher = index.searcher();
+            for segment_reader in searcher.segment_readers() {
+                let keys: Vec<Term> = (0..100)
+                    .map(|i| Term::from_field_text(id_field, &format!("id{}", i)))
+                    .collect();
+                let num_present = keys.iter()
+                    .filter(|key| segment_reader.doc_id_for_key(key).is_some())
+                    .count();
+                let num_may_contain = keys.iter()
+                    .filter(|key| segment_reader.may_contain_key(key))
+                    .count();
+                assert_eq!(num_present, segment_reader.num_docs() as usize);
+                assert!(num_may_contain >= num_present);
+                assert!(num_may_contain < 100);
+            }
+            let address = searcher
+                .doc_address_for_key(&Term::from_field_text(id_field, "id10"))
+                .unwrap();
+            assert_eq!(searcher.segment_reader(address.segment_ord()).num_docs(), 1);
+        }
+        index_writer.delete_term(Term::from_field_text(id_field, "id3"));
+        index_writer.delete_term(Term::from_field_text(id_field, "id10"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 9);
+        assert!(searcher
+            .doc_address_for_key(&Term::from_field_text(id_field, "id3"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_merge_listener() {
+        use futures::Future;
+        use std::sync::{Arc, Mutex};
+        use {DocIdMapping, SegmentId};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mappings: Arc<Mutex<Vec<DocIdMapping>>> = Arc::new(Mutex::new(Vec::new()));
+        {
+            let mappings = Arc::clone(&mappings);
+            index.add_merge_listener(Box::new(move |doc_id_mapping: &DocIdMapping| {
+                mappings.lock().unwrap().push(doc_id_mapping.clone());
+            }));
+        }
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field => "d"));
+        index_writer.add_document(doc!(text_field => "e"));
+        index_writer.commit().unwrap();
+        index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let old_docs: Vec<(SegmentId, DocId, bool)> = index
+            .searcher()
+            .segment_readers()
+            .iter()
+            .flat_map(|segment_reader| {
+                (0..segment_reader.max_doc())
+                    .map(|doc| {
+                        let segment_id = segment_reader.segment_id();
+                        (segment_id, doc, segment_reader.is_deleted(doc))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.wait_merging_threads().unwrap();
+        let mappings = mappings.lock().unwrap();
+        assert_eq!(mappings.len(), 1);
+        let doc_id_mapping = &mappings[0];
+        assert_eq!(
+            index.searchable_segment_ids().unwrap(),
+            vec![doc_id_mapping.merged_segment_id()]
+        );
+        let mut new_doc_ids: Vec<DocId> = Vec::new();
+        for &(segment_id, doc, deleted) in &old_docs {
+            let new_doc_id = doc_id_mapping.new_doc_id(segment_id, doc);
+            assert_eq!(new_doc_id.is_none(), deleted);
+            new_doc_ids.extend(new_doc_id);
+        }
+        new_doc_ids.sort();
+        assert_eq!(new_doc_ids, vec![0, 1, 2, 3]);
+        assert!(doc_id_mapping
+            .new_doc_id(doc_id_mapping.merged_segment_id(), 0)
+            .is_none());
+    }
+
+    #[test]
+    fn test_memory_limit_flushes_segments() {
+        use {MemoryAccountant, MemoryCap};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let memory_accountant = MemoryAccountant::default();
+        index.set_memory_accountant(memory_accountant.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.commit().unwrap();
+        assert_eq!(index.searchable_segment_ids().unwrap().len(), 1);
+        // no memory is left for the arena: each document gets its own segment.
+        memory_accountant.set_limiter(Box::new(MemoryCap::new(0)));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.add_document(doc!(text_field => "d"));
+        index_writer.add_document(doc!(text_field => "e"));
+        index_writer.commit().unwrap();
+        assert_eq!(index.searchable_segment_ids().unwrap().len(), 4);
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 5);
+        assert_eq!(memory_accountant.usage().total(), 0);
+    }
+
+    #[test]
+    fn test_top_terms() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let score_field = schema_builder.add_u64_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert!(index.add_doc_freq_index(score_field).is_err());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "z"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        // the segment was written before the registration of the index.
+        assert!(index.searcher().top_terms(text_field, 1).is_err());
+
+        let index = Index::create_in_ram(index.schema());
+        index.add_doc_freq_index(text_field).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a b"));
+        index_writer.add_document(doc!(text_field => "a c"));
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "b c"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term = |text: &str| Term::from_field_text(text_field, text);
+        {
+            let segment_terms: Vec<Vec<(Term, u32)>> = searcher
+                .segment_readers()
+                .iter()
+                .map(|segment_reader| {
+                    let terms = segment_reader.terms_by_doc_freq(text_field).unwrap();
+                    let num_terms = terms.num_terms();
+                    let terms: Vec<(Term, u32)> = terms.collect();
+                    assert_eq!(terms.len(), num_terms);
+                    terms
+                })
+                .collect();
+            assert!(segment_terms.contains(&vec![(term("a"), 3), (term("b"), 1), (term("c"), 1)]));
+            assert!(segment_terms.contains(&vec![(term("b"), 3), (term("c"), 1)]));
+        }
+        assert!(searcher.top_terms(text_field, 0).unwrap().is_empty());
+        assert_eq!(
+            searcher.top_terms(text_field, 2).unwrap(),
+            vec![(term("b"), 4), (term("a"), 3)]
+        );
+        assert_eq!(
+            searcher.top_terms(text_field, 10).unwrap(),
+            vec![(term("b"), 4), (term("a"), 3), (term("c"), 2)]
+        );
+    }
+
+    #[test]
+    fn test_search_preset() {
+        use collector::CountCollector;
+        use query::QueryParser;
+        use SearchPreset;
+        let tempdir = TempDir::new("index").unwrap();
+        let mut schema_builder = SchemaBuilder::default();
+        let title_field = schema_builder.add_text_field("title", TEXT);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+        let mut search_preset = SearchPreset {
+            default_fields: vec!["title".to_string(), "missing".to_string()],
+            conjunction_by_default: true,
+            ..SearchPreset::default()
+        };
+        search_preset.field_boosts.insert("title".to_string(), 2f32);
+        let mut settings = IndexSettings::default();
+        settings.search_preset = Some(search_preset.clone());
+        assert!(Index::create_with_settings(tempdir.path(), schema.clone(), settings.clone())
+            .is_err());
+        search_preset.default_fields = vec!["title".to_string()];
+        settings.search_preset = Some(search_preset.clone());
+        {
+            let index =
+                Index::create_with_settings(tempdir.path(), schema.clone(), settings).unwrap();
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title_field => "a b"));
+            index_writer.add_document(doc!(title_field => "a"));
+            index_writer.add_document(doc!(body_field => "a b"));
+            index_writer.commit().unwrap();
+        }
+        let index = Index::open(tempdir.path()).unwrap();
+        assert_eq!(
+            index.load_metas().unwrap().index_settings.search_preset,
+            Some(search_preset)
+        );
+        assert_eq!(index.field_boost(title_field), 2f32);
+        assert_eq!(index.field_boost(body_field), 1f32);
+        let searcher = index.searcher();
+        let count = |query: &str, query_parser: &QueryParser| {
+            let query = query_parser.parse_query(query).unwrap();
+            let mut count_collector = CountCollector::default();
+            searcher.search(&*query, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(count("a b", &index.query_parser()), 1);
+        let index_without_preset = Index::create_in_ram(schema);
+        let query_parser = index_without_preset.query_parser();
+        // title and body are searched, with a disjunction.
+        assert_eq!(count("a b", &query_parser), 3);
+    }
+
+    #[test]
+    fn test_segment_field_bounds() {
+        use collector::CountCollector;
+        use query::{Query, RangeQuery, TermQuery};
+        let mut schema_builder = SchemaBuilder::default();
+        let timestamp_field = schema_builder.add_u64_field("timestamp", INT_INDEXED | FAST);
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for timestamp in 0u64..10u64 {
+            index_writer.add_document(doc!(timestamp_field => timestamp, text_field => "b"));
+        }
+        index_writer.commit().unwrap();
+        for timestamp in 100u64..110u64 {
+            index_writer.add_document(doc!(timestamp_field => timestamp, text_field => "d"));
+        }
+        index_writer.commit().unwrap();
+        let mut segment_metas = index.load_metas().unwrap().segments;
+        segment_metas.sort_by_key(|segment_meta| segment_meta.create_opstamp());
+        let value_bounds: Vec<Option<(u64, u64)>> = segment_metas
+            .iter()
+            .map(|segment_meta| {
+                segment_meta
+                    .field_bounds(timestamp_field)
+                    .unwrap()
+                    .value_bounds
+            })
+            .collect();
+        assert_eq!(value_bounds, vec![Some((0, 9)), Some((100, 109))]);
+        {
+            use std::collections::Bound;
+            let value_bytes =
+                |val: u64| Term::from_field_u64(timestamp_field, val).value_bytes().to_vec();
+            let timestamp_bounds = segment_metas[0].field_bounds(timestamp_field).unwrap();
+            assert!(timestamp_bounds.may_overlap_values(
+                &Bound::Included(value_bytes(9)),
+                &Bound::Unbounded
+            ));
+            assert!(!timestamp_bounds.may_overlap_values(
+                &Bound::Excluded(value_bytes(9)),
+                &Bound::Unbounded
+            ));
+            assert!(!timestamp_bounds.may_overlap_values(
+                &Bound::Unbounded,
+                &Bound::Excluded(value_bytes(0))
+            ));
+        }
+        let text_bounds = segment_metas[1].field_bounds(text_field).unwrap();
+        assert_eq!(text_bounds.term_bounds, Some((b"d".to_vec(), b"d".to_vec())));
+        assert!(text_bounds.value_bounds.is_none());
+        assert!(text_bounds.may_contain_term(b"d"));
+        assert!(!text_bounds.may_contain_term(b"b"));
+
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let count = |query: &Query| {
+            let mut count_collector = CountCollector::default();
+            query.search(&*searcher, &mut count_collector).unwrap();
+            count_collector.count()
+        };
+        assert_eq!(count(&RangeQuery::new_u64(timestamp_field, 5u64..105u64)), 10);
+        assert_eq!(count(&RangeQuery::new_u64(timestamp_field, 10u64..100u64)), 0);
+        assert_eq!(count(&RangeQuery::new_u64(timestamp_field, 109u64..200u64)), 1);
+        let term_query = |text: &str| {
+            TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            )
+        };
+        assert_eq!(count(&term_query("b")), 10);
+        assert_eq!(count(&term_query("c")), 0);
+        assert_eq!(count(&term_query("d")), 10);
+    }
+
+    #[test]
+    fn test_reader_lease() {
+        use std::fs;
+        use std::time::Duration;
+        use tempdir::TempDir;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let tempdir = TempDir::new("index").unwrap();
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.delete_term(Term::from_field_text(text_field, "a"));
+        index_writer.commit().unwrap();
+        // the reader process.
+        let reader_index = Index::open_read_only(tempdir.path()).unwrap();
+        let generation = reader_index.searcher_generation();
+        assert_eq!(generation, index.load_metas().unwrap().generation);
+        let lease = reader_index
+            .acquire_reader_lease(Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(lease.generation(), generation);
+        let leased_files = lease.files().to_vec();
+        assert!(!leased_files.is_empty());
+        index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        index_writer.commit().unwrap();
+        assert_eq!(index.load_metas().unwrap().generation, generation + 1);
+        for path in &leased_files {
+            assert!(index.directory().exists(path));
+        }
+        assert_eq!(reader_index.searcher().num_docs(), 1);
+        drop(lease);
+        // a corrupted lease does not prevent the garbage collection.
+        let corrupted_lease_path = tempdir.path().join(".tantivy-reader-corrupted.lease");
+        fs::write(&corrupted_lease_path, b"not a lease").unwrap();
+        index_writer.garbage_collect_files().unwrap();
+        assert!(leased_files
+            .iter()
+            .any(|path| !index.directory().exists(path)));
+        // the garbage collection lock is released.
+        assert!(!tempdir.path().join(".tantivy-gc.lock").exists());
+    }
+
+    #[test]
+    fn test_delete_query() {
+        use query::{Query, RangeQuery};
+        let mut schema_builder = SchemaBuilder::default();
+        let year = schema_builder.add_u64_field("year", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for i in 0u64..10u64 {
+            index_writer.add_document(doc!(year => 2000u64 + i));
+        }
+        index_writer.commit().unwrap();
+        index_writer.add_document(doc!(year => 2001u64));
+        index_writer.delete_query(Box::new(RangeQuery::new_u64(year, 2000..2005)));
+        // documents added after the delete are not affected.
+        index_writer.add_document(doc!(year => 2002u64));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        assert_eq!(searcher.num_docs(), 6);
+        let count = |query: RangeQuery| query.count(&*searcher).unwrap();
+        assert_eq!(count(RangeQuery::new_u64(year, 2000..2005)), 1);
+        assert_eq!(count(RangeQuery::new_u64(year, 2005..2010)), 5);
+    }
+
+    #[test]
+    fn test_searcher_doc_store_cache() {
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let text = |doc_id: DocId| {
+            let doc = searcher.doc(&DocAddress(0, doc_id)).unwrap();
+            doc.get_first(text_field).unwrap().text().to_string()
+        };
+        assert_eq!(text(2), "c");
+        assert_eq!(text(0), "a");
+        assert_eq!(text(1), "b");
+        let doc = searcher.doc_fields(&DocAddress(0, 1), &[text_field]).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), "b");
+        // the three documents belong to the same block.
+        let counters = searcher.doc_store_cache_counters();
+        assert_eq!(counters.miss, 1);
+        assert_eq!(counters.hit, 3);
+    }
+
+    #[test]
+    fn test_searcher_is_send() {
+        use Searcher;
+        fn assert_send<T: Send>() {}
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send::<Searcher>();
+        assert_send_sync::<Index>();
+    }
+
+    #[test]
+    fn test_date_field() {
+        use query::{Query, TermQuery};
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let date_field = schema_builder.add_date_field("date", INT_INDEXED | INT_STORED | FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let date = |text: &str| text.parse::<DateTime>().unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(date_field => date("2018-03-21T12:30:00Z")));
+            let json_doc = schema
+                .parse_document(r#"{"date": "1969-07-20T20:17:40Z"}"#)
+                .unwrap();
+            index_writer.add_document(json_doc);
+            index_writer.commit().unwrap();
+        }
+        assert!(schema.parse_document(r#"{"date": "20/07/1969"}"#).is_err());
+        assert!(schema.parse_document(r#"{"date": 1}"#).is_err());
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let fast_field_reader = segment_reader
+            .fast_field_reader::<DateTime>(date_field)
+            .unwrap();
+        assert_eq!(fast_field_reader.get(0), date("2018-03-21T12:30:00Z"));
+        assert_eq!(fast_field_reader.get(1), date("1969-07-20T20:17:40Z"));
+        assert!(segment_reader.fast_field_reader::<i64>(date_field).is_err());
+        let stored_doc = searcher.doc(&DocAddress(0, 1)).unwrap();
+        assert_eq!(
+            stored_doc.get_first(date_field).unwrap().date_value(),
+            date("1969-07-20T20:17:40Z")
+        );
+        assert_eq!(
+            schema.to_json(&stored_doc),
+            r#"{"date":["1969-07-20T20:17:40Z"]}"#
+        );
+        let term = Term::from_field_date(date_field, &date("2018-03-21T12:30:00Z"));
+        let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+        assert_eq!(term_query.count(&*searcher).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_f64_field() {
+        use query::{Query, RangeQuery, TermQuery};
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let price_field = schema_builder.add_f64_field("price", INT_INDEXED | INT_STORED | FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(price_field => 19.99f64));
+            let json_doc = schema.parse_document(r#"{"price": -0.5}"#).unwrap();
+            index_writer.add_document(json_doc);
+            index_writer.add_document(doc!(price_field => 1e12f64));
+            index_writer.commit().unwrap();
+        }
+        assert!(schema.parse_document(r#"{"price": "cheap"}"#).is_err());
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let fast_field_reader = segment_reader.fast_field_reader::<f64>(price_field).unwrap();
+        assert_eq!(fast_field_reader.get(0), 19.99f64);
+        assert_eq!(fast_field_reader.get(1), -0.5f64);
+        assert_eq!(fast_field_reader.min_value(), -0.5f64);
+        assert_eq!(fast_field_reader.max_value(), 1e12f64);
+        assert!(segment_reader.fast_field_reader::<u64>(price_field).is_err());
+        let stored_doc = searcher.doc(&DocAddress(0, 1)).unwrap();
+        assert_eq!(stored_doc.get_first(price_field).unwrap().f64_value(), -0.5f64);
+        assert_eq!(schema.to_json(&stored_doc), r#"{"price":[-0.5]}"#);
+        let term_query = TermQuery::new(
+            Term::from_field_f64(price_field, 19.99f64),
+            IndexRecordOption::Basic,
+        );
+        assert_eq!(term_query.count(&*searcher).unwrap(), 1);
+        let range_query = RangeQuery::new_f64(price_field, -1f64..20f64);
+        assert_eq!(range_query.count(&*searcher).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_opstamp_field() {
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let seq_field = schema_builder.add_u64_field("seq", (FAST | INT_STORED).set_opstamp());
+        let schema = schema_builder.build();
+        let schema_json = serde_json::to_string(&schema).unwrap();
+        assert!(schema_json.contains("\"opstamp\":true"));
+        let deserialized_schema: Schema = serde_json::from_str(&schema_json).unwrap();
+        assert_eq!(
+            deserialized_schema.get_field_entry(seq_field),
+            schema.get_field_entry(seq_field)
+        );
+        let index = Index::create_in_ram(schema);
+        let mut opstamps = vec![];
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            opstamps.push(index_writer.add_document(doc!(text_field => "a")).opstamp());
+            index_writer.delete_term(Term::from_field_text(text_field, "z"));
+            // the value given by the user is replaced.
+            opstamps.push(
+                index_writer
+                    .add_document(doc!(text_field => "b", seq_field => 1_000u64))
+                    .opstamp(),
+            );
+            opstamps.push(index_writer.add_document(doc!(text_field => "c")).opstamp());
+            index_writer.commit().unwrap();
+        }
+        assert_eq!(opstamps, vec![0, 2, 3]);
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let fast_field_reader = searcher
+            .segment_reader(0)
+            .fast_field_reader::<u64>(seq_field)
+            .unwrap();
+        let seqs: Vec<u64> = (0..3).map(|doc| fast_field_reader.get(doc)).collect();
+        assert_eq!(seqs, opstamps);
+        let stored_doc = searcher.doc(&DocAddress(0, 1)).unwrap();
+        assert_eq!(stored_doc.get_all(seq_field).len(), 1);
+        assert_eq!(stored_doc.get_first(seq_field).unwrap().u64_value(), 2);
+    }
+
+    #[test]
+    fn test_dedup() {
+        use {DedupMode, DedupSettings};
+        use directory::{ManagedDirectory, RAMDirectory};
+        let create_index = |mode: DedupMode| {
+            let mut schema_builder = SchemaBuilder::default();
+            let url_field = schema_builder.add_text_field("url", STRING);
+            let body_field = schema_builder.add_text_field("body", TEXT | STORED);
+            schema_builder.add_u64_field("hash", INT_INDEXED);
+            let settings = IndexSettings {
+                dedup: Some(DedupSettings {
+                    hash_field: "hash".to_string(),
+                    fields: vec!["url".to_string()],
+                    mode,
+                }),
+                ..IndexSettings::default()
+            };
+            let index = Index::create_in_ram_with_settings(schema_builder.build(), settings);
+            (index, url_field, body_field)
+        };
+        {
+            let (index, url_field, body_field) = create_index(DedupMode::Skip);
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(url_field => "a", body_field => "first"));
+            assert!(index_writer.is_duplicate(&doc!(url_field => "a")));
+            index_writer.add_document(doc!(url_field => "a", body_field => "second"));
+            index_writer.add_document(doc!(url_field => "b", body_field => "third"));
+            index_writer.commit().unwrap();
+            // the committed documents are also detected.
+            assert!(index_writer.is_duplicate(&doc!(url_field => "b")));
+            index_writer.add_document(doc!(url_field => "b", body_field => "fourth"));
+            index_writer.add_document(doc!(url_field => "c", body_field => "fifth"));
+            index_writer.commit().unwrap();
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            assert_eq!(searcher.num_docs(), 3);
+            let body_term = |text: &str| Term::from_field_text(body_field, text);
+            assert_eq!(searcher.doc_freq(&body_term("first")), 1);
+            assert_eq!(searcher.doc_freq(&body_term("second")), 0);
+            assert_eq!(searcher.doc_freq(&body_term("fourth")), 0);
+        }
+        {
+            let (index, url_field, body_field) = create_index(DedupMode::Replace);
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(url_field => "a", body_field => "first"));
+            index_writer.add_document(doc!(url_field => "b", body_field => "second"));
+            index_writer.commit().unwrap();
+            assert!(!index_writer.is_duplicate(&doc!(url_field => "a")));
+            index_writer.add_document(doc!(url_field => "a", body_field => "third"));
+            index_writer.commit().unwrap();
+            index.load_searchers().unwrap();
+            let searcher = index.searcher();
+            assert_eq!(searcher.num_docs(), 2);
+            let url_a = Term::from_field_text(url_field, "a");
+            let doc_address = searcher.doc_address_for_key(&url_a).unwrap();
+            let doc = searcher.doc(&doc_address).unwrap();
+            assert_eq!(doc.get_first(body_field).unwrap().text(), "third");
+        }
+        {
+            let mut schema_builder = SchemaBuilder::default();
+            schema_builder.add_text_field("url", STRING);
+            let settings = IndexSettings {
+                dedup: Some(DedupSettings {
+                    hash_field: "url".to_string(),
+                    fields: vec!["url".to_string()],
+                    mode: DedupMode::Skip,
+                }),
+                ..IndexSettings::default()
+            };
+            let directory = ManagedDirectory::new(RAMDirectory::create()).unwrap();
+            assert!(
+                Index::from_directory_with_settings(directory, schema_builder.build(), settings)
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_language_router() {
+        use LanguageRouter;
+        use tokenizer::StopWordLanguageDetector;
+        let mut schema_builder = SchemaBuilder::default();
+        let lang_field = schema_builder.add_text_field("lang", STRING | STORED);
+        let body_field = schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let language_router =
+            LanguageRouter::new(box StopWordLanguageDetector, lang_field, vec![body_field]);
+        assert!(
+            index_writer
+                .set_language_router(language_router.route("nl", "nl_stem"))
+                .is_err()
+        );
+        let language_router =
+            LanguageRouter::new(box StopWordLanguageDetector, lang_field, vec![body_field]);
+        index_writer.set_language_router(language_router).unwrap();
+        index_writer.add_document(doc!(body_field => "Les chats sont dans le jardin."));
+        index_writer.add_document(doc!(body_field => "The cats are in the garden."));
+        index_writer.add_document(doc!(body_field => "tantivy cats"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let body_term = |text: &str| Term::from_field_text(body_field, text);
+        let lang_term = |text: &str| Term::from_field_text(lang_field, text);
+        assert_eq!(searcher.doc_freq(&body_term("chat")), 1);
+        assert_eq!(searcher.doc_freq(&body_term("cat")), 1);
+        // the language of the last document is not detected.
+        assert_eq!(searcher.doc_freq(&body_term("cats")), 1);
+        assert_eq!(searcher.doc_freq(&lang_term("fr")), 1);
+        assert_eq!(searcher.doc_freq(&lang_term("en")), 1);
+    }
+
+    #[test]
+    fn test_rollback_to_commit() {
+        use futures::Future;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let settings = IndexSettings {
+            num_kept_commits: 2,
+            ..IndexSettings::default()
+        };
+        let index = Index::create_in_ram_with_settings(schema_builder.build(), settings);
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let mut generations = Vec::new();
+        for _ in 0..3 {
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+            generations.push(index.load_metas().unwrap().generation);
+        }
+        // merges do not create new commits.
+        let segment_ids = index.searchable_segment_ids().unwrap();
+        index_writer
+            .merge(&segment_ids)
+            .wait()
+            .expect("Merging failed");
+        index_writer.garbage_collect_files().unwrap();
+        let commit_opstamps = |index: &Index| -> Vec<u64> {
+            index
+                .list_commits()
+                .unwrap()
+                .iter()
+                .map(|commit_metas| commit_metas.opstamp)
+                .collect()
+        };
+        assert_eq!(commit_opstamps(&index).len(), 3);
+        let num_docs = |generation: u64| index.open_commit(generation).unwrap().num_docs();
+        // the segments of the kept commits are not garbage collected.
+        assert_eq!(num_docs(generations[0]), 1);
+        assert_eq!(num_docs(generations[1]), 2);
+
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.rollback_to(generations[1]).unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 2);
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 3);
+        // the rollback and the last commit pushed
+        // the first commits out of the history.
+        assert_eq!(commit_opstamps(&index).len(), 3);
+        assert!(index.open_commit(generations[0]).is_err());
+        assert!(index_writer.rollback_to(generations[0]).is_err());
+    }
+
+    #[test]
+    fn test_kept_commit_unreadable() {
+        use core::commit_meta_filepath;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let settings = IndexSettings {
+            num_kept_commits: 2,
+            ..IndexSettings::default()
+        };
+        let index = Index::create_in_ram_with_settings(schema_builder.build(), settings);
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        let mut generations = Vec::new();
+        for _ in 0..3 {
+            index_writer.add_document(doc!(text_field => "a"));
+            index_writer.commit().unwrap();
+            generations.push(index.load_metas().unwrap().generation);
+        }
+        let corrupted_path = commit_meta_filepath(generations[0]);
+        index
+            .directory()
+            .box_clone()
+            .atomic_write(&corrupted_path, b"{")
+            .unwrap();
+        assert!(index.open_commit(generations[0]).is_err());
+        // the other kept commit still protects its files.
+        let kept_commit_files = index.kept_commit_files().unwrap();
+        assert!(kept_commit_files.contains(&corrupted_path));
+        assert!(kept_commit_files.contains(&commit_meta_filepath(generations[1])));
+        index_writer.garbage_collect_files().unwrap();
+        assert_eq!(index.open_commit(generations[1]).unwrap().num_docs(), 2);
+    }
+
+    #[test]
+    fn test_add_document_commit_handle() {
+        use futures::Future;
+        use {CommitHandle, Error};
+
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+
+        let rolled_back_handle = index_writer.add_document(doc!(text_field => "a"));
+        index_writer.rollback().unwrap();
+        match rolled_back_handle.wait() {
+            Err(Error(ErrorKind::OperationDiscarded(0), _)) => {}
+            _ => panic!("Expected OperationDiscarded error"),
+        }
+
+        let handles: Vec<CommitHandle> = (0..3)
+            .map(|_| index_writer.add_document(doc!(text_field => "b")))
+            .collect();
+        assert_eq!(handles[2].opstamp(), 2);
+        assert!(!handles[0].is_committed());
+        let acknowledger = thread::spawn(move || {
+            handles
+                .into_iter()
+                .map(|handle| handle.wait().unwrap())
+                .collect::<Vec<u64>>()
+        });
+        assert_eq!(index_writer.commit().unwrap(), 3);
+        assert_eq!(acknowledger.join().unwrap(), vec![3, 3, 3]);
+        assert_eq!(index_writer.commit_opstamp(), 3);
+
+        let delete_opstamp = index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        let delete_handle = index_writer.commit_handle(delete_opstamp);
+        let pending_handle = index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit().unwrap();
+        assert!(delete_handle.is_committed());
+        let last_handle = index_writer.add_document(doc!(text_field => "d"));
+        drop(index_writer);
+        assert_eq!(pending_handle.wait().unwrap(), 6);
+        assert!(last_handle.wait().is_err());
+    }
+
+    #[test]
+    fn test_search_with_budget() {
+        use collector::CountCollector;
+        use query::AllQuery;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        for _ in 0..3 {
+            index_writer.add_document(doc!(text_field => "a b c"));
+        }
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        {
+            let mut collector = CountCollector::default();
+            let outcome = searcher
+                .search_with_budget(&AllQuery, &mut collector, Duration::from_secs(3_600))
+                .unwrap();
+            assert_eq!(outcome, SearchOutcome::Complete);
+            assert_eq!(collector.count(), 3);
+        }
+        {
+            let mut collector = CountCollector::default();
+            let outcome = searcher
+                .search_with_budget(&AllQuery, &mut collector, Duration::from_secs(0))
+                .unwrap();
+            assert!(outcome.is_timed_out());
+            assert_eq!(collector.count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_index_reindex() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        schema_builder.add_u64_field("count", INT_STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "a b"));
+            index_writer.add_document(doc!(title => "b c"));
+            index_writer.add_document(doc!(title => "c d"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+
+        let mut new_schema_builder = SchemaBuilder::default();
+        let new_title = new_schema_builder.add_text_field("title", STRING | STORED);
+        let tempdir = TempDir::new("reindex").unwrap();
+        let new_index = index
+            .reindex(
+                tempdir.path(),
+                new_schema_builder.build(),
+                40_000_000,
+                |named_doc| {
+                    if named_doc.0["title"][0].text() == "c d" {
+                        None
+                    } else {
+                        Some(named_doc)
+                    }
+                },
+            )
+            .unwrap();
+        let searcher = new_index.searcher();
+        assert_eq!(searcher.num_docs(), 2);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(new_title, "a b")), 1);
+        assert_eq!(searcher.doc_freq(&Term::from_field_text(new_title, "a")), 0);
+    }
+
+    #[test]
+    fn test_all_query_skips_deleted_docs() {
+        use collector::{CountCollector, TopCollector};
+        use query::{AllQuery, Query};
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a"));
+        index_writer.add_document(doc!(text_field => "b"));
+        index_writer.add_document(doc!(text_field => "c"));
+        index_writer.commit().unwrap();
+        index_writer.delete_term(Term::from_field_text(text_field, "b"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let alive_docs: Vec<DocId> = segment_reader.alive_bitset().iter().collect();
+        assert_eq!(alive_docs, vec![0, 2]);
+        assert_eq!(AllQuery.count(&*searcher).unwrap(), 2);
+        {
+            let mut collector = CountCollector::default();
+            searcher.search(&AllQuery, &mut collector).unwrap();
+            assert_eq!(collector.count(), 2);
+        }
+        {
+            let mut collector = TopCollector::with_limit(10);
+            searcher.search(&AllQuery, &mut collector).unwrap();
+            let mut docs: Vec<DocId> = collector
+                .docs()
+                .iter()
+                .map(|doc_address| doc_address.doc())
+                .collect();
+            docs.sort();
+            assert_eq!(docs, alive_docs);
+        }
+    }
+
+    #[test]
+    fn test_doc_address_for_key() {
+        let mut schema_builder = SchemaBuilder::default();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let text_field = schema_builder.add_text_field("text", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(id_field => "doc1", text_field => "a"));
+        index_writer.add_document(doc!(id_field => "doc2", text_field => "b"));
+        index_writer.commit().unwrap();
+        // updates doc1 in a new segment.
+        let doc1 = Term::from_field_text(id_field, "doc1");
+        index_writer.delete_term(doc1.clone());
+        index_writer.add_document(doc!(id_field => "doc1", text_field => "c"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let doc_address = searcher.doc_address_for_key(&doc1).unwrap();
+        let doc = searcher.doc(&doc_address).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), "c");
+        let doc2 = Term::from_field_text(id_field, "doc2");
+        let doc_address = searcher.doc_address_for_key(&doc2).unwrap();
+        let doc = searcher.doc(&doc_address).unwrap();
+        assert_eq!(doc.get_first(text_field).unwrap().text(), "b");
+        let doc3 = Term::from_field_text(id_field, "doc3");
+        assert!(searcher.doc_address_for_key(&doc3).is_none());
+    }
+
+    #[test]
+    fn test_index_backup_to() {
+        let tempdir = TempDir::new("index").unwrap();
+        let backup_dir = TempDir::new("backup").unwrap();
+        let backup_path = backup_dir.path().join("backup");
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create(tempdir.path(), schema_builder.build()).unwrap();
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a b c"));
+        index_writer.commit().unwrap();
+        index.backup_to(&backup_path).unwrap();
+        // documents committed after the backup are not part of it.
+        index_writer.add_document(doc!(text_field => "b c d"));
+        index_writer.commit().unwrap();
+        assert!(index.backup_to(&backup_path).is_err());
+        let backup_index = Index::open(&backup_path).unwrap();
+        assert!(backup_index.validate().is_ok());
+        assert_eq!(backup_index.searcher().num_docs(), 1);
+    }
+
+    #[test]
+    fn test_index_size_budget() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+        index_writer.add_document(doc!(text_field => "a b c"));
+        index_writer.commit().unwrap();
+        index.set_size_budget(Some(0)).unwrap();
+        assert_eq!(index.remaining_size_budget(), Some(0));
+        index_writer.add_document(doc!(text_field => "b c d"));
+        match *index_writer.commit().unwrap_err().kind() {
+            ErrorKind::DiskFull(_) => {}
+            _ => panic!("Expected a DiskFull error"),
+        }
+        index_writer.rollback().unwrap();
+        index.set_size_budget(None).unwrap();
+        index_writer.add_document(doc!(text_field => "b c d"));
+        index_writer.commit().unwrap();
+        index.load_searchers().unwrap();
+        assert_eq!(index.searcher().num_docs(), 2);
+    }
 }
@@ -16,7 +16,12 @@ macro_rules! get(
 /// )
 /// ```
 ///
-/// The value can be a `u64`, a `&str`, a `i64`, or a `String`.
+/// The value can be a `u64`, a `&str`, a `i64`, a `String` or a `Facet`.
+/// It can also be an `Option` of these, in which case `None` adds no value,
+/// or a `Vec` or a slice of these, which adds one value per element to the
+/// (multivalued) field. See `IntoFieldValues`.
+///
+/// The field can be any expression evaluating to a `Field`.
 ///
 /// # Warning
 ///
@@ -54,13 +59,33 @@ macro_rules! doc(
             ($crate::Document::default())
         }
     }; // avoids a warning due to the useless `mut`.
-    ($($field:expr => $value:expr),*) => {
+    ($($field:expr => $value:expr),* $(,)*) => {
         {
             let mut document = $crate::Document::default();
             $(
-                document.add($crate::schema::FieldValue::new($field, $value.into()));
+                $crate::schema::IntoFieldValues::add_to($value, $field, &mut document);
             )*
             document
         }
     };
 );
+
+/// Logs a lifecycle event of the `IndexWriter` (segment flushed,
+/// merge started or finished, commit) at the info level,
+/// under the `tantivy::lifecycle` target.
+///
+/// Events are formatted as `event=<name> key=value ...` so that
+/// they can easily be parsed. They are only logged if tantivy
+/// is compiled with the `lifecycle-logging` feature, in which case
+/// the values are not even computed.
+macro_rules! lifecycle_event(
+    ($event:expr $(, $key:ident = $value:expr)*) => {
+        if cfg!(feature = "lifecycle-logging") {
+            info!(
+                target: "tantivy::lifecycle",
+                concat!("event={}" $(, " ", stringify!($key), "={:?}")*),
+                $event $(, $value)*
+            );
+        }
+    };
+);
@@ -14,7 +14,8 @@ mod postings_writer;
 mod term_info;
 mod segment_postings;
 
-use self::recorder::{NothingRecorder, Recorder, TFAndPositionRecorder, TermFrequencyRecorder};
+use self::recorder::{NothingRecorder, Recorder, TFAndPositionRecorder,
+                     TFPositionAndOffsetRecorder, TermFrequencyRecorder};
 pub use self::serializer::{FieldSerializer, InvertedIndexSerializer};
 pub(crate) use self::postings_writer::MultiFieldPostingsWriter;
 
@@ -167,6 +168,7 @@ pub mod tests {
                 let op = AddOperation {
                     opstamp: 0u64,
                     document: doc,
+                    tokenizer_route: None,
                 };
                 segment_writer.add_document(op, &schema).unwrap();
             }
@@ -176,6 +178,7 @@ pub mod tests {
                 let op = AddOperation {
                     opstamp: 1u64,
                     document: doc,
+                    tokenizer_route: None,
                 };
                 segment_writer.add_document(op, &schema).unwrap();
             }
@@ -187,6 +190,7 @@ pub mod tests {
                 let op = AddOperation {
                     opstamp: 2u64,
                     document: doc,
+                    tokenizer_route: None,
                 };
                 segment_writer.add_document(op, &schema).unwrap();
             }
@@ -16,4 +16,12 @@ pub trait Postings: DocSet {
     /// Returns the list of positions of the term, expressed as a list of
     /// token ordinals.
     fn positions(&self) -> &[u32];
+    /// Returns the list of the offsets `(offset_from, offset_to)`, expressed
+    /// in bytes, of the occurences of the term within the original text.
+    ///
+    /// The list is empty unless the field was indexed with
+    /// `IndexRecordOption::WithFreqsAndPositionsAndOffsets`.
+    fn offsets(&self) -> &[(u32, u32)] {
+        &[]
+    }
 }
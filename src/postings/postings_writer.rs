@@ -9,7 +9,8 @@ use schema::{Field, Schema};
 use std::marker::PhantomData;
 use std::ops::DerefMut;
 use datastruct::stacker::{Heap, TermHashMap};
-use postings::{NothingRecorder, TFAndPositionRecorder, TermFrequencyRecorder};
+use postings::{NothingRecorder, TFAndPositionRecorder, TFPositionAndOffsetRecorder,
+               TermFrequencyRecorder};
 use schema::FieldEntry;
 use schema::FieldType;
 use tokenizer::Token;
@@ -34,9 +35,16 @@ fn posting_from_field_entry<'a>(
                 IndexRecordOption::WithFreqsAndPositions => {
                     SpecializedPostingsWriter::<TFAndPositionRecorder>::new_boxed(heap)
                 }
+                IndexRecordOption::WithFreqsAndPositionsAndOffsets => {
+                    SpecializedPostingsWriter::<TFPositionAndOffsetRecorder>::new_boxed(heap)
+                }
             })
             .unwrap_or_else(|| SpecializedPostingsWriter::<NothingRecorder>::new_boxed(heap)),
-        FieldType::U64(_) | FieldType::I64(_) | FieldType::HierarchicalFacet => {
+        FieldType::U64(_)
+        | FieldType::I64(_)
+        | FieldType::Date(_)
+        | FieldType::F64(_)
+        | FieldType::HierarchicalFacet => {
             SpecializedPostingsWriter::<NothingRecorder>::new_boxed(heap)
         }
     }
@@ -73,7 +81,7 @@ impl<'a> MultiFieldPostingsWriter<'a> {
 
     pub fn subscribe(&mut self, doc: DocId, term: &Term) -> UnorderedTermId {
         let postings_writer = self.per_field_postings_writers[term.field().0 as usize].deref_mut();
-        postings_writer.subscribe(&mut self.term_index, doc, 0u32, term, self.heap)
+        postings_writer.subscribe(&mut self.term_index, doc, 0u32, (0u32, 0u32), term, self.heap)
     }
 
     /// Serialize the inverted index.
@@ -149,6 +157,7 @@ pub trait PostingsWriter {
     ///
     /// * doc  - the document id
     /// * pos  - the term position (expressed in tokens)
+    /// * offsets - the byte offsets `(offset_from, offset_to)` of the token
     /// * term - the term
     /// * heap - heap used to store the postings informations as well as the terms
     /// in the hashmap.
@@ -157,6 +166,7 @@ pub trait PostingsWriter {
         term_index: &mut TermHashMap,
         doc: DocId,
         pos: u32,
+        offsets: (u32, u32),
         term: &Term,
         heap: &Heap,
     ) -> UnorderedTermId;
@@ -183,7 +193,8 @@ pub trait PostingsWriter {
         term.set_field(field);
         let mut sink = |token: &Token| {
             term.set_text(token.text.as_str());
-            self.subscribe(term_index, doc_id, token.position as u32, &term, heap);
+            let offsets = (token.offset_from as u32, token.offset_to as u32);
+            self.subscribe(term_index, doc_id, token.position as u32, offsets, &term, heap);
         };
         token_stream.process(&mut sink)
     }
@@ -217,6 +228,7 @@ impl<'a, Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<'
         term_index: &mut TermHashMap,
         doc: DocId,
         position: u32,
+        offsets: (u32, u32),
         term: &Term,
         heap: &Heap,
     ) -> UnorderedTermId {
@@ -229,7 +241,7 @@ impl<'a, Rec: Recorder + 'static> PostingsWriter for SpecializedPostingsWriter<'
             }
             recorder.new_doc(doc, heap);
         }
-        recorder.record_position(position, heap);
+        recorder.record_position(position, offsets, heap);
         term_ord
     }
 
@@ -24,41 +24,75 @@ struct PositionComputer {
     // the positions vec.
     position_to_skip: Option<usize>,
     positions: Vec<u32>,
+    // number of ints per occurence in the positions stream,
+    // 3 if offsets are recorded, 1 otherwise.
+    stride: usize,
+    offsets: Vec<(u32, u32)>,
+    buffer: Vec<u32>,
     positions_stream: CompressedIntStream,
 }
 
 impl PositionComputer {
-    pub fn new(positions_stream: CompressedIntStream) -> PositionComputer {
+    pub fn new(positions_stream: CompressedIntStream, has_offsets: bool) -> PositionComputer {
         PositionComputer {
             position_to_skip: None,
             positions: vec![],
+            stride: if has_offsets { 3 } else { 1 },
+            offsets: vec![],
+            buffer: vec![],
             positions_stream,
         }
     }
 
     pub fn add_skip(&mut self, num_skip: usize) {
+        let stride = self.stride;
         self.position_to_skip = Some(
             self.position_to_skip
-                .map(|prev_skip| prev_skip + num_skip)
+                .map(|prev_skip| prev_skip + num_skip * stride)
                 .unwrap_or(0),
         );
     }
 
-    pub fn positions(&mut self, term_freq: usize) -> &[u32] {
+    fn load(&mut self, term_freq: usize) {
         if let Some(num_skip) = self.position_to_skip {
             self.positions.resize(term_freq, 0u32);
             self.positions_stream.skip(num_skip);
-            self.positions_stream.read(&mut self.positions[..term_freq]);
-
-            let mut cum = 0u32;
-            for i in 0..term_freq as usize {
-                cum += self.positions[i];
-                self.positions[i] = cum;
+            if self.stride == 1 {
+                self.positions_stream.read(&mut self.positions[..term_freq]);
+                let mut cum = 0u32;
+                for i in 0..term_freq as usize {
+                    cum += self.positions[i];
+                    self.positions[i] = cum;
+                }
+            } else {
+                self.buffer.resize(term_freq * 3, 0u32);
+                self.positions_stream.read(&mut self.buffer[..term_freq * 3]);
+                self.offsets.resize(term_freq, (0u32, 0u32));
+                let mut position = 0u32;
+                let mut offset_from = 0u32;
+                for (i, deltas) in self.buffer[..term_freq * 3].chunks(3).enumerate() {
+                    position += deltas[0];
+                    offset_from = offset_from.wrapping_add(deltas[1]);
+                    self.positions[i] = position;
+                    self.offsets[i] = (offset_from, offset_from + deltas[2]);
+                }
             }
             self.position_to_skip = None;
         }
+    }
+
+    pub fn positions(&mut self, term_freq: usize) -> &[u32] {
+        self.load(term_freq);
         &self.positions[..term_freq]
     }
+
+    pub fn offsets(&mut self, term_freq: usize) -> &[(u32, u32)] {
+        if self.stride == 1 {
+            return &[];
+        }
+        self.load(term_freq);
+        &self.offsets[..term_freq]
+    }
 }
 
 /// `SegmentPostings` represents the inverted list or postings associated to
@@ -96,6 +130,7 @@ impl SegmentPostings {
             docs.len(),
             SourceRead::from(data),
             FreqReadingOption::NoFreq,
+            false,
         );
         SegmentPostings::from_block_postings(block_segment_postings, DeleteBitSet::empty(), None)
     }
@@ -111,8 +146,25 @@ impl SegmentPostings {
         delete_bitset: DeleteBitSet,
         positions_stream_opt: Option<CompressedIntStream>,
     ) -> SegmentPostings {
-        let position_computer =
-            positions_stream_opt.map(|stream| UnsafeCell::new(PositionComputer::new(stream)));
+        SegmentPostings::from_block_postings_with_offsets(
+            segment_block_postings,
+            delete_bitset,
+            positions_stream_opt,
+            false,
+        )
+    }
+
+    /// Reads a Segment postings, whose positions stream
+    /// also holds the offsets of the occurences of the term if `has_offsets`
+    /// is true.
+    pub(crate) fn from_block_postings_with_offsets(
+        segment_block_postings: BlockSegmentPostings,
+        delete_bitset: DeleteBitSet,
+        positions_stream_opt: Option<CompressedIntStream>,
+        has_offsets: bool,
+    ) -> SegmentPostings {
+        let position_computer = positions_stream_opt
+            .map(|stream| UnsafeCell::new(PositionComputer::new(stream, has_offsets)));
         SegmentPostings {
             block_cursor: segment_block_postings,
             cur: COMPRESSION_BLOCK_SIZE, // cursor within the block
@@ -132,6 +184,13 @@ impl SegmentPostings {
         }
     }
 
+    /// Returns true iff the term frequencies are decoded.
+    ///
+    /// Otherwise, `.term_freq()` always returns 1.
+    pub(crate) fn has_freqs(&self) -> bool {
+        self.block_cursor.has_freqs()
+    }
+
     fn position_add_skip<F: FnOnce() -> usize>(&self, num_skips_fn: F) {
         if let Some(position_computer) = self.position_computer.as_ref() {
             let num_skips = num_skips_fn();
@@ -319,6 +378,16 @@ impl Postings for SegmentPostings {
             })
             .unwrap_or(&EMPTY_POSITIONS[..])
     }
+
+    fn offsets(&self) -> &[(u32, u32)] {
+        let term_freq = self.term_freq();
+        self.position_computer
+            .as_ref()
+            .map(|position_computer| unsafe {
+                (&mut *position_computer.get()).offsets(term_freq as usize)
+            })
+            .unwrap_or(&[])
+    }
 }
 
 /// `BlockSegmentPostings` is a cursor iterating over blocks
@@ -332,6 +401,7 @@ pub struct BlockSegmentPostings {
     doc_decoder: BlockDecoder,
     freq_decoder: BlockDecoder,
     freq_reading_option: FreqReadingOption,
+    freqs_requested: bool,
 
     doc_freq: usize,
     doc_offset: DocId,
@@ -345,6 +415,7 @@ impl BlockSegmentPostings {
         doc_freq: usize,
         data: SourceRead,
         freq_reading_option: FreqReadingOption,
+        freqs_requested: bool,
     ) -> BlockSegmentPostings {
         let num_bitpacked_blocks: usize = (doc_freq as usize) / COMPRESSION_BLOCK_SIZE;
         let num_vint_docs = (doc_freq as usize) - COMPRESSION_BLOCK_SIZE * num_bitpacked_blocks;
@@ -354,6 +425,7 @@ impl BlockSegmentPostings {
             doc_decoder: BlockDecoder::new(),
             freq_decoder: BlockDecoder::with_val(1),
             freq_reading_option,
+            freqs_requested,
             remaining_data: data,
             doc_offset: 0,
             doc_freq,
@@ -370,7 +442,15 @@ impl BlockSegmentPostings {
     // # Warning
     //
     // This does not reset the positions list.
-    pub(crate) fn reset(&mut self, doc_freq: usize, postings_data: SourceRead) {
+    //
+    // `term_has_freqs` tells whether the postings of the term
+    // include the term frequencies.
+    pub(crate) fn reset(
+        &mut self,
+        doc_freq: usize,
+        postings_data: SourceRead,
+        term_has_freqs: bool,
+    ) {
         let num_binpacked_blocks: usize = doc_freq / COMPRESSION_BLOCK_SIZE;
         let num_vint_docs = doc_freq & (COMPRESSION_BLOCK_SIZE - 1);
         self.num_bitpacked_blocks = num_binpacked_blocks;
@@ -378,6 +458,20 @@ impl BlockSegmentPostings {
         self.remaining_data = postings_data;
         self.doc_offset = 0;
         self.doc_freq = doc_freq;
+        self.freq_reading_option = if !term_has_freqs {
+            FreqReadingOption::NoFreq
+        } else if self.freqs_requested {
+            FreqReadingOption::ReadFreq
+        } else {
+            FreqReadingOption::SkipFreq
+        };
+        match self.freq_reading_option {
+            FreqReadingOption::ReadFreq => {}
+            FreqReadingOption::NoFreq | FreqReadingOption::SkipFreq => {
+                // the frequencies of the previous term must not leak.
+                self.freq_decoder = BlockDecoder::with_val(1);
+            }
+        }
     }
 
     /// Returns the document frequency associated to this block postings.
@@ -409,6 +503,14 @@ impl BlockSegmentPostings {
         self.freq_decoder.output_array()
     }
 
+    /// Returns true iff the term frequencies are decoded.
+    pub(crate) fn has_freqs(&self) -> bool {
+        match self.freq_reading_option {
+            FreqReadingOption::ReadFreq => true,
+            FreqReadingOption::NoFreq | FreqReadingOption::SkipFreq => false,
+        }
+    }
+
     /// Return the frequency at index `idx` of the block.
     #[inline]
     pub fn freq(&self, idx: usize) -> u32 {
@@ -479,6 +581,7 @@ impl BlockSegmentPostings {
             doc_decoder: BlockDecoder::new(),
             freq_decoder: BlockDecoder::with_val(1),
             freq_reading_option: FreqReadingOption::NoFreq,
+            freqs_requested: false,
 
             remaining_data: From::from(ReadOnlySource::empty()),
             doc_offset: 0,
@@ -5,6 +5,7 @@ use schema::Field;
 use schema::FieldEntry;
 use schema::FieldType;
 use schema::Schema;
+use schema::IndexRecordOption;
 use directory::WritePtr;
 use compression::{BlockEncoder, COMPRESSION_BLOCK_SIZE};
 use DocId;
@@ -108,12 +109,23 @@ impl InvertedIndexSerializer {
 
 /// The field serializer is in charge of
 /// the serialization of a specific field.
+///
+/// If the field has a `freqs_min_doc_freq`, the postings of each term
+/// start with the code of the `IndexRecordOption` of the term.
+/// The documents of a term are then buffered until it is known
+/// whether the term reaches the minimum document frequency.
 pub struct FieldSerializer<'a> {
     term_dictionary_builder: TermDictionaryBuilderImpl<&'a mut CountingWriter<WritePtr>>,
     postings_serializer: PostingsSerializer<&'a mut CountingWriter<WritePtr>>,
     positions_serializer_opt: Option<PositionSerializer<&'a mut CountingWriter<WritePtr>>>,
     current_term_info: TermInfo,
     term_open: bool,
+    record_option: IndexRecordOption,
+    freqs_min_doc_freq: Option<u32>,
+    term_record_option: IndexRecordOption,
+    buffering: bool,
+    buffered_docs: Vec<(DocId, u32, usize)>,
+    buffered_position_deltas: Vec<u32>,
 }
 
 impl<'a> FieldSerializer<'a> {
@@ -123,20 +135,12 @@ impl<'a> FieldSerializer<'a> {
         postings_write: &'a mut CountingWriter<WritePtr>,
         positions_write: &'a mut CountingWriter<WritePtr>,
     ) -> io::Result<FieldSerializer<'a>> {
-        let (term_freq_enabled, position_enabled): (bool, bool) = match field_type {
-            FieldType::Str(ref text_options) => {
-                if let Some(text_indexing_options) = text_options.get_indexing_options() {
-                    let index_option = text_indexing_options.index_option();
-                    (
-                        index_option.is_termfreq_enabled(),
-                        index_option.is_position_enabled(),
-                    )
-                } else {
-                    (false, false)
-                }
-            }
-            _ => (false, false),
-        };
+        let record_option = field_type
+            .get_index_record_option()
+            .unwrap_or(IndexRecordOption::Basic);
+        let freqs_min_doc_freq = field_type.freqs_min_doc_freq();
+        let term_freq_enabled = record_option.is_termfreq_enabled();
+        let position_enabled = record_option.is_position_enabled();
         let term_dictionary_builder =
             TermDictionaryBuilderImpl::new(term_dictionary_write, field_type)?;
         let postings_serializer = PostingsSerializer::new(postings_write, term_freq_enabled);
@@ -152,6 +156,12 @@ impl<'a> FieldSerializer<'a> {
             positions_serializer_opt,
             current_term_info: TermInfo::default(),
             term_open: false,
+            record_option,
+            freqs_min_doc_freq,
+            term_record_option: record_option,
+            buffering: false,
+            buffered_docs: Vec::new(),
+            buffered_position_deltas: Vec::new(),
         })
     }
 
@@ -180,9 +190,59 @@ impl<'a> FieldSerializer<'a> {
         self.term_open = true;
         self.postings_serializer.clear();
         self.current_term_info = self.current_term_info();
+        self.term_record_option = self.record_option;
+        self.buffering = self.freqs_min_doc_freq.is_some();
         self.term_dictionary_builder.insert_key(term)
     }
 
+    /// Restricts the information recorded for the current term,
+    /// typically because it is not available in all of the segments
+    /// being merged.
+    ///
+    /// Only relevant for the fields with a `freqs_min_doc_freq`,
+    /// and must be called before the first call to `write_doc`.
+    pub fn set_term_record_option(&mut self, record_option: IndexRecordOption) {
+        if record_option < self.term_record_option {
+            self.term_record_option = record_option;
+        }
+    }
+
+    fn write_term_doc(
+        &mut self,
+        doc_id: DocId,
+        term_freq: u32,
+        position_deltas: &[u32],
+    ) -> io::Result<()> {
+        self.postings_serializer.write_doc(doc_id, term_freq)?;
+        if self.term_record_option.is_position_enabled() {
+            if let Some(ref mut positions_serializer) = self.positions_serializer_opt.as_mut() {
+                positions_serializer.write(position_deltas)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the record option of the current term,
+    /// followed by its buffered documents.
+    fn flush_buffered_docs(&mut self, record_option: IndexRecordOption) -> io::Result<()> {
+        self.buffering = false;
+        self.term_record_option = record_option;
+        self.postings_serializer.write_record_option(record_option)?;
+        let buffered_docs = ::std::mem::replace(&mut self.buffered_docs, Vec::new());
+        let buffered_position_deltas =
+            ::std::mem::replace(&mut self.buffered_position_deltas, Vec::new());
+        let mut start = 0;
+        for &(doc_id, term_freq, end) in &buffered_docs {
+            self.write_term_doc(doc_id, term_freq, &buffered_position_deltas[start..end])?;
+            start = end;
+        }
+        self.buffered_docs = buffered_docs;
+        self.buffered_docs.clear();
+        self.buffered_position_deltas = buffered_position_deltas;
+        self.buffered_position_deltas.clear();
+        Ok(())
+    }
+
     /// Serialize the information that a document contains the current term,
     /// its term frequency, and the position deltas.
     ///
@@ -190,6 +250,10 @@ impl<'a> FieldSerializer<'a> {
     /// For instance, if the positions are `2, 3, 17`,
     /// `position_deltas` is `2, 1, 14`
     ///
+    /// If the field records offsets, each occurence is described by three
+    /// integers: the position delta, the delta of its `offset_from` with the
+    /// previous occurence's, and the length of the token.
+    ///
     /// Term frequencies and positions may be ignored by the serializer depending
     /// on the configuration of the field in the `Schema`.
     pub fn write_doc(
@@ -199,9 +263,17 @@ impl<'a> FieldSerializer<'a> {
         position_deltas: &[u32],
     ) -> io::Result<()> {
         self.current_term_info.doc_freq += 1;
-        self.postings_serializer.write_doc(doc_id, term_freq)?;
-        if let Some(ref mut positions_serializer) = self.positions_serializer_opt.as_mut() {
-            positions_serializer.write(position_deltas)?;
+        if !self.buffering {
+            return self.write_term_doc(doc_id, term_freq, position_deltas);
+        }
+        self.buffered_position_deltas
+            .extend_from_slice(position_deltas);
+        let end = self.buffered_position_deltas.len();
+        self.buffered_docs.push((doc_id, term_freq, end));
+        let min_doc_freq = self.freqs_min_doc_freq.unwrap_or(0);
+        if self.current_term_info.doc_freq >= min_doc_freq {
+            let record_option = self.term_record_option;
+            self.flush_buffered_docs(record_option)?;
         }
         Ok(())
     }
@@ -212,6 +284,10 @@ impl<'a> FieldSerializer<'a> {
     /// using `VInt` encoding.
     pub fn close_term(&mut self) -> io::Result<()> {
         if self.term_open {
+            if self.buffering {
+                // the term did not reach the minimum document frequency.
+                self.flush_buffered_docs(IndexRecordOption::Basic)?;
+            }
             self.term_dictionary_builder
                 .insert_value(&self.current_term_info)?;
             self.postings_serializer.close_term()?;
@@ -257,6 +333,13 @@ impl<W: Write> PostingsSerializer<W> {
         }
     }
 
+    /// Writes the record option of the current term, and
+    /// only records its term frequencies if the option includes them.
+    fn write_record_option(&mut self, record_option: IndexRecordOption) -> io::Result<()> {
+        self.termfreq_enabled = record_option.is_termfreq_enabled();
+        self.postings_write.write_all(&[record_option.code()])
+    }
+
     pub fn write_doc(&mut self, doc_id: DocId, term_freq: u32) -> io::Result<()> {
         self.doc_ids.push(doc_id);
         if self.termfreq_enabled {
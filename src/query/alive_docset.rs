@@ -0,0 +1,127 @@
+use DocId;
+use Score;
+use docset::{DocSet, SkipResult};
+use fastfield::AliveBitSet;
+use query::Scorer;
+
+/// `DocSet` wrapper skipping the deleted documents
+/// of the underlying `DocSet`.
+///
+/// `Weight::alive_scorer` wraps the scorers of the segments
+/// with deletes, so that collectors only see alive documents.
+pub struct AliveDocSet<TDocSet: DocSet> {
+    docset: TDocSet,
+    alive_bitset: AliveBitSet,
+}
+
+impl<TDocSet: DocSet> AliveDocSet<TDocSet> {
+    /// Wraps a `DocSet`.
+    pub fn new(docset: TDocSet, alive_bitset: AliveBitSet) -> AliveDocSet<TDocSet> {
+        AliveDocSet {
+            docset,
+            alive_bitset,
+        }
+    }
+
+    /// Advances the underlying `DocSet` until it reaches
+    /// an alive document.
+    fn advance_to_alive(&mut self) -> bool {
+        while !self.alive_bitset.is_alive(self.docset.doc()) {
+            if !self.docset.advance() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<TDocSet: DocSet> DocSet for AliveDocSet<TDocSet> {
+    fn advance(&mut self) -> bool {
+        self.docset.advance() && self.advance_to_alive()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        match self.docset.skip_next(target) {
+            SkipResult::End => SkipResult::End,
+            skip_result => {
+                if self.alive_bitset.is_alive(self.docset.doc()) {
+                    skip_result
+                } else if self.advance_to_alive() {
+                    SkipResult::OverStep
+                } else {
+                    SkipResult::End
+                }
+            }
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.docset.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.docset.size_hint()
+    }
+}
+
+impl<TScorer: Scorer> Scorer for AliveDocSet<TScorer> {
+    fn score(&mut self) -> Score {
+        self.docset.score()
+    }
+
+    fn term_freq(&self) -> Option<u32> {
+        self.docset.term_freq()
+    }
+
+    fn positions(&self) -> &[u32] {
+        self.docset.positions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use bit_set::BitSet;
+    use directory::{Directory, RAMDirectory};
+    use fastfield::{write_delete_bitset, DeleteBitSet};
+    use query::VecDocSet;
+    use std::path::Path;
+
+    fn alive_bitset(deleted_docs: &[usize], max_doc: usize) -> AliveBitSet {
+        let path = Path::new("test");
+        let mut directory = RAMDirectory::create();
+        let mut bitset = BitSet::with_capacity(max_doc);
+        for &doc in deleted_docs {
+            bitset.insert(doc);
+        }
+        {
+            let mut writer = directory.open_write(path).unwrap();
+            write_delete_bitset(&bitset, &mut writer).unwrap();
+        }
+        let delete_bitset = DeleteBitSet::open(directory.open_read(path).unwrap());
+        AliveBitSet::new(delete_bitset, max_doc as DocId)
+    }
+
+    #[test]
+    fn test_alive_docset_advance() {
+        let docset = VecDocSet::from(vec![1, 2, 3, 5, 8]);
+        let mut alive_docset = AliveDocSet::new(docset, alive_bitset(&[1, 3, 8], 10));
+        assert!(alive_docset.advance());
+        assert_eq!(alive_docset.doc(), 2);
+        assert!(alive_docset.advance());
+        assert_eq!(alive_docset.doc(), 5);
+        assert!(!alive_docset.advance());
+    }
+
+    #[test]
+    fn test_alive_docset_skip_next() {
+        let docset = VecDocSet::from(vec![1, 2, 3, 5, 8]);
+        let mut alive_docset = AliveDocSet::new(docset, alive_bitset(&[1, 3, 8], 10));
+        assert_eq!(alive_docset.skip_next(2), SkipResult::Reached);
+        assert_eq!(alive_docset.doc(), 2);
+        assert_eq!(alive_docset.skip_next(3), SkipResult::OverStep);
+        assert_eq!(alive_docset.doc(), 5);
+        assert_eq!(alive_docset.skip_next(8), SkipResult::End);
+    }
+}
@@ -1,4 +1,4 @@
-use query::Query;
+use query::{ParallelQuery, Query};
 use query::Weight;
 use query::Scorer;
 use core::SegmentReader;
@@ -7,6 +7,8 @@ use Result;
 use Score;
 use DocId;
 use core::Searcher;
+use query::QueryAst;
+use schema::Schema;
 
 /// Query that matches all of the documents.
 ///
@@ -18,6 +20,16 @@ impl Query for AllQuery {
     fn weight(&self, _: &Searcher, _: bool) -> Result<Box<Weight>> {
         Ok(box AllWeight)
     }
+
+    fn query_ast(&self, _schema: &Schema) -> Option<QueryAst> {
+        Some(QueryAst::All)
+    }
+}
+
+impl ParallelQuery for AllQuery {
+    fn parallel_weight(&self, _: &Searcher, _: bool) -> Result<Box<Weight + Send + Sync>> {
+        Ok(box AllWeight)
+    }
 }
 
 /// Weight associated to the `AllQuery` query.
@@ -31,6 +43,10 @@ impl Weight for AllWeight {
             max_doc: reader.max_doc(),
         })
     }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        Ok(reader.max_doc())
+    }
 }
 
 /// Scorer associated to the `AllQuery` query.
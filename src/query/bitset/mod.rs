@@ -24,6 +24,37 @@ impl BitSetDocSet {
         self.cursor_bucket = bucket_addr;
         self.cursor_tinybitset = self.docs.tinyset(bucket_addr);
     }
+
+    /// Positions the `DocSet` on the first document greater or equal
+    /// to `target`, among the documents that were not emitted yet.
+    ///
+    /// Rather than advancing document by document, this directly jumps
+    /// to the bucket of the target, and masks the documents lower
+    /// than the target within this bucket.
+    /// Empty buckets are skipped using `BitSet::first_non_empty_bucket`.
+    pub fn seek(&mut self, target: DocId) -> SkipResult {
+        let target_bucket = target / 64u32;
+        if target_bucket >= self.docs.num_buckets() {
+            self.cursor_bucket = self.docs.num_buckets();
+            self.cursor_tinybitset = TinySet::empty();
+            return SkipResult::End;
+        }
+        if target_bucket > self.cursor_bucket {
+            self.go_to_bucket(target_bucket);
+        }
+        if target_bucket == self.cursor_bucket {
+            let greater_filter: TinySet = TinySet::range_greater_or_equal(target);
+            self.cursor_tinybitset = self.cursor_tinybitset.intersect(greater_filter);
+        }
+        if !self.advance() {
+            SkipResult::End
+        } else if self.doc() == target {
+            SkipResult::Reached
+        } else {
+            debug_assert!(self.doc() > target);
+            SkipResult::OverStep
+        }
+    }
 }
 
 impl From<BitSet> for BitSetDocSet {
@@ -63,44 +94,10 @@ impl DocSet for BitSetDocSet {
         if !self.advance() {
             return SkipResult::End;
         }
-        let target_bucket = target / 64u32;
-
-        // Mask for all of the bits greater or equal
-        // to our target document.
-        match target_bucket.cmp(&self.cursor_bucket) {
-            Ordering::Greater => {
-                self.go_to_bucket(target_bucket);
-                let greater_filter: TinySet = TinySet::range_greater_or_equal(target);
-                self.cursor_tinybitset = self.cursor_tinybitset.intersect(greater_filter);
-                if !self.advance() {
-                    SkipResult::End
-                } else if self.doc() == target {
-                    SkipResult::Reached
-                } else {
-                    debug_assert!(self.doc() > target);
-                    SkipResult::OverStep
-                }
-            }
-            Ordering::Equal => loop {
-                match self.doc().cmp(&target) {
-                    Ordering::Less => {
-                        if !self.advance() {
-                            return SkipResult::End;
-                        }
-                    }
-                    Ordering::Equal => {
-                        return SkipResult::Reached;
-                    }
-                    Ordering::Greater => {
-                        debug_assert!(self.doc() > target);
-                        return SkipResult::OverStep;
-                    }
-                }
-            },
-            Ordering::Less => {
-                debug_assert!(self.doc() > target);
-                SkipResult::OverStep
-            }
+        match self.doc().cmp(&target) {
+            Ordering::Less => self.seek(target),
+            Ordering::Equal => SkipResult::Reached,
+            Ordering::Greater => SkipResult::OverStep,
         }
     }
 
@@ -219,6 +216,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_docbitset_seek() {
+        {
+            let mut docset = create_docbitset(&[1, 5, 6, 7, 130, 5112], 10_000);
+            assert_eq!(docset.seek(6), SkipResult::Reached);
+            assert_eq!(docset.doc(), 6);
+            assert_eq!(docset.seek(8), SkipResult::OverStep);
+            assert_eq!(docset.doc(), 130);
+            assert_eq!(docset.seek(5000), SkipResult::OverStep);
+            assert_eq!(docset.doc(), 5112);
+            assert_eq!(docset.seek(5113), SkipResult::End);
+            assert!(!docset.advance());
+        }
+        {
+            let mut docset = create_docbitset(&[1, 5112], 10_000);
+            assert_eq!(docset.seek(20_000), SkipResult::End);
+            assert!(!docset.advance());
+        }
+        {
+            let mut docset = create_docbitset(&[], 0);
+            assert!(!docset.advance());
+            assert_eq!(docset.seek(3), SkipResult::End);
+        }
+    }
+
+    #[test]
+    fn test_docbitset_skip_beyond_max_value() {
+        let mut docset = create_docbitset(&[1, 5, 6, 7, 5112], 5_113);
+        assert_eq!(docset.skip_next(6_000), SkipResult::End);
+        assert!(!docset.advance());
+    }
+
     #[bench]
     fn bench_bitset_1pct_insert(b: &mut test::Bencher) {
         use tests;
@@ -2,46 +2,144 @@ use Result;
 use super::boolean_weight::BooleanWeight;
 use query::Weight;
 use Searcher;
-use query::Query;
+use query::{Query, WeightCache};
 use schema::Term;
 use query::TermQuery;
 use schema::IndexRecordOption;
 use query::Occur;
+use schema::Schema;
+use query::{BooleanClauseAst, QueryAst};
+use std::collections::BTreeSet;
 
 /// The boolean query combines a set of queries
 ///
 /// The documents matched by the boolean query are
 /// those which
 /// * match all of the sub queries associated with the
-/// `Must` occurence
+/// `Must` or `Filter` occurence
 /// * match none of the sub queries associated with the
 /// `MustNot` occurence.
 /// * match at least one of the subqueries that is not
 /// a `MustNot` occurence.
+///
+/// Only the `Should` and `Must` sub queries contribute to the score.
+/// `Filter` sub queries are evaluated with scoring disabled.
+///
+/// Setting a minimum should match additionally requires
+/// the documents to match at least that many of the
+/// sub queries associated with the `Should` occurence.
 #[derive(Debug)]
 pub struct BooleanQuery {
     subqueries: Vec<(Occur, Box<Query>)>,
+    minimum_should_match: usize,
 }
 
 impl From<Vec<(Occur, Box<Query>)>> for BooleanQuery {
     fn from(subqueries: Vec<(Occur, Box<Query>)>) -> BooleanQuery {
-        BooleanQuery { subqueries }
+        BooleanQuery {
+            subqueries,
+            minimum_should_match: 0,
+        }
     }
 }
 
 impl Query for BooleanQuery {
+    /// The sub queries appearing several times in the boolean tree,
+    /// e.g. `(+"a b" +c) (+"a b" +d)`, share their weight: their documents and
+    /// scores are only computed once per segment.
     fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+        self.weight_with_cache(searcher, scoring_enabled, &mut WeightCache::default())
+    }
+
+    fn weight_with_cache(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+        weight_cache: &mut WeightCache,
+    ) -> Result<Box<Weight>> {
         let sub_weights = self.subqueries
             .iter()
             .map(|&(ref occur, ref subquery)| {
-                Ok((*occur, subquery.weight(searcher, scoring_enabled)?))
+                let scoring_enabled = scoring_enabled && occur.is_scoring();
+                let sub_weight = weight_cache.weight(&**subquery, searcher, scoring_enabled)?;
+                Ok((*occur, sub_weight))
             })
             .collect::<Result<_>>()?;
-        Ok(box BooleanWeight::new(sub_weights, scoring_enabled))
+        let mut boolean_weight = BooleanWeight::new(sub_weights, scoring_enabled);
+        boolean_weight.set_minimum_should_match(self.minimum_should_match);
+        let weight: Box<Weight> = box boolean_weight;
+        Ok(weight)
+    }
+
+    /// Returns the sub queries within parentheses, prefixed
+    /// with `+` for `Must` and `-` for `MustNot`, e.g. `(+title:"a" -title:"b")`.
+    ///
+    /// Queries with a minimum should match, empty queries,
+    /// queries with only `MustNot` sub queries and queries
+    /// with `Filter` sub queries cannot be expressed.
+    fn to_query_string(&self, schema: &Schema) -> Option<String> {
+        let has_must_clause = self.subqueries
+            .iter()
+            .any(|&(occur, _)| occur.is_required());
+        if self.minimum_should_match > 1 || (self.minimum_should_match == 1 && has_must_clause)
+            || self.subqueries
+                .iter()
+                .all(|&(occur, _)| occur == Occur::MustNot)
+        {
+            return None;
+        }
+        let mut subquery_strings = Vec::with_capacity(self.subqueries.len());
+        for &(occur, ref subquery) in &self.subqueries {
+            let prefix = match occur {
+                Occur::Must => "+",
+                Occur::MustNot => "-",
+                Occur::Should => "",
+                Occur::Filter => return None,
+            };
+            subquery_strings.push(format!("{}{}", prefix, subquery.to_query_string(schema)?));
+        }
+        Some(format!("({})", subquery_strings.join(" ")))
+    }
+
+    fn query_terms(&self, term_set: &mut BTreeSet<Term>) {
+        for &(occur, ref subquery) in &self.subqueries {
+            if occur != Occur::MustNot {
+                subquery.query_terms(term_set);
+            }
+        }
+    }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        let clauses = self.subqueries
+            .iter()
+            .map(|&(occur, ref subquery)| {
+                Some(BooleanClauseAst {
+                    occur,
+                    query: subquery.query_ast(schema)?,
+                })
+            })
+            .collect::<Option<Vec<BooleanClauseAst>>>()?;
+        Some(QueryAst::Boolean {
+            clauses,
+            minimum_should_match: self.minimum_should_match,
+        })
     }
 }
 
 impl BooleanQuery {
+    /// Sets the minimum number of `Should` sub queries a document
+    /// has to match. Defaults to 0.
+    ///
+    /// No document matches if it exceeds the number of `Should` sub queries.
+    pub fn set_minimum_should_match(&mut self, minimum_should_match: usize) {
+        self.minimum_should_match = minimum_should_match;
+    }
+
+    /// Returns the minimum number of `Should` sub queries a document has to match.
+    pub fn minimum_should_match(&self) -> usize {
+        self.minimum_should_match
+    }
+
     /// Helper method to create a boolean query matching a given list of terms.
     /// The resulting query is a disjunction of the terms.
     pub fn new_multiterms_query(terms: Vec<Term>) -> BooleanQuery {
@@ -1,6 +1,6 @@
 use query::Weight;
 use core::SegmentReader;
-use query::{Intersection, Union};
+use query::{Intersection, MinShouldMatch, Union};
 use std::collections::HashMap;
 use query::EmptyScorer;
 use query::Scorer;
@@ -11,6 +11,12 @@ use query::Exclude;
 use query::Occur;
 use query::RequiredOptionalScorer;
 use query::score_combiner::{DoNothingCombiner, ScoreCombiner, SumWithCoordsCombiner};
+use query::Explanation;
+use query::{BitSetDocSet, ConstScorer};
+use common::BitSet;
+use query::weight::does_not_match;
+use docset::{DocSet, SkipResult};
+use DocId;
 use Result;
 
 fn scorer_union<TScoreCombiner>(scorers: Vec<Box<Scorer>>) -> Box<Scorer>
@@ -39,9 +45,39 @@ where
     }
 }
 
+/// A filter matching fewer than one document every `FILTER_STREAMING_RATIO`
+/// documents is streamed rather than materialized as a bitset.
+///
+/// The bitset holds a word for every 64 documents: building it would
+/// cost more than skipping through such a sparse filter.
+const FILTER_STREAMING_RATIO: u32 = 64;
+
+/// Returns the intersection of the scorers of the `Filter` clauses,
+/// which are sorted by increasing count estimate.
+///
+/// Unless the most selective filter, matching `min_estimate` documents,
+/// is sparse, the documents are materialized as a bitset, which is then
+/// cheap to skip through.
+fn filter_docset(scorers: Vec<Box<Scorer>>, min_estimate: u32, max_doc: DocId) -> Box<Scorer> {
+    let mut scorer: Box<Scorer> = if scorers.len() == 1 {
+        scorers.into_iter().next().unwrap()
+    } else {
+        box Intersection::from_ordered(scorers)
+    };
+    if min_estimate < max_doc / FILTER_STREAMING_RATIO {
+        return scorer;
+    }
+    let mut doc_bitset = BitSet::with_max_value(max_doc);
+    while scorer.advance() {
+        doc_bitset.insert(scorer.doc());
+    }
+    box ConstScorer::new(BitSetDocSet::from(doc_bitset))
+}
+
 pub struct BooleanWeight {
     weights: Vec<(Occur, Box<Weight>)>,
     scoring_enabled: bool,
+    minimum_should_match: usize,
 }
 
 impl BooleanWeight {
@@ -49,25 +85,79 @@ impl BooleanWeight {
         BooleanWeight {
             weights,
             scoring_enabled,
+            minimum_should_match: 0,
         }
     }
 
+    /// Sets the minimum number of `Should` clauses a document has to match.
+    pub fn set_minimum_should_match(&mut self, minimum_should_match: usize) {
+        self.minimum_should_match = minimum_should_match;
+    }
+
+    fn num_should_clauses(&self) -> usize {
+        self.weights
+            .iter()
+            .filter(|&&(occur, _)| occur == Occur::Should)
+            .count()
+    }
+
+    /// Returns true if the `Should` clauses are required to match,
+    /// beyond the implicit requirement of one `Should` clause
+    /// in the absence of `Must` or `Filter` clause.
+    fn requires_should_clauses(&self) -> bool {
+        let has_must_clause = self.weights
+            .iter()
+            .any(|&(occur, _)| occur.is_required());
+        self.minimum_should_match > 1 || (self.minimum_should_match == 1 && has_must_clause)
+    }
+
+    /// Returns the count estimates of the `Must` and `Filter` clauses,
+    /// aligned with `self.weights`, and `None` for the other clauses.
+    fn required_estimates(&self, reader: &SegmentReader) -> Result<Vec<Option<u32>>> {
+        self.weights
+            .iter()
+            .map(|&(occur, ref weight)| {
+                if occur.is_required() {
+                    weight.count_estimate(reader).map(Some)
+                } else {
+                    Ok(None)
+                }
+            })
+            .collect()
+    }
+
+    /// The `Must` and `Filter` clauses are intersected from the
+    /// most selective to the least selective, according to `estimates`.
     fn complex_scorer<TScoreCombiner: ScoreCombiner>(
         &self,
         reader: &SegmentReader,
+        estimates: &[Option<u32>],
     ) -> Result<Box<Scorer>> {
+        // the other clauses keep their relative order.
+        let mut clause_ords: Vec<usize> = (0..self.weights.len()).collect();
+        clause_ords.sort_by_key(|&ord| estimates[ord].unwrap_or_else(u32::max_value));
         let mut per_occur_scorers: HashMap<Occur, Vec<Box<Scorer>>> = HashMap::new();
-        for &(ref occur, ref subweight) in &self.weights {
+        for ord in clause_ords {
+            let &(occur, ref subweight) = &self.weights[ord];
             let sub_scorer: Box<Scorer> = subweight.scorer(reader)?;
             per_occur_scorers
-                .entry(*occur)
+                .entry(occur)
                 .or_insert_with(Vec::new)
                 .push(sub_scorer);
         }
 
-        let should_scorer_opt: Option<Box<Scorer>> = per_occur_scorers
-            .remove(&Occur::Should)
-            .map(scorer_union::<TScoreCombiner>);
+        let requires_should_clauses = self.requires_should_clauses();
+        let minimum_should_match = self.minimum_should_match;
+        let should_scorer_opt: Option<Box<Scorer>> =
+            per_occur_scorers.remove(&Occur::Should).map(|scorers| {
+                if requires_should_clauses {
+                    let scorer: Box<Scorer> =
+                        box MinShouldMatch::<_, TScoreCombiner>::new(scorers, minimum_should_match);
+                    scorer
+                } else {
+                    scorer_union::<TScoreCombiner>(scorers)
+                }
+            });
 
         let exclude_scorer_opt: Option<Box<Scorer>> = per_occur_scorers
             .remove(&Occur::MustNot)
@@ -87,18 +177,44 @@ impl BooleanWeight {
                             .into_iter()
                             .map(|scorer| *Downcast::<TermScorer>::downcast(scorer).unwrap())
                             .collect();
-                        let scorer: Box<Scorer> = box Intersection::from(scorers);
+                        let scorer: Box<Scorer> = box Intersection::from_ordered(scorers);
                         scorer
                     } else {
-                        let scorer: Box<Scorer> = box Intersection::from(scorers);
+                        let scorer: Box<Scorer> = box Intersection::from_ordered(scorers);
                         scorer
                     }
                 }
             });
 
+        // the filters do not contribute to the score.
+        let min_filter_estimate = self.weights
+            .iter()
+            .zip(estimates)
+            .filter(|&(&(occur, _), _)| occur == Occur::Filter)
+            .filter_map(|(_, &estimate)| estimate)
+            .min()
+            .unwrap_or(0);
+        let filter_scorer_opt: Option<Box<Scorer>> =
+            per_occur_scorers.remove(&Occur::Filter).map(|scorers| {
+                let filter_docset = filter_docset(scorers, min_filter_estimate, reader.max_doc());
+                let scorer: Box<Scorer> = box ConstScorer::with_score(filter_docset, 0f32);
+                scorer
+            });
+
+        let must_scorer_opt: Option<Box<Scorer>> = match (must_scorer_opt, filter_scorer_opt) {
+            (Some(must_scorer), Some(filter_scorer)) => {
+                let scorer: Box<Scorer> = box Intersection::from(vec![must_scorer, filter_scorer]);
+                Some(scorer)
+            }
+            (must_scorer_opt, None) => must_scorer_opt,
+            (None, filter_scorer_opt) => filter_scorer_opt,
+        };
+
         let positive_scorer: Box<Scorer> = match (should_scorer_opt, must_scorer_opt) {
             (Some(should_scorer), Some(must_scorer)) => {
-                if self.scoring_enabled {
+                if requires_should_clauses {
+                    box Intersection::from(vec![must_scorer, should_scorer])
+                } else if self.scoring_enabled {
                     box RequiredOptionalScorer::<_, _, TScoreCombiner>::new(
                         must_scorer,
                         should_scorer,
@@ -122,21 +238,83 @@ impl BooleanWeight {
     }
 }
 
+/// Returns true if `doc` is matched by the scorer of `weight`.
+fn weight_matches(weight: &Weight, reader: &SegmentReader, doc: DocId) -> Result<bool> {
+    Ok(weight.scorer(reader)?.skip_next(doc) == SkipResult::Reached)
+}
+
 impl Weight for BooleanWeight {
     fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
-        if self.weights.is_empty() {
+        if self.weights.is_empty() || self.minimum_should_match > self.num_should_clauses() {
+            return Ok(box EmptyScorer);
+        }
+        let estimates = self.required_estimates(reader)?;
+        // building the scorers is skipped if a required clause matches no document.
+        if estimates.contains(&Some(0)) {
             Ok(box EmptyScorer)
         } else if self.weights.len() == 1 {
             let &(occur, ref weight) = &self.weights[0];
-            if occur == Occur::MustNot {
-                Ok(box EmptyScorer)
-            } else {
-                weight.scorer(reader)
+            match occur {
+                Occur::MustNot => Ok(box EmptyScorer),
+                Occur::Filter => Ok(box ConstScorer::with_score(weight.scorer(reader)?, 0f32)),
+                Occur::Should | Occur::Must => weight.scorer(reader),
             }
         } else if self.scoring_enabled {
-            self.complex_scorer::<SumWithCoordsCombiner>(reader)
+            self.complex_scorer::<SumWithCoordsCombiner>(reader, &estimates)
         } else {
-            self.complex_scorer::<DoNothingCombiner>(reader)
+            self.complex_scorer::<DoNothingCombiner>(reader, &estimates)
+        }
+    }
+
+    /// The score is detailed with the explanations of
+    /// the `Must` and `Should` clauses matching the document.
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        let mut scorer = self.scorer(reader)?;
+        if scorer.skip_next(doc) != SkipResult::Reached {
+            bail!(does_not_match(doc));
+        }
+        if self.weights.len() == 1 && self.weights[0].0.is_scoring() {
+            return self.weights[0].1.explain(reader, doc);
+        }
+        let mut details = Vec::new();
+        for &(occur, ref weight) in &self.weights {
+            if occur.is_scoring() && weight_matches(weight.as_ref(), reader, doc)? {
+                details.push(weight.explain(reader, doc)?);
+            }
+        }
+        let description = format!(
+            "sum of, coord {}/{} clauses matching",
+            details.len(),
+            self.weights.len()
+        );
+        let mut explanation = Explanation::new(description, scorer.score());
+        for detail in details {
+            explanation.add_detail(detail);
+        }
+        Ok(explanation)
+    }
+
+    /// The estimate is bounded by the smallest `Must` or `Filter` clause, or
+    /// by the sum of the `Should` clauses in the absence of `Must` clause.
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        if self.minimum_should_match > self.num_should_clauses() {
+            return Ok(0);
+        }
+        let mut must_estimate: Option<u32> = None;
+        let mut should_estimate = 0u32;
+        for &(occur, ref weight) in &self.weights {
+            match occur {
+                Occur::Must | Occur::Filter => {
+                    let estimate = weight.count_estimate(reader)?;
+                    must_estimate = Some(must_estimate.map_or(estimate, |e| e.min(estimate)));
+                }
+                Occur::Should => {
+                    should_estimate =
+                        should_estimate.saturating_add(weight.count_estimate(reader)?);
+                }
+                Occur::MustNot => {}
+            }
         }
+        Ok(must_estimate.unwrap_or_else(|| should_estimate.min(reader.max_doc())))
     }
 }
@@ -65,6 +65,32 @@ mod tests {
         assert_eq!(query.count(&*index.searcher()).unwrap(), 3);
     }
 
+    #[test]
+    pub fn test_boolean_explain() {
+        use DocAddress;
+        use docset::DocSet;
+        let (index, text_field) = aux_test_helper();
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let query = query_parser.parse_query("+a b d").unwrap();
+        let searcher = index.searcher();
+        let weight = query.weight(&*searcher, true).unwrap();
+        let mut scorer = weight.scorer(searcher.segment_reader(0u32)).unwrap();
+        assert!(scorer.advance());
+        assert_eq!(scorer.doc(), 0);
+        let explanation = query.explain(&*searcher, DocAddress(0, 0)).unwrap();
+        assert_eq!(explanation.value(), scorer.score());
+        // `a` and `b` match, `d` does not.
+        assert_eq!(explanation.details().len(), 2);
+        let sum: f32 = explanation
+            .details()
+            .iter()
+            .map(|detail| detail.value())
+            .sum();
+        assert!((sum - explanation.value()).abs() < 0.0001f32);
+        // the third document does not contain `a`.
+        assert!(query.explain(&*searcher, DocAddress(0, 2)).is_err());
+    }
+
     #[test]
     pub fn test_boolean_single_must_clause() {
         let (index, text_field) = aux_test_helper();
@@ -170,4 +196,261 @@ mod tests {
             assert_eq!(matching_docs(&boolean_query), Vec::<u32>::new());
         }
     }
+
+    #[test]
+    pub fn test_boolean_minimum_should_match() {
+        let (index, text_field) = aux_test_helper();
+        let make_term_query = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            );
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let matching_docs = |occur_texts: Vec<(Occur, &str)>, minimum_should_match: usize| {
+            let mut boolean_query = BooleanQuery::from(
+                occur_texts
+                    .into_iter()
+                    .map(|(occur, text)| (occur, make_term_query(text)))
+                    .collect::<Vec<_>>(),
+            );
+            boolean_query.set_minimum_should_match(minimum_should_match);
+            let searcher = index.searcher();
+            let mut test_collector = TestCollector::default();
+            searcher.search(&boolean_query, &mut test_collector).unwrap();
+            let docs = test_collector.docs();
+            assert_eq!(boolean_query.count(&*searcher).unwrap(), docs.len());
+            docs
+        };
+        let should_abd = || {
+            vec![
+                (Occur::Should, "a"),
+                (Occur::Should, "b"),
+                (Occur::Should, "d"),
+            ]
+        };
+        assert_eq!(matching_docs(should_abd(), 1), vec![0, 1, 2, 3, 4]);
+        assert_eq!(matching_docs(should_abd(), 2), vec![0, 3]);
+        assert_eq!(matching_docs(should_abd(), 3), vec![3]);
+        assert_eq!(matching_docs(should_abd(), 4), Vec::<u32>::new());
+        let must_c_should_ad = vec![
+            (Occur::Must, "c"),
+            (Occur::Should, "a"),
+            (Occur::Should, "d"),
+        ];
+        assert_eq!(matching_docs(must_c_should_ad.clone(), 0), vec![0, 1, 2, 3]);
+        assert_eq!(matching_docs(must_c_should_ad.clone(), 1), vec![0, 1, 3]);
+        assert_eq!(matching_docs(must_c_should_ad, 2), vec![3]);
+        assert_eq!(
+            matching_docs(
+                vec![
+                    (Occur::Should, "a"),
+                    (Occur::Should, "b"),
+                    (Occur::MustNot, "d"),
+                ],
+                2
+            ),
+            vec![0]
+        );
+    }
+
+    #[test]
+    pub fn test_boolean_filter() {
+        use docset::DocSet;
+        use DocAddress;
+        let (index, text_field) = aux_test_helper();
+        let searcher = index.searcher();
+        let make_term_query = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::WithFreqs,
+            );
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let scored_docs = |query: &Query| {
+            let weight = query.weight(&*searcher, true).unwrap();
+            let mut scorer = weight.scorer(searcher.segment_reader(0u32)).unwrap();
+            let mut scored_docs = Vec::new();
+            while scorer.advance() {
+                scored_docs.push((scorer.doc(), scorer.score()));
+            }
+            assert_eq!(query.count(&*searcher).unwrap(), scored_docs.len());
+            scored_docs
+        };
+        let a_scores = scored_docs(&*make_term_query("a"));
+        {
+            let boolean_query = BooleanQuery::from(vec![(Occur::Filter, make_term_query("b"))]);
+            assert_eq!(
+                scored_docs(&boolean_query),
+                vec![(0, 0f32), (2, 0f32), (3, 0f32)]
+            );
+        }
+        {
+            // the filter does not contribute to the score,
+            // and makes the should clause optional.
+            let boolean_query = BooleanQuery::from(vec![
+                (Occur::Should, make_term_query("a")),
+                (Occur::Filter, make_term_query("c")),
+            ]);
+            assert_eq!(
+                scored_docs(&boolean_query),
+                vec![a_scores[0], a_scores[1], (2, 0f32), a_scores[2]]
+            );
+        }
+        {
+            let boolean_query = BooleanQuery::from(vec![
+                (Occur::Must, make_term_query("a")),
+                (Occur::Filter, make_term_query("b")),
+                (Occur::Filter, make_term_query("c")),
+                (Occur::MustNot, make_term_query("d")),
+            ]);
+            assert_eq!(scored_docs(&boolean_query), vec![a_scores[0]]);
+            let explanation = boolean_query.explain(&*searcher, DocAddress(0, 0)).unwrap();
+            assert_eq!(explanation.details().len(), 1);
+        }
+        {
+            let boolean_query = BooleanQuery::from(vec![
+                (Occur::Filter, make_term_query("a")),
+                (Occur::Filter, make_term_query("e")),
+            ]);
+            assert!(scored_docs(&boolean_query).is_empty());
+        }
+    }
+
+    #[test]
+    pub fn test_boolean_count_estimate() {
+        let (index, text_field) = aux_test_helper();
+        let searcher = index.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let make_term_query = |text: &str| {
+            let term_query = TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            );
+            let query: Box<Query> = box term_query;
+            query
+        };
+        let count_estimate = |boolean_query: BooleanQuery| {
+            let weight = boolean_query.weight(&*searcher, true).unwrap();
+            weight.count_estimate(segment_reader).unwrap()
+        };
+        assert_eq!(
+            count_estimate(BooleanQuery::from(vec![
+                (Occur::Must, make_term_query("a")),
+                (Occur::Must, make_term_query("d")),
+            ])),
+            2
+        );
+        assert_eq!(
+            count_estimate(BooleanQuery::from(vec![
+                (Occur::Should, make_term_query("a")),
+                (Occur::Should, make_term_query("b")),
+            ])),
+            5
+        );
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Must, make_term_query("a")),
+            (Occur::Must, make_term_query("z")),
+            (Occur::Should, make_term_query("b")),
+        ]);
+        let weight = boolean_query.weight(&*searcher, true).unwrap();
+        assert_eq!(weight.count_estimate(segment_reader).unwrap(), 0);
+        assert_eq!(weight.count(segment_reader).unwrap(), 0);
+    }
+
+    #[test]
+    pub fn test_boolean_builds_scorers_once() {
+        use core::{Searcher, SegmentReader};
+        use query::Weight;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use Result;
+
+        // relies on the default count estimate.
+        #[derive(Debug)]
+        struct CountingQuery {
+            term_query: TermQuery,
+            num_scorers: Arc<AtomicUsize>,
+        }
+
+        struct CountingWeight {
+            weight: Box<Weight>,
+            num_scorers: Arc<AtomicUsize>,
+        }
+
+        impl Query for CountingQuery {
+            fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+                Ok(box CountingWeight {
+                    weight: self.term_query.weight(searcher, scoring_enabled)?,
+                    num_scorers: Arc::clone(&self.num_scorers),
+                })
+            }
+        }
+
+        impl Weight for CountingWeight {
+            fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+                self.num_scorers.fetch_add(1, Ordering::SeqCst);
+                self.weight.scorer(reader)
+            }
+        }
+
+        let (index, text_field) = aux_test_helper();
+        let searcher = index.searcher();
+        let make_term_query = |text: &str| {
+            TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            )
+        };
+        let num_scorers = Arc::new(AtomicUsize::new(0));
+        let counting_query: Box<Query> = box CountingQuery {
+            term_query: make_term_query("a"),
+            num_scorers: Arc::clone(&num_scorers),
+        };
+        let term_query: Box<Query> = box make_term_query("b");
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Must, counting_query),
+            (Occur::Must, term_query),
+        ]);
+        assert_eq!(boolean_query.count(&*searcher).unwrap(), 2);
+        assert_eq!(num_scorers.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    pub fn test_boolean_sparse_filter() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000 {
+                let text = if i % 250 == 0 { "a b" } else { "a" };
+                index_writer.add_document(doc!(text_field => text));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let make_term_query = |text: &str| {
+            let query: Box<Query> = box TermQuery::new(
+                Term::from_field_text(text_field, text),
+                IndexRecordOption::Basic,
+            );
+            query
+        };
+        // `b` is streamed, as it matches 4 of the 1000 documents.
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Must, make_term_query("a")),
+            (Occur::Filter, make_term_query("b")),
+        ]);
+        assert_eq!(boolean_query.count(&*searcher).unwrap(), 4);
+        // `a` is materialized as a bitset.
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Must, make_term_query("b")),
+            (Occur::Filter, make_term_query("a")),
+        ]);
+        assert_eq!(boolean_query.count(&*searcher).unwrap(), 4);
+    }
 }
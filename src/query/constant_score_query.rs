@@ -0,0 +1,198 @@
+use core::{Searcher, SegmentReader};
+use query::{create_weight, ConstScorer, Explanation, Query, Scorer, Weight, WeightCache};
+use query::weight::does_not_match;
+use docset::{DocSet, SkipResult};
+use DocId;
+use Result;
+use Score;
+use query::QueryAst;
+use schema::{Schema, Term};
+use std::collections::BTreeSet;
+
+/// `ConstantScoreQuery` matches the documents of an underlying query,
+/// and gives all of them the same score.
+///
+/// The underlying query is evaluated with scoring disabled,
+/// which may make it cheaper to evaluate.
+///
+/// Within a `BooleanQuery`, a `ConstantScoreQuery` with a score of 0
+/// acts as a pure filter: it restricts the matching documents
+/// without affecting their relevance. `Occur::Filter` clauses
+/// achieve the same, and additionally have their matches
+/// materialized as a bitset.
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tantivy;
+/// # use tantivy::Index;
+/// # use tantivy::collector::TopCollector;
+/// # use tantivy::schema::{SchemaBuilder, TEXT, INT_INDEXED};
+/// # use tantivy::query::{BooleanQuery, ConstantScoreQuery, Occur, Query};
+/// # use tantivy::query::{RangeQuery, TermQuery};
+/// # use tantivy::schema::IndexRecordOption;
+/// # use tantivy::{Result, Term};
+/// #
+/// # fn run() -> Result<()> {
+/// #     let mut schema_builder = SchemaBuilder::new();
+/// #     let title = schema_builder.add_text_field("title", TEXT);
+/// #     let year = schema_builder.add_u64_field("year", INT_INDEXED);
+/// #     let index = Index::create_in_ram(schema_builder.build());
+/// #     {
+/// #         let mut index_writer = index.writer_with_num_threads(1, 6_000_000)?;
+/// #         index_writer.add_document(doc!(title => "The Diary of Muadib", year => 1965u64));
+/// #         index_writer.add_document(doc!(title => "A Dairy Cow", year => 2008u64));
+/// #         index_writer.commit()?;
+/// #     }
+/// #     index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let term = Term::from_field_text(title, "diary");
+/// let term_query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+/// let year_range = RangeQuery::new_u64(year, 1960..1970);
+/// let year_filter = ConstantScoreQuery::new(Box::new(year_range), 0f32);
+/// let query = BooleanQuery::from(vec![
+///     (Occur::Must, Box::new(term_query) as Box<Query>),
+///     (Occur::Must, Box::new(year_filter) as Box<Query>),
+/// ]);
+/// let mut top_collector = TopCollector::with_limit(10);
+/// searcher.search(&query, &mut top_collector)?;
+/// assert_eq!(top_collector.docs().len(), 1);
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #   run().unwrap()
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ConstantScoreQuery {
+    query: Box<Query>,
+    score: Score,
+}
+
+impl ConstantScoreQuery {
+    /// Creates a query matching the documents of `query`, all with the score `score`.
+    pub fn new(query: Box<Query>, score: Score) -> ConstantScoreQuery {
+        ConstantScoreQuery { query, score }
+    }
+
+    /// Returns the underlying query.
+    pub fn query(&self) -> &Query {
+        &*self.query
+    }
+
+    /// Returns the score of the matching documents.
+    pub fn score(&self) -> Score {
+        self.score
+    }
+}
+
+impl Query for ConstantScoreQuery {
+    fn weight(&self, searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        Ok(box ConstantScoreWeight {
+            weight: create_weight(&*self.query, searcher, false)?,
+            score: self.score,
+        })
+    }
+
+    fn weight_with_cache(
+        &self,
+        searcher: &Searcher,
+        _scoring_enabled: bool,
+        weight_cache: &mut WeightCache,
+    ) -> Result<Box<Weight>> {
+        Ok(box ConstantScoreWeight {
+            weight: weight_cache.weight(&*self.query, searcher, false)?,
+            score: self.score,
+        })
+    }
+
+    fn query_terms(&self, term_set: &mut BTreeSet<Term>) {
+        self.query.query_terms(term_set);
+    }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        Some(QueryAst::ConstantScore {
+            query: box self.query.query_ast(schema)?,
+            score: self.score,
+        })
+    }
+}
+
+/// Weight associated to the `ConstantScoreQuery`.
+pub struct ConstantScoreWeight {
+    weight: Box<Weight>,
+    score: Score,
+}
+
+impl Weight for ConstantScoreWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let scorer = self.weight.scorer(reader)?;
+        Ok(box ConstScorer::with_score(scorer, self.score))
+    }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        self.weight.count_estimate(reader)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        if self.weight.scorer(reader)?.skip_next(doc) != SkipResult::Reached {
+            bail!(does_not_match(doc));
+        }
+        Ok(Explanation::new("constant score", self.score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::ConstantScoreQuery;
+    use collector::TopCollector;
+    use query::{BooleanQuery, Occur, Query, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, TEXT};
+    use Index;
+    use Term;
+
+    #[test]
+    fn test_constant_score_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a a a b"));
+            index_writer.add_document(doc!(text => "a c"));
+            index_writer.add_document(doc!(text => "b c c"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |word: &str| -> Box<Query> {
+            box TermQuery::new(Term::from_field_text(text, word), IndexRecordOption::WithFreqs)
+        };
+        let score_docs = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(query, &mut top_collector).unwrap();
+            let mut score_docs: Vec<(f32, u32)> = top_collector
+                .score_docs()
+                .into_iter()
+                .map(|(score, doc_address)| (score, doc_address.doc()))
+                .collect();
+            score_docs.sort_by_key(|&(_, doc)| doc);
+            score_docs
+        };
+        let constant_score_query = ConstantScoreQuery::new(term_query("a"), 0.5f32);
+        assert_eq!(score_docs(&constant_score_query), vec![(0.5f32, 0), (0.5f32, 1)]);
+        assert_eq!(constant_score_query.count(&*searcher).unwrap(), 2);
+
+        // the filter does not change the scores of the other clause.
+        let filtered_query = BooleanQuery::from(vec![
+            (Occur::Must, term_query("c")),
+            (Occur::Must, box ConstantScoreQuery::new(term_query("a"), 0f32) as Box<Query>),
+        ]);
+        let c_score_docs = score_docs(&*term_query("c"));
+        let filtered_score_docs = score_docs(&filtered_query);
+        assert_eq!(filtered_score_docs.len(), 1);
+        assert_eq!(filtered_score_docs[0], c_score_docs[0]);
+    }
+}
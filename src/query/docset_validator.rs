@@ -0,0 +1,264 @@
+use Result;
+use core::Searcher;
+use query::{Query, Weight, WeightCache};
+#[cfg(feature = "docset-validation")]
+use self::validation::ValidatingWeight;
+
+/// Creates the weight of `query`.
+///
+/// With the `docset-validation` feature, the scorers created by
+/// the weight check the `DocSet` contract, and panic with
+/// the offending query if it is broken.
+/// Searches and sub-queries go through this function.
+pub(crate) fn create_weight<TQuery: Query + ?Sized>(
+    query: &TQuery,
+    searcher: &Searcher,
+    scoring_enabled: bool,
+) -> Result<Box<Weight>> {
+    let weight = query.weight(searcher, scoring_enabled)?;
+    #[cfg(feature = "docset-validation")]
+    let weight: Box<Weight> = box ValidatingWeight::new(weight, format!("{:?}", query));
+    Ok(weight)
+}
+
+/// Creates the weight of a sub query of a boolean query, like `create_weight`,
+/// sharing the weights of equivalent sub queries through `weight_cache`.
+pub(crate) fn create_weight_with_cache<TQuery: Query + ?Sized>(
+    query: &TQuery,
+    searcher: &Searcher,
+    scoring_enabled: bool,
+    weight_cache: &mut WeightCache,
+) -> Result<Box<Weight>> {
+    let weight = query.weight_with_cache(searcher, scoring_enabled, weight_cache)?;
+    #[cfg(feature = "docset-validation")]
+    let weight: Box<Weight> = box ValidatingWeight::new(weight, format!("{:?}", query));
+    Ok(weight)
+}
+
+#[cfg(feature = "docset-validation")]
+mod validation {
+
+    use DocId;
+    use Result;
+    use Score;
+    use core::SegmentReader;
+    use docset::{DocSet, SkipResult};
+    use query::{Explanation, Scorer, Weight};
+    use std::sync::Arc;
+
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    enum State {
+        Unstarted,
+        Positioned(DocId),
+        Ended,
+    }
+
+    /// `Weight` wrapping its scorers in a `ValidatingScorer`.
+    pub struct ValidatingWeight {
+        weight: Box<Weight>,
+        query: Arc<String>,
+    }
+
+    impl ValidatingWeight {
+        pub fn new(weight: Box<Weight>, query: String) -> ValidatingWeight {
+            ValidatingWeight {
+                weight,
+                query: Arc::new(query),
+            }
+        }
+
+        fn validate(&self, scorer: Box<Scorer>) -> Box<Scorer> {
+            box ValidatingScorer {
+                scorer,
+                query: Arc::clone(&self.query),
+                state: State::Unstarted,
+            }
+        }
+    }
+
+    impl Weight for ValidatingWeight {
+        fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+            let scorer = self.weight.scorer(reader)?;
+            Ok(self.validate(scorer))
+        }
+
+        fn alive_scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+            let scorer = self.weight.alive_scorer(reader)?;
+            Ok(self.validate(scorer))
+        }
+
+        fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+            self.weight.count_estimate(reader)
+        }
+
+        fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+            self.weight.explain(reader, doc)
+        }
+
+        fn count(&self, reader: &SegmentReader) -> Result<u32> {
+            self.weight.count(reader)
+        }
+    }
+
+    /// `Scorer` checking the iteration contract of the underlying scorer:
+    ///
+    /// * the doc ids are strictly increasing,
+    /// * `.advance()` and `.skip_next(...)` are not called after the end,
+    /// * `.doc()` and `.score()` are only called on a positioned scorer,
+    /// * the document reached by `.skip_next(target)` is consistent with
+    /// the returned `SkipResult`.
+    struct ValidatingScorer {
+        scorer: Box<Scorer>,
+        query: Arc<String>,
+        state: State,
+    }
+
+    impl ValidatingScorer {
+        fn fail(&self, message: &str) -> ! {
+            panic!(
+                "DocSet contract violated ({}) in state {:?} by the query {}",
+                message, self.state, self.query
+            );
+        }
+
+        fn check_positioned(&self, method: &str) {
+            if let State::Positioned(_) = self.state {
+                return;
+            }
+            self.fail(&format!("{}() called on a scorer that is not positioned", method));
+        }
+
+        fn check_not_ended(&self, method: &str) {
+            if self.state == State::Ended {
+                self.fail(&format!("{}() called after the end", method));
+            }
+        }
+
+        /// Checks that the new document comes after the previous one,
+        /// and records it.
+        fn move_to(&mut self, doc: DocId) {
+            if let State::Positioned(previous_doc) = self.state {
+                if doc <= previous_doc {
+                    self.fail(&format!("doc ids not increasing: {} after {}", doc, previous_doc));
+                }
+            }
+            self.state = State::Positioned(doc);
+        }
+    }
+
+    impl DocSet for ValidatingScorer {
+        fn advance(&mut self) -> bool {
+            self.check_not_ended("advance");
+            if self.scorer.advance() {
+                let doc = self.scorer.doc();
+                self.move_to(doc);
+                true
+            } else {
+                self.state = State::Ended;
+                false
+            }
+        }
+
+        fn skip_next(&mut self, target: DocId) -> SkipResult {
+            self.check_not_ended("skip_next");
+            let skip_result = self.scorer.skip_next(target);
+            match skip_result {
+                SkipResult::Reached | SkipResult::OverStep => {
+                    let doc = self.scorer.doc();
+                    if skip_result == SkipResult::Reached && doc != target {
+                        self.fail(&format!("skip_next({}) reached {}", target, doc));
+                    }
+                    if skip_result == SkipResult::OverStep && doc <= target {
+                        self.fail(&format!("skip_next({}) overstepped to {}", target, doc));
+                    }
+                    self.move_to(doc);
+                }
+                SkipResult::End => {
+                    self.state = State::Ended;
+                }
+            }
+            skip_result
+        }
+
+        fn doc(&self) -> DocId {
+            self.check_positioned("doc");
+            let doc = self.scorer.doc();
+            if self.state != State::Positioned(doc) {
+                self.fail(&format!("doc() changed to {} without advancing", doc));
+            }
+            doc
+        }
+
+        fn size_hint(&self) -> u32 {
+            self.scorer.size_hint()
+        }
+    }
+
+    impl Scorer for ValidatingScorer {
+        fn score(&mut self) -> Score {
+            self.check_positioned("score");
+            self.scorer.score()
+        }
+
+        fn term_freq(&self) -> Option<u32> {
+            self.check_positioned("term_freq");
+            self.scorer.term_freq()
+        }
+
+        fn positions(&self) -> &[u32] {
+            self.check_positioned("positions");
+            self.scorer.positions()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+
+        use super::{State, ValidatingScorer};
+        use docset::{DocSet, SkipResult};
+        use query::{ConstScorer, VecDocSet};
+        use std::sync::Arc;
+
+        fn validating_scorer(docs: Vec<u32>) -> ValidatingScorer {
+            ValidatingScorer {
+                scorer: box ConstScorer::new(VecDocSet::from(docs)),
+                query: Arc::new("TestQuery".to_string()),
+                state: State::Unstarted,
+            }
+        }
+
+        #[test]
+        fn test_validating_scorer() {
+            let mut scorer = validating_scorer(vec![1, 3, 5]);
+            assert!(scorer.advance());
+            assert_eq!(scorer.doc(), 1);
+            assert_eq!(scorer.skip_next(3), SkipResult::Reached);
+            assert_eq!(scorer.skip_next(4), SkipResult::OverStep);
+            assert_eq!(scorer.doc(), 5);
+            assert!(!scorer.advance());
+        }
+
+        #[test]
+        #[should_panic(expected = "called after the end")]
+        fn test_validating_scorer_advance_after_end() {
+            let mut scorer = validating_scorer(vec![1]);
+            assert!(scorer.advance());
+            assert!(!scorer.advance());
+            scorer.advance();
+        }
+
+        #[test]
+        #[should_panic(expected = "not increasing")]
+        fn test_validating_scorer_decreasing_doc_ids() {
+            let mut scorer = validating_scorer(vec![3, 1]);
+            assert!(scorer.advance());
+            scorer.advance();
+        }
+
+        #[test]
+        #[should_panic(expected = "not positioned")]
+        fn test_validating_scorer_doc_before_advance() {
+            validating_scorer(vec![1]).doc();
+        }
+    }
+}
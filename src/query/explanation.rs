@@ -0,0 +1,98 @@
+use Score;
+use std::fmt;
+
+/// Tree of the components of the score of a document,
+/// as returned by `Query::explain`.
+///
+/// Each node holds a value, a human readable description
+/// of what this value is, and the explanations of the values
+/// it was computed from. For instance, the explanation of
+/// a `TermQuery` details the boost of the field, the idf
+/// of the term, its term frequency and the field norm
+/// of the document.
+///
+/// The `Display` implementation renders the tree with
+/// one component per line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Explanation {
+    value: Score,
+    description: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    details: Vec<Explanation>,
+}
+
+impl Explanation {
+    /// Creates a new explanation without any detail.
+    pub fn new<T: ToString>(description: T, value: Score) -> Explanation {
+        Explanation {
+            value,
+            description: description.to_string(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Returns the value of this component of the score.
+    pub fn value(&self) -> Score {
+        self.value
+    }
+
+    /// Returns the description of this component of the score.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the explanations of the values this value
+    /// was computed from.
+    pub fn details(&self) -> &[Explanation] {
+        &self.details
+    }
+
+    /// Appends the explanation of a value this value
+    /// was computed from.
+    pub fn add_detail(&mut self, detail: Explanation) {
+        self.details.push(detail);
+    }
+
+    /// Appends a detail made of a description and a value.
+    pub fn add_const<T: ToString>(&mut self, description: T, value: Score) {
+        self.add_detail(Explanation::new(description, value));
+    }
+
+    fn write_indented(&self, formatter: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        for _ in 0..depth {
+            write!(formatter, "  ")?;
+        }
+        writeln!(formatter, "{} = {}", self.value, self.description)?;
+        for detail in &self.details {
+            detail.write_indented(formatter, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.write_indented(formatter, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::Explanation;
+
+    #[test]
+    fn test_explanation_display() {
+        let mut explanation = Explanation::new("sum of", 3f32);
+        explanation.add_const("a", 1f32);
+        let mut detail = Explanation::new("b", 2f32);
+        detail.add_const("c", 2f32);
+        explanation.add_detail(detail);
+        assert_eq!(explanation.details().len(), 2);
+        assert_eq!(
+            format!("{}", explanation),
+            "3 = sum of\n  1 = a\n  2 = b\n    2 = c\n"
+        );
+    }
+}
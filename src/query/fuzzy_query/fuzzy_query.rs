@@ -0,0 +1,210 @@
+use super::LevenshteinAutomaton;
+use common::BitSet;
+use core::{Searcher, SegmentReader};
+use error::ErrorKind;
+use query::{BitSetDocSet, ConstScorer, Query, Scorer, Weight};
+use query::{QueryAst, TermAst};
+use schema::{Field, IndexRecordOption, Schema, Term};
+use std::str;
+use termdict::TermDictionary;
+use Result;
+use Score;
+
+/// Maximum edit distance supported by the `FuzzyTermQuery`.
+pub(crate) const MAX_DISTANCE: u8 = 2;
+
+/// `FuzzyTermQuery` matches all of the documents containing a term
+/// within a given Levenshtein distance of a target term.
+///
+/// The distance is the number of characters to insert,
+/// delete or substitute to turn one term into the other.
+/// Optionally, the transposition of two adjacent characters
+/// counts as a single edit.
+/// Distances of 1 and 2 are supported.
+///
+/// As for the `RangeQuery`, matched documents all get a constant `Score` of one.
+///
+/// # Implementation
+///
+/// A Levenshtein automaton is intersected with the term dictionary
+/// of each segment, and the documents of the matched terms
+/// are appended into a `BitSet`.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tantivy;
+/// # use tantivy::Index;
+/// # use tantivy::schema::{SchemaBuilder, TEXT};
+/// # use tantivy::query::{FuzzyTermQuery, Query};
+/// # use tantivy::{Result, Term};
+/// #
+/// # fn run() -> Result<()> {
+/// #     let mut schema_builder = SchemaBuilder::new();
+/// #     let title = schema_builder.add_text_field("title", TEXT);
+/// #     let index = Index::create_in_ram(schema_builder.build());
+/// #     {
+/// #         let mut index_writer = index.writer_with_num_threads(1, 6_000_000)?;
+/// #         index_writer.add_document(doc!(title => "The Name of the Wind"));
+/// #         index_writer.add_document(doc!(title => "The Diary of Muadib"));
+/// #         index_writer.add_document(doc!(title => "A Dairy Cow"));
+/// #         index_writer.commit()?;
+/// #     }
+/// #     index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let term = Term::from_field_text(title, "diary");
+/// // "dairy" is a transposition away from "diary".
+/// let query = FuzzyTermQuery::new(term.clone(), 1, true);
+/// assert_eq!(query.count(&*searcher)?, 2);
+/// let query = FuzzyTermQuery::new(term, 1, false);
+/// assert_eq!(query.count(&*searcher)?, 1);
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #   run().unwrap()
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FuzzyTermQuery {
+    term: Term,
+    max_distance: u8,
+    transpositions: bool,
+}
+
+impl FuzzyTermQuery {
+    /// Creates a query matching the terms of the field of `term`
+    /// within `max_distance` edits of its text.
+    ///
+    /// If `transpositions` is true, swapping two adjacent characters
+    /// counts as a single edit.
+    ///
+    /// Searching returns an error if `max_distance` exceeds 2,
+    /// or if `term` is not a text term.
+    pub fn new(term: Term, max_distance: u8, transpositions: bool) -> FuzzyTermQuery {
+        FuzzyTermQuery {
+            term,
+            max_distance,
+            transpositions,
+        }
+    }
+}
+
+impl Query for FuzzyTermQuery {
+    fn weight(&self, searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        if self.max_distance > MAX_DISTANCE {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The edit distance of a fuzzy query is at most {}, got {}.",
+                MAX_DISTANCE, self.max_distance
+            )));
+        }
+        let text = str::from_utf8(self.term.value_bytes()).map_err(|_| {
+            ErrorKind::InvalidArgument(format!("{:?} is not a text term.", self.term))
+        })?;
+        let field = self.term.field();
+        Ok(box FuzzyWeight {
+            field,
+            boost: searcher.field_boost(field),
+            automaton: LevenshteinAutomaton::new(text, self.max_distance, self.transpositions),
+        })
+    }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        Some(QueryAst::Fuzzy {
+            term: TermAst::from_term(&self.term, schema)?,
+            distance: self.max_distance,
+            transpositions: self.transpositions,
+        })
+    }
+}
+
+pub struct FuzzyWeight {
+    field: Field,
+    boost: Score,
+    automaton: LevenshteinAutomaton,
+}
+
+impl Weight for FuzzyWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let mut doc_bitset = BitSet::with_max_value(reader.max_doc());
+        let inverted_index = reader.inverted_index(self.field);
+        let term_dict = inverted_index.terms();
+        term_dict.search(&self.automaton, |term_ord| {
+            let term_info = term_dict.term_info_from_ord(term_ord);
+            let mut block_segment_postings = inverted_index
+                .read_block_postings_from_terminfo(&term_info, IndexRecordOption::Basic);
+            while block_segment_postings.advance() {
+                for &doc in block_segment_postings.docs() {
+                    doc_bitset.insert(doc);
+                }
+            }
+            true
+        });
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(box ConstScorer::with_score(doc_bitset, self.boost))
+    }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        let inverted_index = reader.inverted_index(self.field);
+        let term_dict = inverted_index.terms();
+        let mut doc_freq_sum = 0u32;
+        term_dict.search(&self.automaton, |term_ord| {
+            let doc_freq = term_dict.term_info_from_ord(term_ord).doc_freq;
+            doc_freq_sum = doc_freq_sum.saturating_add(doc_freq);
+            true
+        });
+        Ok(doc_freq_sum.min(reader.max_doc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::FuzzyTermQuery;
+    use collector::TopCollector;
+    use query::Query;
+    use schema::{SchemaBuilder, STRING};
+    use Index;
+    use Term;
+
+    #[test]
+    fn test_fuzzy_term_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let country = schema_builder.add_text_field("country", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for country_name in &["japan", "japon", "jpaan", "korea", "jaapn", "japanese"] {
+                index_writer.add_document(doc!(country => *country_name));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term = Term::from_field_text(country, "japan");
+        let count = |max_distance: u8, transpositions: bool| {
+            FuzzyTermQuery::new(term.clone(), max_distance, transpositions)
+                .count(&*searcher)
+                .unwrap()
+        };
+        assert_eq!(count(0, false), 1);
+        // japan, japon
+        assert_eq!(count(1, false), 2);
+        // japan, japon, jpaan, jaapn
+        assert_eq!(count(1, true), 4);
+        assert_eq!(count(2, false), 4);
+        {
+            let mut top_collector = TopCollector::with_limit(10);
+            let query = FuzzyTermQuery::new(term.clone(), 1, true);
+            searcher.search(&query, &mut top_collector).unwrap();
+            assert!(top_collector
+                .score_docs()
+                .iter()
+                .all(|&(score, _)| score == 1f32));
+        }
+        let query = FuzzyTermQuery::new(term.clone(), 3, false);
+        assert!(query.count(&*searcher).is_err());
+    }
+}
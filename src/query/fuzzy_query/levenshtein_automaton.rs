@@ -0,0 +1,273 @@
+use fst::Automaton;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Key identifying a state while the DFA is being built.
+///
+/// `row` holds the edit distances between the input read so far
+/// and the prefixes of the target, capped at `max_distance + 1`.
+/// With transpositions, the row of the previous input character
+/// and the class of the previous input character are required as well.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct StateKey {
+    row: Vec<u8>,
+    previous_row: Vec<u8>,
+    previous_class: u32,
+}
+
+/// Deterministic automaton over the characters of the input,
+/// accepting the strings within a given edit distance of a target.
+///
+/// The characters of the target each get their own character class,
+/// all of the other characters share a single class.
+struct LevenshteinDfa {
+    char_classes: HashMap<char, u32>,
+    num_classes: usize,
+    // transitions[state * num_classes + class]
+    transitions: Vec<u32>,
+    accepting: Vec<bool>,
+    alive: Vec<bool>,
+}
+
+impl LevenshteinDfa {
+    fn build(target: &str, max_distance: u8, transpositions: bool) -> LevenshteinDfa {
+        let target_chars: Vec<char> = target.chars().collect();
+        let mut char_classes: HashMap<char, u32> = HashMap::new();
+        for &c in &target_chars {
+            let num_char_classes = char_classes.len() as u32;
+            char_classes.entry(c).or_insert(num_char_classes);
+        }
+        let other_class = char_classes.len() as u32;
+        let num_classes = char_classes.len() + 1;
+        let target_classes: Vec<u32> = target_chars.iter().map(|c| char_classes[c]).collect();
+        let cap = max_distance + 1;
+
+        let initial_key = StateKey {
+            row: (0..target_classes.len() + 1)
+                .map(|j| (j as u8).min(cap))
+                .collect(),
+            previous_row: if transpositions {
+                vec![cap; target_classes.len() + 1]
+            } else {
+                Vec::new()
+            },
+            previous_class: other_class,
+        };
+        let mut state_ids: HashMap<StateKey, u32> = HashMap::new();
+        let mut keys: Vec<StateKey> = Vec::new();
+        state_ids.insert(initial_key.clone(), 0);
+        keys.push(initial_key);
+
+        let mut transitions: Vec<u32> = Vec::new();
+        let mut state = 0;
+        while state < keys.len() {
+            for class in 0..num_classes as u32 {
+                let next_key = {
+                    let key = &keys[state];
+                    let mut row = Vec::with_capacity(key.row.len());
+                    row.push(key.row[0].saturating_add(1).min(cap));
+                    for j in 1..key.row.len() {
+                        let cost = if target_classes[j - 1] == class { 0 } else { 1 };
+                        let mut distance = (key.row[j - 1] + cost)
+                            .min(key.row[j] + 1)
+                            .min(row[j - 1] + 1);
+                        if transpositions && j > 1 && class != other_class
+                            && target_classes[j - 2] == class
+                            && target_classes[j - 1] == key.previous_class
+                        {
+                            distance = distance.min(key.previous_row[j - 2] + 1);
+                        }
+                        row.push(distance.min(cap));
+                    }
+                    StateKey {
+                        previous_row: if transpositions {
+                            key.row.clone()
+                        } else {
+                            Vec::new()
+                        },
+                        row,
+                        previous_class: if transpositions { class } else { other_class },
+                    }
+                };
+                let next_state = match state_ids.get(&next_key) {
+                    Some(&next_state) => next_state,
+                    None => {
+                        let next_state = keys.len() as u32;
+                        keys.push(next_key.clone());
+                        state_ids.insert(next_key, next_state);
+                        next_state
+                    }
+                };
+                transitions.push(next_state);
+            }
+            state += 1;
+        }
+        let accepting = keys.iter()
+            .map(|key| *key.row.last().expect("Rows are never empty") <= max_distance)
+            .collect();
+        let alive = keys.iter()
+            .map(|key| {
+                key.row.iter().any(|&distance| distance <= max_distance)
+                    || key.previous_row
+                        .iter()
+                        .any(|&distance| distance < max_distance)
+            })
+            .collect();
+        LevenshteinDfa {
+            char_classes,
+            num_classes,
+            transitions,
+            accepting,
+            alive,
+        }
+    }
+
+    fn transition(&self, state: u32, c: char) -> u32 {
+        let class = self.char_classes
+            .get(&c)
+            .cloned()
+            .unwrap_or((self.num_classes - 1) as u32);
+        self.transitions[state as usize * self.num_classes + class as usize]
+    }
+}
+
+/// State of a `LevenshteinAutomaton`.
+///
+/// The automaton reads UTF-8 bytes: the bytes of a multibyte character
+/// are accumulated until the character is complete.
+#[derive(Clone, Copy, Debug)]
+pub struct Utf8State {
+    dfa_state: u32,
+    codepoint: u32,
+    remaining_bytes: u8,
+}
+
+/// Automaton accepting the terms within a given Levenshtein distance
+/// of a target, that can be intersected with the term dictionary.
+///
+/// The distance is computed over characters.
+/// Optionally, the transposition of two adjacent characters
+/// counts as a single edit.
+///
+/// The DFA is built once, and shared by the clones of the automaton.
+#[derive(Clone)]
+pub struct LevenshteinAutomaton {
+    dfa: Arc<LevenshteinDfa>,
+}
+
+impl LevenshteinAutomaton {
+    /// Builds the automaton of the strings within `max_distance` edits of `target`.
+    pub fn new(target: &str, max_distance: u8, transpositions: bool) -> LevenshteinAutomaton {
+        LevenshteinAutomaton {
+            dfa: Arc::new(LevenshteinDfa::build(target, max_distance, transpositions)),
+        }
+    }
+}
+
+impl Automaton for LevenshteinAutomaton {
+    type State = Utf8State;
+
+    fn start(&self) -> Utf8State {
+        Utf8State {
+            dfa_state: 0,
+            codepoint: 0,
+            remaining_bytes: 0,
+        }
+    }
+
+    fn is_match(&self, state: &Utf8State) -> bool {
+        state.remaining_bytes == 0 && self.dfa.accepting[state.dfa_state as usize]
+    }
+
+    fn can_match(&self, state: &Utf8State) -> bool {
+        self.dfa.alive[state.dfa_state as usize]
+    }
+
+    fn accept(&self, state: &Utf8State, byte: u8) -> Utf8State {
+        let (codepoint, remaining_bytes) = if state.remaining_bytes > 0 && byte & 0xC0 == 0x80 {
+            ((state.codepoint << 6) | u32::from(byte & 0x3F), state.remaining_bytes - 1)
+        } else {
+            // invalid UTF-8 sequences are read as characters
+            // that do not belong to the target.
+            match byte {
+                0x00...0x7F => (u32::from(byte), 0),
+                0xC0...0xDF => (u32::from(byte & 0x1F), 1),
+                0xE0...0xEF => (u32::from(byte & 0x0F), 2),
+                0xF0...0xF7 => (u32::from(byte & 0x07), 3),
+                _ => (0xFFFD, 0),
+            }
+        };
+        if remaining_bytes > 0 {
+            return Utf8State {
+                dfa_state: state.dfa_state,
+                codepoint,
+                remaining_bytes,
+            };
+        }
+        let c = ::std::char::from_u32(codepoint).unwrap_or('\u{FFFD}');
+        Utf8State {
+            dfa_state: self.dfa.transition(state.dfa_state, c),
+            codepoint: 0,
+            remaining_bytes: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::LevenshteinAutomaton;
+    use fst::Automaton;
+
+    fn matches(automaton: &LevenshteinAutomaton, text: &str) -> bool {
+        let mut state = automaton.start();
+        for &byte in text.as_bytes() {
+            if !automaton.can_match(&state) {
+                return false;
+            }
+            state = automaton.accept(&state, byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_levenshtein_automaton() {
+        let automaton = LevenshteinAutomaton::new("abc", 1, false);
+        assert!(matches(&automaton, "abc"));
+        assert!(matches(&automaton, "ab"));
+        assert!(matches(&automaton, "abcd"));
+        assert!(matches(&automaton, "xbc"));
+        assert!(!matches(&automaton, "bac"));
+        assert!(!matches(&automaton, "a"));
+        assert!(!matches(&automaton, "abcde"));
+        let automaton = LevenshteinAutomaton::new("abc", 2, false);
+        assert!(matches(&automaton, "bac"));
+        assert!(matches(&automaton, "a"));
+        assert!(!matches(&automaton, ""));
+        assert!(!matches(&automaton, "xyc1"));
+    }
+
+    #[test]
+    fn test_levenshtein_automaton_transpositions() {
+        let automaton = LevenshteinAutomaton::new("abcd", 1, true);
+        assert!(matches(&automaton, "bacd"));
+        assert!(matches(&automaton, "abdc"));
+        assert!(!matches(&automaton, "badc"));
+        let automaton = LevenshteinAutomaton::new("abcd", 2, true);
+        assert!(matches(&automaton, "badc"));
+        assert!(matches(&automaton, "bacde"));
+    }
+
+    #[test]
+    fn test_levenshtein_automaton_utf8() {
+        let automaton = LevenshteinAutomaton::new("café", 1, false);
+        assert!(matches(&automaton, "cafe"));
+        assert!(matches(&automaton, "caf"));
+        assert!(matches(&automaton, "cafés"));
+        assert!(!matches(&automaton, "cafèè"));
+        let automaton = LevenshteinAutomaton::new("日本語", 1, true);
+        assert!(matches(&automaton, "本日語"));
+        assert!(matches(&automaton, "日本"));
+        assert!(!matches(&automaton, "日"));
+    }
+}
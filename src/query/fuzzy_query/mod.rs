@@ -0,0 +1,6 @@
+mod fuzzy_query;
+mod levenshtein_automaton;
+
+pub use self::fuzzy_query::FuzzyTermQuery;
+pub(crate) use self::fuzzy_query::MAX_DISTANCE as MAX_FUZZY_DISTANCE;
+pub use self::levenshtein_automaton::LevenshteinAutomaton;
@@ -23,6 +23,19 @@ impl<TDocSet: DocSet> From<Vec<TDocSet>> for Intersection<TDocSet> {
 }
 
 impl<TDocSet: DocSet> Intersection<TDocSet> {
+    /// Creates the intersection of `docsets`, keeping their order.
+    ///
+    /// Unlike `Intersection::from`, the docsets are not sorted by size hint:
+    /// the caller is expected to put the most selective docsets first.
+    pub(crate) fn from_ordered(docsets: Vec<TDocSet>) -> Intersection<TDocSet> {
+        assert!(docsets.len() >= 2);
+        Intersection {
+            docsets,
+            finished: false,
+            doc: 0u32,
+        }
+    }
+
     /// Returns an array to the underlying `DocSet`s of the intersection.
     /// These `DocSet` are in the same position as the `IntersectionDocSet`,
     /// so that user can access their `docfreq` and `positions`.
@@ -0,0 +1,329 @@
+use common::BitSet;
+use core::{Searcher, SegmentReader};
+use docset::DocSet;
+use error::ErrorKind;
+use query::{create_weight, BitSetDocSet, ConstScorer, Query, QueryAst, Scorer, Weight};
+use schema::{Field, FieldType, IndexRecordOption, Schema, Term};
+use std::collections::BTreeSet;
+use termdict::{TermDictionary, TermStreamer};
+use Result;
+use Score;
+
+/// Returns true if the terms of both fields can be compared byte-wise.
+fn same_value_type(left: &FieldType, right: &FieldType) -> bool {
+    match (left, right) {
+        (&FieldType::Str(_), &FieldType::Str(_))
+        | (&FieldType::U64(_), &FieldType::U64(_))
+        | (&FieldType::I64(_), &FieldType::I64(_))
+        | (&FieldType::F64(_), &FieldType::F64(_))
+        | (&FieldType::Date(_), &FieldType::Date(_))
+        | (&FieldType::HierarchicalFacet, &FieldType::HierarchicalFacet) => true,
+        _ => false,
+    }
+}
+
+/// `JoinQuery` matches the documents whose `to_field` contains
+/// one of the values of the `from_field` of the documents
+/// matching an underlying query.
+///
+/// This makes simple relational lookups possible within one index.
+/// For instance, with authors and books indexed as different
+/// documents, joining from the `name` of the authors matching
+/// `country:france` to the `author` of the books matches all of
+/// the books written by a french author.
+///
+/// The values of `from_field` are read from the term dictionary
+/// of the segments holding at least one match of the underlying query,
+/// going through the postings of all of its terms. The join is
+/// therefore affordable on fields with a moderate number of terms,
+/// such as identifiers or names, but not on full text fields.
+///
+/// Both fields have to be indexed, and of the same type.
+/// The matching documents all get a score of `1`, the scores of the
+/// underlying query are not propagated.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{SchemaBuilder, STRING};
+/// use tantivy::{Index, Result, Term};
+/// use tantivy::query::{JoinQuery, Query, TermQuery};
+/// use tantivy::schema::IndexRecordOption;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let name = schema_builder.add_text_field("name", STRING);
+///     let country = schema_builder.add_text_field("country", STRING);
+///     let author = schema_builder.add_text_field("author", STRING);
+///     let title = schema_builder.add_text_field("title", STRING);
+///     let index = Index::create_in_ram(schema_builder.build());
+///     {
+///         let mut index_writer = index.writer(3_000_000)?;
+///         index_writer.add_document(doc!(name => "Hugo", country => "france"));
+///         index_writer.add_document(doc!(name => "Tolstoi", country => "russia"));
+///         index_writer.add_document(doc!(author => "Hugo", title => "Les Miserables"));
+///         index_writer.add_document(doc!(author => "Hugo", title => "Notre-Dame de Paris"));
+///         index_writer.add_document(doc!(author => "Tolstoi", title => "War and Peace"));
+///         index_writer.commit()?;
+///     }
+///     index.load_searchers()?;
+///     let searcher = index.searcher();
+///
+///     let french_authors = TermQuery::new(
+///         Term::from_field_text(country, "france"),
+///         IndexRecordOption::Basic,
+///     );
+///     let books_of_french_authors = JoinQuery::new(Box::new(french_authors), name, author);
+///     assert_eq!(books_of_french_authors.count(&*searcher)?, 2);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct JoinQuery {
+    query: Box<Query>,
+    from_field: Field,
+    to_field: Field,
+}
+
+impl JoinQuery {
+    /// Creates a new join query, matching the documents whose
+    /// `to_field` contains one of the values of the `from_field`
+    /// of the documents matching `query`.
+    pub fn new(query: Box<Query>, from_field: Field, to_field: Field) -> JoinQuery {
+        JoinQuery {
+            query,
+            from_field,
+            to_field,
+        }
+    }
+
+    /// Returns the underlying query.
+    pub fn query(&self) -> &Query {
+        &*self.query
+    }
+
+    /// Returns the field the values are collected from.
+    pub fn from_field(&self) -> Field {
+        self.from_field
+    }
+
+    /// Returns the field the values are looked up in.
+    pub fn to_field(&self) -> Field {
+        self.to_field
+    }
+
+    fn check_fields(&self, schema: &Schema) -> Result<()> {
+        let from_entry = schema.get_field_entry(self.from_field);
+        let to_entry = schema.get_field_entry(self.to_field);
+        for field_entry in &[from_entry, to_entry] {
+            if !field_entry.is_indexed() {
+                bail!(ErrorKind::SchemaError(format!(
+                    "Field {:?} is not indexed, it cannot be joined on.",
+                    field_entry.name()
+                )));
+            }
+        }
+        if !same_value_type(from_entry.field_type(), to_entry.field_type()) {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "Fields {:?} and {:?} have different types, they cannot be joined.",
+                from_entry.name(),
+                to_entry.name()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the values of `from_field` of the alive
+    /// documents matching the underlying query.
+    fn join_values(&self, searcher: &Searcher) -> Result<BTreeSet<Vec<u8>>> {
+        let weight = create_weight(&*self.query, searcher, false)?;
+        let mut values = BTreeSet::new();
+        for reader in searcher.segment_readers() {
+            let mut docs = BitSet::with_max_value(reader.max_doc());
+            let mut scorer = weight.alive_scorer(reader)?;
+            while scorer.advance() {
+                docs.insert(scorer.doc());
+            }
+            if docs.len() == 0 {
+                continue;
+            }
+            let inverted_index = reader.inverted_index(self.from_field);
+            let mut term_stream = inverted_index.terms().stream();
+            while term_stream.advance() {
+                if values.contains(term_stream.key()) {
+                    continue;
+                }
+                let term_info = term_stream.value();
+                let mut block_postings = inverted_index
+                    .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
+                'postings: while block_postings.advance() {
+                    for &doc in block_postings.docs() {
+                        if docs.contains(doc) {
+                            values.insert(term_stream.key().to_owned());
+                            break 'postings;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(values)
+    }
+}
+
+impl Query for JoinQuery {
+    fn weight(&self, searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        if let Some(reader) = searcher.segment_readers().first() {
+            self.check_fields(reader.schema())?;
+        }
+        Ok(box JoinWeight {
+            field: self.to_field,
+            boost: searcher.field_boost(self.to_field),
+            values: self.join_values(searcher)?,
+        })
+    }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        Some(QueryAst::Join {
+            query: box self.query.query_ast(schema)?,
+            from_field: schema.fields().get(self.from_field.0 as usize)?.name().to_string(),
+            to_field: schema.fields().get(self.to_field.0 as usize)?.name().to_string(),
+        })
+    }
+}
+
+/// Weight associated to the `JoinQuery`, holding the
+/// values collected from the documents matching the
+/// underlying query.
+pub struct JoinWeight {
+    field: Field,
+    boost: Score,
+    values: BTreeSet<Vec<u8>>,
+}
+
+impl Weight for JoinWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let mut doc_bitset = BitSet::with_max_value(reader.max_doc());
+        let inverted_index = reader.inverted_index(self.field);
+        for value in &self.values {
+            let term = Term::from_field_bytes(self.field, value);
+            if let Some(term_info) = inverted_index.get_term_info(&term) {
+                let mut block_postings = inverted_index
+                    .read_block_postings_from_terminfo(&term_info, IndexRecordOption::Basic);
+                while block_postings.advance() {
+                    for &doc in block_postings.docs() {
+                        doc_bitset.insert(doc);
+                    }
+                }
+            }
+        }
+        Ok(box ConstScorer::with_score(BitSetDocSet::from(doc_bitset), self.boost))
+    }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        let inverted_index = reader.inverted_index(self.field);
+        let mut doc_freq_sum = 0u32;
+        for value in &self.values {
+            let term = Term::from_field_bytes(self.field, value);
+            doc_freq_sum = doc_freq_sum.saturating_add(inverted_index.doc_freq(&term));
+        }
+        Ok(doc_freq_sum.min(reader.max_doc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::JoinQuery;
+    use query::{BooleanQuery, Occur, Query, QueryAst, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, INT_INDEXED, STRING};
+    use collector::TopCollector;
+    use Index;
+    use Term;
+
+    #[test]
+    fn test_join_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let id = schema_builder.add_u64_field("id", INT_INDEXED);
+        let kind = schema_builder.add_text_field("kind", STRING);
+        let author_id = schema_builder.add_u64_field("author_id", INT_INDEXED);
+        let country = schema_builder.add_text_field("country", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(id => 1u64, kind => "author", country => "fr"));
+            index_writer.add_document(doc!(id => 2u64, kind => "author", country => "ru"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(id => 3u64, kind => "author", country => "fr"));
+            index_writer.add_document(doc!(kind => "book", author_id => 1u64));
+            index_writer.add_document(doc!(kind => "book", author_id => 2u64));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(kind => "book", author_id => 3u64));
+            index_writer.add_document(doc!(kind => "book", author_id => 3u64));
+            index_writer.add_document(doc!(kind => "book", author_id => 4u64));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let french_authors = || -> Box<Query> {
+            box TermQuery::new(
+                Term::from_field_text(country, "fr"),
+                IndexRecordOption::Basic,
+            )
+        };
+        let join_query = JoinQuery::new(french_authors(), id, author_id);
+        assert_eq!(join_query.count(&*searcher).unwrap(), 3);
+
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&join_query, &mut top_collector).unwrap();
+        for (score, _) in top_collector.score_docs() {
+            assert_eq!(score, 1f32);
+        }
+
+        // the joined documents can be combined with other clauses.
+        let books: Box<Query> = box TermQuery::new(
+            Term::from_field_text(kind, "book"),
+            IndexRecordOption::Basic,
+        );
+        let join: Box<Query> = box JoinQuery::new(french_authors(), id, author_id);
+        let not_french: Box<Query> = box BooleanQuery::from(vec![
+            (Occur::Must, books),
+            (Occur::MustNot, join),
+        ]);
+        assert_eq!(not_french.count(&*searcher).unwrap(), 2);
+
+        let query_ast = join_query.query_ast(&schema).unwrap();
+        match query_ast {
+            QueryAst::Join {
+                ref from_field,
+                ref to_field,
+                ..
+            } => {
+                assert_eq!(from_field, "id");
+                assert_eq!(to_field, "author_id");
+            }
+            _ => panic!("Expected a join"),
+        }
+        let query = query_ast.to_query(&schema).unwrap();
+        assert_eq!(query.count(&*searcher).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_join_query_incompatible_fields() {
+        let mut schema_builder = SchemaBuilder::default();
+        let id = schema_builder.add_u64_field("id", INT_INDEXED);
+        let name = schema_builder.add_text_field("name", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(id => 1u64, name => "a"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = TermQuery::new(Term::from_field_u64(id, 1u64), IndexRecordOption::Basic);
+        let join_query = JoinQuery::new(box term_query, id, name);
+        assert!(join_query.count(&*searcher).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use docset::{DocSet, SkipResult};
+use query::Scorer;
+use query::score_combiner::{DoNothingCombiner, ScoreCombiner};
+use DocId;
+use Score;
+
+/// Creates a `DocSet` that iterates through the documents matched
+/// by at least `minimum_should_match` of several `DocSet`s.
+///
+/// The score of a document combines the scores of the `DocSet`s
+/// matching it, in the order in which the `DocSet`s were given.
+pub struct MinShouldMatch<TScorer, TScoreCombiner = DoNothingCombiner> {
+    // the docsets that are not exhausted, positioned after the current document.
+    docsets: Vec<TScorer>,
+    minimum_should_match: usize,
+    doc: DocId,
+    score: Score,
+    score_combiner: TScoreCombiner,
+}
+
+impl<TScorer, TScoreCombiner> MinShouldMatch<TScorer, TScoreCombiner>
+where
+    TScoreCombiner: ScoreCombiner,
+    TScorer: Scorer,
+{
+    /// Creates the `DocSet` of the documents matched by at least
+    /// `minimum_should_match` of the `docsets`.
+    pub fn new(
+        docsets: Vec<TScorer>,
+        minimum_should_match: usize,
+    ) -> MinShouldMatch<TScorer, TScoreCombiner> {
+        let non_empty_docsets: Vec<TScorer> = docsets
+            .into_iter()
+            .filter_map(|mut docset| if docset.advance() { Some(docset) } else { None })
+            .collect();
+        MinShouldMatch {
+            docsets: non_empty_docsets,
+            minimum_should_match: minimum_should_match.max(1),
+            doc: 0,
+            score: 0f32,
+            score_combiner: TScoreCombiner::default(),
+        }
+    }
+}
+
+impl<TScorer, TScoreCombiner> DocSet for MinShouldMatch<TScorer, TScoreCombiner>
+where
+    TScoreCombiner: ScoreCombiner,
+    TScorer: Scorer,
+{
+    fn advance(&mut self) -> bool {
+        loop {
+            if self.docsets.len() < self.minimum_should_match {
+                return false;
+            }
+            let doc = self.docsets
+                .iter()
+                .map(|docset| docset.doc())
+                .min()
+                .expect("There is at least one docset");
+            let mut num_matching = 0;
+            self.score_combiner.clear();
+            let mut ord = 0;
+            while ord < self.docsets.len() {
+                if self.docsets[ord].doc() == doc {
+                    num_matching += 1;
+                    self.score_combiner.update(&mut self.docsets[ord]);
+                    if !self.docsets[ord].advance() {
+                        self.docsets.remove(ord);
+                        continue;
+                    }
+                }
+                ord += 1;
+            }
+            if num_matching >= self.minimum_should_match {
+                self.doc = doc;
+                self.score = self.score_combiner.score();
+                return true;
+            }
+        }
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        let mut ord = 0;
+        while ord < self.docsets.len() {
+            if self.docsets[ord].doc() < target
+                && self.docsets[ord].skip_next(target) == SkipResult::End
+            {
+                self.docsets.remove(ord);
+                continue;
+            }
+            ord += 1;
+        }
+        if !self.advance() {
+            SkipResult::End
+        } else if self.doc == target {
+            SkipResult::Reached
+        } else {
+            SkipResult::OverStep
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.docsets
+            .iter()
+            .map(|docset| docset.size_hint())
+            .max()
+            .unwrap_or(0u32)
+    }
+}
+
+impl<TScorer, TScoreCombiner> Scorer for MinShouldMatch<TScorer, TScoreCombiner>
+where
+    TScoreCombiner: ScoreCombiner,
+    TScorer: Scorer,
+{
+    fn score(&mut self) -> Score {
+        self.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::MinShouldMatch;
+    use docset::{DocSet, SkipResult};
+    use query::{ConstScorer, Scorer, VecDocSet};
+    use query::score_combiner::SumCombiner;
+
+    fn min_should_match(
+        docs_list: Vec<Vec<u32>>,
+        minimum_should_match: usize,
+    ) -> MinShouldMatch<ConstScorer<VecDocSet>, SumCombiner> {
+        let scorers = docs_list
+            .into_iter()
+            .map(|docs| ConstScorer::new(VecDocSet::from(docs)))
+            .collect();
+        MinShouldMatch::new(scorers, minimum_should_match)
+    }
+
+    fn docs<TDocSet: DocSet>(mut docset: TDocSet) -> Vec<u32> {
+        let mut docs = Vec::new();
+        while docset.advance() {
+            docs.push(docset.doc());
+        }
+        docs
+    }
+
+    #[test]
+    fn test_min_should_match() {
+        let docs_list = vec![vec![1, 2, 3, 5], vec![2, 3, 4], vec![3, 5, 7], vec![7]];
+        assert_eq!(docs(min_should_match(docs_list.clone(), 1)), vec![1, 2, 3, 4, 5, 7]);
+        assert_eq!(docs(min_should_match(docs_list.clone(), 2)), vec![2, 3, 5, 7]);
+        assert_eq!(docs(min_should_match(docs_list.clone(), 3)), vec![3]);
+        assert!(docs(min_should_match(docs_list, 5)).is_empty());
+    }
+
+    #[test]
+    fn test_min_should_match_score() {
+        let mut scorer = min_should_match(vec![vec![1, 2], vec![2], vec![1, 2]], 2);
+        assert!(scorer.advance());
+        assert_eq!(scorer.doc(), 1);
+        assert_eq!(scorer.score(), 2f32);
+        assert!(scorer.advance());
+        assert_eq!(scorer.doc(), 2);
+        assert_eq!(scorer.score(), 3f32);
+        assert!(!scorer.advance());
+    }
+
+    #[test]
+    fn test_min_should_match_skip_next() {
+        let docs_list = vec![vec![1, 4, 6, 9], vec![4, 5, 9], vec![2, 6, 8, 9]];
+        let mut scorer = min_should_match(docs_list, 2);
+        assert_eq!(scorer.skip_next(4), SkipResult::Reached);
+        assert_eq!(scorer.doc(), 4);
+        assert_eq!(scorer.skip_next(5), SkipResult::OverStep);
+        assert_eq!(scorer.doc(), 6);
+        assert_eq!(scorer.skip_next(9), SkipResult::Reached);
+        assert_eq!(scorer.skip_next(10), SkipResult::End);
+    }
+}
@@ -3,18 +3,26 @@ Query
 */
 
 mod query;
+mod explanation;
 mod boolean_query;
 mod scorer;
 mod occur;
-mod weight;
+pub(crate) mod weight;
 mod term_query;
 mod query_parser;
 mod phrase_query;
 mod all_query;
 mod bitset;
 mod range_query;
+mod fuzzy_query;
+mod regex_query;
+mod query_builder;
+pub(crate) mod query_ast;
+mod constant_score_query;
+mod join_query;
 mod exclude;
 mod union;
+mod min_should_match;
 mod intersection;
 mod reqopt_scorer;
 
@@ -22,9 +30,14 @@ mod reqopt_scorer;
 mod vec_docset;
 
 pub(crate) mod score_combiner;
+mod alive_docset;
+mod rescorer;
+mod docset_validator;
+mod weight_cache;
 
 pub use self::intersection::Intersection;
 pub use self::union::Union;
+pub use self::min_should_match::MinShouldMatch;
 
 #[cfg(test)]
 pub use self::vec_docset::VecDocSet;
@@ -32,16 +45,30 @@ pub use self::vec_docset::VecDocSet;
 pub use self::reqopt_scorer::RequiredOptionalScorer;
 pub use self::exclude::Exclude;
 pub use self::bitset::BitSetDocSet;
+pub use self::rescorer::{RescoreFunction, Rescorer};
 pub use self::boolean_query::BooleanQuery;
 pub use self::occur::Occur;
-pub use self::phrase_query::PhraseQuery;
+pub use self::phrase_query::{PhraseMatchMode, PhraseQuery};
 pub use self::query_parser::QueryParserError;
 pub use self::query_parser::QueryParser;
-pub use self::query::Query;
+pub use self::query_parser::QuerySanitizer;
+pub use self::query::{ParallelQuery, Query};
+pub use self::explanation::Explanation;
 pub use self::scorer::EmptyScorer;
 pub use self::scorer::Scorer;
-pub use self::term_query::TermQuery;
+pub use self::term_query::{BM25Similarity, ConstantSimilarity, Similarity, TermQuery,
+                           TermSimilarity};
 pub use self::weight::Weight;
 pub use self::all_query::{AllQuery, AllScorer, AllWeight};
 pub use self::range_query::RangeQuery;
+pub use self::fuzzy_query::{FuzzyTermQuery, LevenshteinAutomaton};
+pub(crate) use self::fuzzy_query::MAX_FUZZY_DISTANCE;
+pub use self::regex_query::RegexQuery;
+pub use self::query_builder::QueryBuilder;
+pub use self::query_ast::{BooleanClauseAst, BoundAst, QueryAst, TermAst};
+pub use self::constant_score_query::{ConstantScoreQuery, ConstantScoreWeight};
+pub use self::join_query::{JoinQuery, JoinWeight};
 pub use self::scorer::ConstScorer;
+pub use self::alive_docset::AliveDocSet;
+pub(crate) use self::docset_validator::{create_weight, create_weight_with_cache};
+pub use self::weight_cache::WeightCache;
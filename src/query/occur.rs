@@ -1,14 +1,51 @@
 /// Defines whether a term in a query must be present,
 /// should be present or must not be present.
-#[derive(Debug, Clone, Hash, Copy, Eq, PartialEq)]
+///
+/// A clause is evaluated either in a query context, where it
+/// contributes to the score of the documents (`Should`, `Must`),
+/// or in a filter context, where it only restricts the set
+/// of matching documents (`Filter`, `MustNot`).
+#[derive(Debug, Clone, Hash, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Occur {
     /// For a given document to be considered for scoring,
     /// at least one of the document with the Should or the Must
     /// Occur constraint must be within the document.
+    #[serde(rename = "should")]
     Should,
     /// Document without the term are excluded from the search.
+    #[serde(rename = "must")]
     Must,
     /// Document that contain the term are excluded from the
     /// search.
+    #[serde(rename = "must_not")]
     MustNot,
+    /// Document without the term are excluded from the search,
+    /// as with `Must`, but the clause does not contribute to the score.
+    ///
+    /// The clause is evaluated with scoring disabled, and its
+    /// matches are materialized as a bitset.
+    /// This is the occurence to use for the criteria that do not
+    /// relate to relevance, such as a date range or a category.
+    #[serde(rename = "filter")]
+    Filter,
+}
+
+impl Occur {
+    /// Returns true if the clause has to match for a document to match,
+    /// i.e. for `Must` and `Filter`.
+    pub fn is_required(&self) -> bool {
+        match *self {
+            Occur::Must | Occur::Filter => true,
+            Occur::Should | Occur::MustNot => false,
+        }
+    }
+
+    /// Returns true if the clause contributes to the score
+    /// of the documents, i.e. for `Should` and `Must`.
+    pub fn is_scoring(&self) -> bool {
+        match *self {
+            Occur::Should | Occur::Must => true,
+            Occur::MustNot | Occur::Filter => false,
+        }
+    }
 }
@@ -2,7 +2,7 @@ mod phrase_query;
 mod phrase_weight;
 mod phrase_scorer;
 
-pub use self::phrase_query::PhraseQuery;
+pub use self::phrase_query::{PhraseMatchMode, PhraseQuery};
 pub use self::phrase_weight::PhraseWeight;
 pub use self::phrase_scorer::PhraseScorer;
 
@@ -74,6 +74,98 @@ mod tests {
         assert_eq!(test_query(vec!["g", "a"]), empty_vec);
     }
 
+    #[test]
+    pub fn test_phrase_query_slop() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0
+            index_writer.add_document(doc!(text_field=>"a b c"));
+            // 1
+            index_writer.add_document(doc!(text_field=>"a x b x c"));
+            // 2
+            index_writer.add_document(doc!(text_field=>"c b a"));
+            // 3
+            index_writer.add_document(doc!(text_field=>"b x x a x c"));
+            // 4
+            index_writer.add_document(doc!(text_field=>"a a x"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let test_query = |texts: Vec<&str>, slop: u32, match_mode: PhraseMatchMode| {
+            let mut test_collector = TestCollector::default();
+            let terms: Vec<Term> = texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect();
+            let mut phrase_query = PhraseQuery::from(terms);
+            phrase_query.set_slop(slop);
+            phrase_query.set_match_mode(match_mode);
+            searcher
+                .search(&phrase_query, &mut test_collector)
+                .expect("search should succeed");
+            test_collector.docs()
+        };
+        let ordered = PhraseMatchMode::Ordered;
+        let unordered = PhraseMatchMode::Unordered;
+        assert_eq!(test_query(vec!["a", "b", "c"], 0, ordered), vec![0]);
+        assert_eq!(test_query(vec!["a", "b", "c"], 1, ordered), vec![0]);
+        assert_eq!(test_query(vec!["a", "b", "c"], 2, ordered), vec![0, 1]);
+        assert_eq!(test_query(vec!["a", "b", "c"], 0, unordered), vec![0, 2]);
+        assert_eq!(test_query(vec!["a", "b", "c"], 2, unordered), vec![0, 1, 2]);
+        assert_eq!(test_query(vec!["a", "b", "c"], 3, unordered), vec![0, 1, 2, 3]);
+        assert_eq!(test_query(vec!["b", "a"], 1, ordered), vec![2]);
+        assert_eq!(test_query(vec!["b", "a"], 2, ordered), vec![2, 3]);
+        // repeated terms need distinct positions.
+        assert_eq!(test_query(vec!["a", "a"], 0, unordered), vec![4]);
+        assert_eq!(test_query(vec!["a", "a"], 3, ordered), vec![4]);
+        // the largest slops behave as an unbounded slop.
+        assert_eq!(test_query(vec!["a", "b", "c"], u32::max_value(), ordered), vec![0, 1]);
+        assert_eq!(
+            test_query(vec!["a", "b", "c"], u32::max_value(), unordered),
+            vec![0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    pub fn test_phrase_query_slop_scoring() {
+        use collector::TopCollector;
+        use query::QueryParser;
+
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            // 0
+            index_writer.add_document(doc!(text_field=>"quick x x fox"));
+            // 1
+            index_writer.add_document(doc!(text_field=>"quick fox"));
+            // 2
+            index_writer.add_document(doc!(text_field=>"quick x fox"));
+            // 3
+            index_writer.add_document(doc!(text_field=>"quick x x x fox"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![text_field]);
+        let query = query_parser.parse_query("\"quick fox\"~2").unwrap();
+        let mut top_collector = TopCollector::with_limit(10);
+        searcher.search(&*query, &mut top_collector).unwrap();
+        // closer matches come first.
+        let score_docs = top_collector.score_docs();
+        let docs: Vec<u32> = score_docs.iter().map(|score_doc| score_doc.1.doc()).collect();
+        assert_eq!(docs, vec![1, 2, 0]);
+        assert!(score_docs[0].0 > score_docs[1].0);
+        assert!(score_docs[1].0 > score_docs[2].0);
+    }
+
     #[test] // motivated by #234
     pub fn test_phrase_query_docfreq_order() {
         let mut schema_builder = SchemaBuilder::default();
@@ -4,6 +4,30 @@ use core::searcher::Searcher;
 use super::PhraseWeight;
 use query::Weight;
 use Result;
+use schema::Schema;
+use query::query_parser::phrase_to_query_string;
+use query::{QueryAst, TermAst};
+use std::collections::BTreeSet;
+
+/// How the terms of a `PhraseQuery` are matched when a slop is set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PhraseMatchMode {
+    /// The terms appear in the order of the phrase,
+    /// with at most `slop` extra positions between the first
+    /// and the last term.
+    #[serde(rename = "ordered")]
+    Ordered,
+    /// The terms appear in any order, within a window
+    /// of at most `slop` positions more than the number of terms.
+    #[serde(rename = "unordered")]
+    Unordered,
+}
+
+impl Default for PhraseMatchMode {
+    fn default() -> PhraseMatchMode {
+        PhraseMatchMode::Ordered
+    }
+}
 
 /// `PhraseQuery` matches a specific sequence of words.
 ///
@@ -16,29 +40,95 @@ use Result;
 ///
 /// **This is my favorite part of the job.**
 ///
+/// A slop makes it possible to match terms that are close
+/// to each other, rather than strictly adjacent.
+/// With a slop of 2, `"part time"` also matches **part of the time**.
+/// In the `Unordered` mode, it also matches **time for my part**.
+/// Documents in which the terms are closer, or match more often,
+/// get a higher score.
+///
+/// The query parser supports slops with the syntax `"part time"~2`.
+///
 /// Using a `PhraseQuery` on a field requires positions
 /// to be indexed for this field.
 ///
 #[derive(Debug)]
 pub struct PhraseQuery {
     phrase_terms: Vec<Term>,
+    slop: u32,
+    match_mode: PhraseMatchMode,
+}
+
+impl PhraseQuery {
+    /// Sets the number of extra positions tolerated
+    /// between the terms of the phrase. Defaults to 0.
+    pub fn set_slop(&mut self, slop: u32) {
+        self.slop = slop;
+    }
+
+    /// Returns the slop of the query.
+    pub fn slop(&self) -> u32 {
+        self.slop
+    }
+
+    /// Sets whether the terms have to appear in the order of the phrase.
+    /// Defaults to `PhraseMatchMode::Ordered`.
+    pub fn set_match_mode(&mut self, match_mode: PhraseMatchMode) {
+        self.match_mode = match_mode;
+    }
+
+    /// Returns the match mode of the query.
+    pub fn match_mode(&self) -> PhraseMatchMode {
+        self.match_mode
+    }
 }
 
 impl Query for PhraseQuery {
     /// Create the weight associated to a query.
     ///
     /// See [`Weight`](./trait.Weight.html).
-    fn weight(&self, _searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
-        Ok(box PhraseWeight::new(
-            self.phrase_terms.clone(),
-            scoring_enabled,
-        ))
+    fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
+        let boost = searcher.field_boost(self.phrase_terms[0].field());
+        let mut phrase_weight =
+            PhraseWeight::new(self.phrase_terms.clone(), scoring_enabled, boost);
+        phrase_weight.set_slop(self.slop, self.match_mode);
+        Ok(box phrase_weight)
+    }
+
+    /// Unordered phrases cannot be expressed in the syntax of the query parser.
+    fn to_query_string(&self, schema: &Schema) -> Option<String> {
+        match self.match_mode {
+            PhraseMatchMode::Ordered => {
+                phrase_to_query_string(&self.phrase_terms, self.slop, schema)
+            }
+            PhraseMatchMode::Unordered => None,
+        }
+    }
+
+    fn query_terms(&self, term_set: &mut BTreeSet<Term>) {
+        term_set.extend(self.phrase_terms.iter().cloned());
+    }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        let terms = self.phrase_terms
+            .iter()
+            .map(|term| TermAst::from_term(term, schema))
+            .collect::<Option<Vec<TermAst>>>()?;
+        Some(QueryAst::Phrase {
+            terms,
+            slop: self.slop,
+            match_mode: self.match_mode,
+        })
     }
 }
 
 impl From<Vec<Term>> for PhraseQuery {
     fn from(phrase_terms: Vec<Term>) -> PhraseQuery {
         assert!(phrase_terms.len() > 1);
-        PhraseQuery { phrase_terms }
+        PhraseQuery {
+            phrase_terms,
+            slop: 0,
+            match_mode: PhraseMatchMode::default(),
+        }
     }
 }
@@ -1,7 +1,9 @@
 use DocId;
+use Score;
 use docset::{DocSet, SkipResult};
 use postings::{Postings, SegmentPostings};
 use query::{Intersection, Scorer};
+use super::PhraseMatchMode;
 
 struct PostingsWithOffset {
     offset: u32,
@@ -45,12 +47,158 @@ impl DocSet for PostingsWithOffset {
     }
 }
 
+/// Weight of a sloppy match, decreasing with the number
+/// of extra positions between the terms of the phrase.
+fn proximity_weight(num_extra_positions: u32) -> f32 {
+    1f32 / (1f32 + num_extra_positions as f32)
+}
+
+/// Returns the sloppy frequency of the phrase, with the terms
+/// appearing in order, with at most `slop` extra positions between
+/// the first and the last term.
+///
+/// Each start position of the first term counts as one match,
+/// weighted by `proximity_weight`. A frequency of 0 means no match.
+///
+/// `positions_arr` holds the positions of each term of the phrase.
+fn ordered_sloppy_freq(positions_arr: &[&[u32]], slop: u32) -> f32 {
+    let num_terms = positions_arr.len() as u32;
+    // saturates for slops close to `u32::MAX`, as for an unbounded slop.
+    let max_span = slop.saturating_add(num_terms - 1);
+    // the earliest positions following a start position
+    // only move forward as the start position increases.
+    let mut cursors = vec![0usize; positions_arr.len()];
+    let mut sloppy_freq = 0f32;
+    'starts: for &start in positions_arr[0] {
+        let mut previous = start;
+        for (positions, cursor) in positions_arr.iter().zip(cursors.iter_mut()).skip(1) {
+            while *cursor < positions.len() && positions[*cursor] <= previous {
+                *cursor += 1;
+            }
+            if *cursor == positions.len() {
+                break 'starts;
+            }
+            previous = positions[*cursor];
+            if previous - start > max_span {
+                continue 'starts;
+            }
+        }
+        sloppy_freq += proximity_weight(previous - start + 1 - num_terms);
+    }
+    sloppy_freq
+}
+
+/// Returns the sloppy frequency of the phrase, with the terms
+/// appearing in any order, within a window of at most `slop` positions
+/// more than the number of terms.
+///
+/// Each minimal window containing all of the terms counts as one match,
+/// weighted by `proximity_weight`. A frequency of 0 means no match.
+///
+/// `term_groups` associates each term of the phrase with the first
+/// occurrence of the same term in the phrase, as a term repeated
+/// in the phrase has to appear as many times in the window.
+fn unordered_sloppy_freq(positions_arr: &[&[u32]], term_groups: &[usize], slop: u32) -> f32 {
+    let num_terms = positions_arr.len() as u32;
+    // saturates for slops close to `u32::MAX`, as for an unbounded slop.
+    let max_span = slop.saturating_add(num_terms - 1);
+    let mut required_counts = vec![0u32; positions_arr.len()];
+    for &group in term_groups {
+        required_counts[group] += 1;
+    }
+    let mut events: Vec<(u32, usize)> = Vec::new();
+    for (group, &required_count) in required_counts.iter().enumerate() {
+        if required_count > 0 {
+            events.extend(positions_arr[group].iter().map(|&position| (position, group)));
+        }
+    }
+    events.sort();
+    let num_groups = required_counts.iter().filter(|&&count| count > 0).count();
+    let mut counts = vec![0u32; positions_arr.len()];
+    let mut num_complete_groups = 0;
+    let mut window_start = 0;
+    let mut sloppy_freq = 0f32;
+    for &(position, group) in &events {
+        counts[group] += 1;
+        if counts[group] == required_counts[group] {
+            num_complete_groups += 1;
+        }
+        while num_complete_groups == num_groups {
+            let (start_position, start_group) = events[window_start];
+            if counts[start_group] == required_counts[start_group] {
+                // the window cannot be shrunk any further.
+                let span = position - start_position;
+                if span <= max_span {
+                    sloppy_freq += proximity_weight((span + 1).saturating_sub(num_terms));
+                }
+                num_complete_groups -= 1;
+            }
+            counts[start_group] -= 1;
+            window_start += 1;
+        }
+    }
+    sloppy_freq
+}
+
+/// Returns true if the terms appear at consecutive positions.
+fn exact_match(mut positions_arr: Vec<&[u32]>) -> bool {
+    let num_postings = positions_arr.len() as u32;
+
+    let mut ord = 1u32;
+    let mut pos_candidate = positions_arr[0][0];
+    positions_arr[0] = &(positions_arr[0])[1..];
+    let mut count_matching = 1;
+
+    #[cfg_attr(feature = "cargo-clippy", allow(never_loop))]
+    'outer: loop {
+        let target = pos_candidate + ord;
+        let positions = positions_arr[ord as usize];
+        for (i, pos_i) in positions.iter().cloned().enumerate() {
+            if pos_i < target {
+                continue;
+            }
+            if pos_i == target {
+                count_matching += 1;
+                if count_matching == num_postings {
+                    return true;
+                }
+            } else if pos_i > target {
+                count_matching = 1;
+                pos_candidate = positions[i] - ord;
+                positions_arr[ord as usize] = &(positions_arr[ord as usize])[(i + 1)..];
+            }
+            ord += 1;
+            if ord == num_postings {
+                ord = 0;
+            }
+            continue 'outer;
+        }
+        return false;
+    }
+}
+
 pub struct PhraseScorer {
     intersection_docset: Intersection<PostingsWithOffset>,
+    score: Score,
+    slop: u32,
+    match_mode: PhraseMatchMode,
+    term_groups: Vec<usize>,
+    phrase_freq: f32,
 }
 
 impl PhraseScorer {
-    pub fn new(term_postings: Vec<SegmentPostings>) -> PhraseScorer {
+    pub fn new(term_postings: Vec<SegmentPostings>, score: Score) -> PhraseScorer {
+        let term_groups = (0..term_postings.len()).collect();
+        PhraseScorer::with_slop(term_postings, score, 0, PhraseMatchMode::Ordered, term_groups)
+    }
+
+    pub(crate) fn with_slop(
+        term_postings: Vec<SegmentPostings>,
+        score: Score,
+        slop: u32,
+        match_mode: PhraseMatchMode,
+        term_groups: Vec<usize>,
+    ) -> PhraseScorer {
         let postings_with_offsets: Vec<_> = term_postings
             .into_iter()
             .enumerate()
@@ -58,10 +206,19 @@ impl PhraseScorer {
             .collect();
         PhraseScorer {
             intersection_docset: Intersection::from(postings_with_offsets),
+            score,
+            slop,
+            match_mode,
+            term_groups,
+            phrase_freq: 0f32,
         }
     }
 
-    fn phrase_match(&self) -> bool {
+    /// Returns the (sloppy) frequency of the phrase in the current document,
+    /// or 0 if the phrase does not match.
+    ///
+    /// Without slop, an exact match has a frequency of 1.
+    fn compute_phrase_freq(&self) -> f32 {
         // TODO maybe we could avoid decoding positions lazily for all terms
         // when there is > 2 terms.
         //
@@ -72,41 +229,25 @@ impl PhraseScorer {
         for docset in docsets {
             positions_arr[docset.offset as usize] = docset.positions();
         }
-
-        let num_postings = positions_arr.len() as u32;
-
-        let mut ord = 1u32;
-        let mut pos_candidate = positions_arr[0][0];
-        positions_arr[0] = &(positions_arr[0])[1..];
-        let mut count_matching = 1;
-
-        #[cfg_attr(feature = "cargo-clippy", allow(never_loop))]
-        'outer: loop {
-            let target = pos_candidate + ord;
-            let positions = positions_arr[ord as usize];
-            for (i, pos_i) in positions.iter().cloned().enumerate() {
-                if pos_i < target {
-                    continue;
-                }
-                if pos_i == target {
-                    count_matching += 1;
-                    if count_matching == num_postings {
-                        return true;
-                    }
-                } else if pos_i > target {
-                    count_matching = 1;
-                    pos_candidate = positions[i] - ord;
-                    positions_arr[ord as usize] = &(positions_arr[ord as usize])[(i + 1)..];
+        match self.match_mode {
+            PhraseMatchMode::Ordered if self.slop == 0 => {
+                if exact_match(positions_arr) {
+                    1f32
+                } else {
+                    0f32
                 }
-                ord += 1;
-                if ord == num_postings {
-                    ord = 0;
-                }
-                continue 'outer;
             }
-            return false;
+            PhraseMatchMode::Ordered => ordered_sloppy_freq(&positions_arr, self.slop),
+            PhraseMatchMode::Unordered => {
+                unordered_sloppy_freq(&positions_arr, &self.term_groups, self.slop)
+            }
         }
     }
+
+    fn phrase_match(&mut self) -> bool {
+        self.phrase_freq = self.compute_phrase_freq();
+        self.phrase_freq > 0f32
+    }
 }
 
 impl DocSet for PhraseScorer {
@@ -147,7 +288,8 @@ impl DocSet for PhraseScorer {
 }
 
 impl Scorer for PhraseScorer {
+    /// Closer and more frequent matches get a higher score.
     fn score(&mut self) -> f32 {
-        1f32
+        self.score * self.phrase_freq.sqrt()
     }
 }
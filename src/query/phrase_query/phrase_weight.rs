@@ -3,12 +3,16 @@ use query::Scorer;
 use schema::Term;
 use schema::IndexRecordOption;
 use core::SegmentReader;
-use super::PhraseScorer;
+use super::{PhraseMatchMode, PhraseScorer};
 use query::EmptyScorer;
 use Result;
+use Score;
 
 pub struct PhraseWeight {
     phrase_terms: Vec<Term>,
+    boost: Score,
+    slop: u32,
+    match_mode: PhraseMatchMode,
 }
 
 impl PhraseWeight {
@@ -16,11 +20,35 @@ impl PhraseWeight {
     ///
     /// Right now `scoring_enabled` is actually ignored.
     /// In the future, disabling scoring will result in a small performance boost.
-    // TODO use the scoring disable information to avoid compute the
-    // phrase freq in that case, and compute the phrase freq when scoring is enabled.
-    // Right now we never compute it :|
-    pub fn new(phrase_terms: Vec<Term>, _scoring_enabled: bool) -> PhraseWeight {
-        PhraseWeight { phrase_terms }
+    // TODO use the scoring disable information to avoid computing the
+    // sloppy phrase freq in that case.
+    pub fn new(phrase_terms: Vec<Term>, _scoring_enabled: bool, boost: Score) -> PhraseWeight {
+        PhraseWeight {
+            phrase_terms,
+            boost,
+            slop: 0,
+            match_mode: PhraseMatchMode::default(),
+        }
+    }
+
+    /// Sets the slop and the match mode of the phrase.
+    pub fn set_slop(&mut self, slop: u32, match_mode: PhraseMatchMode) {
+        self.slop = slop;
+        self.match_mode = match_mode;
+    }
+
+    /// Returns, for each term of the phrase, the offset of
+    /// the first occurrence of the same term in the phrase.
+    fn term_groups(&self) -> Vec<usize> {
+        self.phrase_terms
+            .iter()
+            .map(|term| {
+                self.phrase_terms
+                    .iter()
+                    .position(|other_term| other_term == term)
+                    .expect("The term belongs to the phrase")
+            })
+            .collect()
     }
 }
 
@@ -37,6 +65,20 @@ impl Weight for PhraseWeight {
                 return Ok(box EmptyScorer);
             }
         }
-        Ok(box PhraseScorer::new(term_postings_list))
+        Ok(box PhraseScorer::with_slop(
+            term_postings_list,
+            self.boost,
+            self.slop,
+            self.match_mode,
+            self.term_groups(),
+        ))
+    }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        Ok(self.phrase_terms
+            .iter()
+            .map(|term| reader.inverted_index(term.field()).doc_freq(term))
+            .min()
+            .unwrap_or(0))
     }
 }
@@ -3,8 +3,17 @@ use collector::Collector;
 use core::searcher::Searcher;
 use common::TimerTree;
 use SegmentLocalId;
+use DocAddress;
+use error::ErrorKind;
+use super::Explanation;
 use super::Weight;
+use super::create_weight;
+use super::WeightCache;
+use schema::Schema;
+use super::QueryAst;
 use std::fmt;
+use std::collections::BTreeSet;
+use Term;
 
 /// The `Query` trait defines a set of documents and a scoring method
 /// for those documents.
@@ -39,6 +48,9 @@ use std::fmt;
 ///
 /// When implementing a new type of `Query`, it is normal to implement a
 /// dedicated `Query`, `Weight` and `Scorer`.
+/// Compiling tantivy with the `docset-validation` feature makes
+/// searches check that the scorers honor the `DocSet` contract,
+/// and panic with the offending query otherwise.
 pub trait Query: fmt::Debug {
     /// Create the weight associated to a query.
     ///
@@ -48,9 +60,82 @@ pub trait Query: fmt::Debug {
     /// See [`Weight`](./trait.Weight.html).
     fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>>;
 
+    /// Creates the weight of the query as a sub query of a boolean query.
+    ///
+    /// Queries holding sub queries create their weights through
+    /// `weight_cache`, so that the sub queries appearing several times
+    /// in a boolean tree share their weight.
+    ///
+    /// The default implementation ignores the cache, and calls `.weight(...)`.
+    fn weight_with_cache(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+        _weight_cache: &mut WeightCache,
+    ) -> Result<Box<Weight>> {
+        self.weight(searcher, scoring_enabled)
+    }
+
+    /// Returns the query in the syntax of the [`QueryParser`](./struct.QueryParser.html),
+    /// or `None` if it cannot be expressed in this syntax.
+    ///
+    /// Parsing the returned string with a `QueryParser` over `schema`
+    /// gives back an equivalent query, as long as the parser composes
+    /// queries as a disjunction by default, and the tokenizers
+    /// of the fields leave the terms unchanged.
+    /// This makes it possible to log queries and to replay them.
+    ///
+    /// The default implementation returns `None`.
+    fn to_query_string(&self, _schema: &Schema) -> Option<String> {
+        None
+    }
+
+    /// Returns the serializable `QueryAst` of the query,
+    /// or `None` if it cannot be represented, for instance
+    /// because it relies on a closure.
+    ///
+    /// The default implementation returns `None`.
+    fn query_ast(&self, _schema: &Schema) -> Option<QueryAst> {
+        None
+    }
+
+    /// Extracts the terms of the query into `term_set`.
+    ///
+    /// Only the terms searched as such are extracted, e.g. to
+    /// highlight them in a `SnippetGenerator`. The terms matched
+    /// by a fuzzy, regex or range query, and the terms of
+    /// the `MustNot` clauses of a `BooleanQuery` are ignored.
+    ///
+    /// The default implementation extracts no term.
+    fn query_terms(&self, _term_set: &mut BTreeSet<Term>) {}
+
+    /// Returns the explanation of the score of the document at `doc_address`,
+    /// as a tree of the components the score was computed from.
+    ///
+    /// Returns an error if the document is deleted, or
+    /// does not match the query.
+    fn explain(&self, searcher: &Searcher, doc_address: DocAddress) -> Result<Explanation> {
+        let DocAddress(segment_ord, doc) = doc_address;
+        if segment_ord as usize >= searcher.segment_readers().len() {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "Unknown segment ordinal {}",
+                segment_ord
+            )));
+        }
+        let reader = searcher.segment_reader(segment_ord);
+        if reader.is_deleted(doc) {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "Document #({}) is deleted",
+                doc
+            )));
+        }
+        let weight = create_weight(self, searcher, true)?;
+        weight.explain(reader, doc)
+    }
+
     /// Returns the number of documents matching the query.
     fn count(&self, searcher: &Searcher) -> Result<usize> {
-        let weight = self.weight(searcher, false)?;
+        let weight = create_weight(self, searcher, false)?;
         let mut result = 0;
         for reader in searcher.segment_readers() {
             result += weight.count(reader)? as usize;
@@ -69,8 +154,9 @@ pub trait Query: fmt::Debug {
     ///
     fn search(&self, searcher: &Searcher, collector: &mut Collector) -> Result<TimerTree> {
         let mut timer_tree = TimerTree::default();
-        let scoring_enabled = collector.requires_scoring();
-        let weight = self.weight(searcher, scoring_enabled)?;
+        // term frequencies and positions are only decoded when scoring is enabled.
+        let scoring_enabled = collector.requires_scoring() || collector.requires_match_info();
+        let weight = create_weight(self, searcher, scoring_enabled)?;
         {
             let mut search_timer = timer_tree.open("search");
             for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
@@ -79,7 +165,7 @@ pub trait Query: fmt::Debug {
                     let _ = segment_search_timer.open("set_segment");
                     collector.set_segment(segment_ord as SegmentLocalId, segment_reader)?;
                 }
-                let mut scorer = weight.scorer(segment_reader)?;
+                let mut scorer = weight.alive_scorer(segment_reader)?;
                 {
                     let _collection_timer = segment_search_timer.open("collection");
                     scorer.collect(collector);
@@ -89,3 +175,17 @@ pub trait Query: fmt::Debug {
         Ok(timer_tree)
     }
 }
+
+/// A `Query` whose weight can be shared by the threads searching
+/// the segments in parallel.
+///
+/// See `Searcher::search_fruit_in_parallel`.
+pub trait ParallelQuery: Query {
+    /// Creates the weight associated to the query, like `.weight(...)`,
+    /// with the bounds required to share it between threads.
+    fn parallel_weight(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> Result<Box<Weight + Send + Sync>>;
+}
@@ -0,0 +1,448 @@
+use error::ErrorKind;
+use query::{AllQuery, BooleanQuery, ConstantScoreQuery, FuzzyTermQuery, JoinQuery, Occur,
+            PhraseMatchMode, PhraseQuery, Query, RangeQuery, RegexQuery, TermQuery};
+use query::query_parser::term_value_to_string;
+use schema::{DateTime, Field, FieldType, IndexRecordOption, Schema, Term};
+use std::collections::Bound;
+use std::str::FromStr;
+use std::time::Duration;
+use Result;
+use Score;
+
+/// Term of a `QueryAst`, identified by the name of its field
+/// and its value written as a string.
+///
+/// Numbers are written in decimal, dates in RFC 3339,
+/// and text terms are used as is, without being tokenized.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermAst {
+    /// Name of the field of the term.
+    pub field: String,
+    /// Value of the term.
+    pub value: String,
+}
+
+/// Bound of a range of a `QueryAst::Range`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundAst {
+    /// The value is within the range.
+    #[serde(rename = "included")]
+    Included(String),
+    /// The value is outside of the range.
+    #[serde(rename = "excluded")]
+    Excluded(String),
+    /// The range is not bounded on this side.
+    #[serde(rename = "unbounded")]
+    Unbounded,
+}
+
+impl Default for BoundAst {
+    fn default() -> BoundAst {
+        BoundAst::Unbounded
+    }
+}
+
+/// Clause of a `QueryAst::Boolean`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BooleanClauseAst {
+    /// Occurence of the sub query.
+    pub occur: Occur,
+    /// Sub query.
+    pub query: QueryAst,
+}
+
+fn default_index_record_option() -> IndexRecordOption {
+    IndexRecordOption::WithFreqs
+}
+
+/// Serializable representation of a `Query` tree.
+///
+/// A `QueryAst` is obtained from a query with `Query::query_ast`,
+/// and turned back into a query with `QueryAst::to_query`.
+/// As it refers to the fields by their name, queries can be built
+/// and serialized, for instance to JSON, by a client, and executed
+/// by a remote service holding the index.
+///
+/// ```rust
+/// # extern crate tantivy;
+/// # extern crate serde_json;
+/// # use tantivy::schema::{SchemaBuilder, TEXT};
+/// # use tantivy::query::{Query, QueryAst};
+/// # fn main() {
+/// #     let mut schema_builder = SchemaBuilder::new();
+/// #     schema_builder.add_text_field("title", TEXT);
+/// #     let schema = schema_builder.build();
+/// let json = r#"{
+///     "type": "boolean",
+///     "clauses": [
+///         {
+///             "occur": "must",
+///             "query": {"type": "term", "term": {"field": "title", "value": "diary"}}
+///         },
+///         {
+///             "occur": "must_not",
+///             "query": {
+///                 "type": "fuzzy",
+///                 "term": {"field": "title", "value": "cow"},
+///                 "distance": 1
+///             }
+///         }
+///     ]
+/// }"#;
+/// let query_ast: QueryAst = serde_json::from_str(json).unwrap();
+/// let query: Box<Query> = query_ast.to_query(&schema).unwrap();
+/// assert_eq!(query.query_ast(&schema).unwrap(), query_ast);
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum QueryAst {
+    /// See `AllQuery`.
+    #[serde(rename = "all")]
+    All,
+    /// See `TermQuery`.
+    #[serde(rename = "term")]
+    Term {
+        /// Term searched.
+        term: TermAst,
+        /// Information decoded from the postings. Defaults to `freq`.
+        #[serde(default = "default_index_record_option")]
+        index_record_option: IndexRecordOption,
+    },
+    /// See `PhraseQuery`.
+    #[serde(rename = "phrase")]
+    Phrase {
+        /// Terms of the phrase, at least two of them.
+        terms: Vec<TermAst>,
+        /// Slop of the phrase. Defaults to 0.
+        #[serde(default)]
+        slop: u32,
+        /// Match mode of the phrase. Defaults to `ordered`.
+        #[serde(default)]
+        match_mode: PhraseMatchMode,
+    },
+    /// See `BooleanQuery`.
+    #[serde(rename = "boolean")]
+    Boolean {
+        /// Sub queries and their occurences.
+        clauses: Vec<BooleanClauseAst>,
+        /// Minimum number of `should` sub queries to match. Defaults to 0.
+        #[serde(default)]
+        minimum_should_match: usize,
+    },
+    /// See `RangeQuery`.
+    #[serde(rename = "range")]
+    Range {
+        /// Name of the field of the range.
+        field: String,
+        /// Lower bound of the range. Defaults to `unbounded`.
+        #[serde(default)]
+        lower: BoundAst,
+        /// Upper bound of the range. Defaults to `unbounded`.
+        #[serde(default)]
+        upper: BoundAst,
+    },
+    /// See `FuzzyTermQuery`.
+    #[serde(rename = "fuzzy")]
+    Fuzzy {
+        /// Target term.
+        term: TermAst,
+        /// Maximum edit distance.
+        distance: u8,
+        /// Whether a transposition counts as a single edit. Defaults to false.
+        #[serde(default)]
+        transpositions: bool,
+    },
+    /// See `RegexQuery`.
+    #[serde(rename = "regex")]
+    Regex {
+        /// Name of the field of the terms.
+        field: String,
+        /// Regular expression the terms match.
+        pattern: String,
+        /// Maximum size of the automaton, in bytes.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_automaton_size: Option<usize>,
+        /// Maximum number of terms matched per segment.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_expansions: Option<usize>,
+        /// Time allowed to expand the regular expression, in milliseconds.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_ms: Option<u64>,
+    },
+    /// See `ConstantScoreQuery`.
+    #[serde(rename = "constant_score")]
+    ConstantScore {
+        /// Underlying query.
+        query: Box<QueryAst>,
+        /// Score of the matching documents.
+        score: Score,
+    },
+    /// See `JoinQuery`.
+    #[serde(rename = "join")]
+    Join {
+        /// Query matching the documents the values are collected from.
+        query: Box<QueryAst>,
+        /// Name of the field the values are collected from.
+        from_field: String,
+        /// Name of the field the values are looked up in.
+        to_field: String,
+    },
+}
+
+impl QueryAst {
+    /// Returns the query represented by the `QueryAst`.
+    ///
+    /// Returns an `InvalidArgument` error if a field does not exist in `schema`,
+    /// if a value cannot be parsed for the type of its field,
+    /// or if a phrase has less than two terms.
+    pub fn to_query(&self, schema: &Schema) -> Result<Box<Query>> {
+        let query: Box<Query> = match *self {
+            QueryAst::All => box AllQuery,
+            QueryAst::Term {
+                ref term,
+                index_record_option,
+            } => box TermQuery::new(term.to_term(schema)?, index_record_option),
+            QueryAst::Phrase {
+                ref terms,
+                slop,
+                match_mode,
+            } => {
+                if terms.len() < 2 {
+                    bail!(ErrorKind::InvalidArgument(format!(
+                        "A phrase requires at least two terms, got {}.",
+                        terms.len()
+                    )));
+                }
+                let terms = terms
+                    .iter()
+                    .map(|term| term.to_term(schema))
+                    .collect::<Result<Vec<Term>>>()?;
+                let mut phrase_query = PhraseQuery::from(terms);
+                phrase_query.set_slop(slop);
+                phrase_query.set_match_mode(match_mode);
+                box phrase_query
+            }
+            QueryAst::Boolean {
+                ref clauses,
+                minimum_should_match,
+            } => {
+                let subqueries = clauses
+                    .iter()
+                    .map(|clause| Ok((clause.occur, clause.query.to_query(schema)?)))
+                    .collect::<Result<Vec<(Occur, Box<Query>)>>>()?;
+                let mut boolean_query = BooleanQuery::from(subqueries);
+                boolean_query.set_minimum_should_match(minimum_should_match);
+                box boolean_query
+            }
+            QueryAst::Range {
+                ref field,
+                ref lower,
+                ref upper,
+            } => {
+                let value_bytes =
+                    |value: &str| -> Result<Vec<u8>> {
+                        Ok(parse_term(schema, field, value)?.value_bytes().to_vec())
+                    };
+                let to_bound = |bound: &BoundAst| -> Result<Bound<Vec<u8>>> {
+                    Ok(match *bound {
+                        BoundAst::Included(ref value) => Bound::Included(value_bytes(value)?),
+                        BoundAst::Excluded(ref value) => Bound::Excluded(value_bytes(value)?),
+                        BoundAst::Unbounded => Bound::Unbounded,
+                    })
+                };
+                let field = resolve_field(schema, field)?;
+                box RangeQuery::from_bounds(field, to_bound(lower)?, to_bound(upper)?)
+            }
+            QueryAst::Fuzzy {
+                ref term,
+                distance,
+                transpositions,
+            } => box FuzzyTermQuery::new(term.to_term(schema)?, distance, transpositions),
+            QueryAst::Regex {
+                ref field,
+                ref pattern,
+                max_automaton_size,
+                max_expansions,
+                timeout_ms,
+            } => {
+                let field = resolve_field(schema, field)?;
+                let mut regex_query = RegexQuery::new(field, pattern);
+                if let Some(max_automaton_size) = max_automaton_size {
+                    regex_query.set_max_automaton_size(max_automaton_size);
+                }
+                if let Some(max_expansions) = max_expansions {
+                    regex_query.set_max_expansions(max_expansions);
+                }
+                regex_query.set_timeout(timeout_ms.map(Duration::from_millis));
+                box regex_query
+            }
+            QueryAst::ConstantScore { ref query, score } => {
+                box ConstantScoreQuery::new(query.to_query(schema)?, score)
+            }
+            QueryAst::Join {
+                ref query,
+                ref from_field,
+                ref to_field,
+            } => box JoinQuery::new(
+                query.to_query(schema)?,
+                resolve_field(schema, from_field)?,
+                resolve_field(schema, to_field)?,
+            ),
+        };
+        Ok(query)
+    }
+}
+
+impl TermAst {
+    /// Returns the `TermAst` of a term,
+    /// or `None` if its field does not belong to `schema`.
+    pub fn from_term(term: &Term, schema: &Schema) -> Option<TermAst> {
+        let field_entry = schema.fields().get(term.field().0 as usize)?;
+        Some(TermAst {
+            field: field_entry.name().to_string(),
+            value: term_value_to_string(term, field_entry.field_type())?,
+        })
+    }
+
+    /// Returns the term represented by the `TermAst`.
+    pub fn to_term(&self, schema: &Schema) -> Result<Term> {
+        parse_term(schema, &self.field, &self.value)
+    }
+}
+
+/// Returns the `BoundAst` of a bound of a range over the values of `field`,
+/// or `None` if `field` does not belong to `schema`.
+pub(crate) fn bound_ast(field: Field, bound: &Bound<Vec<u8>>, schema: &Schema) -> Option<BoundAst> {
+    let value = |value_bytes: &[u8]| {
+        let term = Term::from_field_bytes(field, value_bytes);
+        TermAst::from_term(&term, schema).map(|term_ast| term_ast.value)
+    };
+    Some(match *bound {
+        Bound::Included(ref value_bytes) => BoundAst::Included(value(value_bytes)?),
+        Bound::Excluded(ref value_bytes) => BoundAst::Excluded(value(value_bytes)?),
+        Bound::Unbounded => BoundAst::Unbounded,
+    })
+}
+
+fn resolve_field(schema: &Schema, field_name: &str) -> Result<Field> {
+    let field = schema.get_field(field_name).ok_or_else(|| {
+        ErrorKind::InvalidArgument(format!("The field {:?} does not exist.", field_name))
+    })?;
+    Ok(field)
+}
+
+fn parse_term(schema: &Schema, field_name: &str, value: &str) -> Result<Term> {
+    let field = resolve_field(schema, field_name)?;
+    let invalid_value = || {
+        ErrorKind::InvalidArgument(format!(
+            "Invalid value {:?} for the field {:?}.",
+            value, field_name
+        ))
+    };
+    let term = match *schema.get_field_entry(field).field_type() {
+        FieldType::U64(_) => {
+            let val = u64::from_str(value).map_err(|_| invalid_value())?;
+            Term::from_field_u64(field, val)
+        }
+        FieldType::I64(_) => {
+            let val = i64::from_str(value).map_err(|_| invalid_value())?;
+            Term::from_field_i64(field, val)
+        }
+        FieldType::F64(_) => {
+            let val = f64::from_str(value).map_err(|_| invalid_value())?;
+            Term::from_field_f64(field, val)
+        }
+        FieldType::Date(_) => {
+            let val = DateTime::from_str(value).map_err(|_| invalid_value())?;
+            Term::from_field_date(field, &val)
+        }
+        FieldType::Str(_) | FieldType::HierarchicalFacet => Term::from_field_text(field, value),
+    };
+    Ok(term)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::QueryAst;
+    use query::{BooleanQuery, FuzzyTermQuery, Occur, PhraseMatchMode, PhraseQuery, Query,
+                RangeQuery, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, Term, INT_INDEXED, TEXT};
+    use serde_json;
+
+    #[test]
+    fn test_query_ast_round_trip() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let year = schema_builder.add_i64_field("year", INT_INDEXED);
+        let schema = schema_builder.build();
+        let mut phrase_query = PhraseQuery::from(vec![
+            Term::from_field_text(title, "dairy"),
+            Term::from_field_text(title, "cow"),
+        ]);
+        phrase_query.set_slop(2);
+        phrase_query.set_match_mode(PhraseMatchMode::Unordered);
+        let term_query: Box<Query> = box TermQuery::new(
+            Term::from_field_text(title, "diary"),
+            IndexRecordOption::Basic,
+        );
+        let phrase_query: Box<Query> = box phrase_query;
+        let range_query: Box<Query> = box RangeQuery::new_i64(year, -10..1970);
+        let fuzzy_query: Box<Query> =
+            box FuzzyTermQuery::new(Term::from_field_text(title, "wind"), 1, true);
+        let mut boolean_query = BooleanQuery::from(vec![
+            (Occur::Should, term_query),
+            (Occur::Should, phrase_query),
+            (Occur::Must, range_query),
+            (Occur::MustNot, fuzzy_query),
+        ]);
+        boolean_query.set_minimum_should_match(1);
+        let query_ast = boolean_query.query_ast(&schema).unwrap();
+        let json = serde_json::to_string(&query_ast).unwrap();
+        let deserialized_query_ast: QueryAst = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized_query_ast, query_ast);
+        let query = deserialized_query_ast.to_query(&schema).unwrap();
+        assert_eq!(query.query_ast(&schema).unwrap(), query_ast);
+        let expected_range_clause: serde_json::Value = serde_json::from_str(
+            r#"{
+                "occur": "must",
+                "query": {
+                    "type": "range",
+                    "field": "year",
+                    "lower": {"included": "-10"},
+                    "upper": {"excluded": "1970"}
+                }
+            }"#,
+        ).unwrap();
+        assert_eq!(
+            serde_json::to_value(&query_ast).unwrap()["clauses"][2],
+            expected_range_clause
+        );
+    }
+
+    #[test]
+    fn test_query_ast_errors() {
+        let mut schema_builder = SchemaBuilder::default();
+        schema_builder.add_i64_field("year", INT_INDEXED);
+        let schema = schema_builder.build();
+        let to_query = |json: &str| {
+            let query_ast: QueryAst = serde_json::from_str(json).unwrap();
+            query_ast.to_query(&schema)
+        };
+        assert!(to_query(r#"{"type": "all"}"#).is_ok());
+        let term_json = |field: &str, value: &str| {
+            format!(
+                r#"{{"type": "term", "term": {{"field": "{}", "value": "{}"}}}}"#,
+                field, value
+            )
+        };
+        assert!(to_query(&term_json("year", "1970")).is_ok());
+        assert!(to_query(&term_json("title", "a")).is_err());
+        assert!(to_query(&term_json("year", "a")).is_err());
+        assert!(
+            to_query(r#"{"type": "phrase", "terms": [{"field": "year", "value": "1970"}]}"#)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use query::{AllQuery, BooleanQuery, Occur, PhraseQuery, Query, TermQuery};
+use schema::{IndexRecordOption, Term};
+
+enum Node {
+    Query(Box<Query>),
+    Conjunction(Vec<(Occur, Box<Query>)>),
+    Disjunction(Vec<Box<Query>>),
+}
+
+/// Fluent API to build nested boolean queries.
+///
+/// The operators are applied from left to right:
+/// `a.or(b).and(c)` matches the documents matching `c`
+/// and either `a` or `b`.
+/// Chaining the same operator does not nest the queries:
+/// `a.and(b).and(c)` builds a single `BooleanQuery` with three clauses.
+///
+/// Any `Query` can be used as an operand.
+///
+/// ```rust
+/// # extern crate tantivy;
+/// # use tantivy::Term;
+/// # use tantivy::schema::{SchemaBuilder, TEXT};
+/// use tantivy::query::QueryBuilder;
+///
+/// # fn main() {
+/// #     let mut schema_builder = SchemaBuilder::new();
+/// #     let title = schema_builder.add_text_field("title", TEXT);
+/// let term = |text: &str| Term::from_field_text(title, text);
+/// // (diary AND (cow OR goat)) AND NOT wind
+/// let query = QueryBuilder::term(term("diary"))
+///     .and(QueryBuilder::term(term("cow")).or(QueryBuilder::term(term("goat"))))
+///     .and_not(QueryBuilder::term(term("wind")))
+///     .build();
+/// # }
+/// ```
+pub struct QueryBuilder {
+    node: Node,
+}
+
+impl QueryBuilder {
+    /// Starts from a query matching the documents containing `term`.
+    pub fn term(term: Term) -> QueryBuilder {
+        QueryBuilder::from(TermQuery::new(term, IndexRecordOption::WithFreqs))
+    }
+
+    /// Starts from a query matching the documents containing
+    /// the sequence of terms `terms`.
+    ///
+    /// # Panics
+    /// Panics if `terms` contains less than two terms,
+    /// as `PhraseQuery` does.
+    pub fn phrase(terms: Vec<Term>) -> QueryBuilder {
+        QueryBuilder::from(PhraseQuery::from(terms))
+    }
+
+    /// Starts from a query matching all of the documents.
+    pub fn all() -> QueryBuilder {
+        QueryBuilder::from(AllQuery)
+    }
+
+    /// Matches the documents matching both the current query and `other`.
+    pub fn and<T: Into<QueryBuilder>>(self, other: T) -> QueryBuilder {
+        self.with_clause(Occur::Must, other.into())
+    }
+
+    /// Matches the documents matching both the current query and `other`,
+    /// without `other` contributing to the score.
+    ///
+    /// See `Occur::Filter`.
+    pub fn filter<T: Into<QueryBuilder>>(self, other: T) -> QueryBuilder {
+        self.with_clause(Occur::Filter, other.into())
+    }
+
+    /// Matches the documents matching the current query but not `other`.
+    pub fn and_not<T: Into<QueryBuilder>>(self, other: T) -> QueryBuilder {
+        self.with_clause(Occur::MustNot, other.into())
+    }
+
+    /// Matches the documents matching the current query or `other`.
+    pub fn or<T: Into<QueryBuilder>>(self, other: T) -> QueryBuilder {
+        let other = other.into().build();
+        let subqueries = match self.node {
+            Node::Disjunction(mut subqueries) => {
+                subqueries.push(other);
+                subqueries
+            }
+            node => vec![QueryBuilder { node }.build(), other],
+        };
+        QueryBuilder {
+            node: Node::Disjunction(subqueries),
+        }
+    }
+
+    /// Matches the documents matching the current query,
+    /// or not matching `other`.
+    pub fn or_not<T: Into<QueryBuilder>>(self, other: T) -> QueryBuilder {
+        self.or(QueryBuilder::all().and_not(other))
+    }
+
+    /// Builds the query.
+    pub fn build(self) -> Box<Query> {
+        match self.node {
+            Node::Query(query) => query,
+            Node::Conjunction(subqueries) => box BooleanQuery::from(subqueries),
+            Node::Disjunction(subqueries) => box BooleanQuery::from(
+                subqueries
+                    .into_iter()
+                    .map(|subquery| (Occur::Should, subquery))
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    fn with_clause(self, occur: Occur, other: QueryBuilder) -> QueryBuilder {
+        let other = other.build();
+        let subqueries = match self.node {
+            Node::Conjunction(mut subqueries) => {
+                subqueries.push((occur, other));
+                subqueries
+            }
+            node => vec![(Occur::Must, QueryBuilder { node }.build()), (occur, other)],
+        };
+        QueryBuilder {
+            node: Node::Conjunction(subqueries),
+        }
+    }
+}
+
+impl<TQuery: Query + 'static> From<TQuery> for QueryBuilder {
+    fn from(query: TQuery) -> QueryBuilder {
+        QueryBuilder::from(box query as Box<Query>)
+    }
+}
+
+impl From<Box<Query>> for QueryBuilder {
+    fn from(query: Box<Query>) -> QueryBuilder {
+        QueryBuilder {
+            node: Node::Query(query),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::QueryBuilder;
+    use query::{Query, RangeQuery};
+    use schema::{SchemaBuilder, INT_INDEXED, TEXT};
+    use Index;
+    use Term;
+
+    #[test]
+    fn test_query_builder() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let year = schema_builder.add_u64_field("year", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a b", year => 2000u64));
+            index_writer.add_document(doc!(text => "a c", year => 2001u64));
+            index_writer.add_document(doc!(text => "b c", year => 2002u64));
+            index_writer.add_document(doc!(text => "d", year => 2003u64));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term = |word: &str| QueryBuilder::term(Term::from_field_text(text, word));
+        let count = |query_builder: QueryBuilder| query_builder.build().count(&*searcher).unwrap();
+        assert_eq!(count(term("a")), 2);
+        assert_eq!(count(term("a").and(term("b"))), 1);
+        assert_eq!(count(term("a").or(term("b")).or(term("d"))), 4);
+        assert_eq!(count(term("a").or(term("b")).and(term("c"))), 2);
+        assert_eq!(count(term("a").or(term("b")).and_not(term("c"))), 1);
+        assert_eq!(count(term("a").or(term("b").and(term("c")))), 3);
+        // a or not c
+        assert_eq!(count(term("a").or_not(term("c"))), 3);
+        assert_eq!(count(QueryBuilder::all().and_not(term("a"))), 2);
+        let phrase = QueryBuilder::phrase(vec![
+            Term::from_field_text(text, "a"),
+            Term::from_field_text(text, "c"),
+        ]);
+        assert_eq!(count(phrase), 1);
+        assert_eq!(count(term("c").and(RangeQuery::new_u64(year, 2002..2004))), 1);
+    }
+}
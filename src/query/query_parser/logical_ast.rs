@@ -5,7 +5,9 @@ use query::Occur;
 #[derive(Clone)]
 pub enum LogicalLiteral {
     Term(Term),
-    Phrase(Vec<Term>),
+    Phrase(Vec<Term>, u32),
+    Fuzzy(Term, u8),
+    Prefix(Term),
 }
 
 #[derive(Clone)]
@@ -19,6 +21,7 @@ fn occur_letter(occur: Occur) -> &'static str {
         Occur::Must => "+",
         Occur::MustNot => "-",
         Occur::Should => "",
+        Occur::Filter => "#",
     }
 }
 
@@ -53,7 +56,14 @@ impl fmt::Debug for LogicalLiteral {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
             LogicalLiteral::Term(ref term) => write!(formatter, "{:?}", term),
-            LogicalLiteral::Phrase(ref terms) => write!(formatter, "\"{:?}\"", terms),
+            LogicalLiteral::Phrase(ref terms, 0) => write!(formatter, "\"{:?}\"", terms),
+            LogicalLiteral::Phrase(ref terms, slop) => {
+                write!(formatter, "\"{:?}\"~{}", terms, slop)
+            }
+            LogicalLiteral::Fuzzy(ref term, distance) => {
+                write!(formatter, "{:?}~{}", term, distance)
+            }
+            LogicalLiteral::Prefix(ref term) => write!(formatter, "{:?}*", term),
         }
     }
 }
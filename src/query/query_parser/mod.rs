@@ -1,7 +1,12 @@
 mod query_parser;
 mod query_grammar;
 mod user_input_ast;
+mod query_string;
+mod query_sanitizer;
 
 pub mod logical_ast;
 pub use self::query_parser::QueryParser;
 pub use self::query_parser::QueryParserError;
+pub use self::query_sanitizer::{QuerySanitizer, DEFAULT_MAX_NUM_CHARS};
+pub(crate) use self::query_string::{phrase_to_query_string, term_to_query_string,
+                                    term_value_to_string};
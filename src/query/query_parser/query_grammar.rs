@@ -7,13 +7,30 @@ where
     I: Stream<Item = char>,
 {
     let term_val = || {
-        let word = many1(satisfy(|c: char| c.is_alphanumeric()));
-        let phrase = (char('"'), many1(satisfy(|c| c != '"')), char('"')).map(|(_, s, _)| s);
+        // the distance of a fuzzy word saturates, and is then rejected by the query parser.
+        let operator = (char('~'), many1(digit()))
+            .map(|(_, s): (char, String)| {
+                UserInputOperator::Fuzzy(s.parse::<u8>().unwrap_or(u8::max_value()))
+            })
+            .or(char('*').map(|_| UserInputOperator::Prefix));
+        let word = (
+            many1(satisfy(|c: char| c.is_alphanumeric())),
+            optional(operator),
+        ).map(|(s, operator): (String, Option<UserInputOperator>)| (s, 0, operator));
+        // the slop of a phrase saturates rather than failing on overflow.
+        let slop = (char('~'), many1(digit()))
+            .map(|(_, s): (char, String)| s.parse::<u32>().unwrap_or(u32::max_value()));
+        let phrase = (
+            char('"'),
+            many1(satisfy(|c| c != '"')),
+            char('"'),
+            optional(slop),
+        ).map(|(_, s, _, slop)| (s, slop.unwrap_or(0), None));
         phrase.or(word)
     };
 
     let negative_numbers = (char('-'), many1(satisfy(|c: char| c.is_numeric())))
-        .map(|(s1, s2): (char, String)| format!("{}{}", s1, s2));
+        .map(|(s1, s2): (char, String)| (format!("{}{}", s1, s2), 0, None));
 
     let field = (
         letter(),
@@ -23,13 +40,19 @@ where
     let term_val_with_field = negative_numbers.or(term_val());
 
     let term_query =
-        (field, char(':'), term_val_with_field).map(|(field_name, _, phrase)| UserInputLiteral {
-            field_name: Some(field_name),
-            phrase,
+        (field, char(':'), term_val_with_field).map(|(field_name, _, (phrase, slop, operator))| {
+            UserInputLiteral {
+                field_name: Some(field_name),
+                phrase,
+                slop,
+                operator,
+            }
         });
-    let term_default_field = term_val().map(|phrase| UserInputLiteral {
+    let term_default_field = term_val().map(|(phrase, slop, operator)| UserInputLiteral {
         field_name: None,
         phrase,
+        slop,
+        operator,
     });
     try(term_query)
         .or(term_default_field)
@@ -90,6 +113,14 @@ mod test {
         test_parse_query_to_ast_helper("-abc:toto", "-(abc:\"toto\")");
         test_parse_query_to_ast_helper("abc:a b", "(abc:\"a\" \"b\")");
         test_parse_query_to_ast_helper("abc:\"a b\"", "abc:\"a b\"");
+        test_parse_query_to_ast_helper("abc:\"a b\"~2", "abc:\"a b\"~2");
+        test_parse_query_to_ast_helper("\"a b\"~10 c", "(\"a b\"~10 \"c\")");
+        test_parse_query_to_ast_helper("\"a b\"~0", "\"a b\"");
+        test_parse_query_to_ast_helper("abc~1", "abc~1");
+        test_parse_query_to_ast_helper("title:abc~2 d", "(title:abc~2 \"d\")");
+        test_parse_query_to_ast_helper("abc~300", "abc~255");
+        test_parse_query_to_ast_helper("abc*", "abc*");
+        test_parse_query_to_ast_helper("+title:abc* -d", "(+(title:abc*) -(\"d\"))");
         test_is_parse_err("abc +    ");
     }
 }
@@ -8,11 +8,14 @@ use query::Occur;
 use query::TermQuery;
 use schema::IndexRecordOption;
 use query::PhraseQuery;
-use schema::{FieldType, Term};
+use query::{FuzzyTermQuery, RangeQuery, MAX_FUZZY_DISTANCE};
+use std::collections::Bound;
+use schema::{DateTime, DateTimeParseError, FieldType, Term};
 use std::str::FromStr;
 use tokenizer::TokenizerManager;
-use std::num::ParseIntError;
+use std::num::{ParseFloatError, ParseIntError};
 use core::Index;
+use super::QuerySanitizer;
 
 /// Possible error that may happen when parsing a query.
 #[derive(Debug, PartialEq, Eq)]
@@ -25,6 +28,12 @@ pub enum QueryParserError {
     /// The query contains a term for a `u64`-field, but the value
     /// is not a u64.
     ExpectedInt(ParseIntError),
+    /// The query contains a term for a `f64`-field, but the value
+    /// is not a f64.
+    ExpectedFloat(ParseFloatError),
+    /// The query contains a term for a date field, but the value
+    /// is not a RFC 3339 date.
+    ExpectedDate(DateTimeParseError),
     /// It is forbidden queries that are only "excluding". (e.g. -title:pop)
     AllButQueryForbidden,
     /// If no default field is declared, running a query without any
@@ -36,6 +45,15 @@ pub enum QueryParserError {
     /// The tokenizer for the given field is unknown
     /// The two argument strings are the name of the field, the name of the tokenizer
     UnknownTokenizer(String, String),
+    /// The query is shorter than the minimum length
+    /// of the `QuerySanitizer` of the query parser.
+    QueryTooShort,
+    /// The fuzzy or prefix operator was applied on a field that is not a text field.
+    /// The argument is the name of the field.
+    OperatorNotSupported(String),
+    /// The edit distance of a fuzzy word exceeds the maximum distance
+    /// supported by the `FuzzyTermQuery`.
+    FuzzyDistanceTooLarge(u8),
 }
 
 impl From<ParseIntError> for QueryParserError {
@@ -44,6 +62,18 @@ impl From<ParseIntError> for QueryParserError {
     }
 }
 
+impl From<ParseFloatError> for QueryParserError {
+    fn from(err: ParseFloatError) -> QueryParserError {
+        QueryParserError::ExpectedFloat(err)
+    }
+}
+
+impl From<DateTimeParseError> for QueryParserError {
+    fn from(err: DateTimeParseError) -> QueryParserError {
+        QueryParserError::ExpectedDate(err)
+    }
+}
+
 /// Tantivy's Query parser
 ///
 /// The language covered by the current parser is extremely simple.
@@ -72,11 +102,22 @@ impl From<ParseIntError> for QueryParserError {
 ///
 /// * must terms: By prepending a term by a `+`, a term can be made required for the search.
 ///
+/// * fuzzy terms: By appending `~N` to a word, the word matches the terms
+///   within `N` edits (1 or 2) of it, as a `FuzzyTermQuery`. e.g. `title:diary~1`
+///
+/// * prefix terms: By appending `*` to a word, the word matches all of the
+///   terms starting with it. e.g. `title:dia*`
+///
+///   Fuzzy and prefix terms only apply on text fields, and their matches
+///   all get a constant score. Should the tokenizer split the word into
+///   several tokens, the operator is ignored and the word is searched as a phrase.
+///
 pub struct QueryParser {
     schema: Schema,
     default_fields: Vec<Field>,
     conjunction_by_default: bool,
     tokenizer_manager: TokenizerManager,
+    query_sanitizer: Option<QuerySanitizer>,
 }
 
 impl QueryParser {
@@ -94,6 +135,7 @@ impl QueryParser {
             default_fields,
             tokenizer_manager,
             conjunction_by_default: false,
+            query_sanitizer: None,
         }
     }
 
@@ -114,6 +156,15 @@ impl QueryParser {
         self.conjunction_by_default = true;
     }
 
+    /// Sets the `QuerySanitizer` preprocessing the queries
+    /// before they are parsed.
+    ///
+    /// The queries rejected by the sanitizer yield a
+    /// `QueryParserError::QueryTooShort` error.
+    pub fn set_query_sanitizer(&mut self, query_sanitizer: QuerySanitizer) {
+        self.query_sanitizer = Some(query_sanitizer);
+    }
+
     /// Parse a query
     ///
     /// Note that `parse_query` returns an error if the input
@@ -125,7 +176,14 @@ impl QueryParser {
     /// Implementing a lenient mode for this query parser is tracked
     /// in [Issue 5](https://github.com/fulmicoton/tantivy/issues/5)
     pub fn parse_query(&self, query: &str) -> Result<Box<Query>, QueryParserError> {
-        let logical_ast = self.parse_query_to_logical_ast(query)?;
+        let logical_ast = if let Some(ref query_sanitizer) = self.query_sanitizer {
+            let sanitized_query = query_sanitizer
+                .sanitize(query)
+                .ok_or(QueryParserError::QueryTooShort)?;
+            self.parse_query_to_logical_ast(&sanitized_query)?
+        } else {
+            self.parse_query_to_logical_ast(query)?
+        };
         Ok(convert_to_query(logical_ast))
     }
 
@@ -155,7 +213,9 @@ impl QueryParser {
     fn compute_logical_ast_for_leaf(
         &self,
         field: Field,
-        phrase: &str
+        phrase: &str,
+        slop: u32,
+        operator: Option<UserInputOperator>,
     ) -> Result<Option<LogicalLiteral>, QueryParserError> {
         let field_entry = self.schema.get_field_entry(field);
         let field_type = field_entry.field_type();
@@ -163,6 +223,20 @@ impl QueryParser {
             let field_name = field_entry.name().to_string();
             return Err(QueryParserError::FieldNotIndexed(field_name));
         }
+        if let Some(operator) = operator {
+            match *field_type {
+                FieldType::Str(_) => {}
+                _ => {
+                    let field_name = field_entry.name().to_string();
+                    return Err(QueryParserError::OperatorNotSupported(field_name));
+                }
+            }
+            if let UserInputOperator::Fuzzy(distance) = operator {
+                if distance > MAX_FUZZY_DISTANCE {
+                    return Err(QueryParserError::FuzzyDistanceTooLarge(distance));
+                }
+            }
+        }
         match *field_type {
             FieldType::I64(_) => {
                 let val: i64 = i64::from_str(phrase)?;
@@ -174,6 +248,16 @@ impl QueryParser {
                 let term = Term::from_field_u64(field, val);
                 Ok(Some(LogicalLiteral::Term(term)))
             }
+            FieldType::F64(_) => {
+                let val: f64 = f64::from_str(phrase)?;
+                let term = Term::from_field_f64(field, val);
+                Ok(Some(LogicalLiteral::Term(term)))
+            }
+            FieldType::Date(_) => {
+                let val: DateTime = DateTime::from_str(phrase)?;
+                let term = Term::from_field_date(field, &val);
+                Ok(Some(LogicalLiteral::Term(term)))
+            }
             FieldType::Str(ref str_options) => {
                 if let Some(option) = str_options.get_indexing_options() {
                     let mut tokenizer = self.tokenizer_manager
@@ -193,11 +277,17 @@ impl QueryParser {
                     if terms.is_empty() {
                         Ok(None)
                     } else if terms.len() == 1 {
-                        Ok(Some(LogicalLiteral::Term(
-                            terms.into_iter().next().unwrap(),
-                        )))
+                        let term = terms.into_iter().next().unwrap();
+                        let literal = match operator {
+                            Some(UserInputOperator::Fuzzy(distance)) => {
+                                LogicalLiteral::Fuzzy(term, distance)
+                            }
+                            Some(UserInputOperator::Prefix) => LogicalLiteral::Prefix(term),
+                            None => LogicalLiteral::Term(term),
+                        };
+                        Ok(Some(literal))
                     } else {
-                        Ok(Some(LogicalLiteral::Phrase(terms)))
+                        Ok(Some(LogicalLiteral::Phrase(terms, slop)))
                     }
                 } else {
                     // This should have been seen earlier really.
@@ -263,7 +353,12 @@ impl QueryParser {
                 };
                 let mut asts: Vec<LogicalAST> = Vec::new();
                 for (field, phrase) in term_phrases {
-                    if let Some(ast) = self.compute_logical_ast_for_leaf(field, &phrase)? {
+                    if let Some(ast) = self.compute_logical_ast_for_leaf(
+                        field,
+                        &phrase,
+                        literal.slop,
+                        literal.operator,
+                    )? {
                         asts.push(LogicalAST::Leaf(box ast));
                     }
                 }
@@ -285,11 +380,11 @@ impl QueryParser {
 fn compose_occur(left: Occur, right: Occur) -> Occur {
     match left {
         Occur::Should => right,
-        Occur::Must => {
+        Occur::Must | Occur::Filter => {
             if right == Occur::MustNot {
                 Occur::MustNot
             } else {
-                Occur::Must
+                left
             }
         }
         Occur::MustNot => {
@@ -305,7 +400,24 @@ fn compose_occur(left: Occur, right: Occur) -> Occur {
 fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<Query> {
     match logical_literal {
         LogicalLiteral::Term(term) => box TermQuery::new(term, IndexRecordOption::WithFreqs),
-        LogicalLiteral::Phrase(terms) => box PhraseQuery::from(terms),
+        LogicalLiteral::Phrase(terms, slop) => {
+            let mut phrase_query = PhraseQuery::from(terms);
+            phrase_query.set_slop(slop);
+            box phrase_query
+        }
+        LogicalLiteral::Fuzzy(term, distance) => box FuzzyTermQuery::new(term, distance, true),
+        LogicalLiteral::Prefix(term) => {
+            // the terms starting with the prefix are lower than the prefix
+            // followed by 0xFF, as this byte never appears in UTF-8.
+            let prefix = term.value_bytes().to_vec();
+            let mut upper_bound = prefix.clone();
+            upper_bound.push(0xFF);
+            box RangeQuery::from_bounds(
+                term.field(),
+                Bound::Included(prefix),
+                Bound::Excluded(upper_bound),
+            )
+        }
     }
 }
 
@@ -331,6 +443,7 @@ mod test {
     use schema::{TextOptions, TextFieldIndexing, IndexRecordOption};
     use super::QueryParser;
     use super::QueryParserError;
+    use super::QuerySanitizer;
     use Index;
     use tokenizer::SimpleTokenizer;
     use super::super::logical_ast::*;
@@ -345,6 +458,8 @@ mod test {
         schema_builder.add_text_field("notindexed_u64", STORED);
         schema_builder.add_text_field("notindexed_i64", STORED);
         schema_builder.add_text_field("nottokenized", STRING);
+        schema_builder.add_date_field("date", INT_INDEXED);
+        schema_builder.add_f64_field("price", INT_INDEXED);
         let schema = schema_builder.build();
         let default_fields = vec![title, text];
         let tokenizer_manager = TokenizerManager::default();
@@ -378,6 +493,17 @@ mod test {
         assert!(query_parser.parse_query("toto").is_ok());
     }
 
+    #[test]
+    pub fn test_parse_query_with_sanitizer() {
+        let mut query_parser = make_query_parser();
+        query_parser.set_query_sanitizer(QuerySanitizer::default().set_min_num_chars(3));
+        assert!(query_parser.parse_query("\ttoto\u{0} ").is_ok());
+        assert_eq!(
+            query_parser.parse_query(" to ").unwrap_err(),
+            QueryParserError::QueryTooShort
+        );
+    }
+
     #[test]
     pub fn test_parse_nonindexed_field_yields_error() {
         let query_parser = make_query_parser();
@@ -490,6 +616,37 @@ mod test {
              Term([0, 0, 0, 0, 98])]\"",
             false,
         );
+        test_parse_query_to_logical_ast_helper(
+            "title:\"a b\"~3",
+            "\"[Term([0, 0, 0, 0, 97]), \
+             Term([0, 0, 0, 0, 98])]\"~3",
+            false,
+        );
+    }
+
+    #[test]
+    pub fn test_query_string_round_trip() {
+        let query_parser = make_query_parser();
+        let schema = query_parser.schema.clone();
+        let assert_round_trip = |query_str: &str, expected: &str| {
+            let query = query_parser.parse_query(query_str).unwrap();
+            let query_string = query.to_query_string(&schema).unwrap();
+            assert_eq!(query_string, expected);
+            let reparsed_query = query_parser.parse_query(&query_string).unwrap();
+            assert_eq!(reparsed_query.to_query_string(&schema).unwrap(), expected);
+        };
+        assert_round_trip("title:a", "title:\"a\"");
+        assert_round_trip("title:\"a b\"~2", "title:\"a b\"~2");
+        assert_round_trip("signed:-3 unsigned:4", "(signed:\"-3\" unsigned:\"4\")");
+        assert_round_trip(
+            "date:\"2018-03-21T14:30:00+02:00\"",
+            "date:\"2018-03-21T12:30:00Z\"",
+        );
+        assert_round_trip("price:\"-2.50\"", "price:\"-2.5\"");
+        assert_round_trip(
+            "+title:a -(text:b title:c) d",
+            "(+title:\"a\" -(text:\"b\" title:\"c\") (title:\"d\" text:\"d\"))",
+        );
     }
 
     #[test]
@@ -557,6 +714,91 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_query_parser_expected_float() {
+        let query_parser = make_query_parser();
+        assert!(query_parser.parse_query("price:\"3.5\"").is_ok());
+        assert!(query_parser.parse_query("price:\"-0.25\"").is_ok());
+        assert!(query_parser.parse_query("price:3").is_ok());
+        assert_matches!(
+            query_parser.parse_query("price:abc"),
+            Err(QueryParserError::ExpectedFloat(_))
+        );
+    }
+
+    #[test]
+    pub fn test_query_parser_expected_date() {
+        let query_parser = make_query_parser();
+        assert!(query_parser.parse_query("date:\"2018-03-21\"").is_ok());
+        assert!(query_parser.parse_query("date:\"2018-03-21T12:30:00Z\"").is_ok());
+        assert_matches!(
+            query_parser.parse_query("date:\"2018-02-30\""),
+            Err(QueryParserError::ExpectedDate(_))
+        );
+        assert_matches!(
+            query_parser.parse_query("date:1521635400"),
+            Err(QueryParserError::ExpectedDate(_))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_fuzzy_and_prefix() {
+        test_parse_query_to_logical_ast_helper(
+            "title:toto~1",
+            "Term([0, 0, 0, 0, 116, 111, 116, 111])~1",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "title:ToTo*",
+            "Term([0, 0, 0, 0, 116, 111, 116, 111])*",
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "toto*",
+            "(Term([0, 0, 0, 0, 116, 111, 116, 111])* \
+             Term([0, 0, 0, 1, 116, 111, 116, 111])*)",
+            false,
+        );
+        let query_parser = make_query_parser();
+        assert_matches!(
+            query_parser.parse_query("title:toto~3"),
+            Err(QueryParserError::FuzzyDistanceTooLarge(3))
+        );
+        assert_matches!(
+            query_parser.parse_query("unsigned:18*"),
+            Err(QueryParserError::OperatorNotSupported(_))
+        );
+    }
+
+    #[test]
+    pub fn test_query_parser_fuzzy_and_prefix_search() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(title => "The Diary of Muadib"));
+            index_writer.add_document(doc!(title => "A Dairy Cow"));
+            index_writer.add_document(doc!(title => "The Dialogues"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![title]);
+        let count = |query: &str| {
+            let query = query_parser.parse_query(query).unwrap();
+            query.count(&*searcher).unwrap()
+        };
+        assert_eq!(count("diary~1"), 2);
+        assert_eq!(count("diary~0"), 1);
+        assert_eq!(count("Dia*"), 2);
+        assert_eq!(count("+dia* -diary"), 1);
+        assert_eq!(count("title:d*"), 3);
+        // the prefix matches the term itself.
+        assert_eq!(count("dialogues*"), 1);
+        assert_eq!(count("dialoguesx*"), 0);
+    }
+
     #[test]
     pub fn test_parse_query_to_ast_conjunction() {
         test_parse_query_to_logical_ast_helper(
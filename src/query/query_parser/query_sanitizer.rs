@@ -0,0 +1,138 @@
+/// Default maximum number of chars of a sanitized query.
+pub const DEFAULT_MAX_NUM_CHARS: usize = 256;
+
+/// Preprocesses the query strings typed by users before they are parsed,
+/// typically from a public-facing search box.
+///
+/// In order, the sanitizer
+/// * replaces the whitespace control characters (`\n`, `\t`, ...) by
+///   a space, and strips the other control characters.
+/// * collapses the repeated `*` wildcards into one.
+/// * trims the query.
+/// * caps the query to `max_num_chars` chars.
+///
+/// Queries shorter than `min_num_chars` chars after these
+/// steps are rejected.
+///
+/// ```rust
+/// use tantivy::query::QuerySanitizer;
+///
+/// let query_sanitizer = QuerySanitizer::default().set_min_num_chars(2);
+/// assert_eq!(query_sanitizer.sanitize("  tax\tpay***er\u{0}  ").unwrap(), "tax pay*er");
+/// assert!(query_sanitizer.sanitize(" a ").is_none());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuerySanitizer {
+    min_num_chars: usize,
+    max_num_chars: usize,
+    strip_control_chars: bool,
+    collapse_wildcards: bool,
+}
+
+impl Default for QuerySanitizer {
+    fn default() -> QuerySanitizer {
+        QuerySanitizer {
+            min_num_chars: 1,
+            max_num_chars: DEFAULT_MAX_NUM_CHARS,
+            strip_control_chars: true,
+            collapse_wildcards: true,
+        }
+    }
+}
+
+impl QuerySanitizer {
+    /// Sets the minimum number of chars of a query. Defaults to `1`,
+    /// so that only the empty queries are rejected.
+    pub fn set_min_num_chars(mut self, min_num_chars: usize) -> QuerySanitizer {
+        self.min_num_chars = min_num_chars;
+        self
+    }
+
+    /// Sets the maximum number of chars of a query.
+    /// The longer queries are truncated.
+    ///
+    /// Defaults to `DEFAULT_MAX_NUM_CHARS`.
+    pub fn set_max_num_chars(mut self, max_num_chars: usize) -> QuerySanitizer {
+        self.max_num_chars = max_num_chars;
+        self
+    }
+
+    /// Sets whether control characters are stripped. Defaults to `true`.
+    pub fn set_strip_control_chars(mut self, strip_control_chars: bool) -> QuerySanitizer {
+        self.strip_control_chars = strip_control_chars;
+        self
+    }
+
+    /// Sets whether repeated `*` wildcards are collapsed. Defaults to `true`.
+    pub fn set_collapse_wildcards(mut self, collapse_wildcards: bool) -> QuerySanitizer {
+        self.collapse_wildcards = collapse_wildcards;
+        self
+    }
+
+    /// Returns the sanitized query, or `None` if it is
+    /// shorter than the minimum number of chars.
+    pub fn sanitize(&self, query: &str) -> Option<String> {
+        let mut sanitized = String::with_capacity(query.len());
+        for c in query.chars() {
+            if self.strip_control_chars && c.is_control() {
+                if c.is_whitespace() {
+                    sanitized.push(' ');
+                }
+                continue;
+            }
+            if self.collapse_wildcards && c == '*' && sanitized.ends_with('*') {
+                continue;
+            }
+            sanitized.push(c);
+        }
+        let capped: String = sanitized
+            .trim()
+            .chars()
+            .take(self.max_num_chars)
+            .collect();
+        let capped = capped.trim_right();
+        if capped.chars().count() < self.min_num_chars {
+            None
+        } else {
+            Some(capped.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::QuerySanitizer;
+
+    #[test]
+    fn test_query_sanitizer_default() {
+        let query_sanitizer = QuerySanitizer::default();
+        assert_eq!(query_sanitizer.sanitize(" happy  tax ").unwrap(), "happy  tax");
+        assert_eq!(
+            query_sanitizer.sanitize("happy\ntax\u{7}payer").unwrap(),
+            "happy taxpayer"
+        );
+        assert_eq!(query_sanitizer.sanitize("ta**x * **").unwrap(), "ta*x * *");
+        assert!(query_sanitizer.sanitize("").is_none());
+        assert!(query_sanitizer.sanitize(" \t\u{0} ").is_none());
+        let long_query: String = ::std::iter::repeat("é").take(300).collect();
+        assert_eq!(
+            query_sanitizer.sanitize(&long_query).unwrap().chars().count(),
+            256
+        );
+    }
+
+    #[test]
+    fn test_query_sanitizer_settings() {
+        let query_sanitizer = QuerySanitizer::default()
+            .set_min_num_chars(3)
+            .set_max_num_chars(6)
+            .set_strip_control_chars(false)
+            .set_collapse_wildcards(false);
+        assert!(query_sanitizer.sanitize(" ab ").is_none());
+        assert_eq!(query_sanitizer.sanitize("abc").unwrap(), "abc");
+        assert_eq!(query_sanitizer.sanitize("ab\u{7}c**").unwrap(), "ab\u{7}c**");
+        // the query is trimmed after being capped.
+        assert_eq!(query_sanitizer.sanitize("happy tax").unwrap(), "happy");
+    }
+}
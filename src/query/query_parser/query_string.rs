@@ -0,0 +1,59 @@
+use schema::{DateTime, FieldType, Schema, Term};
+use std::str;
+
+/// Returns the value of a term as a string, e.g. `2018-03-21T00:00:00Z` for a date,
+/// or `None` if the bytes of a text term are not valid utf-8.
+pub(crate) fn term_value_to_string(term: &Term, field_type: &FieldType) -> Option<String> {
+    let value = match *field_type {
+        FieldType::U64(_) => term.get_u64().to_string(),
+        FieldType::I64(_) => term.get_i64().to_string(),
+        FieldType::F64(_) => term.get_f64().to_string(),
+        FieldType::Date(_) => DateTime::from_timestamp_micros(term.get_i64()).to_string(),
+        FieldType::Str(_) | FieldType::HierarchicalFacet => {
+            str::from_utf8(term.value_bytes()).ok()?.to_string()
+        }
+    };
+    Some(value)
+}
+
+/// Returns the `field:"value"` part of the query string of a term or a phrase,
+/// or `None` if the field name or the value cannot be expressed
+/// in the syntax of the query parser.
+fn literal_to_query_string(terms: &[Term], schema: &Schema) -> Option<String> {
+    let field = terms.first()?.field();
+    let field_entry = schema.fields().get(field.0 as usize)?;
+    let field_name = field_entry.name();
+    let mut field_name_chars = field_name.chars();
+    let is_valid_field_name = field_name_chars.next().map_or(false, char::is_alphabetic)
+        && field_name_chars.all(|c| c.is_alphanumeric() || c == '_');
+    if !is_valid_field_name {
+        return None;
+    }
+    let mut values = Vec::with_capacity(terms.len());
+    for term in terms {
+        if term.field() != field {
+            return None;
+        }
+        let value = term_value_to_string(term, field_entry.field_type())?;
+        if value.is_empty() || value.contains('"') {
+            return None;
+        }
+        values.push(value);
+    }
+    Some(format!("{}:\"{}\"", field_name, values.join(" ")))
+}
+
+/// Returns the query string of a term, e.g. `title:"diary"`.
+pub(crate) fn term_to_query_string(term: &Term, schema: &Schema) -> Option<String> {
+    literal_to_query_string(&[term.clone()], schema)
+}
+
+/// Returns the query string of an ordered phrase, e.g. `title:"diary cow"~2`.
+pub(crate) fn phrase_to_query_string(terms: &[Term], slop: u32, schema: &Schema) -> Option<String> {
+    let literal = literal_to_query_string(terms, schema)?;
+    if slop > 0 {
+        Some(format!("{}~{}", literal, slop))
+    } else {
+        Some(literal)
+    }
+}
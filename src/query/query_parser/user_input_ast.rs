@@ -1,16 +1,42 @@
 use std::fmt;
 
+/// Operator applied on a single word of the query.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UserInputOperator {
+    /// `word~N` matches the terms within `N` edits of the word.
+    Fuzzy(u8),
+    /// `word*` matches the terms starting with the word.
+    Prefix,
+}
+
 pub struct UserInputLiteral {
     pub field_name: Option<String>,
     pub phrase: String,
+    pub slop: u32,
+    pub operator: Option<UserInputOperator>,
 }
 
 impl fmt::Debug for UserInputLiteral {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if let Some(operator) = self.operator {
+            if let Some(ref field_name) = self.field_name {
+                write!(formatter, "{}:", field_name)?;
+            }
+            return match operator {
+                UserInputOperator::Fuzzy(distance) => {
+                    write!(formatter, "{}~{}", self.phrase, distance)
+                }
+                UserInputOperator::Prefix => write!(formatter, "{}*", self.phrase),
+            };
+        }
         match self.field_name {
-            Some(ref field_name) => write!(formatter, "{}:\"{}\"", field_name, self.phrase),
-            None => write!(formatter, "\"{}\"", self.phrase),
+            Some(ref field_name) => write!(formatter, "{}:\"{}\"", field_name, self.phrase)?,
+            None => write!(formatter, "\"{}\"", self.phrase)?,
+        }
+        if self.slop > 0 {
+            write!(formatter, "~{}", self.slop)?;
         }
+        Ok(())
     }
 }
 
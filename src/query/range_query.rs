@@ -1,14 +1,18 @@
-use schema::{Field, IndexRecordOption, Term};
+use schema::{DateTime, Field, IndexRecordOption, Term};
 use query::{Query, Scorer, Weight};
 use termdict::{TermDictionary, TermStreamer, TermStreamerBuilder};
 use core::SegmentReader;
 use common::BitSet;
 use Result;
+use Score;
 use core::Searcher;
 use query::BitSetDocSet;
 use query::ConstScorer;
 use std::collections::Bound;
 use std::collections::range::RangeArgument;
+use schema::Schema;
+use query::QueryAst;
+use query::query_ast::bound_ast;
 
 fn map_bound<TFrom, Transform: Fn(TFrom) -> Vec<u8>>(
     bound: Bound<TFrom>,
@@ -113,6 +117,47 @@ impl RangeQuery {
         }
     }
 
+    /// Create a new `RangeQuery` over a `f64` field.
+    pub fn new_f64<TRangeArgument: RangeArgument<f64>>(
+        field: Field,
+        range: TRangeArgument,
+    ) -> RangeQuery {
+        let make_term_val = |val: &f64| Term::from_field_f64(field, *val).value_bytes().to_owned();
+        RangeQuery {
+            field,
+            left_bound: map_bound(range.start(), &make_term_val),
+            right_bound: map_bound(range.end(), &make_term_val),
+        }
+    }
+
+    /// Create a new `RangeQuery` over a date field.
+    pub fn new_date<TRangeArgument: RangeArgument<DateTime>>(
+        field: Field,
+        range: TRangeArgument,
+    ) -> RangeQuery {
+        let make_term_val =
+            |val: &DateTime| Term::from_field_date(field, val).value_bytes().to_owned();
+        RangeQuery {
+            field,
+            left_bound: map_bound(range.start(), &make_term_val),
+            right_bound: map_bound(range.end(), &make_term_val),
+        }
+    }
+
+    /// Create a new `RangeQuery` over the values of `field`,
+    /// given the bounds of the bytes of the values of its terms.
+    pub(crate) fn from_bounds(
+        field: Field,
+        left_bound: Bound<Vec<u8>>,
+        right_bound: Bound<Vec<u8>>,
+    ) -> RangeQuery {
+        RangeQuery {
+            field,
+            left_bound,
+            right_bound,
+        }
+    }
+
     /// Create a new `RangeQuery` over a `Str` field.
     pub fn new_str<'b, TRangeArgument: RangeArgument<&'b str>>(
         field: Field,
@@ -128,22 +173,46 @@ impl RangeQuery {
 }
 
 impl Query for RangeQuery {
-    fn weight(&self, _searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+    fn weight(&self, searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
         Ok(box RangeWeight {
             field: self.field,
+            boost: searcher.field_boost(self.field),
             left_bound: self.left_bound.clone(),
             right_bound: self.right_bound.clone(),
         })
     }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        Some(QueryAst::Range {
+            field: schema.fields().get(self.field.0 as usize)?.name().to_string(),
+            lower: bound_ast(self.field, &self.left_bound, schema)?,
+            upper: bound_ast(self.field, &self.right_bound, schema)?,
+        })
+    }
 }
 
 pub struct RangeWeight {
     field: Field,
+    boost: Score,
     left_bound: Bound<Vec<u8>>,
     right_bound: Bound<Vec<u8>>,
 }
 
 impl RangeWeight {
+    /// Returns false if the term or value bounds of the field
+    /// in the segment do not overlap with the range, in which case
+    /// the segment is skipped.
+    fn may_match(&self, reader: &SegmentReader) -> bool {
+        reader
+            .segment_meta()
+            .field_bounds(self.field)
+            .map(|field_bounds| {
+                field_bounds.may_overlap_terms(&self.left_bound, &self.right_bound)
+                    && field_bounds.may_overlap_values(&self.left_bound, &self.right_bound)
+            })
+            .unwrap_or(true)
+    }
+
     fn term_range<'a, T>(&self, term_dict: &'a T) -> T::Streamer
     where
         T: TermDictionary<'a> + 'a,
@@ -169,6 +238,12 @@ impl Weight for RangeWeight {
         let max_doc = reader.max_doc();
         let mut doc_bitset = BitSet::with_max_value(max_doc);
 
+        if !self.may_match(reader) {
+            return Ok(box ConstScorer::with_score(
+                BitSetDocSet::from(doc_bitset),
+                self.boost,
+            ));
+        }
         let inverted_index = reader.inverted_index(self.field);
         let term_dict = inverted_index.terms();
         let mut term_range = self.term_range(term_dict);
@@ -183,7 +258,21 @@ impl Weight for RangeWeight {
             }
         }
         let doc_bitset = BitSetDocSet::from(doc_bitset);
-        Ok(box ConstScorer::new(doc_bitset))
+        Ok(box ConstScorer::with_score(doc_bitset, self.boost))
+    }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        if !self.may_match(reader) {
+            return Ok(0u32);
+        }
+        let inverted_index = reader.inverted_index(self.field);
+        let term_dict = inverted_index.terms();
+        let mut term_range = self.term_range(term_dict);
+        let mut doc_freq_sum = 0u32;
+        while term_range.advance() {
+            doc_freq_sum = doc_freq_sum.saturating_add(term_range.value().doc_freq);
+        }
+        Ok(doc_freq_sum.min(reader.max_doc()))
     }
 }
 
@@ -191,7 +280,7 @@ impl Weight for RangeWeight {
 mod tests {
 
     use Index;
-    use schema::{Document, Field, SchemaBuilder, INT_INDEXED};
+    use schema::{DateTime, Document, Field, SchemaBuilder, INT_INDEXED};
     use collector::CountCollector;
     use std::collections::Bound;
     use query::Query;
@@ -284,4 +373,70 @@ mod tests {
         assert_eq!(count_multiples(RangeQuery::new_i64(int_field, 9..)), 91);
     }
 
+    #[test]
+    fn test_range_query_date() {
+        let mut schema_builder = SchemaBuilder::new();
+        let date_field = schema_builder.add_date_field("date", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let date = |text: &str| text.parse::<DateTime>().unwrap();
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 6_000_000).unwrap();
+            // one document per hour, over the 1969-12-31 and 1970-01-01.
+            for hour in -24i64..24i64 {
+                let timestamp_secs = hour * 3_600;
+                index_writer.add_document(doc!(
+                    date_field => DateTime::from_timestamp_secs(timestamp_secs)
+                ));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let count = |range_query: RangeQuery| range_query.count(&*searcher).unwrap();
+        assert_eq!(
+            count(RangeQuery::new_date(
+                date_field,
+                date("1969-12-31")..date("1970-01-01")
+            )),
+            24
+        );
+        assert_eq!(
+            count(RangeQuery::new_date(
+                date_field,
+                date("1969-12-31T22:30:00Z")..date("1970-01-01T01:00:00.000001Z")
+            )),
+            3
+        );
+        assert_eq!(count(RangeQuery::new_date(date_field, date("1970-01-01")..)), 24);
+    }
+
+    #[test]
+    fn test_range_query_f64() {
+        let mut schema_builder = SchemaBuilder::new();
+        let price_field = schema_builder.add_f64_field("price", INT_INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 6_000_000).unwrap();
+            // prices from -5.0 to 4.75, by steps of 0.25.
+            for i in -20i64..20i64 {
+                index_writer.add_document(doc!(price_field => i as f64 * 0.25));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let count = |range_query: RangeQuery| range_query.count(&*searcher).unwrap();
+        assert_eq!(count(RangeQuery::new_f64(price_field, -0.5..0.5)), 4);
+        assert_eq!(count(RangeQuery::new_f64(price_field, -1.1..-0.9)), 1);
+        assert_eq!(
+            count(RangeQuery::new_f64(
+                price_field,
+                (Bound::Excluded(-5.0), Bound::Included(-4.0))
+            )),
+            4
+        );
+        assert_eq!(count(RangeQuery::new_f64(price_field, 4.5..)), 2);
+        assert_eq!(count(RangeQuery::new_f64(price_field, ..-4.8)), 1);
+    }
+
 }
@@ -0,0 +1,323 @@
+use common::BitSet;
+use core::{Searcher, SegmentReader};
+use error::ErrorKind;
+use fst_regex::{self, Regex};
+use postings::TermInfo;
+use query::{BitSetDocSet, ConstScorer, Query, Scorer, Weight};
+use query::QueryAst;
+use schema::{Field, IndexRecordOption, Schema};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termdict::TermDictionary;
+use Result;
+use Score;
+
+/// Default maximum size of the automaton of a `RegexQuery`, in bytes.
+const DEFAULT_MAX_AUTOMATON_SIZE: usize = 10 * (1 << 20);
+
+/// Default maximum number of terms a `RegexQuery` expands to, per segment.
+const DEFAULT_MAX_EXPANSIONS: usize = 10_000;
+
+/// Number of expanded terms between two checks of the deadline.
+const DEADLINE_CHECK_INTERVAL: usize = 256;
+
+/// `RegexQuery` matches all of the documents containing a term
+/// of a field that matches a regular expression.
+///
+/// The regular expression must match the whole term.
+///
+/// A regular expression such as `.*a.*b.*` may match a large part of
+/// the term dictionary, and building its automaton or enumerating
+/// its terms can be costly. The query is therefore bounded by:
+///
+/// * the size of its automaton (10MB by default). The automaton
+/// is also limited to 10,000 states,
+/// * the number of terms it expands to in a segment (10,000 by default),
+/// * an optional timeout.
+///
+/// Exceeding one of these limits makes the search fail with
+/// `ErrorKind::QueryTooComplex`, rather than silently returning partial results.
+///
+/// As for the `RangeQuery`, matched documents all get a constant `Score` of one.
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use]
+/// # extern crate tantivy;
+/// # use tantivy::Index;
+/// # use tantivy::schema::{SchemaBuilder, TEXT};
+/// # use tantivy::query::{Query, RegexQuery};
+/// # use tantivy::Result;
+/// #
+/// # fn run() -> Result<()> {
+/// #     let mut schema_builder = SchemaBuilder::new();
+/// #     let title = schema_builder.add_text_field("title", TEXT);
+/// #     let index = Index::create_in_ram(schema_builder.build());
+/// #     {
+/// #         let mut index_writer = index.writer_with_num_threads(1, 6_000_000)?;
+/// #         index_writer.add_document(doc!(title => "The Name of the Wind"));
+/// #         index_writer.add_document(doc!(title => "The Diary of Muadib"));
+/// #         index_writer.add_document(doc!(title => "A Dairy Cow"));
+/// #         index_writer.commit()?;
+/// #     }
+/// #     index.load_searchers()?;
+/// let searcher = index.searcher();
+///
+/// let mut query = RegexQuery::new(title, "d[ai]{2}ry");
+/// assert_eq!(query.count(&*searcher)?, 2);
+///
+/// // "diary" and "dairy" are two terms.
+/// query.set_max_expansions(1);
+/// assert!(query.count(&*searcher).is_err());
+/// #     Ok(())
+/// # }
+/// #
+/// # fn main() {
+/// #   run().unwrap()
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RegexQuery {
+    field: Field,
+    pattern: String,
+    max_automaton_size: usize,
+    max_expansions: usize,
+    timeout: Option<Duration>,
+}
+
+impl RegexQuery {
+    /// Creates a query matching the terms of `field` matching `pattern`.
+    ///
+    /// Searching returns an error if `pattern` is not a valid regular expression.
+    pub fn new(field: Field, pattern: &str) -> RegexQuery {
+        RegexQuery {
+            field,
+            pattern: pattern.to_string(),
+            max_automaton_size: DEFAULT_MAX_AUTOMATON_SIZE,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+            timeout: None,
+        }
+    }
+
+    /// Sets the maximum size of the automaton of the regular expression, in bytes.
+    pub fn set_max_automaton_size(&mut self, max_automaton_size: usize) {
+        self.max_automaton_size = max_automaton_size;
+    }
+
+    /// Sets the maximum number of terms the regular expression
+    /// may match within a segment.
+    pub fn set_max_expansions(&mut self, max_expansions: usize) {
+        self.max_expansions = max_expansions;
+    }
+
+    /// Sets the time allowed to expand the regular expression into terms,
+    /// counted from the creation of the `Weight`.
+    ///
+    /// The timeout is only checked every few terms, so it may be slightly exceeded.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+}
+
+impl Query for RegexQuery {
+    fn weight(&self, searcher: &Searcher, _scoring_enabled: bool) -> Result<Box<Weight>> {
+        let automaton = match Regex::with_size_limit(self.max_automaton_size, &self.pattern) {
+            Ok(automaton) => automaton,
+            Err(fst_regex::Error::CompiledTooBig(_)) => {
+                bail!(ErrorKind::QueryTooComplex(format!(
+                    "the automaton of the regular expression {:?} exceeds {} bytes",
+                    self.pattern, self.max_automaton_size
+                )));
+            }
+            Err(fst_regex::Error::TooManyStates(max_states)) => {
+                bail!(ErrorKind::QueryTooComplex(format!(
+                    "the automaton of the regular expression {:?} exceeds {} states",
+                    self.pattern, max_states
+                )));
+            }
+            Err(e) => {
+                bail!(ErrorKind::InvalidArgument(format!(
+                    "Invalid regular expression {:?}: {}",
+                    self.pattern, e
+                )));
+            }
+        };
+        Ok(box RegexWeight {
+            field: self.field,
+            boost: searcher.field_boost(self.field),
+            automaton: Arc::new(automaton),
+            max_expansions: self.max_expansions,
+            deadline: self.timeout.map(|timeout| Instant::now() + timeout),
+        })
+    }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        let timeout_ms = self.timeout.map(|timeout| {
+            timeout.as_secs() * 1_000 + u64::from(timeout.subsec_nanos() / 1_000_000)
+        });
+        Some(QueryAst::Regex {
+            field: schema.fields().get(self.field.0 as usize)?.name().to_string(),
+            pattern: self.pattern.clone(),
+            max_automaton_size: Some(self.max_automaton_size),
+            max_expansions: Some(self.max_expansions),
+            timeout_ms,
+        })
+    }
+}
+
+pub struct RegexWeight {
+    field: Field,
+    boost: Score,
+    automaton: Arc<Regex>,
+    max_expansions: usize,
+    deadline: Option<Instant>,
+}
+
+impl RegexWeight {
+    /// Calls `visit` with the `TermInfo` of each term of the segment
+    /// matching the regular expression.
+    ///
+    /// Fails if the limits of the query are exceeded.
+    fn expand<F: FnMut(&TermInfo)>(&self, reader: &SegmentReader, mut visit: F) -> Result<()> {
+        let term_dict = reader.inverted_index(self.field).terms();
+        let mut num_terms = 0;
+        let mut timed_out = false;
+        term_dict.search(&*self.automaton, |term_ord| {
+            num_terms += 1;
+            if num_terms > self.max_expansions {
+                return false;
+            }
+            if let Some(deadline) = self.deadline {
+                if num_terms % DEADLINE_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                    timed_out = true;
+                    return false;
+                }
+            }
+            visit(&term_dict.term_info_from_ord(term_ord));
+            true
+        });
+        if timed_out {
+            bail!(ErrorKind::QueryTooComplex(
+                "the expansion of the regular expression timed out".to_string()
+            ));
+        }
+        if num_terms > self.max_expansions {
+            bail!(ErrorKind::QueryTooComplex(format!(
+                "the regular expression matches more than {} terms",
+                self.max_expansions
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Weight for RegexWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let mut doc_bitset = BitSet::with_max_value(reader.max_doc());
+        let inverted_index = reader.inverted_index(self.field);
+        self.expand(reader, |term_info| {
+            let mut block_segment_postings = inverted_index
+                .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic);
+            while block_segment_postings.advance() {
+                for &doc in block_segment_postings.docs() {
+                    doc_bitset.insert(doc);
+                }
+            }
+        })?;
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(box ConstScorer::with_score(doc_bitset, self.boost))
+    }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        let mut doc_freq_sum = 0u32;
+        self.expand(reader, |term_info| {
+            doc_freq_sum = doc_freq_sum.saturating_add(term_info.doc_freq);
+        })?;
+        Ok(doc_freq_sum.min(reader.max_doc()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::RegexQuery;
+    use query::Query;
+    use schema::{SchemaBuilder, STRING};
+    use std::time::Duration;
+    use Index;
+
+    #[test]
+    fn test_regex_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let country = schema_builder.add_text_field("country", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for country_name in &["japan", "japon", "korea", "jamaica", "kenya", "jordan"] {
+                index_writer.add_document(doc!(country => *country_name));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let count = |pattern: &str| RegexQuery::new(country, pattern).count(&*searcher).unwrap();
+        assert_eq!(count("jap[ao]n"), 2);
+        assert_eq!(count("j.*"), 4);
+        assert_eq!(count("k.*a"), 2);
+        // the whole term must match.
+        assert_eq!(count("jap"), 0);
+
+        let error_kind = |query: &RegexQuery| match query.count(&*searcher) {
+            Err(e) => e.kind().to_string(),
+            Ok(_) => panic!("The query {:?} should fail", query),
+        };
+        assert!(error_kind(&RegexQuery::new(country, "jap(an")).starts_with("an invalid argument"));
+        {
+            let mut query = RegexQuery::new(country, "j.*");
+            query.set_max_expansions(3);
+            assert!(error_kind(&query).starts_with("the query is too complex"));
+            query.set_max_expansions(4);
+            assert_eq!(query.count(&*searcher).unwrap(), 4);
+        }
+        {
+            let mut query = RegexQuery::new(country, "[a-z]{10,100}");
+            query.set_max_automaton_size(1_000);
+            assert!(error_kind(&query).starts_with("the query is too complex"));
+        }
+        {
+            // the determinization of the automaton requires 2^20 states.
+            let query = RegexQuery::new(country, "[ab]*a[ab]{20}");
+            assert!(error_kind(&query).contains("states"));
+        }
+        {
+            let mut query = RegexQuery::new(country, ".*a.*");
+            query.set_timeout(Some(Duration::from_secs(60)));
+            assert_eq!(query.count(&*searcher).unwrap(), 6);
+        }
+    }
+
+    #[test]
+    fn test_regex_query_timeout() {
+        let mut schema_builder = SchemaBuilder::default();
+        let id = schema_builder.add_text_field("id", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..1_000 {
+                index_writer.add_document(doc!(id => format!("id{}", i)));
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut query = RegexQuery::new(id, "id.*");
+        assert_eq!(query.count(&*searcher).unwrap(), 1_000);
+        // the deadline is already reached when the terms get expanded.
+        query.set_timeout(Some(Duration::from_secs(0)));
+        match query.count(&*searcher) {
+            Err(e) => assert!(e.kind().to_string().contains("timed out")),
+            Ok(_) => panic!("The expansion of the regular expression should time out"),
+        }
+    }
+}
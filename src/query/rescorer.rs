@@ -0,0 +1,116 @@
+use DocId;
+use Result;
+use Score;
+use core::SegmentReader;
+use docset::SkipResult;
+use query::{create_weight, Query, Weight};
+use Searcher;
+
+/// Function computing the rescore of a document, typically from a fast field.
+pub type RescoreFunction = Box<Fn(&SegmentReader, DocId) -> Result<Score> + Send + Sync>;
+
+enum RescoreSource {
+    Query(Box<Query>),
+    Function(RescoreFunction),
+}
+
+/// Second scoring stage, applied to the top candidates of a search.
+///
+/// The base query is used to cheaply select the top-N candidates.
+/// The rescorer then computes a second score for each of these candidates only,
+/// either with an expensive query, or with a function of the document,
+/// and combines both scores as
+/// `query_weight * score + rescore_query_weight * rescore`.
+///
+/// Candidates not matching the rescore query keep their weighted base score.
+/// See `Searcher::rescore`.
+pub struct Rescorer {
+    source: RescoreSource,
+    query_weight: Score,
+    rescore_query_weight: Score,
+}
+
+impl Rescorer {
+    /// Creates a rescorer scoring the candidates with a query.
+    pub fn with_query(query: Box<Query>) -> Rescorer {
+        Rescorer::new(RescoreSource::Query(query))
+    }
+
+    /// Creates a rescorer scoring the candidates with a function.
+    pub fn with_function(function: RescoreFunction) -> Rescorer {
+        Rescorer::new(RescoreSource::Function(function))
+    }
+
+    fn new(source: RescoreSource) -> Rescorer {
+        Rescorer {
+            source,
+            query_weight: 1f32,
+            rescore_query_weight: 1f32,
+        }
+    }
+
+    /// Sets the weights of the original score and of the rescore
+    /// in the final score. Both default to `1`.
+    pub fn set_weights(&mut self, query_weight: Score, rescore_query_weight: Score) {
+        self.query_weight = query_weight;
+        self.rescore_query_weight = rescore_query_weight;
+    }
+
+    /// Rescores the candidates of a given segment.
+    ///
+    /// `candidates` must be sorted by increasing `DocId`.
+    /// Returns the rescores of the candidates, or `None` for
+    /// the candidates that do not match the rescore query.
+    pub(crate) fn rescore_segment(
+        &self,
+        weight: Option<&Weight>,
+        segment_reader: &SegmentReader,
+        candidates: &[DocId],
+    ) -> Result<Vec<Option<Score>>> {
+        match self.source {
+            RescoreSource::Function(ref function) => candidates
+                .iter()
+                .map(|&doc| function(segment_reader, doc).map(Some))
+                .collect(),
+            RescoreSource::Query(_) => {
+                let weight = weight.expect("The weight of the rescore query is required");
+                let mut scorer = weight.alive_scorer(segment_reader)?;
+                let mut rescores = Vec::with_capacity(candidates.len());
+                let mut started = false;
+                let mut exhausted = false;
+                for &doc in candidates {
+                    if !exhausted && (!started || scorer.doc() < doc) {
+                        started = true;
+                        exhausted = scorer.skip_next(doc) == SkipResult::End;
+                    }
+                    // the scorer may also have overstepped
+                    // the previous candidate onto this one.
+                    if !exhausted && scorer.doc() == doc {
+                        rescores.push(Some(scorer.score()));
+                    } else {
+                        rescores.push(None);
+                    }
+                }
+                Ok(rescores)
+            }
+        }
+    }
+
+    /// Returns the weight of the rescore query, if the
+    /// rescorer relies on a query.
+    pub(crate) fn weight(&self, searcher: &Searcher) -> Result<Option<Box<Weight>>> {
+        match self.source {
+            RescoreSource::Query(ref query) => Ok(Some(create_weight(&**query, searcher, true)?)),
+            RescoreSource::Function(_) => Ok(None),
+        }
+    }
+
+    /// Combines the original score and the rescore of a candidate.
+    pub(crate) fn combine(&self, score: Score, rescore: Option<Score>) -> Score {
+        let score = self.query_weight * score;
+        match rescore {
+            Some(rescore) => score + self.rescore_query_weight * rescore,
+            None => score,
+        }
+    }
+}
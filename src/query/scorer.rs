@@ -1,11 +1,36 @@
 use DocId;
 use Score;
-use collector::Collector;
+use collector::{Collector, MatchInfo};
 use docset::{DocSet, SkipResult};
 use common::BitSet;
-use std::ops::DerefMut;
+use std::ops::{Deref, DerefMut};
+use std::time::Instant;
 use downcast;
 
+/// Number of documents collected between two checks of the deadline
+/// in `Scorer::collect_until`.
+const DEADLINE_CHECK_INTERVAL: u32 = 1_024;
+
+/// Pushes the current document of the scorer to the collector.
+#[inline]
+fn collect_doc<TScorer: Scorer + ?Sized>(
+    scorer: &mut TScorer,
+    collector: &mut Collector,
+    requires_match_info: bool,
+) {
+    let doc = scorer.doc();
+    let score = scorer.score();
+    if requires_match_info {
+        let match_info = MatchInfo {
+            term_freq: scorer.term_freq(),
+            positions: scorer.positions(),
+        };
+        collector.collect_with_match(doc, score, &match_info);
+    } else {
+        collector.collect(doc, score);
+    }
+}
+
 /// Scored set of documents matching a query within a specific segment.
 ///
 /// See [`Query`](./trait.Query.html).
@@ -15,12 +40,50 @@ pub trait Scorer: downcast::Any + DocSet + 'static {
     /// This method will perform a bit of computation and is not cached.
     fn score(&mut self) -> Score;
 
+    /// Returns the term frequency of the current document,
+    /// for scorers matching a single term.
+    ///
+    /// Returns `None` if the scorer does not expose it, or
+    /// if the postings were read without the term frequencies.
+    fn term_freq(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns the positions matched in the current document.
+    ///
+    /// The positions are only available if the postings were read
+    /// with positions. Scorers that do not expose them return an empty slice.
+    fn positions(&self) -> &[u32] {
+        &[]
+    }
+
     /// Consumes the complete `DocSet` and
     /// push the scored documents to the collector.
     fn collect(&mut self, collector: &mut Collector) {
+        let requires_match_info = collector.requires_match_info();
+        while self.advance() {
+            collect_doc(self, collector, requires_match_info);
+        }
+    }
+
+    /// Pushes the scored documents to the collector until
+    /// the `DocSet` is consumed or `deadline` is reached.
+    ///
+    /// The deadline is only checked every few documents, so it may be
+    /// slightly exceeded.
+    /// Returns `false` iff the deadline was reached before
+    /// all of the documents were collected.
+    fn collect_until(&mut self, collector: &mut Collector, deadline: Instant) -> bool {
+        let requires_match_info = collector.requires_match_info();
+        let mut num_collected = 0u32;
         while self.advance() {
-            collector.collect(self.doc(), self.score());
+            collect_doc(self, collector, requires_match_info);
+            num_collected += 1;
+            if num_collected % DEADLINE_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                return false;
+            }
         }
+        true
     }
 }
 
@@ -34,10 +97,23 @@ impl Scorer for Box<Scorer> {
         self.deref_mut().score()
     }
 
+    fn term_freq(&self) -> Option<u32> {
+        self.deref().term_freq()
+    }
+
+    fn positions(&self) -> &[u32] {
+        self.deref().positions()
+    }
+
     fn collect(&mut self, collector: &mut Collector) {
         let scorer = self.deref_mut();
         scorer.collect(collector);
     }
+
+    fn collect_until(&mut self, collector: &mut Collector, deadline: Instant) -> bool {
+        let scorer = self.deref_mut();
+        scorer.collect_until(collector, deadline)
+    }
 }
 
 /// `EmptyScorer` is a dummy `Scorer` in which no document matches.
@@ -70,8 +146,9 @@ impl Scorer for EmptyScorer {
 /// The `ConstScorer` is useful if you have a `DocSet` where
 /// you needed a scorer.
 ///
-/// The `ConstScorer`'s constant score can be set
-/// by calling `.set_score(...)`.
+/// The `ConstScorer`'s constant score defaults to `1f32`. It can be given
+/// at construction with `ConstScorer::with_score(...)`, or set
+/// by calling `.set_score(...)`, e.g. to propagate the boost of a query.
 pub struct ConstScorer<TDocSet: DocSet> {
     docset: TDocSet,
     score: Score,
@@ -80,10 +157,13 @@ pub struct ConstScorer<TDocSet: DocSet> {
 impl<TDocSet: DocSet> ConstScorer<TDocSet> {
     /// Creates a new `ConstScorer`.
     pub fn new(docset: TDocSet) -> ConstScorer<TDocSet> {
-        ConstScorer {
-            docset,
-            score: 1f32,
-        }
+        ConstScorer::with_score(docset, 1f32)
+    }
+
+    /// Creates a new `ConstScorer` returning `score` for all of
+    /// the documents.
+    pub fn with_score(docset: TDocSet, score: Score) -> ConstScorer<TDocSet> {
+        ConstScorer { docset, score }
     }
 
     /// Sets the constant score to a different value.
@@ -120,14 +200,30 @@ impl<TDocSet: DocSet> DocSet for ConstScorer<TDocSet> {
 
 impl<TDocSet: DocSet + 'static> Scorer for ConstScorer<TDocSet> {
     fn score(&mut self) -> Score {
-        1f32
+        self.score
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::EmptyScorer;
+    use super::{ConstScorer, EmptyScorer, Scorer};
     use DocSet;
+    use query::VecDocSet;
+
+    #[test]
+    fn test_const_scorer() {
+        let mut const_scorer = ConstScorer::new(VecDocSet::from(vec![1u32, 3u32]));
+        assert!(const_scorer.advance());
+        assert_eq!(const_scorer.score(), 1f32);
+        const_scorer.set_score(0.5f32);
+        assert_eq!(const_scorer.score(), 0.5f32);
+        let mut const_scorer = ConstScorer::with_score(VecDocSet::from(vec![1u32, 3u32]), 2f32);
+        assert!(const_scorer.advance());
+        assert_eq!(const_scorer.doc(), 1u32);
+        assert_eq!(const_scorer.score(), 2f32);
+        assert!(const_scorer.advance());
+        assert_eq!(const_scorer.score(), 2f32);
+    }
 
     #[test]
     fn test_empty_scorer() {
@@ -0,0 +1,130 @@
+use Score;
+use Searcher;
+use Term;
+use schema::BM25Params;
+use super::TermSimilarity;
+use query::Explanation;
+
+/// Inverse document frequency, as defined by BM25.
+fn idf(doc_freq: u32, num_docs: u32) -> Score {
+    let doc_freq = doc_freq as Score;
+    let num_docs = num_docs as Score;
+    (1.0 + (num_docs - doc_freq + 0.5) / (doc_freq + 0.5)).ln()
+}
+
+/// BM25 similarity of a term, computed from the statistics
+/// of the whole searcher.
+///
+/// The score of a document is
+/// `weight * tf * (k1 + 1) / (tf + k1 * (1 - b + b * fieldnorm / average_fieldnorm))`
+/// in which `weight` is the idf of the term.
+#[derive(Clone, Debug)]
+pub struct BM25Weight {
+    weight: Score,
+    k1: Score,
+    b: Score,
+    average_fieldnorm: Score,
+}
+
+impl BM25Weight {
+    pub(crate) fn new(
+        weight: Score,
+        bm25_params: BM25Params,
+        average_fieldnorm: Score,
+    ) -> BM25Weight {
+        BM25Weight {
+            weight,
+            k1: bm25_params.k1(),
+            b: bm25_params.b(),
+            average_fieldnorm,
+        }
+    }
+
+    pub(crate) fn for_term(searcher: &Searcher, term: &Term) -> BM25Weight {
+        let field = term.field();
+        let weight = idf(searcher.doc_freq(term), searcher.num_docs());
+        BM25Weight::new(
+            weight,
+            searcher.bm25_params(field),
+            searcher.average_fieldnorm(field),
+        )
+    }
+}
+
+impl BM25Weight {
+    /// Term frequency component of the score, the score being
+    /// the product of the weight and of this component.
+    ///
+    /// If the field norm is not available, the field is assumed
+    /// to be of average length.
+    fn tf_norm(&self, fieldnorm_opt: Option<u64>, term_freq: u32) -> Score {
+        let length_ratio = match fieldnorm_opt {
+            Some(fieldnorm) if self.average_fieldnorm > 0f32 => {
+                fieldnorm as Score / self.average_fieldnorm
+            }
+            _ => 1f32,
+        };
+        let term_freq = term_freq as Score;
+        let norm = self.k1 * (1f32 - self.b + self.b * length_ratio);
+        term_freq * (self.k1 + 1f32) / (term_freq + norm)
+    }
+}
+
+impl TermSimilarity for BM25Weight {
+    fn score(&self, fieldnorm_opt: Option<u64>, term_freq: u32) -> Score {
+        self.weight * self.tf_norm(fieldnorm_opt, term_freq)
+    }
+
+    fn explain(&self, fieldnorm_opt: Option<u64>, term_freq: u32) -> Explanation {
+        let tf_norm = self.tf_norm(fieldnorm_opt, term_freq);
+        let mut explanation =
+            Explanation::new("bm25, product of idf and tf norm", self.weight * tf_norm);
+        explanation.add_const("idf", self.weight);
+        let mut tf_explanation = Explanation::new(
+            "tf norm, tf * (k1 + 1) / (tf + k1 * (1 - b + b * fieldnorm / avgfieldnorm))",
+            tf_norm,
+        );
+        tf_explanation.add_const("tf", term_freq as Score);
+        tf_explanation.add_const("k1", self.k1);
+        tf_explanation.add_const("b", self.b);
+        match fieldnorm_opt {
+            Some(fieldnorm) => tf_explanation.add_const("fieldnorm", fieldnorm as Score),
+            None => tf_explanation.add_const("fieldnorm (unavailable)", self.average_fieldnorm),
+        }
+        tf_explanation.add_const("avgfieldnorm", self.average_fieldnorm);
+        explanation.add_detail(tf_explanation);
+        explanation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{idf, BM25Weight};
+    use query::term_query::TermSimilarity;
+    use schema::BM25Params;
+
+    fn abs_diff(left: f32, right: f32) -> f32 {
+        (right - left).abs()
+    }
+
+    #[test]
+    fn test_idf() {
+        assert!(abs_diff(idf(1, 1), 0.2876821) < 0.0001f32);
+        assert!(idf(1, 100) > idf(10, 100));
+    }
+
+    #[test]
+    fn test_bm25_score() {
+        let bm25_weight = BM25Weight::new(2f32, BM25Params::default(), 4f32);
+        // average length
+        assert!(abs_diff(bm25_weight.score(Some(4), 1), 2f32) < 0.0001f32);
+        assert!(abs_diff(bm25_weight.score(None, 1), 2f32) < 0.0001f32);
+        // shorter fields score higher
+        assert!(bm25_weight.score(Some(2), 1) > bm25_weight.score(Some(8), 1));
+        // the term frequency saturates
+        assert!(bm25_weight.score(Some(4), 100) < 2f32 * 2.2f32);
+        let no_length_norm = BM25Weight::new(2f32, BM25Params::new(1.2, 0f32).unwrap(), 4f32);
+        assert_eq!(no_length_norm.score(Some(2), 1), no_length_norm.score(Some(8), 1));
+    }
+}
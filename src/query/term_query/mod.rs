@@ -1,7 +1,11 @@
+mod bm25;
+mod similarity;
 mod term_query;
 mod term_weight;
 mod term_scorer;
 
+pub use self::bm25::BM25Weight;
+pub use self::similarity::{BM25Similarity, ConstantSimilarity, Similarity, TermSimilarity};
 pub use self::term_query::TermQuery;
 pub use self::term_weight::TermWeight;
 pub use self::term_scorer::TermScorer;
@@ -10,9 +14,10 @@ pub use self::term_scorer::TermScorer;
 mod tests {
 
     use docset::DocSet;
-    use postings::SegmentPostings;
+    use postings::{Postings, SegmentPostings};
     use query::{Query, Scorer};
-    use query::term_query::TermScorer;
+    use query::term_query::{BM25Weight, TermScorer};
+    use std::sync::Arc;
     use query::TermQuery;
     use Index;
     use schema::*;
@@ -50,7 +55,75 @@ mod tests {
         let mut term_scorer = term_weight.scorer(segment_reader).unwrap();
         assert!(term_scorer.advance());
         assert_eq!(term_scorer.doc(), 0);
-        assert_eq!(term_scorer.score(), 0.30685282);
+        assert!(abs_diff(term_scorer.score(), 0.28768207) < 0.001f32);
+    }
+
+    #[test]
+    pub fn test_term_query_index_record_option() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b a a"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let mut term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let segment_reader = searcher.segment_reader(0);
+        {
+            let term_weight = term_query.specialized_weight(&searcher, true);
+            let mut term_scorer = term_weight.specialized_scorer(segment_reader).unwrap();
+            assert!(term_scorer.advance());
+            assert_eq!(term_scorer.postings().term_freq(), 3);
+        }
+        term_query.set_index_record_option(IndexRecordOption::Basic);
+        assert_eq!(term_query.index_record_option(), IndexRecordOption::Basic);
+        {
+            let term_weight = term_query.specialized_weight(&searcher, true);
+            let mut term_scorer = term_weight.specialized_scorer(segment_reader).unwrap();
+            assert!(term_scorer.advance());
+            assert_eq!(term_scorer.doc(), 0);
+            assert_eq!(term_scorer.postings().term_freq(), 1);
+        }
+    }
+
+    #[test]
+    pub fn test_term_query_explain() {
+        use DocAddress;
+        let mut schema_builder = SchemaBuilder::default();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text_field => "a b a"));
+            index_writer.add_document(doc!(text_field => "b c"));
+            assert!(index_writer.commit().is_ok());
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = TermQuery::new(
+            Term::from_field_text(text_field, "a"),
+            IndexRecordOption::WithFreqs,
+        );
+        let term_weight = term_query.specialized_weight(&searcher, true);
+        let mut term_scorer = term_weight
+            .specialized_scorer(searcher.segment_reader(0))
+            .unwrap();
+        assert!(term_scorer.advance());
+        let explanation = term_query.explain(&searcher, DocAddress(0, 0)).unwrap();
+        assert!(abs_diff(explanation.value(), term_scorer.score()) < 0.0001f32);
+        assert_eq!(explanation.description(), "weight(text:\"a\"), product of");
+        assert_eq!(explanation.details()[0].description(), "boost");
+        let bm25_explanation = &explanation.details()[1];
+        assert_eq!(bm25_explanation.details()[0].description(), "idf");
+        let tf_explanation = &bm25_explanation.details()[1];
+        assert_eq!(tf_explanation.details()[0].value(), 2f32);
+        assert!(term_query.explain(&searcher, DocAddress(0, 1)).is_err());
     }
 
     #[test]
@@ -60,12 +133,13 @@ mod tests {
         assert_eq!(left_fieldnorms.get(1), 4);
         let left = SegmentPostings::create_from_docs(&[1]);
         let mut left_scorer = TermScorer {
-            idf: 0.30685282,
+            similarity: Arc::new(BM25Weight::new(0.30685282, BM25Params::default(), 8f32)),
+            boost: 1f32,
             fieldnorm_reader_opt: Some(left_fieldnorms),
             postings: left,
         };
         left_scorer.advance();
-        assert!(abs_diff(left_scorer.score(), 0.15342641) < 0.001f32);
+        assert!(abs_diff(left_scorer.score(), 0.38575783) < 0.001f32);
     }
 
 }
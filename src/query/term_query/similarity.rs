@@ -0,0 +1,85 @@
+use Score;
+use Searcher;
+use Term;
+use std::sync::Arc;
+use super::bm25::BM25Weight;
+use query::Explanation;
+
+/// Scoring function of the matches of a term,
+/// as returned by a `Similarity`.
+pub trait TermSimilarity: Send + Sync {
+    /// Returns the score of a document, given the number of tokens
+    /// of its field if available, and the term frequency.
+    ///
+    /// The term frequency is `1` if the frequencies are not decoded.
+    fn score(&self, fieldnorm_opt: Option<u64>, term_freq: u32) -> Score;
+
+    /// Returns the explanation of `.score(...)`.
+    ///
+    /// The default implementation only details the term frequency
+    /// and the field norm.
+    fn explain(&self, fieldnorm_opt: Option<u64>, term_freq: u32) -> Explanation {
+        let mut explanation = Explanation::new("similarity", self.score(fieldnorm_opt, term_freq));
+        explanation.add_const("tf", term_freq as Score);
+        if let Some(fieldnorm) = fieldnorm_opt {
+            explanation.add_const("fieldnorm", fieldnorm as Score);
+        }
+        explanation
+    }
+}
+
+/// Defines how the matches of a `TermQuery` are scored.
+///
+/// Similarities are registered per field with `Index::set_similarity`,
+/// and default to `BM25Similarity`. This makes it possible to
+/// experiment with other models, such as DFR or LM Dirichlet,
+/// by combining the statistics of the searcher (`doc_freq`,
+/// `average_fieldnorm`, ...) with the term frequency and the
+/// field norm of each document.
+///
+/// The boost of the field is applied on top of the score.
+pub trait Similarity: Send + Sync {
+    /// Returns the scoring function of the matches of `term`.
+    ///
+    /// It is called once per query, so that the statistics of
+    /// the searcher are computed once.
+    fn term_similarity(&self, searcher: &Searcher, term: &Term) -> Arc<TermSimilarity>;
+}
+
+/// BM25 similarity, parameterized by the `BM25Params`
+/// of the field. See `Searcher::bm25_params`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BM25Similarity;
+
+impl Similarity for BM25Similarity {
+    fn term_similarity(&self, searcher: &Searcher, term: &Term) -> Arc<TermSimilarity> {
+        Arc::new(BM25Weight::for_term(searcher, term))
+    }
+}
+
+/// Similarity scoring all of the matches with a constant score,
+/// regardless of the term frequency and of the field norm.
+#[derive(Clone, Copy, Debug)]
+pub struct ConstantSimilarity(pub Score);
+
+impl Default for ConstantSimilarity {
+    fn default() -> ConstantSimilarity {
+        ConstantSimilarity(1f32)
+    }
+}
+
+impl TermSimilarity for ConstantSimilarity {
+    fn score(&self, _fieldnorm_opt: Option<u64>, _term_freq: u32) -> Score {
+        self.0
+    }
+
+    fn explain(&self, _fieldnorm_opt: Option<u64>, _term_freq: u32) -> Explanation {
+        Explanation::new("constant similarity", self.0)
+    }
+}
+
+impl Similarity for ConstantSimilarity {
+    fn term_similarity(&self, _searcher: &Searcher, _term: &Term) -> Arc<TermSimilarity> {
+        Arc::new(*self)
+    }
+}
@@ -1,20 +1,29 @@
 use Term;
 use Result;
 use super::term_weight::TermWeight;
-use query::Query;
+use query::{ParallelQuery, Query};
 use query::Weight;
 use schema::IndexRecordOption;
 use Searcher;
+use schema::Schema;
+use query::query_parser::term_to_query_string;
+use query::{QueryAst, TermAst};
+use std::collections::BTreeSet;
 
 /// A Term query matches all of the documents
 /// containing a specific term.
 ///
-/// The score associated is defined as
-/// `idf` *  sqrt(`term_freq` / `field norm`)
+/// The score associated is defined by the `Similarity` of the field,
+/// multiplied by the boost of the field. See `Searcher::similarity`.
+///
+/// The default `BM25Similarity` scores the matches as
+/// `idf * term_freq * (k1 + 1) / (term_freq + k1 * (1 - b + b * field_norm / avg_field_norm))`
 /// in which :
-/// * `idf`        - inverse document frequency.
-/// * `term_freq`  - number of occurrences of the term in the field
-/// * `field norm` - number of tokens in the field.
+/// * `idf`            - inverse document frequency.
+/// * `term_freq`      - number of occurrences of the term in the field
+/// * `field_norm`     - number of tokens in the field.
+/// * `avg_field_norm` - average number of tokens in the field.
+/// * `k1`, `b`        - the `BM25Params` of the field. See `Searcher::bm25_params`.
 #[derive(Debug)]
 pub struct TermQuery {
     term: Term,
@@ -30,6 +39,22 @@ impl TermQuery {
         }
     }
 
+    /// Returns the information decoded from the postings at search time.
+    pub fn index_record_option(&self) -> IndexRecordOption {
+        self.index_record_option
+    }
+
+    /// Sets the information decoded from the postings at search time.
+    ///
+    /// For instance, a term query used as a pure filter on a field
+    /// indexed with frequencies can use `IndexRecordOption::Basic`
+    /// to skip the decoding of the term frequency blocks entirely.
+    /// Options richer than the ones the field was indexed with
+    /// are ignored.
+    pub fn set_index_record_option(&mut self, index_record_option: IndexRecordOption) {
+        self.index_record_option = index_record_option;
+    }
+
     /// Returns a weight object.
     ///
     /// While `.weight(...)` returns a boxed trait object,
@@ -41,9 +66,12 @@ impl TermQuery {
         } else {
             IndexRecordOption::Basic
         };
+        let field = self.term.field();
         TermWeight {
-            num_docs: searcher.num_docs(),
-            doc_freq: searcher.doc_freq(&self.term),
+            similarity: searcher
+                .similarity(field)
+                .term_similarity(searcher, &self.term),
+            boost: searcher.field_boost(field),
             term: self.term.clone(),
             index_record_option,
         }
@@ -54,4 +82,29 @@ impl Query for TermQuery {
     fn weight(&self, searcher: &Searcher, scoring_enabled: bool) -> Result<Box<Weight>> {
         Ok(box self.specialized_weight(searcher, scoring_enabled))
     }
+
+    fn to_query_string(&self, schema: &Schema) -> Option<String> {
+        term_to_query_string(&self.term, schema)
+    }
+
+    fn query_terms(&self, term_set: &mut BTreeSet<Term>) {
+        term_set.insert(self.term.clone());
+    }
+
+    fn query_ast(&self, schema: &Schema) -> Option<QueryAst> {
+        Some(QueryAst::Term {
+            term: TermAst::from_term(&self.term, schema)?,
+            index_record_option: self.index_record_option,
+        })
+    }
+}
+
+impl ParallelQuery for TermQuery {
+    fn parallel_weight(
+        &self,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> Result<Box<Weight + Send + Sync>> {
+        Ok(box self.specialized_weight(searcher, scoring_enabled))
+    }
 }
@@ -5,9 +5,12 @@ use postings::SegmentPostings;
 use query::Scorer;
 use postings::Postings;
 use fastfield::FastFieldReader;
+use super::TermSimilarity;
+use std::sync::Arc;
 
 pub struct TermScorer {
-    pub idf: Score,
+    pub similarity: Arc<TermSimilarity>,
+    pub boost: Score,
     pub fieldnorm_reader_opt: Option<FastFieldReader<u64>>,
     pub postings: SegmentPostings,
 }
@@ -39,13 +42,21 @@ impl DocSet for TermScorer {
 impl Scorer for TermScorer {
     fn score(&mut self) -> Score {
         let doc = self.postings.doc();
-        let tf = match self.fieldnorm_reader_opt {
-            Some(ref fieldnorm_reader) => {
-                let field_norm = fieldnorm_reader.get(doc);
-                (self.postings.term_freq() as f32 / field_norm as f32)
-            }
-            None => self.postings.term_freq() as f32,
-        };
-        self.idf * tf.sqrt()
+        let fieldnorm_opt = self.fieldnorm_reader_opt
+            .as_ref()
+            .map(|fieldnorm_reader| fieldnorm_reader.get(doc));
+        self.boost * self.similarity.score(fieldnorm_opt, self.postings.term_freq())
+    }
+
+    fn term_freq(&self) -> Option<u32> {
+        if self.postings.has_freqs() {
+            Some(self.postings.term_freq())
+        } else {
+            None
+        }
+    }
+
+    fn positions(&self) -> &[u32] {
+        self.postings.positions()
     }
 }
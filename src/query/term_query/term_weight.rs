@@ -2,15 +2,23 @@ use Term;
 use query::Weight;
 use core::SegmentReader;
 use query::Scorer;
-use docset::DocSet;
+use query::Explanation;
+use query::weight::does_not_match;
+use query::query_parser::term_to_query_string;
+use docset::{DocSet, SkipResult};
+use postings::Postings;
 use postings::SegmentPostings;
 use schema::IndexRecordOption;
 use super::term_scorer::TermScorer;
+use super::TermSimilarity;
+use std::sync::Arc;
+use Score;
 use Result;
+use DocId;
 
 pub struct TermWeight {
-    pub(crate) num_docs: u32,
-    pub(crate) doc_freq: u32,
+    pub(crate) similarity: Arc<TermSimilarity>,
+    pub(crate) boost: Score,
     pub(crate) term: Term,
     pub(crate) index_record_option: IndexRecordOption,
 }
@@ -21,14 +29,40 @@ impl Weight for TermWeight {
         Ok(box specialized_scorer)
     }
 
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        let field = self.term.field();
+        Ok(reader
+            .inverted_index(field)
+            .get_term_info(&self.term)
+            .map(|term_info| term_info.doc_freq)
+            .unwrap_or(0))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        let mut scorer = self.specialized_scorer(reader)?;
+        if scorer.skip_next(doc) != SkipResult::Reached {
+            bail!(does_not_match(doc));
+        }
+        let fieldnorm_opt = scorer
+            .fieldnorm_reader_opt
+            .as_ref()
+            .map(|fieldnorm_reader| fieldnorm_reader.get(doc));
+        let similarity_explanation = self.similarity
+            .explain(fieldnorm_opt, scorer.postings().term_freq());
+        let term_str = term_to_query_string(&self.term, reader.schema())
+            .unwrap_or_else(|| format!("{:?}", self.term));
+        let mut explanation = Explanation::new(
+            format!("weight({}), product of", term_str),
+            self.boost * similarity_explanation.value(),
+        );
+        explanation.add_const("boost", self.boost);
+        explanation.add_detail(similarity_explanation);
+        Ok(explanation)
+    }
+
     fn count(&self, reader: &SegmentReader) -> Result<u32> {
         if reader.num_deleted_docs() == 0 {
-            let field = self.term.field();
-            Ok(reader
-                .inverted_index(field)
-                .get_term_info(&self.term)
-                .map(|term_info| term_info.doc_freq)
-                .unwrap_or(0))
+            self.count_estimate(reader)
         } else {
             Ok(self.specialized_scorer(reader)?.count())
         }
@@ -36,26 +70,35 @@ impl Weight for TermWeight {
 }
 
 impl TermWeight {
-    fn idf(&self) -> f32 {
-        1.0 + (self.num_docs as f32 / (self.doc_freq as f32 + 1.0)).ln()
-    }
-
     /// If the field is not found, returns an empty `DocSet`.
     pub fn specialized_scorer(&self, reader: &SegmentReader) -> Result<TermScorer> {
         let field = self.term.field();
-        let inverted_index = reader.inverted_index(field);
+        // the term dictionary is not even opened if
+        // the term is out of the bounds of the field.
+        let out_of_bounds = reader
+            .segment_meta()
+            .field_bounds(field)
+            .map(|field_bounds| !field_bounds.may_contain_term(self.term.value_bytes()))
+            .unwrap_or(false);
+        let postings_opt: Option<SegmentPostings> = if out_of_bounds {
+            None
+        } else {
+            reader
+                .inverted_index(field)
+                .read_postings(&self.term, self.index_record_option)
+        };
         let fieldnorm_reader_opt = reader.get_fieldnorms_reader(field);
-        let postings_opt: Option<SegmentPostings> =
-            inverted_index.read_postings(&self.term, self.index_record_option);
         if let Some(segment_postings) = postings_opt {
             Ok(TermScorer {
-                idf: self.idf(),
+                similarity: Arc::clone(&self.similarity),
+                boost: self.boost,
                 fieldnorm_reader_opt,
                 postings: segment_postings,
             })
         } else {
             Ok(TermScorer {
-                idf: 1f32,
+                similarity: Arc::clone(&self.similarity),
+                boost: self.boost,
                 fieldnorm_reader_opt: None,
                 postings: SegmentPostings::empty(),
             })
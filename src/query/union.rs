@@ -9,7 +9,12 @@ use query::score_combiner::{DoNothingCombiner, ScoreCombiner};
 const HORIZON_NUM_TINYBITSETS: usize = 64;
 const HORIZON: u32 = 64u32 * HORIZON_NUM_TINYBITSETS as u32;
 
-/// Creates a `DocSet` that iterator through the intersection of two `DocSet`s.
+/// Creates a `DocSet` that iterates through the union of several `DocSet`s.
+///
+/// The documents are emitted in strictly increasing doc id order, each
+/// document being emitted exactly once, whatever the order of the `DocSet`s.
+/// The scores of a document are combined in the order in which the `DocSet`s
+/// were given, so that the resulting score is deterministic.
 pub struct Union<TScorer, TScoreCombiner = DoNothingCombiner> {
     docsets: Vec<TScorer>,
     bitsets: Box<[TinySet; HORIZON_NUM_TINYBITSETS]>,
@@ -224,7 +229,11 @@ impl<TScorer, TScoreCombiner> DocSet for Union<TScorer, TScoreCombiner>
     }
 
     fn size_hint(&self) -> u32 {
-        0u32
+        self.docsets
+            .iter()
+            .map(|docset| docset.size_hint())
+            .max()
+            .unwrap_or(0u32)
     }
 }
 
@@ -251,7 +260,8 @@ mod tests {
     use postings::tests::test_skip_against_unoptimized;
     use query::VecDocSet;
     use query::ConstScorer;
-    use query::score_combiner::DoNothingCombiner;
+    use query::score_combiner::{DoNothingCombiner, SumCombiner};
+    use query::Scorer;
 
     fn aux_test_union(vals: Vec<Vec<u32>>) {
         let mut val_set: BTreeSet<u32> = BTreeSet::new();
@@ -355,6 +365,64 @@ mod tests {
         assert_eq!(docset.doc(), 1u32)
     }
 
+    #[test]
+    fn test_union_strictly_increasing() {
+        let docs_list = vec![
+            tests::sample_with_seed(100_000, 0.01, 1),
+            tests::sample_with_seed(100_000, 0.05, 2),
+            tests::sample_with_seed(100_000, 0.05, 2),
+            vec![0u32, 99_999u32],
+        ];
+        let make_union = |docs_list: &[Vec<DocId>]| {
+            Union::<_, DoNothingCombiner>::from(
+                docs_list
+                    .iter()
+                    .cloned()
+                    .map(VecDocSet::from)
+                    .map(ConstScorer::new)
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let collect_docs = |docs_list: &[Vec<DocId>]| {
+            let mut union = make_union(docs_list);
+            let mut docs = vec![];
+            while union.advance() {
+                docs.push(union.doc());
+            }
+            docs
+        };
+        let docs = collect_docs(&docs_list);
+        assert_eq!(docs[0], 0u32);
+        assert_eq!(*docs.last().unwrap(), 99_999u32);
+        for window in docs.windows(2) {
+            assert!(window[0] < window[1]);
+        }
+        let mut reversed_docs_list = docs_list.clone();
+        reversed_docs_list.reverse();
+        assert_eq!(collect_docs(&reversed_docs_list), docs);
+    }
+
+    #[test]
+    fn test_union_scores_are_deterministic() {
+        let docs_list = vec![vec![1u32, 5u32, 9u32], vec![5u32, 9u32], vec![9u32, 3_000_000u32]];
+        let mut union = Union::<_, SumCombiner>::from(
+            docs_list
+                .iter()
+                .cloned()
+                .map(VecDocSet::from)
+                .map(ConstScorer::new)
+                .collect::<Vec<_>>(),
+        );
+        let mut scored_docs = vec![];
+        while union.advance() {
+            scored_docs.push((union.doc(), union.score()));
+        }
+        assert_eq!(
+            scored_docs,
+            vec![(1u32, 1f32), (5u32, 2f32), (9u32, 3f32), (3_000_000u32, 1f32)]
+        );
+    }
+
     #[test]
     fn test_union_skip_random() {
         test_aux_union_skip(
@@ -1,6 +1,9 @@
-use super::Scorer;
+use super::{AliveDocSet, Explanation, Scorer};
 use Result;
+use DocId;
 use core::SegmentReader;
+use docset::{DocSet, SkipResult};
+use error::ErrorKind;
 
 /// A Weight is the specialization of a Query
 /// for a given set of segments.
@@ -11,8 +14,56 @@ pub trait Weight {
     /// See [`Query`](./trait.Query.html).
     fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>>;
 
-    /// Returns the number documents within the given `SegmentReader`.
+    /// Returns the scorer for the given segment, skipping
+    /// the deleted documents.
+    ///
+    /// Searches go through this method, so that collectors only
+    /// see the alive documents, whether or not the scorer returned
+    /// by `.scorer(...)` takes deletes into account.
+    fn alive_scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        let scorer = self.scorer(reader)?;
+        if reader.num_deleted_docs() == 0 {
+            Ok(scorer)
+        } else {
+            Ok(box AliveDocSet::new(scorer, reader.alive_bitset()))
+        }
+    }
+
+    /// Returns an estimate of the number of documents matched
+    /// in the given segment, deleted documents included.
+    ///
+    /// The estimate is meant to be much cheaper to compute than `.count(...)`,
+    /// and helps choosing an execution strategy per segment, e.g.
+    /// materializing a bitset versus streaming the documents.
+    /// The default implementation returns `max_doc`, without building
+    /// a scorer. Weights that can estimate their matches from the
+    /// statistics of the segment should override it.
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        Ok(reader.max_doc())
+    }
+
+    /// Returns the explanation of the score of the document `doc`
+    /// of the given segment.
+    ///
+    /// Returns an error if the document does not match.
+    /// The default implementation only reports the score
+    /// computed by the scorer, without any detail.
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        let mut scorer = self.scorer(reader)?;
+        match scorer.skip_next(doc) {
+            SkipResult::Reached => Ok(Explanation::new("score", scorer.score())),
+            SkipResult::OverStep | SkipResult::End => Err(does_not_match(doc).into()),
+        }
+    }
+
+    /// Returns the number of alive documents within the given `SegmentReader`.
     fn count(&self, reader: &SegmentReader) -> Result<u32> {
-        Ok(self.scorer(reader)?.count())
+        Ok(self.alive_scorer(reader)?.count())
     }
 }
+
+/// Error returned when explaining the score of a document
+/// that does not match the query.
+pub(crate) fn does_not_match(doc: DocId) -> ErrorKind {
+    ErrorKind::InvalidArgument(format!("Document #({}) does not match the query", doc))
+}
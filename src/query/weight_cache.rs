@@ -0,0 +1,589 @@
+use DocId;
+use Result;
+use Score;
+use core::{SegmentId, SegmentReader, Searcher};
+use docset::{DocSet, SkipResult};
+use query::{create_weight_with_cache, Explanation, Query, QueryAst, Scorer, Weight};
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+/// Maximum number of documents buffered for the consumers lagging behind.
+///
+/// Past it, the most lagging consumer is detached from the shared
+/// documents, and goes on with its own scorer.
+const MAX_BUFFERED_DOCS: usize = 1_024;
+
+/// Document produced by the scorer of a shared sub query.
+#[derive(Clone, Copy)]
+struct SharedDoc {
+    doc: DocId,
+    score: Score,
+    // the scorer has no document in `[covered_from, doc)`.
+    covered_from: DocId,
+}
+
+/// Outcome of moving a consumer through the shared documents.
+enum Fetch {
+    Doc(DocId, Score),
+    End,
+    /// The documents needed by the consumer, starting from the given
+    /// `DocId`, were skipped or dropped. The consumer has to go on with
+    /// its own scorer.
+    Detached(DocId),
+}
+
+enum Cursor {
+    /// Smallest document the consumer may still need.
+    Active(DocId),
+    /// The consumer lagged too far behind, and will be detached
+    /// on its next move.
+    Lagging(DocId),
+    Done,
+}
+
+/// Documents and scores of the scorer of a shared sub query on a segment.
+///
+/// They are computed lazily, as the consumers move, and only buffered until
+/// every consumer went past them. When a consumer skips past the
+/// produced documents, the scorer skips as well. The documents it skipped
+/// are recorded as missing, and a consumer needing them is detached.
+struct SharedDocs {
+    scorer: Box<Scorer>,
+    // documents produced by the scorer, which a consumer may still need.
+    buffer: VecDeque<SharedDoc>,
+    // smallest document the scorer can still produce.
+    scorer_next: DocId,
+    // once the scorer is exhausted, the scorer has no document from it on.
+    end: Option<DocId>,
+    cursors: Vec<Cursor>,
+}
+
+impl SharedDocs {
+    fn new(scorer: Box<Scorer>) -> SharedDocs {
+        SharedDocs {
+            scorer,
+            buffer: VecDeque::new(),
+            scorer_next: 0,
+            end: None,
+            cursors: Vec::new(),
+        }
+    }
+
+    /// Registers a new consumer, starting from the first document.
+    fn add_consumer(&mut self) -> usize {
+        self.cursors.push(Cursor::Active(0));
+        self.cursors.len() - 1
+    }
+
+    fn remove_consumer(&mut self, consumer: usize) {
+        self.cursors[consumer] = Cursor::Done;
+        self.trim();
+    }
+
+    /// Moves `consumer` to its next document greater than or equal to `target`.
+    fn fetch(&mut self, consumer: usize, target: DocId) -> Fetch {
+        let fetched = match self.cursors[consumer] {
+            Cursor::Active(lower_bound) => self.lookup(cmp::max(lower_bound, target)),
+            Cursor::Lagging(lower_bound) => Fetch::Detached(cmp::max(lower_bound, target)),
+            Cursor::Done => Fetch::End,
+        };
+        self.cursors[consumer] = match fetched {
+            Fetch::Doc(doc, _) => Cursor::Active(doc + 1),
+            Fetch::End | Fetch::Detached(_) => Cursor::Done,
+        };
+        while self.buffer.len() > MAX_BUFFERED_DOCS && self.detach_most_lagging(consumer) {}
+        self.trim();
+        fetched
+    }
+
+    /// Returns the first document greater than or equal to `lower_bound`,
+    /// moving the scorer if needed.
+    fn lookup(&mut self, lower_bound: DocId) -> Fetch {
+        let buffered_doc = self.buffer
+            .iter()
+            .find(|shared_doc| shared_doc.doc >= lower_bound)
+            .cloned();
+        if let Some(shared_doc) = buffered_doc {
+            return if shared_doc.covered_from <= lower_bound {
+                Fetch::Doc(shared_doc.doc, shared_doc.score)
+            } else {
+                Fetch::Detached(lower_bound)
+            };
+        }
+        if let Some(end) = self.end {
+            return if end <= lower_bound {
+                Fetch::End
+            } else {
+                Fetch::Detached(lower_bound)
+            };
+        }
+        if lower_bound < self.scorer_next {
+            // the documents were dropped before this consumer asked for them.
+            return Fetch::Detached(lower_bound);
+        }
+        let found = if lower_bound == self.scorer_next {
+            self.scorer.advance()
+        } else {
+            self.scorer.skip_next(lower_bound) != SkipResult::End
+        };
+        if !found {
+            self.end = Some(lower_bound);
+            return Fetch::End;
+        }
+        let shared_doc = SharedDoc {
+            doc: self.scorer.doc(),
+            score: self.scorer.score(),
+            covered_from: lower_bound,
+        };
+        self.scorer_next = shared_doc.doc + 1;
+        self.buffer.push_back(shared_doc);
+        Fetch::Doc(shared_doc.doc, shared_doc.score)
+    }
+
+    /// Marks the active consumer needing the smallest document, other
+    /// than `consumer`, as lagging. Returns false if there is none.
+    fn detach_most_lagging(&mut self, consumer: usize) -> bool {
+        let most_lagging = self.cursors
+            .iter()
+            .enumerate()
+            .filter(|&(other, _)| other != consumer)
+            .filter_map(|(other, cursor)| match *cursor {
+                Cursor::Active(lower_bound) => Some((lower_bound, other)),
+                _ => None,
+            })
+            .min();
+        match most_lagging {
+            Some((lower_bound, other)) => {
+                self.cursors[other] = Cursor::Lagging(lower_bound);
+                self.trim();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the buffered documents every active consumer went past.
+    fn trim(&mut self) {
+        let min_lower_bound = self.cursors
+            .iter()
+            .filter_map(|cursor| match *cursor {
+                Cursor::Active(lower_bound) => Some(lower_bound),
+                _ => None,
+            })
+            .min();
+        while let Some(shared_doc) = self.buffer.front().cloned() {
+            if min_lower_bound.map_or(false, |lower_bound| shared_doc.doc >= lower_bound) {
+                break;
+            }
+            self.buffer.pop_front();
+        }
+    }
+}
+
+/// Sub query appearing at least once in a boolean tree.
+struct SharedLeg {
+    weight: Box<Weight>,
+    num_uses: AtomicUsize,
+    // documents of the segments being searched. They are
+    // released as soon as the scorers of the segment are dropped.
+    segments: Mutex<HashMap<SegmentId, Weak<Mutex<SharedDocs>>>>,
+}
+
+impl SharedLeg {
+    fn shared_docs(&self, reader: &SegmentReader) -> Result<Arc<Mutex<SharedDocs>>> {
+        let segment_id = reader.segment_id();
+        let mut segments = self.segments.lock().expect("Weight cache lock poisoned");
+        segments.retain(|_, shared_docs| shared_docs.upgrade().is_some());
+        let existing_shared_docs = segments
+            .get(&segment_id)
+            .and_then(|shared_docs| shared_docs.upgrade());
+        if let Some(shared_docs) = existing_shared_docs {
+            return Ok(shared_docs);
+        }
+        let shared_docs = Arc::new(Mutex::new(SharedDocs::new(self.weight.scorer(reader)?)));
+        segments.insert(segment_id, Arc::downgrade(&shared_docs));
+        Ok(shared_docs)
+    }
+}
+
+/// Weights of the sub queries of a boolean tree,
+/// shared by the branches containing the same sub query.
+///
+/// The cache is created by the outermost `BooleanQuery`, and handed
+/// down to its sub queries with `Query::weight_with_cache`.
+///
+/// Sub queries are identified by their `QueryAst`, and the ones
+/// which cannot be represented as a `QueryAst` are never shared.
+#[derive(Default)]
+pub struct WeightCache {
+    legs: Vec<(QueryAst, bool, Arc<SharedLeg>)>,
+}
+
+impl WeightCache {
+    /// Creates the weight of a sub query of a boolean query,
+    /// or of a constant score query.
+    ///
+    /// Equivalent sub queries share their weight, so that their
+    /// documents and scores are computed only once per segment.
+    pub(crate) fn weight(
+        &mut self,
+        query: &Query,
+        searcher: &Searcher,
+        scoring_enabled: bool,
+    ) -> Result<Box<Weight>> {
+        let query_ast_opt = searcher
+            .segment_readers()
+            .first()
+            .and_then(|reader| query.query_ast(reader.schema()));
+        let query_ast = match query_ast_opt {
+            Some(query_ast) => query_ast,
+            None => return create_weight_with_cache(query, searcher, scoring_enabled, self),
+        };
+        let cached_leg = self.legs
+            .iter()
+            .find(|&&(ref ast, scoring, _)| scoring == scoring_enabled && *ast == query_ast)
+            .map(|&(_, _, ref leg)| Arc::clone(leg));
+        if let Some(leg) = cached_leg {
+            leg.num_uses.fetch_add(1, Ordering::SeqCst);
+            return Ok(box CachedWeight { leg });
+        }
+        let leg = Arc::new(SharedLeg {
+            weight: create_weight_with_cache(query, searcher, scoring_enabled, self)?,
+            num_uses: AtomicUsize::new(1),
+            segments: Mutex::new(HashMap::new()),
+        });
+        self.legs
+            .push((query_ast, scoring_enabled, Arc::clone(&leg)));
+        Ok(box CachedWeight { leg })
+    }
+}
+
+/// Weight of a sub query appearing in a boolean tree.
+///
+/// If the same sub query appears in several branches of the tree,
+/// the scorers of the branches share the scorer of the sub query on
+/// each segment, and each of them iterates with its own cursor.
+/// Otherwise, the weight simply forwards to the weight of the sub query.
+struct CachedWeight {
+    leg: Arc<SharedLeg>,
+}
+
+impl Weight for CachedWeight {
+    fn scorer(&self, reader: &SegmentReader) -> Result<Box<Scorer>> {
+        if self.leg.num_uses.load(Ordering::SeqCst) == 1 {
+            return self.leg.weight.scorer(reader);
+        }
+        let shared_docs = self.leg.shared_docs(reader)?;
+        let (consumer, size_hint) = {
+            let mut shared_docs = shared_docs.lock().expect("Weight cache lock poisoned");
+            (shared_docs.add_consumer(), shared_docs.scorer.size_hint())
+        };
+        Ok(box MemoizedScorer {
+            leg: Arc::clone(&self.leg),
+            reader: reader.clone(),
+            shared_docs,
+            consumer,
+            size_hint,
+            own_scorer: None,
+            current: None,
+        })
+    }
+
+    fn count_estimate(&self, reader: &SegmentReader) -> Result<u32> {
+        self.leg.weight.count_estimate(reader)
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> Result<Explanation> {
+        self.leg.weight.explain(reader, doc)
+    }
+}
+
+/// Scorer iterating over the documents of a shared sub query.
+///
+/// If the documents it needs are not shared anymore, it detaches
+/// and goes on with its own scorer of the sub query.
+struct MemoizedScorer {
+    leg: Arc<SharedLeg>,
+    reader: SegmentReader,
+    shared_docs: Arc<Mutex<SharedDocs>>,
+    consumer: usize,
+    size_hint: u32,
+    own_scorer: Option<Box<Scorer>>,
+    current: Option<(DocId, Score)>,
+}
+
+impl MemoizedScorer {
+    fn shared_docs(&self) -> MutexGuard<SharedDocs> {
+        self.shared_docs.lock().expect("Weight cache lock poisoned")
+    }
+
+    /// Moves to the next document, or to the first next document greater
+    /// than or equal to `target` if any.
+    fn fetch(&mut self, target: Option<DocId>) -> Option<(DocId, Score)> {
+        if let Some(ref mut scorer) = self.own_scorer {
+            let found = match target {
+                Some(target) => scorer.skip_next(target) != SkipResult::End,
+                None => scorer.advance(),
+            };
+            return if found {
+                Some((scorer.doc(), scorer.score()))
+            } else {
+                None
+            };
+        }
+        let fetched = self.shared_docs()
+            .fetch(self.consumer, target.unwrap_or(0));
+        match fetched {
+            Fetch::Doc(doc, score) => Some((doc, score)),
+            Fetch::End => None,
+            Fetch::Detached(lower_bound) => {
+                // the shared scorer was created from the same weight and reader.
+                let mut scorer = self.leg
+                    .weight
+                    .scorer(&self.reader)
+                    .expect("Failed to create the scorer of a shared sub query");
+                let doc_score_opt = if scorer.skip_next(lower_bound) != SkipResult::End {
+                    Some((scorer.doc(), scorer.score()))
+                } else {
+                    None
+                };
+                self.own_scorer = Some(scorer);
+                doc_score_opt
+            }
+        }
+    }
+
+    fn current(&self) -> (DocId, Score) {
+        self.current.expect(
+            "You may not call .doc() on a scorer \
+             where the last call to advance() did not return true.",
+        )
+    }
+}
+
+impl DocSet for MemoizedScorer {
+    fn advance(&mut self) -> bool {
+        self.current = self.fetch(None);
+        self.current.is_some()
+    }
+
+    fn skip_next(&mut self, target: DocId) -> SkipResult {
+        self.current = self.fetch(Some(target));
+        match self.current {
+            Some((doc, _)) if doc == target => SkipResult::Reached,
+            Some(_) => SkipResult::OverStep,
+            None => SkipResult::End,
+        }
+    }
+
+    fn doc(&self) -> DocId {
+        self.current().0
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.size_hint
+    }
+}
+
+impl Scorer for MemoizedScorer {
+    fn score(&mut self) -> Score {
+        self.current().1
+    }
+}
+
+impl Drop for MemoizedScorer {
+    fn drop(&mut self) {
+        if let Ok(mut shared_docs) = self.shared_docs.lock() {
+            shared_docs.remove_consumer(self.consumer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{MemoizedScorer, WeightCache, MAX_BUFFERED_DOCS};
+    use collector::TopCollector;
+    use docset::{DocSet, SkipResult};
+    use downcast::Downcast;
+    use query::{BooleanQuery, Intersection, Occur, PhraseQuery, Query, TermQuery};
+    use schema::{IndexRecordOption, SchemaBuilder, TEXT};
+    use Index;
+    use Term;
+
+    #[test]
+    fn test_memoized_scorer() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a b"));
+            index_writer.add_document(doc!(text => "b"));
+            index_writer.add_document(doc!(text => "a"));
+            index_writer.add_document(doc!(text => "a"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |value: &str| {
+            TermQuery::new(Term::from_field_text(text, value), IndexRecordOption::Basic)
+        };
+        let reader = searcher.segment_reader(0);
+        let mut weight_cache = WeightCache::default();
+        let left = weight_cache.weight(&term_query("a"), &*searcher, true).unwrap();
+        // a sub query appearing once is not shared.
+        assert!(!Downcast::<MemoizedScorer>::is_type(&*left.scorer(reader).unwrap()));
+        let right = weight_cache.weight(&term_query("a"), &*searcher, true).unwrap();
+        let other = weight_cache.weight(&term_query("b"), &*searcher, true).unwrap();
+        assert!(!Downcast::<MemoizedScorer>::is_type(&*other.scorer(reader).unwrap()));
+        let mut left_scorer = left.scorer(reader).unwrap();
+        let mut right_scorer = right.scorer(reader).unwrap();
+        assert!(Downcast::<MemoizedScorer>::is_type(&*left_scorer));
+        // both scorers have their own cursor.
+        assert!(left_scorer.advance());
+        assert!(left_scorer.advance());
+        assert_eq!(left_scorer.doc(), 2);
+        assert!(right_scorer.advance());
+        assert_eq!(right_scorer.doc(), 0);
+        assert_eq!(right_scorer.skip_next(3), SkipResult::Reached);
+        assert_eq!(left_scorer.skip_next(3), SkipResult::Reached);
+        assert!(!left_scorer.advance());
+        assert!(!right_scorer.advance());
+        // a scorer created once the documents were dropped goes on with its own scorer.
+        let mut late_scorer = right.scorer(reader).unwrap();
+        assert_eq!(late_scorer.skip_next(1), SkipResult::OverStep);
+        assert_eq!(late_scorer.doc(), 2);
+        drop(left_scorer);
+        drop(right_scorer);
+        // the remaining scorer skips on its own.
+        assert_eq!(late_scorer.skip_next(4), SkipResult::End);
+    }
+
+    #[test]
+    fn test_shared_sub_query() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(text => "a b c"));
+            index_writer.add_document(doc!(text => "a b d"));
+            index_writer.add_document(doc!(text => "b a c"));
+            index_writer.commit().unwrap();
+            index_writer.add_document(doc!(text => "a b a b d"));
+            index_writer.add_document(doc!(text => "c d"));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let phrase = || -> Box<Query> {
+            box PhraseQuery::from(vec![
+                Term::from_field_text(text, "a"),
+                Term::from_field_text(text, "b"),
+            ])
+        };
+        let term = |value: &str| -> Box<Query> {
+            box TermQuery::new(
+                Term::from_field_text(text, value),
+                IndexRecordOption::WithFreqs,
+            )
+        };
+        let branch = |value: &str| -> Box<Query> {
+            box BooleanQuery::from(vec![(Occur::Must, phrase()), (Occur::Must, term(value))])
+        };
+        // (+"a b" +c) (+"a b" +d)
+        let query = BooleanQuery::from(vec![
+            (Occur::Should, branch("c")),
+            (Occur::Should, branch("d")),
+        ]);
+        assert_eq!(query.count(&*searcher).unwrap(), 3);
+
+        let search = |query: &Query| {
+            let mut top_collector = TopCollector::with_limit(10);
+            searcher.search(query, &mut top_collector).unwrap();
+            top_collector.score_docs()
+        };
+        let score_docs = search(&query);
+        assert_eq!(score_docs.len(), 3);
+        // the scores are the same as without sharing the phrase query.
+        for (score, doc_address) in score_docs {
+            let explanation = query.explain(&*searcher, doc_address).unwrap();
+            assert!((explanation.value() - score).abs() < 0.0001f32);
+        }
+    }
+
+    #[test]
+    fn test_memoized_scorer_skips() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            for i in 0..2_000 {
+                let doc = match i % 100 {
+                    0 => doc!(text => "a c"),
+                    50 => doc!(text => "a d"),
+                    _ => doc!(text => "a"),
+                };
+                index_writer.add_document(doc);
+            }
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let term_query = |value: &str| {
+            TermQuery::new(Term::from_field_text(text, value), IndexRecordOption::Basic)
+        };
+        let reader = searcher.segment_reader(0);
+        let mut weight_cache = WeightCache::default();
+        let left = weight_cache.weight(&term_query("a"), &*searcher, true).unwrap();
+        let right = weight_cache.weight(&term_query("a"), &*searcher, true).unwrap();
+        let c = weight_cache.weight(&term_query("c"), &*searcher, true).unwrap();
+        let d = weight_cache.weight(&term_query("d"), &*searcher, true).unwrap();
+        let num_buffered_docs = || {
+            let segments = weight_cache.legs[0].2.segments.lock().unwrap();
+            segments
+                .values()
+                .filter_map(|shared_docs| shared_docs.upgrade())
+                .map(|shared_docs| shared_docs.lock().unwrap().buffer.len())
+                .sum::<usize>()
+        };
+        {
+            // (+c +a) (+d +a), driven by the rare terms.
+            let c_scorers = vec![c.scorer(reader).unwrap(), left.scorer(reader).unwrap()];
+            let d_scorers = vec![d.scorer(reader).unwrap(), right.scorer(reader).unwrap()];
+            let mut c_branch = Intersection::from_ordered(c_scorers);
+            let mut d_branch = Intersection::from_ordered(d_scorers);
+            let mut docs = Vec::new();
+            let mut c_alive = c_branch.advance();
+            let mut d_alive = d_branch.advance();
+            while c_alive || d_alive {
+                if c_alive && (!d_alive || c_branch.doc() < d_branch.doc()) {
+                    docs.push(c_branch.doc());
+                    c_alive = c_branch.advance();
+                } else {
+                    docs.push(d_branch.doc());
+                    d_alive = d_branch.advance();
+                }
+                // the shared scorer skips along with the consumers.
+                assert!(num_buffered_docs() <= 2);
+            }
+            let expected_docs: Vec<u32> = (0..40).map(|i| i * 50).collect();
+            assert_eq!(docs, expected_docs);
+        }
+        {
+            let mut lagging_scorer = left.scorer(reader).unwrap();
+            let mut leading_scorer = right.scorer(reader).unwrap();
+            assert!(lagging_scorer.advance());
+            assert_eq!(leading_scorer.count(), 2_000);
+            assert!(num_buffered_docs() <= MAX_BUFFERED_DOCS);
+            // the lagging scorer goes on with its own scorer.
+            assert!(lagging_scorer.advance());
+            assert_eq!(lagging_scorer.doc(), 1);
+            assert_eq!(lagging_scorer.count(), 1_998);
+        }
+    }
+}
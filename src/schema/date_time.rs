@@ -0,0 +1,296 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+const MICROS_PER_SEC: i64 = 1_000_000;
+const SECS_PER_DAY: i64 = 86_400;
+
+/// A point in time, with a microsecond precision.
+///
+/// Dates are indexed, and stored in fast fields,
+/// as the number of microseconds elapsed since
+/// the Unix epoch (`1970-01-01T00:00:00Z`).
+///
+/// Their textual representation, used in json documents
+/// and in the query parser, is RFC 3339, e.g. `2018-03-21T12:30:00Z`.
+/// A date alone, as `2018-03-21`, stands for the midnight UTC of that day.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct DateTime {
+    timestamp_micros: i64,
+}
+
+impl DateTime {
+    /// Creates a `DateTime` from a number of microseconds since the Unix epoch.
+    pub fn from_timestamp_micros(timestamp_micros: i64) -> DateTime {
+        DateTime { timestamp_micros }
+    }
+
+    /// Creates a `DateTime` from a number of seconds since the Unix epoch.
+    pub fn from_timestamp_secs(timestamp_secs: i64) -> DateTime {
+        DateTime::from_timestamp_micros(timestamp_secs * MICROS_PER_SEC)
+    }
+
+    /// Returns the number of microseconds since the Unix epoch.
+    pub fn timestamp_micros(&self) -> i64 {
+        self.timestamp_micros
+    }
+
+    /// Returns the number of whole seconds since the Unix epoch.
+    pub fn timestamp_secs(&self) -> i64 {
+        floor_div(self.timestamp_micros, MICROS_PER_SEC).0
+    }
+}
+
+/// Error returned when a string is not a valid RFC 3339 date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateTimeParseError(String);
+
+impl fmt::Display for DateTimeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid RFC 3339 date {:?}", self.0)
+    }
+}
+
+impl Error for DateTimeParseError {
+    fn description(&self) -> &str {
+        "invalid RFC 3339 date"
+    }
+}
+
+impl FromStr for DateTime {
+    type Err = DateTimeParseError;
+
+    fn from_str(text: &str) -> Result<DateTime, DateTimeParseError> {
+        parse_rfc3339(text.as_bytes()).ok_or_else(|| DateTimeParseError(text.to_string()))
+    }
+}
+
+impl fmt::Display for DateTime {
+    /// Formats the date in RFC 3339, in UTC.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (timestamp_secs, micros) = floor_div(self.timestamp_micros, MICROS_PER_SEC);
+        let (days, secs_of_day) = floor_div(timestamp_secs, SECS_PER_DAY);
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            secs_of_day / 3_600,
+            secs_of_day / 60 % 60,
+            secs_of_day % 60
+        )?;
+        if micros != 0 {
+            write!(f, ".{:06}", micros)?;
+        }
+        write!(f, "Z")
+    }
+}
+
+/// Returns the quotient and the remainder of the division
+/// of `value` by `divisor`, rounding towards negative infinity.
+fn floor_div(value: i64, divisor: i64) -> (i64, i64) {
+    let (quotient, remainder) = (value / divisor, value % divisor);
+    if remainder < 0 {
+        (quotient - 1, remainder + divisor)
+    } else {
+        (quotient, remainder)
+    }
+}
+
+/// Returns the number of days between the Unix epoch and a date
+/// of the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = floor_div(year, 400).0;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = floor_div(days, 146_097).0;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Consumes exactly `num_digits` digits.
+fn parse_digits(cursor: &mut &[u8], num_digits: usize) -> Option<i64> {
+    if cursor.len() < num_digits {
+        return None;
+    }
+    let mut value = 0i64;
+    for &byte in &cursor[..num_digits] {
+        if !(byte as char).is_digit(10) {
+            return None;
+        }
+        value = value * 10 + i64::from(byte - b'0');
+    }
+    *cursor = &cursor[num_digits..];
+    Some(value)
+}
+
+fn parse_byte(cursor: &mut &[u8], expected: &[u8]) -> Option<u8> {
+    let byte = *cursor.first()?;
+    if !expected.contains(&byte) {
+        return None;
+    }
+    *cursor = &cursor[1..];
+    Some(byte)
+}
+
+/// Parses `YYYY-MM-DD`, optionally followed by `THH:MM:SS[.fraction]`
+/// and a `Z` or `+HH:MM` timezone.
+fn parse_rfc3339(mut cursor: &[u8]) -> Option<DateTime> {
+    let cursor = &mut cursor;
+    let year = parse_digits(cursor, 4)?;
+    parse_byte(cursor, b"-")?;
+    let month = parse_digits(cursor, 2)?;
+    parse_byte(cursor, b"-")?;
+    let day = parse_digits(cursor, 2)?;
+    if month < 1 || month > 12 || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let mut timestamp_secs = days_from_civil(year, month, day) * SECS_PER_DAY;
+    let mut micros = 0i64;
+    if !cursor.is_empty() {
+        parse_byte(cursor, b"Tt ")?;
+        let hours = parse_digits(cursor, 2)?;
+        parse_byte(cursor, b":")?;
+        let minutes = parse_digits(cursor, 2)?;
+        parse_byte(cursor, b":")?;
+        let secs = parse_digits(cursor, 2)?;
+        if hours > 23 || minutes > 59 || secs > 59 {
+            return None;
+        }
+        timestamp_secs += hours * 3_600 + minutes * 60 + secs;
+        if parse_byte(cursor, b".").is_some() {
+            // digits beyond the microsecond are ignored.
+            let mut num_digits = 0;
+            while let Some(digit) = parse_digits(cursor, 1) {
+                if num_digits < 6 {
+                    micros = micros * 10 + digit;
+                }
+                num_digits += 1;
+            }
+            if num_digits == 0 {
+                return None;
+            }
+            for _ in num_digits..6 {
+                micros *= 10;
+            }
+        }
+        match parse_byte(cursor, b"Zz+-")? {
+            b'Z' | b'z' => {}
+            sign => {
+                let offset_hours = parse_digits(cursor, 2)?;
+                parse_byte(cursor, b":")?;
+                let offset_minutes = parse_digits(cursor, 2)?;
+                if offset_hours > 23 || offset_minutes > 59 {
+                    return None;
+                }
+                let offset_secs = offset_hours * 3_600 + offset_minutes * 60;
+                if sign == b'+' {
+                    timestamp_secs -= offset_secs;
+                } else {
+                    timestamp_secs += offset_secs;
+                }
+            }
+        }
+        if !cursor.is_empty() {
+            return None;
+        }
+    }
+    Some(DateTime::from_timestamp_micros(
+        timestamp_secs * MICROS_PER_SEC + micros,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::DateTime;
+
+    fn parse(text: &str) -> Option<DateTime> {
+        text.parse::<DateTime>().ok()
+    }
+
+    #[test]
+    fn test_date_time_parse() {
+        assert_eq!(parse("1970-01-01T00:00:00Z").unwrap().timestamp_micros(), 0);
+        assert_eq!(parse("1970-01-01").unwrap().timestamp_micros(), 0);
+        assert_eq!(
+            parse("2018-03-21T12:30:15Z").unwrap().timestamp_secs(),
+            1_521_635_415
+        );
+        assert_eq!(
+            parse("2018-03-21T14:30:15+02:00"),
+            parse("2018-03-21T12:30:15Z")
+        );
+        assert_eq!(
+            parse("2018-03-21T10:00:15-02:30"),
+            parse("2018-03-21T12:30:15Z")
+        );
+        assert_eq!(
+            parse("2018-03-21T12:30:15.25Z").unwrap().timestamp_micros(),
+            1_521_635_415_250_000
+        );
+        assert_eq!(
+            parse("2018-03-21T12:30:15.123456789Z")
+                .unwrap()
+                .timestamp_micros(),
+            1_521_635_415_123_456
+        );
+        assert_eq!(
+            parse("1969-12-31T23:59:59.5Z").unwrap().timestamp_micros(),
+            -500_000
+        );
+        assert_eq!(parse("2016-02-29").unwrap().timestamp_secs(), 1_456_704_000);
+        assert!(parse("2017-02-29").is_none());
+        assert!(parse("2018-13-01").is_none());
+        assert!(parse("2018-03-21T24:00:00Z").is_none());
+        assert!(parse("2018-03-21T12:30:15").is_none());
+        assert!(parse("2018-03-21T12:30:15.Z").is_none());
+        assert!(parse("2018-03-21T12:30:15Zabc").is_none());
+        assert!(parse("18-03-21").is_none());
+        assert!(parse("").is_none());
+    }
+
+    #[test]
+    fn test_date_time_display() {
+        let display = |text: &str| parse(text).unwrap().to_string();
+        assert_eq!(display("1970-01-01"), "1970-01-01T00:00:00Z");
+        assert_eq!(display("2018-03-21T14:30:15+02:00"), "2018-03-21T12:30:15Z");
+        assert_eq!(display("1969-12-31T23:59:59.5Z"), "1969-12-31T23:59:59.500000Z");
+        assert_eq!(display("0001-01-01T00:00:00Z"), "0001-01-01T00:00:00Z");
+        assert_eq!(display("2000-02-29T23:59:59Z"), "2000-02-29T23:59:59Z");
+        assert_eq!(
+            DateTime::from_timestamp_secs(1_521_635_415).to_string(),
+            "2018-03-21T12:30:15Z"
+        );
+    }
+}
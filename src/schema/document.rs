@@ -87,6 +87,16 @@ impl Document {
         self.add(FieldValue::new(field, Value::I64(value)));
     }
 
+    /// Add a f64 field
+    pub fn add_f64(&mut self, field: Field, value: f64) {
+        self.add(FieldValue::new(field, Value::F64(value)));
+    }
+
+    /// Add a date field
+    pub fn add_date(&mut self, field: Field, value: &DateTime) {
+        self.add(FieldValue::new(field, Value::Date(*value)));
+    }
+
     /// Add a field value
     pub fn add(&mut self, field_value: FieldValue) {
         self.field_values.push(field_value);
@@ -130,6 +140,25 @@ impl Document {
     }
 }
 
+impl Document {
+    /// Deserializes a document, only decoding the values of the given fields.
+    ///
+    /// The values of other fields are skipped without being decoded.
+    pub(crate) fn deserialize_fields(cursor: &mut &[u8], fields: &[Field]) -> io::Result<Document> {
+        let num_field_values = VInt::deserialize(cursor)?.val() as usize;
+        let mut field_values = Vec::new();
+        for _ in 0..num_field_values {
+            let field = Field::deserialize(cursor)?;
+            if fields.contains(&field) {
+                field_values.push(FieldValue::new(field, Value::deserialize(cursor)?));
+            } else {
+                super::value::skip_value(cursor)?;
+            }
+        }
+        Ok(Document::from(field_values))
+    }
+}
+
 impl BinarySerializable for Document {
     fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         let field_values = self.field_values();
@@ -149,6 +178,67 @@ impl BinarySerializable for Document {
     }
 }
 
+/// Values that can be added to a field of a document
+/// with the `doc!` macro.
+///
+/// It is implemented for the types that can be converted into a `Value`,
+/// for `Option`s of them (`None` adds no value), and for `Vec`s and
+/// slices of them, which add one value per element.
+pub trait IntoFieldValues {
+    /// Adds the values to the given field of the document.
+    fn add_to(self, field: Field, document: &mut Document);
+}
+
+macro_rules! impl_into_field_values(
+    ($($value_type:ty),*) => {
+        $(
+            impl IntoFieldValues for $value_type {
+                fn add_to(self, field: Field, document: &mut Document) {
+                    document.add(FieldValue::new(field, Value::from(self)));
+                }
+            }
+        )*
+    };
+);
+
+impl_into_field_values!(Value, String, u64, i64, f64, Facet, DateTime);
+
+impl<'a> IntoFieldValues for &'a str {
+    fn add_to(self, field: Field, document: &mut Document) {
+        document.add(FieldValue::new(field, Value::from(self)));
+    }
+}
+
+impl<T: IntoFieldValues> IntoFieldValues for Option<T> {
+    fn add_to(self, field: Field, document: &mut Document) {
+        if let Some(values) = self {
+            values.add_to(field, document);
+        }
+    }
+}
+
+impl<T: IntoFieldValues> IntoFieldValues for Vec<T> {
+    fn add_to(self, field: Field, document: &mut Document) {
+        for values in self {
+            values.add_to(field, document);
+        }
+    }
+}
+
+impl<'a, T: IntoFieldValues + Clone> IntoFieldValues for &'a [T] {
+    fn add_to(self, field: Field, document: &mut Document) {
+        for values in self {
+            values.clone().add_to(field, document);
+        }
+    }
+}
+
+impl<'a, T: IntoFieldValues + Clone> IntoFieldValues for &'a Vec<T> {
+    fn add_to(self, field: Field, document: &mut Document) {
+        self.as_slice().add_to(field, document);
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -163,4 +253,27 @@ mod tests {
         assert_eq!(doc.field_values().len(), 1);
     }
 
+    #[test]
+    fn test_doc_macro() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let tags = schema_builder.add_text_field("tags", STRING);
+        let counts = schema_builder.add_u64_field("counts", INT_STORED);
+        let fields = vec![title, tags];
+        let no_title: Option<&str> = None;
+        let tag_list = vec!["a".to_string(), "b".to_string()];
+        let doc = doc!(
+            fields[0] => Some("My title"),
+            fields[0] => no_title,
+            tags => &tag_list,
+            tags => vec!["c"],
+            counts => &[1u64, 2u64][..],
+            counts => 3u64,
+        );
+        assert_eq!(doc.get_all(title).len(), 1);
+        assert_eq!(doc.get_all(tags).len(), 3);
+        assert_eq!(doc.get_all(counts).len(), 3);
+        assert_eq!(doc!(title => None::<String>).len(), 0);
+    }
+
 }
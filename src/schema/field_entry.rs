@@ -14,7 +14,7 @@ use schema::FieldType;
 /// - a field name
 /// - a field type, itself wrapping up options describing
 /// how the field should be indexed.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FieldEntry {
     name: String,
     field_type: FieldType,
@@ -48,6 +48,24 @@ impl FieldEntry {
         }
     }
 
+    /// Creates a new date field entry in the schema, given
+    /// a name, and some options.
+    pub fn new_date(field_name: String, field_type: IntOptions) -> FieldEntry {
+        FieldEntry {
+            name: field_name,
+            field_type: FieldType::Date(field_type),
+        }
+    }
+
+    /// Creates a new f64 field entry in the schema, given
+    /// a name, and some options.
+    pub fn new_f64(field_name: String, field_type: IntOptions) -> FieldEntry {
+        FieldEntry {
+            name: field_name,
+            field_type: FieldType::F64(field_type),
+        }
+    }
+
     /// Creates a field entry for a facet.
     pub fn new_facet(field_name: String) -> FieldEntry {
         FieldEntry {
@@ -70,15 +88,21 @@ impl FieldEntry {
     pub fn is_indexed(&self) -> bool {
         match self.field_type {
             FieldType::Str(ref options) => options.get_indexing_options().is_some(),
-            FieldType::U64(ref options) | FieldType::I64(ref options) => options.is_indexed(),
+            FieldType::U64(ref options)
+            | FieldType::I64(ref options)
+            | FieldType::Date(ref options)
+            | FieldType::F64(ref options) => options.is_indexed(),
             FieldType::HierarchicalFacet => true,
         }
     }
 
-    /// Returns true iff the field is a int (signed or unsigned) fast field
+    /// Returns true iff the field is a int (signed or unsigned), date or f64 fast field
     pub fn is_int_fast(&self) -> bool {
         match self.field_type {
-            FieldType::U64(ref options) | FieldType::I64(ref options) => options.is_fast(),
+            FieldType::U64(ref options)
+            | FieldType::I64(ref options)
+            | FieldType::Date(ref options)
+            | FieldType::F64(ref options) => options.is_fast(),
             _ => false,
         }
     }
@@ -86,7 +110,10 @@ impl FieldEntry {
     /// Returns true iff the field is stored
     pub fn is_stored(&self) -> bool {
         match self.field_type {
-            FieldType::U64(ref options) | FieldType::I64(ref options) => options.is_stored(),
+            FieldType::U64(ref options)
+            | FieldType::I64(ref options)
+            | FieldType::Date(ref options)
+            | FieldType::F64(ref options) => options.is_stored(),
             FieldType::Str(ref options) => options.is_stored(),
             FieldType::HierarchicalFacet => true,
             // TODO make stored hierachical facet optional
@@ -118,6 +145,14 @@ impl Serialize for FieldEntry {
             FieldType::HierarchicalFacet => {
                 s.serialize_field("type", "hierarchical_facet")?;
             }
+            FieldType::Date(ref options) => {
+                s.serialize_field("type", "date")?;
+                s.serialize_field("options", options)?;
+            }
+            FieldType::F64(ref options) => {
+                s.serialize_field("type", "f64")?;
+                s.serialize_field("options", options)?;
+            }
         }
 
         s.end()
@@ -182,6 +217,8 @@ impl<'de> Deserialize<'de> for FieldEntry {
                                 "text" => field_type = Some(FieldType::Str(map.next_value()?)),
                                 "u64" => field_type = Some(FieldType::U64(map.next_value()?)),
                                 "i64" => field_type = Some(FieldType::I64(map.next_value()?)),
+                                "date" => field_type = Some(FieldType::Date(map.next_value()?)),
+                                "f64" => field_type = Some(FieldType::F64(map.next_value()?)),
                                 _ => {
                                     let msg = format!("Unrecognised type {}", ty);
                                     return Err(de::Error::custom(msg));
@@ -2,8 +2,8 @@ use schema::{IntOptions, TextOptions};
 
 use serde_json::Value as JsonValue;
 use schema::Value;
-use schema::IndexRecordOption;
-use schema::Facet;
+use schema::{BM25Params, IndexRecordOption};
+use schema::{DateTime, Facet};
 
 /// Possible error that may occur while parsing a field value
 /// At this point the JSON is known to be valid.
@@ -29,6 +29,14 @@ pub enum FieldType {
     I64(IntOptions),
     /// Hierachical Facet
     HierarchicalFacet,
+    /// Date field type configuration.
+    ///
+    /// Dates are indexed as `i64` numbers of microseconds.
+    Date(IntOptions),
+    /// 64-bits floats field type configuration.
+    ///
+    /// Floats are indexed as `u64`, as mapped by `common::f64_to_u64`.
+    F64(IntOptions),
 }
 
 impl FieldType {
@@ -36,9 +44,10 @@ impl FieldType {
     pub fn is_indexed(&self) -> bool {
         match *self {
             FieldType::Str(ref text_options) => text_options.get_indexing_options().is_some(),
-            FieldType::U64(ref int_options) | FieldType::I64(ref int_options) => {
-                int_options.is_indexed()
-            }
+            FieldType::U64(ref int_options)
+            | FieldType::I64(ref int_options)
+            | FieldType::Date(ref int_options)
+            | FieldType::F64(ref int_options) => int_options.is_indexed(),
             FieldType::HierarchicalFacet => true,
         }
     }
@@ -52,7 +61,10 @@ impl FieldType {
             FieldType::Str(ref text_options) => text_options
                 .get_indexing_options()
                 .map(|indexing_options| indexing_options.index_option()),
-            FieldType::U64(ref int_options) | FieldType::I64(ref int_options) => {
+            FieldType::U64(ref int_options)
+            | FieldType::I64(ref int_options)
+            | FieldType::Date(ref int_options)
+            | FieldType::F64(ref int_options) => {
                 if int_options.is_indexed() {
                     Some(IndexRecordOption::Basic)
                 } else {
@@ -63,6 +75,30 @@ impl FieldType {
         }
     }
 
+    /// Returns the parameters of the BM25 similarity set in the schema
+    /// for the field, if any.
+    pub fn bm25_params(&self) -> Option<BM25Params> {
+        match *self {
+            FieldType::Str(ref text_options) => text_options
+                .get_indexing_options()
+                .and_then(|indexing_options| indexing_options.bm25_params()),
+            _ => None,
+        }
+    }
+
+    /// Returns the minimum document frequency of the terms recorded
+    /// with their term frequencies, if set in the schema for the field.
+    ///
+    /// See `TextFieldIndexing::set_freqs_min_doc_freq`.
+    pub fn freqs_min_doc_freq(&self) -> Option<u32> {
+        match *self {
+            FieldType::Str(ref text_options) => text_options
+                .get_indexing_options()
+                .and_then(|indexing_options| indexing_options.freqs_min_doc_freq()),
+            _ => None,
+        }
+    }
+
     /// Parses a field value from json, given the target FieldType.
     ///
     /// Tantivy will not try to cast values.
@@ -75,7 +111,19 @@ impl FieldType {
                 FieldType::U64(_) | FieldType::I64(_) => Err(ValueParsingError::TypeError(
                     format!("Expected an integer, got {:?}", json),
                 )),
+                FieldType::F64(_) => Err(ValueParsingError::TypeError(format!(
+                    "Expected a number, got {:?}",
+                    json
+                ))),
                 FieldType::HierarchicalFacet => Ok(Value::Facet(Facet::from(field_text))),
+                FieldType::Date(_) => field_text.parse::<DateTime>().map(Value::Date).map_err(
+                    |_| {
+                        ValueParsingError::TypeError(format!(
+                            "Expected a RFC 3339 date, got {:?}",
+                            json
+                        ))
+                    },
+                ),
             },
             JsonValue::Number(ref field_val_num) => match *self {
                 FieldType::I64(_) => {
@@ -94,7 +142,15 @@ impl FieldType {
                         Err(ValueParsingError::OverflowError(msg))
                     }
                 }
-                FieldType::Str(_) | FieldType::HierarchicalFacet => {
+                FieldType::F64(_) => {
+                    if let Some(field_val_f64) = field_val_num.as_f64() {
+                        Ok(Value::F64(field_val_f64))
+                    } else {
+                        let msg = format!("Expected a f64, got {:?}", json);
+                        Err(ValueParsingError::OverflowError(msg))
+                    }
+                }
+                FieldType::Str(_) | FieldType::HierarchicalFacet | FieldType::Date(_) => {
                     let msg = format!("Expected a string, got {:?}", json);
                     Err(ValueParsingError::TypeError(msg))
                 }
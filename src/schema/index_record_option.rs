@@ -26,13 +26,22 @@ pub enum IndexRecordOption {
     /// Positions are required to run [PhraseQueries](../query/struct.PhraseQuery.html).
     #[serde(rename = "position")]
     WithFreqsAndPositions,
+    /// records the document id, the term frequency, the positions of
+    /// the occurences in the document, as well as their offsets, expressed
+    /// in bytes, within the original text.
+    /// Offsets make it possible to highlight the matches of a query
+    /// without tokenizing the stored text again.
+    #[serde(rename = "offset")]
+    WithFreqsAndPositionsAndOffsets,
 }
 
 impl IndexRecordOption {
     /// Returns true iff the term frequency will be encoded.
     pub fn is_termfreq_enabled(&self) -> bool {
         match *self {
-            IndexRecordOption::WithFreqsAndPositions | IndexRecordOption::WithFreqs => true,
+            IndexRecordOption::WithFreqsAndPositionsAndOffsets
+            | IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqs => true,
             _ => false,
         }
     }
@@ -40,7 +49,8 @@ impl IndexRecordOption {
     /// Returns true iff the term positions within the document are stored as well.
     pub fn is_position_enabled(&self) -> bool {
         match *self {
-            IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithFreqsAndPositionsAndOffsets
+            | IndexRecordOption::WithFreqsAndPositions => true,
             _ => false,
         }
     }
@@ -50,7 +60,31 @@ impl IndexRecordOption {
     pub fn has_freq(&self) -> bool {
         match *self {
             IndexRecordOption::Basic => false,
-            IndexRecordOption::WithFreqs | IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithFreqs
+            | IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqsAndPositionsAndOffsets => true,
+        }
+    }
+
+    /// Code of the option, as written in the postings
+    /// of the fields with a per-term record option.
+    pub(crate) fn code(&self) -> u8 {
+        match *self {
+            IndexRecordOption::Basic => 0u8,
+            IndexRecordOption::WithFreqs => 1u8,
+            IndexRecordOption::WithFreqsAndPositions => 2u8,
+            IndexRecordOption::WithFreqsAndPositionsAndOffsets => 3u8,
+        }
+    }
+
+    /// Returns the option of the given code, or `None` if the code is invalid.
+    pub(crate) fn from_code(code: u8) -> Option<IndexRecordOption> {
+        match code {
+            0u8 => Some(IndexRecordOption::Basic),
+            1u8 => Some(IndexRecordOption::WithFreqs),
+            2u8 => Some(IndexRecordOption::WithFreqsAndPositions),
+            3u8 => Some(IndexRecordOption::WithFreqsAndPositionsAndOffsets),
+            _ => None,
         }
     }
 
@@ -59,7 +93,17 @@ impl IndexRecordOption {
     pub fn has_positions(&self) -> bool {
         match *self {
             IndexRecordOption::Basic | IndexRecordOption::WithFreqs => false,
-            IndexRecordOption::WithFreqsAndPositions => true,
+            IndexRecordOption::WithFreqsAndPositions
+            | IndexRecordOption::WithFreqsAndPositionsAndOffsets => true,
+        }
+    }
+
+    /// Returns true iff this option include encoding
+    /// the offsets of the occurences of the terms.
+    pub fn has_offsets(&self) -> bool {
+        match *self {
+            IndexRecordOption::WithFreqsAndPositionsAndOffsets => true,
+            _ => false,
         }
     }
 }
@@ -19,6 +19,12 @@ pub struct IntOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     fast: Option<Cardinality>,
     stored: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    opstamp: bool,
+}
+
+fn is_false(val: &bool) -> bool {
+    !*val
 }
 
 impl IntOptions {
@@ -37,6 +43,12 @@ impl IntOptions {
         self.fast.is_some()
     }
 
+    /// Returns true iff the value is filled with the opstamp
+    /// of the document.
+    pub fn is_opstamp(&self) -> bool {
+        self.opstamp
+    }
+
     /// Set the u64 options as stored.
     ///
     /// Only the fields that are set as *stored* are
@@ -66,6 +78,21 @@ impl IntOptions {
         self
     }
 
+    /// Set the u64 options as filled with the opstamp of the document.
+    ///
+    /// The opstamp, returned when the document is added, increases
+    /// with the insertion order of the documents, so that, combined
+    /// with `FAST`, the field makes it possible to sort the documents
+    /// by insertion order, or to resume an iteration after a given document.
+    ///
+    /// The values of the field given in the document are replaced
+    /// by its opstamp.
+    /// This option is ignored for fields that are not `u64` fields.
+    pub fn set_opstamp(mut self) -> IntOptions {
+        self.opstamp = true;
+        self
+    }
+
     /// Returns the cardinality of the fastfield.
     ///
     /// If the field has not been declared as a fastfield, then
@@ -81,6 +108,7 @@ impl Default for IntOptions {
             indexed: false,
             stored: false,
             fast: None,
+            opstamp: false,
         }
     }
 }
@@ -92,6 +120,7 @@ pub const FAST: IntOptions = IntOptions {
     indexed: false,
     stored: false,
     fast: Some(Cardinality::SingleValue),
+    opstamp: false,
 };
 
 /// Shortcut for a u64 indexed field.
@@ -101,6 +130,7 @@ pub const INT_INDEXED: IntOptions = IntOptions {
     indexed: true,
     stored: false,
     fast: None,
+    opstamp: false,
 };
 
 /// Shortcut for a u64 stored field.
@@ -110,6 +140,7 @@ pub const INT_STORED: IntOptions = IntOptions {
     indexed: false,
     stored: true,
     fast: None,
+    opstamp: false,
 };
 
 impl BitOr for IntOptions {
@@ -120,6 +151,7 @@ impl BitOr for IntOptions {
         res.indexed = self.indexed | other.indexed;
         res.stored = self.stored | other.stored;
         res.fast = self.fast.or(other.fast);
+        res.opstamp = self.opstamp | other.opstamp;
         res
     }
 }
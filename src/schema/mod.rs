@@ -104,6 +104,7 @@ mod schema;
 mod term;
 mod document;
 mod facet;
+mod date_time;
 
 mod field_type;
 mod field_entry;
@@ -116,15 +117,16 @@ mod value;
 mod named_field_document;
 mod index_record_option;
 
-pub use self::named_field_document::NamedFieldDocument;
+pub use self::named_field_document::{deserialize_values, NamedFieldDocument};
 pub use self::schema::{Schema, SchemaBuilder};
 pub use self::value::Value;
 pub use self::schema::DocParsingError;
 
 pub use self::facet::Facet;
+pub use self::date_time::{DateTime, DateTimeParseError};
 pub use self::facet::FACET_SEP_BYTE;
 
-pub use self::document::Document;
+pub use self::document::{Document, IntoFieldValues};
 pub use self::field::Field;
 pub use self::term::Term;
 
@@ -134,7 +136,8 @@ pub use self::field_value::FieldValue;
 
 pub use self::text_options::TextOptions;
 pub use self::index_record_option::IndexRecordOption;
-pub use self::text_options::TextFieldIndexing;
+pub use self::text_options::{BM25Params, DocFreqPruning, StoredValueLimit, StoredValueOverflow,
+                             TextFieldIndexing};
 pub use self::text_options::TEXT;
 pub use self::text_options::STRING;
 pub use self::text_options::STORED;
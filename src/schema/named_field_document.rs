@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 use schema::Value;
+use serde::{Deserialize, Deserializer};
 
 /// Internal representation of a document used for JSON
 /// serialization.
@@ -7,5 +8,29 @@ use schema::Value;
 /// A `NamedFieldDocument` is a simple representation of a document
 /// as a `BTreeMap<String, Vec<Value>>`.
 ///
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct NamedFieldDocument(pub BTreeMap<String, Vec<Value>>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+/// Deserializes either a single value or a sequence of values into a `Vec`.
+///
+/// `Schema::from_document` maps fields with a single value to a scalar.
+/// Members of a struct meant to receive all of the values of a field
+/// should therefore be annotated with
+/// `#[serde(default, deserialize_with = "tantivy::schema::deserialize_values")]`.
+pub fn deserialize_values<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
@@ -5,6 +5,7 @@ use std::sync::Arc;
 
 use serde_json::{self, Map as JsonObject, Value as JsonValue};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::DeserializeOwned;
 use serde::ser::SerializeSeq;
 use serde::de::{SeqAccess, Visitor};
 use super::*;
@@ -73,6 +74,42 @@ impl SchemaBuilder {
         self.add_field(field_entry)
     }
 
+    /// Adds a new f64 field.
+    /// Returns the associated field handle
+    ///
+    /// # Caution
+    ///
+    /// Appending two fields with the same name
+    /// will result in the shadowing of the first
+    /// by the second one.
+    /// The first field will get a field id
+    /// but only the second one will be indexed
+    pub fn add_f64_field(&mut self, field_name_str: &str, field_options: IntOptions) -> Field {
+        let field_name = String::from(field_name_str);
+        let field_entry = FieldEntry::new_f64(field_name, field_options);
+        self.add_field(field_entry)
+    }
+
+    /// Adds a new date field.
+    /// Returns the associated field handle
+    ///
+    /// Internally, dates are handled as `i64` numbers of
+    /// microseconds since the Unix epoch, so that they
+    /// accept the same options as `i64` fields.
+    ///
+    /// # Caution
+    ///
+    /// Appending two fields with the same name
+    /// will result in the shadowing of the first
+    /// by the second one.
+    /// The first field will get a field id
+    /// but only the second one will be indexed
+    pub fn add_date_field(&mut self, field_name_str: &str, field_options: IntOptions) -> Field {
+        let field_name = String::from(field_name_str);
+        let field_entry = FieldEntry::new_date(field_name, field_options);
+        self.add_field(field_entry)
+    }
+
     /// Adds a new text field.
     /// Returns the associated field handle
     ///
@@ -196,6 +233,90 @@ impl Schema {
         NamedFieldDocument(field_map)
     }
 
+    /// Build a document object from a named doc, as returned
+    /// by `to_named_doc`.
+    pub fn convert_named_doc(
+        &self,
+        named_doc: NamedFieldDocument,
+    ) -> Result<Document, DocParsingError> {
+        let mut doc = Document::default();
+        for (field_name, values) in named_doc.0 {
+            for value in values {
+                let json_value = serde_json::to_value(&value)
+                    .map_err(|e| DocParsingError::MappingError(e.to_string()))?;
+                self.add_json_value(&mut doc, &field_name, &json_value)?;
+            }
+        }
+        Ok(doc)
+    }
+
+    /// Converts a value, typically a struct deriving `Serialize`,
+    /// into a document.
+    ///
+    /// The members of the struct are mapped to the fields of the same name.
+    /// Sequences are mapped to several values of the same field,
+    /// and `None` values are skipped.
+    pub fn to_document<T: Serialize>(&self, value: &T) -> Result<Document, DocParsingError> {
+        let json_obj = match serde_json::to_value(value) {
+            Ok(JsonValue::Object(json_obj)) => json_obj,
+            Ok(_) => {
+                return Err(DocParsingError::MappingError(
+                    "Only structs and maps can be converted into a document.".to_string(),
+                ))
+            }
+            Err(e) => return Err(DocParsingError::MappingError(e.to_string())),
+        };
+        let mut doc = Document::default();
+        for (field_name, json_value) in &json_obj {
+            match *json_value {
+                JsonValue::Null => {}
+                JsonValue::Array(ref json_items) => for json_item in json_items {
+                    self.add_json_value(&mut doc, field_name, json_item)?;
+                },
+                _ => self.add_json_value(&mut doc, field_name, json_value)?,
+            }
+        }
+        Ok(doc)
+    }
+
+    /// Converts a document into a value of a type deriving `Deserialize`.
+    ///
+    /// Fields with a single value are mapped to a scalar, fields with
+    /// several values to a sequence. See `deserialize_values` for
+    /// struct members that may receive several values.
+    /// Fields missing from the document are missing from the
+    /// serialized representation, so they should be mapped to `Option`s
+    /// or to members with a default value.
+    pub fn from_document<T: DeserializeOwned>(&self, doc: &Document) -> Result<T, DocParsingError> {
+        let mut json_obj = JsonObject::new();
+        for (field_name, values) in self.to_named_doc(doc).0 {
+            let json_value = if values.len() == 1 {
+                serde_json::to_value(&values[0])
+            } else {
+                serde_json::to_value(&values)
+            }.map_err(|e| DocParsingError::MappingError(e.to_string()))?;
+            json_obj.insert(field_name, json_value);
+        }
+        serde_json::from_value(JsonValue::Object(json_obj))
+            .map_err(|e| DocParsingError::MappingError(e.to_string()))
+    }
+
+    fn add_json_value(
+        &self,
+        doc: &mut Document,
+        field_name: &str,
+        json_value: &JsonValue,
+    ) -> Result<(), DocParsingError> {
+        let field = self.get_field(field_name)
+            .ok_or_else(|| DocParsingError::NoSuchFieldInSchema(field_name.to_string()))?;
+        let value = self.get_field_entry(field)
+            .field_type()
+            .value_from_json(json_value)
+            .map_err(|e| DocParsingError::ValueError(field_name.to_string(), e))?;
+        doc.add(FieldValue::new(field, value));
+        Ok(())
+    }
+
     /// Encode the schema in JSON.
     ///
     /// Encoding a document cannot fail.
@@ -217,32 +338,24 @@ impl Schema {
 
         let mut doc = Document::default();
         for (field_name, json_value) in json_obj.iter() {
-            match self.get_field(field_name) {
-                Some(field) => {
-                    let field_entry = self.get_field_entry(field);
-                    let field_type = field_entry.field_type();
-                    match *json_value {
-                        JsonValue::Array(ref json_items) => for json_item in json_items {
-                            let value = field_type
-                                .value_from_json(json_item)
-                                .map_err(|e| DocParsingError::ValueError(field_name.clone(), e))?;
-                            doc.add(FieldValue::new(field, value));
-                        },
-                        _ => {
-                            let value = field_type
-                                .value_from_json(json_value)
-                                .map_err(|e| DocParsingError::ValueError(field_name.clone(), e))?;
-                            doc.add(FieldValue::new(field, value));
-                        }
-                    }
-                }
-                None => return Err(DocParsingError::NoSuchFieldInSchema(field_name.clone())),
+            match *json_value {
+                JsonValue::Array(ref json_items) => for json_item in json_items {
+                    self.add_json_value(&mut doc, field_name, json_item)?;
+                },
+                _ => self.add_json_value(&mut doc, field_name, json_value)?,
             }
         }
         Ok(doc)
     }
 }
 
+impl PartialEq for Schema {
+    fn eq(&self, other: &Schema) -> bool {
+        self.fields() == other.fields()
+    }
+}
+
+impl Eq for Schema {}
 
 impl Serialize for Schema {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -303,6 +416,8 @@ pub enum DocParsingError {
     ValueError(String, ValueParsingError),
     /// The json-document contains a field that is not declared in the schema.
     NoSuchFieldInSchema(String),
+    /// A value could not be mapped to or from a document.
+    MappingError(String),
 }
 
 #[cfg(test)]
@@ -411,6 +526,58 @@ mod tests {
         assert_eq!(doc, doc_serdeser);
     }
 
+    #[test]
+    pub fn test_named_doc_serde() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let count = schema_builder.add_u64_field("count", INT_STORED);
+        let schema = schema_builder.build();
+        let doc = doc!(title => "my title", title => "my subtitle", count => 4u64);
+        let named_doc_json = serde_json::to_string(&schema.to_named_doc(&doc)).unwrap();
+        assert_eq!(
+            named_doc_json,
+            r#"{"count":[4],"title":["my title","my subtitle"]}"#
+        );
+        let named_doc: NamedFieldDocument = serde_json::from_str(&named_doc_json).unwrap();
+        assert_eq!(schema.convert_named_doc(named_doc).unwrap(), doc);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Book {
+        title: String,
+        author: Option<String>,
+        count: u64,
+        #[serde(default, deserialize_with = "deserialize_values")]
+        tags: Vec<String>,
+    }
+
+    #[test]
+    pub fn test_typed_document_mapping() {
+        let mut schema_builder = SchemaBuilder::default();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        schema_builder.add_text_field("author", STRING | STORED);
+        let count = schema_builder.add_u64_field("count", INT_STORED);
+        let tags = schema_builder.add_text_field("tags", STRING | STORED);
+        let schema = schema_builder.build();
+        for book_tags in vec![vec![], vec!["scifi"], vec!["scifi", "classic"]] {
+            let book = Book {
+                title: "Dune".to_string(),
+                author: None,
+                count: 3,
+                tags: book_tags.iter().map(|tag| tag.to_string()).collect(),
+            };
+            let doc = schema.to_document(&book).unwrap();
+            assert_eq!(doc.get_first(title).unwrap().text(), "Dune");
+            assert_eq!(doc.get_first(count).unwrap().u64_value(), 3);
+            assert_eq!(doc.get_all(tags).len(), book_tags.len());
+            assert_eq!(doc.len(), 2 + book_tags.len());
+            let book_deser: Book = schema.from_document(&doc).unwrap();
+            assert_eq!(book_deser, book);
+        }
+        assert!(schema.to_document(&"not a struct").is_err());
+        assert!(schema.from_document::<Book>(&doc!(count => 3u64)).is_err());
+    }
+
     #[test]
     pub fn test_parse_document() {
         let mut schema_builder = SchemaBuilder::default();
@@ -3,6 +3,8 @@ use std::fmt;
 use common;
 use byteorder::{BigEndian, ByteOrder};
 use super::Field;
+use super::Facet;
+use super::DateTime;
 use std::str;
 
 /// Size (in bytes) of the buffer of a int field.
@@ -29,6 +31,28 @@ impl Term {
         Term::from_field_u64(field, val_u64)
     }
 
+    /// Builds a term given a field, and a f64-value
+    ///
+    /// Assuming the term has a field id of 1, and a f64 value of 3.5,
+    /// the Term will have 12 bytes.
+    ///
+    /// The first four byte are dedicated to storing the field id as a u32.
+    /// The 8 following bytes are encoding the f64 value,
+    /// as mapped by `common::f64_to_u64`, so that the order
+    /// of the terms is the order of the values.
+    pub fn from_field_f64(field: Field, val: f64) -> Term {
+        let val_u64: u64 = common::f64_to_u64(val);
+        Term::from_field_u64(field, val_u64)
+    }
+
+    /// Builds a term given a field, and a date.
+    ///
+    /// The date is encoded as its `i64` number of microseconds
+    /// since the Unix epoch.
+    pub fn from_field_date(field: Field, val: &DateTime) -> Term {
+        Term::from_field_i64(field, val.timestamp_micros())
+    }
+
     /// Builds a term given a field, and a string value
     ///
     /// Assuming the term has a field id of 2, and a text value of "abc",
@@ -43,6 +67,27 @@ impl Term {
         term
     }
 
+    /// Builds a term given a field, and a facet.
+    ///
+    /// The term matches the documents associated with the facet
+    /// or with any of its descendants.
+    pub fn from_facet(field: Field, facet: &Facet) -> Term {
+        let facet_bytes = facet.encoded_bytes();
+        let mut term = Term(Vec::with_capacity(4 + facet_bytes.len()));
+        term.set_field(field);
+        term.0.extend(facet_bytes);
+        term
+    }
+
+    /// Builds a term given a field, and the bytes of its value,
+    /// as stored in the term dictionary of the field.
+    pub fn from_field_bytes(field: Field, bytes: &[u8]) -> Term {
+        let mut term = Term(Vec::with_capacity(4 + bytes.len()));
+        term.set_field(field);
+        term.0.extend(bytes);
+        term
+    }
+
     /// Builds a term given a field, and a u64-value
     ///
     /// Assuming the term has a field id of 1, and a u64 value of 3234,
@@ -130,6 +175,15 @@ where
         common::u64_to_i64(BigEndian::read_u64(&self.0.as_ref()[4..]))
     }
 
+    /// Returns the `f64` value stored in a term.
+    ///
+    /// # Panics
+    /// ... or returns an invalid value
+    /// if the term is not a `f64` field.
+    pub fn get_f64(&self) -> f64 {
+        common::u64_to_f64(BigEndian::read_u64(&self.0.as_ref()[4..]))
+    }
+
     /// Returns the text associated with the term.
     ///
     /// # Panics
@@ -203,4 +257,19 @@ mod tests {
             assert_eq!(term.as_slice()[11], (983u64 % 256u64) as u8);
         }
     }
+
+    #[test]
+    pub fn test_term_f64() {
+        let mut schema_builder = SchemaBuilder::default();
+        let price_field = schema_builder.add_f64_field("price", INT_INDEXED);
+        let terms: Vec<Term> = [-10.5f64, -0.25f64, 0f64, 0.25f64, 3f64]
+            .iter()
+            .map(|&val| Term::from_field_f64(price_field, val))
+            .collect();
+        for pair in terms.windows(2) {
+            assert!(pair[0].as_slice() < pair[1].as_slice());
+        }
+        assert_eq!(terms[1].get_f64(), -0.25f64);
+        assert_eq!(terms[4].get_f64(), 3f64);
+    }
 }
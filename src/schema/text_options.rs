@@ -1,12 +1,21 @@
 use std::ops::BitOr;
 use std::borrow::Cow;
 use schema::IndexRecordOption;
+use schema::Value;
+use common::fnv1a_hash;
+use error::ErrorKind;
+use serde::de;
+use serde::{Deserialize, Deserializer};
+use std::result;
+use Result;
 
 /// Define how a text field should be handled by tantivy.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TextOptions {
     indexing: Option<TextFieldIndexing>,
     stored: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stored_value_limit: Option<StoredValueLimit>,
 }
 
 impl TextOptions {
@@ -31,6 +40,31 @@ impl TextOptions {
         self.indexing = Some(indexing);
         self
     }
+
+    /// Caps the length of the stored values of the field.
+    ///
+    /// A single huge value would otherwise bloat the blocks of the
+    /// doc store, and the memory required to merge them.
+    /// The values are still indexed in full, unless the document is rejected.
+    pub fn set_stored_value_limit(mut self, stored_value_limit: StoredValueLimit) -> TextOptions {
+        self.stored_value_limit = Some(stored_value_limit);
+        self
+    }
+
+    /// Returns the cap on the length of the stored values, if any.
+    pub fn stored_value_limit(&self) -> Option<StoredValueLimit> {
+        self.stored_value_limit
+    }
+
+    /// Returns the limit exceeded by `value`, if the field is stored.
+    pub(crate) fn exceeded_stored_value_limit(&self, value: &Value) -> Option<StoredValueLimit> {
+        match (self.stored_value_limit, value) {
+            (Some(limit), &Value::Str(ref text)) if self.stored && limit.is_exceeded_by(text) => {
+                Some(limit)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Default for TextOptions {
@@ -38,10 +72,164 @@ impl Default for TextOptions {
         TextOptions {
             indexing: None,
             stored: false,
+            stored_value_limit: None,
         }
     }
 }
 
+/// Defines what happens to a stored value exceeding
+/// the maximum length of its field.
+///
+/// See `StoredValueLimit`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum StoredValueOverflow {
+    /// Only the beginning of the value is stored, truncated
+    /// at the last char boundary within the maximum length.
+    #[serde(rename = "truncate")]
+    Truncate,
+    /// The whole document is rejected: it is neither indexed nor stored,
+    /// and `IndexWriter::try_add_document` returns an error.
+    #[serde(rename = "reject")]
+    Reject,
+    /// The value is replaced by its 64-bit FNV-1a hash,
+    /// written as 16 hexadecimal digits.
+    #[serde(rename = "hash")]
+    StoreHash,
+}
+
+/// Maximum length, in bytes, of the stored values of a text field.
+///
+/// See `TextOptions::set_stored_value_limit`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct StoredValueLimit {
+    /// Maximum length of a value, in bytes.
+    pub max_len: usize,
+    /// Behaviour when a value is longer than `max_len`.
+    pub overflow: StoredValueOverflow,
+}
+
+impl StoredValueLimit {
+    /// Returns true iff `text` is longer than the limit.
+    pub(crate) fn is_exceeded_by(&self, text: &str) -> bool {
+        text.len() > self.max_len
+    }
+
+    /// Returns the value to store in place of `text`, which exceeds the limit.
+    pub(crate) fn limited_value(&self, text: &str) -> String {
+        match self.overflow {
+            StoredValueOverflow::Truncate => {
+                let mut end = self.max_len;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                text[..end].to_string()
+            }
+            StoredValueOverflow::StoreHash => format!("{:016x}", fnv1a_hash(text.as_bytes())),
+            StoredValueOverflow::Reject => text.to_string(),
+        }
+    }
+}
+
+/// Pruning of the terms appearing in too many documents,
+/// applied when segments are merged.
+///
+/// See `TextFieldIndexing::set_doc_freq_pruning`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct DocFreqPruning {
+    /// Percentage of the documents above which a term is pruned.
+    pub max_doc_freq_percent: u32,
+    /// Number of documents below which a merged segment is not pruned.
+    pub min_num_docs: u32,
+}
+
+impl DocFreqPruning {
+    /// Returns true if a term appearing in `doc_freq`
+    /// of the `num_docs` documents should be pruned.
+    pub(crate) fn prunes(&self, doc_freq: u64, num_docs: u64) -> bool {
+        num_docs >= u64::from(self.min_num_docs)
+            && doc_freq * 100 > u64::from(self.max_doc_freq_percent) * num_docs
+    }
+}
+
+/// Parameters of the BM25 similarity, used to score the matches
+/// of the term queries.
+///
+/// See `TextFieldIndexing::set_bm25_params` and `Index::set_bm25_params`.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct BM25Params {
+    k1: f32,
+    b: f32,
+}
+
+impl BM25Params {
+    /// Creates BM25 parameters.
+    ///
+    /// * `k1` is the saturation of the term frequency.
+    /// The higher `k1`, the more repeated occurrences of a term matter.
+    /// * `b` is the normalization by the length of the field, between 0 and 1.
+    /// With `b = 0`, the length of the field is ignored.
+    ///
+    /// # Errors
+    /// Returns an error if `k1` is negative or not finite,
+    /// or if `b` is not between 0 and 1.
+    pub fn new(k1: f32, b: f32) -> Result<BM25Params> {
+        if !k1.is_finite() || !b.is_finite() {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The BM25 parameters must be finite, got k1={} and b={}",
+                k1, b
+            )));
+        }
+        if k1 < 0f32 {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The BM25 parameter k1 must be positive, got k1={}",
+                k1
+            )));
+        }
+        if b < 0f32 || b > 1f32 {
+            bail!(ErrorKind::InvalidArgument(format!(
+                "The BM25 parameter b must be between 0 and 1, got b={}",
+                b
+            )));
+        }
+        Ok(BM25Params { k1, b })
+    }
+
+    /// Saturation of the term frequency.
+    pub fn k1(&self) -> f32 {
+        self.k1
+    }
+
+    /// Normalization by the length of the field.
+    pub fn b(&self) -> f32 {
+        self.b
+    }
+}
+
+impl Default for BM25Params {
+    fn default() -> BM25Params {
+        BM25Params { k1: 1.2, b: 0.75 }
+    }
+}
+
+// The parameters are checked to be finite, hence never NaN.
+impl Eq for BM25Params {}
+
+impl<'de> Deserialize<'de> for BM25Params {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RawBM25Params {
+            k1: f32,
+            b: f32,
+        }
+        let raw_params = RawBM25Params::deserialize(deserializer)?;
+        BM25Params::new(raw_params.k1, raw_params.b)
+            .map_err(|e| de::Error::custom(e.to_string()))
+    }
+}
+
 /// Configuration defining indexing for a text field.
 /// It wraps:
 ///
@@ -51,6 +239,12 @@ impl Default for TextOptions {
 pub struct TextFieldIndexing {
     record: IndexRecordOption,
     tokenizer: Cow<'static, str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    doc_freq_pruning: Option<DocFreqPruning>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bm25: Option<BM25Params>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    freqs_min_doc_freq: Option<u32>,
 }
 
 impl Default for TextFieldIndexing {
@@ -58,6 +252,9 @@ impl Default for TextFieldIndexing {
         TextFieldIndexing {
             tokenizer: Cow::Borrowed("default"),
             record: IndexRecordOption::Basic,
+            doc_freq_pruning: None,
+            bm25: None,
+            freqs_min_doc_freq: None,
         }
     }
 }
@@ -88,6 +285,71 @@ impl TextFieldIndexing {
     pub fn index_option(&self) -> IndexRecordOption {
         self.record
     }
+
+    /// Prunes the terms appearing in more than `max_doc_freq_percent`
+    /// percent of the documents, in the segments resulting from a merge
+    /// of at least `min_num_docs` documents.
+    ///
+    /// This trades a little recall for much smaller postings on huge
+    /// corpora: the pruned terms behave as stopwords, and a query on
+    /// a pruned term, or a phrase query including it, does not match
+    /// the documents of the pruned segments.
+    ///
+    /// The document frequencies include the deleted documents
+    /// of the segments being merged.
+    pub fn set_doc_freq_pruning(
+        mut self,
+        max_doc_freq_percent: u32,
+        min_num_docs: u32,
+    ) -> TextFieldIndexing {
+        self.doc_freq_pruning = Some(DocFreqPruning {
+            max_doc_freq_percent,
+            min_num_docs,
+        });
+        self
+    }
+
+    /// Returns the pruning of the frequent terms, if any.
+    pub fn doc_freq_pruning(&self) -> Option<DocFreqPruning> {
+        self.doc_freq_pruning
+    }
+
+    /// Sets the parameters of the BM25 similarity of the field.
+    ///
+    /// They can be overridden at search time with `Index::set_bm25_params`.
+    pub fn set_bm25_params(mut self, bm25_params: BM25Params) -> TextFieldIndexing {
+        self.bm25 = Some(bm25_params);
+        self
+    }
+
+    /// Returns the parameters of the BM25 similarity of the field,
+    /// if they were set.
+    pub fn bm25_params(&self) -> Option<BM25Params> {
+        self.bm25
+    }
+
+    /// Only records the term frequencies and positions of the terms
+    /// appearing in at least `min_doc_freq` documents of a segment.
+    ///
+    /// The rarer terms are recorded as with `IndexRecordOption::Basic`:
+    /// their term frequency is reported as `1`, and phrase queries
+    /// including them do not match. Most of the terms of huge text
+    /// fields are rare, so this shrinks the postings and positions,
+    /// while keeping the information of the terms that matter
+    /// for scoring and phrase queries.
+    ///
+    /// When segments are merged, a term keeps the information
+    /// available in all of the merged segments.
+    pub fn set_freqs_min_doc_freq(mut self, min_doc_freq: u32) -> TextFieldIndexing {
+        self.freqs_min_doc_freq = Some(min_doc_freq);
+        self
+    }
+
+    /// Returns the minimum document frequency of the terms
+    /// recorded with their term frequencies, if set.
+    pub fn freqs_min_doc_freq(&self) -> Option<u32> {
+        self.freqs_min_doc_freq
+    }
 }
 
 /// The field will be untokenized and indexed
@@ -95,8 +357,12 @@ pub const STRING: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("raw"),
         record: IndexRecordOption::Basic,
+        doc_freq_pruning: None,
+        bm25: None,
+        freqs_min_doc_freq: None,
     }),
     stored: false,
+    stored_value_limit: None,
 };
 
 /// The field will be tokenized and indexed
@@ -104,8 +370,12 @@ pub const TEXT: TextOptions = TextOptions {
     indexing: Some(TextFieldIndexing {
         tokenizer: Cow::Borrowed("default"),
         record: IndexRecordOption::WithFreqsAndPositions,
+        doc_freq_pruning: None,
+        bm25: None,
+        freqs_min_doc_freq: None,
     }),
     stored: false,
+    stored_value_limit: None,
 };
 
 /// A stored fields of a document can be retrieved given its `DocId`.
@@ -115,6 +385,7 @@ pub const TEXT: TextOptions = TextOptions {
 pub const STORED: TextOptions = TextOptions {
     indexing: None,
     stored: true,
+    stored_value_limit: None,
 };
 
 impl BitOr for TextOptions {
@@ -124,6 +395,7 @@ impl BitOr for TextOptions {
         let mut res = TextOptions::default();
         res.indexing = self.indexing.or(other.indexing);
         res.stored = self.stored | other.stored;
+        res.stored_value_limit = self.stored_value_limit.or(other.stored_value_limit);
         res
     }
 }
@@ -131,6 +403,7 @@ impl BitOr for TextOptions {
 #[cfg(test)]
 mod tests {
     use schema::*;
+    use serde_json;
 
     #[test]
     fn test_field_options() {
@@ -165,4 +438,30 @@ mod tests {
         assert!(IndexRecordOption::WithFreqsAndPositions > IndexRecordOption::WithFreqs);
         assert!(IndexRecordOption::WithFreqs > IndexRecordOption::Basic);
     }
+
+    #[test]
+    fn test_stored_value_limit() {
+        let limit = |max_len: usize, overflow: StoredValueOverflow| StoredValueLimit {
+            max_len,
+            overflow,
+        };
+        let truncate = limit(4, StoredValueOverflow::Truncate);
+        assert!(!truncate.is_exceeded_by("abcd"));
+        assert!(truncate.is_exceeded_by("abcde"));
+        assert_eq!(truncate.limited_value("abcde"), "abcd");
+        // "é" is 2 bytes long, and cannot be split.
+        assert_eq!(truncate.limited_value("abcé"), "abc");
+        let hash = limit(4, StoredValueOverflow::StoreHash);
+        assert_eq!(hash.limited_value("abcde").len(), 16);
+        assert_ne!(hash.limited_value("abcde"), hash.limited_value("abcdf"));
+
+        let text_options = STORED | TEXT;
+        assert_eq!(text_options.stored_value_limit(), None);
+        let text_options = text_options.set_stored_value_limit(truncate);
+        assert_eq!(text_options.stored_value_limit(), Some(truncate));
+        assert_eq!(
+            serde_json::to_string(&text_options.stored_value_limit()).unwrap(),
+            r#"{"max_len":4,"overflow":"truncate"}"#
+        );
+    }
 }
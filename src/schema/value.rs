@@ -1,11 +1,16 @@
 use std::fmt;
+use std::cmp::Ordering;
+use common::f64_to_u64;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::Visitor;
-use schema::Facet;
+use schema::{DateTime, Facet};
 
 /// Value represents the value of a any field.
 /// It is an enum over all over all of the possible field type.
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+///
+/// Values are totally ordered, `f64` values following
+/// the order of `common::f64_to_u64`.
+#[derive(Debug, Clone)]
 pub enum Value {
     /// The str type is used for any text information.
     Str(String),
@@ -15,8 +20,53 @@ pub enum Value {
     I64(i64),
     /// Hierarchical Facet
     Facet(Facet),
+    /// Date, with a microsecond precision
+    Date(DateTime),
+    /// 64-bits Float `f64`
+    F64(f64),
 }
 
+impl Value {
+    fn type_rank(&self) -> u8 {
+        match *self {
+            Value::Str(_) => 0,
+            Value::U64(_) => 1,
+            Value::I64(_) => 2,
+            Value::Facet(_) => 3,
+            Value::Date(_) => 4,
+            Value::F64(_) => 5,
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (&Value::Str(ref left), &Value::Str(ref right)) => left.cmp(right),
+            (&Value::U64(ref left), &Value::U64(ref right)) => left.cmp(right),
+            (&Value::I64(ref left), &Value::I64(ref right)) => left.cmp(right),
+            (&Value::Facet(ref left), &Value::Facet(ref right)) => left.cmp(right),
+            (&Value::Date(ref left), &Value::Date(ref right)) => left.cmp(right),
+            (&Value::F64(left), &Value::F64(right)) => f64_to_u64(left).cmp(&f64_to_u64(right)),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
 impl Serialize for Value {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -27,6 +77,8 @@ impl Serialize for Value {
             Value::U64(u) => serializer.serialize_u64(u),
             Value::I64(u) => serializer.serialize_i64(u),
             Value::Facet(ref facet) => facet.serialize(serializer),
+            Value::Date(ref date) => serializer.serialize_str(&date.to_string()),
+            Value::F64(val) => serializer.serialize_f64(val),
         }
     }
 }
@@ -45,6 +97,10 @@ impl<'de> Deserialize<'de> for Value {
                 formatter.write_str("a string or u32")
             }
 
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(Value::F64(v))
+            }
+
             fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
                 Ok(Value::U64(v))
             }
@@ -99,6 +155,28 @@ impl Value {
             _ => panic!("This is not a text field."),
         }
     }
+
+    /// Returns the date value, provided the value is of the `Date` type.
+    ///
+    /// # Panics
+    /// If the value is not of type `Date`
+    pub fn date_value(&self) -> DateTime {
+        match *self {
+            Value::Date(ref value) => *value,
+            _ => panic!("This is not a date field."),
+        }
+    }
+
+    /// Returns the f64-value, provided the value is of the `F64` type.
+    ///
+    /// # Panics
+    /// If the value is not of type `F64`
+    pub fn f64_value(&self) -> f64 {
+        match *self {
+            Value::F64(value) => value,
+            _ => panic!("This is not a f64 field."),
+        }
+    }
 }
 
 impl From<String> for Value {
@@ -119,6 +197,18 @@ impl From<i64> for Value {
     }
 }
 
+impl From<f64> for Value {
+    fn from(v: f64) -> Value {
+        Value::F64(v)
+    }
+}
+
+impl From<DateTime> for Value {
+    fn from(date: DateTime) -> Value {
+        Value::Date(date)
+    }
+}
+
 impl<'a> From<&'a str> for Value {
     fn from(s: &'a str) -> Value {
         Value::Str(s.to_string())
@@ -131,16 +221,43 @@ impl<'a> From<Facet> for Value {
     }
 }
 
+pub(crate) use self::binary_serialize::skip_value;
+
 mod binary_serialize {
-    use common::BinarySerializable;
+    use common::{BinarySerializable, VInt};
     use std::io::{self, Read, Write};
     use super::Value;
-    use schema::Facet;
+    use schema::{DateTime, Facet};
 
     const TEXT_CODE: u8 = 0;
     const U64_CODE: u8 = 1;
     const I64_CODE: u8 = 2;
     const HIERARCHICAL_FACET_CODE: u8 = 3;
+    const DATE_CODE: u8 = 4;
+    const F64_CODE: u8 = 5;
+
+    /// Advances the cursor past a serialized `Value`, without decoding it.
+    pub(crate) fn skip_value(cursor: &mut &[u8]) -> io::Result<()> {
+        let type_code = u8::deserialize(cursor)?;
+        let num_bytes = match type_code {
+            TEXT_CODE | HIERARCHICAL_FACET_CODE => VInt::deserialize(cursor)?.val() as usize,
+            U64_CODE | I64_CODE | DATE_CODE | F64_CODE => 8,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("No field type is associated with code {:?}", type_code),
+                ))
+            }
+        };
+        if cursor.len() < num_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Serialized value is truncated",
+            ));
+        }
+        *cursor = &cursor[num_bytes..];
+        Ok(())
+    }
 
     impl BinarySerializable for Value {
         fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()> {
@@ -161,6 +278,14 @@ mod binary_serialize {
                     HIERARCHICAL_FACET_CODE.serialize(writer)?;
                     facet.serialize(writer)
                 }
+                Value::Date(ref date) => {
+                    DATE_CODE.serialize(writer)?;
+                    date.timestamp_micros().serialize(writer)
+                }
+                Value::F64(val) => {
+                    F64_CODE.serialize(writer)?;
+                    val.to_bits().serialize(writer)
+                }
             }
         }
         fn deserialize<R: Read>(reader: &mut R) -> io::Result<Self> {
@@ -179,6 +304,14 @@ mod binary_serialize {
                     Ok(Value::I64(value))
                 }
                 HIERARCHICAL_FACET_CODE => Ok(Value::Facet(Facet::deserialize(reader)?)),
+                DATE_CODE => {
+                    let timestamp_micros = i64::deserialize(reader)?;
+                    Ok(Value::Date(DateTime::from_timestamp_micros(timestamp_micros)))
+                }
+                F64_CODE => {
+                    let bits = u64::deserialize(reader)?;
+                    Ok(Value::F64(f64::from_bits(bits)))
+                }
                 _ => Err(io::Error::new(
                     io::ErrorKind::InvalidData,
                     format!("No field type is associated with code {:?}", type_code),
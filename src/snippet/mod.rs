@@ -0,0 +1,531 @@
+/*!
+Generates the snippets displayed along with the search results,
+with the terms of the query highlighted.
+*/
+
+use DocAddress;
+use Index;
+use Result;
+use Searcher;
+use Term;
+use docset::{DocSet, SkipResult};
+use error::ErrorKind;
+use postings::Postings;
+use query::Query;
+use schema::{Document, Field, FieldType, IndexRecordOption, Value};
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use tokenizer::{BoxedTokenizer, TokenStream};
+
+const DEFAULT_MAX_NUM_CHARS: usize = 150;
+
+/// Candidate fragment of a text, and the ranges of bytes
+/// of its matching tokens.
+#[derive(Debug)]
+struct FragmentCandidate {
+    start_offset: usize,
+    stop_offset: usize,
+    highlighted: Vec<Range<usize>>,
+    matched_terms: BTreeSet<String>,
+}
+
+impl FragmentCandidate {
+    fn new(start_offset: usize) -> FragmentCandidate {
+        FragmentCandidate {
+            start_offset,
+            stop_offset: start_offset,
+            highlighted: Vec::new(),
+            matched_terms: BTreeSet::new(),
+        }
+    }
+
+    fn add_match(&mut self, token_text: &str, range: Range<usize>) {
+        if !self.matched_terms.contains(token_text) {
+            self.matched_terms.insert(token_text.to_string());
+        }
+        self.highlighted.push(range);
+    }
+
+    /// Fragments with more distinct terms come first,
+    /// then the fragments with more matches.
+    fn cmp_relevance(&self, other: &FragmentCandidate) -> Ordering {
+        self.matched_terms
+            .len()
+            .cmp(&other.matched_terms.len())
+            .then_with(|| self.highlighted.len().cmp(&other.highlighted.len()))
+    }
+}
+
+/// Fragment of a text, with the ranges of bytes
+/// of the terms of the query it contains.
+///
+/// The ranges are relative to the fragment.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snippet {
+    fragment: String,
+    highlighted: Vec<Range<usize>>,
+    highlight_prefix: String,
+    highlight_postfix: String,
+}
+
+impl Snippet {
+    /// Returns the text of the fragment, without highlighting.
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+
+    /// Returns the ranges of bytes of the highlighted terms,
+    /// relative to the fragment.
+    pub fn highlighted(&self) -> &[Range<usize>] {
+        &self.highlighted
+    }
+
+    /// Returns true if the fragment does not contain any term of the query.
+    pub fn is_empty(&self) -> bool {
+        self.highlighted.is_empty()
+    }
+
+    /// Returns the fragment as HTML, with the highlighted terms
+    /// surrounded by the highlight markers.
+    ///
+    /// The text of the fragment is escaped, the markers are not.
+    pub fn to_html(&self) -> String {
+        let mut html = String::with_capacity(self.fragment.len());
+        let mut start_from = 0;
+        for range in &self.highlighted {
+            html.push_str(&encode_html(&self.fragment[start_from..range.start]));
+            html.push_str(&self.highlight_prefix);
+            html.push_str(&encode_html(&self.fragment[range.clone()]));
+            html.push_str(&self.highlight_postfix);
+            start_from = range.end;
+        }
+        html.push_str(&encode_html(&self.fragment[start_from..]));
+        html
+    }
+}
+
+fn encode_html(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => encoded.push_str("&amp;"),
+            '<' => encoded.push_str("&lt;"),
+            '>' => encoded.push_str("&gt;"),
+            '"' => encoded.push_str("&quot;"),
+            '\'' => encoded.push_str("&#39;"),
+            _ => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Generates the snippets of a text field, highlighting
+/// the terms of a query.
+///
+/// The stored text is analyzed again with the tokenizer of the field,
+/// and the byte offsets of the tokens matching the terms of the query
+/// are highlighted. Among the fragments of at most `max_num_chars` bytes,
+/// the one containing the most distinct terms of the query is selected.
+/// If no term matches, the snippet is the beginning of the text.
+///
+/// The field has to be a stored and indexed text field.
+/// If it is indexed with `IndexRecordOption::WithFreqsAndPositionsAndOffsets`,
+/// `snippet_from_doc_address` reads the offsets of the matches
+/// from the index rather than analyzing the text again.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate tantivy;
+/// use tantivy::schema::{SchemaBuilder, TEXT, STORED};
+/// use tantivy::{Index, Result};
+/// use tantivy::query::QueryParser;
+/// use tantivy::snippet::SnippetGenerator;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<()> {
+///     let mut schema_builder = SchemaBuilder::new();
+///     let body = schema_builder.add_text_field("body", TEXT | STORED);
+///     let index = Index::create_in_ram(schema_builder.build());
+///     let doc = doc!(body => "The Diary of Muadib, and of his dairy cow.");
+///
+///     let query_parser = QueryParser::for_index(&index, vec![body]);
+///     let query = query_parser.parse_query("diary cow").unwrap();
+///     let snippet_generator = SnippetGenerator::create(&index, &*query, body)?;
+///     let snippet = snippet_generator.snippet_from_doc(&doc);
+///     assert_eq!(
+///         snippet.to_html(),
+///         "The <b>Diary</b> of Muadib, and of his dairy <b>cow</b>"
+///     );
+///     Ok(())
+/// }
+/// ```
+pub struct SnippetGenerator {
+    terms: BTreeSet<String>,
+    tokenizer: Box<BoxedTokenizer>,
+    field: Field,
+    has_offsets: bool,
+    max_num_chars: usize,
+    highlight_prefix: String,
+    highlight_postfix: String,
+}
+
+impl SnippetGenerator {
+    /// Creates a snippet generator highlighting the terms of `query`
+    /// in `field`.
+    ///
+    /// See `Query::query_terms`.
+    pub fn create(index: &Index, query: &Query, field: Field) -> Result<SnippetGenerator> {
+        let mut term_set = BTreeSet::new();
+        query.query_terms(&mut term_set);
+        let terms: Vec<Term> = term_set.into_iter().collect();
+        SnippetGenerator::new(index, field, &terms)
+    }
+
+    /// Creates a snippet generator highlighting `terms` in `field`.
+    ///
+    /// The terms of other fields are ignored.
+    pub fn new(index: &Index, field: Field, terms: &[Term]) -> Result<SnippetGenerator> {
+        let schema = index.schema();
+        let field_entry = schema.get_field_entry(field);
+        let (tokenizer_name, has_offsets) = match *field_entry.field_type() {
+            FieldType::Str(ref text_options) => {
+                text_options.get_indexing_options().map(|indexing_options| {
+                    (
+                        indexing_options.tokenizer().to_string(),
+                        indexing_options.index_option().has_offsets(),
+                    )
+                })
+            }
+            _ => None,
+        }.ok_or_else(|| {
+            ErrorKind::SchemaError(format!(
+                "Field {:?} is not an indexed text field, snippets cannot be generated.",
+                field_entry.name()
+            ))
+        })?;
+        let tokenizer = index.tokenizers().get(&tokenizer_name).ok_or_else(|| {
+            ErrorKind::InvalidArgument(format!("Unknown tokenizer {:?}", tokenizer_name))
+        })?;
+        let terms = terms
+            .iter()
+            .filter(|term| term.field() == field)
+            .map(|term| term.text().to_string())
+            .collect();
+        Ok(SnippetGenerator {
+            terms,
+            tokenizer,
+            field,
+            has_offsets,
+            max_num_chars: DEFAULT_MAX_NUM_CHARS,
+            highlight_prefix: "<b>".to_string(),
+            highlight_postfix: "</b>".to_string(),
+        })
+    }
+
+    /// Sets the maximum length of the snippets, in bytes. Defaults to 150.
+    ///
+    /// Snippets never split a token, and may therefore be
+    /// shorter than this length.
+    pub fn set_max_num_chars(&mut self, max_num_chars: usize) {
+        self.max_num_chars = max_num_chars;
+    }
+
+    /// Sets the markers surrounding the highlighted terms
+    /// in `Snippet::to_html`. Defaults to `<b>` and `</b>`.
+    pub fn set_highlight_markers<T: ToString>(&mut self, prefix: T, postfix: T) {
+        self.highlight_prefix = prefix.to_string();
+        self.highlight_postfix = postfix.to_string();
+    }
+
+    /// Generates the snippet of the stored values of the field of `doc`.
+    ///
+    /// If the field has several values, the snippet
+    /// is generated from the most relevant one.
+    pub fn snippet_from_doc(&self, doc: &Document) -> Snippet {
+        self.snippet_from_values(doc, |_, text| self.best_fragment(text))
+    }
+
+    /// Generates the snippet of the stored values of the field
+    /// of the document at `doc_address`.
+    ///
+    /// If the field is indexed with `IndexRecordOption::WithFreqsAndPositionsAndOffsets`,
+    /// the matches are the offsets recorded in the postings of the terms,
+    /// so that they are exactly the tokens matched at indexing time.
+    /// Otherwise, this is equivalent to `snippet_from_doc`.
+    pub fn snippet_from_doc_address(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
+    ) -> Result<Snippet> {
+        let doc = searcher.doc(&doc_address)?;
+        if !self.has_offsets {
+            return Ok(self.snippet_from_doc(&doc));
+        }
+        let inverted_index = searcher
+            .segment_reader(doc_address.segment_ord())
+            .inverted_index(self.field);
+        let mut matches: Vec<(Range<usize>, &str)> = Vec::new();
+        for term_text in &self.terms {
+            let term = Term::from_field_text(self.field, term_text);
+            let postings_opt = inverted_index
+                .read_postings(&term, IndexRecordOption::WithFreqsAndPositionsAndOffsets);
+            if let Some(mut postings) = postings_opt {
+                if postings.skip_next(doc_address.doc()) == SkipResult::Reached {
+                    for &(offset_from, offset_to) in postings.offsets() {
+                        let range = offset_from as usize..offset_to as usize;
+                        matches.push((range, term_text.as_str()));
+                    }
+                }
+            }
+        }
+        matches.sort_by_key(|&(ref range, _)| range.start);
+        // the offsets are relative to the concatenation of the values of the field.
+        Ok(self.snippet_from_values(&doc, |value_start, text| {
+            let value_matches: Vec<(Range<usize>, &str)> = matches
+                .iter()
+                .filter(|&&(ref range, _)| {
+                    range.start >= value_start && range.end <= value_start + text.len()
+                })
+                .map(|&(ref range, term_text)| {
+                    ((range.start - value_start)..(range.end - value_start), term_text)
+                })
+                .filter(|&(ref range, _)| {
+                    text.is_char_boundary(range.start) && text.is_char_boundary(range.end)
+                })
+                .collect();
+            if value_matches.is_empty() {
+                self.best_fragment(text)
+            } else {
+                self.best_fragment_from_matches(&value_matches)
+            }
+        }))
+    }
+
+    /// Generates the snippet from the most relevant value of the field,
+    /// given the best fragment of each value.
+    ///
+    /// `best_fragment` is called with the offset of the value within
+    /// the concatenation of the values, and the text of the value.
+    fn snippet_from_values<F>(&self, doc: &Document, mut best_fragment: F) -> Snippet
+    where
+        F: FnMut(usize, &str) -> FragmentCandidate,
+    {
+        let mut best: Option<(&str, FragmentCandidate)> = None;
+        let mut value_start = 0;
+        for value in doc.get_all(self.field) {
+            if let Value::Str(ref text) = *value {
+                let candidate = best_fragment(value_start, text);
+                value_start += text.len();
+                let is_better = match best {
+                    Some((_, ref best_candidate)) => {
+                        candidate.cmp_relevance(best_candidate) == Ordering::Greater
+                    }
+                    None => true,
+                };
+                if is_better {
+                    best = Some((text.as_str(), candidate));
+                }
+            }
+        }
+        match best {
+            Some((text, candidate)) => self.to_snippet(text, candidate),
+            None => self.to_snippet("", FragmentCandidate::new(0)),
+        }
+    }
+
+    /// Generates the snippet of `text`.
+    pub fn snippet(&self, text: &str) -> Snippet {
+        let candidate = self.best_fragment(text);
+        self.to_snippet(text, candidate)
+    }
+
+    /// Returns the most relevant fragment of `text`, the first
+    /// one in case of a tie. If no term matches, the fragment
+    /// is the beginning of the text.
+    fn best_fragment(&self, text: &str) -> FragmentCandidate {
+        let mut candidates: Vec<FragmentCandidate> = Vec::new();
+        let mut token_stream = self.tokenizer.token_stream(text);
+        while let Some(token) = token_stream.next() {
+            let starts_new_fragment = candidates
+                .last()
+                .map(|candidate| token.offset_to - candidate.start_offset > self.max_num_chars)
+                .unwrap_or(true);
+            if starts_new_fragment {
+                candidates.push(FragmentCandidate::new(token.offset_from));
+            }
+            let candidate = candidates
+                .last_mut()
+                .expect("A candidate was pushed. This should never happen.");
+            candidate.stop_offset = token.offset_to;
+            if self.terms.contains(&token.text) {
+                candidate.add_match(&token.text, token.offset_from..token.offset_to);
+            }
+        }
+        let mut best_ord = 0;
+        for (ord, candidate) in candidates.iter().enumerate() {
+            if candidate.cmp_relevance(&candidates[best_ord]) == Ordering::Greater {
+                best_ord = ord;
+            }
+        }
+        if candidates.is_empty() {
+            FragmentCandidate::new(0)
+        } else {
+            candidates.swap_remove(best_ord)
+        }
+    }
+
+    /// Returns the most relevant fragment spanning `matches`,
+    /// the first one in case of a tie.
+    ///
+    /// `matches` are the ranges of bytes of the matching tokens,
+    /// with their term, sorted by offset.
+    fn best_fragment_from_matches(&self, matches: &[(Range<usize>, &str)]) -> FragmentCandidate {
+        let mut best: Option<FragmentCandidate> = None;
+        for (start_ord, &(ref start_range, _)) in matches.iter().enumerate() {
+            let mut candidate = FragmentCandidate::new(start_range.start);
+            for &(ref range, term_text) in &matches[start_ord..] {
+                if range.end - start_range.start > self.max_num_chars {
+                    break;
+                }
+                candidate.stop_offset = range.end;
+                candidate.add_match(term_text, range.clone());
+            }
+            let is_better = match best {
+                Some(ref best_candidate) => {
+                    candidate.cmp_relevance(best_candidate) == Ordering::Greater
+                }
+                None => true,
+            };
+            if is_better {
+                best = Some(candidate);
+            }
+        }
+        best.unwrap_or_else(|| FragmentCandidate::new(0))
+    }
+
+    fn to_snippet(&self, text: &str, candidate: FragmentCandidate) -> Snippet {
+        let start_offset = candidate.start_offset;
+        Snippet {
+            fragment: text[start_offset..candidate.stop_offset].to_string(),
+            highlighted: candidate
+                .highlighted
+                .into_iter()
+                .map(|range| (range.start - start_offset)..(range.end - start_offset))
+                .collect(),
+            highlight_prefix: self.highlight_prefix.clone(),
+            highlight_postfix: self.highlight_postfix.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::SnippetGenerator;
+    use DocAddress;
+    use Index;
+    use Term;
+    use query::QueryParser;
+    use schema::{IndexRecordOption, SchemaBuilder, TextFieldIndexing, TextOptions, STORED,
+                 STRING, TEXT};
+
+    const RUST_TEXT: &'static str = "Rust is a systems programming language sponsored by Mozilla. \
+        It is designed to be a safe, concurrent, practical language. \
+        Rust is syntactically similar to C++, but its designers intend it \
+        to provide better memory safety while maintaining performance.";
+
+    #[test]
+    fn test_snippet() {
+        let mut schema_builder = SchemaBuilder::default();
+        let body = schema_builder.add_text_field("body", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let terms = vec![
+            Term::from_field_text(body, "rust"),
+            Term::from_field_text(body, "language"),
+        ];
+        let mut snippet_generator = SnippetGenerator::new(&index, body, &terms).unwrap();
+        snippet_generator.set_max_num_chars(60);
+        let snippet = snippet_generator.snippet(RUST_TEXT);
+        assert_eq!(
+            snippet.fragment(),
+            "Rust is a systems programming language sponsored by Mozilla"
+        );
+        assert_eq!(snippet.highlighted(), &[0..4, 30..38]);
+        assert_eq!(
+            snippet.to_html(),
+            "<b>Rust</b> is a systems programming <b>language</b> sponsored by Mozilla"
+        );
+        snippet_generator.set_highlight_markers("[", "]");
+        let snippet = snippet_generator.snippet("Is Rust <safe> & fast?");
+        assert_eq!(snippet.to_html(), "Is [Rust] &lt;safe&gt; &amp; fast");
+    }
+
+    #[test]
+    fn test_snippet_best_fragment() {
+        let mut schema_builder = SchemaBuilder::default();
+        let body = schema_builder.add_text_field("body", TEXT | STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query_parser = QueryParser::for_index(&index, vec![body]);
+        let query = query_parser.parse_query("memory safety -mozilla").unwrap();
+        let mut snippet_generator = SnippetGenerator::create(&index, &*query, body).unwrap();
+        snippet_generator.set_max_num_chars(40);
+        let snippet = snippet_generator.snippet(RUST_TEXT);
+        assert_eq!(snippet.fragment(), "provide better memory safety while");
+        assert_eq!(snippet.highlighted().len(), 2);
+
+        // no match: the snippet is the beginning of the text.
+        let snippet = snippet_generator.snippet("Concurrency without data races.");
+        assert!(snippet.is_empty());
+        assert_eq!(snippet.fragment(), "Concurrency without data races");
+
+        let doc = doc!(body => "Nothing to see here", body => "Memory safety");
+        let snippet = snippet_generator.snippet_from_doc(&doc);
+        assert_eq!(snippet.to_html(), "<b>Memory</b> <b>safety</b>");
+    }
+
+    #[test]
+    fn test_snippet_from_offsets() {
+        let mut schema_builder = SchemaBuilder::default();
+        let text_indexing = TextFieldIndexing::default()
+            .set_tokenizer("en_stem")
+            .set_index_option(IndexRecordOption::WithFreqsAndPositionsAndOffsets);
+        let text_options = TextOptions::default()
+            .set_indexing_options(text_indexing)
+            .set_stored();
+        let body = schema_builder.add_text_field("body", text_options);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000).unwrap();
+            index_writer.add_document(doc!(body => "Nothing to see", body => RUST_TEXT));
+            index_writer.commit().unwrap();
+        }
+        index.load_searchers().unwrap();
+        let searcher = index.searcher();
+        let query_parser = QueryParser::for_index(&index, vec![body]);
+        let query = query_parser.parse_query("memory safety").unwrap();
+        let mut snippet_generator = SnippetGenerator::create(&index, &*query, body).unwrap();
+        snippet_generator.set_max_num_chars(40);
+        let snippet = snippet_generator
+            .snippet_from_doc_address(&*searcher, DocAddress(0, 0))
+            .unwrap();
+        assert_eq!(snippet.to_html(), "<b>memory</b> <b>safety</b>");
+
+        let query = query_parser.parse_query("mozilla").unwrap();
+        let snippet_generator = SnippetGenerator::create(&index, &*query, body).unwrap();
+        let snippet = snippet_generator
+            .snippet_from_doc_address(&*searcher, DocAddress(0, 0))
+            .unwrap();
+        assert_eq!(snippet.to_html(), "<b>Mozilla</b>");
+    }
+
+    #[test]
+    fn test_snippet_not_indexed_field() {
+        let mut schema_builder = SchemaBuilder::default();
+        let body = schema_builder.add_text_field("body", STORED);
+        let title = schema_builder.add_text_field("title", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        assert!(SnippetGenerator::new(&index, body, &[]).is_err());
+        assert!(SnippetGenerator::new(&index, title, &[]).is_ok());
+    }
+}
@@ -0,0 +1,109 @@
+use directory::CacheCounters;
+use std::collections::HashMap;
+use std::sync::Arc;
+use SegmentLocalId;
+
+/// Default number of decompressed blocks kept by the cache of a `Searcher`.
+pub(crate) const DEFAULT_NUM_CACHED_BLOCKS: usize = 16;
+
+/// Identifies a block by the ordinal of its segment within the searcher,
+/// and its offset within the doc store.
+type BlockKey = (SegmentLocalId, usize);
+
+struct CachedBlock {
+    data: Arc<Vec<u8>>,
+    last_access: u64,
+}
+
+/// LRU cache of decompressed doc store blocks, bounded
+/// by a number of blocks.
+///
+/// Fetching several documents of the same block, typically the
+/// top hits of a search, then only decompresses the block once.
+pub(crate) struct BlockCache {
+    counters: CacheCounters,
+    capacity: usize,
+    clock: u64,
+    blocks: HashMap<BlockKey, CachedBlock>,
+}
+
+impl BlockCache {
+    /// Creates a cache holding at most `capacity` blocks.
+    pub fn with_capacity(capacity: usize) -> BlockCache {
+        BlockCache {
+            counters: CacheCounters::default(),
+            capacity,
+            clock: 0,
+            blocks: HashMap::new(),
+        }
+    }
+
+    pub fn get(&mut self, key: BlockKey) -> Option<Arc<Vec<u8>>> {
+        self.clock += 1;
+        let clock = self.clock;
+        if let Some(block) = self.blocks.get_mut(&key) {
+            block.last_access = clock;
+            self.counters.hit += 1;
+            return Some(Arc::clone(&block.data));
+        }
+        self.counters.miss += 1;
+        None
+    }
+
+    pub fn insert(&mut self, key: BlockKey, data: Arc<Vec<u8>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.blocks.contains_key(&key) {
+            while self.blocks.len() >= self.capacity {
+                self.evict_least_recently_used();
+            }
+        }
+        let last_access = self.clock;
+        self.blocks.insert(key, CachedBlock { data, last_access });
+    }
+
+    pub fn counters(&self) -> CacheCounters {
+        self.counters.clone()
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let lru_key: Option<BlockKey> = self.blocks
+            .iter()
+            .min_by_key(|&(_, block)| block.last_access)
+            .map(|(key, _)| *key);
+        if let Some(key) = lru_key {
+            self.blocks.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::BlockCache;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_block_cache_lru() {
+        let mut block_cache = BlockCache::with_capacity(2);
+        block_cache.insert((0, 0), Arc::new(vec![1u8]));
+        block_cache.insert((0, 10), Arc::new(vec![2u8]));
+        assert_eq!(*block_cache.get((0, 0)).unwrap(), vec![1u8]);
+        // (0, 10) is the least recently used block.
+        block_cache.insert((1, 0), Arc::new(vec![3u8]));
+        assert!(block_cache.get((0, 10)).is_none());
+        assert_eq!(*block_cache.get((0, 0)).unwrap(), vec![1u8]);
+        assert_eq!(*block_cache.get((1, 0)).unwrap(), vec![3u8]);
+        let counters = block_cache.counters();
+        assert_eq!(counters.hit, 3);
+        assert_eq!(counters.miss, 1);
+    }
+
+    #[test]
+    fn test_block_cache_no_capacity() {
+        let mut block_cache = BlockCache::with_capacity(0);
+        block_cache.insert((0, 0), Arc::new(vec![1u8]));
+        assert!(block_cache.get((0, 0)).is_none());
+    }
+}
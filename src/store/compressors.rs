@@ -0,0 +1,220 @@
+use std::io::{self, Read, Write};
+use lz4;
+#[cfg(feature = "zstd-compression")]
+use zstd;
+
+/// Compression level used when compressing blocks with `zstd`.
+#[cfg(feature = "zstd-compression")]
+const ZSTD_COMPRESSION_LEVEL: i32 = 3;
+
+/// Codec used to compress the blocks of the doc store.
+///
+/// The compressor is recorded in the footer of each store file,
+/// so that readers always pick the right decoder, regardless of
+/// the current settings of the index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Compressor {
+    /// Blocks are stored uncompressed.
+    #[serde(rename = "none")]
+    None,
+    /// Blocks are compressed using LZ4.
+    /// This is the default, as it offers very fast decompression.
+    #[serde(rename = "lz4")]
+    Lz4,
+    /// Blocks are compressed using zstd, which offers
+    /// a better compression ratio at the cost of speed.
+    ///
+    /// Requires the `zstd-compression` feature.
+    #[serde(rename = "zstd")]
+    Zstd,
+    /// Blocks are compressed using zstd, with a dictionary trained
+    /// on a sample of the documents of each segment.
+    ///
+    /// The dictionary captures the content shared by the documents,
+    /// which shrinks the doc store of many small and similar documents,
+    /// such as log lines, significantly.
+    ///
+    /// Requires the `zstd-compression` feature.
+    #[serde(rename = "zstd_dictionary")]
+    ZstdDictionary,
+}
+
+impl Default for Compressor {
+    fn default() -> Compressor {
+        Compressor::Lz4
+    }
+}
+
+impl Compressor {
+    /// Returns the id used to identify the compressor
+    /// in the store footer.
+    pub(crate) fn id(&self) -> u8 {
+        match *self {
+            Compressor::None => 0,
+            Compressor::Lz4 => 1,
+            Compressor::Zstd => 2,
+            Compressor::ZstdDictionary => 3,
+        }
+    }
+
+    /// Returns true if this build of tantivy supports the compressor.
+    ///
+    /// The zstd based compressors require the `zstd-compression` feature.
+    pub fn is_supported(&self) -> bool {
+        match *self {
+            Compressor::None | Compressor::Lz4 => true,
+            Compressor::Zstd | Compressor::ZstdDictionary => cfg!(feature = "zstd-compression"),
+        }
+    }
+
+    /// Returns true if the compressor relies on a dictionary
+    /// trained for each segment.
+    pub(crate) fn uses_dictionary(&self) -> bool {
+        *self == Compressor::ZstdDictionary
+    }
+
+    /// Returns the compressor associated to an id.
+    pub(crate) fn from_id(id: u8) -> io::Result<Compressor> {
+        match id {
+            0 => Ok(Compressor::None),
+            1 => Ok(Compressor::Lz4),
+            2 => Ok(Compressor::Zstd),
+            3 => Ok(Compressor::ZstdDictionary),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown doc store compressor id {}", id),
+            )),
+        }
+    }
+
+    /// Compresses `uncompressed` and appends the result to `compressed`.
+    ///
+    /// `dictionary` is only used by the compressors relying on a dictionary,
+    /// and may be empty.
+    pub(crate) fn compress(
+        &self,
+        uncompressed: &[u8],
+        dictionary: &[u8],
+        compressed: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        match *self {
+            Compressor::None => {
+                compressed.extend_from_slice(uncompressed);
+                Ok(())
+            }
+            Compressor::Lz4 => {
+                let mut encoder = lz4::EncoderBuilder::new().build(compressed)?;
+                encoder.write_all(uncompressed)?;
+                let (_, encoder_result) = encoder.finish();
+                encoder_result
+            }
+            Compressor::Zstd => zstd_compress(uncompressed, compressed),
+            Compressor::ZstdDictionary => {
+                zstd_compress_with_dictionary(uncompressed, dictionary, compressed)
+            }
+        }
+    }
+
+    /// Decompresses `compressed` and appends the result to `decompressed`.
+    ///
+    /// `dictionary` has to be the dictionary used for compression.
+    pub(crate) fn decompress(
+        &self,
+        compressed: &[u8],
+        dictionary: &[u8],
+        decompressed: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        match *self {
+            Compressor::None => {
+                decompressed.extend_from_slice(compressed);
+                Ok(())
+            }
+            Compressor::Lz4 => {
+                let mut lz4_decoder = lz4::Decoder::new(compressed)?;
+                lz4_decoder.read_to_end(decompressed).map(|_| ())
+            }
+            Compressor::Zstd => zstd_decompress(compressed, decompressed),
+            Compressor::ZstdDictionary => {
+                zstd_decompress_with_dictionary(compressed, dictionary, decompressed)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "zstd-compression")]
+fn zstd_compress(uncompressed: &[u8], compressed: &mut Vec<u8>) -> io::Result<()> {
+    zstd::stream::copy_encode(uncompressed, compressed, ZSTD_COMPRESSION_LEVEL)
+}
+
+#[cfg(feature = "zstd-compression")]
+fn zstd_decompress(compressed: &[u8], decompressed: &mut Vec<u8>) -> io::Result<()> {
+    zstd::stream::copy_decode(compressed, decompressed)
+}
+
+#[cfg(feature = "zstd-compression")]
+fn zstd_compress_with_dictionary(
+    uncompressed: &[u8],
+    dictionary: &[u8],
+    compressed: &mut Vec<u8>,
+) -> io::Result<()> {
+    if dictionary.is_empty() {
+        return zstd_compress(uncompressed, compressed);
+    }
+    let mut encoder =
+        zstd::stream::Encoder::with_dictionary(compressed, ZSTD_COMPRESSION_LEVEL, dictionary)?;
+    encoder.write_all(uncompressed)?;
+    encoder.finish().map(|_| ())
+}
+
+#[cfg(feature = "zstd-compression")]
+fn zstd_decompress_with_dictionary(
+    compressed: &[u8],
+    dictionary: &[u8],
+    decompressed: &mut Vec<u8>,
+) -> io::Result<()> {
+    if dictionary.is_empty() {
+        return zstd_decompress(compressed, decompressed);
+    }
+    let mut decoder = zstd::stream::Decoder::with_dictionary(compressed, dictionary)?;
+    decoder.read_to_end(decompressed).map(|_| ())
+}
+
+/// Trains a zstd dictionary of at most `max_size` bytes on `samples`.
+#[cfg(feature = "zstd-compression")]
+pub(crate) fn train_dictionary(samples: &[Vec<u8>], max_size: usize) -> io::Result<Vec<u8>> {
+    zstd::dict::from_samples(samples, max_size)
+}
+
+#[cfg(not(feature = "zstd-compression"))]
+fn zstd_unavailable() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        "The zstd compressor requires tantivy to be compiled with the \
+         `zstd-compression` feature.",
+    )
+}
+
+#[cfg(not(feature = "zstd-compression"))]
+fn zstd_compress(_: &[u8], _: &mut Vec<u8>) -> io::Result<()> {
+    Err(zstd_unavailable())
+}
+
+#[cfg(not(feature = "zstd-compression"))]
+fn zstd_decompress(_: &[u8], _: &mut Vec<u8>) -> io::Result<()> {
+    Err(zstd_unavailable())
+}
+
+#[cfg(not(feature = "zstd-compression"))]
+fn zstd_compress_with_dictionary(_: &[u8], _: &[u8], _: &mut Vec<u8>) -> io::Result<()> {
+    Err(zstd_unavailable())
+}
+
+#[cfg(not(feature = "zstd-compression"))]
+fn zstd_decompress_with_dictionary(_: &[u8], _: &[u8], _: &mut Vec<u8>) -> io::Result<()> {
+    Err(zstd_unavailable())
+}
+
+#[cfg(not(feature = "zstd-compression"))]
+pub(crate) fn train_dictionary(_: &[Vec<u8>], _: usize) -> io::Result<Vec<u8>> {
+    Err(zstd_unavailable())
+}
@@ -5,17 +5,25 @@ A field needs to be marked as stored in the schema in
 order to be handled in the `Store`.
 
 Internally, documents (or rather their stored fields) are serialized to a buffer.
-When the buffer exceeds 16K, the buffer is compressed using `LZ4`
+When the buffer exceeds 16K, the buffer is compressed (using `LZ4` by default)
 and the resulting block is written to disk.
 
+Both the compressor and the block size can be configured
+through the `IndexSettings`.
+With `Compressor::ZstdDictionary`, a dictionary is trained for each
+segment on its first documents, and stored along with the blocks.
+
 One can then request for a specific `DocId`.
 A skip list helps navigating to the right block,
 decompresses it entirely and returns the document within it.
 
 If the last document requested was in the same block,
 the reader is smart enough to avoid decompressing
-the block a second time, but their is no real
-*uncompressed block* cache.
+the block a second time.
+On top of this, each `Searcher` keeps a small LRU cache of
+decompressed blocks, so that fetching several documents
+of the same block, as the top hits of a search often are,
+only decompresses it once.
 
 A typical use case for the store is, once
 the search result page has been computed, returning
@@ -35,8 +43,13 @@ and should rely on either
 
 mod reader;
 mod writer;
+mod compressors;
+mod block_cache;
 pub use self::reader::StoreReader;
 pub use self::writer::StoreWriter;
+pub use self::compressors::Compressor;
+pub(crate) use self::writer::DEFAULT_BLOCK_SIZE;
+pub(crate) use self::block_cache::{BlockCache, DEFAULT_NUM_CACHED_BLOCKS};
 
 #[cfg(test)]
 mod tests {
@@ -51,6 +64,14 @@ mod tests {
     use directory::{Directory, MmapDirectory, RAMDirectory, WritePtr};
 
     fn write_lorem_ipsum_store(writer: WritePtr, num_docs: usize) -> Schema {
+        let store_writer = StoreWriter::new(writer);
+        write_lorem_ipsum_store_with_writer(store_writer, num_docs)
+    }
+
+    fn write_lorem_ipsum_store_with_writer(
+        mut store_writer: StoreWriter,
+        num_docs: usize,
+    ) -> Schema {
         let mut schema_builder = SchemaBuilder::default();
         let field_body = schema_builder.add_text_field("body", TextOptions::default().set_stored());
         let field_title =
@@ -67,7 +88,6 @@ mod tests {
              laborum.",
         );
         {
-            let mut store_writer = StoreWriter::new(writer);
             for i in 0..num_docs {
                 let mut fields: Vec<FieldValue> = Vec::new();
                 {
@@ -96,7 +116,32 @@ mod tests {
         let schema = write_lorem_ipsum_store(store_file, 1_000);
         let field_title = schema.get_field("title").unwrap();
         let store_source = directory.open_read(path).unwrap();
-        let store = StoreReader::from_source(store_source);
+        let store = StoreReader::from_source(store_source).unwrap();
+        for i in 0..1_000 {
+            assert_eq!(
+                *store.get(i).unwrap().get_first(field_title).unwrap().text(),
+                format!("Doc {}", i)
+            );
+        }
+    }
+
+    #[test]
+    fn test_store_legacy_footer() {
+        let path = Path::new("store");
+        let legacy_path = Path::new("legacy_store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        let schema = write_lorem_ipsum_store(store_file, 1_000);
+        let field_title = schema.get_field("title").unwrap();
+        // a legacy store is an LZ4 store whose footer
+        // does not end with the compressor id.
+        let mut store_data = directory.atomic_read(path).unwrap();
+        assert_eq!(store_data.pop(), Some(Compressor::Lz4.id()));
+        directory.atomic_write(legacy_path, &store_data).unwrap();
+        let store_source = directory.open_read(legacy_path).unwrap();
+        let store = StoreReader::from_legacy_source(store_source).unwrap();
+        assert_eq!(store.compressor(), Compressor::Lz4);
+        assert_eq!(store.max_doc(), 1_000);
         for i in 0..1_000 {
             assert_eq!(
                 *store.get(i).unwrap().get_first(field_title).unwrap().text(),
@@ -105,6 +150,82 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "zstd-compression")]
+    #[test]
+    fn test_store_zstd_dictionary() {
+        let path = Path::new("store");
+        for &num_docs in &[10, 5_000] {
+            let mut directory = RAMDirectory::create();
+            let store_file = directory.open_write(path).unwrap();
+            let store_writer = StoreWriter::with_compressor(
+                store_file,
+                Compressor::ZstdDictionary,
+                DEFAULT_BLOCK_SIZE,
+            );
+            let schema = write_lorem_ipsum_store_with_writer(store_writer, num_docs);
+            let field_title = schema.get_field("title").unwrap();
+            let store_source = directory.open_read(path).unwrap();
+            let store = StoreReader::from_source(store_source).unwrap();
+            assert_eq!(store.compressor(), Compressor::ZstdDictionary);
+            assert_eq!(store.max_doc(), num_docs as u32);
+            if num_docs > 10 {
+                assert!(!store.dictionary().is_empty());
+            }
+            for i in 0..num_docs as u32 {
+                assert_eq!(
+                    *store.get(i).unwrap().get_first(field_title).unwrap().text(),
+                    format!("Doc {}", i)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_store_uncompressed_small_blocks() {
+        let path = Path::new("store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        let store_writer = StoreWriter::with_compressor(store_file, Compressor::None, 1_000);
+        let schema = write_lorem_ipsum_store_with_writer(store_writer, 100);
+        let field_title = schema.get_field("title").unwrap();
+        let store_source = directory.open_read(path).unwrap();
+        let store = StoreReader::from_source(store_source).unwrap();
+        assert_eq!(store.compressor(), Compressor::None);
+        assert_eq!(store.max_doc(), 100);
+        for i in 0..100 {
+            assert_eq!(
+                *store.get(i).unwrap().get_first(field_title).unwrap().text(),
+                format!("Doc {}", i)
+            );
+        }
+    }
+
+    #[test]
+    fn test_store_get_fields() {
+        let path = Path::new("store");
+        let mut directory = RAMDirectory::create();
+        let store_file = directory.open_write(path).unwrap();
+        let schema = write_lorem_ipsum_store(store_file, 100);
+        let field_body = schema.get_field("body").unwrap();
+        let field_title = schema.get_field("title").unwrap();
+        let store_source = directory.open_read(path).unwrap();
+        let store = StoreReader::from_source(store_source).unwrap();
+        for i in 0..100 {
+            let doc = store.get_fields(i, &[field_title]).unwrap();
+            assert_eq!(doc.len(), 1);
+            assert!(doc.get_first(field_body).is_none());
+            assert_eq!(
+                *doc.get_first(field_title).unwrap().text(),
+                format!("Doc {}", i)
+            );
+        }
+        assert_eq!(store.get_fields(3, &[]).unwrap().len(), 0);
+        assert_eq!(
+            store.get_fields(3, &[field_body, field_title]).unwrap(),
+            store.get(3).unwrap()
+        );
+    }
+
     #[bench]
     fn bench_store_encode(b: &mut Bencher) {
         let mut directory = MmapDirectory::create_from_tempdir().unwrap();
@@ -121,7 +242,7 @@ mod tests {
         let path = Path::new("store");
         write_lorem_ipsum_store(directory.open_write(path).unwrap(), 1_000);
         let store_source = directory.open_read(path).unwrap();
-        let store = StoreReader::from_source(store_source);
+        let store = StoreReader::from_source(store_source).unwrap();
         b.iter(|| {
             store.get(12).unwrap();
         });
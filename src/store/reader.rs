@@ -3,17 +3,22 @@ use Result;
 use directory::ReadOnlySource;
 use std::cell::RefCell;
 use DocId;
-use schema::Document;
+use schema::{Document, Field};
 use common::BinarySerializable;
 use std::mem::size_of;
-use std::io::{self, Read};
+use std::io;
 use common::VInt;
 use datastruct::SkipList;
-use lz4;
+use super::Compressor;
+use super::block_cache::BlockCache;
+use std::sync::{Arc, Mutex};
+use SegmentLocalId;
 
 /// Reads document off tantivy's [`Store`](./index.html)
 #[derive(Clone)]
 pub struct StoreReader {
+    compressor: Compressor,
+    dictionary: ReadOnlySource,
     data: ReadOnlySource,
     offset_index_source: ReadOnlySource,
     current_block_offset: RefCell<usize>,
@@ -23,15 +28,53 @@ pub struct StoreReader {
 
 impl StoreReader {
     /// Opens a store reader
-    pub fn from_source(data: ReadOnlySource) -> StoreReader {
-        let (data_source, offset_index_source, max_doc) = split_source(data);
-        StoreReader {
+    pub fn from_source(data: ReadOnlySource) -> io::Result<StoreReader> {
+        StoreReader::open(data, false)
+    }
+
+    /// Opens a store reader over a store written before the compressor
+    /// id was recorded in its footer.
+    ///
+    /// The footer of such a store only holds the offset of the skip list
+    /// index and the number of documents, and its blocks are compressed using LZ4.
+    pub fn from_legacy_source(data: ReadOnlySource) -> io::Result<StoreReader> {
+        StoreReader::open(data, true)
+    }
+
+    fn open(data: ReadOnlySource, legacy_footer: bool) -> io::Result<StoreReader> {
+        let (data_source, offset_index_source, dictionary, max_doc, compressor) =
+            split_source(data, legacy_footer)?;
+        Ok(StoreReader {
+            compressor,
+            dictionary,
             data: data_source,
             offset_index_source,
             current_block_offset: RefCell::new(usize::max_value()),
             current_block: RefCell::new(Vec::new()),
             max_doc,
-        }
+        })
+    }
+
+    /// Returns the compressor used to compress the blocks of this store.
+    pub fn compressor(&self) -> Compressor {
+        self.compressor
+    }
+
+    /// Returns the number of documents in the store.
+    pub fn max_doc(&self) -> DocId {
+        self.max_doc
+    }
+
+    /// Returns the number of bytes used by the store.
+    pub fn num_bytes(&self) -> usize {
+        self.data.len() + self.offset_index_source.len() + self.dictionary.len()
+    }
+
+    /// Returns the dictionary used to compress the blocks of this store.
+    ///
+    /// The dictionary is empty if the compressor does not rely on a dictionary.
+    pub fn dictionary(&self) -> &[u8] {
+        self.dictionary.as_slice()
     }
 
     pub(crate) fn block_index(&self) -> SkipList<u64> {
@@ -56,14 +99,18 @@ impl StoreReader {
         &buffer[..block_len]
     }
 
+    fn decompress_block(&self, block_offset: usize, block: &mut Vec<u8>) -> io::Result<()> {
+        let compressed_block = self.compressed_block(block_offset);
+        self.compressor
+            .decompress(compressed_block, self.dictionary.as_slice(), block)
+    }
+
     fn read_block(&self, block_offset: usize) -> io::Result<()> {
         if block_offset != *self.current_block_offset.borrow() {
             let mut current_block_mut = self.current_block.borrow_mut();
             current_block_mut.clear();
-            let compressed_block = self.compressed_block(block_offset);
-            let mut lz4_decoder = lz4::Decoder::new(compressed_block)?;
             *self.current_block_offset.borrow_mut() = usize::max_value();
-            lz4_decoder.read_to_end(&mut current_block_mut).map(|_| ())?;
+            self.decompress_block(block_offset, &mut current_block_mut)?;
             *self.current_block_offset.borrow_mut() = block_offset;
         }
         Ok(())
@@ -72,37 +119,151 @@ impl StoreReader {
     /// Reads a given document.
     ///
     /// Calling `.get(doc)` is relatively costly as it requires
-    /// decompressing a LZ4-compressed block.
+    /// decompressing a compressed block.
     ///
     /// It should not be called to score documents
     /// for instance.
     pub fn get(&self, doc_id: DocId) -> Result<Document> {
+        self.read_doc(doc_id, |cursor| Document::deserialize(cursor))
+    }
+
+    /// Reads the values of some of the fields of a given document.
+    ///
+    /// The block containing the document still needs to be
+    /// decompressed, but the values of the other fields are
+    /// skipped without being decoded.
+    pub fn get_fields(&self, doc_id: DocId, fields: &[Field]) -> Result<Document> {
+        self.read_doc(doc_id, |cursor| Document::deserialize_fields(cursor, fields))
+    }
+
+    /// Reads a given document, or only the given fields if `fields` is defined.
+    ///
+    /// The decompressed block containing the document is looked up in,
+    /// or added to, `block_cache`, rather than in the single block
+    /// kept by the reader.
+    /// `segment_ord` identifies this store within the cache.
+    ///
+    /// The cache is not locked while the block gets decompressed.
+    pub(crate) fn get_with_cache(
+        &self,
+        doc_id: DocId,
+        fields: Option<&[Field]>,
+        segment_ord: SegmentLocalId,
+        block_cache: &Mutex<BlockCache>,
+    ) -> Result<Document> {
+        let (first_doc_id, block_offset) = self.block_offset(doc_id);
+        let block_key = (segment_ord, block_offset as usize);
+        let cached_block = block_cache
+            .lock()
+            .expect("Block cache lock poisoned")
+            .get(block_key);
+        let block = match cached_block {
+            Some(block) => block,
+            None => {
+                let mut block = Vec::new();
+                self.decompress_block(block_offset as usize, &mut block)?;
+                let block = Arc::new(block);
+                block_cache
+                    .lock()
+                    .expect("Block cache lock poisoned")
+                    .insert(block_key, Arc::clone(&block));
+                block
+            }
+        };
+        read_doc_in_block(&block, first_doc_id, doc_id, |cursor| match fields {
+            Some(fields) => Document::deserialize_fields(cursor, fields),
+            None => Document::deserialize(cursor),
+        })
+    }
+
+    fn read_doc<F>(&self, doc_id: DocId, deserialize: F) -> Result<Document>
+    where
+        F: FnOnce(&mut &[u8]) -> io::Result<Document>,
+    {
         let (first_doc_id, block_offset) = self.block_offset(doc_id);
         self.read_block(block_offset as usize)?;
-        let current_block_mut = self.current_block.borrow_mut();
-        let mut cursor = &current_block_mut[..];
-        for _ in first_doc_id..doc_id {
-            let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
-            cursor = &cursor[doc_length..];
-        }
+        let current_block = self.current_block.borrow();
+        read_doc_in_block(&current_block, first_doc_id, doc_id, deserialize)
+    }
+}
+
+/// Reads the document `doc_id` within a decompressed block
+/// starting with the document `first_doc_id`.
+fn read_doc_in_block<F>(
+    block: &[u8],
+    first_doc_id: DocId,
+    doc_id: DocId,
+    deserialize: F,
+) -> Result<Document>
+where
+    F: FnOnce(&mut &[u8]) -> io::Result<Document>,
+{
+    let mut cursor = block;
+    for _ in first_doc_id..doc_id {
         let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
-        cursor = &cursor[..doc_length];
-        Ok(Document::deserialize(&mut cursor)?)
+        cursor = &cursor[doc_length..];
     }
+    let doc_length = VInt::deserialize(&mut cursor)?.val() as usize;
+    cursor = &cursor[..doc_length];
+    Ok(deserialize(&mut cursor)?)
 }
 
+/// Splits the store file into the blocks, the skip list index
+/// and the dictionary.
+///
+/// A legacy footer does not end with the compressor id.
 #[allow(needless_pass_by_value)]
-fn split_source(data: ReadOnlySource) -> (ReadOnlySource, ReadOnlySource, DocId) {
+fn split_source(
+    data: ReadOnlySource,
+    legacy_footer: bool,
+) -> io::Result<(ReadOnlySource, ReadOnlySource, ReadOnlySource, DocId, Compressor)> {
     let data_len = data.len();
-    let footer_offset = data_len - size_of::<u64>() - size_of::<u32>();
+    let footer_len = if legacy_footer {
+        size_of::<u64>() + size_of::<u32>()
+    } else {
+        size_of::<u64>() + size_of::<u32>() + size_of::<u8>()
+    };
+    if data_len < footer_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Doc store file is too short to contain a footer.",
+        ));
+    }
+    let footer_offset = data_len - footer_len;
     let serialized_offset: ReadOnlySource = data.slice(footer_offset, data_len);
     let mut serialized_offset_buf = serialized_offset.as_slice();
-    let offset = u64::deserialize(&mut serialized_offset_buf).unwrap();
-    let offset = offset as usize;
-    let max_doc = u32::deserialize(&mut serialized_offset_buf).unwrap();
-    (
+    let offset = u64::deserialize(&mut serialized_offset_buf)? as usize;
+    let max_doc = u32::deserialize(&mut serialized_offset_buf)?;
+    let compressor = if legacy_footer {
+        Compressor::Lz4
+    } else {
+        Compressor::from_id(u8::deserialize(&mut serialized_offset_buf)?)?
+    };
+    let (index_end, dictionary) = if compressor.uses_dictionary() {
+        let corrupted_dictionary =
+            || io::Error::new(io::ErrorKind::InvalidData, "Doc store dictionary is corrupted.");
+        if footer_offset < offset + size_of::<u32>() {
+            return Err(corrupted_dictionary());
+        }
+        let dictionary_len_offset = footer_offset - size_of::<u32>();
+        let mut dictionary_len_buf = &data.as_slice()[dictionary_len_offset..footer_offset];
+        let dictionary_len = u32::deserialize(&mut dictionary_len_buf)? as usize;
+        if dictionary_len_offset < offset + dictionary_len {
+            return Err(corrupted_dictionary());
+        }
+        let dictionary_offset = dictionary_len_offset - dictionary_len;
+        (
+            dictionary_offset,
+            data.slice(dictionary_offset, dictionary_len_offset),
+        )
+    } else {
+        (footer_offset, data.slice(footer_offset, footer_offset))
+    };
+    Ok((
         data.slice(0, offset),
-        data.slice(offset, footer_offset),
+        data.slice(offset, index_end),
+        dictionary,
         max_doc,
-    )
+        compressor,
+    ))
 }
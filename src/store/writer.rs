@@ -1,14 +1,31 @@
+use Result;
 use directory::WritePtr;
 use DocId;
 use common::{BinarySerializable, VInt};
 use std::io::{self, Write};
+use std::mem;
 use super::StoreReader;
-use lz4;
+use super::Compressor;
+use super::compressors::train_dictionary;
 use datastruct::SkipListBuilder;
 use common::CountingWriter;
 use schema::Document;
 
-const BLOCK_SIZE: usize = 16_384;
+/// Default size of the uncompressed blocks of the doc store.
+pub const DEFAULT_BLOCK_SIZE: usize = 16_384;
+
+/// Number of bytes of documents sampled to train the dictionary
+/// of a `Compressor::ZstdDictionary` store.
+const DICTIONARY_SAMPLE_NUM_BYTES: usize = 1 << 20;
+
+/// Maximum size of the dictionary of a `Compressor::ZstdDictionary` store.
+const DICTIONARY_MAX_SIZE: usize = 32_768;
+
+/// Documents buffered until the dictionary is trained.
+struct DictionarySamples {
+    docs: Vec<Vec<u8>>,
+    num_bytes: usize,
+}
 
 /// Write tantivy's [`Store`](./index.html)
 ///
@@ -18,7 +35,14 @@ const BLOCK_SIZE: usize = 16_384;
 ///
 /// The skip list index on the other hand, is build in memory.
 ///
+/// With a compressor relying on a dictionary, the first documents
+/// are buffered in memory, and used as samples to train the dictionary
+/// before being written.
 pub struct StoreWriter {
+    compressor: Compressor,
+    block_size: usize,
+    dictionary: Vec<u8>,
+    dictionary_samples: Option<DictionarySamples>,
     doc: DocId,
     offset_index_writer: SkipListBuilder<u64>,
     writer: CountingWriter<WritePtr>,
@@ -31,8 +55,35 @@ impl StoreWriter {
     ///
     /// The store writer will writes blocks on disc as
     /// document are added.
+    ///
+    /// Blocks are compressed using LZ4.
     pub fn new(writer: WritePtr) -> StoreWriter {
+        StoreWriter::with_compressor(writer, Compressor::default(), DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Create a store writer using the given compressor.
+    ///
+    /// Documents are accumulated until their serialized size
+    /// exceeds `block_size`, at which point the block is compressed
+    /// and written.
+    pub fn with_compressor(
+        writer: WritePtr,
+        compressor: Compressor,
+        block_size: usize,
+    ) -> StoreWriter {
+        let dictionary_samples = if compressor.uses_dictionary() {
+            Some(DictionarySamples {
+                docs: Vec::new(),
+                num_bytes: 0,
+            })
+        } else {
+            None
+        };
         StoreWriter {
+            compressor,
+            block_size,
+            dictionary: Vec::new(),
+            dictionary_samples,
             doc: 0,
             offset_index_writer: SkipListBuilder::new(4),
             writer: CountingWriter::wrap(writer),
@@ -49,21 +100,76 @@ impl StoreWriter {
     pub fn store(&mut self, stored_document: &Document) -> io::Result<()> {
         self.intermediary_buffer.clear();
         stored_document.serialize(&mut self.intermediary_buffer)?;
-        let doc_num_bytes = self.intermediary_buffer.len();
-        VInt(doc_num_bytes as u64).serialize(&mut self.current_block)?;
-        self.current_block.write_all(&self.intermediary_buffer[..])?;
+        if self.dictionary_samples.is_none() {
+            let serialized_doc = mem::replace(&mut self.intermediary_buffer, Vec::new());
+            let result = self.add_serialized_doc(&serialized_doc);
+            self.intermediary_buffer = serialized_doc;
+            return result;
+        }
+        let is_sample_complete = {
+            let samples = self.dictionary_samples
+                .as_mut()
+                .expect("The documents are being sampled");
+            samples.num_bytes += self.intermediary_buffer.len();
+            samples.docs.push(self.intermediary_buffer.clone());
+            samples.num_bytes >= DICTIONARY_SAMPLE_NUM_BYTES
+        };
+        if is_sample_complete {
+            self.train_dictionary()?;
+        }
+        Ok(())
+    }
+
+    fn add_serialized_doc(&mut self, serialized_doc: &[u8]) -> io::Result<()> {
+        VInt(serialized_doc.len() as u64).serialize(&mut self.current_block)?;
+        self.current_block.write_all(serialized_doc)?;
         self.doc += 1;
-        if self.current_block.len() > BLOCK_SIZE {
+        if self.current_block.len() > self.block_size {
             self.write_and_compress_block()?;
         }
         Ok(())
     }
 
+    /// Trains the dictionary on the buffered documents, and writes them.
+    ///
+    /// If the dictionary cannot be trained, e.g. because of a lack of samples,
+    /// the blocks are compressed without dictionary.
+    fn train_dictionary(&mut self) -> io::Result<()> {
+        if let Some(samples) = self.dictionary_samples.take() {
+            self.dictionary = match train_dictionary(&samples.docs, DICTIONARY_MAX_SIZE) {
+                Ok(dictionary) => dictionary,
+                Err(e) => {
+                    info!(
+                        "Failed to train the doc store dictionary on {} documents. {:?}",
+                        samples.docs.len(),
+                        e
+                    );
+                    Vec::new()
+                }
+            };
+            for serialized_doc in &samples.docs {
+                self.add_serialized_doc(serialized_doc)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Stacks a store reader on top of the documents written so far.
     /// This method is an optimization compared to iterating over the documents
     /// in the store and adding them one by one, as the store's data will
     /// not be decompressed and then recompressed.
-    pub fn stack(&mut self, store_reader: &StoreReader) -> io::Result<()> {
+    ///
+    /// If the `store_reader` was compressed using a different compressor,
+    /// or if the compressor relies on a dictionary, its documents
+    /// are decompressed and added one by one.
+    pub fn stack(&mut self, store_reader: &StoreReader) -> Result<()> {
+        if store_reader.compressor() != self.compressor || self.compressor.uses_dictionary() {
+            for doc_id in 0..store_reader.max_doc() {
+                let doc = store_reader.get(doc_id)?;
+                self.store(&doc)?;
+            }
+            return Ok(());
+        }
         if !self.current_block.is_empty() {
             self.write_and_compress_block()?;
             self.offset_index_writer
@@ -87,12 +193,11 @@ impl StoreWriter {
 
     fn write_and_compress_block(&mut self) -> io::Result<()> {
         self.intermediary_buffer.clear();
-        {
-            let mut encoder = lz4::EncoderBuilder::new().build(&mut self.intermediary_buffer)?;
-            encoder.write_all(&self.current_block)?;
-            let (_, encoder_result) = encoder.finish();
-            encoder_result?;
-        }
+        self.compressor.compress(
+            &self.current_block,
+            &self.dictionary,
+            &mut self.intermediary_buffer,
+        )?;
         (self.intermediary_buffer.len() as u32).serialize(&mut self.writer)?;
         self.writer.write_all(&self.intermediary_buffer)?;
         self.offset_index_writer
@@ -105,14 +210,24 @@ impl StoreWriter {
     ///
     /// Compress the last unfinished block if any,
     /// and serializes the skip list index on disc.
+    ///
+    /// The footer records the compressor used, so that the
+    /// reader can pick the right decoder. The dictionary, if any,
+    /// is written right before the footer.
     pub fn close(mut self) -> io::Result<()> {
+        self.train_dictionary()?;
         if !self.current_block.is_empty() {
             self.write_and_compress_block()?;
         }
         let header_offset: u64 = self.writer.written_bytes() as u64;
         self.offset_index_writer.write(&mut self.writer)?;
+        if self.compressor.uses_dictionary() {
+            self.writer.write_all(&self.dictionary)?;
+            (self.dictionary.len() as u32).serialize(&mut self.writer)?;
+        }
         header_offset.serialize(&mut self.writer)?;
         self.doc.serialize(&mut self.writer)?;
+        self.compressor.id().serialize(&mut self.writer)?;
         self.writer.flush()
     }
 }
@@ -1,5 +1,6 @@
 use std::io::{self, Write};
 use fst;
+use fst::{Automaton, IntoStreamer, Streamer};
 use fst::raw::Fst;
 use directory::ReadOnlySource;
 use common::BinarySerializable;
@@ -89,6 +90,10 @@ fn open_fst_index(source: ReadOnlySource) -> fst::Map {
         ReadOnlySource::Mmap(mmap_readonly) => {
             Fst::from_mmap(mmap_readonly).expect("FST data is corrupted")
         }
+        ReadOnlySource::Lazy(lazy_source) => {
+            let data = lazy_source.fetch();
+            Fst::from_shared_bytes(data.data, data.start, data.len).expect("FST data is corrupted")
+        }
     };
     fst::Map::from(fst)
 }
@@ -160,4 +165,19 @@ impl<'a> TermDictionary<'a> for TermDictionaryImpl {
     fn range(&self) -> TermStreamerBuilderImpl {
         TermStreamerBuilderImpl::new(self, self.fst_index.range())
     }
+
+    /// Intersects the automaton with the fst, so that only
+    /// the prefixes the automaton can match are explored.
+    fn search<A, F>(&self, automaton: &A, mut visit: F)
+    where
+        A: Automaton,
+        F: FnMut(TermOrdinal) -> bool,
+    {
+        let mut stream = self.fst_index.search(automaton).into_stream();
+        while let Some((_, term_ord)) = stream.next() {
+            if !visit(term_ord) {
+                return;
+            }
+        }
+    }
 }
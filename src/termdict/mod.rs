@@ -50,6 +50,7 @@ term `stream`.
 use schema::{Field, FieldType, Term};
 use directory::ReadOnlySource;
 use postings::TermInfo;
+use fst::Automaton;
 
 /// Position of the term in the sorted list of terms.
 pub type TermOrdinal = u64;
@@ -127,6 +128,33 @@ where
             .lt(stop_term.as_slice())
             .into_stream()
     }
+
+    /// Calls `visit` with the ordinal of each term accepted
+    /// by the automaton, in increasing order, until `visit` returns false.
+    ///
+    /// The default implementation runs the automaton over
+    /// all of the terms.
+    fn search<A, F>(&'a self, automaton: &A, mut visit: F)
+    where
+        A: Automaton,
+        F: FnMut(TermOrdinal) -> bool,
+    {
+        let mut term_stream = self.stream();
+        while term_stream.advance() {
+            let mut state = automaton.start();
+            let mut can_match = true;
+            for &byte in term_stream.key() {
+                if !automaton.can_match(&state) {
+                    can_match = false;
+                    break;
+                }
+                state = automaton.accept(&state, byte);
+            }
+            if can_match && automaton.is_match(&state) && !visit(term_stream.term_ord()) {
+                return;
+            }
+        }
+    }
 }
 
 /// Builder for the new term dictionary.
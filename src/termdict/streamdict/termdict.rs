@@ -216,6 +216,10 @@ fn open_fst_index(source: ReadOnlySource) -> io::Result<fst::Map> {
     let fst_result = match source {
         Anonymous(data) => Fst::from_shared_bytes(data.data, data.start, data.len),
         Mmap(mmap_readonly) => Fst::from_mmap(mmap_readonly),
+        Lazy(lazy_source) => {
+            let data = lazy_source.fetch();
+            Fst::from_shared_bytes(data.data, data.start, data.len)
+        }
     };
     let fst = fst_result.map_err(convert_fst_error)?;
     Ok(fst::Map::from(fst))
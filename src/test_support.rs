@@ -0,0 +1,358 @@
+/*!
+Utilities to test custom queries against randomly generated indexes.
+
+Requires the `test-support` feature.
+
+A `RandomIndex` is an in-RAM index of random documents, spread over
+several segments, some of which are deleted.
+The documents are also kept in memory, so that the results of
+a query can be checked against a naive evaluation of the query
+over the documents.
+
+```rust
+extern crate tantivy;
+use tantivy::query::TermQuery;
+use tantivy::schema::IndexRecordOption;
+use tantivy::test_support::RandomIndexBuilder;
+
+# fn main() {
+let random_index = RandomIndexBuilder::with_seed(42).build().unwrap();
+let query = TermQuery::new(random_index.term("w3"), IndexRecordOption::Basic);
+random_index.assert_query_matches(&query, |doc| doc.words.iter().any(|word| word == "w3"));
+# }
+```
+*/
+
+use collector::Collector;
+use fastfield::FastFieldReader;
+use indexer::NoMergePolicy;
+use query::Query;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use schema::{Document, Field, SchemaBuilder, Term, FAST, INT_INDEXED, INT_STORED, TEXT};
+use std::collections::BTreeSet;
+use DocId;
+use Index;
+use Result;
+use Score;
+use SegmentLocalId;
+use SegmentReader;
+
+/// Document of a `RandomIndex`.
+#[derive(Clone, Debug)]
+pub struct RandomDoc {
+    /// Unique id of the document, stored in the `id` field.
+    pub id: u64,
+    /// Words of the `text` field, in order.
+    ///
+    /// The words are `w0`, `w1`, ... up to the size of the vocabulary.
+    /// Words with a small number are more frequent.
+    pub words: Vec<String>,
+    /// Value of the `value` field.
+    pub value: u64,
+}
+
+/// Builder of a `RandomIndex`.
+///
+/// The same seed and parameters always build the same index,
+/// so that failures can be reproduced.
+#[derive(Clone, Debug)]
+pub struct RandomIndexBuilder {
+    seed: u32,
+    num_docs: usize,
+    num_segments: usize,
+    vocabulary_size: usize,
+    max_num_words: usize,
+    max_value: u64,
+    delete_ratio: f32,
+}
+
+impl RandomIndexBuilder {
+    /// Creates a builder, generating the documents with the given seed.
+    pub fn with_seed(seed: u32) -> RandomIndexBuilder {
+        RandomIndexBuilder {
+            seed,
+            num_docs: 1_000,
+            num_segments: 3,
+            vocabulary_size: 20,
+            max_num_words: 10,
+            max_value: 100,
+            delete_ratio: 0.1,
+        }
+    }
+
+    /// Sets the number of documents added to the index,
+    /// deleted documents included. Defaults to 1,000.
+    pub fn num_docs(mut self, num_docs: usize) -> RandomIndexBuilder {
+        self.num_docs = num_docs;
+        self
+    }
+
+    /// Sets the number of segments of the index. Defaults to 3.
+    pub fn num_segments(mut self, num_segments: usize) -> RandomIndexBuilder {
+        self.num_segments = num_segments;
+        self
+    }
+
+    /// Sets the number of distinct words. Defaults to 20.
+    pub fn vocabulary_size(mut self, vocabulary_size: usize) -> RandomIndexBuilder {
+        self.vocabulary_size = vocabulary_size;
+        self
+    }
+
+    /// Sets the maximum number of words of a document. Defaults to 10.
+    pub fn max_num_words(mut self, max_num_words: usize) -> RandomIndexBuilder {
+        self.max_num_words = max_num_words;
+        self
+    }
+
+    /// Sets the upper bound, excluded, of the values
+    /// of the `value` field. Defaults to 100.
+    pub fn max_value(mut self, max_value: u64) -> RandomIndexBuilder {
+        self.max_value = max_value;
+        self
+    }
+
+    /// Sets the ratio of the documents that are deleted. Defaults to 0.1.
+    pub fn delete_ratio(mut self, delete_ratio: f32) -> RandomIndexBuilder {
+        self.delete_ratio = delete_ratio;
+        self
+    }
+
+    /// Generates the documents and indexes them.
+    pub fn build(self) -> Result<RandomIndex> {
+        let mut schema_builder = SchemaBuilder::default();
+        let id_field = schema_builder.add_u64_field("id", INT_INDEXED | INT_STORED | FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let value_field = schema_builder.add_u64_field("value", INT_INDEXED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, self.seed]);
+        let num_segments = self.num_segments.max(1);
+        let docs_per_segment = ((self.num_docs + num_segments - 1) / num_segments).max(1);
+        let mut docs = Vec::with_capacity(self.num_docs);
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 40_000_000)?;
+            // the segments are kept as they are, so that queries are
+            // tested against several segments.
+            index_writer.set_merge_policy(box NoMergePolicy);
+            for id in 0..self.num_docs as u64 {
+                let doc = self.random_doc(id, &mut rng);
+                let mut document = Document::default();
+                document.add_u64(id_field, doc.id);
+                document.add_text(text_field, &doc.words.join(" "));
+                document.add_u64(value_field, doc.value);
+                index_writer.add_document(document);
+                docs.push(doc);
+                if (id as usize + 1) % docs_per_segment == 0 {
+                    index_writer.commit()?;
+                }
+            }
+            let mut alive_docs = Vec::with_capacity(docs.len());
+            for doc in docs {
+                if rng.next_f32() < self.delete_ratio {
+                    index_writer.delete_term(Term::from_field_u64(id_field, doc.id));
+                } else {
+                    alive_docs.push(doc);
+                }
+            }
+            docs = alive_docs;
+            index_writer.commit()?;
+            index_writer.wait_merging_threads()?;
+        }
+        index.load_searchers()?;
+        Ok(RandomIndex {
+            index,
+            id_field,
+            text_field,
+            value_field,
+            docs,
+        })
+    }
+
+    fn random_doc(&self, id: u64, rng: &mut XorShiftRng) -> RandomDoc {
+        let vocabulary_size = self.vocabulary_size.max(1);
+        let num_words = rng.gen_range(0, self.max_num_words + 1);
+        let words = (0..num_words)
+            .map(|_| {
+                // skews the distribution towards the first words.
+                let word_ord = rng.gen_range(0, vocabulary_size)
+                    .min(rng.gen_range(0, vocabulary_size));
+                format!("w{}", word_ord)
+            })
+            .collect();
+        RandomDoc {
+            id,
+            words,
+            value: rng.gen_range(0, self.max_value.max(1)),
+        }
+    }
+}
+
+/// In-RAM index of random documents, along with the documents themselves.
+///
+/// See `RandomIndexBuilder`.
+pub struct RandomIndex {
+    index: Index,
+    id_field: Field,
+    text_field: Field,
+    value_field: Field,
+    docs: Vec<RandomDoc>,
+}
+
+impl RandomIndex {
+    /// Returns the index.
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Returns the alive documents of the index.
+    pub fn docs(&self) -> &[RandomDoc] {
+        &self.docs[..]
+    }
+
+    /// Returns the `id` field, a stored and fast u64 field.
+    pub fn id_field(&self) -> Field {
+        self.id_field
+    }
+
+    /// Returns the `text` field, indexed with positions.
+    pub fn text_field(&self) -> Field {
+        self.text_field
+    }
+
+    /// Returns the `value` field, an indexed and fast u64 field.
+    pub fn value_field(&self) -> Field {
+        self.value_field
+    }
+
+    /// Returns the term of the `text` field for a word.
+    pub fn term(&self, word: &str) -> Term {
+        Term::from_field_text(self.text_field, word)
+    }
+
+    /// Returns the ids of the documents matching `query`.
+    pub fn search_ids(&self, query: &Query) -> Result<BTreeSet<u64>> {
+        let searcher = self.index.searcher();
+        let mut id_collector = IdCollector::new(self.id_field);
+        query.search(&*searcher, &mut id_collector)?;
+        Ok(id_collector.ids)
+    }
+
+    /// Checks the documents matched by `query` against the documents
+    /// for which `matches` returns true, as well as the count
+    /// of matching documents.
+    ///
+    /// # Panics
+    /// Panics with the query and the ids of the documents
+    /// that are missing or unexpected if they differ.
+    pub fn assert_query_matches<F>(&self, query: &Query, matches: F)
+    where
+        F: Fn(&RandomDoc) -> bool,
+    {
+        let expected_ids: BTreeSet<u64> = self.docs
+            .iter()
+            .filter(|doc| matches(doc))
+            .map(|doc| doc.id)
+            .collect();
+        let ids = self.search_ids(query).expect("Search failed");
+        if ids != expected_ids {
+            let missing_ids: Vec<u64> = expected_ids.difference(&ids).cloned().collect();
+            let unexpected_ids: Vec<u64> = ids.difference(&expected_ids).cloned().collect();
+            panic!(
+                "The query {:?} does not match the expected documents. \
+                 Missing ids: {:?}, unexpected ids: {:?}",
+                query, missing_ids, unexpected_ids
+            );
+        }
+        let count = query.count(&*self.index.searcher()).expect("Count failed");
+        assert_eq!(
+            count,
+            expected_ids.len(),
+            "The query {:?} counts {} documents instead of {}",
+            query,
+            count,
+            expected_ids.len()
+        );
+    }
+}
+
+/// Collects the ids of the matching documents.
+struct IdCollector {
+    id_field: Field,
+    id_reader: Option<FastFieldReader<u64>>,
+    ids: BTreeSet<u64>,
+}
+
+impl IdCollector {
+    fn new(id_field: Field) -> IdCollector {
+        IdCollector {
+            id_field,
+            id_reader: None,
+            ids: BTreeSet::new(),
+        }
+    }
+}
+
+impl Collector for IdCollector {
+    fn set_segment(&mut self, _: SegmentLocalId, reader: &SegmentReader) -> Result<()> {
+        self.id_reader = Some(reader.fast_field_reader(self.id_field)?);
+        Ok(())
+    }
+
+    fn collect(&mut self, doc: DocId, _: Score) {
+        let id_reader = self.id_reader
+            .as_ref()
+            .expect("collect() was called before set_segment. This should never happen.");
+        let id = id_reader.get(doc);
+        assert!(self.ids.insert(id), "The document {} was collected twice", id);
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::RandomIndexBuilder;
+    use query::{AllQuery, Occur, Query, RangeQuery, TermQuery};
+    use query::BooleanQuery;
+    use schema::IndexRecordOption;
+
+    #[test]
+    fn test_random_index() {
+        let random_index = RandomIndexBuilder::with_seed(4)
+            .num_docs(500)
+            .num_segments(4)
+            .build()
+            .unwrap();
+        let searcher = random_index.index().searcher();
+        assert_eq!(searcher.segment_readers().len(), 4);
+        assert_eq!(searcher.num_docs() as usize, random_index.docs().len());
+        assert!(random_index.docs().len() < 500);
+        random_index.assert_query_matches(&AllQuery, |_| true);
+        let term_query = |word: &str| -> Box<Query> {
+            box TermQuery::new(random_index.term(word), IndexRecordOption::Basic)
+        };
+        let has_word = |doc: &super::RandomDoc, word: &str| doc.words.iter().any(|w| w == word);
+        random_index.assert_query_matches(&*term_query("w1"), |doc| has_word(doc, "w1"));
+        let boolean_query = BooleanQuery::from(vec![
+            (Occur::Must, term_query("w0")),
+            (Occur::Should, term_query("w2")),
+            (Occur::MustNot, term_query("w3")),
+        ]);
+        random_index.assert_query_matches(&boolean_query, |doc| {
+            has_word(doc, "w0") && !has_word(doc, "w3")
+        });
+        let range_query = RangeQuery::new_u64(random_index.value_field(), 10..20);
+        random_index.assert_query_matches(&range_query, |doc| doc.value >= 10 && doc.value < 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match the expected documents")]
+    fn test_random_index_mismatch() {
+        let random_index = RandomIndexBuilder::with_seed(2).num_docs(100).build().unwrap();
+        random_index.assert_query_matches(&AllQuery, |doc| doc.id % 2 == 0);
+    }
+}
@@ -0,0 +1,144 @@
+use super::Language;
+
+/// Detects the language of a text.
+///
+/// See `StopWordLanguageDetector` for the default implementation,
+/// and `LanguageRouter` for its use at indexing time.
+pub trait LanguageDetector: Send + Sync {
+    /// Returns the ISO 639-1 code of the language of the text,
+    /// or `None` if it could not be detected.
+    fn detect(&self, text: &str) -> Option<String>;
+}
+
+const STOP_WORDS: &[(Language, &[&str])] = &[
+    (
+        Language::English,
+        &[
+            "the", "and", "of", "to", "is", "in", "that", "it", "was", "for", "with", "are",
+            "this", "be", "have", "not",
+        ],
+    ),
+    (
+        Language::French,
+        &[
+            "le", "la", "les", "et", "des", "est", "une", "un", "du", "que", "dans", "pour",
+            "pas", "sur", "qui", "avec",
+        ],
+    ),
+    (
+        Language::German,
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "ein", "eine", "zu", "den", "mit", "sich",
+            "auf", "für", "dem", "von",
+        ],
+    ),
+    (
+        Language::Italian,
+        &[
+            "il", "di", "che", "e", "per", "sono", "della", "gli", "non", "nel", "anche", "è",
+            "questo", "ma", "si", "lo",
+        ],
+    ),
+    (
+        Language::Portuguese,
+        &[
+            "o", "os", "e", "do", "da", "em", "um", "uma", "para", "com", "não", "é", "dos",
+            "das", "ao", "mais",
+        ],
+    ),
+    (
+        Language::Spanish,
+        &[
+            "el", "los", "las", "y", "es", "una", "del", "en", "por", "con", "para", "se",
+            "no", "como", "al", "está",
+        ],
+    ),
+];
+
+fn is_kana(c: char) -> bool {
+    // hiragana and katakana
+    c >= '\u{3040}' && c <= '\u{30ff}'
+}
+
+/// Detects the language of a text from its script,
+/// and from the number of occurrences of the most common
+/// words of each language.
+///
+/// Supports Japanese (`ja`), and the languages of
+/// the `Stemmer` : `en`, `fr`, `de`, `it`, `pt` and `es`.
+///
+/// This is cheap, and reliable on texts of a few sentences,
+/// but not on very short texts such as titles.
+/// A language is only detected if its common words are strictly
+/// more frequent than the ones of any other language.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StopWordLanguageDetector;
+
+impl LanguageDetector for StopWordLanguageDetector {
+    fn detect(&self, text: &str) -> Option<String> {
+        if text.chars().any(is_kana) {
+            return Some("ja".to_string());
+        }
+        let mut counts = vec![0usize; STOP_WORDS.len()];
+        for word in text.split(|c: char| !c.is_alphanumeric()) {
+            if word.is_empty() {
+                continue;
+            }
+            let word = word.to_lowercase();
+            for (count, &(_, stop_words)) in counts.iter_mut().zip(STOP_WORDS) {
+                if stop_words.contains(&word.as_str()) {
+                    *count += 1;
+                }
+            }
+        }
+        let max_count = *counts.iter().max().unwrap_or(&0);
+        if max_count == 0 || counts.iter().filter(|&&count| count == max_count).count() > 1 {
+            return None;
+        }
+        counts
+            .iter()
+            .position(|&count| count == max_count)
+            .map(|ord| STOP_WORDS[ord].0.code().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{LanguageDetector, StopWordLanguageDetector};
+
+    fn detect(text: &str) -> Option<String> {
+        StopWordLanguageDetector.detect(text)
+    }
+
+    #[test]
+    fn test_stop_word_language_detector() {
+        assert_eq!(
+            detect("The cat is sleeping in the garden, and it is not hungry.").unwrap(),
+            "en"
+        );
+        assert_eq!(
+            detect("Le chat dort dans le jardin, et il n'a pas faim.").unwrap(),
+            "fr"
+        );
+        assert_eq!(
+            detect("Die Katze schläft im Garten, und sie ist nicht hungrig.").unwrap(),
+            "de"
+        );
+        assert_eq!(
+            detect("Il gatto dorme nel giardino, e non ha fame.").unwrap(),
+            "it"
+        );
+        assert_eq!(
+            detect("El gato duerme en el jardín, y no tiene hambre.").unwrap(),
+            "es"
+        );
+        assert_eq!(
+            detect("O gato dorme no jardim, e não tem fome.").unwrap(),
+            "pt"
+        );
+        assert_eq!(detect("猫は庭で寝ている。").unwrap(), "ja");
+        assert!(detect("tantivy").is_none());
+        assert!(detect("").is_none());
+    }
+}
@@ -139,6 +139,7 @@ mod japanese_tokenizer;
 mod token_stream_chain;
 mod raw_tokenizer;
 mod alphanum_only;
+mod language_detector;
 
 pub use self::alphanum_only::AlphaNumOnlyFilter;
 pub use self::tokenizer::{Token, TokenFilter, TokenStream, Tokenizer};
@@ -150,7 +151,8 @@ pub(crate) use self::token_stream_chain::TokenStreamChain;
 pub use self::japanese_tokenizer::JapaneseTokenizer;
 pub use self::remove_long::RemoveLongFilter;
 pub use self::lower_caser::LowerCaser;
-pub use self::stemmer::Stemmer;
+pub use self::stemmer::{Language, Stemmer};
+pub use self::language_detector::{LanguageDetector, StopWordLanguageDetector};
 pub use self::facet_tokenizer::FacetTokenizer;
 
 #[cfg(test)]
@@ -196,6 +198,23 @@ mod test {
         assert_eq!(&tokens[3], "payer");
     }
 
+    #[test]
+    fn test_fr_tokenizer() {
+        let tokenizer_manager = TokenizerManager::default();
+        let fr_tokenizer = tokenizer_manager.get("fr_stem").unwrap();
+        let mut tokens: Vec<String> = vec![];
+        {
+            let mut add_token = |token: &Token| {
+                tokens.push(token.text.clone());
+            };
+            fr_tokenizer
+                .token_stream("Des chats")
+                .process(&mut add_token);
+        }
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(&tokens[1], "chat");
+    }
+
     #[test]
     fn test_jp_tokenizer() {
         let tokenizer_manager = TokenizerManager::default();
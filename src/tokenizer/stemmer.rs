@@ -1,20 +1,64 @@
-use std::sync::Arc;
 use super::{Token, TokenFilter, TokenStream};
 use rust_stemmers::{self, Algorithm};
 
-/// `Stemmer` token filter. Currently only English is supported.
+/// Languages supported by the `Stemmer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    /// English
+    English,
+    /// French
+    French,
+    /// German
+    German,
+    /// Italian
+    Italian,
+    /// Portuguese
+    Portuguese,
+    /// Spanish
+    Spanish,
+}
+
+impl Language {
+    /// Returns the ISO 639-1 code of the language.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Language::English => "en",
+            Language::French => "fr",
+            Language::German => "de",
+            Language::Italian => "it",
+            Language::Portuguese => "pt",
+            Language::Spanish => "es",
+        }
+    }
+
+    fn algorithm(&self) -> Algorithm {
+        match *self {
+            Language::English => Algorithm::English,
+            Language::French => Algorithm::French,
+            Language::German => Algorithm::German,
+            Language::Italian => Algorithm::Italian,
+            Language::Portuguese => Algorithm::Portuguese,
+            Language::Spanish => Algorithm::Spanish,
+        }
+    }
+}
+
+/// `Stemmer` token filter.
 /// Tokens are expected to be lowercased beforehands.
 #[derive(Clone)]
 pub struct Stemmer {
-    stemmer_algorithm: Arc<Algorithm>,
+    language: Language,
 }
 
 impl Stemmer {
-    /// Creates a new Stemmer `TokenFilter`.
+    /// Creates a new English Stemmer `TokenFilter`.
     pub fn new() -> Stemmer {
-        Stemmer {
-            stemmer_algorithm: Arc::new(Algorithm::English),
-        }
+        Stemmer::for_language(Language::English)
+    }
+
+    /// Creates a new Stemmer `TokenFilter` for the given language.
+    pub fn for_language(language: Language) -> Stemmer {
+        Stemmer { language }
     }
 }
 
@@ -25,7 +69,7 @@ where
     type ResultTokenStream = StemmerTokenStream<TailTokenStream>;
 
     fn transform(&self, token_stream: TailTokenStream) -> Self::ResultTokenStream {
-        let inner_stemmer = rust_stemmers::Stemmer::create(Algorithm::English);
+        let inner_stemmer = rust_stemmers::Stemmer::create(self.language.algorithm());
         StemmerTokenStream::wrap(inner_stemmer, token_stream)
     }
 }
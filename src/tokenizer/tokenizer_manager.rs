@@ -8,7 +8,7 @@ use tokenizer::SimpleTokenizer;
 use tokenizer::JapaneseTokenizer;
 use tokenizer::RemoveLongFilter;
 use tokenizer::LowerCaser;
-use tokenizer::Stemmer;
+use tokenizer::{Language, Stemmer};
 
 /// The tokenizer manager serves as a store for
 /// all of the pre-configured tokenizer pipelines.
@@ -22,6 +22,9 @@ use tokenizer::Stemmer;
 ///  * `en_stem` : Like `default`, but also applies stemming on the
 ///  resulting tokens. Stemming can improve the recall of your
 ///  search engine.
+///  * `fr_stem`, `de_stem`, `it_stem`, `pt_stem`, `es_stem` : Like `en_stem`,
+///  for the other languages supported by the `Stemmer`.
+///  * `ja` : Japanese tokenizer.
 #[derive(Clone)]
 pub struct TokenizerManager {
     tokenizers: Arc<RwLock<HashMap<String, Box<BoxedTokenizer>>>>,
@@ -67,13 +70,22 @@ impl Default for TokenizerManager {
                 .filter(RemoveLongFilter::limit(40))
                 .filter(LowerCaser),
         );
-        manager.register(
-            "en_stem",
-            SimpleTokenizer
-                .filter(RemoveLongFilter::limit(40))
-                .filter(LowerCaser)
-                .filter(Stemmer::new()),
-        );
+        for &language in &[
+            Language::English,
+            Language::French,
+            Language::German,
+            Language::Italian,
+            Language::Portuguese,
+            Language::Spanish,
+        ] {
+            manager.register(
+                &format!("{}_stem", language.code()),
+                SimpleTokenizer
+                    .filter(RemoveLongFilter::limit(40))
+                    .filter(LowerCaser)
+                    .filter(Stemmer::for_language(language)),
+            );
+        }
         manager.register("ja", JapaneseTokenizer.filter(RemoveLongFilter::limit(40)));
         manager
     }